@@ -0,0 +1,316 @@
+//! Procedural macros for writing bellperson circuits.
+
+extern crate proc_macro;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{braced, parse_macro_input, BinOp, Block, Expr, Ident, Pat, Stmt, Token};
+
+/// Writes a circuit over `AllocatedNum`s as arithmetic.
+///
+/// The first argument names the constraint system, the block contains the circuit:
+///
+/// - `let x = alloc(value);` allocates a private variable, where `value` is an
+///   `Option<Fr>`.
+/// - `let x = input(value);` allocates a variable and exposes it as a public input.
+/// - `let x = <expr>;` computes `<expr>` built from `+`, `-`, `*`, parentheses and
+///   variables, which may also be `AllocatedNum`s from outside of the macro. Each
+///   operation allocates its result and adds one constraint.
+/// - `enforce(<expr> == <expr>);` constrains both sides to be equal. A product on the
+///   left hand side is enforced directly, without allocating it.
+///
+/// Variables are namespaced by their names and operations by the variable they are
+/// assigned to, so names must not repeat within a namespace. Errors are propagated
+/// with `?`, so the macro can only be used in functions returning
+/// `Result<_, SynthesisError>`.
+///
+/// ```
+/// use bellperson::bls::{Bls12, Fr};
+/// use bellperson::util_cs::test_cs::TestConstraintSystem;
+/// use bellperson::{ConstraintSystem, SynthesisError};
+/// use bellperson_macros::circuit;
+/// use ff::PrimeField;
+///
+/// fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS) -> Result<(), SynthesisError> {
+///     circuit!(cs, {
+///         let a = alloc(Fr::from_str("3"));
+///         let b = alloc(Fr::from_str("4"));
+///         let d = alloc(Fr::from_str("5"));
+///         let x = input(Fr::from_str("17"));
+///
+///         let c = a * b + d;
+///         enforce(c == x);
+///     });
+///
+///     Ok(())
+/// }
+///
+/// let mut cs = TestConstraintSystem::<Bls12>::new();
+/// synthesize(&mut cs).unwrap();
+/// assert!(cs.is_satisfied());
+/// ```
+#[proc_macro]
+pub fn circuit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as CircuitInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct CircuitInput {
+    cs: Ident,
+    stmts: Vec<Stmt>,
+}
+
+impl Parse for CircuitInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let cs = input.parse()?;
+        input.parse::<Token![,]>()?;
+
+        let content;
+        braced!(content in input);
+        let stmts = content.call(Block::parse_within)?;
+
+        // Allow a trailing comma.
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(CircuitInput { cs, stmts })
+    }
+}
+
+fn expand(input: CircuitInput) -> syn::Result<TokenStream> {
+    let mut lowering = Lowering {
+        cs: input.cs,
+        stmts: Vec::new(),
+        counter: 0,
+    };
+
+    for stmt in &input.stmts {
+        lowering.stmt(stmt)?;
+    }
+
+    let stmts = lowering.stmts;
+    Ok(quote! { #(#stmts)* })
+}
+
+/// Turns the statements of the circuit into calls on the constraint system.
+struct Lowering {
+    cs: Ident,
+    stmts: Vec<TokenStream>,
+    counter: usize,
+}
+
+impl Lowering {
+    fn stmt(&mut self, stmt: &Stmt) -> syn::Result<()> {
+        match stmt {
+            Stmt::Local(local) => {
+                let ident = match &local.pat {
+                    Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => &pat.ident,
+                    pat => {
+                        return Err(syn::Error::new_spanned(
+                            pat,
+                            "expected a variable name in circuit!",
+                        ))
+                    }
+                };
+                let expr = match &local.init {
+                    Some((_, expr)) => expr,
+                    None => {
+                        return Err(syn::Error::new_spanned(
+                            local,
+                            "variables in circuit! must be assigned",
+                        ))
+                    }
+                };
+
+                self.assign(ident, expr)
+            }
+            Stmt::Semi(Expr::Call(call), _) if call_name(call) == Some("enforce") => {
+                let arg = single_arg(call)?;
+                match strip(arg) {
+                    Expr::Binary(binary) if is_eq(&binary.op) => {
+                        self.enforce(&binary.left, &binary.right)
+                    }
+                    other => Err(syn::Error::new_spanned(
+                        other,
+                        "expected `enforce(<lhs> == <rhs>)`",
+                    )),
+                }
+            }
+            other => Err(syn::Error::new_spanned(
+                other,
+                "unsupported statement in circuit!, expected `let` or `enforce(..);`",
+            )),
+        }
+    }
+
+    fn assign(&mut self, ident: &Ident, expr: &Expr) -> syn::Result<()> {
+        let cs = &self.cs;
+        let name = ident.to_string();
+
+        if let Expr::Call(call) = strip(expr) {
+            match call_name(call) {
+                Some("alloc") => {
+                    let value = single_arg(call)?;
+                    self.stmts.push(quote! {
+                        let #ident = ::bellperson::gadgets::num::AllocatedNum::alloc(
+                            #cs.namespace(|| #name),
+                            || (#value).ok_or(::bellperson::SynthesisError::AssignmentMissing),
+                        )?;
+                    });
+                    return Ok(());
+                }
+                Some("input") => {
+                    let value = single_arg(call)?;
+                    let input_name = format!("{} input", name);
+                    self.stmts.push(quote! {
+                        let #ident = ::bellperson::gadgets::num::AllocatedNum::alloc(
+                            #cs.namespace(|| #name),
+                            || (#value).ok_or(::bellperson::SynthesisError::AssignmentMissing),
+                        )?;
+                        #ident.inputize(#cs.namespace(|| #input_name))?;
+                    });
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+
+        let value = self.lower(&name, expr)?;
+        self.stmts.push(quote! {
+            let #ident = ::std::clone::Clone::clone(&#value);
+        });
+
+        Ok(())
+    }
+
+    fn enforce(&mut self, lhs: &Expr, rhs: &Expr) -> syn::Result<()> {
+        let name = format!("enforce {}", self.next());
+
+        let (a, b) = match strip(lhs) {
+            Expr::Binary(binary) if is_mul(&binary.op) => {
+                let a = self.lower(&name, &binary.left)?;
+                let b = self.lower(&name, &binary.right)?;
+                (quote! { #a.get_variable() }, quote! { #b.get_variable() })
+            }
+            lhs => {
+                let a = self.lower(&name, lhs)?;
+                // The constant one, as returned by `ConstraintSystem::one`.
+                let one = quote! {
+                    ::bellperson::Variable::new_unchecked(::bellperson::Index::Input(0))
+                };
+                (quote! { #a.get_variable() }, one)
+            }
+        };
+        let c = self.lower(&name, rhs)?;
+
+        let cs = &self.cs;
+        self.stmts.push(quote! {
+            #cs.enforce(
+                || #name,
+                |lc| lc + #a,
+                |lc| lc + #b,
+                |lc| lc + #c.get_variable(),
+            );
+        });
+
+        Ok(())
+    }
+
+    /// Emits the operations of `expr` and returns an expression holding its value.
+    fn lower(&mut self, target: &str, expr: &Expr) -> syn::Result<TokenStream> {
+        match strip(expr) {
+            Expr::Path(path) if path.qself.is_none() && path.path.get_ident().is_some() => {
+                Ok(quote! { #path })
+            }
+            Expr::Binary(binary) => {
+                let (method, op) = match binary.op {
+                    BinOp::Add(_) => (quote! { add }, "add"),
+                    BinOp::Sub(_) => (quote! { sub }, "sub"),
+                    BinOp::Mul(_) => (quote! { mul }, "mul"),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            binary.op,
+                            "only `+`, `-` and `*` are supported in circuit!",
+                        ))
+                    }
+                };
+
+                let left = self.lower(target, &binary.left)?;
+                let right = self.lower(target, &binary.right)?;
+
+                let name = format!("{}: {} {}", target, op, self.next());
+                let tmp = Ident::new(
+                    &format!("__circuit_tmp_{}", self.counter),
+                    Span::mixed_site(),
+                );
+                let cs = &self.cs;
+                self.stmts.push(quote! {
+                    let #tmp = ::bellperson::gadgets::num::AllocatedNum::#method(
+                        &#left,
+                        #cs.namespace(|| #name),
+                        &#right,
+                    )?;
+                });
+
+                Ok(quote! { #tmp })
+            }
+            other => Err(syn::Error::new_spanned(
+                other,
+                "expected a variable or an arithmetic expression in circuit!",
+            )),
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        self.counter += 1;
+        self.counter - 1
+    }
+}
+
+/// Removes parentheses and invisible groups around `expr`.
+fn strip(mut expr: &Expr) -> &Expr {
+    loop {
+        match expr {
+            Expr::Paren(paren) => expr = &paren.expr,
+            Expr::Group(group) => expr = &group.expr,
+            _ => return expr,
+        }
+    }
+}
+
+fn call_name(call: &syn::ExprCall) -> Option<&'static str> {
+    let ident = match &*call.func {
+        Expr::Path(path) if path.qself.is_none() => path.path.get_ident()?,
+        _ => return None,
+    };
+
+    ["alloc", "input", "enforce"]
+        .iter()
+        .find(|name| ident == *name)
+        .copied()
+}
+
+fn single_arg(call: &syn::ExprCall) -> syn::Result<&Expr> {
+    if call.args.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            call,
+            "expected exactly one argument",
+        ));
+    }
+
+    Ok(&call.args[0])
+}
+
+fn is_eq(op: &BinOp) -> bool {
+    matches!(op, BinOp::Eq(_))
+}
+
+fn is_mul(op: &BinOp) -> bool {
+    matches!(op, BinOp::Mul(_))
+}
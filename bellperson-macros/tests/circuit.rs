@@ -0,0 +1,100 @@
+use bellperson::bls::{Bls12, Fr};
+use bellperson::gadgets::num::AllocatedNum;
+use bellperson::util_cs::test_cs::TestConstraintSystem;
+use bellperson::{ConstraintSystem, SynthesisError};
+use bellperson_macros::circuit;
+use ff::{Field, PrimeField};
+
+fn fr(s: &str) -> Option<Fr> {
+    Fr::from_str(s)
+}
+
+fn mul_add<CS: ConstraintSystem<Bls12>>(
+    cs: &mut CS,
+    a: Option<Fr>,
+    b: Option<Fr>,
+    d: Option<Fr>,
+    x: Option<Fr>,
+) -> Result<(), SynthesisError> {
+    circuit!(cs, {
+        let a = alloc(a);
+        let b = alloc(b);
+        let d = alloc(d);
+        let x = input(x);
+
+        let c = a * b + d;
+        enforce(c == x);
+    });
+
+    Ok(())
+}
+
+#[test]
+fn test_circuit_mul_add() {
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    mul_add(&mut cs, fr("3"), fr("4"), fr("5"), fr("17")).unwrap();
+
+    assert!(cs.is_satisfied());
+    assert_eq!(cs.num_inputs(), 2);
+    // inputize, mul, add and the final equality.
+    assert_eq!(cs.num_constraints(), 4);
+    assert_eq!(cs.get("c: mul 0/product num"), Fr::from_str("12").unwrap());
+    assert_eq!(cs.get("c: add 1/sum num"), Fr::from_str("17").unwrap());
+    assert_eq!(
+        cs.get_input(1, "x input/input variable"),
+        Fr::from_str("17").unwrap()
+    );
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    mul_add(&mut cs, fr("3"), fr("4"), fr("5"), fr("18")).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_missing_assignment() {
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+    match mul_add(&mut cs, None, fr("4"), fr("5"), fr("17")) {
+        Err(SynthesisError::AssignmentMissing) => {}
+        other => panic!("expected AssignmentMissing, got {:?}", other),
+    }
+}
+
+fn uses_outer<CS: ConstraintSystem<Bls12>>(
+    cs: &mut CS,
+    outer: &AllocatedNum<Bls12>,
+) -> Result<AllocatedNum<Bls12>, SynthesisError> {
+    circuit!(cs, {
+        let y = alloc(fr("7"));
+        let z = alloc(fr("10"));
+
+        // 2 * (7 - 2) = 10, enforced as a single constraint.
+        enforce(outer * (y - outer) == z);
+        let w = (y - outer) * (z + outer);
+        let v = w;
+    });
+
+    assert_eq!(w.get_value(), v.get_value());
+
+    Ok(v)
+}
+
+#[test]
+fn test_circuit_product_enforce_and_outer_variables() {
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+    let outer =
+        AllocatedNum::alloc(cs.namespace(|| "outer"), || Ok(Fr::from_str("2").unwrap())).unwrap();
+    let v = uses_outer(&mut cs, &outer).unwrap();
+
+    assert_eq!(v.get_value(), Fr::from_str("60"));
+    assert!(cs.is_satisfied());
+    // One subtraction and the product for `enforce`, then a subtraction, an addition
+    // and a multiplication for `w`.
+    assert_eq!(cs.num_constraints(), 5);
+
+    let mut broken = Fr::from_str("11").unwrap();
+    broken.negate();
+    cs.set("z/num", broken);
+    assert!(!cs.is_satisfied());
+}
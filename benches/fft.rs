@@ -0,0 +1,133 @@
+use bellperson::{
+    bls::{Bls12, Fr},
+    domain::{fft_in_place, EvaluationDomain, Scalar},
+    multicore::{FftConfig, Worker},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ff::{Field, PrimeField};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+const LOG_SIZES: [u32; 2] = [18, 22];
+
+fn domain(log_size: u32, rng: &mut XorShiftRng) -> EvaluationDomain<Bls12, Scalar<Bls12>> {
+    let coeffs = (0..1 << log_size)
+        .map(|_| Scalar::<Bls12>(Fr::random(&mut *rng)))
+        .collect::<Vec<_>>();
+
+    EvaluationDomain::from_coeffs(coeffs).unwrap()
+}
+
+fn fft_benchmark(c: &mut Criterion) {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let worker = Worker::new();
+
+    // Without a kernel, `fft` runs `parallel_fft` when more than one thread is available.
+    let mut group = c.benchmark_group("parallel_fft");
+    group.sample_size(10);
+    for &log_size in &LOG_SIZES {
+        let mut domain = domain(log_size, &mut rng);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(1 << log_size),
+            &log_size,
+            |b, _| {
+                b.iter(|| domain.fft(&worker, &mut None).unwrap());
+            },
+        );
+    }
+    group.finish();
+
+    // The bare transform over a slice, without the bookkeeping of a domain.
+    let mut group = c.benchmark_group("fft_in_place");
+    group.sample_size(10);
+    for &log_size in &LOG_SIZES {
+        let domain = domain(log_size, &mut rng);
+        let omega = domain.omega();
+        let mut a = domain
+            .into_coeffs()
+            .into_iter()
+            .map(|s| s.0)
+            .collect::<Vec<_>>();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(1 << log_size),
+            &log_size,
+            |b, &log_size| {
+                b.iter(|| fft_in_place(&worker, &mut a, &omega, log_size, &mut None).unwrap());
+            },
+        );
+    }
+    group.finish();
+
+    // Sizes against numbers of sub-FFTs, always splitting, to tune `FftConfig`.
+    let log_cpus = worker.log_num_cpus();
+    let mut log_threads = vec![0, log_cpus / 2, log_cpus, log_cpus + 2];
+    log_threads.dedup();
+    let mut group = c.benchmark_group("fft_config");
+    group.sample_size(10);
+    for log_size in (12..=26).step_by(2) {
+        let mut domain = domain(log_size, &mut rng);
+        for &log_threads in &log_threads {
+            let worker = Worker::with_fft_config(FftConfig {
+                min_parallel_log_size: Some(0),
+                max_log_threads_override: Some(log_threads),
+                min_four_step_log_size: None,
+            });
+            group.bench_with_input(
+                BenchmarkId::new(format!("2^{}_sub_ffts", log_threads), 1 << log_size),
+                &log_size,
+                |b, _| {
+                    b.iter(|| domain.fft(&worker, &mut None).unwrap());
+                },
+            );
+        }
+    }
+    group.finish();
+
+    // The coset shift and the scaling by the inverse of `z`, around the FFTs in the prover.
+    let mut group = c.benchmark_group("coset_scaling");
+    group.sample_size(10);
+    for &log_size in &LOG_SIZES {
+        let mut domain = domain(log_size, &mut rng);
+        group.bench_with_input(
+            BenchmarkId::new("distribute_powers", 1 << log_size),
+            &log_size,
+            |b, _| {
+                b.iter(|| domain.distribute_powers(&worker, Fr::multiplicative_generator()));
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("divide_by_z_on_coset", 1 << log_size),
+            &log_size,
+            |b, _| {
+                b.iter(|| domain.divide_by_z_on_coset(&worker));
+            },
+        );
+    }
+    group.finish();
+
+    #[cfg(feature = "gpu")]
+    {
+        use bellperson::gpu::LockedFFTKernel;
+
+        let mut group = c.benchmark_group("gpu_fft");
+        group.sample_size(10);
+        for &log_size in &LOG_SIZES {
+            let mut domain = domain(log_size, &mut rng);
+            let mut kern = Some(LockedFFTKernel::<Bls12>::new(log_size as usize, false));
+            group.bench_with_input(
+                BenchmarkId::from_parameter(1 << log_size),
+                &log_size,
+                |b, _| {
+                    b.iter(|| domain.fft(&worker, &mut kern).unwrap());
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, fft_benchmark);
+criterion_main!(benches);
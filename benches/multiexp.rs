@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use bellperson::{
+    bls::{Bls12, Engine},
+    multicore::Worker,
+    multiexp::{multiexp, FullDensity},
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ff::{Field, PrimeField, ScalarEngine};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// `n` distinct bases. Sampling each of them with `random` takes far longer than the
+/// benchmarks themselves at 2^20 points, so they are consecutive multiples of a step.
+fn bases<G: CurveAffine>(n: usize, rng: &mut XorShiftRng) -> Arc<Vec<G>> {
+    let step = G::Projective::random(rng);
+    let mut current = G::Projective::random(rng);
+
+    let mut points = Vec::with_capacity(n);
+    for _ in 0..n {
+        points.push(current);
+        current.add_assign(&step);
+    }
+    G::Projective::batch_normalization(&mut points);
+
+    Arc::new(points.into_iter().map(|p| p.into_affine()).collect())
+}
+
+fn bench_multiexp<G: CurveAffine>(c: &mut Criterion, name: &str, log_sizes: &[u32])
+where
+    G::Engine: Engine,
+{
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let pool = Worker::new();
+
+    let max = 1 << *log_sizes.iter().max().unwrap();
+    let bases = bases::<G>(max, &mut rng);
+    let exponents = (0..max)
+        .map(|_| <G::Engine as ScalarEngine>::Fr::random(&mut rng).into_repr())
+        .collect::<Vec<_>>();
+
+    // Sizes below 2^10 use projective buckets, larger ones batched affine additions.
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for &log_size in log_sizes {
+        let size = 1 << log_size;
+        let exponents = Arc::new(exponents[..size].to_vec());
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| {
+                black_box(
+                    multiexp(
+                        &pool,
+                        (bases.clone(), 0),
+                        FullDensity,
+                        exponents.clone(),
+                        &mut None,
+                    )
+                    .wait()
+                    .unwrap(),
+                )
+            });
+        });
+    }
+    group.finish();
+
+    #[cfg(feature = "gpu")]
+    {
+        use bellperson::gpu::LockedMultiexpKernel;
+        use bellperson::multiexp::heterogeneous_multiexp;
+
+        let mut group = c.benchmark_group(format!("{} gpu", name));
+        group.sample_size(10);
+        for &log_size in log_sizes {
+            let size = 1 << log_size;
+            let exponents = Arc::new(exponents[..size].to_vec());
+            let mut kern = Some(LockedMultiexpKernel::<G::Engine>::new(
+                log_size as usize,
+                false,
+            ));
+            group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+                b.iter(|| {
+                    black_box(
+                        multiexp(
+                            &pool,
+                            (bases.clone(), 0),
+                            FullDensity,
+                            exponents.clone(),
+                            &mut kern,
+                        )
+                        .wait()
+                        .unwrap(),
+                    )
+                });
+            });
+        }
+        group.finish();
+
+        // How much of the largest multiexp to leave to the GPUs, the rest running on
+        // the CPU at the same time.
+        let log_size = *log_sizes.iter().max().unwrap();
+        let exponents = Arc::new(exponents);
+        let mut kern = LockedMultiexpKernel::<G::Engine>::new(log_size as usize, false);
+        let mut group = c.benchmark_group(format!("{} gpu fraction", name));
+        group.sample_size(10);
+        for &gpu_fraction in &[0.5f32, 0.7, 0.8, 0.9, 0.95, 1.0] {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(gpu_fraction),
+                &gpu_fraction,
+                |b, &gpu_fraction| {
+                    b.iter(|| {
+                        black_box(
+                            heterogeneous_multiexp(
+                                &pool,
+                                bases.clone(),
+                                exponents.clone(),
+                                gpu_fraction,
+                                &mut kern,
+                            )
+                            .unwrap(),
+                        )
+                    });
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+/// 2^16 exponents of which one in `period` is non-zero, as in the A query of a circuit
+/// whose wires are mostly constant, e.g. the fixed tables of a block cipher, against
+/// the mostly random wires of a hash. Below 10% the multiexp only goes over the
+/// non-zero exponents.
+fn bench_sparse_multiexp(c: &mut Criterion) {
+    type G = <Bls12 as Engine>::G1Affine;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let pool = Worker::new();
+
+    let size = 1 << 16;
+    let bases = bases::<G>(size, &mut rng);
+
+    let mut group = c.benchmark_group("multiexp G1 sparse");
+    group.sample_size(10);
+    for &period in &[1, 2, 9, 11, 100] {
+        let exponents = Arc::new(
+            (0..size)
+                .map(|i| {
+                    if i % period == 0 {
+                        <Bls12 as ScalarEngine>::Fr::random(&mut rng)
+                    } else {
+                        <Bls12 as ScalarEngine>::Fr::zero()
+                    }
+                })
+                .map(|e| e.into_repr())
+                .collect::<Vec<_>>(),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("one non-zero in", period),
+            &period,
+            |b, _| {
+                b.iter(|| {
+                    black_box(
+                        multiexp(
+                            &pool,
+                            (bases.clone(), 0),
+                            FullDensity,
+                            exponents.clone(),
+                            &mut None,
+                        )
+                        .wait()
+                        .unwrap(),
+                    )
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+fn multiexp_benchmark(c: &mut Criterion) {
+    bench_multiexp::<<Bls12 as Engine>::G1Affine>(c, "multiexp G1", &[10, 16, 20]);
+    bench_multiexp::<<Bls12 as Engine>::G2Affine>(c, "multiexp G2", &[10, 16]);
+    bench_sparse_multiexp(c);
+}
+
+criterion_group!(benches, multiexp_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+use bellperson::{
+    bls::{Bls12, Engine},
+    gadgets::{
+        boolean::{AllocatedBit, Boolean},
+        multipack,
+        sha256::sha256,
+    },
+    groth16::{create_random_proof, generate_random_parameters},
+    Circuit, ConstraintSystem, SynthesisError,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Proves knowledge of a SHA-256 preimage. The padding and round constants of the
+/// gadget produce many linear combinations with very few terms.
+#[derive(Clone)]
+struct Sha256Circuit {
+    preimage: Option<[u8; 64]>,
+}
+
+impl<E: Engine> Circuit<E> for Sha256Circuit {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let bit_values: Vec<Option<bool>> = match self.preimage {
+            Some(preimage) => multipack::bytes_to_bits(&preimage)
+                .into_iter()
+                .map(Some)
+                .collect(),
+            None => vec![None; 64 * 8],
+        };
+
+        let preimage_bits = bit_values
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("preimage bit {}", i)), b)
+                    .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let hash = sha256(cs.namespace(|| "sha256"), &preimage_bits)?;
+
+        multipack::pack_into_inputs(cs.namespace(|| "pack hash"), &hash)
+    }
+}
+
+fn prover_benchmark(c: &mut Criterion) {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params =
+        generate_random_parameters::<Bls12, _, _>(Sha256Circuit { preimage: None }, &mut rng)
+            .unwrap();
+
+    let mut group = c.benchmark_group("prover");
+    group.sample_size(10);
+    group.bench_function("create_proof SHA-256(64 bytes)", |b| {
+        b.iter(|| {
+            let circuit = Sha256Circuit {
+                preimage: Some([42; 64]),
+            };
+            black_box(create_random_proof(circuit, &params, &mut rng).unwrap());
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, prover_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+use bellperson::{
+    bls::{Bls12, Fr},
+    r1cs::R1CSMatrixCSR,
+    Index, LinearCombination, Variable,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ff::Field;
+use rand::Rng;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+const CONSTRAINTS: usize = 1_000_000;
+
+fn value(var: &Variable, inputs: &[Fr], aux: &[Fr]) -> Fr {
+    match var.get_unchecked() {
+        Index::Input(i) => inputs[i],
+        Index::Aux(i) => aux[i],
+    }
+}
+
+fn r1cs_benchmark(c: &mut Criterion) {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let inputs = (0..16).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+    let aux = (0..CONSTRAINTS)
+        .map(|_| Fr::random(&mut rng))
+        .collect::<Vec<_>>();
+
+    // Rows of one to four terms, mostly over variables allocated shortly before, like
+    // those of gadgets.
+    let lcs = (0..CONSTRAINTS)
+        .map(|row| {
+            (0..rng.gen_range(1, 5)).fold(LinearCombination::<Bls12>::zero(), |lc, _| {
+                let var = if rng.gen_range(0, 16) == 0 {
+                    Variable::new_unchecked(Index::Input(rng.gen_range(0, inputs.len())))
+                } else {
+                    Variable::new_unchecked(Index::Aux(row.saturating_sub(rng.gen_range(0, 64))))
+                };
+                lc + (Fr::random(&mut rng), var)
+            })
+        })
+        .collect::<Vec<_>>();
+    let matrix = R1CSMatrixCSR::from_lcs(&lcs);
+
+    let mut group = c.benchmark_group("r1cs 10^6 constraints");
+    group.sample_size(10);
+    group.bench_function("LinearCombination", |b| {
+        b.iter(|| {
+            let values = lcs
+                .iter()
+                .map(|lc| {
+                    lc.iter().fold(Fr::zero(), |mut acc, (var, coeff)| {
+                        let mut term = value(var, &inputs, &aux);
+                        term.mul_assign(coeff);
+                        acc.add_assign(&term);
+                        acc
+                    })
+                })
+                .collect::<Vec<_>>();
+            black_box(values);
+        });
+    });
+    group.bench_function("R1CSMatrixCSR rows", |b| {
+        b.iter(|| {
+            let values = (0..matrix.num_rows())
+                .map(|i| {
+                    let (columns, coeffs) = matrix.row(i);
+                    columns
+                        .iter()
+                        .zip(coeffs)
+                        .fold(Fr::zero(), |mut acc, (var, coeff)| {
+                            let mut term = value(var, &inputs, &aux);
+                            term.mul_assign(coeff);
+                            acc.add_assign(&term);
+                            acc
+                        })
+                })
+                .collect::<Vec<_>>();
+            black_box(values);
+        });
+    });
+    group.bench_function("R1CSMatrixCSR::evaluate", |b| {
+        b.iter(|| black_box(matrix.evaluate(&inputs, &aux)));
+    });
+    // The prover copies the terms of every constraint into the matrices before
+    // evaluating them, which the first benchmark does not.
+    group.bench_function("R1CSMatrixCSR::from_lcs and evaluate", |b| {
+        b.iter(|| {
+            let matrix = R1CSMatrixCSR::from_lcs(&lcs);
+            black_box(matrix.evaluate(&inputs, &aux))
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, r1cs_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,122 @@
+use bellperson::{
+    bls::{Bls12, Fr},
+    groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+        verify_proofs_batch, Parameters, Proof,
+    },
+    Circuit, ConstraintSystem, SynthesisError,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ff::Field;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Exposes `inputs` as public inputs, next to a single multiplication.
+#[derive(Clone)]
+struct InputsCircuit {
+    inputs: Vec<Option<Fr>>,
+}
+
+impl Circuit<Bls12> for InputsCircuit {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        for (i, input) in self.inputs.iter().enumerate() {
+            cs.alloc_input(
+                || format!("input {}", i),
+                || input.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+        }
+
+        let value = self.inputs.first().cloned().flatten();
+        let x = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+        let x2 = cs.alloc(
+            || "x^2",
+            || {
+                let mut tmp = value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.square();
+                Ok(tmp)
+            },
+        )?;
+        cs.enforce(|| "x * x = x^2", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+
+        Ok(())
+    }
+}
+
+fn rng() -> XorShiftRng {
+    XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ])
+}
+
+/// Parameters whose verifying key has `ic_size` elements.
+fn setup(ic_size: usize, rng: &mut XorShiftRng) -> Parameters<Bls12> {
+    let circuit = InputsCircuit {
+        inputs: vec![None; ic_size - 1],
+    };
+
+    generate_random_parameters::<Bls12, _, _>(circuit, rng).unwrap()
+}
+
+fn prove(
+    params: &Parameters<Bls12>,
+    ic_size: usize,
+    rng: &mut XorShiftRng,
+) -> (Proof<Bls12>, Vec<Fr>) {
+    let inputs = (0..ic_size - 1)
+        .map(|_| Fr::random(&mut *rng))
+        .collect::<Vec<_>>();
+    let circuit = InputsCircuit {
+        inputs: inputs.iter().cloned().map(Some).collect(),
+    };
+
+    (create_random_proof(circuit, params, rng).unwrap(), inputs)
+}
+
+fn verifier_benchmark(c: &mut Criterion) {
+    let mut rng = rng();
+
+    let mut group = c.benchmark_group("prepare_verifying_key");
+    for &ic_size in &[2, 16, 256] {
+        let params = setup(ic_size, &mut rng);
+        group.bench_with_input(BenchmarkId::from_parameter(ic_size), &ic_size, |b, _| {
+            b.iter(|| black_box(prepare_verifying_key(&params.vk)));
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("verify_proof");
+    for &ic_size in &[2, 16, 256] {
+        let params = setup(ic_size, &mut rng);
+        let pvk = prepare_verifying_key(&params.vk);
+        let (proof, inputs) = prove(&params, ic_size, &mut rng);
+        group.bench_with_input(BenchmarkId::from_parameter(ic_size), &ic_size, |b, _| {
+            b.iter(|| assert!(verify_proof(&pvk, &proof, &inputs).unwrap()));
+        });
+    }
+    group.finish();
+
+    let ic_size = 16;
+    let params = setup(ic_size, &mut rng);
+    let pvk = prepare_verifying_key(&params.vk);
+    let (proofs, inputs): (Vec<_>, Vec<_>) =
+        (0..64).map(|_| prove(&params, ic_size, &mut rng)).unzip();
+
+    let mut group = c.benchmark_group("verify_proofs_batch");
+    for &batch_size in &[1, 8, 64] {
+        let proofs = proofs[..batch_size].iter().collect::<Vec<_>>();
+        let inputs = &inputs[..batch_size];
+        group.bench_with_input(
+            BenchmarkId::from_parameter(batch_size),
+            &batch_size,
+            |b, _| {
+                let mut rng = self::rng();
+                b.iter(|| assert!(verify_proofs_batch(&pvk, &mut rng, &proofs, inputs).unwrap()));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, verifier_benchmark);
+criterion_main!(benches);
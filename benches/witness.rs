@@ -0,0 +1,59 @@
+use bellperson::{
+    bls::{Bls12, Fr},
+    gadgets::num::AllocatedNum,
+    witness::WitnessPlan,
+    Circuit, ConstraintSystem, SynthesisError,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ff::{Field, PrimeField};
+
+/// `width` independent chains of `depth` steps `y = y^2 + x_j`, with the free `x_j`.
+struct Chains {
+    width: usize,
+    depth: usize,
+}
+
+impl Circuit<Bls12> for Chains {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        for j in 0..self.width {
+            let mut cs = cs.namespace(|| format!("chain {}", j));
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                Err(SynthesisError::AssignmentMissing)
+            })?;
+
+            let mut y = x.clone();
+            for i in 0..self.depth {
+                let mut cs = cs.namespace(|| format!("step {}", i));
+                y = y
+                    .square(cs.namespace(|| "square"))?
+                    .add(cs.namespace(|| "add"), &x)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The same number of definitions, in one chain of `2^15` layers, or in `2^13`
+/// chains of four layers.
+fn witness_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("witness extension");
+    group.sample_size(10);
+
+    for &(name, width, depth) in &[("deep", 1, 1 << 14), ("wide", 1 << 13, 2)] {
+        let plan = WitnessPlan::<Bls12>::from_circuit(Chains { width, depth }).unwrap();
+        let inputs = vec![Fr::one()];
+        let mut aux = vec![Fr::from_str("3").unwrap(); width * (2 * depth + 1)];
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                plan.extend(&inputs, &mut aux);
+                black_box(&aux);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, witness_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+//! Prints the structural breakdown of the constraints of the SHA-256 gadget.
+//!
+//! Run with `cargo run --example constraint_stats`.
+
+use bellperson::bls::Bls12;
+use bellperson::gadgets::boolean::{AllocatedBit, Boolean};
+use bellperson::gadgets::sha256::{sha256, sha256_block_no_padding};
+use bellperson::util_cs::bench_cs::BenchCS;
+use bellperson::util_cs::inspector_cs::{ConstraintStats, ConstraintSystemInspector};
+use bellperson::{ConstraintSystem, SynthesisError};
+
+fn input<CS: ConstraintSystem<Bls12>>(
+    mut cs: CS,
+    bits: usize,
+) -> Result<Vec<Boolean>, SynthesisError> {
+    (0..bits)
+        .map(|i| {
+            AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), None)
+                .map(Boolean::from)
+        })
+        .collect()
+}
+
+fn print(name: &str, stats: ConstraintStats) {
+    println!("{}:", name);
+    println!("  linear:    {:>6}", stats.linear);
+    println!("  quadratic: {:>6}", stats.quadratic);
+    println!("  constant:  {:>6}", stats.constant);
+    println!("  boolean:   {:>6}", stats.boolean);
+    println!("  total:     {:>6}", stats.total());
+}
+
+fn main() -> Result<(), SynthesisError> {
+    // A single compression, of 512 allocated bits.
+    let mut cs = ConstraintSystemInspector::new(BenchCS::<Bls12>::new());
+    let bits = input(cs.namespace(|| "input"), 512)?;
+    sha256_block_no_padding(cs.namespace(|| "sha256"), &bits)?;
+    print("SHA-256 compression of one block", cs.stats());
+
+    // The padded hash of 32 bytes, whose padding is constant.
+    let mut cs = ConstraintSystemInspector::new(BenchCS::<Bls12>::new());
+    let bits = input(cs.namespace(|| "input"), 256)?;
+    sha256(cs.namespace(|| "sha256"), &bits)?;
+    print("SHA-256 of 32 bytes", cs.stats());
+
+    Ok(())
+}
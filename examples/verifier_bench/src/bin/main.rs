@@ -6,6 +6,7 @@
 // --gpu                    Enables GPU
 // --samples                Number of runs
 // --dummy                  Skip param generation and generate dummy params/proofs
+use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -93,10 +94,12 @@ fn random_points<C: CurveProjective, R: Rng>(count: usize, rng: &mut R) -> Vec<C
 
 fn dummy_proofs<E: Engine, R: Rng>(count: usize, rng: &mut R) -> Vec<Proof<E>> {
     (0..count)
-        .map(|_| Proof {
-            a: E::G1::random(rng).into_affine(),
-            b: E::G2::random(rng).into_affine(),
-            c: E::G1::random(rng).into_affine(),
+        .map(|_| {
+            Proof::new(
+                E::G1::random(rng).into_affine(),
+                E::G2::random(rng).into_affine(),
+                E::G1::random(rng).into_affine(),
+            )
         })
         .collect()
 }
@@ -116,6 +119,7 @@ fn dummy_vk<E: Engine, R: Rng>(public: usize, rng: &mut R) -> VerifyingKey<E> {
         delta_g1: E::G1::random(rng).into_affine(),
         delta_g2: E::G2::random(rng).into_affine(),
         ic: random_points::<E::G1, _>(public + 1, rng),
+        circuit: PhantomData,
     }
 }
 
@@ -0,0 +1,17 @@
+//! Reads arbitrary bytes as a proof. Any panic is reported as a crash, invalid bytes
+//! must be rejected with an error.
+#![no_main]
+
+use bellperson::bls::Bls12;
+use bellperson::groth16::Proof;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = Proof::<Bls12>::read(data) {
+        // Whatever was accepted must survive a round trip.
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).unwrap();
+        assert_eq!(&bytes[..], &data[..Proof::<Bls12>::size()]);
+        assert_eq!(Proof::<Bls12>::read(&bytes[..]).unwrap(), proof);
+    }
+});
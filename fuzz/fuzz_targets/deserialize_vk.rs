@@ -0,0 +1,18 @@
+//! Reads arbitrary bytes as a verifying key and as parameters. Any panic is reported
+//! as a crash, invalid bytes must be rejected with an error.
+#![no_main]
+
+use bellperson::bls::Bls12;
+use bellperson::groth16::{Parameters, VerifyingKey};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(vk) = VerifyingKey::<Bls12>::read(data) {
+        let mut bytes = Vec::new();
+        vk.write(&mut bytes).unwrap();
+        assert!(VerifyingKey::<Bls12>::read(&bytes[..]).unwrap() == vk);
+    }
+
+    let _ = Parameters::<Bls12>::read(data, true);
+    let _ = Parameters::<Bls12>::read(data, false);
+});
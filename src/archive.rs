@@ -0,0 +1,571 @@
+//! An append-only file of proofs, which stay verifiable across circuit upgrades.
+//!
+//! Every record holds a proof with the fingerprint of the verifying key it was made
+//! for (see [`circuit_id`]), its public inputs, the time it was archived and optionally
+//! the witness it was made from, as bytes in a format chosen by the application. When
+//! a circuit changes, [`ProofArchive::migrate`] reproves the proofs with a stored
+//! witness for the new parameters, appending the new proofs next to the old ones.
+//!
+//! The file is a sequence of records, each a big-endian `u32` format version and a
+//! `u32` length followed by the body. In [`FORMAT_VERSION`] 1 the body is the
+//! fingerprint, the timestamp as `u64` seconds since the epoch, the compressed proof,
+//! the number of public inputs as `u32` and the inputs as big-endian field elements, and
+//! a `u8` witness flag followed by a `u32` length and the witness if set.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{PrimeField, PrimeFieldRepr};
+use log::warn;
+use rand_core::RngCore;
+
+use crate::bls::Engine;
+use crate::groth16::{
+    create_random_proof, prepare_verifying_key, verify_proof, ConditionalSwap, Parameters,
+    PreparedVerifyingKey, Proof, VerifyingKey,
+};
+use crate::registry::{circuit_id, CircuitId};
+use crate::{Circuit, SynthesisError};
+
+/// Format version of newly written records.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A proof in the archive.
+#[derive(Clone, Debug)]
+pub struct ArchivedProof<E: Engine> {
+    pub proof: Proof<E>,
+    /// Fingerprint of the verifying key of the proof.
+    pub vk_fingerprint: CircuitId,
+    pub public_inputs: Vec<E::Fr>,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Format version the record was stored in.
+    pub format_version: u32,
+    /// The witness, needed to reprove the statement for a new circuit.
+    pub witness: Option<Vec<u8>>,
+}
+
+impl<E: Engine> ArchivedProof<E> {
+    /// Creates a record for a proof made for `vk`, timestamped now.
+    pub fn new(
+        proof: Proof<E>,
+        vk: &VerifyingKey<E>,
+        public_inputs: Vec<E::Fr>,
+        witness: Option<Vec<u8>>,
+    ) -> Self {
+        ArchivedProof {
+            proof,
+            vk_fingerprint: circuit_id(vk),
+            public_inputs,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            format_version: FORMAT_VERSION,
+            witness,
+        }
+    }
+
+    pub fn verify(&self, pvk: &PreparedVerifyingKey<E>) -> Result<bool, SynthesisError> {
+        verify_proof(pvk, &self.proof, &self.public_inputs)
+    }
+
+    fn write_body<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.vk_fingerprint)?;
+        writer.write_u64::<BigEndian>(self.timestamp)?;
+        self.proof.write(&mut writer)?;
+        writer.write_u32::<BigEndian>(self.public_inputs.len() as u32)?;
+        for input in &self.public_inputs {
+            input.into_repr().write_be(&mut writer)?;
+        }
+        match self.witness {
+            Some(ref witness) => {
+                writer.write_u8(1)?;
+                writer.write_u32::<BigEndian>(witness.len() as u32)?;
+                writer.write_all(witness)?;
+            }
+            None => writer.write_u8(0)?,
+        }
+
+        Ok(())
+    }
+
+    fn read_body<R: Read>(mut reader: R, format_version: u32) -> io::Result<Self> {
+        if format_version != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported archive format version {}", format_version),
+            ));
+        }
+
+        let mut vk_fingerprint = [0u8; 32];
+        reader.read_exact(&mut vk_fingerprint)?;
+        let timestamp = reader.read_u64::<BigEndian>()?;
+        let proof = Proof::read(&mut reader)?;
+
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+        let public_inputs = (0..num_inputs)
+            .map(|_| {
+                let mut repr = <E::Fr as PrimeField>::Repr::default();
+                repr.read_be(&mut reader)?;
+                E::Fr::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let witness = match reader.read_u8()? {
+            0 => None,
+            1 => {
+                let len = reader.read_u32::<BigEndian>()?;
+                // Reads at most the bytes which are there, so a corrupt length cannot
+                // allocate more.
+                let mut witness = Vec::new();
+                reader
+                    .by_ref()
+                    .take(u64::from(len))
+                    .read_to_end(&mut witness)?;
+                if witness.len() != len as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated witness",
+                    ));
+                }
+                Some(witness)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid witness flag",
+                ))
+            }
+        };
+
+        Ok(ArchivedProof {
+            proof,
+            vk_fingerprint,
+            public_inputs,
+            timestamp,
+            format_version,
+            witness,
+        })
+    }
+}
+
+/// Outcome of [`ProofArchive::migrate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Proofs reproved for the new circuit.
+    pub migrated: usize,
+    /// Proofs without a witness, which are only verifiable with the old key.
+    pub without_witness: usize,
+    /// Proofs whose witness did not fit or satisfy the new circuit.
+    pub failed: usize,
+}
+
+/// Drops the record at `offset` of the archive `file`, cut short by a crash while
+/// appending it, so the archive can be appended to again.
+fn truncate_torn(file: &File, path: &Path, offset: u64) -> io::Result<()> {
+    warn!(
+        "truncating torn record at {} of the archive {}",
+        offset,
+        path.display()
+    );
+    file.set_len(offset)
+}
+
+/// Proofs stored in an append-only file.
+pub struct ProofArchive<E: Engine> {
+    path: PathBuf,
+    entries: Vec<ArchivedProof<E>>,
+}
+
+impl<E: Engine> ProofArchive<E> {
+    /// Opens the archive at `path`, creating an empty one if there is no file yet.
+    ///
+    /// A final record extending past the end of the file, left by a crash while
+    /// appending it, is truncated away. Any other invalid record is an error.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < len {
+            if len - offset < 8 {
+                truncate_torn(reader.get_ref(), &path, offset)?;
+                break;
+            }
+            let format_version = reader.read_u32::<BigEndian>()?;
+            let body_len = reader.read_u32::<BigEndian>()?;
+            if u64::from(body_len) > len - offset - 8 {
+                truncate_torn(reader.get_ref(), &path, offset)?;
+                break;
+            }
+            let mut body = vec![0u8; body_len as usize];
+            reader.read_exact(&mut body)?;
+
+            let mut body_reader = &body[..];
+            let entry = ArchivedProof::read_body(&mut body_reader, format_version)?;
+            if !body_reader.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "trailing bytes in archive record",
+                ));
+            }
+            entries.push(entry);
+
+            offset += 8 + u64::from(body_len);
+        }
+
+        Ok(ProofArchive { path, entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> &[ArchivedProof<E>] {
+        &self.entries
+    }
+
+    /// The proofs for the verifying key with the fingerprint `vk_fingerprint`.
+    pub fn entries_for<'a>(
+        &'a self,
+        vk_fingerprint: &'a CircuitId,
+    ) -> impl Iterator<Item = &'a ArchivedProof<E>> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.vk_fingerprint == *vk_fingerprint)
+    }
+
+    /// Appends `entry` to the file. The record is written at once, with the current
+    /// format version.
+    pub fn append(&mut self, mut entry: ArchivedProof<E>) -> io::Result<()> {
+        entry.format_version = FORMAT_VERSION;
+
+        let mut body = Vec::new();
+        entry.write_body(&mut body)?;
+        let mut record = Vec::with_capacity(8 + body.len());
+        record.write_u32::<BigEndian>(FORMAT_VERSION)?;
+        record.write_u32::<BigEndian>(body.len() as u32)?;
+        record.extend_from_slice(&body);
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(&record)?;
+        file.sync_data()?;
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Reproves the proofs for `old_vk` with a stored witness for `new_params`,
+    /// appending the new proofs. The old records are kept, so the old proofs stay
+    /// verifiable with `old_vk`.
+    ///
+    /// `rebuild` turns a witness into the new circuit and its public inputs, or `None`
+    /// if the witness does not apply to the new circuit. Does nothing if both keys are
+    /// the same.
+    pub fn migrate<C, F, R>(
+        &mut self,
+        old_vk: &VerifyingKey<E>,
+        new_params: &Parameters<E>,
+        mut rebuild: F,
+        rng: &mut R,
+    ) -> Result<MigrationReport, SynthesisError>
+    where
+        C: Circuit<E>,
+        F: FnMut(&[u8]) -> Option<(C, Vec<E::Fr>)>,
+        R: RngCore,
+        E::G1: ConditionalSwap,
+        E::G2: ConditionalSwap,
+    {
+        let old_id = circuit_id(old_vk);
+        let mut report = MigrationReport::default();
+        if old_id == circuit_id(&new_params.vk) {
+            return Ok(report);
+        }
+
+        let new_pvk = prepare_verifying_key(&new_params.vk);
+        let witnesses = self
+            .entries_for(&old_id)
+            .map(|entry| entry.witness.clone())
+            .collect::<Vec<_>>();
+        for witness in witnesses {
+            let witness = match witness {
+                Some(witness) => witness,
+                None => {
+                    report.without_witness += 1;
+                    continue;
+                }
+            };
+            let (circuit, public_inputs) = match rebuild(&witness) {
+                Some(rebuilt) => rebuilt,
+                None => {
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            match create_random_proof(circuit, new_params, rng) {
+                // The prover does not check the witness, an invalid proof means it does
+                // not satisfy the new circuit.
+                Ok(proof) if !verify_proof(&new_pvk, &proof, &public_inputs)? => {
+                    report.failed += 1;
+                }
+                Ok(proof) => {
+                    self.append(ArchivedProof::new(
+                        proof,
+                        &new_params.vk,
+                        public_inputs,
+                        Some(witness),
+                    ))?;
+                    report.migrated += 1;
+                }
+                Err(SynthesisError::AssignmentMissing) | Err(SynthesisError::Unsatisfiable) => {
+                    report.failed += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::generate_random_parameters;
+    use crate::ConstraintSystem;
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Version 1 proves `y = x^2`, version 2 proves `y = x^2 + x`.
+    struct Versioned {
+        x: Option<Fr>,
+        version: u32,
+    }
+
+    impl Versioned {
+        fn output(x: Fr, version: u32) -> Fr {
+            let mut y = x;
+            y.square();
+            if version == 2 {
+                y.add_assign(&x);
+            }
+            y
+        }
+    }
+
+    impl Circuit<Bls12> for Versioned {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let version = self.version;
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                    Ok(Versioned::output(x, version))
+                },
+            )?;
+            if version == 2 {
+                cs.enforce(
+                    || "x * x = y - x",
+                    |lc| lc + x,
+                    |lc| lc + x,
+                    |lc| lc + y - x,
+                );
+            } else {
+                cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+            }
+
+            Ok(())
+        }
+    }
+
+    fn witness_bytes(x: &Fr) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        x.into_repr().write_be(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_archive_and_migrate() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let path =
+            std::env::temp_dir().join(format!("bellperson-archive-{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let params_v1 = generate_random_parameters::<Bls12, _, _>(
+            Versioned {
+                x: None,
+                version: 1,
+            },
+            rng,
+        )
+        .unwrap();
+        let params_v2 = generate_random_parameters::<Bls12, _, _>(
+            Versioned {
+                x: None,
+                version: 2,
+            },
+            rng,
+        )
+        .unwrap();
+        let pvk_v1 = prepare_verifying_key(&params_v1.vk);
+        let pvk_v2 = prepare_verifying_key(&params_v2.vk);
+
+        let mut archive = ProofArchive::<Bls12>::open(&path).unwrap();
+        assert!(archive.entries().is_empty());
+
+        // Two proofs with a witness, one without.
+        for i in 0..3 {
+            let x = Fr::random(rng);
+            let proof = create_random_proof(
+                Versioned {
+                    x: Some(x),
+                    version: 1,
+                },
+                &params_v1,
+                rng,
+            )
+            .unwrap();
+            let witness = if i < 2 { Some(witness_bytes(&x)) } else { None };
+            archive
+                .append(ArchivedProof::new(
+                    proof,
+                    &params_v1.vk,
+                    vec![Versioned::output(x, 1)],
+                    witness,
+                ))
+                .unwrap();
+        }
+
+        let mut archive = ProofArchive::<Bls12>::open(&path).unwrap();
+        assert_eq!(archive.entries().len(), 3);
+        for entry in archive.entries() {
+            assert_eq!(entry.format_version, FORMAT_VERSION);
+            assert!(entry.verify(&pvk_v1).unwrap());
+        }
+        assert!(archive.entries()[0].witness.is_some());
+        assert!(archive.entries()[2].witness.is_none());
+
+        let rebuild = |witness: &[u8]| {
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            repr.read_be(witness).ok()?;
+            let x = Fr::from_repr(repr).ok()?;
+            Some((
+                Versioned {
+                    x: Some(x),
+                    version: 2,
+                },
+                vec![Versioned::output(x, 2)],
+            ))
+        };
+        let report = archive
+            .migrate(&params_v1.vk, &params_v2, rebuild, rng)
+            .unwrap();
+        assert_eq!(
+            report,
+            MigrationReport {
+                migrated: 2,
+                without_witness: 1,
+                failed: 0,
+            }
+        );
+
+        // Migrating to the same key does nothing.
+        let report = archive
+            .migrate(&params_v2.vk, &params_v2, rebuild, rng)
+            .unwrap();
+        assert_eq!(report, MigrationReport::default());
+
+        let archive = ProofArchive::<Bls12>::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(archive.entries().len(), 5);
+        let old_id = circuit_id(&params_v1.vk);
+        let new_id = circuit_id(&params_v2.vk);
+        assert_eq!(archive.entries_for(&old_id).count(), 3);
+        assert_eq!(archive.entries_for(&new_id).count(), 2);
+        for entry in archive.entries_for(&old_id) {
+            assert!(entry.verify(&pvk_v1).unwrap());
+        }
+        for (old, new) in archive.entries().iter().zip(archive.entries_for(&new_id)) {
+            assert!(new.verify(&pvk_v2).unwrap());
+            assert_eq!(old.witness, new.witness);
+        }
+    }
+
+    #[test]
+    fn test_torn_archive() {
+        let path = std::env::temp_dir().join(format!(
+            "bellperson-archive-torn-{}.bin",
+            std::process::id()
+        ));
+
+        // A header announcing a longer body than the file has, a header claiming 4 GiB,
+        // and half a header.
+        for torn in &[
+            &[0u8, 0, 0, 1, 0, 0, 1, 0, 42][..],
+            &[0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff],
+            &[0, 0, 0],
+        ] {
+            std::fs::write(&path, torn).unwrap();
+            let archive = ProofArchive::<Bls12>::open(&path).unwrap();
+            assert!(archive.entries().is_empty());
+            assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        }
+
+        // A complete record of an unknown version is still an error.
+        std::fs::write(&path, &[0, 0, 0, 2, 0, 0, 0, 1, 42]).unwrap();
+        let result = ProofArchive::<Bls12>::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_corrupt_witness_length() {
+        use crate::bls::{G1Affine, G2Affine};
+        use groupy::CurveAffine;
+
+        let entry = ArchivedProof::<Bls12> {
+            proof: Proof::new(G1Affine::one(), G2Affine::one(), G1Affine::one()),
+            vk_fingerprint: [0; 32],
+            public_inputs: vec![Fr::one()],
+            timestamp: 0,
+            format_version: FORMAT_VERSION,
+            witness: Some(vec![1, 2, 3]),
+        };
+        let mut body = Vec::new();
+        entry.write_body(&mut body).unwrap();
+        let read = ArchivedProof::<Bls12>::read_body(&body[..], FORMAT_VERSION).unwrap();
+        assert_eq!(read.witness, entry.witness);
+
+        // A witness length of 4 GiB fails on the missing bytes instead of allocating.
+        let len_at = body.len() - 3 - 4;
+        body[len_at..len_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = ArchivedProof::<Bls12>::read_body(&body[..], FORMAT_VERSION)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,3 +1,11 @@
+//! The BLS12-381 engine, from `blstrs` with the `blst` feature or from `paired` with
+//! the `pairing` feature.
+//!
+//! The pairing, including the final exponentiation, is implemented by the engine
+//! crate, and [`Engine::Fqk`] exposes no more than the field operations, so the
+//! arithmetic of the pairing can't be specialized here. `blst` already squares in
+//! the cyclotomic subgroup during the hard part of the final exponentiation.
+
 #[cfg(feature = "blst")]
 pub use blstrs::{
     Bls12, Engine, Fp as Fq, Fp12 as Fq12, Fp2 as Fq2, FpRepr as FqRepr, G1Affine, G1Compressed,
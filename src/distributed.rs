@@ -0,0 +1,542 @@
+//! Groth16 proving with the multiexps spread over several nodes.
+//!
+//! The coordinator synthesizes the circuit and computes the H polynomial, then splits
+//! the auxiliary assignment and the coefficients of H into one contiguous range per
+//! node. Each node receives the bases of its ranges ([`ProvingKeyShard`]) and the
+//! exponents ([`WitnessShard`]), and returns its part of every multiexp
+//! ([`PartialProof`]). The coordinator adds them up together with the multiexps over
+//! the public inputs and assembles the proof.
+//!
+//! How shards travel between the nodes is up to the [`NodeTransport`].
+//! [`LocalTransport`] runs all nodes in process.
+
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::Arc;
+
+use bit_vec::BitVec;
+use ff::{Field, PrimeField, ScalarEngine};
+use groupy::CurveProjective;
+use rand_core::RngCore;
+
+use crate::bls::Engine;
+use crate::domain::{DomainPrecompute, FftScratch};
+use crate::groth16::{
+    assemble_proof, execute_fft, synthesize_circuit, ConditionalSwap, ParameterSource, Proof,
+    ProofParts,
+};
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, DensityTracker, FullDensity, SourceBuilder};
+use crate::{Circuit, SynthesisError};
+
+type Exponents<E> = Arc<Vec<<<E as ScalarEngine>::Fr as PrimeField>::Repr>>;
+
+/// The bases a node needs for its part of the proof.
+#[derive(Clone, Debug)]
+pub struct ProvingKeyShard<E: Engine> {
+    pub h: Arc<Vec<E::G1Affine>>,
+    pub l: Arc<Vec<E::G1Affine>>,
+    /// The bases of the A query for the variables of the shard occurring in A.
+    pub a: Arc<Vec<E::G1Affine>>,
+    /// The bases of the B query in G1 for the variables of the shard occurring in B.
+    pub b_g1: Arc<Vec<E::G1Affine>>,
+    /// The bases of the B query in G2 for the variables of the shard occurring in B.
+    pub b_g2: Arc<Vec<E::G2Affine>>,
+}
+
+/// The exponents a node needs for its part of the proof.
+#[derive(Clone, Debug)]
+pub struct WitnessShard<E: Engine> {
+    /// Coefficients of the H polynomial.
+    pub h: Exponents<E>,
+    /// Auxiliary assignment.
+    pub aux: Exponents<E>,
+    /// Which of the auxiliary variables occur in A.
+    pub a_density: Arc<DensityTracker>,
+    /// Which of the auxiliary variables occur in B.
+    pub b_density: Arc<DensityTracker>,
+}
+
+/// The multiexps of a node.
+#[derive(Clone, Debug)]
+pub struct PartialProof<E: Engine> {
+    pub h: E::G1,
+    pub l: E::G1,
+    pub a: E::G1,
+    pub b_g1: E::G1,
+    pub b_g2: E::G2,
+}
+
+/// Computes the multiexps of a shard, this is what every node runs.
+pub fn prove_shard<E: Engine>(
+    key: &ProvingKeyShard<E>,
+    witness: &WitnessShard<E>,
+) -> Result<PartialProof<E>, SynthesisError> {
+    let worker = Worker::new();
+
+    let h = multiexp(
+        &worker,
+        (key.h.clone(), 0),
+        FullDensity,
+        witness.h.clone(),
+        &mut None,
+    );
+    let l = multiexp(
+        &worker,
+        (key.l.clone(), 0),
+        FullDensity,
+        witness.aux.clone(),
+        &mut None,
+    );
+    let a = multiexp(
+        &worker,
+        (key.a.clone(), 0),
+        witness.a_density.clone(),
+        witness.aux.clone(),
+        &mut None,
+    );
+    let b_g1 = multiexp(
+        &worker,
+        (key.b_g1.clone(), 0),
+        witness.b_density.clone(),
+        witness.aux.clone(),
+        &mut None,
+    );
+    let b_g2 = multiexp(
+        &worker,
+        (key.b_g2.clone(), 0),
+        witness.b_density.clone(),
+        witness.aux.clone(),
+        &mut None,
+    );
+
+    Ok(PartialProof {
+        h: h.wait()?,
+        l: l.wait()?,
+        a: a.wait()?,
+        b_g1: b_g1.wait()?,
+        b_g2: b_g2.wait()?,
+    })
+}
+
+/// Connection of the coordinator to the nodes.
+pub trait NodeTransport<E: Engine> {
+    /// Number of nodes the work is split across.
+    fn num_nodes(&self) -> usize;
+
+    /// Sends `node` the shards it should compute the multiexps of.
+    fn send(
+        &mut self,
+        node: usize,
+        key: ProvingKeyShard<E>,
+        witness: WitnessShard<E>,
+    ) -> Result<(), SynthesisError>;
+
+    /// Waits for the results of all nodes, sent to since the last call, in node order.
+    fn gather(&mut self) -> Result<Vec<PartialProof<E>>, SynthesisError>;
+}
+
+/// Transport running every node in the current process, one after the other.
+pub struct LocalTransport<E: Engine> {
+    pending: Vec<Option<(ProvingKeyShard<E>, WitnessShard<E>)>>,
+}
+
+impl<E: Engine> LocalTransport<E> {
+    pub fn new(num_nodes: usize) -> Self {
+        assert!(num_nodes > 0, "at least one node is needed");
+
+        LocalTransport {
+            pending: vec![None; num_nodes],
+        }
+    }
+}
+
+impl<E: Engine> NodeTransport<E> for LocalTransport<E> {
+    fn num_nodes(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn send(
+        &mut self,
+        node: usize,
+        key: ProvingKeyShard<E>,
+        witness: WitnessShard<E>,
+    ) -> Result<(), SynthesisError> {
+        self.pending[node] = Some((key, witness));
+        Ok(())
+    }
+
+    fn gather(&mut self) -> Result<Vec<PartialProof<E>>, SynthesisError> {
+        // The multiexps of every node already use all threads.
+        self.pending
+            .iter_mut()
+            .map(|pending| {
+                let (key, witness) = pending.take().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotConnected, "no shard was sent to the node")
+                })?;
+                prove_shard(&key, &witness)
+            })
+            .collect()
+    }
+}
+
+/// Prover splitting the multiexps over the nodes of a [`NodeTransport`].
+pub struct DistributedProver<E: Engine, T: NodeTransport<E>> {
+    transport: T,
+    _e: PhantomData<E>,
+}
+
+impl<E: Engine, T: NodeTransport<E>> DistributedProver<E, T> {
+    pub fn new(transport: T) -> Self {
+        DistributedProver {
+            transport,
+            _e: PhantomData,
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn create_random_proof<C, R, P>(
+        &mut self,
+        circuit: C,
+        params: P,
+        rng: &mut R,
+    ) -> Result<Proof<E, P::Tag>, SynthesisError>
+    where
+        C: Circuit<E>,
+        R: RngCore,
+        P: ParameterSource<E>,
+        E::G1: ConditionalSwap,
+        E::G2: ConditionalSwap,
+    {
+        let r = E::Fr::random(rng);
+        let s = E::Fr::random(rng);
+
+        self.create_proof(circuit, params, r, s)
+    }
+
+    /// Creates the same proof as `groth16::create_proof`.
+    pub fn create_proof<C, P>(
+        &mut self,
+        circuit: C,
+        params: P,
+        r: E::Fr,
+        s: E::Fr,
+    ) -> Result<Proof<E, P::Tag>, SynthesisError>
+    where
+        C: Circuit<E>,
+        P: ParameterSource<E>,
+        E::G1: ConditionalSwap,
+        E::G2: ConditionalSwap,
+    {
+        let worker = Worker::new();
+        let mut prover = synthesize_circuit::<E, C>(circuit)?;
+        let vk = params.get_vk(prover.input_assignment.len())?;
+
+        let precompute = DomainPrecompute::new(prover.a.len())?;
+        let h = execute_fft(
+            &worker,
+            &mut prover,
+            &precompute,
+            &mut None,
+            &mut FftScratch::new(),
+        )?;
+        let input = Arc::new(
+            prover
+                .input_assignment
+                .iter()
+                .map(|s| s.into_repr())
+                .collect::<Vec<_>>(),
+        );
+        let aux = prover
+            .aux_assignment
+            .iter()
+            .map(|s| s.into_repr())
+            .collect::<Vec<_>>();
+
+        let a_aux_density_total = prover.a_aux_density.get_total_density();
+        let b_input_density_total = prover.b_input_density.get_total_density();
+        let b_aux_density_total = prover.b_aux_density.get_total_density();
+
+        let (a_inputs_source, a_aux_source) = params.get_a(input.len(), a_aux_density_total)?;
+        let (b_g1_inputs_source, b_g1_aux_source) =
+            params.get_b_g1(b_input_density_total, b_aux_density_total)?;
+        let (b_g2_inputs_source, b_g2_aux_source) =
+            params.get_b_g2(b_input_density_total, b_aux_density_total)?;
+
+        let h_bases = params.get_h(h.len())?.get()?;
+        let l_bases = params.get_l(aux.len())?.get()?;
+        let a_bases = a_aux_source.get()?;
+        let b_g1_bases = b_g1_aux_source.get()?;
+        let b_g2_bases = b_g2_aux_source.get()?;
+
+        let num_nodes = self.transport.num_nodes();
+        let h_ranges = split(h.len(), num_nodes);
+        let aux_ranges = split(aux.len(), num_nodes);
+        for (node, (h_range, aux_range)) in h_ranges.into_iter().zip(aux_ranges).enumerate() {
+            let a_range = dense_range(&prover.a_aux_density, &aux_range);
+            let b_range = dense_range(&prover.b_aux_density, &aux_range);
+
+            let key = ProvingKeyShard {
+                h: shard(&h_bases, &h_range)?,
+                l: shard(&l_bases, &aux_range)?,
+                a: shard(&a_bases, &a_range)?,
+                b_g1: shard(&b_g1_bases, &b_range)?,
+                b_g2: shard(&b_g2_bases, &b_range)?,
+            };
+            let witness = WitnessShard {
+                h: Arc::new(h[h_range].to_vec()),
+                aux: Arc::new(aux[aux_range.clone()].to_vec()),
+                a_density: Arc::new(shard_density(&prover.a_aux_density, &aux_range)),
+                b_density: Arc::new(shard_density(&prover.b_aux_density, &aux_range)),
+            };
+
+            self.transport.send(node, key, witness)?;
+        }
+
+        // There are few public inputs, the coordinator handles them itself.
+        let b_input_density = Arc::new(prover.b_input_density);
+        let a_inputs = multiexp(
+            &worker,
+            a_inputs_source,
+            FullDensity,
+            input.clone(),
+            &mut None,
+        );
+        let b_g1_inputs = multiexp(
+            &worker,
+            b_g1_inputs_source,
+            b_input_density.clone(),
+            input.clone(),
+            &mut None,
+        );
+        let b_g2_inputs = multiexp(
+            &worker,
+            b_g2_inputs_source,
+            b_input_density,
+            input,
+            &mut None,
+        );
+
+        let partials = self.transport.gather()?;
+        if partials.len() != num_nodes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a result from every node",
+            )
+            .into());
+        }
+
+        let mut parts = ProofParts {
+            h: E::G1::zero(),
+            l: E::G1::zero(),
+            a: a_inputs.wait()?,
+            b_g1: b_g1_inputs.wait()?,
+            b_g2: b_g2_inputs.wait()?,
+        };
+        for partial in &partials {
+            parts.h.add_assign(&partial.h);
+            parts.l.add_assign(&partial.l);
+            parts.a.add_assign(&partial.a);
+            parts.b_g1.add_assign(&partial.b_g1);
+            parts.b_g2.add_assign(&partial.b_g2);
+        }
+
+        assemble_proof(vk, &parts, r, s)
+    }
+}
+
+/// Splits `0..len` into `num` contiguous ranges of about the same size.
+fn split(len: usize, num: usize) -> Vec<Range<usize>> {
+    let chunk = (len + num - 1) / num;
+    (0..num)
+        .map(|i| std::cmp::min(i * chunk, len)..std::cmp::min((i + 1) * chunk, len))
+        .collect()
+}
+
+/// The positions among the set bits of `density` of the set bits in `range`.
+fn dense_range(density: &DensityTracker, range: &Range<usize>) -> Range<usize> {
+    let before = density.bv.iter().take(range.start).filter(|b| *b).count();
+    let within = density
+        .bv
+        .iter()
+        .skip(range.start)
+        .take(range.len())
+        .filter(|b| *b)
+        .count();
+
+    before..before + within
+}
+
+fn shard_density(density: &DensityTracker, range: &Range<usize>) -> DensityTracker {
+    let bv = density
+        .bv
+        .iter()
+        .skip(range.start)
+        .take(range.len())
+        .collect::<BitVec>();
+    let total_density = bv.iter().filter(|b| *b).count();
+
+    DensityTracker { bv, total_density }
+}
+
+/// The bases of `range`, after the first `skip`, or an error if the parameters have
+/// too few of them for the circuit.
+fn shard<G: Clone>(
+    (bases, skip): &(Arc<Vec<G>>, usize),
+    range: &Range<usize>,
+) -> Result<Arc<Vec<G>>, SynthesisError> {
+    skip.checked_add(range.end)
+        .and_then(|end| bases.get(skip + range.start..end))
+        .map(|bases| Arc::new(bases.to_vec()))
+        .ok_or(SynthesisError::IncompatibleParameters(
+            "too few bases for the circuit",
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{self, generate_random_parameters, prepare_verifying_key, verify_proof};
+    use crate::ConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Squares `x` repeatedly, exposes the result and constrains the sum of all
+    /// intermediate values, which only occurs in C.
+    #[derive(Clone)]
+    struct SquaringSum {
+        x: Option<Fr>,
+        rounds: usize,
+    }
+
+    impl Circuit<Bls12> for SquaringSum {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut value = self.x;
+            let mut var = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+            let mut vars = vec![var];
+            let mut sum_value = value;
+
+            for i in 0..self.rounds {
+                let next_value = value.map(|mut v| {
+                    v.square();
+                    v
+                });
+                let next = cs.alloc(
+                    || format!("x^2^{}", i + 1),
+                    || next_value.ok_or(SynthesisError::AssignmentMissing),
+                )?;
+                cs.enforce(
+                    || format!("squaring {}", i),
+                    |lc| lc + var,
+                    |lc| lc + var,
+                    |lc| lc + next,
+                );
+
+                sum_value = sum_value.and_then(|mut sum| {
+                    sum.add_assign(&next_value?);
+                    Some(sum)
+                });
+                value = next_value;
+                var = next;
+                vars.push(var);
+            }
+
+            let y = cs.alloc_input(|| "y", || value.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "y", |lc| lc + var, |lc| lc + CS::one(), |lc| lc + y);
+
+            let sum = cs.alloc(
+                || "sum",
+                || sum_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(
+                || "sum",
+                |lc| vars.iter().fold(lc, |lc, v| lc + *v),
+                |lc| lc + CS::one(),
+                |lc| lc + sum,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_distributed_proof_matches_local_proof() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = generate_random_parameters::<Bls12, _, _>(
+            SquaringSum {
+                x: None,
+                rounds: 20,
+            },
+            rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let circuit = SquaringSum {
+            x: Some(Fr::random(rng)),
+            rounds: 20,
+        };
+        let r = Fr::random(rng);
+        let s = Fr::random(rng);
+        let expected = groth16::create_proof(circuit.clone(), &params, r, s).unwrap();
+
+        // More nodes than auxiliary variables leaves some nodes without work.
+        for &num_nodes in &[1, 2, 3, 7, 30] {
+            let mut prover = DistributedProver::new(LocalTransport::new(num_nodes));
+            let proof = prover.create_proof(circuit.clone(), &params, r, s).unwrap();
+            assert!(proof == expected);
+        }
+
+        let mut prover = DistributedProver::new(LocalTransport::new(4));
+        let proof = prover
+            .create_random_proof(circuit.clone(), &params, rng)
+            .unwrap();
+        let mut y = circuit.x.unwrap();
+        for _ in 0..20 {
+            y.square();
+        }
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+    }
+
+    #[test]
+    fn test_distributed_proof_with_short_parameters() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        // Parameters of a smaller circuit have too few bases for the larger one.
+        let params =
+            generate_random_parameters::<Bls12, _, _>(SquaringSum { x: None, rounds: 2 }, rng)
+                .unwrap();
+        let circuit = SquaringSum {
+            x: Some(Fr::random(rng)),
+            rounds: 20,
+        };
+
+        let mut prover = DistributedProver::new(LocalTransport::new(2));
+        match prover.create_random_proof(circuit, &params, rng) {
+            Err(SynthesisError::IncompatibleParameters(_)) => {}
+            _ => panic!("short parameters must be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_local_transport_requires_shards() {
+        let mut transport = LocalTransport::<Bls12>::new(2);
+        assert!(transport.gather().is_err());
+    }
+}
@@ -14,14 +14,23 @@
 use crate::bls::Engine;
 use ff::{Field, PrimeField, ScalarEngine};
 use groupy::CurveProjective;
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
-use super::multicore::Worker;
+use super::multicore::{install_in, Worker, THREAD_POOL};
+use super::multiexp::batch_invert;
 use super::SynthesisError;
 
 use crate::gpu;
 
 use log::{info, warn};
 
+/// A polynomial over a radix-2 domain, given by its coefficients or its evaluations.
+///
+/// The values can be taken from any [`Group`], e.g. [`Point`]s for FFTs in the exponent
+/// as used for Lagrange basis commitments. GPU kernels only work with [`Scalar`]s, the
+/// FFTs of other groups always run on the CPU.
 pub struct EvaluationDomain<E: ScalarEngine, G: Group<E>> {
     coeffs: Vec<G>,
     exp: u32,
@@ -29,6 +38,8 @@ pub struct EvaluationDomain<E: ScalarEngine, G: Group<E>> {
     omegainv: E::Fr,
     geninv: E::Fr,
     minv: E::Fr,
+    zinv: E::Fr,
+    twiddles: Arc<DomainTwiddles<E>>,
 }
 
 impl<E: ScalarEngine, G: Group<E>> AsRef<[G]> for EvaluationDomain<E, G> {
@@ -43,24 +54,116 @@ impl<E: ScalarEngine, G: Group<E>> AsMut<[G]> for EvaluationDomain<E, G> {
     }
 }
 
-impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
-    pub fn into_coeffs(self) -> Vec<G> {
-        self.coeffs
+/// The values an [`EvaluationDomain`] needs which only depend on its size.
+///
+/// Computing them takes a few field inversions and exponentiations, so when many
+/// domains of the same size are created, e.g. when proving a batch of circuits, they
+/// should be computed once and passed to [`EvaluationDomain::from_coeffs_with_precompute`].
+pub struct DomainPrecompute<E: ScalarEngine> {
+    exp: u32,
+    omega: E::Fr,
+    omegainv: E::Fr,
+    geninv: E::Fr,
+    minv: E::Fr,
+    zinv: E::Fr,
+    twiddles: Arc<DomainTwiddles<E>>,
+}
+
+/// The twiddle factors of the FFTs and of the inverse FFTs over a domain.
+struct DomainTwiddles<E: ScalarEngine> {
+    forward: Twiddles<E>,
+    inverse: Twiddles<E>,
+}
+
+/// The twiddle factors of the FFTs over a domain of size `n` with the generator
+/// `omega`, its first `n / 2` powers.
+///
+/// Only FFTs on the CPU use them, so they are computed by the first one and kept for
+/// the next ones, while domains transformed on the GPU never hold them. The FFTs of a
+/// sub-domain of size `n / 2^k` use every `2^k`-th factor.
+struct Twiddles<E: ScalarEngine> {
+    omega: E::Fr,
+    len: usize,
+    powers: Mutex<Option<Arc<Vec<E::Fr>>>>,
+}
+
+impl<E: ScalarEngine> Twiddles<E> {
+    fn new(omega: E::Fr, exp: u32) -> Self {
+        Twiddles {
+            omega,
+            len: (1 << exp) / 2,
+            powers: Mutex::new(None),
+        }
+    }
+
+    /// The factors, computed on the first call.
+    fn get(&self) -> Arc<Vec<E::Fr>> {
+        self.powers
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(powers::<E>(&self.omega, self.len)))
+            .clone()
+    }
+}
+
+/// The first `len` powers of `g`, starting at one.
+fn powers<E: ScalarEngine>(g: &E::Fr, len: usize) -> Vec<E::Fr> {
+    let mut powers = vec![E::Fr::zero(); len];
+    install_in(&THREAD_POOL, || {
+        let chunk = std::cmp::max(len / rayon::current_num_threads(), 1024);
+        powers
+            .par_chunks_mut(chunk)
+            .enumerate()
+            .for_each(|(i, powers)| {
+                let mut power = g.pow(&[(i * chunk) as u64]);
+                for p in powers {
+                    *p = power;
+                    power.mul_assign(g);
+                }
+            });
+    });
+
+    powers
+}
+
+/// The largest domain supported by the scalar field of `E`.
+fn max_domain_size<E: ScalarEngine>() -> usize {
+    1 << (E::Fr::S - 1)
+}
+
+impl<E: ScalarEngine> Clone for DomainPrecompute<E> {
+    fn clone(&self) -> Self {
+        DomainPrecompute {
+            exp: self.exp,
+            omega: self.omega,
+            omegainv: self.omegainv,
+            geninv: self.geninv,
+            minv: self.minv,
+            zinv: self.zinv,
+            twiddles: self.twiddles.clone(),
+        }
     }
+}
+
+impl<E: ScalarEngine> DomainPrecompute<E> {
+    /// Precomputes the smallest domain holding `num_coeffs` coefficients.
+    pub fn new(num_coeffs: usize) -> Result<Self, SynthesisError> {
+        // The pairing-friendly curve may not be able to support
+        // large enough (radix2) evaluation domains.
+        let max = max_domain_size::<E>();
+        if num_coeffs > max {
+            return Err(SynthesisError::DomainTooLarge {
+                size: num_coeffs,
+                max,
+            });
+        }
 
-    pub fn from_coeffs(mut coeffs: Vec<G>) -> Result<EvaluationDomain<E, G>, SynthesisError> {
         // Compute the size of our evaluation domain
         let mut m = 1;
         let mut exp = 0;
-        while m < coeffs.len() {
+        while m < num_coeffs {
             m *= 2;
             exp += 1;
-
-            // The pairing-friendly curve may not be able to support
-            // large enough (radix2) evaluation domains.
-            if exp >= E::Fr::S {
-                return Err(SynthesisError::PolynomialDegreeTooLarge);
-            }
         }
         // Compute omega, the 2^exp primitive root of unity
         let mut omega = E::Fr::root_of_unity();
@@ -68,85 +171,364 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
             omega.square();
         }
 
-        // Extend the coeffs vector with zeroes if necessary
-        coeffs.resize(m, G::group_zero());
+        // z(g) = g^m - 1, for dividing by the target polynomial on the coset
+        let mut z = E::Fr::multiplicative_generator().pow(&[m as u64]);
+        z.sub_assign(&E::Fr::one());
 
-        Ok(EvaluationDomain {
-            coeffs,
+        let omegainv = omega.inverse().unwrap();
+
+        Ok(DomainPrecompute {
             exp,
             omega,
-            omegainv: omega.inverse().unwrap(),
+            omegainv,
+            twiddles: Arc::new(DomainTwiddles {
+                forward: Twiddles::new(omega, exp),
+                inverse: Twiddles::new(omegainv, exp),
+            }),
             geninv: E::Fr::multiplicative_generator().inverse().unwrap(),
             minv: E::Fr::from_str(&format!("{}", m))
                 .unwrap()
                 .inverse()
                 .unwrap(),
+            zinv: z.inverse().unwrap(),
+        })
+    }
+
+    /// Number of coefficients of the domain.
+    pub fn size(&self) -> usize {
+        1 << self.exp
+    }
+}
+
+/// The temporary buffers of FFTs on the CPU, which are kept between FFTs so they are
+/// only allocated once.
+///
+/// The same scratch can be used for FFTs of any size, the buffers grow to the largest
+/// one. FFTs which are not passed a scratch allocate their buffers on every call.
+pub struct FftScratch<G> {
+    sub_ffts: Vec<Vec<G>>,
+}
+
+impl<G> Default for FftScratch<G> {
+    fn default() -> Self {
+        FftScratch {
+            sub_ffts: Vec::new(),
+        }
+    }
+}
+
+impl<G> FftScratch<G> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `num` zeroed buffers of `len` elements for the sub-FFTs of a parallel FFT.
+    fn sub_ffts<E: ScalarEngine>(&mut self, num: usize, len: usize) -> &mut [Vec<G>]
+    where
+        G: Group<E>,
+    {
+        if self.sub_ffts.len() < num {
+            self.sub_ffts.resize_with(num, Vec::new);
+        }
+        let sub_ffts = &mut self.sub_ffts[..num];
+        for sub_fft in sub_ffts.iter_mut() {
+            sub_fft.clear();
+            sub_fft.resize(len, G::group_zero());
+        }
+
+        sub_ffts
+    }
+}
+
+impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
+    /// The largest number of coefficients a domain can hold. Circuits with more
+    /// constraints (including one per public input) cannot be proven.
+    pub fn max_size() -> usize {
+        max_domain_size::<E>()
+    }
+
+    pub fn into_coeffs(self) -> Vec<G> {
+        self.coeffs
+    }
+
+    /// The generator of the domain, whose powers are the evaluation points.
+    pub fn omega(&self) -> E::Fr {
+        self.omega
+    }
+
+    pub fn from_coeffs(coeffs: Vec<G>) -> Result<EvaluationDomain<E, G>, SynthesisError> {
+        let precompute = DomainPrecompute::new(coeffs.len())?;
+        Self::from_coeffs_with_precompute(coeffs, &precompute)
+    }
+
+    /// Like [`EvaluationDomain::from_coeffs`], padding the coefficients in place to
+    /// `size`, which must be a power of two not smaller than the number of coefficients.
+    pub fn from_coeffs_into_sized(
+        coeffs: Vec<G>,
+        size: usize,
+    ) -> Result<EvaluationDomain<E, G>, SynthesisError> {
+        if !size.is_power_of_two() {
+            return Err(SynthesisError::InvalidDomainSize(size));
+        }
+
+        let precompute = DomainPrecompute::new(size)?;
+        Self::from_coeffs_with_precompute(coeffs, &precompute)
+    }
+
+    /// Replaces the coefficients with `coeffs`, padded with zeroes to the size of the
+    /// domain, reusing the memory of the current ones.
+    pub fn reuse(&mut self, coeffs: &[G]) -> Result<(), SynthesisError> {
+        let size = self.coeffs.len();
+        if coeffs.len() > size {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        self.coeffs.clear();
+        self.coeffs.extend_from_slice(coeffs);
+        self.coeffs.resize(size, G::group_zero());
+
+        Ok(())
+    }
+
+    /// Like [`EvaluationDomain::from_coeffs`], taking the size dependent values from
+    /// `precompute`. The coefficients are padded with zeroes to the size of the
+    /// precomputed domain, which must be able to hold them.
+    pub fn from_coeffs_with_precompute(
+        mut coeffs: Vec<G>,
+        precompute: &DomainPrecompute<E>,
+    ) -> Result<EvaluationDomain<E, G>, SynthesisError> {
+        if coeffs.len() > precompute.size() {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        // Extend the coeffs vector with zeroes if necessary
+        coeffs.resize(precompute.size(), G::group_zero());
+
+        Ok(EvaluationDomain {
+            coeffs,
+            exp: precompute.exp,
+            omega: precompute.omega,
+            omegainv: precompute.omegainv,
+            geninv: precompute.geninv,
+            minv: precompute.minv,
+            zinv: precompute.zinv,
+            twiddles: precompute.twiddles.clone(),
         })
     }
 
+    /// Turns coefficients into the evaluations at the powers of [`omega`].
+    ///
+    /// [`omega`]: EvaluationDomain::omega
     pub fn fft(
         &mut self,
         worker: &Worker,
         kern: &mut Option<gpu::LockedFFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
-        best_fft(kern, &mut self.coeffs, worker, &self.omega, self.exp)?;
+        self.fft_scratch(worker, kern, None)
+    }
+
+    /// Like [`fft`], keeping the temporary buffers of the FFT in `scratch`.
+    ///
+    /// [`fft`]: EvaluationDomain::fft
+    pub fn fft_with_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: &mut FftScratch<G>,
+    ) -> gpu::GPUResult<()> {
+        self.fft_scratch(worker, kern, Some(scratch))
+    }
+
+    fn fft_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: Option<&mut FftScratch<G>>,
+    ) -> gpu::GPUResult<()> {
+        best_fft(
+            kern,
+            &mut self.coeffs,
+            worker,
+            &self.omega,
+            &self.twiddles.forward,
+            self.exp,
+            scratch,
+        )?;
         Ok(())
     }
 
+    /// Turns evaluations at the powers of [`omega`] into coefficients, the inverse of
+    /// [`fft`].
+    ///
+    /// [`omega`]: EvaluationDomain::omega
+    /// [`fft`]: EvaluationDomain::fft
     pub fn ifft(
         &mut self,
         worker: &Worker,
         kern: &mut Option<gpu::LockedFFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
-        best_fft(kern, &mut self.coeffs, worker, &self.omegainv, self.exp)?;
+        self.ifft_scratch(worker, kern, None)
+    }
 
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            let minv = self.minv;
+    /// Like [`ifft`], keeping the temporary buffers of the FFT in `scratch`.
+    ///
+    /// [`ifft`]: EvaluationDomain::ifft
+    pub fn ifft_with_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: &mut FftScratch<G>,
+    ) -> gpu::GPUResult<()> {
+        self.ifft_scratch(worker, kern, Some(scratch))
+    }
 
-            for v in self.coeffs.chunks_mut(chunk) {
-                scope.spawn(move |_| {
-                    for v in v {
-                        v.group_mul_assign(&minv);
-                    }
-                });
+    fn ifft_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: Option<&mut FftScratch<G>>,
+    ) -> gpu::GPUResult<()> {
+        best_fft(
+            kern,
+            &mut self.coeffs,
+            worker,
+            &self.omegainv,
+            &self.twiddles.inverse,
+            self.exp,
+            scratch,
+        )?;
+
+        let minv = self.minv;
+        worker.scope_chunks_mut(&mut self.coeffs, |_, v| {
+            for v in v {
+                v.group_mul_assign(&minv);
             }
         });
 
         Ok(())
     }
 
+    /// Multiplies the `i`-th coefficient by `g^i`, which turns `p(x)` into `p(g * x)`.
     pub fn distribute_powers(&mut self, worker: &Worker, g: E::Fr) {
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            for (i, v) in self.coeffs.chunks_mut(chunk).enumerate() {
-                scope.spawn(move |_| {
-                    let mut u = g.pow(&[(i * chunk) as u64]);
-                    for v in v.iter_mut() {
-                        v.group_mul_assign(&u);
-                        u.mul_assign(&g);
-                    }
-                });
+        self.distribute_scaled_powers(worker, g, E::Fr::one());
+    }
+
+    /// Multiplies the `i`-th coefficient by `scale * g^i`.
+    fn distribute_scaled_powers(&mut self, worker: &Worker, g: E::Fr, scale: E::Fr) {
+        worker.scope_chunks_mut(&mut self.coeffs, |offset, v| {
+            // Every chunk computes its first power on its own, so there is no serial
+            // pass over the coefficients.
+            let mut u = g.pow(&[offset as u64]);
+            u.mul_assign(&scale);
+            for v in v.iter_mut() {
+                v.group_mul_assign(&u);
+                u.mul_assign(&g);
             }
         });
     }
 
+    /// Like [`fft`], evaluating at the coset of the domain shifted by the multiplicative
+    /// generator of the field.
+    ///
+    /// [`fft`]: EvaluationDomain::fft
     pub fn coset_fft(
         &mut self,
         worker: &Worker,
         kern: &mut Option<gpu::LockedFFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
         self.distribute_powers(worker, E::Fr::multiplicative_generator());
-        self.fft(worker, kern)?;
-        Ok(())
+        self.fft(worker, kern)
+    }
+
+    /// Like [`coset_fft`], keeping the temporary buffers of the FFT in `scratch`.
+    ///
+    /// [`coset_fft`]: EvaluationDomain::coset_fft
+    pub fn coset_fft_with_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: &mut FftScratch<G>,
+    ) -> gpu::GPUResult<()> {
+        self.distribute_powers(worker, E::Fr::multiplicative_generator());
+        self.fft_with_scratch(worker, kern, scratch)
     }
 
+    /// The inverse of [`coset_fft`].
+    ///
+    /// [`coset_fft`]: EvaluationDomain::coset_fft
     pub fn icoset_fft(
         &mut self,
         worker: &Worker,
         kern: &mut Option<gpu::LockedFFTKernel<E>>,
     ) -> gpu::GPUResult<()> {
+        let minv = self.minv;
+        self.scaled_icoset_fft(worker, kern, None, minv)
+    }
+
+    /// Like [`icoset_fft`], keeping the temporary buffers of the FFT in `scratch`.
+    ///
+    /// [`icoset_fft`]: EvaluationDomain::icoset_fft
+    pub fn icoset_fft_with_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: &mut FftScratch<G>,
+    ) -> gpu::GPUResult<()> {
+        let minv = self.minv;
+        self.scaled_icoset_fft(worker, kern, Some(scratch), minv)
+    }
+
+    /// [`divide_by_z_on_coset`] followed by [`icoset_fft`]. The division is folded
+    /// into the scaling after the inverse FFT, so there is a single pass over the
+    /// coefficients besides the FFT itself.
+    ///
+    /// [`divide_by_z_on_coset`]: EvaluationDomain::divide_by_z_on_coset
+    /// [`icoset_fft`]: EvaluationDomain::icoset_fft
+    pub fn icoset_fft_div_z(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+    ) -> gpu::GPUResult<()> {
+        let mut scale = self.minv;
+        scale.mul_assign(&self.zinv);
+        self.scaled_icoset_fft(worker, kern, None, scale)
+    }
+
+    /// Like [`icoset_fft_div_z`], keeping the temporary buffers of the FFT in
+    /// `scratch`.
+    ///
+    /// [`icoset_fft_div_z`]: EvaluationDomain::icoset_fft_div_z
+    pub fn icoset_fft_div_z_with_scratch(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: &mut FftScratch<G>,
+    ) -> gpu::GPUResult<()> {
+        let mut scale = self.minv;
+        scale.mul_assign(&self.zinv);
+        self.scaled_icoset_fft(worker, kern, Some(scratch), scale)
+    }
+
+    /// The inverse FFT on the coset, scaling the result by `scale` instead of `1/m` in
+    /// the same pass as the coset shift.
+    fn scaled_icoset_fft(
+        &mut self,
+        worker: &Worker,
+        kern: &mut Option<gpu::LockedFFTKernel<E>>,
+        scratch: Option<&mut FftScratch<G>>,
+        scale: E::Fr,
+    ) -> gpu::GPUResult<()> {
+        best_fft(
+            kern,
+            &mut self.coeffs,
+            worker,
+            &self.omegainv,
+            &self.twiddles.inverse,
+            self.exp,
+            scratch,
+        )?;
         let geninv = self.geninv;
-        self.ifft(worker, kern)?;
-        self.distribute_powers(worker, geninv);
+        self.distribute_scaled_powers(worker, geninv, scale);
         Ok(())
     }
 
@@ -163,18 +545,11 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
     /// evaluation domain, so we must perform division over
     /// a coset.
     pub fn divide_by_z_on_coset(&mut self, worker: &Worker) {
-        let i = self
-            .z(&E::Fr::multiplicative_generator())
-            .inverse()
-            .unwrap();
+        let i = self.zinv;
 
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            for v in self.coeffs.chunks_mut(chunk) {
-                scope.spawn(move |_| {
-                    for v in v {
-                        v.group_mul_assign(&i);
-                    }
-                });
+        worker.scope_chunks_mut(&mut self.coeffs, |_, v| {
+            for v in v {
+                v.group_mul_assign(&i);
             }
         });
     }
@@ -183,17 +558,9 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
     pub fn mul_assign(&mut self, worker: &Worker, other: &EvaluationDomain<E, Scalar<E>>) {
         assert_eq!(self.coeffs.len(), other.coeffs.len());
 
-        worker.scope(self.coeffs.len(), |scope, chunk| {
-            for (a, b) in self
-                .coeffs
-                .chunks_mut(chunk)
-                .zip(other.coeffs.chunks(chunk))
-            {
-                scope.spawn(move |_| {
-                    for (a, b) in a.iter_mut().zip(b.iter()) {
-                        a.group_mul_assign(&b.0);
-                    }
-                });
+        worker.scope_chunks_mut(&mut self.coeffs, |offset, a| {
+            for (a, b) in a.iter_mut().zip(&other.coeffs[offset..]) {
+                a.group_mul_assign(&b.0);
             }
         });
     }
@@ -202,31 +569,150 @@ impl<E: Engine, G: Group<E>> EvaluationDomain<E, G> {
     pub fn sub_assign(&mut self, worker: &Worker, other: &EvaluationDomain<E, G>) {
         assert_eq!(self.coeffs.len(), other.coeffs.len());
 
+        worker.scope_chunks_mut(&mut self.coeffs, |offset, a| {
+            for (a, b) in a.iter_mut().zip(&other.coeffs[offset..]) {
+                a.group_sub_assign(&b);
+            }
+        });
+    }
+}
+
+impl<E: Engine> EvaluationDomain<E, Scalar<E>> {
+    /// Evaluates at `x` the polynomial whose evaluations at the powers of [`omega`] are
+    /// the values of the domain, in O(n) without an inverse FFT.
+    ///
+    /// This is the barycentric formula `p(x) = (x^n - 1) / n * sum_i p(w^i) w^i / (x - w^i)`.
+    ///
+    /// [`omega`]: EvaluationDomain::omega
+    pub fn evaluate_at(&self, worker: &Worker, x: E::Fr) -> E::Fr {
+        let z = self.z(&x);
+        if z.is_zero() {
+            // `x` is one of the evaluation points.
+            let mut omega_i = E::Fr::one();
+            for v in &self.coeffs {
+                if omega_i == x {
+                    return v.0;
+                }
+                omega_i.mul_assign(&self.omega);
+            }
+            unreachable!("the roots of x^n - 1 are the powers of omega");
+        }
+
+        let sum = Mutex::new(E::Fr::zero());
         worker.scope(self.coeffs.len(), |scope, chunk| {
-            for (a, b) in self
-                .coeffs
-                .chunks_mut(chunk)
-                .zip(other.coeffs.chunks(chunk))
-            {
+            for (i, v) in self.coeffs.chunks(chunk).enumerate() {
+                let sum = &sum;
+                let omega = self.omega;
                 scope.spawn(move |_| {
-                    for (a, b) in a.iter_mut().zip(b.iter()) {
-                        a.group_sub_assign(&b);
+                    let first = omega.pow(&[(i * chunk) as u64]);
+
+                    let mut omega_j = first;
+                    let mut denominators = Vec::with_capacity(v.len());
+                    for _ in v {
+                        let mut d = x;
+                        d.sub_assign(&omega_j);
+                        denominators.push(d);
+                        omega_j.mul_assign(&omega);
+                    }
+                    batch_invert(&mut denominators, &mut Vec::with_capacity(v.len()));
+
+                    let mut omega_j = first;
+                    let mut chunk_sum = E::Fr::zero();
+                    for (v, d) in v.iter().zip(denominators.iter()) {
+                        let mut term = v.0;
+                        term.mul_assign(&omega_j);
+                        term.mul_assign(d);
+                        chunk_sum.add_assign(&term);
+                        omega_j.mul_assign(&omega);
                     }
+
+                    sum.lock().unwrap().add_assign(&chunk_sum);
                 });
             }
         });
+
+        let mut result = sum.into_inner().unwrap();
+        result.mul_assign(&z);
+        result.mul_assign(&self.minv);
+        result
+    }
+
+    /// Multiplies the polynomials given by the coefficients of both domains.
+    ///
+    /// The product is computed by FFTs over the smallest domain holding all its
+    /// coefficients, on the GPU if `fft_kern` is given, and is returned as
+    /// coefficients padded with zeroes to the size of that domain. The pointwise
+    /// multiplication runs on the CPU. Returns [`SynthesisError::DomainTooLarge`] if
+    /// the degree of the product exceeds the largest domain of the field.
+    pub fn multiply(
+        self,
+        other: Self,
+        worker: &Worker,
+        fft_kern: &mut Option<gpu::LockedFFTKernel<E>>,
+    ) -> Result<Self, SynthesisError> {
+        let mut a = self.coeffs;
+        let mut b = other.coeffs;
+        a.truncate(num_coeffs(&a));
+        b.truncate(num_coeffs(&b));
+        if a.is_empty() || b.is_empty() {
+            return Self::from_coeffs(vec![Scalar::group_zero()]);
+        }
+
+        let precompute = product_precompute::<E>(a.len(), b.len())?;
+        let mut a = Self::from_coeffs_with_precompute(a, &precompute)?;
+        let mut b = Self::from_coeffs_with_precompute(b, &precompute)?;
+
+        a.fft(worker, fft_kern)?;
+        b.fft(worker, fft_kern)?;
+        a.mul_assign(worker, &b);
+        a.ifft(worker, fft_kern)?;
+
+        Ok(a)
     }
 }
 
+/// The number of coefficients up to the last non-zero one.
+fn num_coeffs<E: ScalarEngine>(coeffs: &[Scalar<E>]) -> usize {
+    coeffs
+        .iter()
+        .rposition(|c| !c.0.is_zero())
+        .map_or(0, |i| i + 1)
+}
+
+/// The domain of the product of polynomials with `len_a` and `len_b` coefficients.
+fn product_precompute<E: ScalarEngine>(
+    len_a: usize,
+    len_b: usize,
+) -> Result<DomainPrecompute<E>, SynthesisError> {
+    DomainPrecompute::new(len_a + len_b - 1)
+}
+
+/// The values of an [`EvaluationDomain`], a group with the scalar field of `E` acting
+/// on it.
+///
+/// Implemented by [`Scalar`] for the field itself and by [`Point`] for the curve groups.
 pub trait Group<E: ScalarEngine>: Sized + Copy + Clone + Send + Sync {
     fn group_zero() -> Self;
     fn group_mul_assign(&mut self, by: &E::Fr);
     fn group_add_assign(&mut self, other: &Self);
     fn group_sub_assign(&mut self, other: &Self);
+
+    /// Views the elements as field elements, if the group is the scalar field. Only
+    /// those can be transformed on the GPU.
+    fn as_scalars(_elements: &mut [Self]) -> Option<&mut [E::Fr]> {
+        None
+    }
 }
 
+/// Points of a curve, e.g. `E::G1` or `E::G2`.
 pub struct Point<G: CurveProjective>(pub G);
 
+impl<G: CurveProjective> fmt::Debug for Point<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Point").field(&self.0).finish()
+    }
+}
+
 impl<G: CurveProjective> PartialEq for Point<G> {
     fn eq(&self, other: &Point<G>) -> bool {
         self.0 == other.0
@@ -256,8 +742,16 @@ impl<G: CurveProjective> Group<G::Engine> for Point<G> {
     }
 }
 
+/// Elements of the scalar field `E::Fr`.
+#[repr(transparent)]
 pub struct Scalar<E: ScalarEngine>(pub E::Fr);
 
+impl<E: ScalarEngine> fmt::Debug for Scalar<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Scalar").field(&self.0).finish()
+    }
+}
+
 impl<E: ScalarEngine> PartialEq for Scalar<E> {
     fn eq(&self, other: &Scalar<E>) -> bool {
         self.0 == other.0
@@ -272,6 +766,14 @@ impl<E: ScalarEngine> Clone for Scalar<E> {
     }
 }
 
+impl<E: ScalarEngine> Scalar<E> {
+    /// Views field elements as `Scalar`s.
+    fn from_scalars(elements: &mut [E::Fr]) -> &mut [Self] {
+        // `Scalar` is a transparent wrapper of `E::Fr`.
+        unsafe { &mut *(elements as *mut [E::Fr] as *mut [Self]) }
+    }
+}
+
 impl<E: ScalarEngine> Group<E> for Scalar<E> {
     fn group_zero() -> Self {
         Scalar(E::Fr::zero())
@@ -285,29 +787,45 @@ impl<E: ScalarEngine> Group<E> for Scalar<E> {
     fn group_sub_assign(&mut self, other: &Self) {
         self.0.sub_assign(&other.0);
     }
+
+    fn as_scalars(elements: &mut [Self]) -> Option<&mut [E::Fr]> {
+        // `Scalar` is a transparent wrapper of `E::Fr`.
+        Some(unsafe { &mut *(elements as *mut [Self] as *mut [E::Fr]) })
+    }
 }
 
+/// FFT of `a` with `omega`, whose first `a.len() / 2` powers are `twiddles`.
 fn best_fft<E: Engine, T: Group<E>>(
     kern: &mut Option<gpu::LockedFFTKernel<E>>,
     a: &mut [T],
     worker: &Worker,
     omega: &E::Fr,
+    twiddles: &Twiddles<E>,
     log_n: u32,
+    scratch: Option<&mut FftScratch<T>>,
 ) -> gpu::GPUResult<()> {
     if let Some(ref mut kern) = kern {
-        if kern
-            .with(|k: &mut gpu::FFTKernel<E>| gpu_fft(k, a, omega, log_n))
-            .is_ok()
+        if T::as_scalars(a).is_some()
+            && kern
+                .with(|k: &mut gpu::FFTKernel<E>| gpu_fft(k, a, omega, log_n))
+                .is_ok()
         {
             return Ok(());
         }
     }
 
-    let log_cpus = worker.log_num_cpus();
-    if log_n <= log_cpus {
-        serial_fft(a, omega, log_n);
-    } else {
-        parallel_fft(a, worker, omega, log_n, log_cpus);
+    let twiddles = twiddles.get();
+    if worker.fft_config().four_step(log_n) {
+        four_step_fft(a, worker, &twiddles, log_n);
+        return Ok(());
+    }
+
+    match worker
+        .fft_config()
+        .parallel_log_threads(log_n, worker.log_num_cpus())
+    {
+        Some(log_threads) => parallel_fft(a, worker, omega, &twiddles, log_n, log_threads, scratch),
+        None => serial_fft_radix4_twiddles(a, TwiddleSource::Table(&twiddles, 1), log_n),
     }
 
     Ok(())
@@ -319,19 +837,113 @@ pub fn gpu_fft<E: Engine, T: Group<E>>(
     omega: &E::Fr,
     log_n: u32,
 ) -> gpu::GPUResult<()> {
-    // EvaluationDomain module is supposed to work only with E::Fr elements, and not CurveProjective
-    // points. The Bellman authors have implemented an unnecessarry abstraction called Group<E>
-    // which is implemented for both PrimeField and CurveProjective elements. As nowhere in the code
-    // is the CurveProjective version used, T and E::Fr are guaranteed to be equal and thus have same
-    // size.
-    // For compatibility/performance reasons we decided to transmute the array to the desired type
-    // as it seems safe and needs less modifications in the current structure of Bellman library.
-    let a = unsafe { std::mem::transmute::<&mut [T], &mut [E::Fr]>(a) };
+    // The kernel only works with field elements, curve points are left to the CPU.
+    let a = T::as_scalars(a).ok_or(gpu::GPUError::Simple(
+        "GPU FFT is only supported for scalars",
+    ))?;
     kern.radix_fft(a, omega, log_n)?;
     Ok(())
 }
 
-pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_n: u32) {
+/// Transforms the `2^log_n` coefficients `a` into their evaluations at the powers of
+/// `omega`, in place.
+///
+/// This is the transform [`EvaluationDomain::fft`] runs, for buffers managed by the
+/// caller. It runs on the GPU if `kern` is given, like the domain, and falls back to
+/// the CPU if the kernel fails.
+///
+/// Returns an error if `a` does not hold exactly `2^log_n` values, if the field has no
+/// domain of that size, or if `omega` is not a primitive `2^log_n`-th root of unity.
+pub fn fft_in_place<E: Engine>(
+    worker: &Worker,
+    a: &mut [E::Fr],
+    omega: &E::Fr,
+    log_n: u32,
+    kern: &mut Option<gpu::LockedFFTKernel<E>>,
+) -> Result<(), SynthesisError> {
+    check_fft_input::<E>(a.len(), omega, log_n)?;
+
+    // Only computed if the FFT runs on the CPU.
+    let twiddles = Twiddles::new(*omega, log_n);
+    best_fft(
+        kern,
+        Scalar::<E>::from_scalars(a),
+        worker,
+        omega,
+        &twiddles,
+        log_n,
+        None,
+    )?;
+    Ok(())
+}
+
+/// Transforms the evaluations `a` at the powers of `omega` back into the `2^log_n`
+/// coefficients, in place, the inverse of [`fft_in_place`].
+///
+/// `omega` is the root of unity of the forward transform. The preconditions and errors
+/// are those of [`fft_in_place`].
+pub fn ifft_in_place<E: Engine>(
+    worker: &Worker,
+    a: &mut [E::Fr],
+    omega: &E::Fr,
+    log_n: u32,
+    kern: &mut Option<gpu::LockedFFTKernel<E>>,
+) -> Result<(), SynthesisError> {
+    check_fft_input::<E>(a.len(), omega, log_n)?;
+
+    // A primitive root of unity is never zero, so both inverses exist.
+    let omegainv = omega.inverse().unwrap();
+    let minv = E::Fr::from_str(&format!("{}", a.len()))
+        .unwrap()
+        .inverse()
+        .unwrap();
+
+    fft_in_place(worker, a, &omegainv, log_n, kern)?;
+
+    worker.scope_chunks_mut(a, |_, a| {
+        for v in a {
+            v.mul_assign(&minv);
+        }
+    });
+
+    Ok(())
+}
+
+/// Checks the preconditions of [`fft_in_place`].
+fn check_fft_input<E: ScalarEngine>(
+    len: usize,
+    omega: &E::Fr,
+    log_n: u32,
+) -> Result<(), SynthesisError> {
+    if !len.is_power_of_two() {
+        return Err(SynthesisError::InvalidDomainSize(len));
+    }
+    if len.trailing_zeros() != log_n {
+        return Err(SynthesisError::FftSizeMismatch { len, log_n });
+    }
+    let max = max_domain_size::<E>();
+    if len > max {
+        return Err(SynthesisError::DomainTooLarge { size: len, max });
+    }
+
+    // `omega` is a primitive `2^log_n`-th root of unity iff `omega^(2^(log_n - 1))` is
+    // minus one, for `log_n > 0`.
+    let mut expected = E::Fr::one();
+    let mut power = *omega;
+    if log_n > 0 {
+        expected.negate();
+        for _ in 1..log_n {
+            power.square();
+        }
+    }
+    if power != expected {
+        return Err(SynthesisError::InvalidRootOfUnity(log_n));
+    }
+
+    Ok(())
+}
+
+fn bitreverse_permutation<T>(a: &mut [T], log_n: u32) {
     fn bitreverse(mut n: u32, l: u32) -> u32 {
         let mut r = 0;
         for _ in 0..l {
@@ -342,14 +954,20 @@ pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_
     }
 
     let n = a.len() as u32;
-    assert_eq!(n, 1 << log_n);
-
     for k in 0..n {
         let rk = bitreverse(k, log_n);
         if k < rk {
             a.swap(rk as usize, k as usize);
         }
     }
+}
+
+/// Radix-2 FFT, kept as the reference for [`serial_fft_radix4`].
+pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_n: u32) {
+    let n = a.len() as u32;
+    assert_eq!(n, 1 << log_n);
+
+    bitreverse_permutation(a, log_n);
 
     let mut m = 1;
     for _ in 0..log_n {
@@ -375,22 +993,163 @@ pub fn serial_fft<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_
     }
 }
 
-fn parallel_fft<E: ScalarEngine, T: Group<E>>(
-    a: &mut [T],
-    worker: &Worker,
-    omega: &E::Fr,
-    log_n: u32,
-    log_cpus: u32,
-) {
-    assert!(log_n >= log_cpus);
-
-    let num_cpus = 1 << log_cpus;
-    let log_new_n = log_n - log_cpus;
-    let mut tmp = vec![vec![T::group_zero(); 1 << log_new_n]; num_cpus];
-    let new_omega = omega.pow(&[num_cpus as u64]);
+/// FFT merging two radix-2 passes into one radix-4 pass, which halves the number of
+/// passes over `a`. Odd sizes finish with a single radix-2 pass.
+///
+/// The powers of `omega` are computed along the way, so nothing is allocated.
+pub fn serial_fft_radix4<E: ScalarEngine, T: Group<E>>(a: &mut [T], omega: &E::Fr, log_n: u32) {
+    serial_fft_radix4_twiddles(a, TwiddleSource::Root(*omega), log_n);
+}
 
-    worker.scope(0, |scope, _| {
-        let a = &*a;
+/// Where an FFT takes the powers of its root of unity from.
+#[derive(Clone, Copy)]
+enum TwiddleSource<'a, F> {
+    /// A table in which the `k`-th power is at `k * stride`.
+    Table(&'a [F], usize),
+    /// The root of unity, whose powers are computed as they are needed.
+    Root(F),
+}
+
+impl<'a, F: Field> TwiddleSource<'a, F> {
+    /// The `k`-th power of the root of unity.
+    fn power(self, k: usize) -> F {
+        match self {
+            TwiddleSource::Table(twiddles, stride) => twiddles[k * stride],
+            TwiddleSource::Root(root) => root.pow(&[k as u64]),
+        }
+    }
+
+    /// The powers `0, step, 2 * step, ...` of the root of unity.
+    fn with_step(self, step: usize) -> TwiddleSource<'a, F> {
+        match self {
+            TwiddleSource::Table(twiddles, stride) => TwiddleSource::Table(twiddles, step * stride),
+            TwiddleSource::Root(root) => TwiddleSource::Root(root.pow(&[step as u64])),
+        }
+    }
+
+    /// The powers of the root of unity in order, starting at one.
+    fn iter(self) -> impl Iterator<Item = F> + 'a {
+        (0..).scan(F::one(), move |power, k: usize| {
+            Some(match self {
+                TwiddleSource::Table(twiddles, stride) => twiddles[k * stride],
+                TwiddleSource::Root(root) => {
+                    let current = *power;
+                    power.mul_assign(&root);
+                    current
+                }
+            })
+        })
+    }
+}
+
+/// [`serial_fft_radix4`] with the twiddle factors taken from `twiddles`.
+fn serial_fft_radix4_twiddles<E: ScalarEngine, T: Group<E>>(
+    a: &mut [T],
+    twiddles: TwiddleSource<E::Fr>,
+    log_n: u32,
+) {
+    let n = a.len() as u32;
+    assert_eq!(n, 1 << log_n);
+
+    bitreverse_permutation(a, log_n);
+
+    let mut m = 1;
+    let mut remaining = log_n;
+    if remaining >= 2 {
+        // Primitive fourth root of unity
+        let i = twiddles.power((n / 4) as usize);
+
+        while remaining >= 2 {
+            let step = (n / (4 * m)) as usize;
+            let (powers, powers2) = (twiddles.with_step(step), twiddles.with_step(2 * step));
+
+            let mut k = 0;
+            while k < n {
+                for ((j, w), w2) in (0..m).zip(powers.iter()).zip(powers2.iter()) {
+                    let idx0 = (k + j) as usize;
+                    let idx1 = (k + j + m) as usize;
+                    let idx2 = (k + j + 2 * m) as usize;
+                    let idx3 = (k + j + 3 * m) as usize;
+
+                    let mut w3 = w2;
+                    w3.mul_assign(&w);
+
+                    let x0 = a[idx0];
+                    let mut x1 = a[idx1];
+                    x1.group_mul_assign(&w2);
+                    let mut x2 = a[idx2];
+                    x2.group_mul_assign(&w);
+                    let mut x3 = a[idx3];
+                    x3.group_mul_assign(&w3);
+
+                    let mut s01 = x0;
+                    s01.group_add_assign(&x1);
+                    let mut d01 = x0;
+                    d01.group_sub_assign(&x1);
+                    let mut s23 = x2;
+                    s23.group_add_assign(&x3);
+                    let mut d23 = x2;
+                    d23.group_sub_assign(&x3);
+                    d23.group_mul_assign(&i);
+
+                    a[idx0] = s01;
+                    a[idx0].group_add_assign(&s23);
+                    a[idx2] = s01;
+                    a[idx2].group_sub_assign(&s23);
+                    a[idx1] = d01;
+                    a[idx1].group_add_assign(&d23);
+                    a[idx3] = d01;
+                    a[idx3].group_sub_assign(&d23);
+                }
+
+                k += 4 * m;
+            }
+
+            m *= 4;
+            remaining -= 2;
+        }
+    }
+
+    if remaining == 1 {
+        // The last pass is over the whole of `a`, the j-th factor is the j-th power.
+        for (j, w) in (0..m).zip(twiddles.iter()) {
+            let mut t = a[(j + m) as usize];
+            t.group_mul_assign(&w);
+            let mut tmp = a[j as usize];
+            tmp.group_sub_assign(&t);
+            a[(j + m) as usize] = tmp;
+            a[j as usize].group_add_assign(&t);
+        }
+    }
+}
+
+/// FFT of `a` as `2^log_cpus` sub-FFTs in parallel, with `twiddles` the first
+/// `a.len() / 2` powers of `omega`.
+fn parallel_fft<E: ScalarEngine, T: Group<E>>(
+    a: &mut [T],
+    worker: &Worker,
+    omega: &E::Fr,
+    twiddles: &[E::Fr],
+    log_n: u32,
+    log_cpus: u32,
+    scratch: Option<&mut FftScratch<T>>,
+) {
+    assert!(log_n >= log_cpus);
+
+    let num_cpus = 1 << log_cpus;
+    let log_new_n = log_n - log_cpus;
+    let mut own_scratch;
+    let scratch = match scratch {
+        Some(scratch) => scratch,
+        None => {
+            own_scratch = FftScratch::new();
+            &mut own_scratch
+        }
+    };
+    let tmp = scratch.sub_ffts::<E>(num_cpus, 1 << log_new_n);
+
+    worker.scope(0, |scope, _| {
+        let a = &*a;
 
         for (j, tmp) in tmp.iter_mut().enumerate() {
             scope.spawn(move |_scope| {
@@ -410,23 +1169,253 @@ fn parallel_fft<E: ScalarEngine, T: Group<E>>(
                     elt.mul_assign(&omega_j);
                 }
 
-                // Perform sub-FFT
-                serial_fft(tmp, &new_omega, log_new_n);
+                // Perform sub-FFT, over the powers of omega^num_cpus
+                serial_fft_radix4_twiddles(
+                    tmp,
+                    TwiddleSource::Table(twiddles, num_cpus),
+                    log_new_n,
+                );
             });
         }
     });
 
     // TODO: does this hurt or help?
-    worker.scope(a.len(), |scope, chunk| {
-        let tmp = &tmp;
+    let tmp = &*tmp;
+    worker.scope_chunks_mut(a, |mut idx, a| {
+        let mask = (1 << log_cpus) - 1;
+        for a in a {
+            *a = tmp[idx & mask][idx >> log_cpus];
+            idx += 1;
+        }
+    });
+}
 
-        for (idx, a) in a.chunks_mut(chunk).enumerate() {
-            scope.spawn(move |_scope| {
-                let mut idx = idx * chunk;
-                let mask = (1 << log_cpus) - 1;
-                for a in a {
-                    *a = tmp[idx & mask][idx >> log_cpus];
-                    idx += 1;
+/// Four-step FFT of `a`, with `twiddles` the first `a.len() / 2` powers of the root of
+/// unity.
+///
+/// The `n = n1 * n2` values are treated as a matrix with `n1` columns: after a
+/// transpose, every row is transformed with an FFT of size `n2` and scaled, and after
+/// another transpose every row is transformed with an FFT of size `n1`. A last transpose
+/// puts the result in order. The rows are transformed in parallel and each only needs
+/// `sqrt(n)` values at once, so all passes work in place on any slice, including memory
+/// mapped ones, with only `O(sqrt(n))` extra memory.
+fn four_step_fft<E: ScalarEngine, T: Group<E>>(
+    a: &mut [T],
+    worker: &Worker,
+    twiddles: &[E::Fr],
+    log_n: u32,
+) {
+    let n = a.len();
+    assert_eq!(n, 1 << log_n);
+
+    // `n2` is `n1` or `2 * n1`.
+    let log_n1 = log_n / 2;
+    let log_n2 = log_n - log_n1;
+    let n1 = 1 << log_n1;
+    let n2 = 1 << log_n2;
+
+    // Row `j1` holds the elements `j1 + n1 * j2`.
+    transpose(a, worker, n2, n1);
+    transform_rows(a, worker, n2, |j1, row| {
+        serial_fft_radix4_twiddles(row, TwiddleSource::Table(twiddles, n1), log_n2);
+        // Scale by the j1 * k2-th power, which is one for the first row and column.
+        if j1 != 0 {
+            for (k2, v) in row.iter_mut().enumerate().skip(1) {
+                v.group_mul_assign(&twiddle::<E>(twiddles, j1 * k2));
+            }
+        }
+    });
+
+    transpose(a, worker, n1, n2);
+    transform_rows(a, worker, n1, |_, row| {
+        serial_fft_radix4_twiddles(row, TwiddleSource::Table(twiddles, n2), log_n1);
+    });
+
+    // Row `k2` holds the result at `k2 + n2 * k1`.
+    transpose(a, worker, n2, n1);
+}
+
+/// The `e`-th power of the root of unity of order `n`, for `e < n`, with `twiddles` its
+/// first `n / 2` powers.
+fn twiddle<E: ScalarEngine>(twiddles: &[E::Fr], e: usize) -> E::Fr {
+    let half = twiddles.len();
+    if e < half {
+        twiddles[e]
+    } else {
+        // The root of unity to the power of `n / 2` is -1.
+        let mut t = twiddles[e - half];
+        t.negate();
+        t
+    }
+}
+
+/// Calls `f` with the index and the values of every row of length `row_len`, in parallel.
+fn transform_rows<T, F>(a: &mut [T], worker: &Worker, row_len: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let num_rows = a.len() / row_len;
+    worker.scope(num_rows, |scope, chunk| {
+        let f = &f;
+        for (i, rows) in a.chunks_mut(chunk * row_len).enumerate() {
+            scope.spawn(move |_| {
+                for (r, row) in rows.chunks_mut(row_len).enumerate() {
+                    f(i * chunk + r, row);
+                }
+            });
+        }
+    });
+}
+
+/// Transposes the `rows x cols` matrix `a`, stored row by row, in place. The dimensions
+/// are powers of two, one at most twice the other.
+fn transpose<T: Copy + Send>(a: &mut [T], worker: &Worker, rows: usize, cols: usize) {
+    assert_eq!(a.len(), rows * cols);
+
+    if rows == cols {
+        transpose_square(a, worker, rows, 0, cols);
+    } else if cols == 2 * rows {
+        // Transpose the left and right halves, then move the rows of the right half
+        // behind the ones of the left half.
+        transpose_square(a, worker, rows, 0, cols);
+        transpose_square(a, worker, rows, rows, cols);
+        permute_blocks(a, worker, rows, |p| (p % 2) * rows + p / 2);
+    } else {
+        assert_eq!(rows, 2 * cols);
+        // The inverse of the above, interleave the rows of the top and bottom halves
+        // and transpose both halves.
+        permute_blocks(a, worker, cols, |p| 2 * (p % cols) + p / cols);
+        transpose_square(a, worker, cols, 0, rows);
+        transpose_square(a, worker, cols, cols, rows);
+    }
+}
+
+/// A slice shared by the tasks of a scope, which each access their own elements of it.
+struct SharedSlice<'a, T> {
+    ptr: *mut T,
+    len: usize,
+    _slice: std::marker::PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T> Clone for SharedSlice<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for SharedSlice<'a, T> {}
+
+// Safe as the tasks only access disjoint elements, see the methods.
+unsafe impl<'a, T: Send> Send for SharedSlice<'a, T> {}
+unsafe impl<'a, T: Send> Sync for SharedSlice<'a, T> {}
+
+impl<'a, T> SharedSlice<'a, T> {
+    fn new(slice: &'a mut [T]) -> Self {
+        SharedSlice {
+            ptr: slice.as_mut_ptr(),
+            len: slice.len(),
+            _slice: std::marker::PhantomData,
+        }
+    }
+
+    /// Swaps the elements at `i` and `j`, which no other task may access at the same
+    /// time.
+    unsafe fn swap(self, i: usize, j: usize) {
+        assert!(i < self.len && j < self.len);
+        std::ptr::swap(self.ptr.add(i), self.ptr.add(j));
+    }
+
+    /// The elements of `range`, which no other task may access while the returned
+    /// slice lives.
+    unsafe fn range_mut(self, range: std::ops::Range<usize>) -> &'a mut [T] {
+        assert!(range.start <= range.end && range.end <= self.len);
+        std::slice::from_raw_parts_mut(self.ptr.add(range.start), range.len())
+    }
+}
+
+/// Transposes the `n x n` matrix at `offset`, whose rows are `stride` apart, in blocks
+/// which stay in cache.
+///
+/// Every row of blocks is a task, which swaps the blocks right of the diagonal with
+/// those below it, so the tasks touch disjoint elements.
+fn transpose_square<T: Send>(a: &mut [T], worker: &Worker, n: usize, offset: usize, stride: usize) {
+    const BLOCK: usize = 16;
+
+    let a = SharedSlice::new(a);
+    worker.scope(n, |scope, _| {
+        for bi in (0..n).step_by(BLOCK) {
+            scope.spawn(move |_| {
+                for bj in (bi..n).step_by(BLOCK) {
+                    for i in bi..(bi + BLOCK).min(n) {
+                        let first_j = if bi == bj { i + 1 } else { bj };
+                        for j in first_j..(bj + BLOCK).min(n) {
+                            // Safe as only the task of the row of blocks `bi` swaps
+                            // elements of rows or columns in it, right of and below the
+                            // diagonal.
+                            unsafe { a.swap(offset + i * stride + j, offset + j * stride + i) };
+                        }
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Moves the `p`-th block of length `len` to the position `dest(p)`, following the
+/// cycles of the permutation.
+///
+/// All blocks move the same way, so the columns of the blocks are split among the
+/// tasks, each following every cycle for its columns with a buffer of their width.
+fn permute_blocks<T, F>(a: &mut [T], worker: &Worker, len: usize, dest: F)
+where
+    T: Copy + Send,
+    F: Fn(usize) -> usize + Sync,
+{
+    let num_blocks = a.len() / len;
+
+    // The first block of every cycle.
+    let mut moved = vec![false; num_blocks];
+    let mut cycles = Vec::new();
+    for start in 0..num_blocks {
+        if moved[start] {
+            continue;
+        }
+        cycles.push(start);
+        let mut p = start;
+        loop {
+            p = dest(p);
+            moved[p] = true;
+            if p == start {
+                break;
+            }
+        }
+    }
+
+    let a = SharedSlice::new(a);
+    let (cycles, dest) = (&cycles, &dest);
+    worker.scope(len, |scope, chunk| {
+        for lo in (0..len).step_by(chunk) {
+            let hi = (lo + chunk).min(len);
+            scope.spawn(move |_| {
+                // Safe as only this task accesses the columns `lo..hi` of the blocks.
+                let columns =
+                    |block: usize| unsafe { a.range_mut(block * len + lo..block * len + hi) };
+
+                // `hold` has the columns which are moved next, those from `p`.
+                let mut hold = Vec::with_capacity(hi - lo);
+                for &start in cycles {
+                    hold.clear();
+                    hold.extend_from_slice(columns(start));
+                    let mut p = start;
+                    loop {
+                        let d = dest(p);
+                        hold.swap_with_slice(columns(d));
+                        if d == start {
+                            break;
+                        }
+                        p = d;
+                    }
                 }
             });
         }
@@ -524,6 +1513,178 @@ fn fft_composition() {
     test_comp::<Bls12, _>(rng);
 }
 
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn domain_precompute_consistency() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut rand::thread_rng();
+    let worker = Worker::new();
+
+    // Includes the smallest domain and sizes which need to be padded.
+    for &coeffs in &[1, 2, 3, 5, 8, 100] {
+        let v = (0..coeffs)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+
+        let precompute = DomainPrecompute::<Bls12>::new(coeffs).unwrap();
+        let mut d1 = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        let mut d2 = EvaluationDomain::from_coeffs_with_precompute(v.clone(), &precompute).unwrap();
+
+        assert_eq!(precompute.size(), d1.coeffs.len());
+        assert_eq!(d1.exp, d2.exp);
+        assert!(d1.omega == d2.omega);
+        assert!(d1.omegainv == d2.omegainv);
+        assert!(d1.geninv == d2.geninv);
+        assert!(d1.minv == d2.minv);
+        assert!(d1.coeffs == d2.coeffs);
+
+        // The twiddle factors are the powers of omega, shared with the precompute and
+        // only computed once asked for.
+        assert!(Arc::ptr_eq(&precompute.twiddles, &d2.twiddles));
+        assert!(precompute.twiddles.forward.powers.lock().unwrap().is_none());
+        let (forward, inverse) = (d1.twiddles.forward.get(), d1.twiddles.inverse.get());
+        assert_eq!(forward.len(), precompute.size() / 2);
+        let mut power = Fr::one();
+        for (forward, inverse) in forward.iter().zip(inverse.iter()) {
+            assert_eq!(*forward, power);
+            let mut product = *forward;
+            product.mul_assign(inverse);
+            assert_eq!(product, Fr::one());
+            power.mul_assign(&d1.omega);
+        }
+
+        for d in [&mut d1, &mut d2].iter_mut() {
+            d.ifft(&worker, &mut None).unwrap();
+            d.coset_fft(&worker, &mut None).unwrap();
+            d.divide_by_z_on_coset(&worker);
+            d.icoset_fft(&worker, &mut None).unwrap();
+        }
+        assert!(d1.coeffs == d2.coeffs);
+
+        // A larger precomputed domain works as well, a smaller one is rejected.
+        let larger = DomainPrecompute::<Bls12>::new(2 * coeffs).unwrap();
+        let d3 = EvaluationDomain::from_coeffs_with_precompute(v.clone(), &larger).unwrap();
+        assert_eq!(d3.coeffs.len(), larger.size());
+        if coeffs > 1 {
+            let smaller = DomainPrecompute::<Bls12>::new(coeffs / 2).unwrap();
+            assert!(EvaluationDomain::from_coeffs_with_precompute(v, &smaller).is_err());
+        }
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn coset_scaling_consistency() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut rand::thread_rng();
+    let worker = Worker::new();
+
+    // Multiplies the i-th element by scale * g^i, one element after the other.
+    fn serial_distribute(v: &mut [Scalar<Bls12>], g: Fr, scale: Fr) {
+        let mut u = scale;
+        for v in v.iter_mut() {
+            v.group_mul_assign(&u);
+            u.mul_assign(&g);
+        }
+    }
+
+    for &log_d in &[0, 1, 4, 10, 13] {
+        let d = 1 << log_d;
+        let v = (0..d)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let g = Fr::random(rng);
+
+        let mut domain = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        domain.distribute_powers(&worker, g);
+        let mut expected = v.clone();
+        serial_distribute(&mut expected, g, Fr::one());
+        assert!(domain.coeffs == expected);
+
+        let mut domain = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        domain.icoset_fft(&worker, &mut None).unwrap();
+        let mut expected = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        expected.ifft(&worker, &mut None).unwrap();
+        serial_distribute(&mut expected.coeffs, expected.geninv, Fr::one());
+        assert!(domain.coeffs == expected.coeffs);
+
+        let mut domain = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        domain.divide_by_z_on_coset(&worker);
+        let zinv = domain.z(&Fr::multiplicative_generator()).inverse().unwrap();
+        let mut expected = v;
+        serial_distribute(&mut expected, Fr::one(), zinv);
+        assert!(domain.coeffs == expected);
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn fused_icoset_div_z_consistency() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut rand::thread_rng();
+    let worker = Worker::new();
+
+    for &log_d in &[0, 1, 3, 8, 12, 15] {
+        let d = 1 << log_d;
+        let v = (0..d)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+
+        let mut fused = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        fused.icoset_fft_div_z(&worker, &mut None).unwrap();
+
+        let mut unfused = EvaluationDomain::from_coeffs(v).unwrap();
+        unfused.divide_by_z_on_coset(&worker);
+        unfused.icoset_fft(&worker, &mut None).unwrap();
+
+        assert!(fused.coeffs == unfused.coeffs);
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn barycentric_evaluation() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut rand::thread_rng();
+    let worker = Worker::new();
+
+    for &log_d in &[0, 1, 4, 10] {
+        let d = 1 << log_d;
+        let coeffs = (0..d)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut evals = EvaluationDomain::from_coeffs(coeffs).unwrap();
+        evals.fft(&worker, &mut None).unwrap();
+
+        // The polynomial from an inverse FFT, evaluated with Horner's rule.
+        let mut poly = EvaluationDomain::from_coeffs(evals.coeffs.clone()).unwrap();
+        poly.ifft(&worker, &mut None).unwrap();
+        let horner = |x: Fr| {
+            poly.coeffs.iter().rev().fold(Fr::zero(), |mut acc, c| {
+                acc.mul_assign(&x);
+                acc.add_assign(&c.0);
+                acc
+            })
+        };
+
+        for _ in 0..5 {
+            let x = Fr::random(rng);
+            assert_eq!(evals.evaluate_at(&worker, x), horner(x));
+        }
+
+        // Points of the domain give the stored evaluations.
+        for &i in &[0, d / 2, d - 1] {
+            let x = evals.omega.pow(&[i as u64]);
+            assert_eq!(evals.evaluate_at(&worker, x), evals.coeffs[i].0);
+            assert_eq!(evals.evaluate_at(&worker, x), horner(x));
+        }
+    }
+}
+
 #[cfg(any(feature = "pairing", feature = "blst"))]
 #[test]
 fn parallel_fft_consistency() {
@@ -545,7 +1706,15 @@ fn parallel_fft_consistency() {
                 let mut v2 = EvaluationDomain::from_coeffs(v1.coeffs.clone()).unwrap();
 
                 for log_cpus in log_d..min(log_d + 1, 3) {
-                    parallel_fft(&mut v1.coeffs, &worker, &v1.omega, log_d, log_cpus);
+                    parallel_fft(
+                        &mut v1.coeffs,
+                        &worker,
+                        &v1.omega,
+                        &v1.twiddles.forward.get(),
+                        log_d,
+                        log_cpus,
+                        None,
+                    );
                     serial_fft(&mut v2.coeffs, &v2.omega, log_d);
 
                     assert!(v1.coeffs == v2.coeffs);
@@ -559,18 +1728,435 @@ fn parallel_fft_consistency() {
     test_consistency::<Bls12, _>(rng);
 }
 
-pub fn create_fft_kernel<E>(_log_d: usize, priority: bool) -> Option<gpu::FFTKernel<E>>
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn point_fft_naive_evaluation() {
+    use crate::bls::{Bls12, Fr, G1Projective, G2Projective};
+
+    fn test_naive<G: CurveProjective<Engine = Bls12, Scalar = Fr>, R: rand_core::RngCore>(
+        rng: &mut R,
+    ) {
+        let worker = Worker::new();
+
+        for log_d in 0..6 {
+            let d = 1 << log_d;
+            let points = (0..d).map(|_| Point(G::random(rng))).collect::<Vec<_>>();
+
+            // Evaluates `sum_i points[i] * x^i`.
+            let evaluate = |x: Fr| {
+                let mut acc = G::zero();
+                for p in points.iter().rev() {
+                    acc.mul_assign(x.into_repr());
+                    acc.add_assign(&p.0);
+                }
+                acc
+            };
+
+            let mut domain = EvaluationDomain::<Bls12, _>::from_coeffs(points.clone()).unwrap();
+            let omega = domain.omega();
+            domain.fft(&worker, &mut None).unwrap();
+            for (j, p) in domain.as_ref().iter().enumerate() {
+                assert!(p.0 == evaluate(omega.pow(&[j as u64])));
+            }
+            domain.ifft(&worker, &mut None).unwrap();
+            assert!(domain.as_ref() == &points[..]);
+
+            domain.coset_fft(&worker, &mut None).unwrap();
+            for (j, p) in domain.as_ref().iter().enumerate() {
+                let mut x = omega.pow(&[j as u64]);
+                x.mul_assign(&Fr::multiplicative_generator());
+                assert!(p.0 == evaluate(x));
+            }
+            domain.icoset_fft(&worker, &mut None).unwrap();
+            assert!(domain.as_ref() == &points[..]);
+
+            // The inverse FFT of `tau^i * g` is the Lagrange basis at `tau` times `g`,
+            // with `L_j(x) = omega^j / d * (x^d - 1) / (x - omega^j)`.
+            let g = G::random(rng);
+            let tau = Fr::random(rng);
+            let powers = (0..d)
+                .map(|i| {
+                    let mut p = g;
+                    p.mul_assign(tau.pow(&[i as u64]).into_repr());
+                    Point(p)
+                })
+                .collect::<Vec<_>>();
+            let mut lagrange = EvaluationDomain::<Bls12, _>::from_coeffs(powers).unwrap();
+            lagrange.ifft(&worker, &mut None).unwrap();
+
+            let z = lagrange.z(&tau);
+            let dinv = Fr::from_str(&d.to_string()).unwrap().inverse().unwrap();
+            for (j, p) in lagrange.as_ref().iter().enumerate() {
+                let omega_j = omega.pow(&[j as u64]);
+                let mut denominator = tau;
+                denominator.sub_assign(&omega_j);
+                let mut l = denominator.inverse().unwrap();
+                l.mul_assign(&z);
+                l.mul_assign(&omega_j);
+                l.mul_assign(&dinv);
+
+                let mut expected = g;
+                expected.mul_assign(l.into_repr());
+                assert!(p.0 == expected);
+            }
+        }
+    }
+
+    let rng = &mut rand::thread_rng();
+
+    test_naive::<G1Projective, _>(rng);
+    test_naive::<G2Projective, _>(rng);
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn sized_domain() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut rand::thread_rng();
+    let v = (0..5)
+        .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+        .collect::<Vec<_>>();
+
+    // Exact fit
+    let d = EvaluationDomain::from_coeffs_into_sized(v[..4].to_vec(), 4).unwrap();
+    assert!(d.coeffs == &v[..4]);
+    assert_eq!(d.exp, 2);
+
+    // Padding to a larger domain than `from_coeffs` would use
+    let mut d = EvaluationDomain::from_coeffs_into_sized(v.clone(), 32).unwrap();
+    let reference =
+        EvaluationDomain::<Bls12, _>::from_coeffs(vec![Scalar(Fr::zero()); 32]).unwrap();
+    assert_eq!(d.coeffs.len(), 32);
+    assert!(d.coeffs[..5] == v[..]);
+    assert!(d.coeffs[5..].iter().all(|c| c.0.is_zero()));
+    assert!(d.omega == reference.omega);
+    assert!(d.minv == reference.minv);
+
+    // Errors
+    assert!(matches!(
+        EvaluationDomain::from_coeffs_into_sized(v.clone(), 4),
+        Err(SynthesisError::PolynomialDegreeTooLarge)
+    ));
+    assert!(matches!(
+        EvaluationDomain::from_coeffs_into_sized(v.clone(), 12),
+        Err(SynthesisError::InvalidDomainSize(12))
+    ));
+    assert!(matches!(
+        EvaluationDomain::from_coeffs_into_sized(v.clone(), 0),
+        Err(SynthesisError::InvalidDomainSize(0))
+    ));
+
+    // Refilling keeps the memory and the size.
+    let ptr = d.coeffs.as_ptr();
+    d.reuse(&v[..2]).unwrap();
+    assert_eq!(d.coeffs.as_ptr(), ptr);
+    assert_eq!(d.coeffs.len(), 32);
+    assert!(d.coeffs[..2] == v[..2]);
+    assert!(d.coeffs[2..].iter().all(|c| c.0.is_zero()));
+
+    let too_many = vec![Scalar(Fr::one()); 33];
+    assert!(matches!(
+        d.reuse(&too_many),
+        Err(SynthesisError::PolynomialDegreeTooLarge)
+    ));
+    assert!(d.coeffs[..2] == v[..2]);
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn domain_too_large() {
+    use crate::bls::{Bls12, Fr};
+
+    // Coefficients without size, so oversized domains don't need any memory.
+    #[derive(Clone, Copy)]
+    struct Empty;
+
+    impl Group<Bls12> for Empty {
+        fn group_zero() -> Self {
+            Empty
+        }
+        fn group_mul_assign(&mut self, _: &Fr) {}
+        fn group_add_assign(&mut self, _: &Self) {}
+        fn group_sub_assign(&mut self, _: &Self) {}
+    }
+
+    let max = EvaluationDomain::<Bls12, Empty>::max_size();
+    assert_eq!(max, 1 << 31);
+
+    let mut coeffs = Vec::new();
+    // Safe as `Empty` has no size.
+    unsafe { coeffs.set_len(max + 1) };
+
+    match EvaluationDomain::<Bls12, Empty>::from_coeffs(coeffs) {
+        Err(SynthesisError::DomainTooLarge { size, max: m }) => {
+            assert_eq!(size, max + 1);
+            assert_eq!(m, max);
+        }
+        _ => panic!("expected the domain to be too large"),
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn configured_fft_consistency() {
+    use crate::bls::{Bls12, Fr};
+    use crate::multicore::FftConfig;
+
+    let rng = &mut rand::thread_rng();
+
+    for &(min_parallel_log_size, max_log_threads_override) in &[
+        (None, None),
+        (Some(0), Some(0)),
+        (Some(2), Some(1)),
+        (Some(4), Some(6)),
+        (Some(20), None),
+    ] {
+        let worker = Worker::with_fft_config(FftConfig {
+            min_parallel_log_size,
+            max_log_threads_override,
+            min_four_step_log_size: None,
+        });
+
+        for log_d in 0..10 {
+            let v = (0..1 << log_d)
+                .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+                .collect::<Vec<_>>();
+            let mut v1 = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+            let mut v2 = EvaluationDomain::from_coeffs(v).unwrap();
+
+            v1.fft(&worker, &mut None).unwrap();
+            serial_fft(&mut v2.coeffs, &v2.omega, log_d);
+
+            assert!(v1.coeffs == v2.coeffs);
+        }
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn radix4_fft_consistency() {
+    use crate::bls::{Bls12, Engine};
+    use rand_core::RngCore;
+
+    fn test_consistency<E: ScalarEngine + Engine, R: RngCore>(rng: &mut R, log_d: u32) {
+        let d = 1 << log_d;
+
+        let v1 = (0..d)
+            .map(|_| Scalar::<E>(E::Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut v1 = EvaluationDomain::from_coeffs(v1).unwrap();
+        let mut v2 = EvaluationDomain::from_coeffs(v1.coeffs.clone()).unwrap();
+
+        serial_fft_radix4(&mut v1.coeffs, &v1.omega, log_d);
+        serial_fft(&mut v2.coeffs, &v2.omega, log_d);
+
+        assert!(v1.coeffs == v2.coeffs);
+    }
+
+    let rng = &mut rand::thread_rng();
+
+    for log_d in 0..=12 {
+        for _ in 0..3 {
+            test_consistency::<Bls12, _>(rng, log_d);
+        }
+    }
+    test_consistency::<Bls12, _>(rng, 20);
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn fft_in_place_consistency() {
+    use crate::bls::{Bls12, Engine};
+    use rand_core::RngCore;
+
+    fn test_consistency<E: ScalarEngine + Engine, R: RngCore>(rng: &mut R, log_d: u32) {
+        let worker = Worker::new();
+        let d = 1 << log_d;
+
+        let coeffs = (0..d).map(|_| E::Fr::random(rng)).collect::<Vec<_>>();
+        let mut domain =
+            EvaluationDomain::from_coeffs(coeffs.iter().map(|c| Scalar::<E>(*c)).collect())
+                .unwrap();
+        domain.fft(&worker, &mut None).unwrap();
+
+        let mut a = coeffs.clone();
+        fft_in_place(&worker, &mut a, &domain.omega, log_d, &mut None).unwrap();
+        assert!(a.iter().zip(&domain.coeffs).all(|(a, b)| *a == b.0));
+
+        ifft_in_place(&worker, &mut a, &domain.omega, log_d, &mut None).unwrap();
+        assert!(a == coeffs);
+    }
+
+    let rng = &mut rand::thread_rng();
+
+    for log_d in 0..=12 {
+        test_consistency::<Bls12, _>(rng, log_d);
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn fft_in_place_preconditions() {
+    use crate::bls::{Bls12, Fr};
+
+    let worker = Worker::new();
+    let omega = EvaluationDomain::<Bls12, Scalar<Bls12>>::from_coeffs(vec![Scalar(Fr::zero()); 16])
+        .unwrap()
+        .omega;
+
+    let mut a = vec![Fr::one(); 16];
+    assert!(fft_in_place::<Bls12>(&worker, &mut a, &omega, 4, &mut None).is_ok());
+
+    match fft_in_place::<Bls12>(&worker, &mut a[..12], &omega, 4, &mut None) {
+        Err(SynthesisError::InvalidDomainSize(12)) => {}
+        _ => panic!("expected InvalidDomainSize"),
+    }
+    match ifft_in_place::<Bls12>(&worker, &mut a[..8], &omega, 4, &mut None) {
+        Err(SynthesisError::FftSizeMismatch { len: 8, log_n: 4 }) => {}
+        _ => panic!("expected FftSizeMismatch"),
+    }
+
+    // A root of unity of a smaller domain is not primitive.
+    let mut omega_sq = omega;
+    omega_sq.square();
+    match fft_in_place::<Bls12>(&worker, &mut a, &omega_sq, 4, &mut None) {
+        Err(SynthesisError::InvalidRootOfUnity(4)) => {}
+        _ => panic!("expected InvalidRootOfUnity"),
+    }
+    match ifft_in_place::<Bls12>(&worker, &mut a, &Fr::one(), 4, &mut None) {
+        Err(SynthesisError::InvalidRootOfUnity(4)) => {}
+        _ => panic!("expected InvalidRootOfUnity"),
+    }
+    assert!(fft_in_place::<Bls12>(&worker, &mut a[..1], &Fr::one(), 0, &mut None).is_ok());
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn polynomial_multiply() {
+    use crate::bls::{Bls12, Fr};
+
+    let rng = &mut rand::thread_rng();
+    let worker = Worker::new();
+
+    for &(len_a, len_b) in &[(1, 1), (1, 7), (3, 5), (8, 8), (9, 16), (33, 100)] {
+        let a = (0..len_a).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let b = (0..len_b).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+
+        let mut expected = vec![Fr::zero(); len_a + len_b - 1];
+        for (i, a) in a.iter().enumerate() {
+            for (j, b) in b.iter().enumerate() {
+                let mut product = *a;
+                product.mul_assign(b);
+                expected[i + j].add_assign(&product);
+            }
+        }
+
+        // The zeroes padding the inputs do not count towards the degree.
+        let a =
+            EvaluationDomain::<Bls12, _>::from_coeffs(a.into_iter().map(Scalar).collect()).unwrap();
+        let b =
+            EvaluationDomain::<Bls12, _>::from_coeffs(b.into_iter().map(Scalar).collect()).unwrap();
+        let product = a.multiply(b, &worker, &mut None).unwrap().into_coeffs();
+
+        assert_eq!(product.len(), expected.len().next_power_of_two());
+        for (i, c) in product.iter().enumerate() {
+            assert_eq!(c.0, expected.get(i).copied().unwrap_or_else(Fr::zero));
+        }
+    }
+
+    let zero = EvaluationDomain::<Bls12, _>::from_coeffs(vec![Scalar(Fr::zero()); 4]).unwrap();
+    let one = EvaluationDomain::<Bls12, _>::from_coeffs(vec![Scalar(Fr::one())]).unwrap();
+    let product = zero.multiply(one, &worker, &mut None).unwrap();
+    assert!(product.coeffs.iter().all(|c| c.0.is_zero()));
+
+    // Products beyond the two-adicity of the field are rejected.
+    let max = EvaluationDomain::<Bls12, Scalar<Bls12>>::max_size();
+    match product_precompute::<Bls12>(max / 2 + 1, max / 2 + 1) {
+        Err(SynthesisError::DomainTooLarge { size, .. }) => assert_eq!(size, max + 1),
+        _ => panic!("expected the domain to be too large"),
+    }
+}
+
+#[test]
+fn blocked_transpose() {
+    let worker = Worker::with_threads(4);
+
+    // Square and both non-square aspect ratios, including sizes below the block size.
+    for &(log_rows, log_cols) in &[
+        (0, 0),
+        (0, 1),
+        (1, 0),
+        (2, 3),
+        (3, 2),
+        (4, 4),
+        (4, 5),
+        (5, 4),
+        (6, 7),
+        (7, 6),
+    ] {
+        let rows = 1 << log_rows;
+        let cols = 1 << log_cols;
+        let original = (0..rows * cols).collect::<Vec<usize>>();
+
+        let mut a = original.clone();
+        transpose(&mut a, &worker, rows, cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                assert_eq!(a[c * rows + r], original[r * cols + c]);
+            }
+        }
+
+        transpose(&mut a, &worker, cols, rows);
+        assert_eq!(a, original);
+    }
+}
+
+#[cfg(any(feature = "pairing", feature = "blst"))]
+#[test]
+fn four_step_fft_consistency() {
+    use crate::bls::{Bls12, Fr};
+    use crate::multicore::FftConfig;
+
+    let rng = &mut rand::thread_rng();
+    let worker = Worker::with_fft_config(FftConfig {
+        min_four_step_log_size: Some(0),
+        ..FftConfig::default()
+    });
+
+    for log_d in 0..=13 {
+        let v = (0..1 << log_d)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut v1 = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+        let mut v2 = EvaluationDomain::from_coeffs(v).unwrap();
+
+        v1.fft(&worker, &mut None).unwrap();
+        serial_fft(&mut v2.coeffs, &v2.omega, log_d);
+        assert!(v1.coeffs == v2.coeffs);
+
+        v1.ifft(&worker, &mut None).unwrap();
+        v2.ifft(&Worker::new(), &mut None).unwrap();
+        assert!(v1.coeffs == v2.coeffs);
+    }
+}
+
+pub fn create_fft_kernel<E>(
+    _log_d: usize,
+    priority: bool,
+    lock: &gpu::GPULock,
+) -> gpu::GPUResult<gpu::FFTKernel<E>>
 where
     E: Engine,
 {
-    match gpu::FFTKernel::create(priority) {
+    match gpu::FFTKernel::create_with_lock(priority, lock) {
         Ok(k) => {
             info!("GPU FFT kernel instantiated!");
-            Some(k)
+            Ok(k)
         }
         Err(e) => {
             warn!("Cannot instantiate GPU FFT kernel! Error: {}", e);
-            None
+            Err(e)
         }
     }
 }
@@ -617,7 +2203,15 @@ mod tests {
             if log_d <= log_cpus {
                 serial_fft(&mut v2.coeffs, &v2.omega, log_d);
             } else {
-                parallel_fft(&mut v2.coeffs, &worker, &v2.omega, log_d, log_cpus);
+                parallel_fft(
+                    &mut v2.coeffs,
+                    &worker,
+                    &v2.omega,
+                    &v2.twiddles.forward.get(),
+                    log_d,
+                    log_cpus,
+                    None,
+                );
             }
             let cpu_dur =
                 now.elapsed().as_secs() * 1000 as u64 + now.elapsed().subsec_millis() as u64;
@@ -629,4 +2223,58 @@ mod tests {
             println!("============================");
         }
     }
+
+    #[test]
+    pub fn gpu_icoset_fft_div_z_consistency() {
+        let _ = env_logger::try_init();
+
+        let rng = &mut rand::thread_rng();
+
+        let worker = Worker::new();
+        let mut kern = Some(gpu::LockedFFTKernel::<Bls12>::new(20, false));
+
+        for &log_d in &[1, 8, 16, 20] {
+            let d = 1 << log_d;
+
+            let elems = (0..d)
+                .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+                .collect::<Vec<_>>();
+            let mut v1 = EvaluationDomain::from_coeffs(elems.clone()).unwrap();
+            let mut v2 = EvaluationDomain::from_coeffs(elems).unwrap();
+
+            v1.icoset_fft_div_z(&worker, &mut kern).unwrap();
+            v2.divide_by_z_on_coset(&worker);
+            v2.icoset_fft(&worker, &mut None).unwrap();
+
+            assert!(v1.coeffs == v2.coeffs);
+        }
+    }
+
+    #[test]
+    pub fn gpu_ifft_consistency() {
+        let _ = env_logger::try_init();
+
+        let rng = &mut rand::thread_rng();
+
+        let worker = Worker::new();
+        let mut kern = Some(gpu::LockedFFTKernel::<Bls12>::new(20, false));
+
+        for &log_d in &[1, 8, 16, 20] {
+            let d = 1 << log_d;
+
+            let elems = (0..d)
+                .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+                .collect::<Vec<_>>();
+            let mut v1 = EvaluationDomain::from_coeffs(elems.clone()).unwrap();
+            let mut v2 = EvaluationDomain::from_coeffs(elems.clone()).unwrap();
+
+            v1.ifft(&worker, &mut kern).unwrap();
+            v2.ifft(&worker, &mut None).unwrap();
+            assert!(v1.coeffs == v2.coeffs);
+
+            // The forward transform on the GPU undoes the inverse one.
+            v1.fft(&worker, &mut kern).unwrap();
+            assert!(v1.coeffs == elems);
+        }
+    }
 }
@@ -0,0 +1,648 @@
+//! Folding of relaxed R1CS instances, for Nova-style incrementally verifiable
+//! computation.
+//!
+//! A relaxed R1CS instance of the constraint matrices `A`, `B` and `C` is satisfied by
+//! `z = (u, x, W)` and an error vector `E` if `Az ∘ Bz = u·Cz + E`. A satisfying
+//! assignment of a circuit is the special case `u = 1` and `E = 0`.
+//!
+//! Following [Nova], two instances are folded into one by committing to the cross term
+//! `T = Az₁ ∘ Bz₂ + Az₂ ∘ Bz₁ - u₁·Cz₂ - u₂·Cz₁` and taking a random linear combination
+//! of both, with the challenge `r` derived from the instances and the commitment. The
+//! folded instance is satisfiable if and only if both inputs are, except with negligible
+//! probability, so many circuit executions can be accumulated and proven at once with
+//! [`RelaxedR1CSCircuit`].
+//!
+//! `W`, `E` and `T` are committed to with Pedersen vector commitments in G1, computed via
+//! [`multiexp`]. G1 is not defined over the scalar field, so the commitments cannot be
+//! opened in the circuit: `W` and `E` are public inputs of the final proof instead, and
+//! [`FoldingScheme::public_inputs`] checks that they open the commitments of the
+//! accumulated instance.
+//!
+//! [Nova]: https://eprint.iacr.org/2021/370
+
+use std::sync::Arc;
+
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::RngCore;
+use rayon::prelude::*;
+
+use crate::bls::Engine;
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, FullDensity};
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Personalization of the hash deriving the folding challenges.
+const FOLDING_PERSONALIZATION: &[u8; 8] = b"BPFOLD00";
+
+/// A row of a constraint matrix, as the non-zero coefficients of the variables.
+pub(crate) type Row<E> = Vec<(Index, <E as ScalarEngine>::Fr)>;
+
+/// The constraint matrices of a circuit.
+///
+/// Column `Input(0)` belongs to `u`, the other inputs to `x` and the auxiliary
+/// variables to `W`.
+#[derive(Clone, Debug)]
+pub struct R1CSShape<E: Engine> {
+    num_inputs: usize,
+    num_aux: usize,
+    a: Vec<Row<E>>,
+    b: Vec<Row<E>>,
+    c: Vec<Row<E>>,
+}
+
+impl<E: Engine> PartialEq for R1CSShape<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_inputs == other.num_inputs
+            && self.num_aux == other.num_aux
+            && self.a == other.a
+            && self.b == other.b
+            && self.c == other.c
+    }
+}
+
+impl<E: Engine> R1CSShape<E> {
+    /// Synthesizes `circuit` to get its constraint matrices. Assignments are not needed.
+    pub fn from_circuit<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut cs = RecordingConstraintSystem::<E>::new();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(cs.shape)
+    }
+
+    /// Synthesizes `circuit` to get its constraint matrices and its assignment: the
+    /// public inputs, not counting `u`, and the auxiliary variables.
+    pub(crate) fn with_assignment<C: Circuit<E>>(
+        circuit: C,
+    ) -> Result<(Self, Vec<E::Fr>, Vec<E::Fr>), SynthesisError> {
+        let mut cs = RecordingConstraintSystem::<E>::new();
+        circuit.synthesize(&mut cs)?;
+
+        let x = cs.inputs[1..]
+            .iter()
+            .map(|v| v.ok_or(SynthesisError::AssignmentMissing))
+            .collect::<Result<Vec<_>, _>>()?;
+        let w = cs
+            .aux
+            .iter()
+            .map(|v| v.ok_or(SynthesisError::AssignmentMissing))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((cs.shape, x, w))
+    }
+
+    /// The matrices `A`, `B` and `C`, one row per constraint.
+    pub(crate) fn matrices(&self) -> [&[Row<E>]; 3] {
+        [&self.a, &self.b, &self.c]
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Number of public inputs, not counting `u`.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs - 1
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.num_aux
+    }
+
+    /// Computes `Az`, `Bz` and `Cz`.
+    pub(crate) fn multiply(
+        &self,
+        u: &E::Fr,
+        x: &[E::Fr],
+        w: &[E::Fr],
+    ) -> (Vec<E::Fr>, Vec<E::Fr>, Vec<E::Fr>) {
+        let multiply = |m: &[Row<E>]| -> Vec<E::Fr> {
+            m.par_iter()
+                .map(|row| eval_row::<E>(row, u, x, w))
+                .collect()
+        };
+
+        (multiply(&self.a), multiply(&self.b), multiply(&self.c))
+    }
+}
+
+fn eval_row<E: Engine>(row: &[(Index, E::Fr)], u: &E::Fr, x: &[E::Fr], w: &[E::Fr]) -> E::Fr {
+    let mut acc = E::Fr::zero();
+    for (index, coeff) in row {
+        let mut tmp = match *index {
+            Index::Input(0) => *u,
+            Index::Input(i) => x[i - 1],
+            Index::Aux(i) => w[i],
+        };
+        tmp.mul_assign(coeff);
+        acc.add_assign(&tmp);
+    }
+
+    acc
+}
+
+/// The public part of a relaxed R1CS instance.
+#[derive(Clone, Debug)]
+pub struct RelaxedR1CSInstance<E: Engine> {
+    /// Commitment to the witness `W`.
+    pub comm_w: E::G1,
+    /// Commitment to the error vector `E`.
+    pub comm_e: E::G1,
+    pub u: E::Fr,
+    pub x: Vec<E::Fr>,
+}
+
+impl<E: Engine> PartialEq for RelaxedR1CSInstance<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.comm_w == other.comm_w
+            && self.comm_e == other.comm_e
+            && self.u == other.u
+            && self.x == other.x
+    }
+}
+
+impl<E: Engine> RelaxedR1CSInstance<E> {
+    fn write_transcript(&self, state: &mut blake2s_simd::State) {
+        state.update(self.comm_w.into_affine().into_compressed().as_ref());
+        state.update(self.comm_e.into_affine().into_compressed().as_ref());
+        for value in std::iter::once(&self.u).chain(&self.x) {
+            let mut bytes = Vec::new();
+            value
+                .into_repr()
+                .write_le(&mut bytes)
+                .expect("writing to a Vec cannot fail");
+            state.update(&bytes);
+        }
+    }
+}
+
+/// A relaxed R1CS instance together with its witness.
+#[derive(Clone, Debug)]
+pub struct RelaxedR1CS<E: Engine> {
+    pub instance: RelaxedR1CSInstance<E>,
+    pub w: Vec<E::Fr>,
+    pub e: Vec<E::Fr>,
+}
+
+/// Folds relaxed R1CS instances of a single circuit.
+pub struct FoldingScheme<E: Engine> {
+    shape: R1CSShape<E>,
+    generators: Arc<Vec<E::G1Affine>>,
+}
+
+impl<E: Engine> FoldingScheme<E> {
+    /// Sets up folding for `circuit`, with random commitment generators.
+    pub fn setup<C: Circuit<E>, R: RngCore>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Self, SynthesisError> {
+        let shape = R1CSShape::from_circuit(circuit)?;
+
+        let num_generators = std::cmp::max(shape.num_aux(), shape.num_constraints());
+        let generators = (0..num_generators)
+            .map(|_| E::G1::random(rng).into_affine())
+            .collect();
+
+        Ok(FoldingScheme {
+            shape,
+            generators: Arc::new(generators),
+        })
+    }
+
+    pub fn shape(&self) -> &R1CSShape<E> {
+        &self.shape
+    }
+
+    /// Synthesizes `circuit` into a relaxed instance with `u = 1` and `E = 0`.
+    ///
+    /// Fails with `SynthesisError::Unsatisfiable` if the constraints of `circuit`
+    /// differ from the ones of the scheme.
+    pub fn relax<C: Circuit<E>>(&self, circuit: C) -> Result<RelaxedR1CS<E>, SynthesisError> {
+        let (shape, x, w) = R1CSShape::with_assignment(circuit)?;
+        if shape != self.shape {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        Ok(RelaxedR1CS {
+            instance: RelaxedR1CSInstance {
+                comm_w: self.commit(&w)?,
+                comm_e: E::G1::zero(),
+                u: E::Fr::one(),
+                x,
+            },
+            w,
+            e: vec![E::Fr::zero(); self.shape.num_constraints()],
+        })
+    }
+
+    /// Pedersen commitment to `values`.
+    pub fn commit(&self, values: &[E::Fr]) -> Result<E::G1, SynthesisError> {
+        assert!(values.len() <= self.generators.len());
+
+        let exps = values.iter().map(|v| v.into_repr()).collect::<Vec<_>>();
+        multiexp(
+            &Worker::new(),
+            (self.generators.clone(), 0),
+            FullDensity,
+            Arc::new(exps),
+            &mut None,
+        )
+        .wait()
+    }
+
+    /// Folds `b` into `a`, returning the folded instance with its witness and the
+    /// commitment to the cross term, which the verifier needs for
+    /// [`FoldingScheme::fold_instances`].
+    pub fn fold(
+        &self,
+        a: &RelaxedR1CS<E>,
+        b: &RelaxedR1CS<E>,
+    ) -> Result<(RelaxedR1CS<E>, E::G1), SynthesisError> {
+        let t = self.cross_term(a, b);
+        let comm_t = self.commit(&t)?;
+        let r = challenge(&a.instance, &b.instance, &comm_t);
+
+        let instance = fold_instances(&a.instance, &b.instance, &comm_t, &r);
+        let w = fold_vectors::<E>(&a.w, &b.w, None, &r);
+        let e = fold_vectors::<E>(&a.e, &b.e, Some(&t), &r);
+
+        Ok((RelaxedR1CS { instance, w, e }, comm_t))
+    }
+
+    /// Folds the public instances `a` and `b`, as the verifier does, given the
+    /// cross term commitment returned by [`FoldingScheme::fold`].
+    pub fn fold_instances(
+        &self,
+        a: &RelaxedR1CSInstance<E>,
+        b: &RelaxedR1CSInstance<E>,
+        comm_t: &E::G1,
+    ) -> RelaxedR1CSInstance<E> {
+        let r = challenge(a, b, comm_t);
+        fold_instances(a, b, comm_t, &r)
+    }
+
+    /// The public inputs of a [`RelaxedR1CSCircuit`] proof for `instance`, `u`, `x`,
+    /// `w` and `e`, in this order.
+    ///
+    /// Fails with `SynthesisError::Unsatisfiable` if `w` and `e` do not open the
+    /// commitments of `instance`, as the proof only binds them to the instance through
+    /// this check.
+    pub fn public_inputs(
+        &self,
+        instance: &RelaxedR1CSInstance<E>,
+        w: &[E::Fr],
+        e: &[E::Fr],
+    ) -> Result<Vec<E::Fr>, SynthesisError> {
+        if instance.x.len() != self.shape.num_inputs()
+            || w.len() != self.shape.num_aux()
+            || e.len() != self.shape.num_constraints()
+            || instance.comm_w != self.commit(w)?
+            || instance.comm_e != self.commit(e)?
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        Ok(std::iter::once(instance.u)
+            .chain(instance.x.iter().cloned())
+            .chain(w.iter().cloned())
+            .chain(e.iter().cloned())
+            .collect())
+    }
+
+    /// Checks `Az ∘ Bz = u·Cz + E` and that the commitments open to `W` and `E`.
+    pub fn is_satisfied(&self, relaxed: &RelaxedR1CS<E>) -> Result<bool, SynthesisError> {
+        let instance = &relaxed.instance;
+        if instance.x.len() != self.shape.num_inputs()
+            || relaxed.w.len() != self.shape.num_aux()
+            || relaxed.e.len() != self.shape.num_constraints()
+        {
+            return Ok(false);
+        }
+
+        let (az, bz, cz) = self.shape.multiply(&instance.u, &instance.x, &relaxed.w);
+        let satisfied =
+            az.iter()
+                .zip(&bz)
+                .zip(cz.iter().zip(&relaxed.e))
+                .all(|((a, b), (c, e))| {
+                    let mut lhs = *a;
+                    lhs.mul_assign(b);
+                    let mut rhs = *c;
+                    rhs.mul_assign(&instance.u);
+                    rhs.add_assign(e);
+                    lhs == rhs
+                });
+
+        Ok(satisfied
+            && instance.comm_w == self.commit(&relaxed.w)?
+            && instance.comm_e == self.commit(&relaxed.e)?)
+    }
+
+    fn cross_term(&self, a: &RelaxedR1CS<E>, b: &RelaxedR1CS<E>) -> Vec<E::Fr> {
+        let (u1, u2) = (&a.instance.u, &b.instance.u);
+        let (az1, bz1, cz1) = self.shape.multiply(u1, &a.instance.x, &a.w);
+        let (az2, bz2, cz2) = self.shape.multiply(u2, &b.instance.x, &b.w);
+
+        (0..self.shape.num_constraints())
+            .into_par_iter()
+            .map(|i| {
+                let mut t = az1[i];
+                t.mul_assign(&bz2[i]);
+                let mut tmp = az2[i];
+                tmp.mul_assign(&bz1[i]);
+                t.add_assign(&tmp);
+                let mut tmp = cz2[i];
+                tmp.mul_assign(u1);
+                t.sub_assign(&tmp);
+                let mut tmp = cz1[i];
+                tmp.mul_assign(u2);
+                t.sub_assign(&tmp);
+                t
+            })
+            .collect()
+    }
+}
+
+/// Derives the folding challenge from both instances and the cross term commitment.
+fn challenge<E: Engine>(
+    a: &RelaxedR1CSInstance<E>,
+    b: &RelaxedR1CSInstance<E>,
+    comm_t: &E::G1,
+) -> E::Fr {
+    let mut state = blake2s_simd::Params::new()
+        .hash_length(32)
+        .personal(FOLDING_PERSONALIZATION)
+        .to_state();
+    a.write_transcript(&mut state);
+    b.write_transcript(&mut state);
+    state.update(comm_t.into_affine().into_compressed().as_ref());
+    let hash = state.finalize();
+
+    // Keep `CAPACITY` bits of the hash, so the result is always below the modulus.
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.read_le(hash.as_bytes())
+        .expect("the hash is as long as the representation");
+    let bits = 64 * repr.as_ref().len() as u32;
+    repr.shr(bits - E::Fr::CAPACITY);
+
+    E::Fr::from_repr(repr).expect("the value is below the modulus")
+}
+
+fn fold_instances<E: Engine>(
+    a: &RelaxedR1CSInstance<E>,
+    b: &RelaxedR1CSInstance<E>,
+    comm_t: &E::G1,
+    r: &E::Fr,
+) -> RelaxedR1CSInstance<E> {
+    let mut r_square = *r;
+    r_square.square();
+
+    // comm_W = comm_W₁ + r·comm_W₂
+    let mut comm_w = b.comm_w;
+    comm_w.mul_assign(r.into_repr());
+    comm_w.add_assign(&a.comm_w);
+
+    // comm_E = comm_E₁ + r·comm_T + r²·comm_E₂
+    let mut comm_e = b.comm_e;
+    comm_e.mul_assign(r_square.into_repr());
+    let mut tmp = *comm_t;
+    tmp.mul_assign(r.into_repr());
+    comm_e.add_assign(&tmp);
+    comm_e.add_assign(&a.comm_e);
+
+    let mut u = b.u;
+    u.mul_assign(r);
+    u.add_assign(&a.u);
+
+    RelaxedR1CSInstance {
+        comm_w,
+        comm_e,
+        u,
+        x: fold_vectors::<E>(&a.x, &b.x, None, r),
+    }
+}
+
+/// Computes `a + r·t + r²·b` if `t` is given, `a + r·b` otherwise.
+fn fold_vectors<E: Engine>(a: &[E::Fr], b: &[E::Fr], t: Option<&[E::Fr]>, r: &E::Fr) -> Vec<E::Fr> {
+    let mut r_b = *r;
+    if t.is_some() {
+        r_b.square();
+    }
+
+    a.par_iter()
+        .zip(b)
+        .enumerate()
+        .map(|(i, (a, b))| {
+            let mut value = *b;
+            value.mul_assign(&r_b);
+            if let Some(t) = t {
+                let mut tmp = t[i];
+                tmp.mul_assign(r);
+                value.add_assign(&tmp);
+            }
+            value.add_assign(a);
+            value
+        })
+        .collect()
+}
+
+/// Circuit proving that `Az ∘ Bz = u·Cz + E` holds, with `u`, `x`, `W` and `E` as
+/// public inputs, see [`FoldingScheme::public_inputs`].
+///
+/// The commitments of the instance cannot be opened in the circuit, so a proof says
+/// nothing about the folded instance unless its inputs are checked against the
+/// commitments, which [`FoldingScheme::public_inputs`] does.
+pub struct RelaxedR1CSCircuit<'a, E: Engine> {
+    shape: &'a R1CSShape<E>,
+    relaxed: Option<&'a RelaxedR1CS<E>>,
+}
+
+impl<'a, E: Engine> RelaxedR1CSCircuit<'a, E> {
+    /// Circuit for `shape`, the assignment is only needed for proving.
+    pub fn new(shape: &'a R1CSShape<E>, relaxed: Option<&'a RelaxedR1CS<E>>) -> Self {
+        RelaxedR1CSCircuit { shape, relaxed }
+    }
+}
+
+impl<'a, E: Engine> Circuit<E> for RelaxedR1CSCircuit<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let shape = self.shape;
+        let relaxed = self.relaxed;
+        let instance = relaxed.map(|r| &r.instance);
+        let cz = relaxed.map(|r| shape.multiply(&r.instance.u, &r.instance.x, &r.w).2);
+
+        let u_value = instance.map(|i| i.u);
+        let u = cs.alloc_input(|| "u", || u_value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        let x = (0..shape.num_inputs())
+            .map(|i| {
+                cs.alloc_input(
+                    || format!("x {}", i),
+                    || {
+                        instance
+                            .map(|v| v.x[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let w = (0..shape.num_aux())
+            .map(|i| {
+                cs.alloc_input(
+                    || format!("w {}", i),
+                    || {
+                        relaxed
+                            .map(|r| r.w[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let e = (0..shape.num_constraints())
+            .map(|i| {
+                cs.alloc_input(
+                    || format!("e {}", i),
+                    || {
+                        relaxed
+                            .map(|r| r.e[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let variable = |index: Index| match index {
+            Index::Input(0) => u,
+            Index::Input(i) => x[i - 1],
+            Index::Aux(i) => w[i],
+        };
+        let lc = |row: &[(Index, E::Fr)]| {
+            row.iter()
+                .fold(LinearCombination::<E>::zero(), |lc, &(index, coeff)| {
+                    lc + (coeff, variable(index))
+                })
+        };
+
+        for i in 0..shape.num_constraints() {
+            // u·Cz needs a constraint of its own, as u is a variable.
+            let u_cz = cs.alloc(
+                || format!("u cz {}", i),
+                || {
+                    let mut value = cz.as_ref().ok_or(SynthesisError::AssignmentMissing)?[i];
+                    value.mul_assign(&u_value.ok_or(SynthesisError::AssignmentMissing)?);
+                    Ok(value)
+                },
+            )?;
+
+            cs.enforce(
+                || format!("u cz {} computation", i),
+                |lc| lc + u,
+                |_| lc(&shape.c[i]),
+                |lc| lc + u_cz,
+            );
+            cs.enforce(
+                || format!("constraint {}", i),
+                |_| lc(&shape.a[i]),
+                |_| lc(&shape.b[i]),
+                |lc| lc + u_cz + e[i],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Constraint system recording the constraint matrices and the assignment of a circuit.
+struct RecordingConstraintSystem<E: Engine> {
+    shape: R1CSShape<E>,
+    inputs: Vec<Option<E::Fr>>,
+    aux: Vec<Option<E::Fr>>,
+}
+
+impl<E: Engine> RecordingConstraintSystem<E> {
+    fn new() -> Self {
+        RecordingConstraintSystem {
+            shape: R1CSShape {
+                num_inputs: 1,
+                num_aux: 0,
+                a: vec![],
+                b: vec![],
+                c: vec![],
+            },
+            inputs: vec![Some(E::Fr::one())],
+            aux: vec![],
+        }
+    }
+}
+
+fn row<E: Engine>(lc: &LinearCombination<E>) -> Row<E> {
+    let mut row = lc
+        .iter()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .map(|(var, coeff)| (var.get_unchecked(), *coeff))
+        .collect::<Vec<_>>();
+    row.sort_unstable_by_key(|(index, _)| match *index {
+        Index::Input(i) => (0, i),
+        Index::Aux(i) => (1, i),
+    });
+    row
+}
+
+impl<E: Engine> ConstraintSystem<E> for RecordingConstraintSystem<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f().ok());
+        self.shape.num_aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f().ok());
+        self.shape.num_inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.shape.a.push(row(&a(LinearCombination::zero())));
+        self.shape.b.push(row(&b(LinearCombination::zero())));
+        self.shape.c.push(row(&c(LinearCombination::zero())));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
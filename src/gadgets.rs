@@ -2,14 +2,28 @@
 
 pub mod test;
 
+pub mod blake2b;
 pub mod blake2s;
 pub mod boolean;
+pub mod compare;
+pub mod ecc;
+pub mod eddsa;
+pub mod keccak;
 pub mod lookup;
+pub mod merkle;
 pub mod multieq;
 pub mod multipack;
+pub mod mux;
+pub mod nonnative;
 pub mod num;
+pub mod pedersen;
+pub mod poseidon;
+pub mod range;
+pub mod ripemd160;
 pub mod sha256;
 pub mod uint32;
+pub mod uint64;
+pub mod uint8;
 
 use crate::SynthesisError;
 
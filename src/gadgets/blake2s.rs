@@ -2,6 +2,8 @@
 //!
 //! [BLAKE2s]: https://tools.ietf.org/html/rfc7693
 
+use super::uint8::{bits_le_to_bytes, bytes_to_bits_le, bytes_to_uint32s, uint32s_to_bytes};
+use super::uint8::{ByteOrder, UInt8};
 use super::{boolean::Boolean, multieq::MultiEq, uint32::UInt32};
 use crate::{ConstraintSystem, SynthesisError};
 use ff::ScalarEngine;
@@ -303,8 +305,7 @@ fn blake2s_compression<E: ScalarEngine, CS: ConstraintSystem<E>>(
     for i in 0..8 {
         let mut cs = cs.namespace(|| format!("h[{i}] ^ v[{i}] ^ v[{i} + 8]", i = i));
 
-        h[i] = h[i].xor(cs.namespace(|| "first xor"), &v[i])?;
-        h[i] = h[i].xor(cs.namespace(|| "second xor"), &v[i + 8])?;
+        h[i] = UInt32::xor_many(&mut cs, &[h[i].clone(), v[i].clone(), v[i + 8].clone()])?;
     }
 
     Ok(())
@@ -338,14 +339,29 @@ fn blake2s_compression<E: ScalarEngine, CS: ConstraintSystem<E>>(
 */
 
 pub fn blake2s<E: ScalarEngine, CS: ConstraintSystem<E>>(
-    mut cs: CS,
+    cs: CS,
     input: &[Boolean],
     personalization: &[u8],
 ) -> Result<Vec<Boolean>, SynthesisError> {
-    use byteorder::{ByteOrder, LittleEndian};
+    assert!(input.len() % 8 == 0);
+
+    Ok(bytes_to_bits_le(&blake2s_bytes(
+        cs,
+        &bits_le_to_bytes(input),
+        personalization,
+    )?))
+}
+
+/// BLAKE2s of `input`, whose bytes are given as [`UInt8`]s rather than as bits
+/// with the least significant bit of each byte first.
+pub fn blake2s_bytes<E: ScalarEngine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[UInt8],
+    personalization: &[u8],
+) -> Result<Vec<UInt8>, SynthesisError> {
+    use byteorder::{ByteOrder as _, LittleEndian};
 
     assert_eq!(personalization.len(), 8);
-    assert!(input.len() % 8 == 0);
 
     let mut h = Vec::with_capacity(8);
     h.push(UInt32::constant(0x6A09_E667 ^ 0x0101_0000 ^ 32));
@@ -365,19 +381,11 @@ pub fn blake2s<E: ScalarEngine, CS: ConstraintSystem<E>>(
 
     let mut blocks: Vec<Vec<UInt32>> = vec![];
 
-    for block in input.chunks(512) {
-        let mut this_block = Vec::with_capacity(16);
-        for word in block.chunks(32) {
-            let mut tmp = word.to_vec();
-            while tmp.len() < 32 {
-                tmp.push(Boolean::constant(false));
-            }
-            this_block.push(UInt32::from_bits(&tmp));
-        }
-        while this_block.len() < 16 {
-            this_block.push(UInt32::constant(0));
-        }
-        blocks.push(this_block);
+    for block in input.chunks(64) {
+        // The last block is padded with zeros.
+        let mut block = block.to_vec();
+        block.resize(64, UInt8::constant(0));
+        blocks.push(bytes_to_uint32s(&block, ByteOrder::LittleEndian));
     }
 
     if blocks.is_empty() {
@@ -397,12 +405,12 @@ pub fn blake2s<E: ScalarEngine, CS: ConstraintSystem<E>>(
             cs,
             &mut h,
             &blocks[blocks.len() - 1],
-            (input.len() / 8) as u64,
+            input.len() as u64,
             true,
         )?;
     }
 
-    Ok(h.into_iter().flat_map(|b| b.into_bits()).collect())
+    Ok(uint32s_to_bytes(&h, ByteOrder::LittleEndian))
 }
 
 #[cfg(test)]
@@ -65,6 +65,13 @@ impl AllocatedBit {
         })
     }
 
+    /// Wraps `variable`, which other constraints already restrict to zero or one,
+    /// like the flag of [`AllocatedNum::is_zero`](super::num::AllocatedNum::is_zero),
+    /// without another boolean constraint.
+    pub(crate) fn from_constrained_variable(variable: Variable, value: Option<bool>) -> Self {
+        AllocatedBit { variable, value }
+    }
+
     /// Allocate a variable in the constraint system which can only be a
     /// boolean value.
     pub fn alloc<E, CS>(mut cs: CS, value: Option<bool>) -> Result<Self, SynthesisError>
@@ -98,6 +105,37 @@ impl AllocatedBit {
         })
     }
 
+    /// Allocate a public input which can only be a boolean value.
+    pub fn alloc_input<E, CS>(mut cs: CS, value: Option<bool>) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let var = cs.alloc_input(
+            || "boolean",
+            || {
+                if *value.get()? {
+                    Ok(E::Fr::one())
+                } else {
+                    Ok(E::Fr::zero())
+                }
+            },
+        )?;
+
+        // Constrain: (1 - a) * a = 0
+        cs.enforce(
+            || "boolean constraint",
+            |lc| lc + CS::one() - var,
+            |lc| lc + var,
+            |lc| lc,
+        );
+
+        Ok(AllocatedBit {
+            variable: var,
+            value,
+        })
+    }
+
     /// Performs an XOR operation over the two operands, returning
     /// an `AllocatedBit`.
     pub fn xor<E, CS>(mut cs: CS, a: &Self, b: &Self) -> Result<Self, SynthesisError>
@@ -472,6 +510,88 @@ impl Boolean {
         }
     }
 
+    /// XOR over any number of operands. Constants and negations are folded into the
+    /// result, and a variable given twice cancels out, without any constraints. The
+    /// `n` variables left take `n - 1` constraints as a chain of XORs, or, if that is
+    /// fewer, `k + 2` as their sum decomposed into its lowest bit and the `k` bits of
+    /// the rest, which is the case from `n = 6` on.
+    pub fn xor_many<E, CS>(mut cs: CS, bits: &[Self]) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let mut negated = false;
+        let mut operands: Vec<&AllocatedBit> = vec![];
+        for bit in bits {
+            let operand = match *bit {
+                Boolean::Constant(c) => {
+                    negated ^= c;
+                    continue;
+                }
+                Boolean::Is(ref v) => v,
+                Boolean::Not(ref v) => {
+                    negated = !negated;
+                    v
+                }
+            };
+            match operands.iter().position(|o| o.variable == operand.variable) {
+                Some(i) => {
+                    operands.remove(i);
+                }
+                None => operands.push(operand),
+            }
+        }
+
+        let n = operands.len();
+        let mut k = 0;
+        while (n / 2) >> k != 0 {
+            k += 1;
+        }
+
+        let result = if n == 0 {
+            return Ok(Boolean::Constant(negated));
+        } else if n - 1 <= k + 2 {
+            let mut acc = operands[0].clone();
+            for (i, operand) in operands.iter().enumerate().skip(1) {
+                acc = AllocatedBit::xor(cs.namespace(|| format!("xor {}", i)), &acc, operand)?;
+            }
+            acc
+        } else {
+            let sum = operands
+                .iter()
+                .try_fold(0usize, |sum, o| o.value.map(|v| sum + v as usize));
+
+            // The operands, bits and so at most `n`, add up to the same number as
+            // these bits, so they do in the field as well.
+            let parity = AllocatedBit::alloc(cs.namespace(|| "parity"), sum.map(|s| s & 1 == 1))?;
+            let mut packed = LinearCombination::zero() + parity.variable;
+            let mut coeff = E::Fr::one();
+            for j in 1..=k {
+                coeff.double();
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("sum bit {}", j)),
+                    sum.map(|s| (s >> j) & 1 == 1),
+                )?;
+                packed = packed + (coeff, bit.variable);
+            }
+
+            cs.enforce(
+                || "sum packing",
+                |lc| lc,
+                |lc| lc,
+                |_| operands.iter().fold(packed, |lc, o| lc - o.variable),
+            );
+
+            parity
+        };
+
+        if negated {
+            Ok(Boolean::Not(result))
+        } else {
+            Ok(Boolean::Is(result))
+        }
+    }
+
     /// Perform AND over two boolean operands
     pub fn and<'a, E, CS>(cs: CS, a: &'a Self, b: &'a Self) -> Result<Self, SynthesisError>
     where
@@ -724,6 +844,104 @@ impl Boolean {
         }
         .into())
     }
+
+    /// Returns (b, a) if the condition is true, and (a, b) otherwise, like
+    /// [`AllocatedNum::conditionally_reverse`](super::num::AllocatedNum::conditionally_reverse).
+    /// Each result is `x + condition * (y - x)` for its `x` and the other `y`, which is
+    /// a boolean as `x` and `y` are, and both use the same condition, so they cannot be
+    /// chosen apart.
+    pub fn conditionally_reverse<E, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Self,
+    ) -> Result<(Self, Self), SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        match (a, b, condition) {
+            (_, _, &Boolean::Constant(false)) => return Ok((a.clone(), b.clone())),
+            (_, _, &Boolean::Constant(true)) => return Ok((b.clone(), a.clone())),
+            (&Boolean::Constant(x), &Boolean::Constant(y), _) if x == y => {
+                return Ok((a.clone(), b.clone()))
+            }
+            // (false, true) gives (condition, not condition), and (true, false) the
+            // negations.
+            (&Boolean::Constant(x), &Boolean::Constant(_), _) => {
+                let first = if x {
+                    condition.not()
+                } else {
+                    condition.clone()
+                };
+                let second = first.not();
+                return Ok((first, second));
+            }
+            _ => {}
+        }
+
+        let reversed = condition.get_value();
+        let result =
+            |cs: &mut CS, name: &'static str, x: &Self, y: &Self| -> Result<Self, SynthesisError> {
+                let value = match (x.get_value(), y.get_value(), reversed) {
+                    (Some(x), Some(y), Some(reversed)) => Some(if reversed { y } else { x }),
+                    _ => None,
+                };
+                let variable = cs.alloc(
+                    || format!("{} result", name),
+                    || {
+                        if *value.get()? {
+                            Ok(E::Fr::one())
+                        } else {
+                            Ok(E::Fr::zero())
+                        }
+                    },
+                )?;
+
+                // (y - x) * condition = result - x
+                cs.enforce(
+                    || format!("{} conditional reversal", name),
+                    |_| y.lc(CS::one(), E::Fr::one()) - &x.lc(CS::one(), E::Fr::one()),
+                    |_| condition.lc(CS::one(), E::Fr::one()),
+                    |lc| lc + variable - &x.lc(CS::one(), E::Fr::one()),
+                );
+
+                Ok(Boolean::from(AllocatedBit::from_constrained_variable(
+                    variable, value,
+                )))
+            };
+
+        let first = result(&mut cs, "first", a, b)?;
+        let second = result(&mut cs, "second", b, a)?;
+
+        Ok((first, second))
+    }
+}
+
+/// Returns (b, a) if the condition is true, and (a, b) otherwise, for bit vectors of
+/// the same length, with [`Boolean::conditionally_reverse`] on every pair of bits.
+pub fn conditionally_reverse_bits<E, CS>(
+    mut cs: CS,
+    a: &[Boolean],
+    b: &[Boolean],
+    condition: &Boolean,
+) -> Result<(Vec<Boolean>, Vec<Boolean>), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(a.len(), b.len());
+
+    let mut first = Vec::with_capacity(a.len());
+    let mut second = Vec::with_capacity(b.len());
+    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+        let (x, y) =
+            Boolean::conditionally_reverse(cs.namespace(|| format!("bit {}", i)), a, b, condition)?;
+        first.push(x);
+        second.push(y);
+    }
+
+    Ok((first, second))
 }
 
 impl From<AllocatedBit> for Boolean {
@@ -734,7 +952,10 @@ impl From<AllocatedBit> for Boolean {
 
 #[cfg(test)]
 mod test {
-    use super::{field_into_allocated_bits_le, u64_into_boolean_vec_le, AllocatedBit, Boolean};
+    use super::{
+        conditionally_reverse_bits, field_into_allocated_bits_le, u64_into_boolean_vec_le,
+        AllocatedBit, Boolean,
+    };
     use crate::bls::{Bls12, Fr};
     use crate::gadgets::test::*;
     use crate::ConstraintSystem;
@@ -1281,6 +1502,79 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_xor_many() {
+        for n in 0..=10 {
+            for &mask in &[0u32, 0b10_1101_1001, 0x3ff] {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let values = (0..n).map(|i| (mask >> i) & 1 == 1).collect::<Vec<_>>();
+                let mut bits = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &v)| {
+                        Boolean::from(
+                            AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(v))
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                // Constants and negations cost nothing.
+                let mut expected = values.iter().fold(false, |acc, &v| acc ^ v);
+                bits.push(Boolean::constant(true));
+                expected = !expected;
+                if n > 0 {
+                    bits[0] = bits[0].not();
+                    expected = !expected;
+                }
+
+                let before = cs.num_constraints();
+                let r = Boolean::xor_many(cs.namespace(|| "xor"), &bits).unwrap();
+                assert!(cs.is_satisfied());
+                assert_eq!(r.get_value(), Some(expected));
+
+                let mut k = 0;
+                while (n / 2) >> k != 0 {
+                    k += 1;
+                }
+                let chain = if n == 0 { 0 } else { n - 1 };
+                assert_eq!(cs.num_constraints() - before, std::cmp::min(chain, k + 2));
+
+                if n >= 6 {
+                    let parity = values.iter().fold(false, |acc, &v| acc ^ v);
+                    cs.set(
+                        "xor/parity/boolean",
+                        if parity { Fr::zero() } else { Fr::one() },
+                    );
+                    assert_eq!(cs.which_is_unsatisfied(), Some("xor/sum packing"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_xor_many_cancels() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let a = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "a"), Some(true)).unwrap());
+        let b = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "b"), Some(false)).unwrap());
+
+        // a ^ b ^ !a = !b
+        let r =
+            Boolean::xor_many(cs.namespace(|| "xor"), &[a.clone(), b.clone(), a.not()]).unwrap();
+        assert_eq!(r.get_value(), Some(true));
+        match (&r, &b) {
+            (Boolean::Not(ref r), Boolean::Is(ref b)) => {
+                assert!(r.get_variable() == b.get_variable())
+            }
+            _ => panic!("expected the negation of b"),
+        }
+        assert_eq!(cs.num_constraints(), 2);
+
+        let r = Boolean::xor_many(cs.namespace(|| "constants"), &[]).unwrap();
+        assert_eq!(r.get_value(), Some(false));
+        assert_eq!(cs.num_constraints(), 2);
+    }
+
     #[test]
     fn test_boolean_and() {
         let variants = [
@@ -1809,4 +2103,105 @@ mod test {
             assert!(cs.is_satisfied());
         }
     }
+
+    #[test]
+    fn test_conditionally_reverse_bits() {
+        let a_bits = [true, false, true, true, false, false];
+        let b_bits = [false, false, true, false, true, true];
+
+        for &reversed in &[false, true] {
+            for &constant in &[false, true] {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                // Allocated bits, with a constant pair of equal and one of different bits.
+                let mut a = a_bits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &b)| {
+                        Boolean::from(
+                            AllocatedBit::alloc(cs.namespace(|| format!("a {}", i)), Some(b))
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let mut b = b_bits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &b)| {
+                        Boolean::from(
+                            AllocatedBit::alloc(cs.namespace(|| format!("b {}", i)), Some(b))
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                a[1] = Boolean::constant(false);
+                b[1] = Boolean::constant(false);
+                a[4] = Boolean::constant(false);
+                b[4] = Boolean::constant(true);
+
+                let condition = if constant {
+                    Boolean::constant(reversed)
+                } else {
+                    AllocatedBit::alloc(cs.namespace(|| "condition"), Some(reversed))
+                        .unwrap()
+                        .into()
+                };
+                let before = cs.num_constraints();
+                let (c, d) =
+                    conditionally_reverse_bits(cs.namespace(|| "reverse"), &a, &b, &condition)
+                        .unwrap();
+
+                assert!(cs.is_satisfied());
+                let values = |bits: &[Boolean]| {
+                    bits.iter()
+                        .map(|b| b.get_value().unwrap())
+                        .collect::<Vec<_>>()
+                };
+                if reversed {
+                    assert_eq!(values(&c), b_bits);
+                    assert_eq!(values(&d), a_bits);
+                } else {
+                    assert_eq!(values(&c), a_bits);
+                    assert_eq!(values(&d), b_bits);
+                }
+
+                // Two constraints for each pair of allocated bits.
+                let expected = if constant { 0 } else { 2 * 4 };
+                assert_eq!(cs.num_constraints() - before, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_conditionally_reverse_bits_forgery() {
+        for &reversed in &[false, true] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "a"), Some(true)).unwrap());
+            let b = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "b"), Some(false)).unwrap());
+            let condition: Boolean =
+                AllocatedBit::alloc(cs.namespace(|| "condition"), Some(reversed))
+                    .unwrap()
+                    .into();
+            conditionally_reverse_bits(cs.namespace(|| "reverse"), &[a], &[b], &condition).unwrap();
+            assert!(cs.is_satisfied());
+
+            // Neither result can be swapped on its own, and the two results cannot be
+            // the same bit.
+            let (first, second) = if reversed {
+                (Fr::zero(), Fr::one())
+            } else {
+                (Fr::one(), Fr::zero())
+            };
+            cs.set("reverse/bit 0/second result", first);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("reverse/bit 0/second conditional reversal")
+            );
+            cs.set("reverse/bit 0/first result", second);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("reverse/bit 0/first conditional reversal")
+            );
+        }
+    }
 }
@@ -0,0 +1,302 @@
+//! Comparisons of numbers of a known number of bits.
+//!
+//! For `a` and `b` of `n` bits, `2^n + b - a` is positive and has `n + 1` bits, the
+//! top one of which is set exactly if `a <= b`. The comparisons decompose it into
+//! these bits, which takes `n + 2` constraints, and first check that `a` and `b` fit
+//! in `n` bits with [`assert_fits_in_bits`], `n + 1` constraints each, as a number
+//! that does not could be compared either way. All in all, a comparison costs
+//! `3n + 4` constraints, and an enforced one `3n + 3`.
+
+use ff::{Field, PrimeField, ScalarEngine};
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::num::{AllocatedNum, Num};
+use super::range::{assert_fits_in_bits, assert_num_fits_in_bits};
+
+/// Whether `a < b`, for `a` and `b` of at most `num_bits` bits, which is less than
+/// [`PrimeField::CAPACITY`].
+pub fn less_than<E, CS>(
+    cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    Ok(less_or_equal(cs, b, a, num_bits)?.not())
+}
+
+/// Whether `a <= b`, like [`less_than`].
+pub fn less_or_equal<E, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(num_bits < E::Fr::CAPACITY as usize);
+
+    assert_fits_in_bits(cs.namespace(|| "a range"), a, num_bits)?;
+    assert_fits_in_bits(cs.namespace(|| "b range"), b, num_bits)?;
+
+    let mut offset = E::Fr::one();
+    for _ in 0..num_bits {
+        offset.double();
+    }
+    let value = match (a.get_value(), b.get_value()) {
+        (Some(a), Some(b)) => {
+            let mut value = offset;
+            value.add_assign(&b);
+            value.sub_assign(&a);
+            Some(value.into_repr())
+        }
+        _ => None,
+    };
+
+    let mut bits = (0..=num_bits)
+        .map(|i| {
+            AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                value
+                    .as_ref()
+                    .map(|v| (v.as_ref()[i / 64] >> (i % 64)) & 1 == 1),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        packed = packed + (coeff, bit.get_variable());
+        coeff.double();
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |lc| lc,
+        |lc| lc,
+        |lc| lc + (offset, CS::one()) + b.get_variable() - a.get_variable() - &packed,
+    );
+
+    Ok(Boolean::from(bits.pop().unwrap()))
+}
+
+/// Whether `a > b`, like [`less_than`].
+pub fn greater_than<E, CS>(
+    cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    less_than(cs, b, a, num_bits)
+}
+
+/// Whether `a >= b`, like [`less_than`].
+pub fn greater_or_equal<E, CS>(
+    cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    less_or_equal(cs, b, a, num_bits)
+}
+
+/// Enforces `a < b`, for `a` and `b` of at most `num_bits` bits, by checking that
+/// `b - a - 1` fits in `num_bits` bits, which it does not if it wraps around.
+pub fn enforce_less_than<E, CS>(
+    cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    enforce_difference_fits(cs, a, b, minus_one, num_bits)
+}
+
+/// Enforces `a <= b`, like [`enforce_less_than`].
+pub fn enforce_less_or_equal<E, CS>(
+    cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    enforce_difference_fits(cs, a, b, E::Fr::zero(), num_bits)
+}
+
+/// Enforces that `b - a + offset` fits in `num_bits` bits, as well as `a` and `b`.
+fn enforce_difference_fits<E, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    offset: E::Fr,
+    num_bits: usize,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(num_bits < E::Fr::CAPACITY as usize);
+
+    assert_fits_in_bits(cs.namespace(|| "a range"), a, num_bits)?;
+    assert_fits_in_bits(cs.namespace(|| "b range"), b, num_bits)?;
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let difference = Num::from(b.clone())
+        .add(&Num::from(a.clone()).scale(minus_one))
+        .add(&Num::constant(CS::one(), offset));
+
+    assert_num_fits_in_bits(cs.namespace(|| "difference range"), &difference, num_bits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn fr(n: u64) -> Fr {
+        Fr::from_str(&n.to_string()).unwrap()
+    }
+
+    fn alloc_pair(
+        cs: &mut TestConstraintSystem<Bls12>,
+        a: Fr,
+        b: Fr,
+    ) -> (AllocatedNum<Bls12>, AllocatedNum<Bls12>) {
+        (
+            AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap(),
+            AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &num_bits in &[1, 2, 8, 32, 64] {
+            let mask = if num_bits == 64 {
+                u64::max_value()
+            } else {
+                (1 << num_bits) - 1
+            };
+
+            let mut pairs = (0..20)
+                .map(|_| (rng.next_u64() & mask, rng.next_u64() & mask))
+                .collect::<Vec<_>>();
+            // Equal numbers, and the extremes.
+            pairs.extend(&[(0, 0), (mask, mask), (0, mask), (mask, 0)]);
+            let x = rng.next_u64() & mask;
+            pairs.push((x, x));
+
+            for (a, b) in pairs {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let (a_num, b_num) = alloc_pair(&mut cs, fr(a), fr(b));
+
+                let lt = less_than(cs.namespace(|| "lt"), &a_num, &b_num, num_bits).unwrap();
+                let le = less_or_equal(cs.namespace(|| "le"), &a_num, &b_num, num_bits).unwrap();
+                let gt = greater_than(cs.namespace(|| "gt"), &a_num, &b_num, num_bits).unwrap();
+                let ge = greater_or_equal(cs.namespace(|| "ge"), &a_num, &b_num, num_bits).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(lt.get_value(), Some(a < b), "{} < {}", a, b);
+                assert_eq!(le.get_value(), Some(a <= b), "{} <= {}", a, b);
+                assert_eq!(gt.get_value(), Some(a > b), "{} > {}", a, b);
+                assert_eq!(ge.get_value(), Some(a >= b), "{} >= {}", a, b);
+                assert_eq!(cs.num_constraints(), 4 * (3 * num_bits + 4));
+            }
+        }
+    }
+
+    #[test]
+    fn test_enforced_comparisons() {
+        for &(a, b) in &[(3, 5), (5, 5), (5, 3), (0, 255), (255, 255)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let (a_num, b_num) = alloc_pair(&mut cs, fr(a), fr(b));
+            enforce_less_than(cs.namespace(|| "lt"), &a_num, &b_num, 8).unwrap();
+            assert_eq!(cs.is_satisfied(), a < b, "{} < {}", a, b);
+            assert_eq!(cs.num_constraints(), 3 * 8 + 3);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let (a_num, b_num) = alloc_pair(&mut cs, fr(a), fr(b));
+            enforce_less_or_equal(cs.namespace(|| "le"), &a_num, &b_num, 8).unwrap();
+            assert_eq!(cs.is_satisfied(), a <= b, "{} <= {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_comparison_out_of_range() {
+        // 256 does not fit in 8 bits, and neither does r - 1, which would otherwise
+        // compare as less than anything.
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+
+        for &a in &[fr(256), minus_one] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let (a_num, b_num) = alloc_pair(&mut cs, a, fr(3));
+            less_than(cs.namespace(|| "lt"), &a_num, &b_num, 8).unwrap();
+            assert!(!cs.is_satisfied());
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let (a_num, b_num) = alloc_pair(&mut cs, a, fr(3));
+            enforce_less_than(cs.namespace(|| "lt"), &a_num, &b_num, 8).unwrap();
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_forged_comparison() {
+        for &(a, b) in &[(3, 5), (5, 3), (4, 4)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let (a_num, b_num) = alloc_pair(&mut cs, fr(a), fr(b));
+            let lt = less_than(cs.namespace(|| "lt"), &a_num, &b_num, 8).unwrap();
+            assert!(cs.is_satisfied());
+
+            // The result is the negation of the top bit of 2^8 + a - b, and no other
+            // bits make up for flipping it.
+            let forged = if lt.get_value().unwrap() {
+                Fr::one()
+            } else {
+                Fr::zero()
+            };
+            cs.set("lt/bit 8/boolean", forged);
+            assert_eq!(cs.which_is_unsatisfied(), Some("lt/packing constraint"));
+
+            for i in 0..8 {
+                cs.set(&format!("lt/bit {}/boolean", i), Fr::one());
+            }
+            assert_eq!(cs.which_is_unsatisfied(), Some("lt/packing constraint"));
+        }
+    }
+}
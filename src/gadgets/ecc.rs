@@ -0,0 +1,919 @@
+//! Gadgets for points of a twisted Edwards curve over the scalar field, like Jubjub
+//! over the scalar field of BLS12-381.
+//!
+//! The curve `a * x^2 + y^2 = 1 + d * x^2 * y^2` is given by [`CurveParams`], which
+//! must describe a complete curve, with `a` a square and `d` a non-square, so that
+//! the addition formulas hold for all of its points, including the identity `(0, 1)`
+//! and the points of small order. Points are not checked to be in the prime-order
+//! subgroup unless [`EdwardsPoint::assert_prime_order`] is used, or their torsion
+//! component cleared with [`EdwardsPoint::mul_by_cofactor`].
+//!
+//! | Operation                      | Constraints                   |
+//! |--------------------------------|-------------------------------|
+//! | `alloc`                        | 3                             |
+//! | `add`                          | 6                             |
+//! | `double`                       | 5                             |
+//! | `conditionally_negate`         | 1                             |
+//! | `fixed_base_mul`, `n` bits     | at most `9 * ceil(n / 3) - 6` |
+//! | `mul`, `n` bits                | `13 * n - 11`                 |
+
+use ff::{BitIterator, Field, PrimeField, ScalarEngine};
+
+use super::boolean::Boolean;
+use super::lookup::lookup3_xy;
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The constants of a twisted Edwards curve `a * x^2 + y^2 = 1 + d * x^2 * y^2`
+/// with a subgroup of prime order `order` and index `cofactor`.
+#[derive(Clone, Debug)]
+pub struct CurveParams<F: PrimeField> {
+    pub a: F,
+    pub d: F,
+    pub cofactor: u64,
+    pub order: F::Repr,
+}
+
+impl CurveParams<crate::bls::Fr> {
+    /// The Jubjub curve of Zcash Sapling, over the scalar field of BLS12-381.
+    pub fn jubjub() -> Self {
+        let mut a = crate::bls::Fr::one();
+        a.negate();
+
+        // d = -(10240 / 10241)
+        let mut d = crate::bls::Fr::from_str("10241")
+            .unwrap()
+            .inverse()
+            .unwrap();
+        d.mul_assign(&crate::bls::Fr::from_str("10240").unwrap());
+        d.negate();
+
+        let order = crate::bls::Fr::from_str(
+            "6554484396890773809930967563523245729705921265872317281365359162392183254199",
+        )
+        .unwrap()
+        .into_repr();
+
+        CurveParams {
+            a,
+            d,
+            cofactor: 8,
+            order,
+        }
+    }
+}
+
+impl<F: PrimeField> CurveParams<F> {
+    /// Whether `p` is on the curve.
+    pub fn is_on_curve(&self, p: &(F, F)) -> bool {
+        let mut x2 = p.0;
+        x2.square();
+        let mut y2 = p.1;
+        y2.square();
+
+        let mut lhs = x2;
+        lhs.mul_assign(&self.a);
+        lhs.add_assign(&y2);
+
+        let mut rhs = x2;
+        rhs.mul_assign(&y2);
+        rhs.mul_assign(&self.d);
+        rhs.add_assign(&F::one());
+
+        lhs == rhs
+    }
+
+    /// The sum of the points `p` and `q`, outside of a circuit.
+    pub fn add(&self, p: &(F, F), q: &(F, F)) -> (F, F) {
+        // t = d * x1 * x2 * y1 * y2
+        let mut t = p.0;
+        t.mul_assign(&q.0);
+        t.mul_assign(&p.1);
+        t.mul_assign(&q.1);
+        t.mul_assign(&self.d);
+
+        // x3 = (x1 * y2 + y1 * x2) / (1 + t)
+        let mut x3 = p.0;
+        x3.mul_assign(&q.1);
+        let mut tmp = p.1;
+        tmp.mul_assign(&q.0);
+        x3.add_assign(&tmp);
+        let mut den = F::one();
+        den.add_assign(&t);
+        x3.mul_assign(&den.inverse().expect("the curve is complete"));
+
+        // y3 = (y1 * y2 - a * x1 * x2) / (1 - t)
+        let mut y3 = p.1;
+        y3.mul_assign(&q.1);
+        let mut tmp = p.0;
+        tmp.mul_assign(&q.0);
+        tmp.mul_assign(&self.a);
+        y3.sub_assign(&tmp);
+        let mut den = F::one();
+        den.sub_assign(&t);
+        y3.mul_assign(&den.inverse().expect("the curve is complete"));
+
+        (x3, y3)
+    }
+
+    /// The multiple of `p` by the scalar of the little-endian bits `scalar`, outside
+    /// of a circuit.
+    pub fn mul(&self, p: &(F, F), scalar: &[bool]) -> (F, F) {
+        let mut acc = (F::zero(), F::one());
+        for bit in scalar.iter().rev() {
+            acc = self.add(&acc, &acc);
+            if *bit {
+                acc = self.add(&acc, p);
+            }
+        }
+
+        acc
+    }
+}
+
+/// A point of the curve of some [`CurveParams`], which all operations on it must be
+/// given.
+pub struct EdwardsPoint<E: ScalarEngine> {
+    x: AllocatedNum<E>,
+    y: AllocatedNum<E>,
+}
+
+impl<E: ScalarEngine> Clone for EdwardsPoint<E> {
+    fn clone(&self) -> Self {
+        EdwardsPoint {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+impl<E: ScalarEngine> EdwardsPoint<E> {
+    pub fn get_x(&self) -> &AllocatedNum<E> {
+        &self.x
+    }
+
+    pub fn get_y(&self) -> &AllocatedNum<E> {
+        &self.y
+    }
+
+    pub fn get_value(&self) -> Option<(E::Fr, E::Fr)> {
+        Some((self.x.get_value()?, self.y.get_value()?))
+    }
+
+    /// Allocates the point `value`, enforcing that it is on the curve.
+    pub fn alloc<CS>(
+        mut cs: CS,
+        value: Option<(E::Fr, E::Fr)>,
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(value.get()?.0))?;
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(value.get()?.1))?;
+
+        let x2 = x.square(cs.namespace(|| "x^2"))?;
+        let y2 = y.square(cs.namespace(|| "y^2"))?;
+
+        // (d * x^2) * y^2 = a * x^2 + y^2 - 1
+        cs.enforce(
+            || "on curve",
+            |lc| lc + (params.d, x2.get_variable()),
+            |lc| lc + y2.get_variable(),
+            |lc| lc + (params.a, x2.get_variable()) + y2.get_variable() - CS::one(),
+        );
+
+        Ok(EdwardsPoint { x, y })
+    }
+
+    /// Exposes both coordinates of this point as public inputs.
+    pub fn inputize<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.x.inputize(cs.namespace(|| "x"))?;
+        self.y.inputize(cs.namespace(|| "y"))?;
+
+        Ok(())
+    }
+
+    /// The sum of this point and `other`.
+    pub fn add<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // A = x1 * y2
+        let a = self.x.mul(cs.namespace(|| "A"), &other.y)?;
+        // B = y1 * x2
+        let b = self.y.mul(cs.namespace(|| "B"), &other.x)?;
+
+        // U = (x1 + y1) * (y2 - a * x2) = y1 * y2 - a * x1 * x2 + A - a * B
+        let u = AllocatedNum::alloc(cs.namespace(|| "U"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.add_assign(self.y.get_value().get()?);
+
+            let mut t1 = *other.x.get_value().get()?;
+            t1.mul_assign(&params.a);
+            t1.negate();
+            t1.add_assign(other.y.get_value().get()?);
+
+            t0.mul_assign(&t1);
+            Ok(t0)
+        })?;
+        cs.enforce(
+            || "U computation",
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + other.y.get_variable() - (params.a, other.x.get_variable()),
+            |lc| lc + u.get_variable(),
+        );
+
+        // C = d * A * B
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            let mut t = *a.get_value().get()?;
+            t.mul_assign(b.get_value().get()?);
+            t.mul_assign(&params.d);
+            Ok(t)
+        })?;
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (params.d, a.get_variable()),
+            |lc| lc + b.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // x3 = (A + B) / (1 + C)
+        let x = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.add_assign(b.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.add_assign(c.get_value().get()?);
+
+            t1.inverse()
+                .map(|inv| {
+                    t0.mul_assign(&inv);
+                    t0
+                })
+                .ok_or(SynthesisError::DivisionByZero)
+        })?;
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + CS::one() + c.get_variable(),
+            |lc| lc + x.get_variable(),
+            |lc| lc + a.get_variable() + b.get_variable(),
+        );
+
+        // y3 = (U - A + a * B) / (1 - C)
+        let y = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            let mut t0 = *b.get_value().get()?;
+            t0.mul_assign(&params.a);
+            t0.add_assign(u.get_value().get()?);
+            t0.sub_assign(a.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.sub_assign(c.get_value().get()?);
+
+            t1.inverse()
+                .map(|inv| {
+                    t0.mul_assign(&inv);
+                    t0
+                })
+                .ok_or(SynthesisError::DivisionByZero)
+        })?;
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + CS::one() - c.get_variable(),
+            |lc| lc + y.get_variable(),
+            |lc| lc + u.get_variable() - a.get_variable() + (params.a, b.get_variable()),
+        );
+
+        Ok(EdwardsPoint { x, y })
+    }
+
+    /// Twice this point, with one constraint less than adding it to itself.
+    pub fn double<CS>(
+        &self,
+        mut cs: CS,
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        // A = x * y
+        let a = self.x.mul(cs.namespace(|| "A"), &self.y)?;
+
+        // U = (x + y) * (y - a * x) = y^2 - a * x^2 + A - a * A
+        let u = AllocatedNum::alloc(cs.namespace(|| "U"), || {
+            let mut t0 = *self.x.get_value().get()?;
+            t0.add_assign(self.y.get_value().get()?);
+
+            let mut t1 = *self.x.get_value().get()?;
+            t1.mul_assign(&params.a);
+            t1.negate();
+            t1.add_assign(self.y.get_value().get()?);
+
+            t0.mul_assign(&t1);
+            Ok(t0)
+        })?;
+        cs.enforce(
+            || "U computation",
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + self.y.get_variable() - (params.a, self.x.get_variable()),
+            |lc| lc + u.get_variable(),
+        );
+
+        // C = d * A^2
+        let c = AllocatedNum::alloc(cs.namespace(|| "C"), || {
+            let mut t = *a.get_value().get()?;
+            t.square();
+            t.mul_assign(&params.d);
+            Ok(t)
+        })?;
+        cs.enforce(
+            || "C computation",
+            |lc| lc + (params.d, a.get_variable()),
+            |lc| lc + a.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // x3 = 2 * A / (1 + C)
+        let x = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.double();
+
+            let mut t1 = E::Fr::one();
+            t1.add_assign(c.get_value().get()?);
+
+            t1.inverse()
+                .map(|inv| {
+                    t0.mul_assign(&inv);
+                    t0
+                })
+                .ok_or(SynthesisError::DivisionByZero)
+        })?;
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + CS::one() + c.get_variable(),
+            |lc| lc + x.get_variable(),
+            |lc| lc + a.get_variable() + a.get_variable(),
+        );
+
+        // y3 = (U - A + a * A) / (1 - C)
+        let y = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            let mut t0 = *a.get_value().get()?;
+            t0.mul_assign(&params.a);
+            t0.add_assign(u.get_value().get()?);
+            t0.sub_assign(a.get_value().get()?);
+
+            let mut t1 = E::Fr::one();
+            t1.sub_assign(c.get_value().get()?);
+
+            t1.inverse()
+                .map(|inv| {
+                    t0.mul_assign(&inv);
+                    t0
+                })
+                .ok_or(SynthesisError::DivisionByZero)
+        })?;
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + CS::one() - c.get_variable(),
+            |lc| lc + y.get_variable(),
+            |lc| lc + u.get_variable() - a.get_variable() + (params.a, a.get_variable()),
+        );
+
+        Ok(EdwardsPoint { x, y })
+    }
+
+    /// The negation `(-x, y)` of this point if `condition` is true, and this point
+    /// otherwise.
+    pub fn conditionally_negate<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+            let mut x = *self.x.get_value().get()?;
+            if *condition.get_value().get()? {
+                x.negate();
+            }
+            Ok(x)
+        })?;
+
+        // (x + x) * condition = x - x'
+        cs.enforce(
+            || "conditional negation",
+            |lc| lc + self.x.get_variable() + self.x.get_variable(),
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + self.x.get_variable() - x.get_variable(),
+        );
+
+        Ok(EdwardsPoint {
+            x,
+            y: self.y.clone(),
+        })
+    }
+
+    /// This point if `condition` is true, and the identity otherwise.
+    fn conditionally_select<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+            if *condition.get_value().get()? {
+                Ok(*self.x.get_value().get()?)
+            } else {
+                Ok(E::Fr::zero())
+            }
+        })?;
+
+        // x * condition = x'
+        cs.enforce(
+            || "x selection",
+            |lc| lc + self.x.get_variable(),
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + x.get_variable(),
+        );
+
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+            if *condition.get_value().get()? {
+                Ok(*self.y.get_value().get()?)
+            } else {
+                Ok(E::Fr::one())
+            }
+        })?;
+
+        // (y - 1) * condition = y' - 1
+        cs.enforce(
+            || "y selection",
+            |lc| lc + self.y.get_variable() - CS::one(),
+            |_| condition.lc(CS::one(), E::Fr::one()),
+            |lc| lc + y.get_variable() - CS::one(),
+        );
+
+        Ok(EdwardsPoint { x, y })
+    }
+
+    /// The multiple of the constant point `base` by the scalar of the little-endian
+    /// bits `scalar`, which must not be empty.
+    ///
+    /// The scalar is split into windows of 3 bits, and the multiple of the window of
+    /// `base` looked up in a table of 8 points with
+    /// [`lookup3_xy`](super::lookup::lookup3_xy), so only the sum of the windows
+    /// takes additions.
+    pub fn fixed_base_mul<CS>(
+        mut cs: CS,
+        base: &(E::Fr, E::Fr),
+        scalar: &[Boolean],
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(!scalar.is_empty(), "the scalar has no bits");
+
+        let mut window_base = *base;
+        let mut acc: Option<Self> = None;
+        for (i, window) in scalar.chunks(3).enumerate() {
+            let mut bits = window.to_vec();
+            bits.resize(3, Boolean::constant(false));
+
+            let mut table = Vec::with_capacity(8);
+            let mut entry = (E::Fr::zero(), E::Fr::one());
+            for _ in 0..8 {
+                table.push(entry);
+                entry = params.add(&entry, &window_base);
+            }
+            window_base = entry;
+
+            let (x, y) = lookup3_xy(cs.namespace(|| format!("window {}", i)), &bits, &table)?;
+            let point = EdwardsPoint { x, y };
+
+            acc = Some(match acc {
+                Some(acc) => acc.add(cs.namespace(|| format!("addition {}", i)), &point, params)?,
+                None => point,
+            });
+        }
+
+        Ok(acc.expect("the scalar is not empty"))
+    }
+
+    /// The multiple of this point by the scalar of the little-endian bits `scalar`,
+    /// which must not be empty, by doubling and adding.
+    pub fn mul<CS>(
+        &self,
+        mut cs: CS,
+        scalar: &[Boolean],
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(!scalar.is_empty(), "the scalar has no bits");
+
+        let mut base = self.clone();
+        let mut acc: Option<Self> = None;
+        for (i, bit) in scalar.iter().enumerate() {
+            if i > 0 {
+                base = base.double(cs.namespace(|| format!("doubling {}", i)), params)?;
+            }
+
+            let selected =
+                base.conditionally_select(cs.namespace(|| format!("selection {}", i)), bit)?;
+            acc = Some(match acc {
+                Some(acc) => acc.add(
+                    cs.namespace(|| format!("addition {}", i)),
+                    &selected,
+                    params,
+                )?,
+                None => selected,
+            });
+        }
+
+        Ok(acc.expect("the scalar is not empty"))
+    }
+
+    /// The multiple of this point by the constant with the big-endian bits `bits`,
+    /// which must have a set bit.
+    fn mul_constant<CS, I>(
+        &self,
+        mut cs: CS,
+        bits: I,
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+        I: IntoIterator<Item = bool>,
+    {
+        let mut acc: Option<Self> = None;
+        for (i, bit) in bits.into_iter().skip_while(|bit| !bit).enumerate() {
+            acc = Some(match acc {
+                Some(acc) => {
+                    let acc = acc.double(cs.namespace(|| format!("doubling {}", i)), params)?;
+                    if bit {
+                        acc.add(cs.namespace(|| format!("addition {}", i)), self, params)?
+                    } else {
+                        acc
+                    }
+                }
+                None => self.clone(),
+            });
+        }
+
+        Ok(acc.expect("the constant is not zero"))
+    }
+
+    /// The multiple of this point by the cofactor of the curve, which is in the
+    /// prime-order subgroup. Points of small order, including the identity, give the
+    /// identity.
+    pub fn mul_by_cofactor<CS>(
+        &self,
+        cs: CS,
+        params: &CurveParams<E::Fr>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.mul_constant(cs, BitIterator::new([params.cofactor]), params)
+    }
+
+    /// Enforces that this point has the prime order of the subgroup, by enforcing
+    /// that its multiple by the order is the identity, which leaves the identity and
+    /// the points of prime order, and that `x` is not zero, which leaves out the
+    /// identity.
+    pub fn assert_prime_order<CS>(
+        &self,
+        mut cs: CS,
+        params: &CurveParams<E::Fr>,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let multiple = self.mul_constant(
+            cs.namespace(|| "multiple by order"),
+            BitIterator::new(params.order),
+            params,
+        )?;
+
+        cs.enforce(
+            || "multiple x is zero",
+            |lc| lc + multiple.x.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc,
+        );
+        cs.enforce(
+            || "multiple y is one",
+            |lc| lc + multiple.y.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + CS::one(),
+        );
+
+        // Zero has no inverse, so this constraint can't be satisfied for it.
+        let inv = cs.alloc(
+            || "x inverse",
+            || {
+                Ok(self
+                    .x
+                    .get_value()
+                    .get()?
+                    .inverse()
+                    .unwrap_or_else(E::Fr::zero))
+            },
+        )?;
+        cs.enforce(
+            || "x is not zero",
+            |lc| lc + self.x.get_variable(),
+            |lc| lc + inv,
+            |lc| lc + CS::one(),
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::field_into_boolean_vec_le;
+    use crate::gadgets::test::*;
+    use ff::SqrtField;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    /// A random point of the curve, of any order.
+    fn random_point<R: RngCore>(params: &CurveParams<Fr>, rng: &mut R) -> (Fr, Fr) {
+        loop {
+            // x^2 = (1 - y^2) / (a - d * y^2)
+            let y = Fr::random(rng);
+            let mut y2 = y;
+            y2.square();
+
+            let mut num = Fr::one();
+            num.sub_assign(&y2);
+            let mut den = y2;
+            den.mul_assign(&params.d);
+            den.negate();
+            den.add_assign(&params.a);
+
+            if let Some(x) = den.inverse().and_then(|inv| {
+                num.mul_assign(&inv);
+                num.sqrt()
+            }) {
+                let p = (x, y);
+                assert!(params.is_on_curve(&p));
+                return p;
+            }
+        }
+    }
+
+    /// A random point of the prime-order subgroup.
+    fn random_subgroup_point<R: RngCore>(params: &CurveParams<Fr>, rng: &mut R) -> (Fr, Fr) {
+        loop {
+            let p = random_point(params, rng);
+            let p = params.mul(&p, &[false, false, false, true]);
+            if !p.0.is_zero() {
+                return p;
+            }
+        }
+    }
+
+    /// The points of order 2 and 4.
+    fn small_order_points() -> Vec<(Fr, Fr)> {
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        // (+-sqrt(1 / a), 0) with a = -1
+        let i = minus_one.sqrt().unwrap();
+        let mut minus_i = i;
+        minus_i.negate();
+
+        vec![
+            (Fr::zero(), minus_one),
+            (i, Fr::zero()),
+            (minus_i, Fr::zero()),
+        ]
+    }
+
+    fn scalar_bits(bits: &[Boolean]) -> Vec<bool> {
+        bits.iter().map(|b| b.get_value().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_edwards_alloc() {
+        let params = CurveParams::jubjub();
+        let mut rng = rng();
+
+        let identity = (Fr::zero(), Fr::one());
+        let mut points = vec![identity, random_point(&params, &mut rng)];
+        points.extend(small_order_points());
+        for p in points {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let point = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(p), &params).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 3);
+            assert_eq!(point.get_value(), Some(p));
+        }
+
+        let (x, mut y) = random_point(&params, &mut rng);
+        y.add_assign(&Fr::one());
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        EdwardsPoint::alloc(cs.namespace(|| "p"), Some((x, y)), &params).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("p/on curve"));
+    }
+
+    #[test]
+    fn test_edwards_add_double() {
+        let params = CurveParams::jubjub();
+        let mut rng = rng();
+
+        let identity = (Fr::zero(), Fr::one());
+        let mut points = vec![identity];
+        points.extend(small_order_points());
+        for _ in 0..10 {
+            points.push(random_point(&params, &mut rng));
+        }
+
+        for p in &points {
+            for q in &points {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let a = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(*p), &params).unwrap();
+                let b = EdwardsPoint::alloc(cs.namespace(|| "q"), Some(*q), &params).unwrap();
+
+                let sum = a.add(cs.namespace(|| "add"), &b, &params).unwrap();
+                assert_eq!(sum.get_value(), Some(params.add(p, q)));
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints(), 6 + 6);
+            }
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(*p), &params).unwrap();
+            let doubled = a.double(cs.namespace(|| "double"), &params).unwrap();
+            assert_eq!(doubled.get_value(), Some(params.add(p, p)));
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 3 + 5);
+        }
+    }
+
+    #[test]
+    fn test_edwards_conditionally_negate() {
+        let params = CurveParams::jubjub();
+        let mut rng = rng();
+
+        let p = random_point(&params, &mut rng);
+        let mut minus_x = p.0;
+        minus_x.negate();
+
+        for &(condition, expected) in &[(false, p), (true, (minus_x, p.1))] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(p), &params).unwrap();
+            let negated = a
+                .conditionally_negate(cs.namespace(|| "negate"), &Boolean::constant(condition))
+                .unwrap();
+            assert_eq!(negated.get_value(), Some(expected));
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 3 + 1);
+
+            // The sum with the negation is the identity.
+            if condition {
+                let sum = a.add(cs.namespace(|| "add"), &negated, &params).unwrap();
+                assert_eq!(sum.get_value(), Some((Fr::zero(), Fr::one())));
+            }
+        }
+    }
+
+    #[test]
+    fn test_edwards_fixed_base_mul() {
+        let params = CurveParams::jubjub();
+        let mut rng = rng();
+
+        for _ in 0..5 {
+            let base = random_subgroup_point(&params, &mut rng);
+            let scalar = Fr::random(&mut rng);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let bits = field_into_boolean_vec_le(cs.namespace(|| "scalar"), Some(scalar)).unwrap();
+            let num_bits = cs.num_constraints();
+            let product =
+                EdwardsPoint::fixed_base_mul(cs.namespace(|| "mul"), &base, &bits, &params)
+                    .unwrap();
+
+            assert_eq!(
+                product.get_value(),
+                Some(params.mul(&base, &scalar_bits(&bits)))
+            );
+            assert!(cs.is_satisfied());
+            let windows = (bits.len() + 2) / 3;
+            assert_eq!(cs.num_constraints() - num_bits, 9 * windows - 6);
+        }
+
+        // A zero scalar gives the identity.
+        let base = random_subgroup_point(&params, &mut rng);
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let bits = vec![Boolean::constant(false); 4];
+        let product =
+            EdwardsPoint::fixed_base_mul(cs.namespace(|| "mul"), &base, &bits, &params).unwrap();
+        assert_eq!(product.get_value(), Some((Fr::zero(), Fr::one())));
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_edwards_mul() {
+        let params = CurveParams::jubjub();
+        let mut rng = rng();
+
+        let identity = (Fr::zero(), Fr::one());
+        let mut bases = vec![identity, random_point(&params, &mut rng)];
+        bases.extend(small_order_points());
+
+        for base in bases {
+            let scalar = Fr::random(&mut rng);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let p = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(base), &params).unwrap();
+            let bits = field_into_boolean_vec_le(cs.namespace(|| "scalar"), Some(scalar)).unwrap();
+            let num_before = cs.num_constraints();
+            let product = p.mul(cs.namespace(|| "mul"), &bits, &params).unwrap();
+
+            assert_eq!(
+                product.get_value(),
+                Some(params.mul(&base, &scalar_bits(&bits)))
+            );
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints() - num_before, 13 * bits.len() - 11);
+        }
+    }
+
+    #[test]
+    fn test_edwards_subgroup() {
+        let params = CurveParams::jubjub();
+        let mut rng = rng();
+
+        let identity = (Fr::zero(), Fr::one());
+        let g = random_subgroup_point(&params, &mut rng);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let p = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(g), &params).unwrap();
+        p.assert_prime_order(cs.namespace(|| "order"), &params)
+            .unwrap();
+        assert!(cs.is_satisfied());
+
+        // Clearing the cofactor of a point of prime order multiplies it by 8.
+        let cleared = p
+            .mul_by_cofactor(cs.namespace(|| "cofactor"), &params)
+            .unwrap();
+        assert_eq!(
+            cleared.get_value(),
+            Some(params.mul(&g, &[false, false, false, true]))
+        );
+        assert!(cs.is_satisfied());
+
+        // The identity is in the subgroup, but does not have prime order.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let p = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(identity), &params).unwrap();
+        p.assert_prime_order(cs.namespace(|| "order"), &params)
+            .unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("order/x is not zero"));
+
+        for t in small_order_points() {
+            // Points of small order, alone or added to one of prime order, are not
+            // of prime order.
+            for q in &[t, params.add(&g, &t)] {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let p = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(*q), &params).unwrap();
+                p.assert_prime_order(cs.namespace(|| "order"), &params)
+                    .unwrap();
+                assert!(!cs.is_satisfied());
+            }
+
+            // Their torsion component is cleared by the cofactor.
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let p = EdwardsPoint::alloc(cs.namespace(|| "p"), Some(t), &params).unwrap();
+            let cleared = p
+                .mul_by_cofactor(cs.namespace(|| "cofactor"), &params)
+                .unwrap();
+            assert_eq!(cleared.get_value(), Some(identity));
+            assert!(cs.is_satisfied());
+        }
+    }
+}
@@ -0,0 +1,542 @@
+//! Verification of Schnorr signatures over a twisted Edwards curve, like EdDSA.
+//!
+//! A signature of the message `M` under the public key `A = [a]B` is a point
+//! `R = [k]B` and the scalar `s = k + c * a` modulo the order of `B`, for the challenge
+//! `c = H(R, A, M)`. It is valid if `R + [c]A = [s]B`, and [`verify`] returns whether
+//! it is rather than enforcing it, so that a circuit can combine the results of
+//! several signatures.
+//!
+//! A key or `R` of small order makes the equation hold for other messages or keys,
+//! so they are rejected, as is an `s` that is not less than the order, which would
+//! make another valid encoding of the same signature. The challenge is computed by a
+//! [`ChallengeHasher`], which is [`Blake2sChallenge`] or [`PoseidonChallenge`] or
+//! another in-circuit hash that a signer computes the same way.
+
+use ff::{BitIterator, PrimeField};
+
+use super::blake2s::blake2s;
+use super::boolean::Boolean;
+use super::ecc::{CurveParams, EdwardsPoint};
+use super::multipack::pack_bits;
+use super::poseidon::poseidon_hash;
+use crate::bls::Engine;
+use crate::poseidon::PoseidonConstants;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The curve of the signatures and the base point `B`, which must have the prime
+/// order of the subgroup.
+#[derive(Clone, Debug)]
+pub struct EddsaParams<F: PrimeField> {
+    pub curve: CurveParams<F>,
+    pub generator: (F, F),
+}
+
+/// An in-circuit hash of the challenge `H(R, A, M)`.
+pub trait ChallengeHasher<E: Engine> {
+    /// The little-endian bits of the challenge for the point `r` of the signature,
+    /// the public key `pk` and the message `msg_bits`.
+    fn challenge<CS>(
+        &self,
+        cs: CS,
+        r: &EdwardsPoint<E>,
+        pk: &EdwardsPoint<E>,
+        msg_bits: &[Boolean],
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>;
+}
+
+/// The challenge as the BLAKE2s hash, with `personalization`, of the coordinates
+/// `x` and `y` of `R` and then of `A`, each as the little-endian bytes of its
+/// representation, followed by the message, whose bits must be whole bytes with the
+/// least significant bit first.
+#[derive(Clone, Debug)]
+pub struct Blake2sChallenge {
+    pub personalization: [u8; 8],
+}
+
+impl<E: Engine> ChallengeHasher<E> for Blake2sChallenge {
+    fn challenge<CS>(
+        &self,
+        mut cs: CS,
+        r: &EdwardsPoint<E>,
+        pk: &EdwardsPoint<E>,
+        msg_bits: &[Boolean],
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let coordinate_bits = (E::Fr::NUM_BITS as usize + 7) / 8 * 8;
+
+        let mut input = vec![];
+        for (name, coordinate) in &[
+            ("R x", r.get_x()),
+            ("R y", r.get_y()),
+            ("A x", pk.get_x()),
+            ("A y", pk.get_y()),
+        ] {
+            let mut bits = coordinate.to_bits_le_strict(cs.namespace(|| *name))?;
+            bits.resize(coordinate_bits, Boolean::constant(false));
+            input.extend(bits);
+        }
+        input.extend(msg_bits.iter().cloned());
+
+        blake2s(cs.namespace(|| "hash"), &input, &self.personalization)
+    }
+}
+
+/// The challenge as the bits of the [`poseidon_hash`] of the coordinates `x` and `y`
+/// of `R` and then of `A`, followed by the message packed like
+/// [`compute_multipacking`](super::multipack::compute_multipacking).
+pub struct PoseidonChallenge<E: Engine> {
+    pub constants: PoseidonConstants<E>,
+}
+
+impl<E: Engine> ChallengeHasher<E> for PoseidonChallenge<E> {
+    fn challenge<CS>(
+        &self,
+        mut cs: CS,
+        r: &EdwardsPoint<E>,
+        pk: &EdwardsPoint<E>,
+        msg_bits: &[Boolean],
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut preimage = vec![
+            r.get_x().clone(),
+            r.get_y().clone(),
+            pk.get_x().clone(),
+            pk.get_y().clone(),
+        ];
+        for (i, chunk) in msg_bits.chunks(E::Fr::CAPACITY as usize).enumerate() {
+            preimage.push(pack_bits(cs.namespace(|| format!("message {}", i)), chunk)?);
+        }
+
+        let hash = poseidon_hash(cs.namespace(|| "hash"), &preimage, &self.constants)?;
+
+        hash.to_bits_le_strict(cs.namespace(|| "hash bits"))
+    }
+}
+
+/// Whether `sig_r` and the little-endian bits `sig_s_bits` are a valid signature of
+/// `msg_bits` under `pk`, with the challenge of `hasher`. It is not if `sig_r` or
+/// `pk` has small order, or if `s` is not less than the order of the subgroup.
+pub fn verify<E, CS, H>(
+    mut cs: CS,
+    params: &EddsaParams<E::Fr>,
+    pk: &EdwardsPoint<E>,
+    msg_bits: &[Boolean],
+    sig_r: &EdwardsPoint<E>,
+    sig_s_bits: &[Boolean],
+    hasher: &H,
+) -> Result<Boolean, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    H: ChallengeHasher<E>,
+{
+    let curve = &params.curve;
+
+    // The cofactor takes exactly the points of small order to the identity, the
+    // only point it gives with `x = 0`.
+    let r_small = sig_r
+        .mul_by_cofactor(cs.namespace(|| "R cofactor"), curve)?
+        .get_x()
+        .is_zero(cs.namespace(|| "R small order"))?;
+    let pk_small = pk
+        .mul_by_cofactor(cs.namespace(|| "A cofactor"), curve)?
+        .get_x()
+        .is_zero(cs.namespace(|| "A small order"))?;
+
+    let s_in_range = less_than_constant(cs.namespace(|| "s range"), sig_s_bits, curve.order)?;
+
+    let c = hasher.challenge(cs.namespace(|| "challenge"), sig_r, pk, msg_bits)?;
+
+    let lhs = EdwardsPoint::fixed_base_mul(
+        cs.namespace(|| "[s]B"),
+        &params.generator,
+        sig_s_bits,
+        curve,
+    )?;
+    let rhs = pk.mul(cs.namespace(|| "[c]A"), &c, curve)?.add(
+        cs.namespace(|| "R + [c]A"),
+        sig_r,
+        curve,
+    )?;
+
+    let x_equal = lhs
+        .get_x()
+        .equals(cs.namespace(|| "x equal"), rhs.get_x())?;
+    let y_equal = lhs
+        .get_y()
+        .equals(cs.namespace(|| "y equal"), rhs.get_y())?;
+
+    let mut valid = Boolean::and(cs.namespace(|| "equal"), &x_equal, &y_equal)?;
+    for (name, check) in &[
+        ("s in range", s_in_range),
+        ("R not small", r_small.not()),
+        ("A not small", pk_small.not()),
+    ] {
+        valid = Boolean::and(cs.namespace(|| *name), &valid, check)?;
+    }
+
+    Ok(valid)
+}
+
+/// Whether the number of the little-endian bits `bits` is less than the constant
+/// `c`, comparing them from the most significant bit.
+fn less_than_constant<E, CS, R>(
+    mut cs: CS,
+    bits: &[Boolean],
+    c: R,
+) -> Result<Boolean, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    R: AsRef<[u64]>,
+{
+    let mut c_bits = BitIterator::new(c).collect::<Vec<_>>();
+    c_bits.reverse();
+
+    // Whether the bits above the current one are less than, and equal to, those of
+    // `c`. More bits than `c` has are compared against zeros, and more bits of `c`
+    // against zeros of the number.
+    let mut less = Boolean::constant(false);
+    let mut equal = Boolean::constant(true);
+    for i in (0..bits.len().max(c_bits.len())).rev() {
+        let bit = bits
+            .get(i)
+            .cloned()
+            .unwrap_or_else(|| Boolean::constant(false));
+        let mut cs = cs.namespace(|| format!("bit {}", i));
+
+        if c_bits.get(i).cloned().unwrap_or(false) {
+            let below = Boolean::and(cs.namespace(|| "below"), &equal, &bit.not())?;
+            less = Boolean::and(cs.namespace(|| "less"), &less.not(), &below.not())?.not();
+            equal = Boolean::and(cs.namespace(|| "equal"), &equal, &bit)?;
+        } else {
+            equal = Boolean::and(cs.namespace(|| "equal"), &equal, &bit.not())?;
+        }
+    }
+
+    Ok(less)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::multipack::{bytes_to_bits_le, compute_multipacking};
+    use crate::gadgets::poseidon::POSEIDON_HASH_DOMAIN;
+    use crate::gadgets::test::*;
+    use crate::poseidon::domain_tag;
+    use blake2s_simd::Params as Blake2sParams;
+    use ff::{Field, PrimeFieldRepr, SqrtField};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    type Scalar = [u64; 4];
+
+    fn scalar_add(a: &Scalar, b: &Scalar) -> Scalar {
+        let mut sum = [0; 4];
+        let mut carry = 0;
+        for i in 0..4 {
+            let s = u128::from(a[i]) + u128::from(b[i]) + carry;
+            sum[i] = s as u64;
+            carry = s >> 64;
+        }
+        assert_eq!(carry, 0);
+        sum
+    }
+
+    fn scalar_sub(a: &Scalar, b: &Scalar) -> Scalar {
+        let mut diff = [0; 4];
+        let mut borrow = 0;
+        for i in 0..4 {
+            let d = i128::from(a[i]) - i128::from(b[i]) - borrow;
+            diff[i] = d as u64;
+            borrow = if d < 0 { 1 } else { 0 };
+        }
+        assert_eq!(borrow, 0);
+        diff
+    }
+
+    fn scalar_geq(a: &Scalar, b: &Scalar) -> bool {
+        a.iter().rev().cmp(b.iter().rev()) != std::cmp::Ordering::Less
+    }
+
+    fn scalar_add_mod(a: &Scalar, b: &Scalar, r: &Scalar) -> Scalar {
+        let sum = scalar_add(a, b);
+        if scalar_geq(&sum, r) {
+            scalar_sub(&sum, r)
+        } else {
+            sum
+        }
+    }
+
+    /// `k + c * a` modulo `r`, for `k` and `a` less than `r`.
+    fn scalar_mul_add_mod(k: &Scalar, c: &[bool], a: &Scalar, r: &Scalar) -> Scalar {
+        let mut acc = [0; 4];
+        for bit in c.iter().rev() {
+            acc = scalar_add_mod(&acc, &acc, r);
+            if *bit {
+                acc = scalar_add_mod(&acc, a, r);
+            }
+        }
+        scalar_add_mod(&acc, k, r)
+    }
+
+    fn scalar_bits(s: &Scalar) -> Vec<bool> {
+        (0..256).map(|i| (s[i / 64] >> (i % 64)) & 1 == 1).collect()
+    }
+
+    /// A random scalar less than `r`.
+    fn random_scalar<R: RngCore>(r: &Scalar, rng: &mut R) -> Scalar {
+        loop {
+            let mut s = [
+                rng.next_u64(),
+                rng.next_u64(),
+                rng.next_u64(),
+                rng.next_u64(),
+            ];
+            s[3] &= (1 << 60) - 1;
+            if !scalar_geq(&s, r) {
+                return s;
+            }
+        }
+    }
+
+    /// A random point of the prime-order subgroup.
+    fn random_generator<R: RngCore>(curve: &CurveParams<Fr>, rng: &mut R) -> (Fr, Fr) {
+        loop {
+            let y = Fr::random(rng);
+            let mut y2 = y;
+            y2.square();
+
+            let mut num = Fr::one();
+            num.sub_assign(&y2);
+            let mut den = y2;
+            den.mul_assign(&curve.d);
+            den.negate();
+            den.add_assign(&curve.a);
+
+            let x = den.inverse().and_then(|inv| {
+                num.mul_assign(&inv);
+                num.sqrt()
+            });
+            if let Some(x) = x {
+                let p = curve.mul(&(x, y), &[false, false, false, true]);
+                if !p.0.is_zero() {
+                    return p;
+                }
+            }
+        }
+    }
+
+    fn params<R: RngCore>(rng: &mut R) -> EddsaParams<Fr> {
+        let curve = CurveParams::jubjub();
+        let generator = random_generator(&curve, rng);
+
+        EddsaParams { curve, generator }
+    }
+
+    fn scalar_of_repr(words: &[u64]) -> Scalar {
+        let mut s = [0; 4];
+        s.copy_from_slice(words);
+        s
+    }
+
+    fn order(params: &EddsaParams<Fr>) -> Scalar {
+        scalar_of_repr(params.curve.order.as_ref())
+    }
+
+    fn blake2s_challenge(r: &(Fr, Fr), pk: &(Fr, Fr), msg: &[u8]) -> Vec<bool> {
+        let mut h = Blake2sParams::new()
+            .hash_length(32)
+            .personal(b"EdDSAsig")
+            .to_state();
+        for coordinate in &[r.0, r.1, pk.0, pk.1] {
+            let mut bytes = vec![];
+            coordinate.into_repr().write_le(&mut bytes).unwrap();
+            h.update(&bytes);
+        }
+        h.update(msg);
+
+        bytes_to_bits_le(h.finalize().as_ref())
+    }
+
+    fn poseidon_challenge(
+        constants: &PoseidonConstants<Bls12>,
+        r: &(Fr, Fr),
+        pk: &(Fr, Fr),
+        msg: &[bool],
+    ) -> Vec<bool> {
+        let mut preimage = vec![r.0, r.1, pk.0, pk.1];
+        preimage.extend(compute_multipacking::<Bls12>(msg));
+        let tag = domain_tag::<Bls12>(POSEIDON_HASH_DOMAIN, preimage.len());
+
+        let hash = constants.hash(tag, &preimage).into_repr();
+        scalar_bits(&scalar_of_repr(hash.as_ref()))[..Fr::NUM_BITS as usize].to_vec()
+    }
+
+    /// A signature `(R, s)` with the nonce `k`.
+    fn sign<C>(params: &EddsaParams<Fr>, a: &Scalar, k: &Scalar, challenge: C) -> ((Fr, Fr), Scalar)
+    where
+        C: FnOnce(&(Fr, Fr), &(Fr, Fr)) -> Vec<bool>,
+    {
+        let curve = &params.curve;
+        let pk = curve.mul(&params.generator, &scalar_bits(a));
+        let r = curve.mul(&params.generator, &scalar_bits(k));
+        let c = challenge(&r, &pk);
+
+        (r, scalar_mul_add_mod(k, &c, a, &order(params)))
+    }
+
+    fn alloc_bits(cs: &mut TestConstraintSystem<Bls12>, name: &str, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("{} {}", name, i)), Some(*b))
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Verifies the signature in a new constraint system, which must be satisfied.
+    fn verify_signature<H: ChallengeHasher<Bls12>>(
+        params: &EddsaParams<Fr>,
+        pk: &(Fr, Fr),
+        msg: &[bool],
+        r: &(Fr, Fr),
+        s: &[bool],
+        hasher: &H,
+    ) -> bool {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let pk = EdwardsPoint::alloc(cs.namespace(|| "pk"), Some(*pk), &params.curve).unwrap();
+        let r = EdwardsPoint::alloc(cs.namespace(|| "R"), Some(*r), &params.curve).unwrap();
+        let msg = alloc_bits(&mut cs, "msg", msg);
+        let s = alloc_bits(&mut cs, "s", s);
+
+        let valid = verify(cs.namespace(|| "verify"), params, &pk, &msg, &r, &s, hasher).unwrap();
+        assert!(cs.is_satisfied());
+
+        valid.get_value().unwrap()
+    }
+
+    #[test]
+    fn test_eddsa_blake2s() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = params(&mut rng);
+        let order = order(&params);
+        let hasher = Blake2sChallenge {
+            personalization: *b"EdDSAsig",
+        };
+
+        let msg = b"delegate proving";
+        let msg_bits = bytes_to_bits_le(msg);
+        let a = random_scalar(&order, &mut rng);
+        let k = random_scalar(&order, &mut rng);
+        let pk = params.curve.mul(&params.generator, &scalar_bits(&a));
+        let (r, s) = sign(&params, &a, &k, |r, pk| blake2s_challenge(r, pk, msg));
+        let s_bits = scalar_bits(&s);
+
+        assert!(verify_signature(
+            &params, &pk, &msg_bits, &r, &s_bits, &hasher
+        ));
+
+        // A flipped bit of the signature or the message.
+        let mut flipped = s_bits.clone();
+        flipped[3] = !flipped[3];
+        assert!(!verify_signature(
+            &params, &pk, &msg_bits, &r, &flipped, &hasher
+        ));
+        let mut flipped = msg_bits.clone();
+        flipped[0] = !flipped[0];
+        assert!(!verify_signature(
+            &params, &pk, &flipped, &r, &s_bits, &hasher
+        ));
+
+        // `s + r` satisfies the equation, but is out of range.
+        let s_plus_order = scalar_bits(&scalar_add(&s, &order));
+        assert!(!verify_signature(
+            &params,
+            &pk,
+            &msg_bits,
+            &r,
+            &s_plus_order,
+            &hasher
+        ));
+
+        // The identity as the key satisfies the equation for `R = [s]B` and any message.
+        let identity = (Fr::zero(), Fr::one());
+        let (r, s) = sign(&params, &[0; 4], &k, |r, pk| blake2s_challenge(r, pk, msg));
+        assert_eq!(s, k);
+        assert!(!verify_signature(
+            &params,
+            &identity,
+            &msg_bits,
+            &r,
+            &scalar_bits(&s),
+            &hasher
+        ));
+
+        // The identity as `R` satisfies it for `s = c * a`.
+        let c = blake2s_challenge(&identity, &pk, msg);
+        let s = scalar_mul_add_mod(&[0; 4], &c, &a, &order);
+        assert!(!verify_signature(
+            &params,
+            &pk,
+            &msg_bits,
+            &identity,
+            &scalar_bits(&s),
+            &hasher
+        ));
+    }
+
+    #[test]
+    fn test_eddsa_poseidon() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = params(&mut rng);
+        let order = order(&params);
+        let hasher = PoseidonChallenge {
+            constants: PoseidonConstants::<Bls12>::new(8),
+        };
+
+        let msg_bits = (0..300)
+            .map(|_| rng.next_u32() % 2 == 1)
+            .collect::<Vec<_>>();
+        let a = random_scalar(&order, &mut rng);
+        let k = random_scalar(&order, &mut rng);
+        let pk = params.curve.mul(&params.generator, &scalar_bits(&a));
+        let (r, s) = sign(&params, &a, &k, |r, pk| {
+            poseidon_challenge(&hasher.constants, r, pk, &msg_bits)
+        });
+        let s_bits = scalar_bits(&s);
+
+        assert!(verify_signature(
+            &params, &pk, &msg_bits, &r, &s_bits, &hasher
+        ));
+
+        let mut flipped = s_bits.clone();
+        flipped[0] = !flipped[0];
+        assert!(!verify_signature(
+            &params, &pk, &msg_bits, &r, &flipped, &hasher
+        ));
+        let mut flipped = msg_bits.clone();
+        flipped[299] = !flipped[299];
+        assert!(!verify_signature(
+            &params, &pk, &flipped, &r, &s_bits, &hasher
+        ));
+    }
+}
@@ -0,0 +1,318 @@
+//! The [Keccak-256] hash function, as used by Ethereum: Keccak-f[1600] with a
+//! rate of 1088 bits and the original Keccak padding, whose first padding byte is
+//! `0x01`, rather than the `0x06` of SHA3-256.
+//!
+//! The input is a sequence of bytes, each given as 8 bits with the least
+//! significant bit first, like the input of [`blake2s`](super::blake2s::blake2s).
+//! The digest is returned in the same order.
+//!
+//! A permutation costs 153,600 constraints, or fewer for the first block, whose
+//! capacity is constant. Absorbing allocated input into a later block costs one
+//! constraint per bit, and constant input costs nothing.
+//!
+//! [Keccak-256]: https://keccak.team/keccak_specs_summary.html
+
+use super::{boolean::Boolean, uint64::UInt64};
+use crate::{ConstraintSystem, SynthesisError};
+use ff::ScalarEngine;
+
+/// The number of bits absorbed per permutation, `1600 - 2 * 256`.
+const RATE: usize = 1088;
+
+const ROUNDS: usize = 24;
+
+/*
+   The round constants of the iota step, XORed into lane (0, 0).
+*/
+
+const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808A,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808B,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008A,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000A,
+    0x0000_0000_8000_808B,
+    0x8000_0000_0000_008B,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800A,
+    0x8000_0000_8000_000A,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+/*
+   The left rotations of the rho step, of lane (x, y) at x + 5 * y.
+*/
+
+#[rustfmt::skip]
+const RHO: [usize; 25] = [
+     0,  1, 62, 28, 27,
+    36, 44,  6, 55, 20,
+     3, 10, 43, 25, 39,
+    41, 45, 15, 21,  8,
+    18,  2, 61, 56, 14,
+];
+
+fn rotl(lane: &UInt64, by: usize) -> UInt64 {
+    lane.rotr(64 - by)
+}
+
+/// `a ^ (!b & c)`, bit by bit.
+fn chi<E, CS>(mut cs: CS, a: &UInt64, b: &UInt64, c: &UInt64) -> Result<UInt64, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let bits = a
+        .clone()
+        .into_bits()
+        .iter()
+        .zip(b.clone().into_bits())
+        .zip(c.clone().into_bits())
+        .enumerate()
+        .map(|(i, ((a, b), c))| {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            let and = Boolean::and(cs.namespace(|| "not b and c"), &b.not(), &c)?;
+            Boolean::xor(cs.namespace(|| "a xor"), a, &and)
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    Ok(UInt64::from_bits(&bits))
+}
+
+/// The Keccak-f[1600] permutation of the 25 lanes of `state`, lane (x, y) at
+/// x + 5 * y.
+fn keccak_f1600<E, CS>(mut cs: CS, mut a: Vec<UInt64>) -> Result<Vec<UInt64>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(a.len(), 25);
+
+    for (round, &round_constant) in ROUND_CONSTANTS.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        // theta
+        let mut c = Vec::with_capacity(5);
+        for x in 0..5 {
+            let mut column = a[x].clone();
+            for y in 1..5 {
+                column = column.xor(cs.namespace(|| format!("c {} {}", x, y)), &a[x + 5 * y])?;
+            }
+            c.push(column);
+        }
+        let mut d = Vec::with_capacity(5);
+        for x in 0..5 {
+            d.push(c[(x + 4) % 5].xor(
+                cs.namespace(|| format!("d {}", x)),
+                &rotl(&c[(x + 1) % 5], 1),
+            )?);
+        }
+        for (i, lane) in a.iter_mut().enumerate() {
+            *lane = lane.xor(cs.namespace(|| format!("theta {}", i)), &d[i % 5])?;
+        }
+
+        // rho and pi
+        let mut b = vec![UInt64::constant(0); 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = rotl(&a[x + 5 * y], RHO[x + 5 * y]);
+            }
+        }
+
+        // chi
+        for x in 0..5 {
+            for y in 0..5 {
+                a[x + 5 * y] = chi(
+                    cs.namespace(|| format!("chi {} {}", x, y)),
+                    &b[x + 5 * y],
+                    &b[(x + 1) % 5 + 5 * y],
+                    &b[(x + 2) % 5 + 5 * y],
+                )?;
+            }
+        }
+
+        // iota
+        a[0] = a[0].xor(cs.namespace(|| "iota"), &UInt64::constant(round_constant))?;
+    }
+
+    Ok(a)
+}
+
+/// Keccak-256 of `input`, a 32 byte digest.
+pub fn keccak256<E: ScalarEngine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[Boolean],
+) -> Result<Vec<Boolean>, SynthesisError> {
+    assert!(input.len() % 8 == 0);
+
+    // pad10*1, with the first padding bit at the least significant bit of the
+    // byte after the input, and the last at the most significant bit of the
+    // last byte of the block.
+    let mut padded = input.to_vec();
+    padded.push(Boolean::constant(true));
+    while padded.len() % RATE != RATE - 1 {
+        padded.push(Boolean::constant(false));
+    }
+    padded.push(Boolean::constant(true));
+
+    let mut state = vec![UInt64::constant(0); 25];
+    for (i, block) in padded.chunks(RATE).enumerate() {
+        let mut cs = cs.namespace(|| format!("block {}", i));
+
+        for (j, lane) in block.chunks(64).enumerate() {
+            state[j] = state[j].xor(
+                cs.namespace(|| format!("absorb {}", j)),
+                &UInt64::from_bits(lane),
+            )?;
+        }
+        state = keccak_f1600(cs.namespace(|| "keccak-f"), state)?;
+    }
+
+    Ok(state
+        .into_iter()
+        .take(4)
+        .flat_map(|lane| lane.into_bits())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::Bls12;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use tiny_keccak::{Hasher, Keccak};
+
+    use super::keccak256;
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::ConstraintSystem;
+
+    fn alloc_input<CS: ConstraintSystem<Bls12>>(mut cs: CS, data: &[u8]) -> Vec<Boolean> {
+        let mut input_bits = vec![];
+
+        for (byte_i, input_byte) in data.iter().enumerate() {
+            for bit_i in 0..8 {
+                let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+
+                input_bits.push(
+                    AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8))
+                        .unwrap()
+                        .into(),
+                );
+            }
+        }
+
+        input_bits
+    }
+
+    fn assert_digest(out: Vec<Boolean>, expected: &[u8]) {
+        assert_eq!(out.len(), expected.len() * 8);
+
+        let mut out = out.into_iter();
+        for b in expected.iter() {
+            for i in 0..8 {
+                let c = out.next().unwrap().get_value().unwrap();
+
+                assert_eq!(c, (b >> i) & 1u8 == 1u8);
+            }
+        }
+    }
+
+    fn tiny_keccak256(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Keccak::v256();
+        hasher.update(data);
+        let mut out = [0; 32];
+        hasher.finalize(&mut out);
+        out
+    }
+
+    #[test]
+    fn test_keccak256_known_answers() {
+        let data = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let vectors: [(&[u8], [u8; 32]); 3] = [
+            (
+                b"",
+                hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            ),
+            (
+                b"abc",
+                hex!("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+            ),
+            (
+                &data,
+                hex!("bfb0aa97863e797943cf7c33bb7e880bb4543f3d2703c0923c6901c2af57b890"),
+            ),
+        ];
+
+        for (data, expected) in vectors.iter() {
+            assert_eq!(&tiny_keccak256(data), expected);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_input(&mut cs, data);
+            let out = keccak256(&mut cs, &input_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_digest(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_keccak256_constraints() {
+        // One block, whose capacity is constant.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_input(&mut cs, &[0xff; 32]);
+        keccak256(&mut cs, &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 256 + 150_848);
+
+        // Two blocks, absorbing 512 allocated bits into the second.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_input(&mut cs, &[0xff; 200]);
+        keccak256(&mut cs, &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1600 + 153_088 + 512 + 153_600);
+    }
+
+    #[test]
+    fn test_blank_hash() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let out = keccak256(&mut cs, &[]).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+        assert_digest(out, &tiny_keccak256(&[]));
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        // Around the block boundary, where the padding is a single 0x81 byte or
+        // fills a block of its own.
+        for &input_len in &[1, 134, 135, 136, 137] {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.next_u32() as u8).collect();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_input(&mut cs, &data);
+            let out = keccak256(&mut cs, &input_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_digest(out, &tiny_keccak256(&data));
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Authentication paths of binary Merkle trees.
+//!
+//! A path lists the siblings of the nodes from the leaf up to the root, and the index
+//! of the leaf is given by its bits, least significant first: bit `i` is set if the
+//! node at height `i` is the right child of its parent, so that the sibling is hashed
+//! as the left input. Leaf `5 = 0b101` of a tree of height 3 is the right child of a
+//! left child of a right child.
+//!
+//! The hash of the children of a node is pluggable with [`CircuitHasher`], which is
+//! implemented for [`PoseidonConstants`] of arity 2 and for SHA-256 with
+//! [`Sha256Hasher`]. Every level costs one hash and two constraints to order the
+//! children, or none for a constant index bit.
+
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use sha2::{Digest, Sha256};
+
+use super::boolean::Boolean;
+use super::multipack;
+use super::num::AllocatedNum;
+use super::poseidon::{poseidon_hash_2, POSEIDON_HASH_DOMAIN};
+use super::sha256::sha256;
+use crate::bls::Engine;
+use crate::poseidon::{domain_tag, PoseidonConstants};
+use crate::{ConstraintSystem, SynthesisError};
+
+/// A hash of two field elements, in a circuit and outside of it.
+pub trait CircuitHasher<E: Engine> {
+    /// The hash of `left` and `right`.
+    fn hash_two<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError>;
+
+    /// The value [`CircuitHasher::hash_two`] computes for `left` and `right`.
+    fn compute_hash_two(&self, left: E::Fr, right: E::Fr) -> E::Fr;
+}
+
+/// The Poseidon hash of [`poseidon_hash_2`], for constants of arity 2.
+impl<E: Engine> CircuitHasher<E> for PoseidonConstants<E> {
+    fn hash_two<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError> {
+        poseidon_hash_2(cs, &[left.clone(), right.clone()], self)
+    }
+
+    fn compute_hash_two(&self, left: E::Fr, right: E::Fr) -> E::Fr {
+        assert_eq!(self.arity, 2, "the constants must have arity 2");
+
+        self.hash(domain_tag::<E>(POSEIDON_HASH_DOMAIN, 2), &[left, right])
+    }
+}
+
+/// SHA-256 of the 32 byte little endian encodings of the two elements, of which the
+/// first [`PrimeField::CAPACITY`] bits are packed into an element like
+/// [`multipack::pack_bits`] does.
+///
+/// Decomposing the inputs into bits and the two compressions of SHA-256 cost tens of
+/// thousands of constraints, compared to about 240 for Poseidon.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl<E: Engine> CircuitHasher<E> for Sha256Hasher {
+    fn hash_two<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>,
+    ) -> Result<AllocatedNum<E>, SynthesisError> {
+        let mut preimage = Vec::with_capacity(512);
+        for (name, num) in &[("left", left), ("right", right)] {
+            let bits = num.to_bits_le_strict(cs.namespace(|| format!("{} bits", name)))?;
+
+            // Bytes in little endian order, each with its most significant bit first.
+            for byte in 0..32 {
+                for i in (0..8).rev() {
+                    preimage.push(
+                        bits.get(8 * byte + i)
+                            .cloned()
+                            .unwrap_or_else(|| Boolean::constant(false)),
+                    );
+                }
+            }
+        }
+
+        let digest = sha256(cs.namespace(|| "sha256"), &preimage)?;
+        multipack::pack_bits(cs.namespace(|| "pack"), &digest)
+    }
+
+    fn compute_hash_two(&self, left: E::Fr, right: E::Fr) -> E::Fr {
+        let mut preimage = Vec::with_capacity(64);
+        for element in &[left, right] {
+            element
+                .into_repr()
+                .write_le(&mut preimage)
+                .expect("writing to a vec never fails");
+        }
+
+        let bits = multipack::bytes_to_bits(&Sha256::digest(&preimage));
+        multipack::compute_multipacking::<E>(&bits[..E::Fr::CAPACITY as usize])[0]
+    }
+}
+
+/// The root of the tree in which `leaf` has the siblings `path` at the index of
+/// `index_bits`, as described in the [module documentation](self).
+pub fn verify_path<E, CS, H>(
+    mut cs: CS,
+    leaf: AllocatedNum<E>,
+    path: &[AllocatedNum<E>],
+    index_bits: &[Boolean],
+    hasher: &H,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    H: CircuitHasher<E>,
+{
+    assert_eq!(
+        path.len(),
+        index_bits.len(),
+        "the path must have a sibling for every bit of the index"
+    );
+
+    let mut node = leaf;
+    for (i, (sibling, bit)) in path.iter().zip(index_bits).enumerate() {
+        let mut cs = cs.namespace(|| format!("level {}", i));
+        let (left, right) =
+            AllocatedNum::conditionally_reverse(cs.namespace(|| "order"), &node, sibling, bit)?;
+        node = hasher.hash_two(cs.namespace(|| "hash"), &left, &right)?;
+    }
+
+    Ok(node)
+}
+
+/// Enforces that `leaf` is at the index of `index_bits` in the tree with the root
+/// `expected_root`, with [`verify_path`] and one constraint more.
+pub fn enforce_path<E, CS, H>(
+    mut cs: CS,
+    leaf: AllocatedNum<E>,
+    path: &[AllocatedNum<E>],
+    index_bits: &[Boolean],
+    hasher: &H,
+    expected_root: &AllocatedNum<E>,
+) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    H: CircuitHasher<E>,
+{
+    let root = verify_path(cs.namespace(|| "path"), leaf, path, index_bits, hasher)?;
+
+    // root * 1 = expected_root
+    cs.enforce(
+        || "root",
+        |lc| lc + root.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + expected_root.get_variable(),
+    );
+
+    Ok(())
+}
+
+/// The path of the leaf at `index` and the root of the tree of `leaves`, padded with
+/// zeros to a power of two, for [`verify_path`].
+pub fn compute_path<E, H>(hasher: &H, leaves: &[E::Fr], index: usize) -> (Vec<E::Fr>, E::Fr)
+where
+    E: Engine,
+    H: CircuitHasher<E>,
+{
+    assert!(index < leaves.len(), "leaf {} is not in the tree", index);
+
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), E::Fr::zero());
+
+    let mut path = vec![];
+    let mut index = index;
+    while level.len() > 1 {
+        path.push(level[index ^ 1]);
+        level = level
+            .chunks(2)
+            .map(|pair| hasher.compute_hash_two(pair[0], pair[1]))
+            .collect();
+        index /= 2;
+    }
+
+    (path, level[0])
+}
+
+/// The bits of `index` for a tree of `height`, least significant first.
+pub fn index_to_bits(index: usize, height: usize) -> Vec<bool> {
+    (0..height).map(|i| (index >> i) & 1 == 1).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Allocates the leaf, its path and its index, returning the root.
+    fn verify<H: CircuitHasher<Bls12>>(
+        cs: &mut TestConstraintSystem<Bls12>,
+        hasher: &H,
+        leaf: Fr,
+        path: &[Fr],
+        index: usize,
+    ) -> AllocatedNum<Bls12> {
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+        let path = path
+            .iter()
+            .enumerate()
+            .map(|(i, &sibling)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || Ok(sibling))
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let index_bits = index_to_bits(index, path.len())
+            .into_iter()
+            .enumerate()
+            .map(|(i, bit)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("index bit {}", i)), Some(bit))
+                        .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        verify_path(cs.namespace(|| "verify"), leaf, &path, &index_bits, hasher).unwrap()
+    }
+
+    #[test]
+    fn test_verify_path_poseidon() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let hasher = PoseidonConstants::<Bls12>::new(2);
+
+        // Heights 1 to 4, with a number of leaves that is not a power of two.
+        for &(num_leaves, index) in &[(2, 0), (2, 1), (3, 2), (6, 5), (11, 6), (16, 13)] {
+            let leaves = (0..num_leaves)
+                .map(|_| Fr::random(&mut rng))
+                .collect::<Vec<_>>();
+            let (path, root) = compute_path::<Bls12, _>(&hasher, &leaves, index);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let computed = verify(&mut cs, &hasher, leaves[index], &path, index);
+            assert!(cs.is_satisfied());
+            assert_eq!(computed.get_value(), Some(root), "leaf {}", index);
+
+            // Another leaf, or the right leaf at another index, has another root.
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let other = verify(&mut cs, &hasher, path[0], &path, index);
+            assert_ne!(other.get_value(), Some(root));
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let other = verify(&mut cs, &hasher, leaves[index], &path, index ^ 1);
+            assert_ne!(other.get_value(), Some(root));
+        }
+    }
+
+    #[test]
+    fn test_enforce_path() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let hasher = PoseidonConstants::<Bls12>::new(2);
+
+        let leaves = (0..7).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let index = 5;
+        let (path, root) = compute_path::<Bls12, _>(&hasher, &leaves, index);
+
+        // A corrupted sibling leads to another root.
+        let mut corrupted = path.clone();
+        corrupted[1].add_assign(&Fr::one());
+
+        for (path, satisfied) in vec![(path, true), (corrupted, false)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaves[index])).unwrap();
+            let siblings = path
+                .iter()
+                .enumerate()
+                .map(|(i, &sibling)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || Ok(sibling))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            let expected_root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(root)).unwrap();
+            let index_bits = index_to_bits(index, path.len())
+                .into_iter()
+                .map(Boolean::constant)
+                .collect::<Vec<_>>();
+            enforce_path(
+                cs.namespace(|| "enforce"),
+                leaf,
+                &siblings,
+                &index_bits,
+                &hasher,
+                &expected_root,
+            )
+            .unwrap();
+
+            assert_eq!(cs.is_satisfied(), satisfied);
+            if !satisfied {
+                assert_eq!(cs.which_is_unsatisfied(), Some("enforce/root"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_path_sha256() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let leaves = (0..3).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let index = 2;
+        let (path, root) = compute_path::<Bls12, _>(&Sha256Hasher, &leaves, index);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let computed = verify(&mut cs, &Sha256Hasher, leaves[index], &path, index);
+        assert!(cs.is_satisfied());
+        assert_eq!(computed.get_value(), Some(root));
+
+        // A corrupted sibling changes the digest, which the packing binds to the root.
+        cs.set("sibling 0/num", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+}
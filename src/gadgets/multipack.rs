@@ -1,19 +1,39 @@
 //! Helpers for packing vectors of bits into scalar field elements.
+//!
+//! By default every element holds [`PrimeField::CAPACITY`] bits, little endian, and
+//! the last one the rest. The `_with_capacity` variants hold fewer bits per element,
+//! e.g. a multiple of 8 to match a verifier packing whole bytes, and panic for zero
+//! bits or more than the capacity. No bits pack into no elements.
 
-use super::boolean::Boolean;
+use super::boolean::{AllocatedBit, Boolean};
 use super::num::{AllocatedNum, Num};
 use super::Assignment;
-use crate::{ConstraintSystem, SynthesisError};
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
 use ff::{Field, PrimeField, ScalarEngine};
 
 /// Takes a sequence of booleans and exposes them as compact
 /// public inputs
-pub fn pack_into_inputs<E, CS>(mut cs: CS, bits: &[Boolean]) -> Result<(), SynthesisError>
+pub fn pack_into_inputs<E, CS>(cs: CS, bits: &[Boolean]) -> Result<(), SynthesisError>
 where
     E: ScalarEngine,
     CS: ConstraintSystem<E>,
 {
-    for (i, bits) in bits.chunks(E::Fr::CAPACITY as usize).enumerate() {
+    pack_into_inputs_with_capacity(cs, bits, E::Fr::CAPACITY as usize)
+}
+
+/// [`pack_into_inputs`] with `bits_per_element` bits per input.
+pub fn pack_into_inputs_with_capacity<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    bits_per_element: usize,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    check_capacity::<E>(bits_per_element);
+
+    for (i, bits) in bits.chunks(bits_per_element).enumerate() {
         let mut num = Num::<E>::zero();
         let mut coeff = E::Fr::one();
         for bit in bits {
@@ -36,6 +56,82 @@ where
     Ok(())
 }
 
+/// The inverse of [`pack_into_inputs`]: decomposes `nums` into the `total_bits` bits
+/// they pack, [`PrimeField::CAPACITY`] bits for each number but the last, which holds
+/// the rest. A sum of fewer bits than the field has is less than its modulus, so the
+/// decomposition is unique, and a number that does not fit in its bits leaves the
+/// constraints unsatisfied. Each number costs one constraint per bit and one for the
+/// packing.
+///
+/// Panics if there are not as many numbers as `pack_into_inputs` makes of
+/// `total_bits` bits.
+pub fn unpack_from_inputs<E, CS>(
+    cs: CS,
+    nums: &[AllocatedNum<E>],
+    total_bits: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    unpack_from_inputs_with_capacity(cs, nums, total_bits, E::Fr::CAPACITY as usize)
+}
+
+/// [`unpack_from_inputs`] with `bits_per_element` bits per number.
+pub fn unpack_from_inputs_with_capacity<E, CS>(
+    mut cs: CS,
+    nums: &[AllocatedNum<E>],
+    total_bits: usize,
+    bits_per_element: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    check_capacity::<E>(bits_per_element);
+
+    let capacity = bits_per_element;
+    assert_eq!(
+        nums.len(),
+        (total_bits + capacity - 1) / capacity,
+        "{} bits do not pack into {} numbers",
+        total_bits,
+        nums.len()
+    );
+
+    let mut result = Vec::with_capacity(total_bits);
+    for (i, num) in nums.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("input {}", i));
+        let num_bits = capacity.min(total_bits - i * capacity);
+        let value = num.get_value().map(|v| v.into_repr());
+
+        let mut packed = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for j in 0..num_bits {
+            let bit = AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", j)),
+                value
+                    .as_ref()
+                    .map(|v| (v.as_ref()[j / 64] >> (j % 64)) & 1 == 1),
+            )?;
+            packed = packed + (coeff, bit.get_variable());
+            result.push(Boolean::from(bit));
+
+            coeff.double();
+        }
+
+        // bits * 1 = num
+        cs.enforce(
+            || "packing constraint",
+            |_| packed,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+    }
+
+    Ok(result)
+}
+
 pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
     bytes
         .iter()
@@ -51,9 +147,19 @@ pub fn bytes_to_bits_le(bytes: &[u8]) -> Vec<bool> {
 }
 
 pub fn compute_multipacking<E: ScalarEngine>(bits: &[bool]) -> Vec<E::Fr> {
+    compute_multipacking_with_capacity::<E>(bits, E::Fr::CAPACITY as usize)
+}
+
+/// The inputs [`pack_into_inputs_with_capacity`] exposes for `bits`.
+pub fn compute_multipacking_with_capacity<E: ScalarEngine>(
+    bits: &[bool],
+    bits_per_element: usize,
+) -> Vec<E::Fr> {
+    check_capacity::<E>(bits_per_element);
+
     let mut result = vec![];
 
-    for bits in bits.chunks(E::Fr::CAPACITY as usize) {
+    for bits in bits.chunks(bits_per_element) {
         let mut cur = E::Fr::zero();
         let mut coeff = E::Fr::one();
 
@@ -72,14 +178,29 @@ pub fn compute_multipacking<E: ScalarEngine>(bits: &[bool]) -> Vec<E::Fr> {
 }
 
 /// Takes a sequence of booleans and exposes them as a single compact Num.
-pub fn pack_bits<E, CS>(mut cs: CS, bits: &[Boolean]) -> Result<AllocatedNum<E>, SynthesisError>
+pub fn pack_bits<E, CS>(cs: CS, bits: &[Boolean]) -> Result<AllocatedNum<E>, SynthesisError>
 where
     E: ScalarEngine,
     CS: ConstraintSystem<E>,
 {
+    pack_bits_with_capacity(cs, bits, E::Fr::CAPACITY as usize)
+}
+
+/// [`pack_bits`] of the first `bits_per_element` bits.
+pub fn pack_bits_with_capacity<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    bits_per_element: usize,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    check_capacity::<E>(bits_per_element);
+
     let mut num = Num::<E>::zero();
     let mut coeff = E::Fr::one();
-    for bit in bits.iter().take(E::Fr::CAPACITY as usize) {
+    for bit in bits.iter().take(bits_per_element) {
         num = num.add_bool_with_coeff(CS::one(), &bit, coeff);
 
         coeff.double();
@@ -101,6 +222,14 @@ where
     Ok(alloc_num)
 }
 
+fn check_capacity<E: ScalarEngine>(bits_per_element: usize) {
+    assert!(
+        bits_per_element > 0 && bits_per_element <= E::Fr::CAPACITY as usize,
+        "cannot pack {} bits per element",
+        bits_per_element
+    );
+}
+
 #[test]
 fn test_multipacking() {
     use crate::bls::Bls12;
@@ -139,3 +268,161 @@ fn test_multipacking() {
         assert!(cs.verify(&expected_inputs));
     }
 }
+
+#[test]
+fn test_unpacking() {
+    use crate::bls::{Bls12, Fr};
+    use crate::ConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::gadgets::test::*;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    // Around multiples of the capacity, 254 bits for BLS12-381.
+    for &num_bits in &[0, 1, 8, 253, 254, 255, 256, 507, 508, 509, 600] {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let bits: Vec<bool> = (0..num_bits).map(|_| rng.next_u32() % 2 != 0).collect();
+        let nums = compute_multipacking::<Bls12>(&bits)
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("num {}", i)), || Ok(value)).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let unpacked = unpack_from_inputs(cs.namespace(|| "unpack"), &nums, num_bits).unwrap();
+        assert_eq!(
+            unpacked.iter().map(|b| b.get_value()).collect::<Vec<_>>(),
+            bits.iter().map(|&b| Some(b)).collect::<Vec<_>>()
+        );
+        assert_eq!(cs.num_constraints(), num_bits + nums.len());
+
+        // Packing the bits again gives the same inputs.
+        pack_into_inputs(cs.namespace(|| "pack"), &unpacked).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&compute_multipacking::<Bls12>(&bits)));
+    }
+
+    // 256 does not fit in the 8 bits of the last number.
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    let nums = [Fr::one(), Fr::from_str("256").unwrap()]
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("num {}", i)), || Ok(value)).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let capacity = Fr::CAPACITY as usize;
+    unpack_from_inputs(cs.namespace(|| "unpack"), &nums, capacity + 8).unwrap();
+    assert_eq!(
+        cs.which_is_unsatisfied(),
+        Some("unpack/input 1/packing constraint")
+    );
+}
+
+#[test]
+fn test_multipacking_with_capacity() {
+    use crate::bls::{Bls12, Fr};
+    use crate::ConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::boolean::AllocatedBit;
+    use crate::gadgets::test::*;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let capacity = Fr::CAPACITY as usize;
+    for &bits_per_element in &[1, 8, 248, capacity] {
+        // Nothing, one element exactly and one bit over, and several.
+        for &num_bits in &[
+            0,
+            1,
+            bits_per_element,
+            bits_per_element + 1,
+            2 * bits_per_element + 3,
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let bits: Vec<bool> = (0..num_bits).map(|_| rng.next_u32() % 2 != 0).collect();
+            let circuit_bits = bits
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(b))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let expected_inputs =
+                compute_multipacking_with_capacity::<Bls12>(&bits, bits_per_element);
+            assert_eq!(
+                expected_inputs.len(),
+                (num_bits + bits_per_element - 1) / bits_per_element
+            );
+
+            pack_into_inputs_with_capacity(
+                cs.namespace(|| "pack"),
+                &circuit_bits,
+                bits_per_element,
+            )
+            .unwrap();
+            let num =
+                pack_bits_with_capacity(cs.namespace(|| "num"), &circuit_bits, bits_per_element)
+                    .unwrap();
+            assert_eq!(
+                num.get_value().unwrap(),
+                expected_inputs.first().copied().unwrap_or_else(Fr::zero)
+            );
+
+            let nums = expected_inputs
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("num {}", i)), || Ok(value))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            let unpacked = unpack_from_inputs_with_capacity(
+                cs.namespace(|| "unpack"),
+                &nums,
+                num_bits,
+                bits_per_element,
+            )
+            .unwrap();
+            assert_eq!(
+                unpacked.iter().map(|b| b.get_value()).collect::<Vec<_>>(),
+                bits.iter().map(|&b| Some(b)).collect::<Vec<_>>()
+            );
+
+            assert!(cs.is_satisfied());
+            assert!(cs.verify(&expected_inputs));
+        }
+    }
+
+    // The default capacity is the field's.
+    let bits: Vec<bool> = (0..600).map(|_| rng.next_u32() % 2 != 0).collect();
+    assert_eq!(
+        compute_multipacking::<Bls12>(&bits),
+        compute_multipacking_with_capacity::<Bls12>(&bits, capacity)
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot pack")]
+fn test_multipacking_over_capacity() {
+    use crate::bls::{Bls12, Fr};
+
+    compute_multipacking_with_capacity::<Bls12>(&[true], Fr::CAPACITY as usize + 1);
+}
@@ -0,0 +1,356 @@
+//! Selecting one of a number of values by the bits of an index.
+//!
+//! [`mux`] selects among allocated numbers by one bit of the index at a time, from
+//! the least significant one. Selecting `y` over `x` by a bit `b` is the single
+//! constraint `(y - x) * b = r - x`, so `n` values take `n - 1` constraints. Each
+//! value is multiplied by something that depends on the index, so no selection among
+//! variables takes fewer: a sum of the products of the values with indicators of the
+//! index takes `n` multiplications, and the indicators come on top of that.
+//!
+//! The values of [`mux_constants`] are not variables, so the result is a linear
+//! combination of the products of the index bits instead, like in
+//! [`lookup3_xy`](super::lookup::lookup3_xy). A product of the bits of an index `i`
+//! is needed for every `i < n` with more than one bit set, one constraint each, which
+//! for `n - 1` of `k` bits is `n - k - 1`, and the result needs none.
+//!
+//! Indices of at least `n` cannot be selected: both check the index against `n - 1`
+//! from its most significant bit, like [`range`](super::range), with at most one
+//! constraint per index bit, and none if `n` is a power of two that uses all of
+//! them. A constant index out of range fails with [`SynthesisError::Unsatisfiable`].
+
+use ff::{Field, ScalarEngine};
+
+use crate::{ConstraintSystem, SynthesisError};
+
+use super::boolean::Boolean;
+use super::num::{AllocatedNum, Num};
+use super::Assignment;
+
+/// The value of `values` at the index of the little-endian `index_bits`, which must
+/// be less than `values.len()`. There must be enough bits to index every value.
+pub fn mux<E, CS>(
+    mut cs: CS,
+    index_bits: &[Boolean],
+    values: &[AllocatedNum<E>],
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(!values.is_empty());
+    assert!(bit_length(values.len() - 1) <= index_bits.len());
+
+    enforce_index_below(cs.namespace(|| "range"), index_bits, values.len())?;
+
+    // Pairs of values are selected between by each bit, and the last value of an odd
+    // number of them is passed on, as no index below `n` selects past it.
+    let mut level = values.to_vec();
+    for (j, bit) in index_bits.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("bit {}", j));
+        level = level
+            .chunks(2)
+            .enumerate()
+            .map(|(i, pair)| match pair {
+                [x, y] => select(cs.namespace(|| format!("select {}", i)), bit, x, y),
+                [x] => Ok(x.clone()),
+                _ => unreachable!(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+
+    Ok(level.pop().unwrap())
+}
+
+/// [`mux`] over constants, as a linear combination of the products of the index bits.
+pub fn mux_constants<E, CS>(
+    mut cs: CS,
+    index_bits: &[Boolean],
+    values: &[E::Fr],
+) -> Result<Num<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(!values.is_empty());
+    let k = bit_length(values.len() - 1);
+    assert!(k <= index_bits.len());
+
+    enforce_index_below(cs.namespace(|| "range"), index_bits, values.len())?;
+
+    // The coefficients of the products of the bits of each index, such that the
+    // coefficients of the bits of an index and its subsets add up to its value. Those
+    // of the indices below `n` only depend on the values of indices below `n`.
+    let mut coeffs = values.to_vec();
+    coeffs.resize(1 << k, E::Fr::zero());
+    for j in 0..k {
+        for i in 0..coeffs.len() {
+            if i & (1 << j) != 0 {
+                let subset = coeffs[i ^ (1 << j)];
+                coeffs[i].sub_assign(&subset);
+            }
+        }
+    }
+
+    let mut products = vec![Boolean::constant(true)];
+    let mut result = Num::zero();
+    for (i, coeff) in coeffs.iter().enumerate().take(values.len()) {
+        if i > 0 {
+            let top = bit_length(i) - 1;
+            let product = Boolean::and(
+                cs.namespace(|| format!("product {}", i)),
+                &products[i ^ (1 << top)],
+                &index_bits[top],
+            )?;
+            products.push(product);
+        }
+
+        result = result.add_bool_with_coeff(CS::one(), &products[i], *coeff);
+    }
+
+    Ok(result)
+}
+
+/// `y` if `bit` is set, and `x` otherwise.
+fn select<E, CS>(
+    mut cs: CS,
+    bit: &Boolean,
+    x: &AllocatedNum<E>,
+    y: &AllocatedNum<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    match *bit {
+        Boolean::Constant(false) => return Ok(x.clone()),
+        Boolean::Constant(true) => return Ok(y.clone()),
+        _ => {}
+    }
+
+    let selected = AllocatedNum::alloc(&mut cs, || {
+        if *bit.get_value().get()? {
+            Ok(*y.get_value().get()?)
+        } else {
+            Ok(*x.get_value().get()?)
+        }
+    })?;
+
+    cs.enforce(
+        || "selection",
+        |lc| lc + y.get_variable() - x.get_variable(),
+        |_| bit.lc(CS::one(), E::Fr::one()),
+        |lc| lc + selected.get_variable() - x.get_variable(),
+    );
+
+    Ok(selected)
+}
+
+/// Enforces that the index of `bits` is less than `n`. Below a zero bit of `n - 1`,
+/// the bit of the index must be zero if all bits above it equal those of `n - 1`, and
+/// the bits below the lowest zero can be anything.
+fn enforce_index_below<E, CS>(mut cs: CS, bits: &[Boolean], n: usize) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let bound_bit = |j: usize| j < bit_length(n - 1) && ((n - 1) >> j) & 1 == 1;
+
+    let lowest_zero = match (0..bits.len()).find(|&j| !bound_bit(j)) {
+        Some(j) => j,
+        None => return Ok(()),
+    };
+
+    // Whether the bits so far equal those of `n - 1`.
+    let mut equal = Boolean::constant(true);
+    for j in (lowest_zero..bits.len()).rev() {
+        if bound_bit(j) {
+            equal = Boolean::and(
+                cs.namespace(|| format!("equal to bit {}", j)),
+                &equal,
+                &bits[j],
+            )?;
+            continue;
+        }
+
+        match (&equal, &bits[j]) {
+            (&Boolean::Constant(false), _) | (_, &Boolean::Constant(false)) => {}
+            (&Boolean::Constant(true), &Boolean::Constant(true)) => {
+                return Err(SynthesisError::Unsatisfiable)
+            }
+            (equal, bit) => cs.enforce(
+                || format!("bit {} range", j),
+                |_| equal.lc(CS::one(), E::Fr::one()),
+                |_| bit.lc(CS::one(), E::Fr::one()),
+                |lc| lc,
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of bits of `x`.
+fn bit_length(x: usize) -> usize {
+    (0usize.count_zeros() - x.leading_zeros()) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn index_bits(cs: &mut TestConstraintSystem<Bls12>, index: usize, k: usize) -> Vec<Boolean> {
+        (0..k)
+            .map(|j| {
+                Boolean::from(
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("index bit {}", j)),
+                        Some((index >> j) & 1 == 1),
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mux() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for k in 1..=4 {
+            // Every number of values that needs `k` bits, with and without padding.
+            for n in (1 << (k - 1)) + 1..=(1 << k) {
+                let values = (0..n).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+                for index in 0..(1 << k) {
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+                    let bits = index_bits(&mut cs, index, k);
+                    let nums = values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| {
+                            AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*v))
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>();
+
+                    let result = mux(cs.namespace(|| "mux"), &bits, &nums).unwrap();
+                    assert_eq!(cs.is_satisfied(), index < n, "{} of {}", index, n);
+                    if index < n {
+                        assert_eq!(result.get_value(), Some(values[index]));
+                    }
+                    let tree_cost = cs.num_constraints() - k;
+
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+                    let bits = index_bits(&mut cs, index, k);
+                    let result = mux_constants(cs.namespace(|| "mux"), &bits, &values).unwrap();
+                    assert_eq!(cs.is_satisfied(), index < n, "{} of {}", index, n);
+                    if index < n {
+                        assert_eq!(result.get_value(), Some(values[index]));
+                    }
+                    let constants_cost = cs.num_constraints() - k;
+
+                    // The same range check, and a product for every index but the
+                    // powers of two instead of a selection for every value but one.
+                    assert_eq!(constants_cost + k, tree_cost);
+                    if n == 1 << k {
+                        assert_eq!(tree_cost, n - 1);
+                        assert_eq!(constants_cost, n - k - 1);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mux_forgery() {
+        let values = [Fr::one(), Fr::zero(), Fr::one()];
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let bits = index_bits(&mut cs, 2, 2);
+        let nums = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*v)).unwrap()
+            })
+            .collect::<Vec<_>>();
+        mux(cs.namespace(|| "mux"), &bits, &nums).unwrap();
+        assert!(cs.is_satisfied());
+
+        cs.set("mux/bit 1/select 0/num", Fr::zero());
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("mux/bit 1/select 0/selection")
+        );
+        cs.set("mux/bit 1/select 0/num", Fr::one());
+
+        // Index 3 passes the last value on, as it would be padding.
+        cs.set("index bit 0/boolean", Fr::one());
+        cs.set("mux/bit 0/select 0/num", Fr::zero());
+        assert_eq!(cs.which_is_unsatisfied(), Some("mux/range/bit 0 range"));
+    }
+
+    #[test]
+    fn test_mux_constant_index() {
+        let values = [Fr::one(), Fr::zero(), Fr::one()];
+        let bits = |index: usize| {
+            (0..3)
+                .map(|j| Boolean::constant((index >> j) & 1 == 1))
+                .collect::<Vec<_>>()
+        };
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let nums = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*v)).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        for index in 0..3 {
+            let result = mux(
+                cs.namespace(|| format!("mux {}", index)),
+                &bits(index),
+                &nums,
+            );
+            assert_eq!(result.unwrap().get_variable(), nums[index].get_variable());
+
+            let result = mux_constants(
+                cs.namespace(|| format!("constants {}", index)),
+                &bits(index),
+                &values,
+            );
+            assert_eq!(result.unwrap().get_value(), Some(values[index]));
+        }
+        assert_eq!(cs.num_constraints(), 0);
+
+        for index in 3..8 {
+            assert!(matches!(
+                mux(
+                    cs.namespace(|| format!("mux {}", index)),
+                    &bits(index),
+                    &nums
+                ),
+                Err(SynthesisError::Unsatisfiable)
+            ));
+            assert!(matches!(
+                mux_constants(
+                    cs.namespace(|| format!("constants {}", index)),
+                    &bits(index),
+                    &values
+                ),
+                Err(SynthesisError::Unsatisfiable)
+            ));
+        }
+    }
+}
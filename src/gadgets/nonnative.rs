@@ -0,0 +1,1045 @@
+//! Arithmetic modulo a prime other than the one of the scalar field, such as the
+//! base field of secp256k1, with numbers of several limbs.
+//!
+//! A [`BigNat`] is a natural number `sum l_i 2^(w i)` of limbs `l_i`, least
+//! significant first, which are linear combinations of the circuit. Allocated limbs
+//! have `w` bits, but adding numbers adds their limbs without carrying, so every
+//! number keeps an upper bound of its limbs, which the checks below depend on.
+//!
+//! - Two numbers are equal if their limbs are with carries between them,
+//!   `l_i - l'_i + c_i = c_(i+1) 2^w` with no carries into the first limb and out of
+//!   the last one. The carries are witnessed, shifted to be positive and decomposed
+//!   into as many bits as the bounds of the limbs allow them to have, so none of these
+//!   equations wraps around the scalar field. Consecutive limbs are grouped into one
+//!   as long as that still holds, which saves the carries between them.
+//! - A product is witnessed as its limbs before carrying, the coefficients of the
+//!   product of the polynomials whose coefficients are the limbs of the factors, and
+//!   the polynomials are multiplied at as many points, one constraint each.
+//! - A number is reduced modulo a constant `m` by witnessing the quotient `q` and the
+//!   remainder `r < m` and checking that `q m + r` equals the number. The limbs of
+//!   `q m` are linear in those of `q`, so this costs the bits of `q` and `r`, the
+//!   check `r < m` and the carries.
+//!
+//! For secp256k1's modulus of 256 bits and numbers of four limbs of 64 bits, the
+//! operations cost:
+//!
+//! | operation | constraints |
+//! |---|---|
+//! | [`BigNat::alloc`] | 256 |
+//! | [`BigNat::add`] | 0 |
+//! | [`BigNat::enforce_equal`] of allocated numbers | 4 |
+//! | [`BigNat::assert_less_than_constant`] with the modulus | 261 |
+//! | [`BigNat::mul_mod`] of allocated numbers | 1049 |
+//! | [`BigNat::to_bits_le`] | 260 |
+//!
+//! A product of 1049 constraints is the product of 7 limbs, a quotient of 5 limbs
+//! and a remainder of 4, the check of the remainder and 3 carries of 67 bits between
+//! groups of two limbs.
+
+use std::cmp::{self, Ordering};
+
+use ff::{Field, PrimeField, ScalarEngine};
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::Assignment;
+
+/// The shape of a [`BigNat`], `n_limbs` limbs of `limb_width` bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BigNatParams {
+    pub limb_width: usize,
+    pub n_limbs: usize,
+}
+
+/// A natural number of several limbs, as described in the [module
+/// documentation](self). Values are given and returned as little endian 64-bit
+/// words.
+#[derive(Clone)]
+pub struct BigNat<E: ScalarEngine> {
+    limbs: Vec<LinearCombination<E>>,
+    values: Option<Vec<Nat>>,
+    limb_width: usize,
+    /// An upper bound of every limb.
+    max_word: Nat,
+}
+
+impl<E: ScalarEngine> BigNat<E> {
+    /// Allocates `value` with the bits of its limbs, one constraint per bit. A value
+    /// which does not fit in the limbs fails with [`SynthesisError::Unsatisfiable`].
+    pub fn alloc<CS>(
+        cs: CS,
+        value: Option<&[u64]>,
+        params: BigNatParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        Self::alloc_nat(cs, value.map(Nat::from_words), params)
+    }
+
+    fn alloc_nat<CS>(
+        mut cs: CS,
+        value: Option<Nat>,
+        params: BigNatParams,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let BigNatParams {
+            limb_width,
+            n_limbs,
+        } = params;
+        assert!(limb_width > 0 && n_limbs > 0);
+        if let Some(ref value) = value {
+            if value.bits() > limb_width * n_limbs {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        let mut limbs = Vec::with_capacity(n_limbs);
+        for i in 0..n_limbs {
+            let mut limb = LinearCombination::zero();
+            let mut coeff = E::Fr::one();
+            for j in 0..limb_width {
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("limb {} bit {}", i, j)),
+                    value.as_ref().map(|v| v.bit(i * limb_width + j)),
+                )?;
+                limb = limb + (coeff, bit.get_variable());
+                coeff.double();
+            }
+            limbs.push(limb);
+        }
+
+        Ok(BigNat {
+            limbs,
+            values: value.map(|v| (0..n_limbs).map(|i| v.limb(i, limb_width)).collect()),
+            limb_width,
+            max_word: Nat::ones(limb_width),
+        })
+    }
+
+    /// The constant `value`.
+    ///
+    /// # Panics
+    ///
+    /// If `value` does not fit in the limbs.
+    pub fn constant<CS>(value: &[u64], params: BigNatParams) -> Self
+    where
+        CS: ConstraintSystem<E>,
+    {
+        Self::constant_nat::<CS>(&Nat::from_words(value), params)
+    }
+
+    fn constant_nat<CS>(value: &Nat, params: BigNatParams) -> Self
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let BigNatParams {
+            limb_width,
+            n_limbs,
+        } = params;
+        assert!(limb_width > 0 && n_limbs > 0);
+        assert!(
+            value.bits() <= limb_width * n_limbs,
+            "the constant does not fit in the limbs"
+        );
+
+        let values = (0..n_limbs)
+            .map(|i| value.limb(i, limb_width))
+            .collect::<Vec<_>>();
+
+        BigNat {
+            limbs: values
+                .iter()
+                .map(|v| LinearCombination::zero() + (v.to_field::<E::Fr>(), CS::one()))
+                .collect(),
+            max_word: values.iter().max().cloned().unwrap_or_else(Nat::zero),
+            values: Some(values),
+            limb_width,
+        }
+    }
+
+    /// The number of the little endian `bits`, in limbs of `limb_width` of them,
+    /// without any constraints.
+    pub fn from_bits_le<CS>(bits: &[Boolean], limb_width: usize) -> Self
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(limb_width > 0);
+        let n_limbs = limbs_for(bits.len(), limb_width);
+
+        let mut limbs = vec![LinearCombination::zero(); n_limbs];
+        let mut values = Some(vec![Nat::zero(); n_limbs]);
+        for (i, bit) in bits.iter().enumerate() {
+            let (limb, j) = (i / limb_width, i % limb_width);
+            let coeff = Nat::pow2(j);
+            limbs[limb] = limbs[limb].clone() + &bit.lc(CS::one(), coeff.to_field::<E::Fr>());
+            values = match (values, bit.get_value()) {
+                (Some(mut values), Some(bit)) => {
+                    if bit {
+                        values[limb] = values[limb].add(&coeff);
+                    }
+                    Some(values)
+                }
+                _ => None,
+            };
+        }
+
+        BigNat {
+            limbs,
+            values,
+            limb_width,
+            max_word: Nat::ones(limb_width),
+        }
+    }
+
+    /// The bits of the number, little endian, `limb_width` per limb, with
+    /// `limb_width + 1` constraints per limb.
+    ///
+    /// # Panics
+    ///
+    /// If the limbs may have more bits, as after [`BigNat::add`]. Reducing the number
+    /// gives limbs of `limb_width` bits.
+    pub fn to_bits_le<CS>(&self, mut cs: CS) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert!(
+            self.max_word.bits() <= self.limb_width,
+            "the limbs have more than {} bits",
+            self.limb_width
+        );
+
+        let mut bits = Vec::with_capacity(self.limb_width * self.limbs.len());
+        for (i, limb) in self.limbs.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("limb {}", i));
+            let value = self.values.as_ref().map(|values| &values[i]);
+
+            let mut packed = LinearCombination::zero();
+            let mut coeff = E::Fr::one();
+            for j in 0..self.limb_width {
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", j)),
+                    value.map(|v| v.bit(j)),
+                )?;
+                packed = packed + (coeff, bit.get_variable());
+                coeff.double();
+                bits.push(Boolean::from(bit));
+            }
+            packed = packed - limb;
+
+            cs.enforce(|| "packing constraint", |lc| lc, |lc| lc, |_| packed);
+        }
+
+        Ok(bits)
+    }
+
+    pub fn params(&self) -> BigNatParams {
+        BigNatParams {
+            limb_width: self.limb_width,
+            n_limbs: self.limbs.len(),
+        }
+    }
+
+    /// The value, as little endian 64-bit words without leading zeros.
+    pub fn get_value(&self) -> Option<Vec<u64>> {
+        self.value().map(|v| v.0)
+    }
+
+    fn value(&self) -> Option<Nat> {
+        self.value_below(self.limbs.len())
+    }
+
+    /// The value of the limbs below limb `n`.
+    fn value_below(&self, n: usize) -> Option<Nat> {
+        self.values.as_ref().map(|values| {
+            values
+                .iter()
+                .take(n)
+                .enumerate()
+                .fold(Nat::zero(), |acc, (i, v)| {
+                    acc.add(&v.shl(i * self.limb_width))
+                })
+        })
+    }
+
+    /// The sum of the numbers, without any constraints. Its limbs are the sums of the
+    /// limbs, which may have more bits than those of the numbers.
+    pub fn add(&self, other: &Self) -> Self {
+        assert_eq!(self.limb_width, other.limb_width);
+        let n_limbs = cmp::max(self.limbs.len(), other.limbs.len());
+
+        let limbs = (0..n_limbs)
+            .map(|i| {
+                let mut limb = LinearCombination::zero();
+                for limbs in &[&self.limbs, &other.limbs] {
+                    if let Some(l) = limbs.get(i) {
+                        limb = limb + l;
+                    }
+                }
+                limb
+            })
+            .collect();
+        let values = match (&self.values, &other.values) {
+            (Some(a), Some(b)) => Some(
+                (0..n_limbs)
+                    .map(|i| {
+                        let zero = Nat::zero();
+                        a.get(i).unwrap_or(&zero).add(b.get(i).unwrap_or(&zero))
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        BigNat {
+            limbs,
+            values,
+            limb_width: self.limb_width,
+            max_word: self.max_word.add(&other.max_word),
+        }
+    }
+
+    /// `self * other mod modulus`, reduced with limbs of `limb_width` bits. The
+    /// modulus must not be zero.
+    pub fn mul_mod<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        modulus: &[u64],
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let product = self.mul(cs.namespace(|| "product"), other)?;
+
+        product.reduce(cs.namespace(|| "reduction"), modulus)
+    }
+
+    /// The limbs of the product before carrying.
+    fn mul<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        assert_eq!(self.limb_width, other.limb_width);
+        let n_limbs = self.limbs.len() + other.limbs.len() - 1;
+        let max_word = Nat::from_u64(cmp::min(self.limbs.len(), other.limbs.len()) as u64)
+            .mul(&self.max_word)
+            .mul(&other.max_word);
+        assert_fits_field::<E::Fr>(&max_word);
+
+        let values = match (&self.values, &other.values) {
+            (Some(a), Some(b)) => {
+                let mut values = vec![Nat::zero(); n_limbs];
+                for (i, a) in a.iter().enumerate() {
+                    for (j, b) in b.iter().enumerate() {
+                        values[i + j] = values[i + j].add(&a.mul(b));
+                    }
+                }
+                Some(values)
+            }
+            _ => None,
+        };
+
+        let limbs = (0..n_limbs)
+            .map(|k| {
+                let limb =
+                    cs.alloc(|| format!("limb {}", k), || Ok(values.get()?[k].to_field()))?;
+                Ok(LinearCombination::zero() + limb)
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        // The polynomials of degree n_limbs - 1 agree at n_limbs points.
+        let evaluate = |limbs: &[LinearCombination<E>], x: &E::Fr| {
+            let mut power = E::Fr::one();
+            limbs.iter().fold(LinearCombination::zero(), |acc, limb| {
+                let acc = acc + (power, limb);
+                power.mul_assign(x);
+                acc
+            })
+        };
+        for x in 0..n_limbs {
+            let point = Nat::from_u64(x as u64).to_field::<E::Fr>();
+            cs.enforce(
+                || format!("product at {}", x),
+                |_| evaluate(&self.limbs[..], &point),
+                |_| evaluate(&other.limbs[..], &point),
+                |_| evaluate(&limbs[..], &point),
+            );
+        }
+
+        Ok(BigNat {
+            limbs,
+            values,
+            limb_width: self.limb_width,
+            max_word,
+        })
+    }
+
+    /// The number modulo the constant `modulus`, which must not be zero, with limbs of
+    /// `limb_width` bits.
+    pub fn reduce<CS>(&self, mut cs: CS, modulus: &[u64]) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let modulus = Nat::from_words(modulus);
+        assert!(!modulus.is_zero(), "the modulus is zero");
+        let limb_width = self.limb_width;
+
+        let max_value = (0..self.limbs.len()).fold(Nat::zero(), |acc, i| {
+            acc.add(&self.max_word.shl(i * limb_width))
+        });
+        let (max_quotient, _) = max_value.div_rem(&modulus);
+        let max_remainder = modulus.checked_sub(&Nat::one()).unwrap();
+
+        let (quotient, remainder) = match self.value() {
+            Some(value) => {
+                let (quotient, remainder) = value.div_rem(&modulus);
+                (Some(quotient), Some(remainder))
+            }
+            None => (None, None),
+        };
+        let quotient = Self::alloc_nat(
+            cs.namespace(|| "quotient"),
+            quotient,
+            BigNatParams {
+                limb_width,
+                n_limbs: limbs_for(max_quotient.bits(), limb_width),
+            },
+        )?;
+        let remainder = Self::alloc_nat(
+            cs.namespace(|| "remainder"),
+            remainder,
+            BigNatParams {
+                limb_width,
+                n_limbs: limbs_for(max_remainder.bits(), limb_width),
+            },
+        )?;
+        remainder.assert_less_than(cs.namespace(|| "remainder range"), &modulus)?;
+
+        enforce_equal_when_carried(
+            cs.namespace(|| "quotient and remainder"),
+            self,
+            &quotient.mul_constant(&modulus).add(&remainder),
+        )?;
+
+        Ok(remainder)
+    }
+
+    /// The product with a constant, whose limbs are linear in those of the number.
+    fn mul_constant(&self, c: &Nat) -> Self {
+        let c_limbs = (0..limbs_for(c.bits(), self.limb_width))
+            .map(|j| c.limb(j, self.limb_width))
+            .collect::<Vec<_>>();
+        let n_limbs = self.limbs.len() + c_limbs.len() - 1;
+
+        let mut limbs = vec![LinearCombination::zero(); n_limbs];
+        for (i, limb) in self.limbs.iter().enumerate() {
+            for (j, c) in c_limbs.iter().enumerate() {
+                limbs[i + j] = limbs[i + j].clone() + (c.to_field::<E::Fr>(), limb);
+            }
+        }
+        let values = self.values.as_ref().map(|own| {
+            let mut values = vec![Nat::zero(); n_limbs];
+            for (i, v) in own.iter().enumerate() {
+                for (j, c) in c_limbs.iter().enumerate() {
+                    values[i + j] = values[i + j].add(&v.mul(c));
+                }
+            }
+            values
+        });
+
+        BigNat {
+            limbs,
+            values,
+            limb_width: self.limb_width,
+            max_word: c_limbs
+                .iter()
+                .fold(Nat::zero(), |acc, c| acc.add(c))
+                .mul(&self.max_word),
+        }
+    }
+
+    /// Enforces that the numbers are equal, with the limbs carried as described in
+    /// the [module documentation](self). Numbers reduced modulo the same modulus are
+    /// equal modulo it only if they are equal.
+    pub fn enforce_equal<CS>(&self, cs: CS, other: &Self) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        enforce_equal_when_carried(cs, self, other)
+    }
+
+    /// Enforces that the number is less than the constant `c`, by witnessing the
+    /// limbs of `c - 1 - self` with as many bits as `c - 1` has and carrying their
+    /// sum with the number. Nothing is less than zero, so `c = 0` fails with
+    /// [`SynthesisError::Unsatisfiable`].
+    pub fn assert_less_than_constant<CS>(&self, cs: CS, c: &[u64]) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        self.assert_less_than(cs, &Nat::from_words(c))
+    }
+
+    fn assert_less_than<CS>(&self, mut cs: CS, c: &Nat) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let bound = c
+            .checked_sub(&Nat::one())
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let params = BigNatParams {
+            limb_width: self.limb_width,
+            n_limbs: limbs_for(bound.bits(), self.limb_width),
+        };
+
+        // Without a natural number to witness for a number above the bound, zero
+        // leaves the constraints unsatisfied.
+        let slack = self
+            .value()
+            .map(|v| bound.checked_sub(&v).unwrap_or_else(Nat::zero));
+        let slack = Self::alloc_nat(cs.namespace(|| "slack"), slack, params)?;
+
+        enforce_equal_when_carried(
+            cs.namespace(|| "sum"),
+            &self.add(&slack),
+            &Self::constant_nat::<CS>(&bound, params),
+        )
+    }
+}
+
+/// The number of limbs of `limb_width` bits that `bits` bits need, at least one.
+fn limbs_for(bits: usize, limb_width: usize) -> usize {
+    cmp::max((bits + limb_width - 1) / limb_width, 1)
+}
+
+/// Panics if a limb of at most `max_word` is too large to be carried, as checked by
+/// [`group_size`].
+fn assert_fits_field<F: PrimeField>(max_word: &Nat) {
+    assert!(
+        max_word.bits() + 4 <= F::CAPACITY as usize,
+        "limbs of {} bits do not fit in the scalar field",
+        max_word.bits()
+    );
+}
+
+/// How many limbs of `limb_width` bits, at most `max_word` each, can be grouped into
+/// one for carrying. For `b = max(log2(max_word), limb_width)` bits of the limbs and
+/// groups of `k`, the groups are less than `2^(b + (k - 1) limb_width + 1)`, the
+/// carries less than `2^(b - limb_width + 3)`, and carries multiplied by the width of
+/// a group less than `2^(b + (k - 1) limb_width + 3)`, so their sums do not wrap
+/// around the field if `b + (k - 1) limb_width + 4` bits fit in it.
+fn group_size<F: PrimeField>(max_word: &Nat, limb_width: usize) -> usize {
+    let bits = cmp::max(max_word.bits(), limb_width) + 4;
+    assert!(
+        bits <= F::CAPACITY as usize,
+        "limbs of {} bits do not fit in the scalar field",
+        bits - 4
+    );
+
+    (F::CAPACITY as usize - bits) / limb_width + 1
+}
+
+/// Enforces `a = b`, both with limbs of the same width, with the carries described in
+/// the [module documentation](self).
+fn enforce_equal_when_carried<E, CS>(
+    mut cs: CS,
+    a: &BigNat<E>,
+    b: &BigNat<E>,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(a.limb_width, b.limb_width);
+    let limb_width = a.limb_width;
+    let n_limbs = cmp::max(a.limbs.len(), b.limbs.len());
+    let max_word = cmp::max(&a.max_word, &b.max_word);
+
+    let group_len = cmp::min(group_size::<E::Fr>(max_word, limb_width), n_limbs);
+    let group_width = group_len * limb_width;
+    let n_groups = (n_limbs + group_len - 1) / group_len;
+
+    // A carry out of a group is at most (max_group + max_carry) / 2^group_width, so
+    // max_carry = ceil(max_group / (2^group_width - 1)) bounds all of them. They are
+    // shifted by max_carry, to be positive.
+    let max_group =
+        (0..group_len).fold(Nat::zero(), |acc, t| acc.add(&max_word.shl(t * limb_width)));
+    let divisor = Nat::ones(group_width);
+    let (max_carry, _) = max_group
+        .add(&divisor)
+        .checked_sub(&Nat::one())
+        .unwrap()
+        .div_rem(&divisor);
+    let carry_bits = max_carry.shl(1).bits();
+
+    let max_carry_fr = max_carry.to_field::<E::Fr>();
+    let shift = Nat::pow2(group_width).to_field::<E::Fr>();
+    let mut shifted_max_carry = max_carry_fr;
+    shifted_max_carry.mul_assign(&shift);
+
+    let group = |limbs: &[LinearCombination<E>], g: usize| {
+        let mut lc = LinearCombination::zero();
+        for t in 0..group_len {
+            if let Some(limb) = limbs.get(g * group_len + t) {
+                lc = lc + (Nat::pow2(t * limb_width).to_field::<E::Fr>(), limb);
+            }
+        }
+        lc
+    };
+
+    let mut carry_in: Option<LinearCombination<E>> = None;
+    for g in 0..n_groups {
+        let mut cs = cs.namespace(|| format!("group {}", g));
+
+        // a_g - b_g + c_g - c_(g+1) 2^group_width = 0, with shifted carries.
+        let mut sum = group(&a.limbs, g) - &group(&b.limbs, g);
+        if let Some(carry) = carry_in.take() {
+            sum = sum + &carry - (max_carry_fr, CS::one());
+        }
+        if g + 1 < n_groups {
+            let value = match (
+                a.value_below((g + 1) * group_len),
+                b.value_below((g + 1) * group_len),
+            ) {
+                (Some(a), Some(b)) => {
+                    Some(shifted_carry(&a, &b, (g + 1) * group_width, &max_carry))
+                }
+                _ => None,
+            };
+
+            let mut carry = LinearCombination::zero();
+            let mut coeff = E::Fr::one();
+            for i in 0..carry_bits {
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("carry bit {}", i)),
+                    value.as_ref().map(|v| v.bit(i)),
+                )?;
+                carry = carry + (coeff, bit.get_variable());
+                coeff.double();
+            }
+
+            sum = sum - (shift, &carry) + (shifted_max_carry, CS::one());
+            carry_in = Some(carry);
+        }
+
+        cs.enforce(|| "carry", |lc| lc, |lc| lc, |_| sum);
+    }
+
+    Ok(())
+}
+
+/// The carry `(a - b) / 2^shift` out of the limbs below `shift` bits, plus
+/// `max_carry`. A carry below `-max_carry` only comes with an assignment that does
+/// not satisfy the constraints, and is replaced by zero.
+fn shifted_carry(a: &Nat, b: &Nat, shift: usize, max_carry: &Nat) -> Nat {
+    match a.checked_sub(b) {
+        Some(diff) => max_carry.add(&diff.shr(shift)),
+        None => max_carry
+            .checked_sub(&b.checked_sub(a).unwrap().shr(shift))
+            .unwrap_or_else(Nat::zero),
+    }
+}
+
+/// A natural number of little endian 64-bit words, without leading zero words.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Nat(Vec<u64>);
+
+impl Nat {
+    fn zero() -> Self {
+        Nat(Vec::new())
+    }
+
+    fn one() -> Self {
+        Nat::from_u64(1)
+    }
+
+    fn from_u64(x: u64) -> Self {
+        Nat::from_words(&[x])
+    }
+
+    fn from_words(words: &[u64]) -> Self {
+        let mut nat = Nat(words.to_vec());
+        nat.trim();
+        nat
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    /// `2^k`.
+    fn pow2(k: usize) -> Self {
+        let mut words = vec![0; k / 64 + 1];
+        words[k / 64] = 1 << (k % 64);
+        Nat(words)
+    }
+
+    /// `2^k - 1`.
+    fn ones(k: usize) -> Self {
+        Nat::pow2(k).checked_sub(&Nat::one()).unwrap()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn bits(&self) -> usize {
+        match self.0.last() {
+            Some(top) => 64 * self.0.len() - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0
+            .get(i / 64)
+            .map_or(false, |word| (word >> (i % 64)) & 1 == 1)
+    }
+
+    /// The `i`-th limb of `width` bits.
+    fn limb(&self, i: usize, width: usize) -> Nat {
+        let shifted = self.shr(i * width);
+        let mut words = shifted.0;
+        words.truncate((width + 63) / 64);
+        if width % 64 != 0 {
+            if let Some(top) = words.get_mut(width / 64) {
+                *top &= (1 << (width % 64)) - 1;
+            }
+        }
+        Nat::from_words(&words)
+    }
+
+    fn add(&self, other: &Nat) -> Nat {
+        let len = cmp::max(self.0.len(), other.0.len());
+        let mut words = Vec::with_capacity(len + 1);
+        let mut carry = 0u128;
+        for i in 0..len {
+            let sum = u128::from(self.word(i)) + u128::from(other.word(i)) + carry;
+            words.push(sum as u64);
+            carry = sum >> 64;
+        }
+        words.push(carry as u64);
+
+        Nat::from_words(&words)
+    }
+
+    fn checked_sub(&self, other: &Nat) -> Option<Nat> {
+        if self < other {
+            return None;
+        }
+
+        let mut words = Vec::with_capacity(self.0.len());
+        let mut borrow = false;
+        for i in 0..self.0.len() {
+            let (diff, b1) = self.word(i).overflowing_sub(other.word(i));
+            let (diff, b2) = diff.overflowing_sub(u64::from(borrow));
+            words.push(diff);
+            borrow = b1 || b2;
+        }
+
+        Some(Nat::from_words(&words))
+    }
+
+    fn mul(&self, other: &Nat) -> Nat {
+        let mut words = vec![0u64; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.0.iter().enumerate() {
+                let t = u128::from(words[i + j]) + u128::from(a) * u128::from(b) + carry;
+                words[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            words[i + other.0.len()] = carry as u64;
+        }
+
+        Nat::from_words(&words)
+    }
+
+    fn shl(&self, k: usize) -> Nat {
+        if self.is_zero() {
+            return Nat::zero();
+        }
+
+        let (word_shift, bit_shift) = (k / 64, k % 64);
+        let mut words = vec![0u64; self.0.len() + word_shift + 1];
+        for (i, &w) in self.0.iter().enumerate() {
+            words[i + word_shift] |= w << bit_shift;
+            if bit_shift != 0 {
+                words[i + word_shift + 1] |= w >> (64 - bit_shift);
+            }
+        }
+
+        Nat::from_words(&words)
+    }
+
+    fn shr(&self, k: usize) -> Nat {
+        let (word_shift, bit_shift) = (k / 64, k % 64);
+        let words = (word_shift..self.0.len())
+            .map(|i| {
+                let low = self.0[i] >> bit_shift;
+                match (bit_shift, self.0.get(i + 1)) {
+                    (0, _) | (_, None) => low,
+                    (_, Some(&high)) => low | (high << (64 - bit_shift)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Nat::from_words(&words)
+    }
+
+    /// The quotient and the remainder of the division by `d`, bit by bit.
+    fn div_rem(&self, d: &Nat) -> (Nat, Nat) {
+        assert!(!d.is_zero(), "division by zero");
+
+        let mut quotient = vec![0u64; self.0.len()];
+        let mut remainder = Nat::zero();
+        for i in (0..self.bits()).rev() {
+            remainder = remainder.shl(1);
+            if self.bit(i) {
+                remainder = remainder.add(&Nat::one());
+            }
+            if let Some(rest) = remainder.checked_sub(d) {
+                remainder = rest;
+                quotient[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        (Nat::from_words(&quotient), remainder)
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        self.0.get(i).copied().unwrap_or(0)
+    }
+
+    /// The number as an element of `F`, which it must be less than the modulus of.
+    fn to_field<F: PrimeField>(&self) -> F {
+        let mut repr = F::Repr::default();
+        repr.as_mut()[..self.0.len()].copy_from_slice(&self.0);
+
+        F::from_repr(repr).expect("the number is less than the modulus")
+    }
+}
+
+impl Ord for Nat {
+    fn cmp(&self, other: &Nat) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for Nat {
+    fn partial_cmp(&self, other: &Nat) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::Bls12;
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    /// The modulus of the base field of secp256k1, `2^256 - 2^32 - 977`.
+    const SECP256K1_P: [u64; 4] = [
+        0xffff_fffe_ffff_fc2f,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ];
+
+    const PARAMS: BigNatParams = BigNatParams {
+        limb_width: 64,
+        n_limbs: 4,
+    };
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    /// A random number less than the modulus.
+    fn random_element(rng: &mut XorShiftRng) -> Nat {
+        let words = (0..4).map(|_| rng.next_u64()).collect::<Vec<_>>();
+        Nat::from_words(&words)
+            .div_rem(&Nat::from_words(&SECP256K1_P))
+            .1
+    }
+
+    fn alloc(cs: &mut TestConstraintSystem<Bls12>, name: &str, value: &Nat) -> BigNat<Bls12> {
+        BigNat::alloc(cs.namespace(|| name), Some(&value.0[..]), PARAMS).unwrap()
+    }
+
+    #[test]
+    fn test_nat() {
+        let mut rng = rng();
+        let p = Nat::from_words(&SECP256K1_P);
+
+        for _ in 0..100 {
+            let a = random_element(&mut rng);
+            let b = random_element(&mut rng);
+            assert!(a < p);
+
+            let (q, r) = a.mul(&b).div_rem(&p);
+            assert!(r < p);
+            assert_eq!(q.mul(&p).add(&r), a.mul(&b));
+            assert_eq!(a.add(&b).checked_sub(&b), Some(a.clone()));
+            assert_eq!(a.shl(77).shr(77), a);
+            assert_eq!(
+                (0..4).fold(Nat::zero(), |acc, i| acc.add(&a.limb(i, 64).shl(64 * i))),
+                a
+            );
+        }
+        assert_eq!(Nat::ones(65).bits(), 65);
+        assert_eq!(Nat::one().checked_sub(&p), None);
+    }
+
+    #[test]
+    fn test_bignat_bits() {
+        let mut rng = rng();
+        let a = random_element(&mut rng);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = alloc(&mut cs, "a", &a);
+        assert_eq!(cs.num_constraints(), 256);
+        assert_eq!(num.get_value(), Some(a.0.clone()));
+
+        let bits = num.to_bits_le(cs.namespace(|| "bits")).unwrap();
+        assert_eq!(cs.num_constraints(), 256 + 260);
+        for (i, bit) in bits.iter().enumerate() {
+            assert_eq!(bit.get_value(), Some(a.bit(i)));
+        }
+
+        let from_bits = BigNat::from_bits_le::<TestConstraintSystem<Bls12>>(&bits, 64);
+        assert_eq!(from_bits.get_value(), Some(a.0.clone()));
+        from_bits
+            .enforce_equal(cs.namespace(|| "equal"), &num)
+            .unwrap();
+        assert_eq!(cs.num_constraints(), 256 + 260 + 4);
+        assert!(cs.is_satisfied());
+
+        // 2^256 needs a fifth limb.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        assert!(matches!(
+            BigNat::alloc(&mut cs, Some(&[0, 0, 0, 0, 1][..]), PARAMS),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    #[test]
+    fn test_bignat_less_than_modulus() {
+        let p = Nat::from_words(&SECP256K1_P);
+        let below = p.checked_sub(&Nat::one()).unwrap();
+        let above = p.add(&Nat::one());
+
+        for (value, reduced) in &[(below, true), (p.clone(), false), (above, false)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let num = alloc(&mut cs, "num", value);
+            num.assert_less_than_constant(cs.namespace(|| "reduced"), &SECP256K1_P)
+                .unwrap();
+
+            assert_eq!(cs.is_satisfied(), *reduced, "{:?}", value);
+            assert_eq!(cs.num_constraints(), 256 + 261);
+        }
+    }
+
+    #[test]
+    fn test_bignat_mul_mod() {
+        let mut rng = rng();
+        let p = Nat::from_words(&SECP256K1_P);
+        let minus_one = p.checked_sub(&Nat::one()).unwrap();
+
+        let mut cases = vec![(minus_one.clone(), minus_one), (Nat::zero(), Nat::one())];
+        cases.extend((0..10).map(|_| (random_element(&mut rng), random_element(&mut rng))));
+        for (a, b) in cases {
+            let expected = a.mul(&b).div_rem(&p).1;
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let x = alloc(&mut cs, "a", &a);
+            let y = alloc(&mut cs, "b", &b);
+            let before = cs.num_constraints();
+            let product = x.mul_mod(cs.namespace(|| "mul"), &y, &SECP256K1_P).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints() - before, 1049);
+            assert_eq!(product.get_value(), Some(expected.0.clone()));
+            assert_eq!(product.params(), PARAMS);
+
+            // The product is the only reduced number equal to it.
+            let wrong = alloc(&mut cs, "wrong", &expected.add(&Nat::one()));
+            wrong
+                .enforce_equal(cs.namespace(|| "wrong product"), &product)
+                .unwrap();
+            assert!(!cs.is_satisfied());
+        }
+
+        // (a + b) * c, from limbs of 65 bits.
+        let (a, b, c) = (
+            random_element(&mut rng),
+            random_element(&mut rng),
+            random_element(&mut rng),
+        );
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let sum = alloc(&mut cs, "a", &a).add(&alloc(&mut cs, "b", &b));
+        let product = sum
+            .mul_mod(
+                cs.namespace(|| "mul"),
+                &alloc(&mut cs, "c", &c),
+                &SECP256K1_P,
+            )
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            product.get_value(),
+            Some(a.add(&b).mul(&c).div_rem(&p).1 .0)
+        );
+    }
+
+    #[test]
+    fn test_bignat_carry_overflow() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        // 2^66 in the third limb, claimed to be at most 2^64 - 1, and 4 in the fourth,
+        // are equal, but the carry of 4 out of the group of the first three limbs
+        // exceeds the bound of 1 the claim gives it.
+        let values = [0, 0, 1 << 66, 0]
+            .iter()
+            .map(|&v: &u128| Nat::from_words(&[v as u64, (v >> 64) as u64]))
+            .collect::<Vec<_>>();
+        let limbs = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let limb = cs
+                    .alloc(|| format!("limb {}", i), || Ok(v.to_field()))
+                    .unwrap();
+                LinearCombination::zero() + limb
+            })
+            .collect();
+        let forged = BigNat {
+            limbs,
+            values: Some(values),
+            limb_width: 64,
+            max_word: Nat::ones(64),
+        };
+        let honest = alloc(&mut cs, "honest", &Nat::from_u64(4).shl(192));
+        assert_eq!(forged.get_value(), honest.get_value());
+
+        forged
+            .enforce_equal(cs.namespace(|| "equal"), &honest)
+            .unwrap();
+        assert!(!cs.is_satisfied());
+        assert_eq!(cs.which_is_unsatisfied(), Some("equal/group 0/carry"));
+    }
+}
@@ -212,6 +212,70 @@ impl<E: ScalarEngine> AllocatedNum<E> {
         Ok(bits.into_iter().map(Boolean::from).collect())
     }
 
+    pub fn add<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "sum num",
+            || {
+                let mut tmp = *self.value.get()?;
+                tmp.add_assign(other.value.get()?);
+
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: (a + b) * 1 = a + b
+        cs.enforce(
+            || "addition constraint",
+            |lc| lc + self.variable + other.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + var,
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    pub fn sub<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "difference num",
+            || {
+                let mut tmp = *self.value.get()?;
+                tmp.sub_assign(other.value.get()?);
+
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: (a - b) * 1 = a - b
+        cs.enforce(
+            || "subtraction constraint",
+            |lc| lc + self.variable - other.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + var,
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
     pub fn mul<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<E>,
@@ -306,9 +370,176 @@ impl<E: ScalarEngine> AllocatedNum<E> {
         Ok(())
     }
 
+    /// The inverse of this number. Zero has none, so synthesis fails with
+    /// [`SynthesisError::DivisionByZero`] if the value is zero, and the constraint
+    /// `a * inv = 1` can't be satisfied for it by any witness.
+    pub fn invert<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "inverse num",
+            || {
+                let tmp = *self.value.get()?;
+
+                let inv = tmp.inverse().ok_or(SynthesisError::DivisionByZero)?;
+                value = Some(inv);
+
+                Ok(inv)
+            },
+        )?;
+
+        // Constrain: a * inv = 1
+        cs.enforce(
+            || "inversion constraint",
+            |lc| lc + self.variable,
+            |lc| lc + var,
+            |lc| lc + CS::one(),
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    /// The quotient of this number by `other`, with the constraint
+    /// `quotient * other = self`. Synthesis fails with
+    /// [`SynthesisError::DivisionByZero`] if `other` is zero. Note that the
+    /// constraint holds for any quotient if both numbers are zero, so `other` must
+    /// be known to be nonzero, or be checked with [`invert`](Self::invert).
+    pub fn div<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "quotient num",
+            || {
+                let mut tmp = other
+                    .value
+                    .get()?
+                    .inverse()
+                    .ok_or(SynthesisError::DivisionByZero)?;
+                tmp.mul_assign(self.value.get()?);
+
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: (a / b) * b = a
+        cs.enforce(
+            || "division constraint",
+            |lc| lc + var,
+            |lc| lc + other.variable,
+            |lc| lc + self.variable,
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    /// The inverse of this number, or zero for zero, and whether the number is zero.
+    /// This costs four constraints:
+    ///
+    /// * `a * inv = 1 - is_zero`, so that `is_zero` is set for zero, and `inv` is the
+    ///   inverse otherwise,
+    /// * `a * is_zero = 0`, so that `is_zero` is unset for other numbers,
+    /// * `inv * is_zero = 0`, so that `inv` is zero for zero,
+    /// * and the boolean constraint of `is_zero`.
+    pub fn invert_or_zero<CS>(&self, mut cs: CS) -> Result<(Self, Boolean), SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let is_zero =
+            AllocatedBit::alloc(cs.namespace(|| "is zero"), self.value.map(|v| v.is_zero()))?;
+
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "inverse num",
+            || {
+                let tmp = self.value.get()?.inverse().unwrap_or_else(E::Fr::zero);
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: a * inv = 1 - is_zero
+        cs.enforce(
+            || "inversion constraint",
+            |lc| lc + self.variable,
+            |lc| lc + var,
+            |lc| lc + CS::one() - is_zero.get_variable(),
+        );
+
+        // Constrain: a * is_zero = 0
+        cs.enforce(
+            || "zero flag constraint",
+            |lc| lc + self.variable,
+            |lc| lc + is_zero.get_variable(),
+            |lc| lc,
+        );
+
+        // Constrain: inv * is_zero = 0
+        cs.enforce(
+            || "zero inverse constraint",
+            |lc| lc + var,
+            |lc| lc + is_zero.get_variable(),
+            |lc| lc,
+        );
+
+        Ok((
+            AllocatedNum {
+                value,
+                variable: var,
+            },
+            Boolean::from(is_zero),
+        ))
+    }
+
+    /// Whether this number is zero, with two constraints.
+    pub fn is_zero<CS>(&self, cs: CS) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        is_zero_lc(cs, LinearCombination::zero() + self.variable, self.value)
+    }
+
+    /// Whether this number equals `other`, with two constraints.
+    pub fn equals<CS>(&self, cs: CS, other: &Self) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let value = match (self.value, other.value) {
+            (Some(mut a), Some(b)) => {
+                a.sub_assign(&b);
+                Some(a)
+            }
+            _ => None,
+        };
+
+        is_zero_lc(
+            cs,
+            LinearCombination::zero() + self.variable - other.variable,
+            value,
+        )
+    }
+
     /// Takes two allocated numbers (a, b) and returns
     /// (b, a) if the condition is true, and (a, b)
-    /// otherwise.
+    /// otherwise. A constant condition needs no
+    /// constraints. Otherwise both results take one
+    /// constraint over the same condition, so they are
+    /// always swapped together.
     pub fn conditionally_reverse<CS>(
         mut cs: CS,
         a: &Self,
@@ -318,6 +549,12 @@ impl<E: ScalarEngine> AllocatedNum<E> {
     where
         CS: ConstraintSystem<E>,
     {
+        match *condition {
+            Boolean::Constant(false) => return Ok((a.clone(), b.clone())),
+            Boolean::Constant(true) => return Ok((b.clone(), a.clone())),
+            _ => {}
+        }
+
         let c = Self::alloc(cs.namespace(|| "conditional reversal result 1"), || {
             if *condition.get_value().get()? {
                 Ok(*b.value.get()?)
@@ -366,6 +603,58 @@ pub struct Num<E: ScalarEngine> {
     lc: LinearCombination<E>,
 }
 
+/// Whether `lc`, of value `value`, is zero, with the constraints
+/// `lc * inv = 1 - is_zero` and `lc * is_zero = 0`. The second one leaves `is_zero`
+/// no value but zero if `lc` is not zero, and the first one no value but one if it
+/// is, so no other constraint is needed to make it a boolean.
+fn is_zero_lc<E, CS>(
+    mut cs: CS,
+    lc: LinearCombination<E>,
+    value: Option<E::Fr>,
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let is_zero_value = value.map(|v| v.is_zero());
+
+    let is_zero = cs.alloc(
+        || "is zero",
+        || {
+            if *is_zero_value.get()? {
+                Ok(E::Fr::one())
+            } else {
+                Ok(E::Fr::zero())
+            }
+        },
+    )?;
+    let inv = cs.alloc(
+        || "ephemeral inverse",
+        || Ok(value.get()?.inverse().unwrap_or_else(E::Fr::zero)),
+    )?;
+
+    // Constrain: lc * inv = 1 - is_zero
+    cs.enforce(
+        || "inversion constraint",
+        |_| lc.clone(),
+        |lc| lc + inv,
+        |lc| lc + CS::one() - is_zero,
+    );
+
+    // Constrain: lc * is_zero = 0
+    cs.enforce(
+        || "zero flag constraint",
+        |_| lc,
+        |lc| lc + is_zero,
+        |lc| lc,
+    );
+
+    Ok(Boolean::from(AllocatedBit::from_constrained_variable(
+        is_zero,
+        is_zero_value,
+    )))
+}
+
 impl<E: ScalarEngine> From<AllocatedNum<E>> for Num<E> {
     fn from(num: AllocatedNum<E>) -> Num<E> {
         Num {
@@ -383,6 +672,14 @@ impl<E: ScalarEngine> Num<E> {
         }
     }
 
+    /// The constant `value`, where `one` is the variable of the constant one.
+    pub fn constant(one: Variable, value: E::Fr) -> Self {
+        Num {
+            value: Some(value),
+            lc: LinearCombination::zero() + (value, one),
+        }
+    }
+
     pub fn get_value(&self) -> Option<E::Fr> {
         self.value
     }
@@ -424,6 +721,54 @@ impl<E: ScalarEngine> Num<E> {
         Num { value, lc }
     }
 
+    /// Whether this number is zero. A constant gives a constant, without any
+    /// constraints, and otherwise this is [`AllocatedNum::is_zero`].
+    pub fn is_zero<CS>(&self, cs: CS) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        match self.constant_value::<CS>() {
+            Some(value) => Ok(Boolean::constant(value.is_zero())),
+            None => is_zero_lc(cs, self.lc.clone(), self.value),
+        }
+    }
+
+    /// Whether this number equals `other`, like [`is_zero`](Self::is_zero) of their
+    /// difference.
+    pub fn equals<CS>(&self, cs: CS, other: &Self) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<E>,
+    {
+        let value = match (self.value, other.value) {
+            (Some(mut a), Some(b)) => {
+                a.sub_assign(&b);
+                Some(a)
+            }
+            _ => None,
+        };
+
+        Num {
+            value,
+            lc: self.lc(E::Fr::one()) - &other.lc,
+        }
+        .is_zero(cs)
+    }
+
+    /// The value of this number, if its linear combination only has terms of the
+    /// constant one.
+    pub(crate) fn constant_value<CS: ConstraintSystem<E>>(&self) -> Option<E::Fr> {
+        let mut value = E::Fr::zero();
+        for (var, coeff) in self.lc.iter() {
+            if *var == CS::one() {
+                value.add_assign(coeff);
+            } else if !coeff.is_zero() {
+                return None;
+            }
+        }
+
+        Some(value)
+    }
+
     pub fn scale(mut self, scalar: E::Fr) -> Self {
         for (_variable, fr) in self.lc.0.iter_mut() {
             fr.mul_assign(&scalar);
@@ -440,12 +785,12 @@ impl<E: ScalarEngine> Num<E> {
 #[cfg(test)]
 mod test {
     use crate::bls::{Bls12, Fr};
-    use crate::ConstraintSystem;
+    use crate::{ConstraintSystem, SynthesisError};
     use ff::{BitIterator, Field, PrimeField};
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
 
-    use super::{AllocatedNum, Boolean, Num};
+    use super::{AllocatedBit, AllocatedNum, Boolean, Num};
     use crate::gadgets::test::*;
 
     #[test]
@@ -488,6 +833,26 @@ mod test {
         assert!(!cs.is_satisfied());
     }
 
+    #[test]
+    fn test_num_addition_and_subtraction() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let n =
+            AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("12").unwrap())).unwrap();
+        let n2 =
+            AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("10").unwrap())).unwrap();
+        let n3 = n.add(cs.namespace(|| "add"), &n2).unwrap();
+        let n4 = n.sub(cs.namespace(|| "sub"), &n2).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("add/sum num") == Fr::from_str("22").unwrap());
+        assert!(n3.value.unwrap() == Fr::from_str("22").unwrap());
+        assert!(cs.get("sub/difference num") == Fr::from_str("2").unwrap());
+        assert!(n4.value.unwrap() == Fr::from_str("2").unwrap());
+        cs.set("sub/difference num", Fr::from_str("3").unwrap());
+        assert!(!cs.is_satisfied());
+    }
+
     #[test]
     fn test_num_conditional_reversal() {
         let mut rng = XorShiftRng::from_seed([
@@ -523,6 +888,68 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_num_conditional_reversal_allocated() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &reversed in &[false, true] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let condition = Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| "condition"), Some(reversed)).unwrap(),
+            );
+            let (c, d) =
+                AllocatedNum::conditionally_reverse(cs.namespace(|| "reverse"), &a, &b, &condition)
+                    .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 1 + 2);
+            if reversed {
+                assert_eq!((c.value, d.value), (b.value, a.value));
+            } else {
+                assert_eq!((c.value, d.value), (a.value, b.value));
+            }
+
+            // Neither result can be swapped on its own.
+            let (first, second) = if reversed {
+                (b.value.unwrap(), a.value.unwrap())
+            } else {
+                (a.value.unwrap(), b.value.unwrap())
+            };
+            cs.set("reverse/conditional reversal result 2/num", first);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("reverse/second conditional reversal")
+            );
+            cs.set("reverse/conditional reversal result 2/num", second);
+            cs.set("reverse/conditional reversal result 1/num", second);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("reverse/first conditional reversal")
+            );
+        }
+
+        // A constant condition needs no constraints.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+        for &reversed in &[false, true] {
+            AllocatedNum::conditionally_reverse(
+                cs.namespace(|| format!("reverse {}", reversed)),
+                &a,
+                &b,
+                &Boolean::constant(reversed),
+            )
+            .unwrap();
+        }
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
     #[test]
     fn test_num_nonzero() {
         {
@@ -543,6 +970,250 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_num_invert() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let value = if i == 0 {
+                Fr::one()
+            } else {
+                Fr::random(&mut rng)
+            };
+            let n = AllocatedNum::alloc(&mut cs, || Ok(value)).unwrap();
+            let inv = n.invert(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 1);
+            assert_eq!(inv.value.unwrap(), value.inverse().unwrap());
+
+            // A forged inverse.
+            let mut forged = inv.value.unwrap();
+            forged.add_assign(&Fr::one());
+            cs.set("inverse num", forged);
+            assert_eq!(cs.which_is_unsatisfied(), Some("inversion constraint"));
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::zero())).unwrap();
+            assert!(matches!(
+                n.invert(&mut cs),
+                Err(SynthesisError::DivisionByZero)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_num_div() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let (a, b) = match i {
+                0 => (Fr::zero(), Fr::random(&mut rng)),
+                1 => (Fr::random(&mut rng), Fr::one()),
+                _ => (Fr::random(&mut rng), Fr::random(&mut rng)),
+            };
+            let n = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap();
+            let n2 = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+            let n3 = n.div(&mut cs, &n2).unwrap();
+
+            assert!(cs.is_satisfied());
+            let mut product = n3.value.unwrap();
+            product.mul_assign(&b);
+            assert_eq!(product, a);
+
+            let mut forged = n3.value.unwrap();
+            forged.add_assign(&Fr::one());
+            cs.set("quotient num", forged);
+            assert_eq!(cs.which_is_unsatisfied(), Some("division constraint"));
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::one())).unwrap();
+            let n2 = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::zero())).unwrap();
+            assert!(matches!(
+                n.div(&mut cs, &n2),
+                Err(SynthesisError::DivisionByZero)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_num_invert_or_zero() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let value = match i {
+                0 => Fr::zero(),
+                1 => Fr::one(),
+                _ => Fr::random(&mut rng),
+            };
+            let n = AllocatedNum::alloc(&mut cs, || Ok(value)).unwrap();
+            let (inv, is_zero) = n.invert_or_zero(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 4);
+            assert_eq!(is_zero.get_value(), Some(value.is_zero()));
+            assert_eq!(inv.value.unwrap(), value.inverse().unwrap_or_else(Fr::zero));
+
+            // A forged inverse, which for zero is any nonzero value.
+            let mut forged = inv.value.unwrap();
+            forged.add_assign(&Fr::one());
+            cs.set("inverse num", forged);
+            assert!(!cs.is_satisfied());
+            cs.set("inverse num", inv.value.unwrap());
+            assert!(cs.is_satisfied());
+
+            // A forged flag, claiming that zero is not zero, or the other way around.
+            let flag = if value.is_zero() {
+                Fr::zero()
+            } else {
+                Fr::one()
+            };
+            cs.set("is zero/boolean", flag);
+            assert!(!cs.is_satisfied());
+            if !value.is_zero() {
+                cs.set("inverse num", Fr::zero());
+                assert_eq!(cs.which_is_unsatisfied(), Some("zero flag constraint"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_is_zero() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let value = match i {
+                0 => Fr::zero(),
+                1 => Fr::one(),
+                _ => Fr::random(&mut rng),
+            };
+            let n = AllocatedNum::alloc(&mut cs, || Ok(value)).unwrap();
+            let is_zero = n.is_zero(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 2);
+            assert_eq!(is_zero.get_value(), Some(value.is_zero()));
+
+            if value.is_zero() {
+                // Forced false for zero, with any inverse.
+                cs.set("is zero", Fr::zero());
+                assert_eq!(cs.which_is_unsatisfied(), Some("inversion constraint"));
+                cs.set("ephemeral inverse", Fr::random(&mut rng));
+                assert_eq!(cs.which_is_unsatisfied(), Some("inversion constraint"));
+            } else {
+                // Forced true for another number, with the inverse it needs.
+                cs.set("is zero", Fr::one());
+                assert!(!cs.is_satisfied());
+                cs.set("ephemeral inverse", Fr::zero());
+                assert_eq!(cs.which_is_unsatisfied(), Some("zero flag constraint"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_equals() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = Fr::random(&mut rng);
+            let b = if i % 2 == 0 { a } else { Fr::random(&mut rng) };
+            let n = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap();
+            let n2 = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+            let equal = n.equals(cs.namespace(|| "equals"), &n2).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(equal.get_value(), Some(a == b));
+
+            // The flag can't be flipped, whatever the inverse.
+            let (flag, inverse) = if a == b {
+                (Fr::zero(), Fr::random(&mut rng))
+            } else {
+                (Fr::one(), Fr::zero())
+            };
+            cs.set("equals/is zero", flag);
+            cs.set("equals/ephemeral inverse", inverse);
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_num_is_zero_constants() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let one = TestConstraintSystem::<Bls12>::one();
+
+        let a = Fr::random(&mut rng);
+        let constant = Num::<Bls12>::constant(one, a);
+        let zero = Num::<Bls12>::zero();
+
+        // Constants need no constraints.
+        assert!(matches!(
+            zero.is_zero(cs.namespace(|| "zero")).unwrap(),
+            Boolean::Constant(true)
+        ));
+        assert!(matches!(
+            constant.is_zero(cs.namespace(|| "constant")).unwrap(),
+            Boolean::Constant(false)
+        ));
+        assert!(matches!(
+            constant
+                .equals(cs.namespace(|| "same"), &Num::constant(one, a))
+                .unwrap(),
+            Boolean::Constant(true)
+        ));
+        assert!(matches!(
+            constant.equals(cs.namespace(|| "other"), &zero).unwrap(),
+            Boolean::Constant(false)
+        ));
+        assert_eq!(cs.num_constraints(), 0);
+
+        // A variable needs them.
+        let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(a)).unwrap();
+        let equal = Num::from(n)
+            .equals(cs.namespace(|| "variable"), &constant)
+            .unwrap();
+        assert_eq!(equal.get_value(), Some(true));
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 2);
+
+        cs.set("variable/is zero", Fr::zero());
+        assert!(!cs.is_satisfied());
+    }
+
     #[test]
     fn test_into_bits_strict() {
         let mut negone = Fr::one();
@@ -0,0 +1,228 @@
+//! Pedersen commitments on a twisted Edwards curve over the scalar field, like
+//! Jubjub, whose openings can be checked inside a circuit.
+//!
+//! A commitment to `value` with the random `blinding` is `[value]G + [blinding]H` for
+//! the generators of [`EdwardsPedersenGenerators`], which are hashed from
+//! [`PEDERSEN_DOMAIN`] like those of the commitments in G1. Values and blindings are
+//! scalars of the prime-order subgroup, given by their little-endian bits, so a value
+//! is only bound modulo the order of the subgroup; circuits needing a unique value
+//! enforce its range themselves.
+
+use ff::{Field, PrimeField, ScalarEngine, SqrtField};
+use sha2::Sha256;
+
+use super::boolean::Boolean;
+use super::ecc::{CurveParams, EdwardsPoint};
+use crate::transcript::FiatShamirRng;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// Domain separator the generators are derived from.
+pub const PEDERSEN_DOMAIN: &[u8] = b"bellperson-edwards-pedersen-generators";
+
+/// The curve of the commitments and their generators `G` and `H`, derived once by
+/// their owner and passed to every commitment and opening.
+#[derive(Clone, Debug)]
+pub struct EdwardsPedersenGenerators<F: PrimeField> {
+    pub curve: CurveParams<F>,
+    pub g: (F, F),
+    pub h: (F, F),
+}
+
+impl<F: PrimeField + SqrtField> EdwardsPedersenGenerators<F> {
+    /// Hashes [`PEDERSEN_DOMAIN`] to two points of the prime-order subgroup of
+    /// `curve` of unknown discrete logarithms.
+    pub fn new(curve: CurveParams<F>) -> Self {
+        let mut rng = FiatShamirRng::<Sha256>::new(PEDERSEN_DOMAIN);
+        let g = hash_to_subgroup(&curve, &mut rng);
+        let h = hash_to_subgroup(&curve, &mut rng);
+
+        EdwardsPedersenGenerators { curve, g, h }
+    }
+}
+
+impl<F: PrimeField> EdwardsPedersenGenerators<F> {
+    /// Commits to the little-endian bits `value`, hidden by the little-endian bits
+    /// `blinding`, which must be a scalar chosen uniformly at random.
+    pub fn commit(&self, value: &[bool], blinding: &[bool]) -> (F, F) {
+        self.curve.add(
+            &self.curve.mul(&self.g, value),
+            &self.curve.mul(&self.h, blinding),
+        )
+    }
+
+    /// Checks that `commitment` opens to `value` with `blinding`, outside of a
+    /// circuit.
+    pub fn verify_opening(&self, commitment: &(F, F), value: &[bool], blinding: &[bool]) -> bool {
+        self.commit(value, blinding) == *commitment
+    }
+}
+
+/// A point of the prime-order subgroup from `rng`, the multiple by the cofactor of a
+/// point with a random `y`.
+fn hash_to_subgroup<F, R>(curve: &CurveParams<F>, rng: &mut R) -> (F, F)
+where
+    F: PrimeField + SqrtField,
+    R: rand_core::RngCore,
+{
+    let cofactor = (0..64)
+        .map(|i| (curve.cofactor >> i) & 1 == 1)
+        .collect::<Vec<_>>();
+
+    loop {
+        // x^2 = (1 - y^2) / (a - d * y^2)
+        let y = F::random(rng);
+        let mut y2 = y;
+        y2.square();
+
+        let mut num = F::one();
+        num.sub_assign(&y2);
+        let mut den = y2;
+        den.mul_assign(&curve.d);
+        den.negate();
+        den.add_assign(&curve.a);
+
+        let x = den.inverse().and_then(|inv| {
+            num.mul_assign(&inv);
+            num.sqrt()
+        });
+        if let Some(x) = x {
+            // Only the points of small order have a multiple with `x = 0`.
+            let p = curve.mul(&(x, y), &cofactor);
+            if !p.0.is_zero() {
+                return p;
+            }
+        }
+    }
+}
+
+/// Whether `commitment` opens to the little-endian bits `value_bits` with the
+/// little-endian bits `blinding_bits`, neither of which may be empty.
+pub fn verify_opening<E, CS>(
+    mut cs: CS,
+    generators: &EdwardsPedersenGenerators<E::Fr>,
+    commitment: &EdwardsPoint<E>,
+    value_bits: &[Boolean],
+    blinding_bits: &[Boolean],
+) -> Result<Boolean, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let curve = &generators.curve;
+
+    let value = EdwardsPoint::fixed_base_mul(
+        cs.namespace(|| "[value]G"),
+        &generators.g,
+        value_bits,
+        curve,
+    )?;
+    let blinding = EdwardsPoint::fixed_base_mul(
+        cs.namespace(|| "[blinding]H"),
+        &generators.h,
+        blinding_bits,
+        curve,
+    )?;
+    let expected = value.add(cs.namespace(|| "sum"), &blinding, curve)?;
+
+    let x_equal = expected
+        .get_x()
+        .equals(cs.namespace(|| "x equal"), commitment.get_x())?;
+    let y_equal = expected
+        .get_y()
+        .equals(cs.namespace(|| "y equal"), commitment.get_y())?;
+
+    Boolean::and(cs.namespace(|| "equal"), &x_equal, &y_equal)
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::*;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn alloc_bits(cs: &mut TestConstraintSystem<Bls12>, name: &str, bits: &[bool]) -> Vec<Boolean> {
+        bits.iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("{} {}", name, i)), Some(*b))
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Checks the opening in a new constraint system, which must be satisfied.
+    fn check_opening(
+        generators: &EdwardsPedersenGenerators<Fr>,
+        commitment: &(Fr, Fr),
+        value: &[bool],
+        blinding: &[bool],
+    ) -> bool {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let commitment = EdwardsPoint::alloc(
+            cs.namespace(|| "commitment"),
+            Some(*commitment),
+            &generators.curve,
+        )
+        .unwrap();
+        let value = alloc_bits(&mut cs, "value", value);
+        let blinding = alloc_bits(&mut cs, "blinding", blinding);
+
+        let valid = verify_opening(
+            cs.namespace(|| "open"),
+            generators,
+            &commitment,
+            &value,
+            &blinding,
+        )
+        .unwrap();
+        assert!(cs.is_satisfied());
+
+        valid.get_value().unwrap()
+    }
+
+    #[test]
+    fn test_edwards_pedersen() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let generators = EdwardsPedersenGenerators::new(CurveParams::jubjub());
+        assert!(generators.curve.is_on_curve(&generators.g));
+        assert!(generators.curve.is_on_curve(&generators.h));
+        assert_ne!(generators.g, generators.h);
+        assert_eq!(
+            generators.g,
+            EdwardsPedersenGenerators::new(CurveParams::jubjub()).g
+        );
+
+        let mut bits = |n: usize| (0..n).map(|_| rng.next_u32() & 1 == 1).collect::<Vec<_>>();
+        let (value, blinding) = (bits(64), bits(251));
+        let commitment = generators.commit(&value, &blinding);
+        assert!(generators.verify_opening(&commitment, &value, &blinding));
+        assert!(check_opening(&generators, &commitment, &value, &blinding));
+
+        // A flipped bit of the value or the blinding.
+        let mut flipped = value.clone();
+        flipped[5] = !flipped[5];
+        assert!(!generators.verify_opening(&commitment, &flipped, &blinding));
+        assert!(!check_opening(
+            &generators,
+            &commitment,
+            &flipped,
+            &blinding
+        ));
+        let mut flipped = blinding.clone();
+        flipped[0] = !flipped[0];
+        assert!(!check_opening(&generators, &commitment, &value, &flipped));
+
+        // Another commitment to the same value.
+        let other = generators.commit(&value, &bits(251));
+        assert!(!check_opening(&generators, &other, &value, &blinding));
+    }
+}
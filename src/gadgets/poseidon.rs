@@ -0,0 +1,495 @@
+//! The [Poseidon](crate::poseidon) hash, computing the same hashes as
+//! [`PoseidonConstants::hash`] for any constants, e.g. those of
+//! [`PoseidonConstants::new`], the standard ones of [`PoseidonConstants::standard`]
+//! or constants generated elsewhere.
+//!
+//! Every S-box costs 3 constraints, so a permutation costs
+//! `3 * (width * full_rounds + partial_rounds)`: the partial rounds only constrain the
+//! first element of the state, and the other elements stay linear combinations of the
+//! S-box outputs, whose linear layers are free. The S-boxes of constant elements, like
+//! the capacity in the first round, cost nothing, and allocating the hash costs one
+//! constraint. Hashing two elements with [`PoseidonConstants::new(2)`] costs 235
+//! constraints, and eight elements with [`PoseidonConstants::new(8)`] 385.
+//!
+//! [`PoseidonConstants::new(2)`]: PoseidonConstants::new
+//! [`PoseidonConstants::new(8)`]: PoseidonConstants::new
+
+use std::mem;
+
+use ff::Field;
+
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::bls::Engine;
+use crate::poseidon::{self, domain_tag, PoseidonConstants};
+use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+/// Domain of the tags of the hashes of [`poseidon_hash`].
+pub const POSEIDON_HASH_DOMAIN: &[u8] = b"bellperson-poseidon-gadget";
+
+/// An element of the state.
+enum Elt<E: Engine> {
+    Constant(E::Fr),
+    /// `lc + constant`, whose value is `value`.
+    Num {
+        value: Option<E::Fr>,
+        lc: LinearCombination<E>,
+        constant: E::Fr,
+    },
+}
+
+impl<E: Engine> Elt<E> {
+    fn value(&self) -> Option<E::Fr> {
+        match self {
+            Elt::Constant(c) => Some(*c),
+            Elt::Num { value, .. } => *value,
+        }
+    }
+
+    fn lc(&self, one: Variable) -> LinearCombination<E> {
+        match self {
+            Elt::Constant(c) => LinearCombination::zero() + (*c, one),
+            Elt::Num { lc, constant, .. } => lc.clone() + (*constant, one),
+        }
+    }
+
+    fn add_constant(&mut self, c: &E::Fr) {
+        match self {
+            Elt::Constant(x) => x.add_assign(c),
+            Elt::Num {
+                value, constant, ..
+            } => {
+                constant.add_assign(c);
+                if let Some(value) = value {
+                    value.add_assign(c);
+                }
+            }
+        }
+    }
+
+    fn add_num(&mut self, num: &AllocatedNum<E>) {
+        let (value, lc, constant) = match self {
+            Elt::Constant(c) => (Some(*c), LinearCombination::zero(), *c),
+            Elt::Num {
+                value,
+                lc,
+                constant,
+            } => (*value, mem::take(lc), *constant),
+        };
+
+        *self = Elt::Num {
+            value: value.and_then(|mut value| {
+                value.add_assign(&num.get_value()?);
+                Some(value)
+            }),
+            lc: lc + num.get_variable(),
+            constant,
+        };
+    }
+
+    /// `sum(coeffs[i] * elts[i])`
+    fn linear_combination(coeffs: &[E::Fr], elts: &[Elt<E>]) -> Self {
+        let mut value = Some(E::Fr::zero());
+        let mut lc = None;
+        let mut constant = E::Fr::zero();
+
+        for (coeff, elt) in coeffs.iter().zip(elts) {
+            value = value.and_then(|mut value| {
+                let mut term = elt.value()?;
+                term.mul_assign(coeff);
+                value.add_assign(&term);
+                Some(value)
+            });
+            let mut term = match elt {
+                Elt::Constant(c) => *c,
+                Elt::Num {
+                    lc: elt_lc,
+                    constant: elt_constant,
+                    ..
+                } => {
+                    lc = Some(lc.unwrap_or_else(LinearCombination::zero) + (*coeff, elt_lc));
+                    *elt_constant
+                }
+            };
+            term.mul_assign(coeff);
+            constant.add_assign(&term);
+        }
+
+        match lc {
+            Some(lc) => Elt::Num {
+                value,
+                lc,
+                constant,
+            },
+            None => Elt::Constant(constant),
+        }
+    }
+}
+
+impl<E: Engine> From<AllocatedNum<E>> for Elt<E> {
+    fn from(num: AllocatedNum<E>) -> Self {
+        Elt::Num {
+            value: num.get_value(),
+            lc: LinearCombination::zero() + num.get_variable(),
+            constant: E::Fr::zero(),
+        }
+    }
+}
+
+/// `x^5`, allocating `x^2`, `x^4` and `x^5` unless `x` is constant.
+fn quintic_s_box<E, CS>(mut cs: CS, x: &Elt<E>) -> Result<Elt<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let value = match x {
+        Elt::Constant(c) => {
+            let mut c = *c;
+            poseidon::quintic_s_box(&mut c);
+            return Ok(Elt::Constant(c));
+        }
+        Elt::Num { value, .. } => *value,
+    };
+    let x_lc = x.lc(CS::one());
+
+    let x2 = AllocatedNum::alloc(cs.namespace(|| "x^2"), || {
+        let mut x2 = *value.get()?;
+        x2.square();
+        Ok(x2)
+    })?;
+    cs.enforce(
+        || "x * x = x^2",
+        |lc| lc + &x_lc,
+        |lc| lc + &x_lc,
+        |lc| lc + x2.get_variable(),
+    );
+
+    let x4 = x2.square(cs.namespace(|| "x^4"))?;
+
+    let x5 = AllocatedNum::alloc(cs.namespace(|| "x^5"), || {
+        let mut x5 = *x4.get_value().get()?;
+        x5.mul_assign(value.get()?);
+        Ok(x5)
+    })?;
+    cs.enforce(
+        || "x^4 * x = x^5",
+        |lc| lc + x4.get_variable(),
+        |lc| lc + &x_lc,
+        |lc| lc + x5.get_variable(),
+    );
+
+    Ok(x5.into())
+}
+
+/// Applies the permutation of `constants` to `state`, like [`PoseidonConstants::permute`].
+fn permute<E, CS>(
+    mut cs: CS,
+    mut state: Vec<Elt<E>>,
+    constants: &PoseidonConstants<E>,
+) -> Result<Vec<Elt<E>>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let half = constants.full_rounds / 2;
+    let rounds = constants.full_rounds + constants.partial_rounds;
+
+    for (round, round_constants) in constants
+        .round_constants
+        .chunks(constants.width())
+        .enumerate()
+    {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        for (s, c) in state.iter_mut().zip(round_constants) {
+            s.add_constant(c);
+        }
+        if round < half || round >= rounds - half {
+            for (i, s) in state.iter_mut().enumerate() {
+                *s = quintic_s_box(cs.namespace(|| format!("s-box {}", i)), s)?;
+            }
+        } else {
+            // The other elements go through the round without a constraint.
+            state[0] = quintic_s_box(cs.namespace(|| "s-box 0"), &state[0])?;
+        }
+        state = constants
+            .mds
+            .iter()
+            .map(|row| Elt::linear_combination(row, &state))
+            .collect();
+    }
+
+    Ok(state)
+}
+
+/// Hashes `preimage` with the tag of [`POSEIDON_HASH_DOMAIN`] for its length, the hash
+/// [`PoseidonConstants::hash`] computes for the tag
+/// `domain_tag(POSEIDON_HASH_DOMAIN, preimage.len())`.
+pub fn poseidon_hash<E, CS>(
+    cs: CS,
+    preimage: &[AllocatedNum<E>],
+    constants: &PoseidonConstants<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let tag = domain_tag::<E>(POSEIDON_HASH_DOMAIN, preimage.len());
+
+    poseidon_hash_with_tag(cs, tag, preimage, constants)
+}
+
+/// Hashes `preimage` with `domain_tag` in the capacity, like
+/// [`PoseidonConstants::hash`].
+pub fn poseidon_hash_with_tag<E, CS>(
+    mut cs: CS,
+    domain_tag: E::Fr,
+    preimage: &[AllocatedNum<E>],
+    constants: &PoseidonConstants<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let width = constants.width();
+    assert!(
+        constants.mds.len() == width && constants.mds.iter().all(|row| row.len() == width),
+        "the MDS matrix must have the width of the state"
+    );
+    assert_eq!(
+        constants.round_constants.len(),
+        width * (constants.full_rounds + constants.partial_rounds),
+        "there must be one round constant per element and round"
+    );
+
+    let mut state = (0..width)
+        .map(|i| match i {
+            0 => Elt::Constant(domain_tag),
+            _ => Elt::Constant(E::Fr::zero()),
+        })
+        .collect::<Vec<_>>();
+
+    let chunks = if preimage.is_empty() {
+        vec![preimage]
+    } else {
+        preimage.chunks(constants.arity).collect()
+    };
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            s.add_num(x);
+        }
+        state = permute(
+            cs.namespace(|| format!("permutation {}", i)),
+            state,
+            constants,
+        )?;
+    }
+
+    let out = &state[1];
+    let hash = AllocatedNum::alloc(cs.namespace(|| "hash"), || {
+        out.value().ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+        || "hash is the first rate element",
+        |lc| lc + &out.lc(CS::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + hash.get_variable(),
+    );
+
+    Ok(hash)
+}
+
+/// Hashes two elements with constants of arity 2, e.g. the children of a node of a
+/// binary Merkle tree.
+pub fn poseidon_hash_2<E, CS>(
+    cs: CS,
+    preimage: &[AllocatedNum<E>; 2],
+    constants: &PoseidonConstants<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(constants.arity, 2, "the constants must have arity 2");
+
+    poseidon_hash(cs, preimage, constants)
+}
+
+/// Hashes eight elements with constants of arity 8, e.g. the children of a node of an
+/// octree.
+pub fn poseidon_hash_8<E, CS>(
+    cs: CS,
+    preimage: &[AllocatedNum<E>; 8],
+    constants: &PoseidonConstants<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(constants.arity, 8, "the constants must have arity 8");
+
+    poseidon_hash(cs, preimage, constants)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bls::{Bls12, Fr};
+    use ff::{Field, PrimeField};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+
+    fn alloc_preimage<CS: ConstraintSystem<Bls12>>(
+        mut cs: CS,
+        values: &[Fr],
+    ) -> Vec<AllocatedNum<Bls12>> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("preimage {}", i)), || Ok(*value))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_poseidon_hash_matches_native() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &(arity, len) in &[(1, 3), (2, 0), (2, 2), (2, 5), (4, 3), (8, 8), (8, 17)] {
+            let constants = PoseidonConstants::<Bls12>::new(arity);
+            let values = (0..len).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let preimage = alloc_preimage(cs.namespace(|| "preimage"), &values);
+            let hash = poseidon_hash(&mut cs, &preimage, &constants).unwrap();
+
+            assert!(cs.is_satisfied());
+            let tag = domain_tag::<Bls12>(POSEIDON_HASH_DOMAIN, len);
+            assert_eq!(hash.get_value().unwrap(), constants.hash(tag, &values));
+
+            // The hash is bound to the preimage.
+            let mut wrong = hash.get_value().unwrap();
+            wrong.add_assign(&Fr::one());
+            cs.set("hash/num", wrong);
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_poseidon_hash_external_constants() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        // Constants built elsewhere, with other rounds and a random matrix.
+        let (arity, full_rounds, partial_rounds) = (3, 4, 11);
+        let width = arity + 1;
+        let constants = PoseidonConstants::<Bls12> {
+            arity,
+            full_rounds,
+            partial_rounds,
+            mds: (0..width)
+                .map(|_| (0..width).map(|_| Fr::random(rng)).collect())
+                .collect(),
+            round_constants: (0..width * (full_rounds + partial_rounds))
+                .map(|_| Fr::random(rng))
+                .collect(),
+        };
+        let tag = Fr::random(rng);
+        let values = (0..7).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let preimage = alloc_preimage(cs.namespace(|| "preimage"), &values);
+        let hash = poseidon_hash_with_tag(&mut cs, tag, &preimage, &constants).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(hash.get_value().unwrap(), constants.hash(tag, &values));
+        // Three permutations, the first with a constant capacity.
+        assert_eq!(
+            cs.num_constraints(),
+            3 * (3 * (width * full_rounds + partial_rounds) - 1) + 1
+        );
+    }
+
+    #[test]
+    fn test_poseidon_hash_standard() {
+        // With a zero tag, hashing `1, ..., arity` is the permutation of the test
+        // vectors of the reference implementation, of which the hash is the second
+        // element.
+        for &(arity, expected) in &[
+            (
+                2,
+                "37068251774887509885063625701815026138353041152735229476479055620962268601796",
+            ),
+            (
+                4,
+                "46100521179547351030890356806707858465640857221128207179269773056079077808119",
+            ),
+        ] {
+            let constants = PoseidonConstants::<Bls12>::standard(arity);
+            let values = (1..=arity)
+                .map(|i| Fr::from_str(&i.to_string()).unwrap())
+                .collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let preimage = alloc_preimage(cs.namespace(|| "preimage"), &values);
+            let hash = poseidon_hash_with_tag(&mut cs, Fr::zero(), &preimage, &constants).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(hash.get_value().unwrap(), Fr::from_str(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_poseidon_hash_2_and_8() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let constants = PoseidonConstants::<Bls12>::new(2);
+        let values = [Fr::random(rng), Fr::random(rng)];
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let preimage = alloc_preimage(cs.namespace(|| "preimage"), &values);
+        let hash = poseidon_hash_2(
+            &mut cs,
+            &[preimage[0].clone(), preimage[1].clone()],
+            &constants,
+        )
+        .unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 235);
+        let tag = domain_tag::<Bls12>(POSEIDON_HASH_DOMAIN, 2);
+        assert_eq!(hash.get_value().unwrap(), constants.hash(tag, &values));
+
+        let constants = PoseidonConstants::<Bls12>::new(8);
+        let values = (0..8).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let preimage = alloc_preimage(cs.namespace(|| "preimage"), &values);
+        let children = [
+            preimage[0].clone(),
+            preimage[1].clone(),
+            preimage[2].clone(),
+            preimage[3].clone(),
+            preimage[4].clone(),
+            preimage[5].clone(),
+            preimage[6].clone(),
+            preimage[7].clone(),
+        ];
+        let hash = poseidon_hash_8(&mut cs, &children, &constants).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 385);
+        let tag = domain_tag::<Bls12>(POSEIDON_HASH_DOMAIN, 8);
+        assert_eq!(hash.get_value().unwrap(), constants.hash(tag, &values));
+    }
+}
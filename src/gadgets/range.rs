@@ -0,0 +1,350 @@
+//! Range checks against constants.
+//!
+//! A number is decomposed into as many bits as the largest allowed value has, and
+//! the bits are compared against the bits of that value from the most significant
+//! one, like [`AllocatedNum::to_bits_le_strict`] compares against `r - 1`: below a
+//! zero bit of the bound, the number's bit must be zero if all bits above it equal
+//! the bound's. A run of one bits of the bound needs the AND of its bits, one
+//! constraint per bit but the first, and the comparison needs no constraints for a
+//! bound of only ones, like `2^k - 1`.
+//!
+//! For a bound `c - 1` of `n` bits, a check costs `n + 1 + max(m - 1, 0)`
+//! constraints, where `m` is the number of one bits of `c - 1` above its lowest zero
+//! bit: one per bit, the ANDs of the runs of ones, and the packing of the bits.
+
+use ff::{BitIterator, Field, PrimeField, ScalarEngine};
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::AllocatedBit;
+use super::num::{AllocatedNum, Num};
+
+/// Enforces `num < c`, with the constraints counted in the [module
+/// documentation](self). Nothing is less than zero, so `c = 0` fails with
+/// [`SynthesisError::Unsatisfiable`].
+pub fn assert_less_than_constant<E, CS>(
+    cs: CS,
+    num: &AllocatedNum<E>,
+    c: &E::Fr,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let bound = bound_below(c)?;
+
+    enforce_at_most(
+        cs,
+        LinearCombination::zero() + num.get_variable(),
+        num.get_value(),
+        &bound,
+    )
+}
+
+/// Enforces `num < 2^k`, with `k + 1` constraints. Every number of the field has
+/// fewer than [`PrimeField::NUM_BITS`] bits, so larger `k` need none.
+pub fn assert_fits_in_bits<E, CS>(
+    cs: CS,
+    num: &AllocatedNum<E>,
+    k: usize,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    if k >= E::Fr::NUM_BITS as usize {
+        return Ok(());
+    }
+
+    enforce_at_most(
+        cs,
+        LinearCombination::zero() + num.get_variable(),
+        num.get_value(),
+        &all_ones::<E>(k),
+    )
+}
+
+/// [`assert_less_than_constant`] for a [`Num`]. A constant is compared during
+/// synthesis, without any constraints, and fails with
+/// [`SynthesisError::Unsatisfiable`] if it is not less than `c`. Otherwise the
+/// bits pack into the linear combination of `num`, which needs no variable of its
+/// own.
+pub fn assert_num_less_than_constant<E, CS>(
+    cs: CS,
+    num: &Num<E>,
+    c: &E::Fr,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let bound = bound_below(c)?;
+
+    match num.constant_value::<CS>() {
+        Some(value) if value.into_repr() <= bound.into_repr() => Ok(()),
+        Some(_) => Err(SynthesisError::Unsatisfiable),
+        None => enforce_at_most(cs, num.lc(E::Fr::one()), num.get_value(), &bound),
+    }
+}
+
+/// [`assert_fits_in_bits`] for a [`Num`], which treats constants like
+/// [`assert_num_less_than_constant`].
+pub fn assert_num_fits_in_bits<E, CS>(cs: CS, num: &Num<E>, k: usize) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    if k >= E::Fr::NUM_BITS as usize {
+        return Ok(());
+    }
+    let bound = all_ones::<E>(k);
+
+    match num.constant_value::<CS>() {
+        Some(value) if value.into_repr() <= bound.into_repr() => Ok(()),
+        Some(_) => Err(SynthesisError::Unsatisfiable),
+        None => enforce_at_most(cs, num.lc(E::Fr::one()), num.get_value(), &bound),
+    }
+}
+
+/// `c - 1`, the largest number less than `c`.
+fn bound_below<F: PrimeField>(c: &F) -> Result<F, SynthesisError> {
+    if c.is_zero() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    let mut bound = *c;
+    bound.sub_assign(&F::one());
+    Ok(bound)
+}
+
+/// `2^k - 1`.
+fn all_ones<E: ScalarEngine>(k: usize) -> E::Fr {
+    let mut bound = E::Fr::one();
+    for _ in 0..k {
+        bound.double();
+    }
+    bound.sub_assign(&E::Fr::one());
+    bound
+}
+
+/// Enforces `lc <= bound` for the value `value` of `lc`, by decomposing it into the
+/// bits of `bound`.
+fn enforce_at_most<E, CS>(
+    mut cs: CS,
+    lc: LinearCombination<E>,
+    value: Option<E::Fr>,
+    bound: &E::Fr,
+) -> Result<(), SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    // The bits of the bound from its most significant one, and as many of the value.
+    let bound_bits = BitIterator::new(bound.into_repr())
+        .skip_while(|b| !b)
+        .collect::<Vec<_>>();
+    let n = bound_bits.len();
+    let value_bits = value.map(|v| {
+        let bits = BitIterator::new(v.into_repr()).collect::<Vec<_>>();
+        bits[bits.len() - n..].to_vec()
+    });
+
+    // Whether the bits so far equal those of the bound, at the last zero of the bound.
+    let mut last_run: Option<AllocatedBit> = None;
+    let mut current_run = vec![];
+
+    let mut bits = Vec::with_capacity(n);
+    for (i, &b) in bound_bits.iter().enumerate() {
+        let a_bit = value_bits.as_ref().map(|bits| bits[i]);
+
+        if b {
+            let a_bit = AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), a_bit)?;
+            current_run.push(a_bit.clone());
+            bits.push(a_bit);
+        } else {
+            if !current_run.is_empty() {
+                if let Some(last_run) = last_run.take() {
+                    current_run.push(last_run);
+                }
+
+                let mut cs = cs.namespace(|| format!("run ending at {}", i));
+                let mut run = current_run[0].clone();
+                for (j, bit) in current_run.iter().enumerate().skip(1) {
+                    run = AllocatedBit::and(cs.namespace(|| format!("and {}", j)), &run, bit)?;
+                }
+                last_run = Some(run);
+                current_run.clear();
+            }
+
+            // If the bits so far equal those of the bound, this one must be zero, as
+            // the bound's is.
+            let a_bit = AllocatedBit::alloc_conditionally(
+                cs.namespace(|| format!("bit {}", i)),
+                a_bit,
+                last_run.as_ref().expect("the bound starts with a one"),
+            )?;
+            bits.push(a_bit);
+        }
+    }
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter().rev() {
+        packed = packed + (coeff, bit.get_variable());
+        coeff.double();
+    }
+    packed = packed - &lc;
+
+    cs.enforce(|| "packing constraint", |lc| lc, |lc| lc, |_| packed);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Whether `x < c` is satisfiable, and the number of constraints it takes.
+    fn less_than(x: Fr, c: Fr) -> (bool, usize) {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        assert_less_than_constant(cs.namespace(|| "less than"), &num, &c).unwrap();
+        (cs.is_satisfied(), cs.num_constraints())
+    }
+
+    fn fr(n: u64) -> Fr {
+        Fr::from_str(&n.to_string()).unwrap()
+    }
+
+    fn minus_one(x: Fr) -> Fr {
+        let mut x = x;
+        x.sub_assign(&Fr::one());
+        x
+    }
+
+    #[test]
+    fn test_less_than_constant() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        // The field modulus minus one, the largest bound.
+        let mut modulus_minus_one = Fr::zero();
+        modulus_minus_one.sub_assign(&Fr::one());
+
+        let mut constants = vec![
+            fr(1),
+            fr(2),
+            fr(10),
+            fr(1000),
+            fr(1 << 16),
+            fr((1 << 16) + 1),
+            fr(u64::max_value()),
+            modulus_minus_one,
+        ];
+        constants.extend((0..5).map(|_| Fr::random(&mut rng)));
+
+        for c in constants {
+            assert!(less_than(Fr::zero(), c).0);
+            assert!(less_than(minus_one(c), c).0);
+            assert!(!less_than(c, c).0);
+
+            let mut above = c;
+            above.add_assign(&fr(1000));
+            if above.into_repr() > c.into_repr() {
+                assert!(!less_than(above, c).0);
+            }
+            assert!(!less_than(modulus_minus_one, c).0);
+        }
+    }
+
+    #[test]
+    fn test_less_than_constant_constraints() {
+        // 9 = 0b1001, with no run of ones above its lowest zero but the first.
+        assert_eq!(less_than(Fr::zero(), fr(10)), (true, 4 + 1));
+        // 2^16 - 1 is all ones.
+        assert_eq!(less_than(Fr::zero(), fr(1 << 16)), (true, 16 + 1));
+        // 2^16 = 0b1_0000_0000_0000_0000, with a single one.
+        assert_eq!(less_than(Fr::zero(), fr((1 << 16) + 1)), (true, 17 + 1));
+        // 0b1101_0111, with three ones above its lowest zero.
+        assert_eq!(less_than(Fr::zero(), fr(0b1101_1000)), (true, 8 + 1 + 2));
+        // c = 1 only allows zero, which needs no bits.
+        assert_eq!(less_than(Fr::zero(), fr(1)), (true, 1));
+        assert_eq!(less_than(fr(1), fr(1)), (false, 1));
+    }
+
+    #[test]
+    fn test_less_than_zero() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::zero())).unwrap();
+        assert!(matches!(
+            assert_less_than_constant(cs.namespace(|| "less than"), &num, &Fr::zero()),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    #[test]
+    fn test_fits_in_bits() {
+        for &k in &[0, 1, 8, 32, 64] {
+            let max = if k == 64 {
+                fr(u64::max_value())
+            } else {
+                fr((1 << k) - 1)
+            };
+            let mut above = max;
+            above.add_assign(&Fr::one());
+
+            for &(x, fits) in &[(Fr::zero(), true), (max, true), (above, false)] {
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+                assert_fits_in_bits(cs.namespace(|| "fits"), &num, k).unwrap();
+                assert_eq!(cs.is_satisfied(), fits, "k = {}", k);
+                assert_eq!(cs.num_constraints(), k + 1);
+            }
+        }
+
+        // Every number has fewer bits than the field.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut x = Fr::zero();
+        x.sub_assign(&Fr::one());
+        let num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        assert_fits_in_bits(cs.namespace(|| "fits"), &num, 255).unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_num_range() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let one = TestConstraintSystem::<Bls12>::one();
+
+        // Constants need no constraints.
+        let constant = Num::<Bls12>::constant(one, fr(99));
+        assert_num_less_than_constant(cs.namespace(|| "constant"), &constant, &fr(100)).unwrap();
+        assert_num_fits_in_bits(cs.namespace(|| "constant bits"), &constant, 7).unwrap();
+        assert!(matches!(
+            assert_num_less_than_constant(cs.namespace(|| "too large"), &constant, &fr(99)),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        assert!(matches!(
+            assert_num_fits_in_bits(cs.namespace(|| "too many bits"), &constant, 6),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        assert_eq!(cs.num_constraints(), 0);
+
+        // x + 99, without allocating the sum.
+        for &(x, expected) in &[(0, true), (1, false)] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(fr(x))).unwrap();
+            let sum = Num::from(num).add(&constant);
+            assert_num_less_than_constant(cs.namespace(|| "less than"), &sum, &fr(100)).unwrap();
+            assert_eq!(cs.is_satisfied(), expected);
+            assert_eq!(cs.num_constraints(), 7 + 1 + 1);
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Circuits for the [RIPEMD-160] hash function, and for `hash160`, the RIPEMD-160 of
+//! the SHA-256 of a message, as used for Bitcoin addresses.
+//!
+//! The input and the digest are sequences of bytes, each given as 8 bits with the
+//! most significant bit first, like those of [`sha256`](super::sha256::sha256).
+//!
+//! The message words and the state are little-endian. A block costs 19,129
+//! constraints, or fewer for the first, whose state is constant.
+//!
+//! [RIPEMD-160]: https://homes.esat.kuleuven.be/~bosselae/ripemd160.html
+
+use super::boolean::Boolean;
+use super::multieq::MultiEq;
+use super::sha256::sha256;
+use super::uint32::UInt32;
+use crate::{ConstraintSystem, SynthesisError};
+use ff::ScalarEngine;
+
+#[allow(clippy::unreadable_literal)]
+const IV: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/*
+   The message word added in each step of the left and right lines.
+*/
+
+#[rustfmt::skip]
+const WORDS_LEFT: [usize; 80] = [
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+     7,  4, 13,  1, 10,  6, 15,  3, 12,  0,  9,  5,  2, 14, 11,  8,
+     3, 10, 14,  4,  9, 15,  8,  1,  2,  7,  0,  6, 13, 11,  5, 12,
+     1,  9, 11, 10,  0,  8, 12,  4, 13,  3,  7, 15, 14,  5,  6,  2,
+     4,  0,  5,  9,  7, 12,  2, 10, 14,  1,  3,  8, 11,  6, 15, 13,
+];
+
+#[rustfmt::skip]
+const WORDS_RIGHT: [usize; 80] = [
+     5, 14,  7,  0,  9,  2, 11,  4, 13,  6, 15,  8,  1, 10,  3, 12,
+     6, 11,  3,  7,  0, 13,  5, 10, 14, 15,  8, 12,  4,  9,  1,  2,
+    15,  5,  1,  3,  7, 14,  6,  9, 11,  8, 12,  2, 10,  0,  4, 13,
+     8,  6,  4,  1,  3, 11, 15,  0,  5, 12,  2, 13,  9,  7, 10, 14,
+    12, 15, 10,  4,  1,  5,  8,  7,  6,  2, 13, 14,  0,  3,  9, 11,
+];
+
+/*
+   The left rotation of each step of the left and right lines.
+*/
+
+#[rustfmt::skip]
+const ROTATIONS_LEFT: [usize; 80] = [
+    11, 14, 15, 12,  5,  8,  7,  9, 11, 13, 14, 15,  6,  7,  9,  8,
+     7,  6,  8, 13, 11,  9,  7, 15,  7, 12, 15,  9, 11,  7, 13, 12,
+    11, 13,  6,  7, 14,  9, 13, 15, 14,  8, 13,  6,  5, 12,  7,  5,
+    11, 12, 14, 15, 14, 15,  9,  8,  9, 14,  5,  6,  8,  6,  5, 12,
+     9, 15,  5, 11,  6,  8, 13, 12,  5, 12, 13, 14, 11,  8,  5,  6,
+];
+
+#[rustfmt::skip]
+const ROTATIONS_RIGHT: [usize; 80] = [
+     8,  9,  9, 11, 13, 15, 15,  5,  7,  7,  8, 11, 14, 14, 12,  6,
+     9, 13, 15,  7, 12,  8,  9, 11,  7,  7, 12,  7,  6, 15, 13, 11,
+     9,  7, 15, 11,  8,  6,  6, 14, 12, 13,  5, 14, 13, 13,  7,  5,
+    15,  5,  8, 11, 14, 14,  6, 14,  6,  9, 12,  9, 12,  5, 15,  8,
+     8,  5, 12,  9, 12,  5, 14,  6,  8, 13,  6,  5, 15, 13, 11, 11,
+];
+
+/// One of the two parallel lines of the compression function, of five rounds of
+/// 16 steps.
+struct Line {
+    words: &'static [usize; 80],
+    rotations: &'static [usize; 80],
+    /// The constant added in each round.
+    constants: [u32; 5],
+    /// The boolean function of each round, as an index into `f`.
+    functions: [usize; 5],
+}
+
+#[allow(clippy::unreadable_literal)]
+const LEFT: Line = Line {
+    words: &WORDS_LEFT,
+    rotations: &ROTATIONS_LEFT,
+    constants: [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e],
+    functions: [0, 1, 2, 3, 4],
+};
+
+#[allow(clippy::unreadable_literal)]
+const RIGHT: Line = Line {
+    words: &WORDS_RIGHT,
+    rotations: &ROTATIONS_RIGHT,
+    constants: [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000],
+    functions: [4, 3, 2, 1, 0],
+};
+
+fn rotl(x: &UInt32, by: usize) -> UInt32 {
+    x.rotr(32 - by)
+}
+
+/// `a | !b`, bit by bit.
+fn or_not<E, CS>(mut cs: CS, a: &UInt32, b: &UInt32) -> Result<UInt32, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let bits = a
+        .clone()
+        .into_bits()
+        .iter()
+        .zip(b.clone().into_bits())
+        .enumerate()
+        .map(|(i, (a, b))| {
+            // a | !b = !(!a & b)
+            Ok(Boolean::and(cs.namespace(|| format!("bit {}", i)), &a.not(), &b)?.not())
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    Ok(UInt32::from_bits(&bits))
+}
+
+/// The boolean function `i` of the rounds of RIPEMD-160.
+fn f<E, CS>(
+    mut cs: CS,
+    i: usize,
+    x: &UInt32,
+    y: &UInt32,
+    z: &UInt32,
+) -> Result<UInt32, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    match i {
+        // x ^ y ^ z
+        0 => x
+            .xor(cs.namespace(|| "x xor y"), y)?
+            .xor(cs.namespace(|| "xor z"), z),
+        // (x & y) | (!x & z)
+        1 => UInt32::sha256_ch(cs, x, y, z),
+        // (x | !y) ^ z
+        2 => or_not(cs.namespace(|| "x or not y"), x, y)?.xor(cs.namespace(|| "xor z"), z),
+        // (x & z) | (y & !z)
+        3 => UInt32::sha256_ch(cs, z, x, y),
+        // x ^ (y | !z)
+        4 => x.xor(
+            cs.namespace(|| "x xor"),
+            &or_not(cs.namespace(|| "y or not z"), y, z)?,
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// The state of the line `spec` after its 80 steps from `current_hash_value`.
+fn line<E, CS, M>(
+    mut cs: M,
+    x: &[UInt32],
+    current_hash_value: &[UInt32],
+    spec: &Line,
+) -> Result<Vec<UInt32>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+    M: ConstraintSystem<E, Root = MultiEq<E, CS>>,
+{
+    let mut a = current_hash_value[0].clone();
+    let mut b = current_hash_value[1].clone();
+    let mut c = current_hash_value[2].clone();
+    let mut d = current_hash_value[3].clone();
+    let mut e = current_hash_value[4].clone();
+
+    for j in 0..80 {
+        let cs = &mut cs.namespace(|| format!("step {}", j));
+        let round = j / 16;
+
+        // t := rotl(a + f(b, c, d) + x[r] + k, s) + e
+        let f_bcd = f(cs.namespace(|| "f"), spec.functions[round], &b, &c, &d)?;
+        let t = UInt32::addmany(
+            cs.namespace(|| "sum"),
+            &[
+                a,
+                f_bcd,
+                x[spec.words[j]].clone(),
+                UInt32::constant(spec.constants[round]),
+            ],
+        )?;
+        let t = UInt32::addmany(
+            cs.namespace(|| "t"),
+            &[rotl(&t, spec.rotations[j]), e.clone()],
+        )?;
+
+        a = e;
+        e = d;
+        d = rotl(&c, 10);
+        c = b;
+        b = t;
+    }
+
+    Ok(vec![a, b, c, d, e])
+}
+
+fn get_ripemd160_iv() -> Vec<UInt32> {
+    IV.iter().map(|&v| UInt32::constant(v)).collect()
+}
+
+fn ripemd160_compression_function<E, CS>(
+    cs: CS,
+    input: &[Boolean],
+    current_hash_value: &[UInt32],
+) -> Result<Vec<UInt32>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(input.len(), 512);
+    assert_eq!(current_hash_value.len(), 5);
+
+    // Little-endian words of bytes with their most significant bit first.
+    let x = input
+        .chunks(32)
+        .map(|word| {
+            let bits = word
+                .chunks(8)
+                .flat_map(|byte| byte.iter().rev().cloned())
+                .collect::<Vec<_>>();
+            UInt32::from_bits(&bits)
+        })
+        .collect::<Vec<_>>();
+
+    let mut cs = MultiEq::new(cs);
+
+    let l = line(cs.namespace(|| "left"), &x, current_hash_value, &LEFT)?;
+    let r = line(cs.namespace(|| "right"), &x, current_hash_value, &RIGHT)?;
+
+    // h[i] := h[i + 1] + l[i + 2] + r[i + 3]
+    let mut new_hash_value = Vec::with_capacity(5);
+    for i in 0..5 {
+        new_hash_value.push(UInt32::addmany(
+            cs.namespace(|| format!("new h{}", i)),
+            &[
+                current_hash_value[(i + 1) % 5].clone(),
+                l[(i + 2) % 5].clone(),
+                r[(i + 3) % 5].clone(),
+            ],
+        )?);
+    }
+
+    Ok(new_hash_value)
+}
+
+/// RIPEMD-160 of `input`, a 20 byte digest.
+pub fn ripemd160<E, CS>(mut cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(input.len() % 8 == 0);
+
+    let mut padded = input.to_vec();
+    let plen = padded.len() as u64;
+    // append a single '1' bit
+    padded.push(Boolean::constant(true));
+    // append K '0' bits, where K is the minimum number >= 0 such that L + 1 + K + 64 is a multiple of 512
+    while (padded.len() + 64) % 512 != 0 {
+        padded.push(Boolean::constant(false));
+    }
+    // append L as a 64-bit little-endian integer, making the total post-processed length a multiple of 512 bits
+    for byte in 0..8 {
+        for i in (0..8).rev() {
+            padded.push(Boolean::constant((plen >> (8 * byte + i)) & 1 == 1));
+        }
+    }
+    assert!(padded.len() % 512 == 0);
+
+    let mut cur = get_ripemd160_iv();
+    for (i, block) in padded.chunks(512).enumerate() {
+        cur = ripemd160_compression_function(cs.namespace(|| format!("block {}", i)), block, &cur)?;
+    }
+
+    Ok(cur
+        .into_iter()
+        .flat_map(|word| {
+            word.into_bits()
+                .chunks(8)
+                .flat_map(|byte| byte.iter().rev().cloned())
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// `hash160` of `input`, the RIPEMD-160 of its SHA-256, a 20 byte digest.
+pub fn hash160<E, CS>(mut cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let digest = sha256(cs.namespace(|| "sha256"), input)?;
+    ripemd160(cs.namespace(|| "ripemd160"), &digest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::Bls12;
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn alloc_input<CS: ConstraintSystem<Bls12>>(mut cs: CS, data: &[u8]) -> Vec<Boolean> {
+        let mut input_bits = vec![];
+
+        for (byte_i, input_byte) in data.iter().enumerate() {
+            for bit_i in (0..8).rev() {
+                let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+
+                input_bits.push(
+                    AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8))
+                        .unwrap()
+                        .into(),
+                );
+            }
+        }
+
+        input_bits
+    }
+
+    fn assert_digest(out: Vec<Boolean>, expected: &[u8]) {
+        assert_eq!(out.len(), expected.len() * 8);
+
+        let mut out = out.into_iter();
+        for b in expected.iter() {
+            for i in (0..8).rev() {
+                let c = out.next().unwrap().get_value().unwrap();
+
+                assert_eq!(c, (b >> i) & 1u8 == 1u8);
+            }
+        }
+    }
+
+    #[test]
+    fn test_blank_hash() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let out = ripemd160(&mut cs, &[]).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+        assert_digest(out, &hex!("9c1185a5c5e9fc54612808977ee8f548b2258d31"));
+    }
+
+    #[test]
+    fn test_against_vectors() {
+        // The test vectors of the RIPEMD-160 specification.
+        let vectors: [(&[u8], [u8; 20]); 8] = [
+            (b"", hex!("9c1185a5c5e9fc54612808977ee8f548b2258d31")),
+            (b"a", hex!("0bdc9d2d256b3ee9daae347be6f4dc835a467ffe")),
+            (b"abc", hex!("8eb208f7e05d987a9b044a8e98c6b087f15a0bfc")),
+            (
+                b"message digest",
+                hex!("5d0689ef49d2fae572b881b123a85ffa21595f36"),
+            ),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                hex!("f71c27109c692c1b56bbdceb5b9d2865b3708dbc"),
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                hex!("12a053384a9c0c88e405a06c27dcf49ada62eb2b"),
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                hex!("b0e20b6e3116640286ed3a87a5713079b21f5189"),
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                hex!("9b752e45573d4b39f4dbd3323cab82bf63326bfb"),
+            ),
+        ];
+
+        for (data, expected) in vectors.iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_input(&mut cs, data);
+            let out = ripemd160(&mut cs, &input_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_digest(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_ripemd160_constraints() {
+        // The longest input of a single block.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_input(&mut cs, &[0xff; 55]);
+        ripemd160(&mut cs, &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 440 + 18_797);
+
+        // The padding takes a block of its own, compressed from an allocated state.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_input(&mut cs, &[0xff; 64]);
+        ripemd160(&mut cs, &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 512 + 18_797 + 19_129);
+    }
+
+    #[test]
+    fn test_hash160() {
+        use ::ripemd160::Ripemd160;
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        // Around the block boundaries of both hashes, whose padding is the same.
+        for &input_len in &[0, 1, 32, 55, 56, 64, 119, 120] {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.next_u32() as u8).collect();
+            let expected = Ripemd160::digest(&Sha256::digest(&data));
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_input(&mut cs, &data);
+            let out = hash160(&mut cs, &input_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_digest(out, &expected);
+        }
+    }
+}
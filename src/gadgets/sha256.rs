@@ -3,11 +3,12 @@
 //!
 //! [SHA-256]: https://tools.ietf.org/html/rfc6234
 
-use super::boolean::Boolean;
+use super::boolean::{AllocatedBit, Boolean};
 use super::multieq::MultiEq;
 use super::uint32::UInt32;
+use super::uint8::{bits_be_to_bytes, bytes_to_bits_be, UInt8};
 use crate::{ConstraintSystem, SynthesisError};
-use ff::ScalarEngine;
+use ff::{Field, PrimeField, ScalarEngine};
 
 #[allow(clippy::unreadable_literal)]
 const ROUND_CONSTANTS: [u32; 64] = [
@@ -44,15 +45,130 @@ where
     )
 }
 
-pub fn sha256<E, CS>(mut cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+pub fn sha256<E, CS>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
 where
     E: ScalarEngine,
     CS: ConstraintSystem<E>,
 {
-    assert!(input.len() % 8 == 0);
+    sha256_final(cs, &sha256_iv(), input, input.len() as u64)
+}
+
+/// SHA-256 of `input`, whose bytes are given as [`UInt8`]s rather than as bits
+/// with the most significant bit of each byte first.
+pub fn sha256_bytes<E, CS>(cs: CS, input: &[UInt8]) -> Result<Vec<UInt8>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    Ok(bits_be_to_bytes(&sha256(cs, &bytes_to_bits_be(input))?))
+}
+
+/// `SHA-256(SHA-256(input))`. The digest of [`sha256`] has the bit order of its
+/// input, the most significant bit of each byte first, so it is hashed again as is.
+pub fn sha256d<E, CS>(mut cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let inner = sha256(cs.namespace(|| "inner"), input)?;
+
+    sha256(cs.namespace(|| "outer"), &inner)
+}
+
+/// `SHA-256(tag || input)` for a constant `tag`, whose bits are constants and cost
+/// no allocations.
+pub fn sha256_tagged<E, CS>(
+    cs: CS,
+    tag: &[u8],
+    input: &[Boolean],
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    let mut message = bytes_to_bits_be(&UInt8::constant_vec(tag));
+    message.extend_from_slice(input);
+
+    sha256(cs, &message)
+}
+
+/// The state of SHA-256 between two compressions, its eight words.
+pub type Sha256State = [UInt32; 8];
+
+/// The initial state of SHA-256.
+pub fn sha256_iv() -> Sha256State {
+    sha256_midstate(&IV)
+}
+
+/// A constant state, like the midstate after a public prefix of a message,
+/// computed outside of the circuit. Compressing the prefix in the circuit would
+/// cost no constraints either, but would take as long as for a witness.
+pub fn sha256_midstate(state: &[u32; 8]) -> Sha256State {
+    [
+        UInt32::constant(state[0]),
+        UInt32::constant(state[1]),
+        UInt32::constant(state[2]),
+        UInt32::constant(state[3]),
+        UInt32::constant(state[4]),
+        UInt32::constant(state[5]),
+        UInt32::constant(state[6]),
+        UInt32::constant(state[7]),
+    ]
+}
+
+/// Compresses the 512 bits of `block` into `state`, without padding.
+pub fn sha256_compression_from_state<E, CS>(
+    cs: CS,
+    state: &Sha256State,
+    block: &[Boolean],
+) -> Result<Sha256State, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(block.len(), 512);
+
+    let mut words = sha256_compression_function(cs, block, state)?.into_iter();
+    let mut next = || words.next().expect("the state has eight words");
+
+    Ok([
+        next(),
+        next(),
+        next(),
+        next(),
+        next(),
+        next(),
+        next(),
+        next(),
+    ])
+}
 
-    let mut padded = input.to_vec();
-    let plen = padded.len() as u64;
+/// Hashes the end of a message of `total_len_bits` bits, `remaining`, from `state`,
+/// the state after the bits before it. This pads the message, so `remaining` may
+/// have any number of bytes.
+///
+/// The bits before `remaining` must be a whole number of blocks, as a state is only
+/// defined between blocks, or [`SynthesisError::UnalignedHashState`] is returned.
+pub fn sha256_final<E, CS>(
+    mut cs: CS,
+    state: &Sha256State,
+    remaining: &[Boolean],
+    total_len_bits: u64,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(remaining.len() % 8 == 0);
+    let prefix_len = total_len_bits
+        .checked_sub(remaining.len() as u64)
+        .expect("the message is longer than its total length");
+    if prefix_len % 512 != 0 {
+        return Err(SynthesisError::UnalignedHashState(prefix_len));
+    }
+
+    let mut padded = remaining.to_vec();
+    let plen = total_len_bits;
     // append a single '1' bit
     padded.push(Boolean::constant(true));
     // append K '0' bits, where K is the minimum number >= 0 such that L + 1 + K + 64 is a multiple of 512
@@ -60,12 +176,10 @@ where
         padded.push(Boolean::constant(false));
     }
     // append L as a 64-bit big-endian integer, making the total post-processed length a multiple of 512 bits
-    for b in (0..64).rev().map(|i| (plen >> i) & 1 == 1) {
-        padded.push(Boolean::constant(b));
-    }
+    padded.extend(bytes_to_bits_be(&UInt8::constant_vec(&plen.to_be_bytes())));
     assert!(padded.len() % 512 == 0);
 
-    let mut cur = get_sha256_iv();
+    let mut cur = state.to_vec();
     for (i, block) in padded.chunks(512).enumerate() {
         cur = sha256_compression_function(cs.namespace(|| format!("block {}", i)), block, &cur)?;
     }
@@ -73,6 +187,150 @@ where
     Ok(cur.into_iter().flat_map(|e| e.into_bits_be()).collect())
 }
 
+/// Hashes the first `len_bits` bits of `data`, which holds `max_len` bytes, padding the
+/// message inside the circuit, so that its length can be part of the witness.
+///
+/// The length must be a whole number of bytes of at most `max_len`, or the constraints
+/// are unsatisfiable; the bits of `data` past it are ignored. All
+/// `K = (max_len + 8) / 64 + 1` blocks a message of `max_len` bytes needs are
+/// compressed, with the padding byte `0x80` and the length placed by indicators of the
+/// length, and the digest is selected among the states after each block with one
+/// constraint per bit and block. This costs at most
+/// `26161 * K + 12 * max_len` constraints, dominated by the 25840 of each compression.
+pub fn sha256_var_length<E, CS>(
+    mut cs: CS,
+    data: &[Boolean],
+    len_bits: &UInt32,
+    max_len: usize,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: ScalarEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(data.len(), 8 * max_len);
+    let num_blocks = (max_len + 8) / 64 + 1;
+
+    let len = len_bits.clone().into_bits();
+    for (i, bit) in len[..3].iter().enumerate() {
+        Boolean::enforce_equal(
+            cs.namespace(|| format!("whole bytes {}", i)),
+            bit,
+            &Boolean::constant(false),
+        )?;
+    }
+
+    // is_len[p] is set iff the length is p bytes, and cum[p] iff it is at most p.
+    let len_bytes = len_bits.get_value().map(|v| v as usize / 8);
+    let mut is_len = Vec::with_capacity(max_len + 1);
+    let mut cum: Vec<Boolean> = Vec::with_capacity(max_len + 1);
+    for p in 0..=max_len {
+        let mut cs = cs.namespace(|| format!("length indicator {}", p));
+        let bit = AllocatedBit::alloc(cs.namespace(|| "bit"), len_bytes.map(|l| l == p))?;
+        // bit * (len / 8 - p) = 0
+        cs.enforce(
+            || "set only at the length",
+            |lc| lc + bit.get_variable(),
+            |lc| {
+                let mut coeff = E::Fr::one();
+                let lc = len[3..].iter().fold(lc, |lc, b| {
+                    let lc = lc + &b.lc(CS::one(), coeff);
+                    coeff.double();
+                    lc
+                });
+                lc - (E::Fr::from_str(&p.to_string()).unwrap(), CS::one())
+            },
+            |lc| lc,
+        );
+        let bit = Boolean::from(bit);
+
+        let at_most = match cum.last() {
+            Some(prev) => Boolean::xor(cs.namespace(|| "at most"), prev, &bit)?,
+            None => bit.clone(),
+        };
+        is_len.push(bit);
+        cum.push(at_most);
+    }
+    // Some indicator is set, so the length is at most max_len.
+    Boolean::enforce_equal(
+        cs.namespace(|| "length in range"),
+        &cum[max_len],
+        &Boolean::constant(true),
+    )?;
+
+    // needs[k] is set iff the padded message has more than k blocks, and last[k] iff
+    // block k is the last one.
+    let needs = (0..num_blocks)
+        .map(|k| match k {
+            0 => Boolean::constant(true),
+            _ => cum[64 * k - 9].not(),
+        })
+        .collect::<Vec<_>>();
+    let last = (0..num_blocks)
+        .map(|k| match needs.get(k + 1) {
+            Some(next) => Boolean::and(
+                cs.namespace(|| format!("last block {}", k)),
+                &needs[k],
+                &next.not(),
+            ),
+            None => Ok(needs[k].clone()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut padded = Vec::with_capacity(512 * num_blocks);
+    for p in 0..64 * num_blocks {
+        let mut cs = cs.namespace(|| format!("padded byte {}", p));
+        let k = p / 64;
+        for b in 0..8 {
+            // The message before the length, then 0x80, then zeros.
+            let mut bit = match data.get(8 * p + b) {
+                Some(d) => Boolean::and(cs.namespace(|| format!("data {}", b)), d, &cum[p].not())?,
+                None => Boolean::constant(false),
+            };
+            if b == 0 && p <= max_len {
+                bit = Boolean::xor(cs.namespace(|| "padding"), &bit, &is_len[p])?;
+            }
+            // The length in bits as a 64 bit big endian integer, at the end of the last
+            // block.
+            let from_end = 64 * (k + 1) - p;
+            if from_end <= 4 {
+                let i = 8 * (from_end - 1) + (7 - b);
+                let len_bit =
+                    Boolean::and(cs.namespace(|| format!("length {}", b)), &len[i], &last[k])?;
+                bit = Boolean::xor(
+                    cs.namespace(|| format!("with length {}", b)),
+                    &bit,
+                    &len_bit,
+                )?;
+            }
+            padded.push(bit);
+        }
+    }
+
+    let mut cur = get_sha256_iv();
+    let mut digest: Option<Vec<Boolean>> = None;
+    for (k, block) in padded.chunks(512).enumerate() {
+        cur = sha256_compression_function(cs.namespace(|| format!("block {}", k)), block, &cur)?;
+        let state = cur.iter().cloned().flat_map(|e| e.into_bits_be());
+        digest = Some(match digest {
+            None => state.collect(),
+            Some(prev) => state
+                .zip(prev.iter())
+                .enumerate()
+                .map(|(i, (s, prev))| {
+                    Boolean::sha256_ch(
+                        cs.namespace(|| format!("select {} {}", k, i)),
+                        &needs[k],
+                        &s,
+                        prev,
+                    )
+                })
+                .collect::<Result<_, _>>()?,
+        });
+    }
+
+    Ok(digest.expect("there is at least one block"))
+}
+
 fn get_sha256_iv() -> Vec<UInt32> {
     IV.iter().map(|&v| UInt32::constant(v)).collect()
 }
@@ -102,14 +360,16 @@ where
         let cs = &mut cs.namespace(|| format!("w extension {}", i));
 
         // s0 := (w[i-15] rightrotate 7) xor (w[i-15] rightrotate 18) xor (w[i-15] rightshift 3)
-        let mut s0 = w[i - 15].rotr(7);
-        s0 = s0.xor(cs.namespace(|| "first xor for s0"), &w[i - 15].rotr(18))?;
-        s0 = s0.xor(cs.namespace(|| "second xor for s0"), &w[i - 15].shr(3))?;
+        let s0 = UInt32::xor_many(
+            cs.namespace(|| "s0"),
+            &[w[i - 15].rotr(7), w[i - 15].rotr(18), w[i - 15].shr(3)],
+        )?;
 
         // s1 := (w[i-2] rightrotate 17) xor (w[i-2] rightrotate 19) xor (w[i-2] rightshift 10)
-        let mut s1 = w[i - 2].rotr(17);
-        s1 = s1.xor(cs.namespace(|| "first xor for s1"), &w[i - 2].rotr(19))?;
-        s1 = s1.xor(cs.namespace(|| "second xor for s1"), &w[i - 2].shr(10))?;
+        let s1 = UInt32::xor_many(
+            cs.namespace(|| "s1"),
+            &[w[i - 2].rotr(17), w[i - 2].rotr(19), w[i - 2].shr(10)],
+        )?;
 
         let tmp = UInt32::addmany(
             cs.namespace(|| "computation of w[i]"),
@@ -158,9 +418,10 @@ where
 
         // S1 := (e rightrotate 6) xor (e rightrotate 11) xor (e rightrotate 25)
         let new_e = e.compute(cs.namespace(|| "deferred e computation"), &[])?;
-        let mut s1 = new_e.rotr(6);
-        s1 = s1.xor(cs.namespace(|| "first xor for s1"), &new_e.rotr(11))?;
-        s1 = s1.xor(cs.namespace(|| "second xor for s1"), &new_e.rotr(25))?;
+        let s1 = UInt32::xor_many(
+            cs.namespace(|| "s1"),
+            &[new_e.rotr(6), new_e.rotr(11), new_e.rotr(25)],
+        )?;
 
         // ch := (e and f) xor ((not e) and g)
         let ch = UInt32::sha256_ch(cs.namespace(|| "ch"), &new_e, &f, &g)?;
@@ -176,9 +437,10 @@ where
 
         // S0 := (a rightrotate 2) xor (a rightrotate 13) xor (a rightrotate 22)
         let new_a = a.compute(cs.namespace(|| "deferred a computation"), &[])?;
-        let mut s0 = new_a.rotr(2);
-        s0 = s0.xor(cs.namespace(|| "first xor for s0"), &new_a.rotr(13))?;
-        s0 = s0.xor(cs.namespace(|| "second xor for s0"), &new_a.rotr(22))?;
+        let s0 = UInt32::xor_many(
+            cs.namespace(|| "s0"),
+            &[new_a.rotr(2), new_a.rotr(13), new_a.rotr(22)],
+        )?;
 
         // maj := (a and b) xor (a and c) xor (b and c)
         let maj = UInt32::sha256_maj(cs.namespace(|| "maj"), &new_a, &b, &c)?;
@@ -384,4 +646,217 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_var_length_against_vectors() {
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let max_len = 120;
+        let data: Vec<u8> = (0..max_len).map(|_| rng.next_u32() as u8).collect();
+
+        let synthesize = |len_bits: u32| {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = data
+                .iter()
+                .enumerate()
+                .flat_map(|(byte_i, &byte)| (0..8).rev().map(move |bit_i| (byte_i, byte, bit_i)))
+                .map(|(byte_i, byte, bit_i)| {
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)),
+                        Some((byte >> bit_i) & 1u8 == 1u8),
+                    )
+                    .unwrap()
+                    .into()
+                })
+                .collect::<Vec<Boolean>>();
+            let len = UInt32::alloc(cs.namespace(|| "len"), Some(len_bits)).unwrap();
+
+            let before = cs.num_constraints();
+            let r =
+                sha256_var_length(cs.namespace(|| "sha256"), &input_bits, &len, max_len).unwrap();
+            (cs, r, before)
+        };
+
+        let mut num_constraints = None;
+        for &len in &[0, 55, 56, 64, max_len] {
+            let (cs, r, before) = synthesize(8 * len as u32);
+            assert!(cs.is_satisfied(), "length {}", len);
+
+            // The shape of the circuit does not depend on the length.
+            let constraints = cs.num_constraints() - before;
+            assert_eq!(*num_constraints.get_or_insert(constraints), constraints);
+            assert!(constraints <= 26161 * 3 + 12 * max_len);
+
+            let expected = Sha256::digest(&data[..len]);
+            let expected = expected
+                .iter()
+                .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8));
+            for (b, e) in r.iter().zip(expected) {
+                assert_eq!(b.get_value().unwrap(), e, "length {}", len);
+            }
+        }
+
+        // Lengths which are not whole bytes or too long.
+        assert!(!synthesize(8 * 10 + 3).0.is_satisfied());
+        assert!(!synthesize(8 * (max_len as u32 + 1)).0.is_satisfied());
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, data: &[u8]) -> Vec<Boolean> {
+        data.iter()
+            .enumerate()
+            .flat_map(|(byte_i, &byte)| (0..8).rev().map(move |bit_i| (byte_i, byte, bit_i)))
+            .map(|(byte_i, byte, bit_i)| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)),
+                    Some((byte >> bit_i) & 1u8 == 1u8),
+                )
+                .unwrap()
+                .into()
+            })
+            .collect()
+    }
+
+    fn bit_values(bits: &[Boolean]) -> Vec<bool> {
+        bits.iter().map(|b| b.get_value().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_midstate() {
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let data: Vec<u8> = (0..200).map(|_| rng.next_u32() as u8).collect();
+        let expected = Sha256::digest(&data)
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+            .collect::<Vec<_>>();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_bits(&mut cs, &data);
+        let full = sha256(&mut cs, &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(bit_values(&full), expected);
+        let full_constraints = cs.num_constraints();
+
+        for &split in &[0, 64, 128, 192] {
+            // The midstate after a public prefix, compressed from constants for free.
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let mut state = sha256_iv();
+            for (i, block) in data[..split].chunks(64).enumerate() {
+                let block = block
+                    .iter()
+                    .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                    .map(Boolean::constant)
+                    .collect::<Vec<_>>();
+                state = sha256_compression_from_state(
+                    cs.namespace(|| format!("block {}", i)),
+                    &state,
+                    &block,
+                )
+                .unwrap();
+            }
+            assert_eq!(cs.num_constraints(), 0);
+            let mut midstate = [0u32; 8];
+            for (m, word) in midstate.iter_mut().zip(state.iter()) {
+                *m = word.get_value().unwrap();
+            }
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let remaining = alloc_bits(&mut cs, &data[split..]);
+            let out = sha256_final(
+                &mut cs,
+                &sha256_midstate(&midstate),
+                &remaining,
+                8 * data.len() as u64,
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(bit_values(&out), expected);
+            // Each block of the prefix saves its allocated bits and a compression.
+            if split == 0 {
+                assert_eq!(cs.num_constraints(), full_constraints);
+            } else {
+                assert!(cs.num_constraints() < full_constraints);
+            }
+        }
+    }
+
+    #[test]
+    fn test_midstate_partial_block() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let remaining = alloc_bits(&mut cs, &[0; 10]);
+
+        // 90 bytes before the remaining 10.
+        let out = sha256_final(&mut cs, &sha256_iv(), &remaining, 8 * 100);
+        assert!(matches!(out, Err(SynthesisError::UnalignedHashState(720))));
+    }
+
+    #[test]
+    fn test_sha256d_against_vectors() {
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &len in &[0, 1, 32, 55, 64, 100] {
+            let data: Vec<u8> = (0..len).map(|_| rng.next_u32() as u8).collect();
+            let expected = Sha256::digest(&Sha256::digest(&data))
+                .iter()
+                .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                .collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits = alloc_bits(&mut cs, &data);
+            let out = sha256d(cs.namespace(|| "sha256d"), &input_bits).unwrap();
+
+            assert!(cs.is_satisfied(), "length {}", len);
+            assert_eq!(bit_values(&out), expected, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_sha256_tagged_against_vectors() {
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &tag in &[&b""[..], &b"protocol/commitment"[..], &[0xa5; 64][..]] {
+            for &len in &[0, 1, 32, 100] {
+                let data: Vec<u8> = (0..len).map(|_| rng.next_u32() as u8).collect();
+                let mut h = Sha256::new();
+                h.update(tag);
+                h.update(&data);
+                let expected = h
+                    .finalize()
+                    .iter()
+                    .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                    .collect::<Vec<_>>();
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let input_bits = alloc_bits(&mut cs, &data);
+                let out = sha256_tagged(cs.namespace(|| "sha256"), tag, &input_bits).unwrap();
+
+                assert!(cs.is_satisfied(), "tag {:?}, length {}", tag, len);
+                assert_eq!(bit_values(&out), expected, "tag {:?}, length {}", tag, len);
+                // The tag alone is hashed from constants.
+                if len == 0 {
+                    assert_eq!(cs.num_constraints(), 0);
+                }
+            }
+        }
+    }
 }
@@ -6,6 +6,8 @@ use ff::{Field, PrimeField, ScalarEngine};
 use crate::{ConstraintSystem, LinearCombination, SynthesisError};
 
 use super::boolean::{AllocatedBit, Boolean};
+use super::num::AllocatedNum;
+use super::Assignment;
 
 use super::multieq::MultiEq;
 
@@ -74,6 +76,72 @@ impl UInt32 {
         Ok(UInt32 { bits, value })
     }
 
+    /// The value, if it is known.
+    pub fn get_value(&self) -> Option<u32> {
+        self.value
+    }
+
+    /// Decomposes `num` into a `UInt32`, enforcing that its value is below 2^32.
+    ///
+    /// The 32 allocated bits must pack into `num`, and as no multiple of the field
+    /// modulus is below 2^32, no other value has the same bits. A larger witness
+    /// leaves the packing constraint unsatisfied.
+    pub fn from_allocated_num<E, CS>(
+        mut cs: CS,
+        num: &AllocatedNum<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let value = num.get_value().map(|v| v.into_repr().as_ref()[0] as u32);
+        let uint = Self::alloc(cs.namespace(|| "bits"), value)?;
+
+        let lc = uint.pack_bits::<E, CS>();
+        cs.enforce(
+            || "packing constraint",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+
+        Ok(uint)
+    }
+
+    /// Packs the bits of this `UInt32` into an [`AllocatedNum`].
+    pub fn to_allocated_num<E, CS>(&self, mut cs: CS) -> Result<AllocatedNum<E>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+            let value = self.value.get()?;
+            Ok(E::Fr::from_repr(u64::from(*value).into()).expect("32 bits are in the field"))
+        })?;
+
+        let lc = self.pack_bits::<E, CS>();
+        cs.enforce(
+            || "packing constraint",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+
+        Ok(num)
+    }
+
+    /// The linear combination of the bits with the coefficients `2^i`.
+    fn pack_bits<E: ScalarEngine, CS: ConstraintSystem<E>>(&self) -> LinearCombination<E> {
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for bit in &self.bits {
+            lc = lc + &bit.lc(CS::one(), coeff);
+            coeff.double();
+        }
+
+        lc
+    }
+
     pub fn into_bits_be(self) -> Vec<Boolean> {
         let mut ret = self.bits;
         ret.reverse();
@@ -280,6 +348,32 @@ impl UInt32 {
         })
     }
 
+    /// XOR of any number of `UInt32`s, bit by bit with [`Boolean::xor_many`].
+    pub fn xor_many<E, CS>(mut cs: CS, operands: &[Self]) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let new_value = operands
+            .iter()
+            .try_fold(0, |acc, x| x.value.map(|v| acc ^ v));
+
+        let bits = (0..32)
+            .map(|i| {
+                let bits = operands
+                    .iter()
+                    .map(|x| x.bits[i].clone())
+                    .collect::<Vec<_>>();
+                Boolean::xor_many(cs.namespace(|| format!("xor of bit {}", i)), &bits)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt32 {
+            bits,
+            value: new_value,
+        })
+    }
+
     /// Perform modular addition of several `UInt32` objects.
     pub fn addmany<E, CS, M>(mut cs: M, operands: &[Self]) -> Result<Self, SynthesisError>
     where
@@ -385,12 +479,13 @@ impl UInt32 {
 #[cfg(test)]
 mod test {
     use super::UInt32;
-    use crate::bls::Bls12;
+    use crate::bls::{Bls12, Fr};
     use crate::gadgets::boolean::Boolean;
     use crate::gadgets::multieq::MultiEq;
+    use crate::gadgets::num::AllocatedNum;
     use crate::gadgets::test::*;
     use crate::ConstraintSystem;
-    use ff::Field;
+    use ff::{Field, PrimeField};
     use rand_core::{RngCore, SeedableRng};
     use rand_xorshift::XorShiftRng;
 
@@ -509,6 +604,45 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_uint32_xor_many() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u32();
+            let b = rng.next_u32();
+            let c = rng.next_u32();
+            let d = rng.next_u32();
+
+            let operands = [
+                UInt32::alloc(cs.namespace(|| "a"), Some(a)).unwrap(),
+                UInt32::constant(b),
+                UInt32::alloc(cs.namespace(|| "c"), Some(c)).unwrap(),
+                UInt32::alloc(cs.namespace(|| "d"), Some(d)).unwrap(),
+            ];
+            let before = cs.num_constraints();
+            let r = UInt32::xor_many(cs.namespace(|| "xor"), &operands).unwrap();
+
+            assert!(cs.is_satisfied());
+            let expected = a ^ b ^ c ^ d;
+            assert_eq!(r.value, Some(expected));
+            for (i, bit) in r.into_bits().iter().enumerate() {
+                assert_eq!(bit.get_value(), Some((expected >> i) & 1 == 1));
+            }
+
+            // Like a chain of XORs, which the constant does not add to.
+            assert_eq!(cs.num_constraints() - before, 2 * 32);
+        }
+
+        let r = UInt32::xor_many(TestConstraintSystem::<Bls12>::new(), &[]).unwrap();
+        assert_eq!(r.value, Some(0));
+    }
+
     #[test]
     fn test_uint32_addmany_constants() {
         let mut rng = XorShiftRng::from_seed([
@@ -752,4 +886,61 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_uint32_allocated_num() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let v = match i {
+                0 => 0,
+                1 => u32::max_value(),
+                _ => rng.next_u32(),
+            };
+            let fr = Fr::from_str(&v.to_string()).unwrap();
+            let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(fr)).unwrap();
+
+            let uint = UInt32::from_allocated_num(cs.namespace(|| "from num"), &num).unwrap();
+            assert_eq!(uint.get_value(), Some(v));
+            assert_eq!(cs.num_constraints(), 32 + 1);
+
+            let packed = uint.to_allocated_num(cs.namespace(|| "to num")).unwrap();
+            assert_eq!(packed.get_value(), Some(fr));
+            assert_eq!(cs.num_constraints(), 32 + 2);
+            assert!(cs.is_satisfied());
+
+            // The packed number must be the value of the bits.
+            let mut other = fr;
+            other.add_assign(&Fr::one());
+            cs.set("to num/num/num", other);
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint32_from_allocated_num_out_of_range() {
+        // 2^32 has the same low 32 bits as zero, and so do larger values.
+        for value in &[
+            "4294967296",
+            "4294967301",
+            "52435875175126190479447740508185965837690552500527637822603658699938581184512",
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let fr = Fr::from_str(value).unwrap();
+            let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(fr)).unwrap();
+            UInt32::from_allocated_num(cs.namespace(|| "from num"), &num).unwrap();
+
+            assert!(!cs.is_satisfied());
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("from num/packing constraint")
+            );
+        }
+    }
 }
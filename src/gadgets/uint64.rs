@@ -0,0 +1,994 @@
+//! Circuit representation of a [`u64`], with helpers for the [`blake2b`]
+//! gadgets.
+//!
+//! [`blake2b`]: super::blake2b
+
+use ff::{Field, PrimeField, ScalarEngine};
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::num::AllocatedNum;
+use super::Assignment;
+
+use super::multieq::MultiEq;
+
+/// Represents an interpretation of 64 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt64 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u64>,
+}
+
+impl UInt64 {
+    /// Construct a constant `UInt64` from a `u64`
+    pub fn constant(value: u64) -> Self {
+        let mut bits = Vec::with_capacity(64);
+
+        let mut tmp = value;
+        for _ in 0..64 {
+            if tmp & 1 == 1 {
+                bits.push(Boolean::constant(true))
+            } else {
+                bits.push(Boolean::constant(false))
+            }
+
+            tmp >>= 1;
+        }
+
+        UInt64 {
+            bits,
+            value: Some(value),
+        }
+    }
+
+    /// Allocate a `UInt64` in the constraint system
+    pub fn alloc<E, CS>(mut cs: CS, value: Option<u64>) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let values = match value {
+            Some(mut val) => {
+                let mut v = Vec::with_capacity(64);
+
+                for _ in 0..64 {
+                    v.push(Some(val & 1 == 1));
+                    val >>= 1;
+                }
+
+                v
+            }
+            None => vec![None; 64],
+        };
+
+        let bits = values
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                Ok(Boolean::from(AllocatedBit::alloc(
+                    cs.namespace(|| format!("allocated bit {}", i)),
+                    v,
+                )?))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt64 { bits, value })
+    }
+
+    /// Allocate a `UInt64` as 64 public inputs, one per bit, least significant first.
+    /// [`multipack`](super::multipack) packs the bits into fewer field elements.
+    pub fn alloc_input<E, CS>(mut cs: CS, value: Option<u64>) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let bits = (0..64)
+            .map(|i| {
+                Ok(Boolean::from(AllocatedBit::alloc_input(
+                    cs.namespace(|| format!("input bit {}", i)),
+                    value.map(|v| (v >> i) & 1 == 1),
+                )?))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt64 { bits, value })
+    }
+
+    /// The value, if it is known.
+    pub fn get_value(&self) -> Option<u64> {
+        self.value
+    }
+
+    /// Decomposes `num` into a `UInt64`, enforcing that its value is below 2^64.
+    ///
+    /// The 64 allocated bits must pack into `num`, and as no multiple of the field
+    /// modulus is below 2^64, no other value has the same bits. A larger witness
+    /// leaves the packing constraint unsatisfied.
+    pub fn from_allocated_num<E, CS>(
+        mut cs: CS,
+        num: &AllocatedNum<E>,
+    ) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let value = num.get_value().map(|v| v.into_repr().as_ref()[0]);
+        let uint = Self::alloc(cs.namespace(|| "bits"), value)?;
+
+        let lc = uint.pack_bits::<E, CS>();
+        cs.enforce(
+            || "packing constraint",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+
+        Ok(uint)
+    }
+
+    /// Packs the bits of this `UInt64` into an [`AllocatedNum`].
+    pub fn to_allocated_num<E, CS>(&self, mut cs: CS) -> Result<AllocatedNum<E>, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+            let value = self.value.get()?;
+            Ok(E::Fr::from_repr((*value).into()).expect("64 bits are in the field"))
+        })?;
+
+        let lc = self.pack_bits::<E, CS>();
+        cs.enforce(
+            || "packing constraint",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+
+        Ok(num)
+    }
+
+    /// The linear combination of the bits with the coefficients `2^i`.
+    fn pack_bits<E: ScalarEngine, CS: ConstraintSystem<E>>(&self) -> LinearCombination<E> {
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for bit in &self.bits {
+            lc = lc + &bit.lc(CS::one(), coeff);
+            coeff.double();
+        }
+
+        lc
+    }
+
+    pub fn into_bits_be(self) -> Vec<Boolean> {
+        let mut ret = self.bits;
+        ret.reverse();
+        ret
+    }
+
+    pub fn from_bits_be(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 64);
+
+        let mut value = Some(0u64);
+        for b in bits {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b.get_value() {
+                Some(true) => {
+                    value.as_mut().map(|v| *v |= 1);
+                }
+                Some(false) => {}
+                None => {
+                    value = None;
+                }
+            }
+        }
+
+        UInt64 {
+            value,
+            bits: bits.iter().rev().cloned().collect(),
+        }
+    }
+
+    /// Turns this `UInt64` into its little-endian byte order representation.
+    pub fn into_bits(self) -> Vec<Boolean> {
+        self.bits
+    }
+
+    /// Converts a little-endian byte order representation of bits into a
+    /// `UInt64`.
+    pub fn from_bits(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 64);
+
+        let mut value = Some(0u64);
+        for b in bits.iter().rev() {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b.get_value() {
+                Some(true) => {
+                    value.as_mut().map(|v| *v |= 1);
+                }
+                Some(false) => {}
+                None => value = None,
+            }
+        }
+
+        UInt64 {
+            value,
+            bits: bits.to_vec(),
+        }
+    }
+
+    pub fn rotr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let new_bits = self
+            .bits
+            .iter()
+            .skip(by)
+            .chain(self.bits.iter())
+            .take(64)
+            .cloned()
+            .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v.rotate_right(by as u32)),
+        }
+    }
+
+    pub fn rotl(&self, by: usize) -> Self {
+        self.rotr(64 - by % 64)
+    }
+
+    pub fn shr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let fill = Boolean::constant(false);
+
+        let new_bits = self
+            .bits
+            .iter() // The bits are least significant first
+            .skip(by) // Skip the bits that will be lost during the shift
+            .chain(Some(&fill).into_iter().cycle()) // Rest will be zeros
+            .take(64) // Only 64 bits needed!
+            .cloned()
+            .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v >> by as u32),
+        }
+    }
+
+    pub fn shl(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let fill = Boolean::constant(false);
+
+        let new_bits = Some(&fill)
+            .into_iter()
+            .cycle()
+            .take(by) // The low bits are zeros
+            .chain(self.bits.iter()) // followed by the bits that are kept
+            .take(64)
+            .cloned()
+            .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v << by as u32),
+        }
+    }
+
+    /// XOR this `UInt64` with another `UInt64`
+    pub fn xor<E, CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a ^ b),
+            _ => None,
+        };
+
+        let bits = self
+            .bits
+            .iter()
+            .zip(other.bits.iter())
+            .enumerate()
+            .map(|(i, (a, b))| Boolean::xor(cs.namespace(|| format!("xor of bit {}", i)), a, b))
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt64 {
+            bits,
+            value: new_value,
+        })
+    }
+
+    /// XOR of any number of `UInt64`s, bit by bit with [`Boolean::xor_many`].
+    pub fn xor_many<E, CS>(mut cs: CS, operands: &[Self]) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let new_value = operands
+            .iter()
+            .try_fold(0, |acc, x| x.value.map(|v| acc ^ v));
+
+        let bits = (0..64)
+            .map(|i| {
+                let bits = operands
+                    .iter()
+                    .map(|x| x.bits[i].clone())
+                    .collect::<Vec<_>>();
+                Boolean::xor_many(cs.namespace(|| format!("xor of bit {}", i)), &bits)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt64 {
+            bits,
+            value: new_value,
+        })
+    }
+
+    /// AND this `UInt64` with another `UInt64`
+    pub fn and<E, CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a & b),
+            _ => None,
+        };
+
+        let bits = self
+            .bits
+            .iter()
+            .zip(other.bits.iter())
+            .enumerate()
+            .map(|(i, (a, b))| Boolean::and(cs.namespace(|| format!("and of bit {}", i)), a, b))
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt64 {
+            bits,
+            value: new_value,
+        })
+    }
+
+    /// Perform modular addition of several `UInt64` objects.
+    pub fn addmany<E, CS, M>(mut cs: M, operands: &[Self]) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+        M: ConstraintSystem<E, Root = MultiEq<E, CS>>,
+    {
+        // Make some arbitrary bounds for ourselves to avoid overflows
+        // in the scalar field
+        assert!(E::Fr::NUM_BITS >= 128);
+        assert!(operands.len() >= 2); // Weird trivial cases that should never happen
+        assert!(operands.len() <= 10);
+
+        // Compute the maximum value of the sum so we allocate enough bits for
+        // the result
+        let mut max_value = (operands.len() as u128) * (u128::from(u64::max_value()));
+
+        // Keep track of the resulting value
+        let mut result_value = Some(0u128);
+
+        // This is a linear combination that we will enforce to equal the
+        // output
+        let mut lc = LinearCombination::zero();
+
+        let mut all_constants = true;
+
+        // Iterate over the operands
+        for op in operands {
+            // Accumulate the value
+            match op.value {
+                Some(val) => {
+                    result_value.as_mut().map(|v| *v += u128::from(val));
+                }
+                None => {
+                    // If any of our operands have unknown value, we won't
+                    // know the value of the result
+                    result_value = None;
+                }
+            }
+
+            // Iterate over each bit of the operand and add the operand to
+            // the linear combination
+            let mut coeff = E::Fr::one();
+            for bit in &op.bits {
+                lc = lc + &bit.lc(CS::one(), coeff);
+
+                all_constants &= bit.is_constant();
+
+                coeff.double();
+            }
+        }
+
+        // The value of the actual result is modulo 2^64
+        let modular_value = result_value.map(|v| v as u64);
+
+        if all_constants && modular_value.is_some() {
+            // We can just return a constant, rather than
+            // unpacking the result into allocated bits.
+
+            return Ok(UInt64::constant(modular_value.unwrap()));
+        }
+
+        // Storage area for the resulting bits
+        let mut result_bits = vec![];
+
+        // Linear combination representing the output,
+        // for comparison with the sum of the operands
+        let mut result_lc = LinearCombination::zero();
+
+        // Allocate each bit of the result
+        let mut coeff = E::Fr::one();
+        let mut i = 0;
+        while max_value != 0 {
+            // Allocate the bit
+            let b = AllocatedBit::alloc(
+                cs.namespace(|| format!("result bit {}", i)),
+                result_value.map(|v| (v >> i) & 1 == 1),
+            )?;
+
+            // Add this bit to the result combination
+            result_lc = result_lc + (coeff, b.get_variable());
+
+            result_bits.push(b.into());
+
+            max_value >>= 1;
+            i += 1;
+            coeff.double();
+        }
+
+        // Enforce equality between the sum and result
+        cs.get_root().enforce_equal(i, &lc, &result_lc);
+
+        // Discard carry bits that we don't care about
+        result_bits.truncate(64);
+
+        Ok(UInt64 {
+            bits: result_bits,
+            value: modular_value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UInt64;
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::Boolean;
+    use crate::gadgets::multieq::MultiEq;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::test::*;
+    use crate::ConstraintSystem;
+    use ff::{Field, PrimeField};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_uint64_from_bits() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..1000 {
+            let v = (0..64)
+                .map(|_| Boolean::constant(rng.next_u32() % 2 != 0))
+                .collect::<Vec<_>>();
+
+            let b = UInt64::from_bits(&v);
+            let b_be = UInt64::from_bits_be(&v.iter().rev().cloned().collect::<Vec<_>>());
+            assert_eq!(b.value, b_be.value);
+
+            for (i, bit) in b.bits.iter().enumerate() {
+                match *bit {
+                    Boolean::Constant(bit) => {
+                        assert!(bit == ((b.value.unwrap() >> i) & 1 == 1));
+                    }
+                    _ => unreachable!(),
+                }
+            }
+
+            let expected_to_be_same = b.into_bits();
+
+            for x in v.iter().zip(expected_to_be_same.iter()) {
+                match x {
+                    (&Boolean::Constant(true), &Boolean::Constant(true)) => {}
+                    (&Boolean::Constant(false), &Boolean::Constant(false)) => {}
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_xor() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+
+            let mut expected = a ^ b ^ c;
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "first xor"), &b_bit).unwrap();
+            let r = r.xor(cs.namespace(|| "second xor"), &c_bit).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 64 * 3);
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                assert_eq!(b.get_value().unwrap(), expected & 1 == 1);
+
+                expected >>= 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_xor_many() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+            let d = rng.next_u64();
+
+            let operands = [
+                UInt64::alloc(cs.namespace(|| "a"), Some(a)).unwrap(),
+                UInt64::constant(b),
+                UInt64::alloc(cs.namespace(|| "c"), Some(c)).unwrap(),
+                UInt64::alloc(cs.namespace(|| "d"), Some(d)).unwrap(),
+            ];
+            let before = cs.num_constraints();
+            let r = UInt64::xor_many(cs.namespace(|| "xor"), &operands).unwrap();
+
+            assert!(cs.is_satisfied());
+            let expected = a ^ b ^ c ^ d;
+            assert_eq!(r.value, Some(expected));
+            for (i, bit) in r.into_bits().iter().enumerate() {
+                assert_eq!(bit.get_value(), Some((expected >> i) & 1 == 1));
+            }
+
+            // Like a chain of XORs, which the constant does not add to.
+            assert_eq!(cs.num_constraints() - before, 2 * 64);
+        }
+
+        let r = UInt64::xor_many(TestConstraintSystem::<Bls12>::new(), &[]).unwrap();
+        assert_eq!(r.value, Some(0));
+    }
+
+    #[test]
+    fn test_uint64_addmany() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+            let d = rng.next_u64();
+
+            let mut expected = (a ^ b).wrapping_add(c).wrapping_add(d);
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::constant(c);
+            let d_bit = UInt64::alloc(cs.namespace(|| "d_bit"), Some(d)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &[r, c_bit, d_bit]).unwrap()
+            };
+
+            assert!(cs.is_satisfied());
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                match *b {
+                    Boolean::Is(ref b) => {
+                        assert!(b.get_value().unwrap() == (expected & 1 == 1));
+                    }
+                    Boolean::Not(ref b) => {
+                        assert!(!b.get_value().unwrap() == (expected & 1 == 1));
+                    }
+                    Boolean::Constant(_) => unreachable!(),
+                }
+
+                expected >>= 1;
+            }
+
+            // Flip a bit and see if the addition constraint still works
+            if cs.get("addition/result bit 0/boolean").is_zero() {
+                cs.set("addition/result bit 0/boolean", Field::one());
+            } else {
+                cs.set("addition/result bit 0/boolean", Field::zero());
+            }
+
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany_constants() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+
+            let expected = a.wrapping_add(b).wrapping_add(c);
+
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(
+                    cs.namespace(|| "addition"),
+                    &[
+                        UInt64::constant(a),
+                        UInt64::constant(b),
+                        UInt64::constant(c),
+                    ],
+                )
+                .unwrap()
+            };
+
+            assert_eq!(cs.num_constraints(), 0);
+            assert!(r.value == Some(expected));
+            assert!(r.bits.iter().all(|b| b.is_constant()));
+        }
+    }
+
+    #[test]
+    fn test_uint64_rotr() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let mut num = rng.next_u64();
+
+        let a = UInt64::constant(num);
+
+        for i in 0..64 {
+            let b = a.rotr(i);
+            assert_eq!(a.bits.len(), b.bits.len());
+
+            assert!(b.value.unwrap() == num);
+
+            let mut tmp = num;
+            for b in &b.bits {
+                match *b {
+                    Boolean::Constant(b) => {
+                        assert_eq!(b, tmp & 1 == 1);
+                    }
+                    _ => unreachable!(),
+                }
+
+                tmp >>= 1;
+            }
+
+            num = num.rotate_right(1);
+        }
+    }
+
+    #[test]
+    fn test_uint64_shifts() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..50 {
+            for i in 0..130 {
+                let num = rng.next_u64();
+                let a = UInt64::constant(num);
+
+                for (r, expected) in [
+                    (a.shr(i), num.wrapping_shr(i as u32)),
+                    (a.shl(i), num.wrapping_shl(i as u32)),
+                    (a.rotl(i), num.rotate_left(i as u32)),
+                ]
+                .iter()
+                {
+                    assert_eq!(r.value.unwrap(), *expected);
+
+                    let b = UInt64::constant(*expected);
+                    assert_eq!(r.bits.len(), b.bits.len());
+                    for (a, b) in r.bits.iter().zip(b.bits.iter()) {
+                        assert_eq!(a.get_value().unwrap(), b.get_value().unwrap());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_and() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+
+            let mut expected = a & b & c;
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+
+            let r = a_bit.and(cs.namespace(|| "first and"), &b_bit).unwrap();
+            let r = r.and(cs.namespace(|| "second and"), &c_bit).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                match *b {
+                    Boolean::Is(ref b) => {
+                        assert!(b.get_value().unwrap() == (expected & 1 == 1));
+                    }
+                    Boolean::Not(ref b) => {
+                        assert!(!b.get_value().unwrap() == (expected & 1 == 1));
+                    }
+                    Boolean::Constant(b) => {
+                        assert!(b == (expected & 1 == 1));
+                    }
+                }
+
+                expected >>= 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_alloc_input() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let num = rng.next_u64();
+        let a = UInt64::alloc_input(cs.namespace(|| "a"), Some(num)).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 1 + 64);
+        assert_eq!(cs.num_constraints(), 64);
+        assert_eq!(a.get_value(), Some(num));
+        assert_eq!(
+            a.into_bits()
+                .iter()
+                .map(|b| b.get_value().unwrap())
+                .collect::<Vec<_>>(),
+            (0..64).map(|i| (num >> i) & 1 == 1).collect::<Vec<_>>()
+        );
+
+        // A bit which is not boolean.
+        cs.set("a/input bit 3/boolean", Fr::from_str("2").unwrap());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_uint64_against_u64() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let by = (rng.next_u32() % 64) as usize;
+            let a_bit = UInt64::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_bit = UInt64::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+
+            let xor = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let and = a_bit.and(cs.namespace(|| "and"), &b_bit).unwrap();
+            assert_eq!(xor.get_value(), Some(a ^ b));
+            assert_eq!(and.get_value(), Some(a & b));
+            assert_eq!(a_bit.rotr(by).get_value(), Some(a.rotate_right(by as u32)));
+            assert_eq!(a_bit.rotl(by).get_value(), Some(a.rotate_left(by as u32)));
+            assert_eq!(a_bit.shr(by).get_value(), Some(a >> by));
+            assert_eq!(a_bit.shl(by).get_value(), Some(a << by));
+
+            // Sums of up to 10 operands, whose carries take up to 4 bits.
+            let num_operands = 2 + (rng.next_u32() % 9) as usize;
+            let values = (0..num_operands)
+                .map(|_| rng.next_u64())
+                .collect::<Vec<_>>();
+            let operands = values
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    UInt64::alloc(cs.namespace(|| format!("operand {}", i)), Some(v)).unwrap()
+                })
+                .collect::<Vec<_>>();
+            let sum = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &operands).unwrap()
+            };
+            let expected = values.iter().fold(0u64, |acc, v| acc.wrapping_add(*v));
+            assert_eq!(sum.get_value(), Some(expected));
+            assert_eq!(
+                UInt64::from_bits(&sum.clone().into_bits()).get_value(),
+                Some(expected)
+            );
+            assert_eq!(
+                UInt64::from_bits_be(&sum.into_bits_be()).get_value(),
+                Some(expected)
+            );
+
+            assert!(cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_constraints_parity() {
+        use crate::gadgets::uint32::UInt32;
+
+        fn count<F: FnOnce(&mut TestConstraintSystem<Bls12>)>(f: F) -> usize {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            f(&mut cs);
+            assert!(cs.is_satisfied());
+            cs.num_constraints()
+        }
+
+        let alloc64 = |cs: &mut TestConstraintSystem<Bls12>, name: &str| {
+            UInt64::alloc(cs.namespace(|| name), Some(u64::max_value())).unwrap()
+        };
+        let alloc32 = |cs: &mut TestConstraintSystem<Bls12>, name: &str| {
+            UInt32::alloc(cs.namespace(|| name), Some(u32::max_value())).unwrap()
+        };
+
+        // Bitwise operations cost twice as much as on a `UInt32`.
+        assert_eq!(
+            count(|cs| {
+                alloc64(cs, "a");
+            }),
+            2 * count(|cs| {
+                alloc32(cs, "a");
+            })
+        );
+        let xor64 = count(|cs| {
+            let (a, b) = (alloc64(cs, "a"), alloc64(cs, "b"));
+            a.xor(cs.namespace(|| "xor"), &b).unwrap();
+        });
+        let xor32 = count(|cs| {
+            let (a, b) = (alloc32(cs, "a"), alloc32(cs, "b"));
+            a.xor(cs.namespace(|| "xor"), &b).unwrap();
+        });
+        assert_eq!(xor64, 2 * xor32);
+        let and64 = count(|cs| {
+            let (a, b) = (alloc64(cs, "a"), alloc64(cs, "b"));
+            a.and(cs.namespace(|| "and"), &b).unwrap();
+        });
+        assert_eq!(and64, xor64);
+
+        // An addition allocates the 32 more bits of its result, and the carries and
+        // the packed equality cost the same.
+        for num_operands in 2..=10 {
+            let add64 = count(|cs| {
+                let operands = (0..num_operands)
+                    .map(|i| alloc64(cs, &format!("operand {}", i)))
+                    .collect::<Vec<_>>();
+                let mut cs = MultiEq::new(cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &operands).unwrap();
+            });
+            let add32 = count(|cs| {
+                let operands = (0..num_operands)
+                    .map(|i| alloc32(cs, &format!("operand {}", i)))
+                    .collect::<Vec<_>>();
+                let mut cs = MultiEq::new(cs);
+                UInt32::addmany(cs.namespace(|| "addition"), &operands).unwrap();
+            });
+            assert_eq!(add64 - 64 * num_operands, add32 - 32 * num_operands + 32);
+        }
+    }
+
+    #[test]
+    fn test_uint64_allocated_num() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for i in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let v = match i {
+                0 => 0,
+                1 => u64::max_value(),
+                _ => rng.next_u64(),
+            };
+            let fr = Fr::from_str(&v.to_string()).unwrap();
+            let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(fr)).unwrap();
+
+            let uint = UInt64::from_allocated_num(cs.namespace(|| "from num"), &num).unwrap();
+            assert_eq!(uint.get_value(), Some(v));
+            assert_eq!(cs.num_constraints(), 64 + 1);
+
+            let packed = uint.to_allocated_num(cs.namespace(|| "to num")).unwrap();
+            assert_eq!(packed.get_value(), Some(fr));
+            assert_eq!(cs.num_constraints(), 64 + 2);
+            assert!(cs.is_satisfied());
+
+            // The packed number must be the value of the bits.
+            let mut other = fr;
+            other.add_assign(&Fr::one());
+            cs.set("to num/num/num", other);
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_from_allocated_num_out_of_range() {
+        // 2^64 has the same low 64 bits as zero, and so do larger values.
+        for value in &[
+            "18446744073709551616",
+            "18446744073709551621",
+            "52435875175126190479447740508185965837690552500527637822603658699938581184512",
+        ] {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let fr = Fr::from_str(value).unwrap();
+            let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(fr)).unwrap();
+            UInt64::from_allocated_num(cs.namespace(|| "from num"), &num).unwrap();
+
+            assert!(!cs.is_satisfied());
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("from num/packing constraint")
+            );
+        }
+    }
+}
@@ -0,0 +1,487 @@
+//! Circuit representation of a [`u8`], and the conversions of bytes to bits and
+//! words.
+//!
+//! Hash gadgets take their input as bits, and disagree on the order of the bits
+//! of a byte: [`sha256`](super::sha256::sha256) takes the most significant bit
+//! first, while [`blake2s`](super::blake2s::blake2s) and
+//! [`multipack`](super::multipack) take the least significant bit first. Keeping
+//! data as [`UInt8`]s until it reaches a gadget, and converting it with
+//! [`bytes_to_bits_be`] or [`bytes_to_bits_le`], makes the order explicit.
+
+use ff::ScalarEngine;
+
+use crate::{ConstraintSystem, SynthesisError};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::uint32::UInt32;
+use super::uint64::UInt64;
+
+/// Represents an interpretation of 8 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt8 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u8>,
+}
+
+impl UInt8 {
+    /// Construct a constant `UInt8` from a `u8`
+    pub fn constant(value: u8) -> Self {
+        let bits = (0..8)
+            .map(|i| Boolean::constant((value >> i) & 1 == 1))
+            .collect();
+
+        UInt8 {
+            bits,
+            value: Some(value),
+        }
+    }
+
+    /// Construct constant `UInt8`s from bytes
+    pub fn constant_vec(values: &[u8]) -> Vec<Self> {
+        values.iter().map(|&v| UInt8::constant(v)).collect()
+    }
+
+    /// Allocate a `UInt8` in the constraint system
+    pub fn alloc<E, CS>(mut cs: CS, value: Option<u8>) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let bits = (0..8)
+            .map(|i| {
+                Ok(Boolean::from(AllocatedBit::alloc(
+                    cs.namespace(|| format!("allocated bit {}", i)),
+                    value.map(|v| (v >> i) & 1 == 1),
+                )?))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt8 { bits, value })
+    }
+
+    /// The value, if it is known.
+    pub fn get_value(&self) -> Option<u8> {
+        self.value
+    }
+
+    /// The bits of this `UInt8`, least significant first.
+    pub fn into_bits_le(self) -> Vec<Boolean> {
+        self.bits
+    }
+
+    /// Converts 8 bits, least significant first, into a `UInt8`.
+    pub fn from_bits_le(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 8);
+
+        let mut value = Some(0u8);
+        for (i, b) in bits.iter().enumerate() {
+            match b.get_value() {
+                Some(true) => {
+                    value.as_mut().map(|v| *v |= 1 << i);
+                }
+                Some(false) => {}
+                None => value = None,
+            }
+        }
+
+        UInt8 {
+            bits: bits.to_vec(),
+            value,
+        }
+    }
+
+    /// The bits of this `UInt8`, most significant first.
+    pub fn into_bits_be(self) -> Vec<Boolean> {
+        let mut ret = self.bits;
+        ret.reverse();
+        ret
+    }
+
+    /// Converts 8 bits, most significant first, into a `UInt8`.
+    pub fn from_bits_be(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 8);
+
+        let bits = bits.iter().rev().cloned().collect::<Vec<_>>();
+        Self::from_bits_le(&bits)
+    }
+
+    /// XOR this `UInt8` with another `UInt8`
+    pub fn xor<E, CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a ^ b),
+            _ => None,
+        };
+
+        let bits = self
+            .bits
+            .iter()
+            .zip(other.bits.iter())
+            .enumerate()
+            .map(|(i, (a, b))| Boolean::xor(cs.namespace(|| format!("xor of bit {}", i)), a, b))
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt8 {
+            bits,
+            value: new_value,
+        })
+    }
+
+    /// AND this `UInt8` with another `UInt8`
+    pub fn and<E, CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        E: ScalarEngine,
+        CS: ConstraintSystem<E>,
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a & b),
+            _ => None,
+        };
+
+        let bits = self
+            .bits
+            .iter()
+            .zip(other.bits.iter())
+            .enumerate()
+            .map(|(i, (a, b))| Boolean::and(cs.namespace(|| format!("and of bit {}", i)), a, b))
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt8 {
+            bits,
+            value: new_value,
+        })
+    }
+}
+
+/// The bits of `bytes`, in order, with the least significant bit of each byte
+/// first, as [`blake2s`](super::blake2s::blake2s) takes them.
+pub fn bytes_to_bits_le(bytes: &[UInt8]) -> Vec<Boolean> {
+    bytes
+        .iter()
+        .flat_map(|byte| byte.clone().into_bits_le())
+        .collect()
+}
+
+/// The bits of `bytes`, in order, with the most significant bit of each byte
+/// first, as [`sha256`](super::sha256::sha256) takes them.
+pub fn bytes_to_bits_be(bytes: &[UInt8]) -> Vec<Boolean> {
+    bytes
+        .iter()
+        .flat_map(|byte| byte.clone().into_bits_be())
+        .collect()
+}
+
+/// The bytes of bits given with the least significant bit of each byte first.
+pub fn bits_le_to_bytes(bits: &[Boolean]) -> Vec<UInt8> {
+    assert!(bits.len() % 8 == 0);
+
+    bits.chunks(8).map(UInt8::from_bits_le).collect()
+}
+
+/// The bytes of bits given with the most significant bit of each byte first.
+pub fn bits_be_to_bytes(bits: &[Boolean]) -> Vec<UInt8> {
+    assert!(bits.len() % 8 == 0);
+
+    bits.chunks(8).map(UInt8::from_bits_be).collect()
+}
+
+impl UInt32 {
+    /// Converts 4 bytes, the least significant first, into a `UInt32`.
+    pub fn from_bytes_le(bytes: &[UInt8]) -> Self {
+        assert_eq!(bytes.len(), 4);
+
+        UInt32::from_bits(&bytes_to_bits_le(bytes))
+    }
+
+    /// Converts 4 bytes, the most significant first, into a `UInt32`.
+    pub fn from_bytes_be(bytes: &[UInt8]) -> Self {
+        assert_eq!(bytes.len(), 4);
+
+        UInt32::from_bits_be(&bytes_to_bits_be(bytes))
+    }
+
+    /// The 4 bytes of this `UInt32`, the least significant first.
+    pub fn into_bytes_le(self) -> Vec<UInt8> {
+        bits_le_to_bytes(&self.into_bits())
+    }
+
+    /// The 4 bytes of this `UInt32`, the most significant first.
+    pub fn into_bytes_be(self) -> Vec<UInt8> {
+        bits_be_to_bytes(&self.into_bits_be())
+    }
+}
+
+impl UInt64 {
+    /// Converts 8 bytes, the least significant first, into a `UInt64`.
+    pub fn from_bytes_le(bytes: &[UInt8]) -> Self {
+        assert_eq!(bytes.len(), 8);
+
+        UInt64::from_bits(&bytes_to_bits_le(bytes))
+    }
+
+    /// Converts 8 bytes, the most significant first, into a `UInt64`.
+    pub fn from_bytes_be(bytes: &[UInt8]) -> Self {
+        assert_eq!(bytes.len(), 8);
+
+        UInt64::from_bits_be(&bytes_to_bits_be(bytes))
+    }
+
+    /// The 8 bytes of this `UInt64`, the least significant first.
+    pub fn into_bytes_le(self) -> Vec<UInt8> {
+        bits_le_to_bytes(&self.into_bits())
+    }
+
+    /// The 8 bytes of this `UInt64`, the most significant first.
+    pub fn into_bytes_be(self) -> Vec<UInt8> {
+        bits_be_to_bytes(&self.into_bits_be())
+    }
+}
+
+/// The order of the bytes of a word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// The least significant byte first.
+    LittleEndian,
+    /// The most significant byte first.
+    BigEndian,
+}
+
+/// Converts `bytes` into words of 4 bytes each.
+pub fn bytes_to_uint32s(bytes: &[UInt8], order: ByteOrder) -> Vec<UInt32> {
+    assert!(bytes.len() % 4 == 0);
+
+    bytes
+        .chunks(4)
+        .map(|word| match order {
+            ByteOrder::LittleEndian => UInt32::from_bytes_le(word),
+            ByteOrder::BigEndian => UInt32::from_bytes_be(word),
+        })
+        .collect()
+}
+
+/// Converts `words` into their bytes.
+pub fn uint32s_to_bytes(words: &[UInt32], order: ByteOrder) -> Vec<UInt8> {
+    words
+        .iter()
+        .flat_map(|word| match order {
+            ByteOrder::LittleEndian => word.clone().into_bytes_le(),
+            ByteOrder::BigEndian => word.clone().into_bytes_be(),
+        })
+        .collect()
+}
+
+/// Converts `bytes` into words of 8 bytes each.
+pub fn bytes_to_uint64s(bytes: &[UInt8], order: ByteOrder) -> Vec<UInt64> {
+    assert!(bytes.len() % 8 == 0);
+
+    bytes
+        .chunks(8)
+        .map(|word| match order {
+            ByteOrder::LittleEndian => UInt64::from_bytes_le(word),
+            ByteOrder::BigEndian => UInt64::from_bytes_be(word),
+        })
+        .collect()
+}
+
+/// Converts `words` into their bytes.
+pub fn uint64s_to_bytes(words: &[UInt64], order: ByteOrder) -> Vec<UInt8> {
+    words
+        .iter()
+        .flat_map(|word| match order {
+            ByteOrder::LittleEndian => word.clone().into_bytes_le(),
+            ByteOrder::BigEndian => word.clone().into_bytes_be(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bls::Bls12;
+    use crate::gadgets::blake2s::blake2s_bytes;
+    use crate::gadgets::sha256::{sha256, sha256_bytes};
+    use crate::gadgets::test::TestConstraintSystem;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn alloc_bytes<CS: ConstraintSystem<Bls12>>(mut cs: CS, data: &[u8]) -> Vec<UInt8> {
+        data.iter()
+            .enumerate()
+            .map(|(i, &b)| UInt8::alloc(cs.namespace(|| format!("byte {}", i)), Some(b)).unwrap())
+            .collect()
+    }
+
+    fn values(bytes: &[UInt8]) -> Vec<u8> {
+        bytes.iter().map(|b| b.get_value().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_uint8_bits() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let v = rng.next_u32() as u8;
+            let a = UInt8::alloc(cs.namespace(|| "a"), Some(v)).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 8);
+            assert_eq!(a.get_value(), Some(v));
+
+            let le = a.clone().into_bits_le();
+            let be = a.into_bits_be();
+            for i in 0..8 {
+                assert_eq!(le[i].get_value(), Some((v >> i) & 1 == 1));
+                assert_eq!(be[7 - i].get_value(), Some((v >> i) & 1 == 1));
+            }
+            assert_eq!(UInt8::from_bits_le(&le).get_value(), Some(v));
+            assert_eq!(UInt8::from_bits_be(&be).get_value(), Some(v));
+            // The same bits in the other order are another byte.
+            assert_eq!(UInt8::from_bits_be(&le).get_value(), Some(v.reverse_bits()));
+
+            let c = UInt8::constant(v);
+            assert_eq!(UInt8::from_bits_le(&c.into_bits_le()).get_value(), Some(v));
+        }
+    }
+
+    #[test]
+    fn test_uint8_xor_and() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let (a, b, c) = (
+                rng.next_u32() as u8,
+                rng.next_u32() as u8,
+                rng.next_u32() as u8,
+            );
+            let a_byte = UInt8::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_byte = UInt8::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+            let c_byte = UInt8::constant(c);
+
+            let xor = a_byte.xor(cs.namespace(|| "xor"), &b_byte).unwrap();
+            let and = xor.and(cs.namespace(|| "and"), &c_byte).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(xor.get_value(), Some(a ^ b));
+            assert_eq!(and.get_value(), Some((a ^ b) & c));
+            assert_eq!(
+                UInt8::from_bits_le(&and.into_bits_le()).get_value(),
+                Some((a ^ b) & c)
+            );
+        }
+    }
+
+    #[test]
+    fn test_bytes_to_words() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let data = (0..64).map(|_| rng.next_u32() as u8).collect::<Vec<_>>();
+        let bytes = alloc_bytes(&mut cs, &data);
+
+        for &order in &[ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let words = bytes_to_uint32s(&bytes, order);
+            for (word, expected) in words.iter().zip(data.chunks(4)) {
+                let mut expected_bytes = [0; 4];
+                expected_bytes.copy_from_slice(expected);
+                let expected = match order {
+                    ByteOrder::LittleEndian => u32::from_le_bytes(expected_bytes),
+                    ByteOrder::BigEndian => u32::from_be_bytes(expected_bytes),
+                };
+                assert_eq!(word.get_value(), Some(expected));
+            }
+            assert_eq!(values(&uint32s_to_bytes(&words, order)), data);
+
+            let words = bytes_to_uint64s(&bytes, order);
+            for (word, expected) in words.iter().zip(data.chunks(8)) {
+                let mut expected_bytes = [0; 8];
+                expected_bytes.copy_from_slice(expected);
+                let expected = match order {
+                    ByteOrder::LittleEndian => u64::from_le_bytes(expected_bytes),
+                    ByteOrder::BigEndian => u64::from_be_bytes(expected_bytes),
+                };
+                assert_eq!(word.get_value(), Some(expected));
+            }
+            assert_eq!(values(&uint64s_to_bytes(&words, order)), data);
+        }
+
+        assert_eq!(values(&bits_le_to_bytes(&bytes_to_bits_le(&bytes))), data);
+        assert_eq!(values(&bits_be_to_bytes(&bytes_to_bits_be(&bytes))), data);
+
+        // Words in the other byte order hold the bytes reversed.
+        let le = bytes_to_uint32s(&bytes, ByteOrder::LittleEndian);
+        let be = bytes_to_uint32s(&bytes, ByteOrder::BigEndian);
+        for (le, be) in le.iter().zip(be.iter()) {
+            assert_eq!(
+                le.get_value().unwrap().swap_bytes(),
+                be.get_value().unwrap()
+            );
+        }
+
+        // Nothing but the allocation of the bytes costs constraints.
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 64 * 8);
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        use blake2s_simd::Params as Blake2sParams;
+        use sha2::{Digest, Sha256};
+
+        let data = (0..100).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let bytes = alloc_bytes(cs.namespace(|| "data"), &data);
+        let digest = sha256_bytes(cs.namespace(|| "sha256"), &bytes).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(values(&digest), Sha256::digest(&data).to_vec());
+
+        let digest = blake2s_bytes(cs.namespace(|| "blake2s"), &bytes, b"12345678").unwrap();
+        assert!(cs.is_satisfied());
+        let expected = Blake2sParams::new()
+            .hash_length(32)
+            .personal(b"12345678")
+            .hash(&data);
+        assert_eq!(values(&digest), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_endianness_mix_up() {
+        use sha2::{Digest, Sha256};
+
+        let data = b"abc";
+        let expected = Sha256::digest(data).to_vec();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let bytes = alloc_bytes(cs.namespace(|| "data"), data);
+
+        let right = sha256(cs.namespace(|| "right"), &bytes_to_bits_be(&bytes)).unwrap();
+        assert_eq!(values(&bits_be_to_bytes(&right)), expected);
+
+        // The bits of each byte in the order blake2s takes them are another message.
+        let wrong = sha256(cs.namespace(|| "wrong"), &bytes_to_bits_le(&bytes)).unwrap();
+        assert_ne!(values(&bits_be_to_bytes(&wrong)), expected);
+        assert_eq!(
+            values(&bits_be_to_bytes(&wrong)),
+            Sha256::digest(&data.iter().map(|b| b.reverse_bits()).collect::<Vec<_>>()).to_vec()
+        );
+
+        // As is the digest read in the wrong order.
+        assert_ne!(values(&bits_le_to_bytes(&right)), expected);
+        assert!(cs.is_satisfied());
+    }
+}
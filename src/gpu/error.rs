@@ -16,10 +16,35 @@ pub enum GPUError {
     KernelUninitialized,
     #[error("GPU accelerator is disabled!")]
     GPUDisabled,
+    #[error("No working GPU found!")]
+    DeviceNotFound,
+    #[error("GPU out of memory! {requested} bytes requested.")]
+    OutOfMemory { requested: usize },
+    #[error("GPU kernel compilation failed: {0}")]
+    KernelCompilationFailed(String),
+    #[error("GPU kernel execution failed: {0}")]
+    KernelExecutionFailed(String),
+    #[error("Data transfer between the host and the GPU failed!")]
+    TransferFailed,
+    #[cfg(feature = "gpu")]
+    #[error("Cannot acquire GPU lock: {0}")]
+    Lock(#[from] std::io::Error),
+    #[error("GPU result of chunk {chunk} differs from the CPU result on device '{device}'!")]
+    ResultMismatch { chunk: usize, device: String },
 }
 
 pub type GPUResult<T> = std::result::Result<T, GPUError>;
 
+#[cfg(feature = "gpu")]
+impl GPUError {
+    /// A failed allocation of `n` elements of `T` on the device.
+    pub(crate) fn out_of_memory<T>(n: usize) -> Self {
+        GPUError::OutOfMemory {
+            requested: n * std::mem::size_of::<T>(),
+        }
+    }
+}
+
 #[cfg(feature = "gpu")]
 impl From<std::boxed::Box<dyn std::any::Any + std::marker::Send>> for GPUError {
     fn from(e: std::boxed::Box<dyn std::any::Any + std::marker::Send>) -> Self {
@@ -12,14 +12,33 @@ const LOG2_MAX_ELEMENTS: usize = 32; // At most 2^32 elements is supported.
 const MAX_LOG2_RADIX: u32 = 8; // Radix256
 const MAX_LOG2_LOCAL_WORK_SIZE: u32 = 7; // 128
 
-pub struct FFTKernel<E>
+/// The precalculated values of the FFTs of one size with one `omega`.
+struct Twiddles<E>
 where
     E: Engine,
 {
-    program: opencl::Program,
+    omega: E::Fr,
+    n: usize,
     pq_buffer: opencl::Buffer<E::Fr>,
     omegas_buffer: opencl::Buffer<E::Fr>,
-    _lock: locks::GPULock, // RFC 1857: struct fields are dropped in the same order as they are declared.
+}
+
+/// The kernel keeps the precalculated values of this many FFTs, which covers an FFT and
+/// its inverse.
+const MAX_CACHED_TWIDDLES: usize = 2;
+
+pub struct FFTKernel<E>
+where
+    E: Engine,
+{
+    program: opencl::Program,
+    /// Uploaded once per size and direction, most recently used last.
+    twiddles: Vec<Twiddles<E>>,
+    /// The source and destination buffers of the last FFT and their size, reused by the
+    /// next FFT of the same size.
+    fft_buffers: Option<(usize, opencl::Buffer<E::Fr>, opencl::Buffer<E::Fr>)>,
+    /// The lock of a kernel from [`FFTKernel::create`], which holds it itself.
+    _lock: Option<locks::GPULock>, // RFC 1857: struct fields are dropped in the same order as they are declared.
     priority: bool,
 }
 
@@ -28,11 +47,18 @@ where
     E: Engine,
 {
     pub fn create(priority: bool) -> GPUResult<FFTKernel<E>> {
-        let lock = locks::GPULock::lock();
+        let lock = locks::GPULock::lock()?;
+        let mut kernel = Self::create_with_lock(priority, &lock)?;
+        kernel._lock = Some(lock);
 
+        Ok(kernel)
+    }
+
+    /// Creates a kernel while the caller holds `lock`, which must outlive the kernel.
+    pub fn create_with_lock(priority: bool, _lock: &locks::GPULock) -> GPUResult<FFTKernel<E>> {
         let devices = opencl::Device::all()?;
         if devices.is_empty() {
-            return Err(GPUError::Simple("No working GPUs found!"));
+            return Err(GPUError::DeviceNotFound);
         }
 
         // Select the first device for FFT
@@ -40,37 +66,43 @@ where
 
         let src = sources::kernel::<E>(device.brand() == opencl::Brand::Nvidia);
 
-        let program = opencl::Program::from_opencl(device, &src)?;
-        let pq_buffer = program.create_buffer::<E::Fr>(1 << MAX_LOG2_RADIX >> 1)?;
-        let omegas_buffer = program.create_buffer::<E::Fr>(LOG2_MAX_ELEMENTS)?;
+        let program = opencl::Program::from_opencl(device, &src)
+            .map_err(|e| GPUError::KernelCompilationFailed(e.to_string()))?;
 
         info!("FFT: 1 working device(s) selected.");
         info!("FFT: Device 0: {}", program.device().name());
 
         Ok(FFTKernel {
             program,
-            pq_buffer,
-            omegas_buffer,
-            _lock: lock,
+            twiddles: Vec::new(),
+            fft_buffers: None,
+            _lock: None,
             priority,
         })
     }
 
+    fn create_buffer(&self, n: usize) -> GPUResult<opencl::Buffer<E::Fr>> {
+        self.program
+            .create_buffer::<E::Fr>(n)
+            .map_err(|_| GPUError::out_of_memory::<E::Fr>(n))
+    }
+
     /// Peforms a FFT round
     /// * `log_n` - Specifies log2 of number of elements
     /// * `log_p` - Specifies log2 of `p`, (http://www.bealto.com/gpu-fft_group-1.html)
     /// * `deg` - 1=>radix2, 2=>radix4, 3=>radix8, ...
     /// * `max_deg` - The precalculated values pq` and `omegas` are valid for radix degrees up to `max_deg`
     fn radix_fft_round(
-        &mut self,
+        &self,
         src_buffer: &opencl::Buffer<E::Fr>,
         dst_buffer: &opencl::Buffer<E::Fr>,
+        twiddles: &Twiddles<E>,
         log_n: u32,
         log_p: u32,
         deg: u32,
         max_deg: u32,
     ) -> GPUResult<()> {
-        if locks::PriorityLock::should_break(self.priority) {
+        if locks::PriorityLock::should_break(self.priority)? {
             return Err(GPUError::GPUTaken);
         }
 
@@ -86,19 +118,23 @@ where
             kernel,
             src_buffer,
             dst_buffer,
-            &self.pq_buffer,
-            &self.omegas_buffer,
+            &twiddles.pq_buffer,
+            &twiddles.omegas_buffer,
             opencl::LocalBuffer::<E::Fr>::new(1 << deg),
             n,
             log_p,
             deg,
             max_deg
-        )?;
+        )
+        .map_err(|e| GPUError::KernelExecutionFailed(e.to_string()))?;
         Ok(())
     }
 
     /// Share some precalculated values between threads to boost the performance
-    fn setup_pq_omegas(&mut self, omega: &E::Fr, n: usize, max_deg: u32) -> GPUResult<()> {
+    fn setup_pq_omegas(&self, omega: &E::Fr, n: usize, max_deg: u32) -> GPUResult<Twiddles<E>> {
+        let mut pq_buffer = self.create_buffer(1 << MAX_LOG2_RADIX >> 1)?;
+        let mut omegas_buffer = self.create_buffer(LOG2_MAX_ELEMENTS)?;
+
         // Precalculate:
         // [omega^(0/(2^(deg-1))), omega^(1/(2^(deg-1))), ..., omega^((2^(deg-1)-1)/(2^(deg-1)))]
         let mut pq = vec![E::Fr::zero(); 1 << max_deg >> 1];
@@ -111,7 +147,9 @@ where
                 pq[i].mul_assign(&twiddle);
             }
         }
-        self.pq_buffer.write_from(0, &pq)?;
+        pq_buffer
+            .write_from(0, &pq)
+            .map_err(|_| GPUError::TransferFailed)?;
 
         // Precalculate [omega, omega^2, omega^4, omega^8, ..., omega^(2^31)]
         let mut omegas = vec![E::Fr::zero(); 32];
@@ -119,7 +157,38 @@ where
         for i in 1..LOG2_MAX_ELEMENTS {
             omegas[i] = omegas[i - 1].pow([2u64]);
         }
-        self.omegas_buffer.write_from(0, &omegas)?;
+        omegas_buffer
+            .write_from(0, &omegas)
+            .map_err(|_| GPUError::TransferFailed)?;
+
+        Ok(Twiddles {
+            omega: *omega,
+            n,
+            pq_buffer,
+            omegas_buffer,
+        })
+    }
+
+    /// Makes the precalculated values for FFTs of size `n` with `omega` the last entry
+    /// of `self.twiddles`, uploading them only if they are not cached.
+    fn load_twiddles(&mut self, omega: &E::Fr, n: usize, max_deg: u32) -> GPUResult<()> {
+        match self
+            .twiddles
+            .iter()
+            .position(|t| t.omega == *omega && t.n == n)
+        {
+            Some(i) => {
+                let twiddles = self.twiddles.remove(i);
+                self.twiddles.push(twiddles);
+            }
+            None => {
+                let twiddles = self.setup_pq_omegas(omega, n, max_deg)?;
+                if self.twiddles.len() == MAX_CACHED_TWIDDLES {
+                    self.twiddles.remove(0);
+                }
+                self.twiddles.push(twiddles);
+            }
+        }
 
         Ok(())
     }
@@ -129,22 +198,38 @@ where
     /// * `log_n` - Specifies log2 of number of elements
     pub fn radix_fft(&mut self, a: &mut [E::Fr], omega: &E::Fr, log_n: u32) -> GPUResult<()> {
         let n = 1 << log_n;
-        let mut src_buffer = self.program.create_buffer::<E::Fr>(n)?;
-        let mut dst_buffer = self.program.create_buffer::<E::Fr>(n)?;
+        let (mut src_buffer, mut dst_buffer) = match self.fft_buffers.take() {
+            Some((len, src_buffer, dst_buffer)) if len == n => (src_buffer, dst_buffer),
+            _ => (self.create_buffer(n)?, self.create_buffer(n)?),
+        };
 
         let max_deg = cmp::min(MAX_LOG2_RADIX, log_n);
-        self.setup_pq_omegas(omega, n, max_deg)?;
+        self.load_twiddles(omega, n, max_deg)?;
+        let twiddles = self.twiddles.last().unwrap();
 
-        src_buffer.write_from(0, &*a)?;
+        src_buffer
+            .write_from(0, &*a)
+            .map_err(|_| GPUError::TransferFailed)?;
         let mut log_p = 0u32;
         while log_p < log_n {
             let deg = cmp::min(max_deg, log_n - log_p);
-            self.radix_fft_round(&src_buffer, &dst_buffer, log_n, log_p, deg, max_deg)?;
+            self.radix_fft_round(
+                &src_buffer,
+                &dst_buffer,
+                twiddles,
+                log_n,
+                log_p,
+                deg,
+                max_deg,
+            )?;
             log_p += deg;
             std::mem::swap(&mut src_buffer, &mut dst_buffer);
         }
 
-        src_buffer.read_into(0, a)?;
+        src_buffer
+            .read_into(0, a)
+            .map_err(|_| GPUError::TransferFailed)?;
+        self.fft_buffers = Some((n, src_buffer, dst_buffer));
 
         Ok(())
     }
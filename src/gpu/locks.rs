@@ -15,12 +15,12 @@ fn tmp_path(filename: &str) -> PathBuf {
 #[derive(Debug)]
 pub struct GPULock(File);
 impl GPULock {
-    pub fn lock() -> GPULock {
+    pub fn lock() -> GPUResult<GPULock> {
         debug!("Acquiring GPU lock...");
-        let f = File::create(tmp_path(GPU_LOCK_NAME)).unwrap();
-        f.lock_exclusive().unwrap();
+        let f = File::create(tmp_path(GPU_LOCK_NAME))?;
+        f.lock_exclusive()?;
         debug!("GPU lock acquired!");
-        GPULock(f)
+        Ok(GPULock(f))
     }
 }
 impl Drop for GPULock {
@@ -36,27 +36,24 @@ impl Drop for GPULock {
 #[derive(Debug)]
 pub struct PriorityLock(File);
 impl PriorityLock {
-    pub fn lock() -> PriorityLock {
+    pub fn lock() -> GPUResult<PriorityLock> {
         debug!("Acquiring priority lock...");
-        let f = File::create(tmp_path(PRIORITY_LOCK_NAME)).unwrap();
-        f.lock_exclusive().unwrap();
+        let f = File::create(tmp_path(PRIORITY_LOCK_NAME))?;
+        f.lock_exclusive()?;
         debug!("Priority lock acquired!");
-        PriorityLock(f)
+        Ok(PriorityLock(f))
     }
-    pub fn wait(priority: bool) {
+    pub fn wait(priority: bool) -> GPUResult<()> {
         if !priority {
-            File::create(tmp_path(PRIORITY_LOCK_NAME))
-                .unwrap()
-                .lock_exclusive()
-                .unwrap();
+            File::create(tmp_path(PRIORITY_LOCK_NAME))?.lock_exclusive()?;
         }
+        Ok(())
     }
-    pub fn should_break(priority: bool) -> bool {
-        !priority
-            && File::create(tmp_path(PRIORITY_LOCK_NAME))
-                .unwrap()
+    pub fn should_break(priority: bool) -> GPUResult<bool> {
+        Ok(!priority
+            && File::create(tmp_path(PRIORITY_LOCK_NAME))?
                 .try_lock_exclusive()
-                .is_err()
+                .is_err())
     }
 }
 impl Drop for PriorityLock {
@@ -71,16 +68,77 @@ use super::multiexp::MultiexpKernel;
 use crate::bls::Engine;
 use crate::domain::create_fft_kernel;
 use crate::multiexp::create_multiexp_kernel;
+use std::sync::{Arc, Mutex};
+
+/// The GPU kernels of a computation which alternates between FFTs and multiexps, e.g. a
+/// batch of proofs.
+///
+/// The kernels share a single [`GPULock`], so that they stay alive side by side instead
+/// of being created anew for every turn. When a high-priority process takes the GPU,
+/// all of them are freed together, releasing the lock.
+pub struct GPUKernels<E: Engine>(Arc<Mutex<Kernels<E>>>);
+
+impl<E: Engine> Clone for GPUKernels<E> {
+    fn clone(&self) -> Self {
+        GPUKernels(self.0.clone())
+    }
+}
+
+impl<E: Engine> Default for GPUKernels<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> GPUKernels<E> {
+    pub fn new() -> Self {
+        GPUKernels(Arc::new(Mutex::new(Kernels {
+            fft: None,
+            multiexp: None,
+            lock: None,
+        })))
+    }
+}
+
+struct Kernels<E: Engine> {
+    fft: Option<FFTKernel<E>>,
+    multiexp: Option<MultiexpKernel<E>>,
+    lock: Option<GPULock>, // RFC 1857: struct fields are dropped in the same order as they are declared.
+}
+
+impl<E: Engine> Kernels<E> {
+    /// Takes the lock, unless one of the kernels holds it already.
+    fn lock(&mut self, priority: bool) -> GPUResult<()> {
+        if self.lock.is_none() {
+            PriorityLock::wait(priority)?;
+            self.lock = Some(GPULock::lock()?);
+        }
+        Ok(())
+    }
+
+    /// Releases the lock if no kernel holds it.
+    fn unlock_if_unused(&mut self) {
+        if self.fft.is_none() && self.multiexp.is_none() {
+            self.lock = None;
+        }
+    }
+
+    fn free(&mut self) {
+        self.fft = None;
+        self.multiexp = None;
+        self.lock = None;
+    }
+}
 
 macro_rules! locked_kernel {
-    ($class:ident, $kern:ident, $func:ident, $name:expr) => {
+    ($class:ident, $kern:ident, $field:ident, $func:ident, $name:expr) => {
         pub struct $class<E>
         where
             E: Engine,
         {
             log_d: usize,
             priority: bool,
-            kernel: Option<$kern<E>>,
+            kernels: GPUKernels<E>,
         }
 
         impl<E> $class<E>
@@ -88,27 +146,19 @@ macro_rules! locked_kernel {
             E: Engine,
         {
             pub fn new(log_d: usize, priority: bool) -> $class<E> {
+                Self::with_kernels(log_d, priority, &GPUKernels::new())
+            }
+
+            /// A kernel of `kernels`, sharing the GPU with the other kernels of it.
+            pub fn with_kernels(
+                log_d: usize,
+                priority: bool,
+                kernels: &GPUKernels<E>,
+            ) -> $class<E> {
                 $class::<E> {
                     log_d,
                     priority,
-                    kernel: None,
-                }
-            }
-
-            fn init(&mut self) {
-                if self.kernel.is_none() {
-                    PriorityLock::wait(self.priority);
-                    info!("GPU is available for {}!", $name);
-                    self.kernel = $func::<E>(self.log_d, self.priority);
-                }
-            }
-
-            fn free(&mut self) {
-                if let Some(_kernel) = self.kernel.take() {
-                    warn!(
-                        "GPU acquired by a high priority process! Freeing up {} kernels...",
-                        $name
-                    );
+                    kernels: kernels.clone(),
                 }
             }
 
@@ -120,23 +170,38 @@ macro_rules! locked_kernel {
                     return Err(GPUError::GPUDisabled);
                 }
 
-                self.init();
-
+                let mut kernels = self.kernels.0.lock().unwrap();
                 loop {
-                    if let Some(ref mut k) = self.kernel {
-                        match f(k) {
-                            Err(GPUError::GPUTaken) => {
-                                self.free();
-                                self.init();
-                            }
+                    if kernels.$field.is_none() {
+                        kernels.lock(self.priority)?;
+                        info!("GPU is available for {}!", $name);
+                        let lock = kernels.lock.as_ref().expect("the lock was just taken");
+                        match $func::<E>(self.log_d, self.priority, lock) {
+                            Ok(kernel) => kernels.$field = Some(kernel),
                             Err(e) => {
-                                warn!("GPU {} failed! Falling back to CPU... Error: {}", $name, e);
+                                kernels.unlock_if_unused();
                                 return Err(e);
                             }
-                            Ok(v) => return Ok(v),
                         }
-                    } else {
-                        return Err(GPUError::KernelUninitialized);
+                    }
+
+                    let k = kernels
+                        .$field
+                        .as_mut()
+                        .expect("the kernel was just created");
+                    match f(k) {
+                        Err(GPUError::GPUTaken) => {
+                            warn!(
+                                "GPU acquired by a high priority process! Freeing up {} kernels...",
+                                $name
+                            );
+                            kernels.free();
+                        }
+                        Err(e) => {
+                            warn!("GPU {} failed! Falling back to CPU... Error: {}", $name, e);
+                            return Err(e);
+                        }
+                        Ok(v) => return Ok(v),
                     }
                 }
             }
@@ -144,10 +209,11 @@ macro_rules! locked_kernel {
     };
 }
 
-locked_kernel!(LockedFFTKernel, FFTKernel, create_fft_kernel, "FFT");
+locked_kernel!(LockedFFTKernel, FFTKernel, fft, create_fft_kernel, "FFT");
 locked_kernel!(
     LockedMultiexpKernel,
     MultiexpKernel,
+    multiexp,
     create_multiexp_kernel,
     "Multiexp"
 );
@@ -2,6 +2,12 @@ mod error;
 
 pub use self::error::*;
 
+#[cfg(any(feature = "gpu", test))]
+mod verify;
+
+#[cfg(any(feature = "gpu", test))]
+pub(crate) use self::verify::*;
+
 #[cfg(feature = "gpu")]
 mod locks;
 
@@ -4,7 +4,7 @@ use super::sources;
 use super::utils;
 use crate::bls::Engine;
 use crate::multicore::Worker;
-use crate::multiexp::{multiexp as cpu_multiexp, FullDensity};
+use crate::multiexp::{multiexp_slice, MultiexpConfig};
 use ff::{PrimeField, ScalarEngine};
 use groupy::{CurveAffine, CurveProjective};
 use log::{error, info};
@@ -42,6 +42,8 @@ where
     n: usize,
 
     priority: bool,
+    // Identifies the device in the process, to skip it once it returned a wrong result.
+    device: String,
     _phantom: std::marker::PhantomData<E::Fr>,
 }
 
@@ -80,16 +82,24 @@ fn calc_best_chunk_size(max_window_size: usize, core_count: usize, exp_bits: usi
         .ceil() as usize
 }
 
-fn calc_chunk_size<E>(mem: u64, core_count: usize) -> usize
+fn calc_chunk_size<E>(mem: u64, core_count: usize) -> GPUResult<usize>
 where
     E: Engine,
 {
     let aff_size = std::mem::size_of::<E::G1Affine>() + std::mem::size_of::<E::G2Affine>();
     let exp_size = exp_size::<E>();
     let proj_size = std::mem::size_of::<E::G1>() + std::mem::size_of::<E::G2>();
-    ((((mem as f64) * (1f64 - MEMORY_PADDING)) as usize)
-        - (2 * core_count * ((1 << MAX_WINDOW_SIZE) + 1) * proj_size))
-        / (aff_size + exp_size)
+    // The buckets and results of all threads, which leave no memory for the bases and
+    // exponents on a device too small for them.
+    let bucket_mem = 2 * core_count * ((1 << MAX_WINDOW_SIZE) + 1) * proj_size;
+    let usable_mem = ((mem as f64) * (1f64 - MEMORY_PADDING)) as usize;
+    if usable_mem <= bucket_mem {
+        return Err(GPUError::OutOfMemory {
+            requested: bucket_mem,
+        });
+    }
+
+    Ok((usable_mem - bucket_mem) / (aff_size + exp_size))
 }
 
 fn exp_size<E: Engine>() -> usize {
@@ -100,21 +110,28 @@ impl<E> SingleMultiexpKernel<E>
 where
     E: Engine,
 {
-    pub fn create(d: opencl::Device, priority: bool) -> GPUResult<SingleMultiexpKernel<E>> {
+    /// A kernel on `d`, identified by `device` in the process.
+    pub fn create(
+        d: opencl::Device,
+        device: String,
+        priority: bool,
+    ) -> GPUResult<SingleMultiexpKernel<E>> {
         let src = sources::kernel::<E>(d.brand() == opencl::Brand::Nvidia);
 
         let exp_bits = exp_size::<E>() * 8;
         let core_count = utils::get_core_count(&d);
         let mem = d.memory();
-        let max_n = calc_chunk_size::<E>(mem, core_count);
+        let max_n = calc_chunk_size::<E>(mem, core_count)?;
         let best_n = calc_best_chunk_size(MAX_WINDOW_SIZE, core_count, exp_bits);
         let n = std::cmp::min(max_n, best_n);
 
         Ok(SingleMultiexpKernel {
-            program: opencl::Program::from_opencl(d, &src)?,
+            program: opencl::Program::from_opencl(d, &src)
+                .map_err(|e| GPUError::KernelCompilationFailed(e.to_string()))?,
             core_count,
             n,
             priority,
+            device,
             _phantom: std::marker::PhantomData,
         })
     }
@@ -128,7 +145,7 @@ where
     where
         G: CurveAffine,
     {
-        if locks::PriorityLock::should_break(self.priority) {
+        if locks::PriorityLock::should_break(self.priority)? {
             return Err(GPUError::GPUTaken);
         }
 
@@ -142,19 +159,34 @@ where
         // be `num_groups` * `num_windows` threads in total.
         // Each thread will use `num_groups` * `num_windows` * `bucket_len` buckets.
 
-        let mut base_buffer = self.program.create_buffer::<G>(n)?;
-        base_buffer.write_from(0, bases)?;
+        let mut base_buffer = self
+            .program
+            .create_buffer::<G>(n)
+            .map_err(|_| GPUError::out_of_memory::<G>(n))?;
+        base_buffer
+            .write_from(0, bases)
+            .map_err(|_| GPUError::TransferFailed)?;
         let mut exp_buffer = self
             .program
-            .create_buffer::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)?;
-        exp_buffer.write_from(0, exps)?;
-
+            .create_buffer::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)
+            .map_err(|_| {
+                GPUError::out_of_memory::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)
+            })?;
+        exp_buffer
+            .write_from(0, exps)
+            .map_err(|_| GPUError::TransferFailed)?;
+
+        let num_buckets = 2 * self.core_count * bucket_len;
         let bucket_buffer = self
             .program
-            .create_buffer::<<G as CurveAffine>::Projective>(2 * self.core_count * bucket_len)?;
+            .create_buffer::<<G as CurveAffine>::Projective>(num_buckets)
+            .map_err(|_| GPUError::out_of_memory::<<G as CurveAffine>::Projective>(num_buckets))?;
         let result_buffer = self
             .program
-            .create_buffer::<<G as CurveAffine>::Projective>(2 * self.core_count)?;
+            .create_buffer::<<G as CurveAffine>::Projective>(2 * self.core_count)
+            .map_err(|_| {
+                GPUError::out_of_memory::<<G as CurveAffine>::Projective>(2 * self.core_count)
+            })?;
 
         // Make global work size divisible by `LOCAL_WORK_SIZE`
         let mut global_work_size = num_windows * num_groups;
@@ -183,10 +215,13 @@ where
             num_groups as u32,
             num_windows as u32,
             window_size as u32
-        )?;
+        )
+        .map_err(|e| GPUError::KernelExecutionFailed(e.to_string()))?;
 
         let mut results = vec![<G as CurveAffine>::Projective::zero(); num_groups * num_windows];
-        result_buffer.read_into(0, &mut results)?;
+        result_buffer
+            .read_into(0, &mut results)
+            .map_err(|_| GPUError::TransferFailed)?;
 
         // Using the algorithm below, we can calculate the final result by accumulating the results
         // of those `NUM_GROUPS` * `NUM_WINDOWS` threads.
@@ -213,7 +248,8 @@ where
     E: Engine,
 {
     kernels: Vec<SingleMultiexpKernel<E>>,
-    _lock: locks::GPULock, // RFC 1857: struct fields are dropped in the same order as they are declared.
+    /// The lock of a kernel from [`MultiexpKernel::create`], which holds it itself.
+    _lock: Option<locks::GPULock>, // RFC 1857: struct fields are dropped in the same order as they are declared.
 }
 
 impl<E> MultiexpKernel<E>
@@ -221,27 +257,53 @@ where
     E: Engine,
 {
     pub fn create(priority: bool) -> GPUResult<MultiexpKernel<E>> {
-        let lock = locks::GPULock::lock();
+        let lock = locks::GPULock::lock()?;
+        let mut kernel = Self::create_with_lock(priority, &lock)?;
+        kernel._lock = Some(lock);
+
+        Ok(kernel)
+    }
 
+    /// Creates a kernel while the caller holds `lock`, which must outlive the kernel.
+    pub fn create_with_lock(
+        priority: bool,
+        _lock: &locks::GPULock,
+    ) -> GPUResult<MultiexpKernel<E>> {
         let devices = opencl::Device::all()?;
+        if devices.is_empty() {
+            return Err(GPUError::DeviceNotFound);
+        }
 
+        // The error of the first device is returned if none of them work.
+        let mut first_error = None;
+        // Devices which returned a wrong result to an earlier kernel are left out.
         let kernels: Vec<_> = devices
             .into_iter()
-            .map(|d| (d.clone(), SingleMultiexpKernel::<E>::create(d, priority)))
-            .filter_map(|(device, res)| {
-                if let Err(ref e) = res {
+            .enumerate()
+            .map(|(i, d)| (format!("{} #{}", d.name(), i), d))
+            .filter(|(id, _)| !super::is_faulty(id))
+            .map(|(id, d)| {
+                (
+                    d.clone(),
+                    SingleMultiexpKernel::<E>::create(d, id, priority),
+                )
+            })
+            .filter_map(|(device, res)| match res {
+                Ok(kernel) => Some(kernel),
+                Err(e) => {
                     error!(
                         "Cannot initialize kernel for device '{}'! Error: {}",
                         device.name(),
                         e
                     );
+                    first_error.get_or_insert(e);
+                    None
                 }
-                res.ok()
             })
             .collect();
 
         if kernels.is_empty() {
-            return Err(GPUError::Simple("No working GPUs found!"));
+            return Err(first_error.unwrap_or(GPUError::DeviceNotFound));
         }
         info!(
             "Multiexp: {} working device(s) selected. (CPU utilization: {})",
@@ -258,7 +320,7 @@ where
         }
         Ok(MultiexpKernel::<E> {
             kernels,
-            _lock: lock,
+            _lock: None,
         })
     }
 
@@ -269,61 +331,74 @@ where
         exps: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
         skip: usize,
         n: usize,
+        config: &MultiexpConfig,
     ) -> GPUResult<<G as CurveAffine>::Projective>
     where
         G: CurveAffine,
         <G as groupy::CurveAffine>::Engine: crate::bls::Engine,
     {
-        let num_devices = self.kernels.len();
+        let mut kernels = self
+            .kernels
+            .iter_mut()
+            .filter(|k| !super::is_faulty(&k.device))
+            .collect::<Vec<_>>();
+        if kernels.is_empty() {
+            return Err(GPUError::Simple("All GPUs returned wrong results!"));
+        }
+
+        let num_devices = kernels.len();
         // Bases are skipped by `self.1` elements, when converted from (Arc<Vec<G>>, usize) to Source
         // https://github.com/zkcrypto/bellman/blob/10c5010fd9c2ca69442dc9775ea271e286e776d8/src/multiexp.rs#L38
         let bases = &bases[skip..(skip + n)];
         let exps = &exps[..n];
 
-        let cpu_n = ((n as f64) * get_cpu_utilization()) as usize;
-        let n = n - cpu_n;
-        let (cpu_bases, bases) = bases.split_at(cpu_n);
-        let (cpu_exps, exps) = exps.split_at(cpu_n);
+        let (gpu_range, cpu_range) = config.gpu_split(n);
+        let (cpu_bases, cpu_exps) = (&bases[cpu_range.clone()], &exps[cpu_range]);
+        let n = gpu_range.len();
+        let (bases, exps) = (&bases[gpu_range.clone()], &exps[gpu_range]);
 
         let chunk_size = ((n as f64) / (num_devices as f64)).ceil() as usize;
 
-        crate::multicore::THREAD_POOL.install(|| {
+        pool.install(|| {
             use rayon::prelude::*;
 
             let mut acc = <G as CurveAffine>::Projective::zero();
 
-            let results = if n > 0 {
-                bases
-                    .par_chunks(chunk_size)
-                    .zip(exps.par_chunks(chunk_size))
-                    .zip(self.kernels.par_iter_mut())
-                    .map(|((bases, exps), kern)| -> Result<<G as CurveAffine>::Projective, GPUError> {
-                        let mut acc = <G as CurveAffine>::Projective::zero();
-                        for (bases, exps) in bases.chunks(kern.n).zip(exps.chunks(kern.n)) {
-                            let result = kern.multiexp(bases, exps, bases.len())?;
-                            acc.add_assign(&result);
-                        }
-
-                        Ok(acc)
-                    })
-                    .collect::<Vec<_>>()
-            } else {
-                Vec::new()
-            };
-
-            let cpu_acc = cpu_multiexp(
-                &pool,
-                (Arc::new(cpu_bases.to_vec()), 0),
-                FullDensity,
-                Arc::new(cpu_exps.to_vec()),
-                &mut None,
+            // The CPU part runs on the pool while the GPUs work on theirs.
+            let (results, cpu_acc) = rayon::join(
+                || {
+                    if n > 0 {
+                        bases
+                            .par_chunks(chunk_size)
+                            .zip(exps.par_chunks(chunk_size))
+                            .zip(kernels.par_iter_mut())
+                            .map(|((bases, exps), kern)| -> Result<<G as CurveAffine>::Projective, GPUError> {
+                                let n = kern.n;
+                                let device = kern.device.clone();
+                                super::verified_multiexp(
+                                    bases,
+                                    exps,
+                                    n,
+                                    config,
+                                    &device,
+                                    |bases, exps| kern.multiexp(bases, exps, bases.len()),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    } else {
+                        Vec::new()
+                    }
+                },
+                || multiexp_slice(cpu_bases, cpu_exps),
             );
 
             for r in results {
                 acc.add_assign(&r?);
             }
 
-            acc.add_assign(&cpu_acc.wait().unwrap());
+            let cpu_acc =
+                cpu_acc.map_err(|_| GPUError::Simple("The CPU part of the multiexp failed!"))?;
+            acc.add_assign(&cpu_acc);
             Ok(acc)
         })
     }
@@ -1,5 +1,6 @@
 use super::error::{GPUError, GPUResult};
 use crate::multicore::Worker;
+use crate::multiexp::MultiexpConfig;
 use ff::{PrimeField, ScalarEngine};
 use groupy::CurveAffine;
 use std::marker::PhantomData;
@@ -7,6 +8,8 @@ use std::sync::Arc;
 
 // This module is compiled instead of `fft.rs` and `multiexp.rs` if `gpu` feature is disabled.
 
+pub struct GPULock;
+
 pub struct FFTKernel<E>(PhantomData<E>)
 where
     E: ScalarEngine;
@@ -19,6 +22,10 @@ where
         return Err(GPUError::GPUDisabled);
     }
 
+    pub fn create_with_lock(_: bool, _: &GPULock) -> GPUResult<FFTKernel<E>> {
+        return Err(GPUError::GPUDisabled);
+    }
+
     pub fn radix_fft(&mut self, _: &mut [E::Fr], _: &E::Fr, _: u32) -> GPUResult<()> {
         return Err(GPUError::GPUDisabled);
     }
@@ -36,6 +43,10 @@ where
         return Err(GPUError::GPUDisabled);
     }
 
+    pub fn create_with_lock(_: bool, _: &GPULock) -> GPUResult<MultiexpKernel<E>> {
+        return Err(GPUError::GPUDisabled);
+    }
+
     pub fn multiexp<G>(
         &mut self,
         _: &Worker,
@@ -43,6 +54,7 @@ where
         _: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
         _: usize,
         _: usize,
+        _: &MultiexpConfig,
     ) -> GPUResult<<G as CurveAffine>::Projective>
     where
         G: CurveAffine,
@@ -53,6 +65,32 @@ where
 
 use crate::bls::Engine;
 
+pub struct GPUKernels<E>(PhantomData<E>);
+
+impl<E> Clone for GPUKernels<E> {
+    fn clone(&self) -> Self {
+        GPUKernels(PhantomData)
+    }
+}
+
+impl<E> Default for GPUKernels<E>
+where
+    E: Engine,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> GPUKernels<E>
+where
+    E: Engine,
+{
+    pub fn new() -> Self {
+        GPUKernels(PhantomData)
+    }
+}
+
 macro_rules! locked_kernel {
     ($class:ident) => {
         pub struct $class<E>(PhantomData<E>);
@@ -65,6 +103,10 @@ macro_rules! locked_kernel {
                 $class::<E>(PhantomData)
             }
 
+            pub fn with_kernels(_: usize, _: bool, _: &GPUKernels<E>) -> $class<E> {
+                $class::<E>(PhantomData)
+            }
+
             pub fn with<F, R, K>(&mut self, _: F) -> GPUResult<R>
             where
                 F: FnMut(&mut K) -> GPUResult<R>,
@@ -1,4 +1,4 @@
-use log::{info, warn};
+use log::{error, info, warn};
 use rust_gpu_tools::*;
 use std::collections::HashMap;
 use std::env;
@@ -42,11 +42,18 @@ lazy_static::lazy_static! {
         match env::var("BELLMAN_CUSTOM_GPU").and_then(|var| {
             for card in var.split(",") {
                 let splitted = card.split(":").collect::<Vec<_>>();
-                if splitted.len() != 2 { panic!("Invalid BELLMAN_CUSTOM_GPU!"); }
-                let name = splitted[0].trim().to_string();
-                let cores : usize = splitted[1].trim().parse().expect("Invalid BELLMAN_CUSTOM_GPU!");
-                info!("Adding \"{}\" to GPU list with {} CUDA cores.", name, cores);
-                core_counts.insert(name, cores);
+                let cores = match splitted[..] {
+                    [_, cores] => cores.trim().parse::<usize>().ok(),
+                    _ => None,
+                };
+                match cores {
+                    Some(cores) => {
+                        let name = splitted[0].trim().to_string();
+                        info!("Adding \"{}\" to GPU list with {} CUDA cores.", name, cores);
+                        core_counts.insert(name, cores);
+                    }
+                    None => error!("Invalid BELLMAN_CUSTOM_GPU entry \"{}\"! Ignoring it...", card),
+                }
             }
             Ok(())
         }) { Err(_) => { }, Ok(_) => { } }
@@ -74,8 +81,13 @@ pub fn get_core_count(d: &opencl::Device) -> usize {
 }
 
 pub fn dump_device_list() {
-    for d in opencl::Device::all().unwrap() {
-        info!("Device: {:?}", d);
+    match opencl::Device::all() {
+        Ok(devices) => {
+            for d in devices {
+                info!("Device: {:?}", d);
+            }
+        }
+        Err(e) => error!("Cannot list devices! Error: {}", e),
     }
 }
 
@@ -0,0 +1,221 @@
+use super::error::{GPUError, GPUResult};
+use crate::multiexp::{multiexp_slice, MultiexpConfig};
+use ff::{PrimeField, ScalarEngine};
+use groupy::{CurveAffine, CurveProjective};
+use rand::Rng;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+// Checks of GPU results against the CPU, kept apart from the OpenCL code so they can be
+// tested without a GPU.
+
+lazy_static::lazy_static! {
+    /// The devices which returned a wrong result. No kernel of the process uses them
+    /// afterwards, including kernels created later.
+    static ref FAULTY_DEVICES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Whether `device` returned a wrong result to [`verified_multiexp`] before.
+pub(crate) fn is_faulty(device: &str) -> bool {
+    FAULTY_DEVICES.lock().unwrap().contains(device)
+}
+
+/// Adds up the multiexps `kernel` computes for consecutive chunks of at most
+/// `chunk_size` elements. A random sample of the chunks, as set by
+/// `config.verify_gpu_sample_rate`, is recomputed on the CPU and compared.
+///
+/// Fails with `GPUError::ResultMismatch` on the first chunk whose results differ, and
+/// marks `device`, which must identify the device in the process, as faulty.
+pub(crate) fn verified_multiexp<G, F>(
+    bases: &[G],
+    exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    chunk_size: usize,
+    config: &MultiexpConfig,
+    device: &str,
+    mut kernel: F,
+) -> GPUResult<<G as CurveAffine>::Projective>
+where
+    G: CurveAffine,
+    F: FnMut(
+        &[G],
+        &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    ) -> GPUResult<<G as CurveAffine>::Projective>,
+{
+    let mut rng = rand::thread_rng();
+    let mut acc = <G as CurveAffine>::Projective::zero();
+
+    for (chunk, (bases, exps)) in bases
+        .chunks(chunk_size)
+        .zip(exps.chunks(chunk_size))
+        .enumerate()
+    {
+        let result = kernel(bases, exps)?;
+
+        let rate = config.verify_gpu_sample_rate;
+        if rate > 0f32 && rng.gen::<f32>() < rate {
+            let expected = multiexp_slice(bases, exps)
+                .map_err(|_| GPUError::Simple("Cannot recompute GPU result on the CPU!"))?;
+            if result != expected {
+                FAULTY_DEVICES.lock().unwrap().insert(device.to_string());
+                return Err(GPUError::ResultMismatch {
+                    chunk,
+                    device: device.to_string(),
+                });
+            }
+        }
+
+        acc.add_assign(&result);
+    }
+
+    Ok(acc)
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, G1Affine};
+    use ff::Field;
+    use rand_core::SeedableRng;
+
+    type Repr = <<Bls12 as ScalarEngine>::Fr as PrimeField>::Repr;
+
+    fn setup(n: usize) -> (Vec<G1Affine>, Vec<Repr>) {
+        let rng = &mut rand_xorshift::XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let bases = (0..n)
+            .map(|_| <G1Affine as CurveAffine>::Projective::random(rng).into_affine())
+            .collect();
+        let exps = (0..n)
+            .map(|_| <Bls12 as ScalarEngine>::Fr::random(rng).into_repr())
+            .collect();
+
+        (bases, exps)
+    }
+
+    /// A kernel computing correct results, except for chunk `faulty`.
+    fn mock_kernel(
+        faulty: usize,
+    ) -> impl FnMut(&[G1Affine], &[Repr]) -> GPUResult<<G1Affine as CurveAffine>::Projective> {
+        let mut chunk = 0;
+        move |bases, exps| {
+            let mut result = multiexp_slice(bases, exps).unwrap();
+            if chunk == faulty {
+                result.add_assign_mixed(&G1Affine::one());
+            }
+            chunk += 1;
+            Ok(result)
+        }
+    }
+
+    #[test]
+    fn test_verified_multiexp() {
+        let (bases, exps) = setup(100);
+        let expected = multiexp_slice(&bases, &exps).unwrap();
+
+        let config = MultiexpConfig {
+            verify_gpu_sample_rate: 1f32,
+            ..MultiexpConfig::default()
+        };
+        let result =
+            verified_multiexp(&bases, &exps, 16, &config, "mock", mock_kernel(usize::MAX)).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_verified_multiexp_mismatch() {
+        let (bases, exps) = setup(100);
+
+        let config = MultiexpConfig {
+            verify_gpu_sample_rate: 1f32,
+            ..MultiexpConfig::default()
+        };
+        assert!(!is_faulty("wrong mock"));
+        match verified_multiexp(&bases, &exps, 16, &config, "wrong mock", mock_kernel(2)) {
+            Err(GPUError::ResultMismatch { chunk, device }) => {
+                assert_eq!(chunk, 2);
+                assert_eq!(device, "wrong mock");
+            }
+            other => panic!("expected a result mismatch, got {:?}", other),
+        }
+        // The device stays faulty for the whole process, other devices are not.
+        assert!(is_faulty("wrong mock"));
+        assert!(!is_faulty("mock"));
+
+        // Without sampling the wrong result is passed through, like before.
+        let config = MultiexpConfig::default();
+        let result = verified_multiexp(&bases, &exps, 16, &config, "mock", mock_kernel(2)).unwrap();
+        assert_ne!(result, multiexp_slice(&bases, &exps).unwrap());
+    }
+
+    /// A kernel failing with `error()` on chunk `failing`, and counting its calls.
+    fn failing_kernel<'a>(
+        failing: usize,
+        error: fn() -> GPUError,
+        calls: &'a mut usize,
+    ) -> impl FnMut(&[G1Affine], &[Repr]) -> GPUResult<<G1Affine as CurveAffine>::Projective> + 'a
+    {
+        move |bases, exps| {
+            *calls += 1;
+            if *calls - 1 == failing {
+                return Err(error());
+            }
+            Ok(multiexp_slice(bases, exps).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_verified_multiexp_error() {
+        use crate::SynthesisError;
+
+        let (bases, exps) = setup(100);
+        let config = MultiexpConfig {
+            verify_gpu_sample_rate: 1f32,
+            ..MultiexpConfig::default()
+        };
+
+        let errors: [fn() -> GPUError; 5] = [
+            || GPUError::DeviceNotFound,
+            || GPUError::OutOfMemory { requested: 1 << 30 },
+            || GPUError::KernelCompilationFailed("mock".to_string()),
+            || GPUError::KernelExecutionFailed("mock".to_string()),
+            || GPUError::TransferFailed,
+        ];
+        for &error in errors.iter() {
+            let mut calls = 0;
+            let result = verified_multiexp(
+                &bases,
+                &exps,
+                16,
+                &config,
+                "mock",
+                failing_kernel(3, error, &mut calls),
+            );
+
+            // The error is returned as it is, and no chunks are computed after it.
+            let err = result.unwrap_err();
+            assert_eq!(err.to_string(), error().to_string());
+            assert_eq!(calls, 4);
+
+            // At the boundary it becomes a `SynthesisError`, still with its message.
+            let err = SynthesisError::from(err);
+            assert!(matches!(err, SynthesisError::GPUError(_)));
+            assert!(err.to_string().contains(&error().to_string()));
+        }
+
+        match verified_multiexp(
+            &bases,
+            &exps,
+            16,
+            &config,
+            "mock",
+            failing_kernel(0, || GPUError::OutOfMemory { requested: 42 }, &mut 0),
+        ) {
+            Err(GPUError::OutOfMemory { requested }) => assert_eq!(requested, 42),
+            other => panic!("expected running out of memory, got {:?}", other),
+        }
+    }
+}
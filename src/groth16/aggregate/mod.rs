@@ -0,0 +1,370 @@
+//! Aggregation of Groth16 proofs following [SnarkPack].
+//!
+//! [`aggregate_proofs`] turns `n` proofs of the same circuit into one proof of
+//! `O(log n)` elements, which [`verify_aggregated`] checks with `O(log n)` pairings
+//! plus a multiexp over the public inputs.
+//!
+//! The prover commits to the `A`, `B` and `C` of all proofs in the target group, with
+//! keys from an [`AggSRS`], and combines the Groth16 equations of the proofs with the
+//! powers of a random `r`. Two inner pairing product arguments show that the combined
+//! values were computed from the committed proofs, halving the vectors and the keys in
+//! every round as in an inner product argument, and KZG openings show that the final
+//! keys were folded correctly.
+//!
+//! The challenges are derived with a [`FiatShamirRng`] given by the caller. The
+//! aggregated proof does not bind the public inputs by itself: the caller must absorb
+//! them, and anything else identifying the statement, before aggregating, and absorb
+//! the same before verifying.
+//!
+//! [SnarkPack]: https://eprint.iacr.org/2021/529
+
+mod prove;
+mod srs;
+mod verify;
+
+pub use self::prove::*;
+pub use self::srs::*;
+pub use self::verify::*;
+
+use digest::Digest;
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use rayon::prelude::*;
+
+use super::VerifyingKey;
+use crate::bls::{Engine, PairingCurveAffine};
+use crate::multicore::{install_in, THREAD_POOL};
+use crate::transcript::FiatShamirRng;
+use crate::SynthesisError;
+
+/// Domain separator absorbed before the messages of the aggregation.
+const AGGREGATE_DOMAIN: &[u8] = b"bellperson-snarkpack";
+
+/// An aggregation of Groth16 proofs, created by [`aggregate_proofs`].
+///
+/// Elements of the target group and pairs of them come first for the key with the
+/// secret `a` of the [`AggSRS`], then for the key with the secret `b`.
+#[derive(Clone, Debug)]
+pub struct AggregatedProof<E: Engine> {
+    /// Commitments to the `A` and `B` of the proofs.
+    pub com_ab: (E::Fqk, E::Fqk),
+    /// Commitments to the `C` of the proofs.
+    pub com_c: (E::Fqk, E::Fqk),
+    /// `prod e(r^i A_i, B_i)`
+    pub z_ab: E::Fqk,
+    /// `sum r^i C_i`
+    pub z_c: E::G1Affine,
+    /// The cross terms of the folding rounds.
+    pub rounds: Vec<GipaRound<E>>,
+    /// `A`, `B` and `C` folded to a single element.
+    pub final_a: E::G1Affine,
+    pub final_b: E::G2Affine,
+    pub final_c: E::G1Affine,
+    /// The commitment keys folded to a single element.
+    pub final_v: (E::G2Affine, E::G2Affine),
+    pub final_w: (E::G1Affine, E::G1Affine),
+    /// KZG openings of the folded keys at the last challenge.
+    pub opening_v: (E::G2Affine, E::G2Affine),
+    pub opening_w: (E::G1Affine, E::G1Affine),
+}
+
+/// The cross terms of one round of the inner pairing product arguments, where the left
+/// terms pair the right half of the proofs with the left half of the keys and vice
+/// versa.
+#[derive(Clone, Debug)]
+pub struct GipaRound<E: Engine> {
+    pub com_ab_l: (E::Fqk, E::Fqk),
+    pub com_ab_r: (E::Fqk, E::Fqk),
+    pub z_ab_l: E::Fqk,
+    pub z_ab_r: E::Fqk,
+    pub com_c_l: (E::Fqk, E::Fqk),
+    pub com_c_r: (E::Fqk, E::Fqk),
+    pub z_c_l: E::G1Affine,
+    pub z_c_r: E::G1Affine,
+}
+
+impl<E: Engine> GipaRound<E> {
+    fn absorb<H: Digest + Clone>(&self, transcript: &mut FiatShamirRng<H>) {
+        for gt in &[
+            self.com_ab_l.0,
+            self.com_ab_l.1,
+            self.com_ab_r.0,
+            self.com_ab_r.1,
+            self.z_ab_l,
+            self.z_ab_r,
+            self.com_c_l.0,
+            self.com_c_l.1,
+            self.com_c_r.0,
+            self.com_c_r.1,
+        ] {
+            transcript.absorb_gt(gt);
+        }
+        transcript.absorb_g1(&self.z_c_l);
+        transcript.absorb_g1(&self.z_c_r);
+    }
+}
+
+fn check_proof_count<E: Engine>(count: usize, srs: &AggSRS<E>) -> Result<(), SynthesisError> {
+    if count < 2 || !count.is_power_of_two() || count > srs.size() {
+        return Err(SynthesisError::InvalidProofCount(count));
+    }
+
+    Ok(())
+}
+
+/// Absorbs the verifying key, the number of proofs and the commitments to the proofs
+/// and derives `r` and its inverse.
+fn commitment_challenge<E: Engine, H: Digest + Clone>(
+    transcript: &mut FiatShamirRng<H>,
+    vk: &VerifyingKey<E>,
+    count: usize,
+    com_ab: &(E::Fqk, E::Fqk),
+    com_c: &(E::Fqk, E::Fqk),
+) -> (E::Fr, E::Fr) {
+    transcript.absorb_bytes(AGGREGATE_DOMAIN);
+    for g1 in [vk.alpha_g1, vk.beta_g1, vk.delta_g1].iter().chain(&vk.ic) {
+        transcript.absorb_g1(g1);
+    }
+    for g2 in &[vk.beta_g2, vk.gamma_g2, vk.delta_g2] {
+        transcript.absorb_g2(g2);
+    }
+    transcript.absorb_bytes(&(count as u64).to_le_bytes());
+    for gt in &[com_ab.0, com_ab.1, com_c.0, com_c.1] {
+        transcript.absorb_gt(gt);
+    }
+
+    challenge::<E, H>(transcript)
+}
+
+/// Absorbs the folded proofs and keys and derives the point the keys are opened at.
+fn opening_challenge<E: Engine, H: Digest + Clone>(
+    transcript: &mut FiatShamirRng<H>,
+    final_a: &E::G1Affine,
+    final_b: &E::G2Affine,
+    final_c: &E::G1Affine,
+    final_v: &(E::G2Affine, E::G2Affine),
+    final_w: &(E::G1Affine, E::G1Affine),
+) -> E::Fr {
+    transcript.absorb_g1(final_a);
+    transcript.absorb_g2(final_b);
+    transcript.absorb_g1(final_c);
+    transcript.absorb_g2(&final_v.0);
+    transcript.absorb_g2(&final_v.1);
+    transcript.absorb_g1(&final_w.0);
+    transcript.absorb_g1(&final_w.1);
+
+    E::Fr::random(transcript)
+}
+
+/// A nonzero challenge and its inverse.
+fn challenge<E: Engine, H: Digest + Clone>(transcript: &mut FiatShamirRng<H>) -> (E::Fr, E::Fr) {
+    loop {
+        let x = E::Fr::random(&mut *transcript);
+        if let Some(xinv) = x.inverse() {
+            return (x, xinv);
+        }
+    }
+}
+
+/// The powers `1, x, x^2, ...` of `x`.
+fn powers<F: Field>(x: &F, n: usize) -> Vec<F> {
+    let mut powers = Vec::with_capacity(n);
+    let mut power = F::one();
+    for _ in 0..n {
+        powers.push(power);
+        power.mul_assign(x);
+    }
+    powers
+}
+
+/// The factors of [`fold_coefficients`] for the keys of A: `x_j^-1 r^-(m / 2^(j + 1))`.
+fn v_factors<E: Engine>(x_invs: &[E::Fr], r_inv: &E::Fr, m: usize) -> Vec<E::Fr> {
+    let mut half = m;
+    x_invs
+        .iter()
+        .map(|x_inv| {
+            half /= 2;
+            let mut factor = r_inv.pow(&[half as u64]);
+            factor.mul_assign(x_inv);
+            factor
+        })
+        .collect()
+}
+
+/// The coefficients of `prod_j (1 + factors[j] X^(2^(k - 1 - j)))` for `k` factors,
+/// the polynomial whose coefficients the commitment keys are folded with.
+fn fold_coefficients<F: Field>(factors: &[F]) -> Vec<F> {
+    // Starting from the factor of the smallest power, every factor doubles the
+    // coefficients, the new ones shifted by the previous degree plus one.
+    let mut coeffs = vec![F::one()];
+    for factor in factors.iter().rev() {
+        let shifted = coeffs
+            .iter()
+            .map(|c| {
+                let mut c = *c;
+                c.mul_assign(factor);
+                c
+            })
+            .collect::<Vec<_>>();
+        coeffs.extend(shifted);
+    }
+    coeffs
+}
+
+/// Evaluates the polynomial of [`fold_coefficients`] at `z`.
+fn fold_evaluation<F: Field>(factors: &[F], z: &F) -> F {
+    let mut acc = F::one();
+    let mut power = *z;
+    for factor in factors.iter().rev() {
+        let mut term = *factor;
+        term.mul_assign(&power);
+        term.add_assign(&F::one());
+        acc.mul_assign(&term);
+        power.square();
+    }
+    acc
+}
+
+/// The quotient `(f(X) - f(z)) / (X - z)` of the polynomial with the coefficients
+/// `coeffs`, by synthetic division.
+fn kzg_quotient<F: Field>(coeffs: &[F], z: &F) -> Vec<F> {
+    let mut quotient = vec![F::zero(); coeffs.len() - 1];
+    let mut acc = F::zero();
+    for (q, c) in quotient.iter_mut().zip(&coeffs[1..]).rev() {
+        acc.mul_assign(z);
+        acc.add_assign(c);
+        *q = acc;
+    }
+    quotient
+}
+
+/// `prod e(a_i, b_i)` over all pairs of slices.
+fn pairing<E: Engine>(pairs: &[(&[E::G1Affine], &[E::G2Affine])]) -> E::Fqk {
+    let mut ml = E::Fqk::one();
+    for (a, b) in pairs {
+        ml.mul_assign(&miller_loop::<E>(a, b));
+    }
+
+    E::final_exponentiation(&ml).expect("a Miller loop is never zero")
+}
+
+fn miller_loop<E: Engine>(a: &[E::G1Affine], b: &[E::G2Affine]) -> E::Fqk {
+    install_in(&THREAD_POOL, || {
+        let chunk_size = std::cmp::max(1, a.len() / rayon::current_num_threads());
+        a.par_chunks(chunk_size)
+            .zip(b.par_chunks(chunk_size))
+            .map(|(a, b)| {
+                let prepared = a
+                    .iter()
+                    .zip(b)
+                    .map(|(a, b)| (a.prepare(), b.prepare()))
+                    .collect::<Vec<_>>();
+                let refs = prepared.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+                E::miller_loop(&refs)
+            })
+            .reduce(E::Fqk::one, |mut acc, ml| {
+                acc.mul_assign(&ml);
+                acc
+            })
+    })
+}
+
+/// `sum scalars_i * bases_i`
+fn msm<G: CurveAffine>(bases: &[G], scalars: &[G::Scalar]) -> G::Projective {
+    install_in(&THREAD_POOL, || {
+        bases
+            .par_iter()
+            .zip(scalars.par_iter())
+            .map(|(base, scalar)| base.mul(scalar.into_repr()))
+            .reduce(G::Projective::zero, |mut acc, point| {
+                acc.add_assign(&point);
+                acc
+            })
+    })
+}
+
+/// `points_i * scalars_i`, elementwise.
+fn scale<G: CurveAffine>(points: &[G], scalars: &[G::Scalar]) -> Vec<G> {
+    let mut scaled = install_in(&THREAD_POOL, || {
+        points
+            .par_iter()
+            .zip(scalars.par_iter())
+            .map(|(point, scalar)| point.mul(scalar.into_repr()))
+            .collect::<Vec<_>>()
+    });
+    G::Projective::batch_normalization(&mut scaled);
+    scaled.into_iter().map(|p| p.into_affine()).collect()
+}
+
+/// `lo + x * hi`, elementwise.
+fn fold<G: CurveAffine>(lo: &[G], hi: &[G], x: &G::Scalar) -> Vec<G> {
+    let mut folded = install_in(&THREAD_POOL, || {
+        lo.par_iter()
+            .zip(hi.par_iter())
+            .map(|(lo, hi)| {
+                let mut point = hi.mul(x.into_repr());
+                point.add_assign_mixed(lo);
+                point
+            })
+            .collect::<Vec<_>>()
+    });
+    G::Projective::batch_normalization(&mut folded);
+    folded.into_iter().map(|p| p.into_affine()).collect()
+}
+
+/// `com * l^x * r^xinv`, the folding of a commitment in the target group.
+fn fold_gt<E: Engine>(com: &E::Fqk, l: &E::Fqk, r: &E::Fqk, x: &E::Fr, xinv: &E::Fr) -> E::Fqk {
+    let mut folded = *com;
+    folded.mul_assign(&l.pow(x.into_repr()));
+    folded.mul_assign(&r.pow(xinv.into_repr()));
+    folded
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::Fr;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_fold_polynomials() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for k in 0..5 {
+            let factors = (0..k).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+            let coeffs = fold_coefficients(&factors);
+            assert_eq!(coeffs.len(), 1 << k);
+
+            let z = Fr::random(rng);
+            let mut eval = Fr::zero();
+            for c in coeffs.iter().rev() {
+                eval.mul_assign(&z);
+                eval.add_assign(c);
+            }
+            assert_eq!(eval, fold_evaluation(&factors, &z));
+
+            // f(X) - f(z) = q(X) (X - z), checked at a random point.
+            let quotient = kzg_quotient(&coeffs, &z);
+            let x = Fr::random(rng);
+            let (mut fx, mut qx) = (Fr::zero(), Fr::zero());
+            for c in coeffs.iter().rev() {
+                fx.mul_assign(&x);
+                fx.add_assign(c);
+            }
+            for c in quotient.iter().rev() {
+                qx.mul_assign(&x);
+                qx.add_assign(c);
+            }
+            let mut x_minus_z = x;
+            x_minus_z.sub_assign(&z);
+            qx.mul_assign(&x_minus_z);
+            fx.sub_assign(&eval);
+            assert_eq!(fx, qx);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use digest::Digest;
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::{
+    challenge, check_proof_count, commitment_challenge, fold, fold_coefficients, kzg_quotient, msm,
+    opening_challenge, pairing, powers, scale, v_factors, AggSRS, AggregatedProof, GipaRound,
+};
+use crate::bls::Engine;
+use crate::groth16::{Proof, VerifyingKey};
+use crate::transcript::FiatShamirRng;
+use crate::SynthesisError;
+
+/// Aggregates `proofs`, all for the circuit of `vk`, into a single proof.
+///
+/// The number of proofs must be a power of two of at least 2 and at most the size of
+/// `srs`; pad a batch with copies of one of its proofs otherwise. The public inputs of
+/// the proofs are not part of the aggregated proof, so they must have been absorbed in
+/// `transcript`, which derives the challenges, beforehand.
+pub fn aggregate_proofs<E, H>(
+    proofs: &[Proof<E>],
+    vk: &VerifyingKey<E>,
+    srs: &AggSRS<E>,
+    transcript: &mut FiatShamirRng<H>,
+) -> Result<AggregatedProof<E>, SynthesisError>
+where
+    E: Engine,
+    H: Digest + Clone,
+{
+    let m = proofs.len();
+    check_proof_count(m, srs)?;
+
+    let mut a = proofs.iter().map(|p| p.a).collect::<Vec<_>>();
+    let mut b = proofs.iter().map(|p| p.b).collect::<Vec<_>>();
+    let mut c = proofs.iter().map(|p| p.c).collect::<Vec<_>>();
+    let mut v1 = srs.h_alpha[..m].to_vec();
+    let mut v2 = srs.h_beta[..m].to_vec();
+    let mut w1 = srs.g_alpha[m..2 * m].to_vec();
+    let mut w2 = srs.g_beta[m..2 * m].to_vec();
+
+    let com_ab = (
+        pairing::<E>(&[(&a[..], &v1[..]), (&w1[..], &b[..])]),
+        pairing::<E>(&[(&a[..], &v2[..]), (&w2[..], &b[..])]),
+    );
+    let com_c = (
+        pairing::<E>(&[(&c[..], &v1[..])]),
+        pairing::<E>(&[(&c[..], &v2[..])]),
+    );
+    let (r, r_inv) = commitment_challenge::<E, H>(transcript, vk, m, &com_ab, &com_c);
+
+    // Scaling A and C by r^i and the keys of A and C by r^-i keeps the commitments.
+    let r_powers = powers(&r, m);
+    let r_inv_powers = powers(&r_inv, m);
+    a = scale(&a, &r_powers);
+    c = scale(&c, &r_powers);
+    v1 = scale(&v1, &r_inv_powers);
+    v2 = scale(&v2, &r_inv_powers);
+
+    let z_ab = pairing::<E>(&[(&a[..], &b[..])]);
+    let z_c = scaled_sum::<E>(&c, &E::Fr::one());
+    transcript.absorb_gt(&z_ab);
+    transcript.absorb_g1(&z_c);
+
+    // C is paired with a vector of ones, which stays uniform when folded.
+    let mut s = E::Fr::one();
+    let mut rounds = Vec::new();
+    let mut xs = Vec::new();
+    let mut x_invs = Vec::new();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (c_l, c_r) = c.split_at(half);
+        let (v1_l, v1_r) = v1.split_at(half);
+        let (v2_l, v2_r) = v2.split_at(half);
+        let (w1_l, w1_r) = w1.split_at(half);
+        let (w2_l, w2_r) = w2.split_at(half);
+
+        let round = GipaRound {
+            com_ab_l: (
+                pairing::<E>(&[(a_r, v1_l), (w1_r, b_l)]),
+                pairing::<E>(&[(a_r, v2_l), (w2_r, b_l)]),
+            ),
+            com_ab_r: (
+                pairing::<E>(&[(a_l, v1_r), (w1_l, b_r)]),
+                pairing::<E>(&[(a_l, v2_r), (w2_l, b_r)]),
+            ),
+            z_ab_l: pairing::<E>(&[(a_r, b_l)]),
+            z_ab_r: pairing::<E>(&[(a_l, b_r)]),
+            com_c_l: (pairing::<E>(&[(c_r, v1_l)]), pairing::<E>(&[(c_r, v2_l)])),
+            com_c_r: (pairing::<E>(&[(c_l, v1_r)]), pairing::<E>(&[(c_l, v2_r)])),
+            z_c_l: scaled_sum::<E>(c_r, &s),
+            z_c_r: scaled_sum::<E>(c_l, &s),
+        };
+        round.absorb(transcript);
+        let (x, x_inv) = challenge::<E, H>(transcript);
+
+        let folded_a = fold(a_l, a_r, &x);
+        let folded_b = fold(b_l, b_r, &x_inv);
+        let folded_c = fold(c_l, c_r, &x);
+        let folded_v1 = fold(v1_l, v1_r, &x_inv);
+        let folded_v2 = fold(v2_l, v2_r, &x_inv);
+        let folded_w1 = fold(w1_l, w1_r, &x);
+        let folded_w2 = fold(w2_l, w2_r, &x);
+        a = folded_a;
+        b = folded_b;
+        c = folded_c;
+        v1 = folded_v1;
+        v2 = folded_v2;
+        w1 = folded_w1;
+        w2 = folded_w2;
+
+        let mut one_plus_x_inv = x_inv;
+        one_plus_x_inv.add_assign(&E::Fr::one());
+        s.mul_assign(&one_plus_x_inv);
+        rounds.push(round);
+        xs.push(x);
+        x_invs.push(x_inv);
+    }
+
+    let final_v = (v1[0], v2[0]);
+    let final_w = (w1[0], w2[0]);
+    let z = opening_challenge::<E, H>(transcript, &a[0], &b[0], &c[0], &final_v, &final_w);
+
+    // The keys of A are folded with the coefficients of
+    // f_v(X) = prod_j (1 + x_j^-1 (X / r)^(m / 2^(j + 1))), those of B with
+    // f_w(X) = X^m prod_j (1 + x_j X^(m / 2^(j + 1))).
+    let v_factors = v_factors::<E>(&x_invs, &r_inv, m);
+    let v_quotient = kzg_quotient(&fold_coefficients(&v_factors), &z);
+    let mut w_coeffs = vec![E::Fr::zero(); m];
+    w_coeffs.extend(fold_coefficients(&xs));
+    let w_quotient = kzg_quotient(&w_coeffs, &z);
+
+    Ok(AggregatedProof {
+        com_ab,
+        com_c,
+        z_ab,
+        z_c,
+        rounds,
+        final_a: a[0],
+        final_b: b[0],
+        final_c: c[0],
+        final_v,
+        final_w,
+        opening_v: (
+            msm(&srs.h_alpha[..m - 1], &v_quotient).into_affine(),
+            msm(&srs.h_beta[..m - 1], &v_quotient).into_affine(),
+        ),
+        opening_w: (
+            msm(&srs.g_alpha[..2 * m - 1], &w_quotient).into_affine(),
+            msm(&srs.g_beta[..2 * m - 1], &w_quotient).into_affine(),
+        ),
+    })
+}
+
+/// `s * sum points_i`
+fn scaled_sum<E: Engine>(points: &[E::G1Affine], s: &E::Fr) -> E::G1Affine {
+    let mut acc = E::G1::zero();
+    for point in points {
+        acc.add_assign_mixed(point);
+    }
+    acc.mul_assign(s.into_repr());
+    acc.into_affine()
+}
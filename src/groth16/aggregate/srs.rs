@@ -0,0 +1,76 @@
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::RngCore;
+use rayon::prelude::*;
+
+use super::powers;
+use crate::bls::Engine;
+use crate::multicore::{install_in, THREAD_POOL};
+
+/// The structured reference string for aggregating up to `n` proofs: the powers of two
+/// secrets `a` and `b` on the generators `G` of G1 and `H` of G2.
+///
+/// It comes from a trusted setup of its own, independent of the parameters of the
+/// circuits, and one SRS aggregates proofs of any circuit over the same engine. Whoever
+/// learns `a` or `b` can forge aggregated proofs, so the secrets must be destroyed
+/// after the setup, as those of [`generate_random_parameters`].
+///
+/// [`generate_random_parameters`]: crate::groth16::generate_random_parameters
+#[derive(Clone, Debug)]
+pub struct AggSRS<E: Engine> {
+    /// `a^i G` for `i < 2n`.
+    pub(super) g_alpha: Vec<E::G1Affine>,
+    /// `b^i G` for `i < 2n`.
+    pub(super) g_beta: Vec<E::G1Affine>,
+    /// `a^i H` for `i < n`.
+    pub(super) h_alpha: Vec<E::G2Affine>,
+    /// `b^i H` for `i < n`.
+    pub(super) h_beta: Vec<E::G2Affine>,
+}
+
+impl<E: Engine> PartialEq for AggSRS<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.g_alpha == other.g_alpha
+            && self.g_beta == other.g_beta
+            && self.h_alpha == other.h_alpha
+            && self.h_beta == other.h_beta
+    }
+}
+
+impl<E: Engine> AggSRS<E> {
+    /// Creates the SRS for up to `n` proofs, with secrets sampled from `rng` and
+    /// dropped when done.
+    ///
+    /// Panics if `n` is not a power of two or smaller than 2.
+    pub fn setup<R: RngCore>(n: usize, rng: &mut R) -> Self {
+        assert!(
+            n >= 2 && n.is_power_of_two(),
+            "the SRS size must be a power of two of at least 2"
+        );
+        let a = E::Fr::random(rng);
+        let b = E::Fr::random(rng);
+
+        AggSRS {
+            g_alpha: multiply_generator(&powers(&a, 2 * n)),
+            g_beta: multiply_generator(&powers(&b, 2 * n)),
+            h_alpha: multiply_generator(&powers(&a, n)),
+            h_beta: multiply_generator(&powers(&b, n)),
+        }
+    }
+
+    /// The maximal number of proofs this SRS aggregates.
+    pub fn size(&self) -> usize {
+        self.h_alpha.len()
+    }
+}
+
+fn multiply_generator<G: CurveAffine>(scalars: &[G::Scalar]) -> Vec<G> {
+    let mut points = install_in(&THREAD_POOL, || {
+        scalars
+            .par_iter()
+            .map(|s| G::one().mul(s.into_repr()))
+            .collect::<Vec<_>>()
+    });
+    G::Projective::batch_normalization(&mut points);
+    points.into_iter().map(|p| p.into_affine()).collect()
+}
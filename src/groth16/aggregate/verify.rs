@@ -0,0 +1,176 @@
+use digest::Digest;
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::{
+    challenge, check_proof_count, commitment_challenge, fold_evaluation, fold_gt, msm,
+    opening_challenge, powers, v_factors, AggSRS, AggregatedProof,
+};
+use crate::bls::{Engine, PairingCurveAffine};
+use crate::groth16::VerifyingKey;
+use crate::transcript::FiatShamirRng;
+use crate::SynthesisError;
+
+/// Verifies an aggregation of proofs for the circuit of `vk`, with `public_inputs[i]`
+/// the public inputs of the `i`-th proof.
+///
+/// `transcript` must have absorbed the same values as the one given to
+/// [`aggregate_proofs`](super::aggregate_proofs), in the same order.
+pub fn verify_aggregated<E, H>(
+    agg: &AggregatedProof<E>,
+    vk: &VerifyingKey<E>,
+    srs: &AggSRS<E>,
+    transcript: &mut FiatShamirRng<H>,
+    public_inputs: &[Vec<E::Fr>],
+) -> Result<bool, SynthesisError>
+where
+    E: Engine,
+    H: Digest + Clone,
+{
+    let m = public_inputs.len();
+    check_proof_count(m, srs)?;
+    if public_inputs
+        .iter()
+        .any(|inputs| inputs.len() + 1 != vk.ic.len())
+    {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    if agg.rounds.len() != m.trailing_zeros() as usize {
+        return Ok(false);
+    }
+
+    let (r, r_inv) = commitment_challenge::<E, H>(transcript, vk, m, &agg.com_ab, &agg.com_c);
+    transcript.absorb_gt(&agg.z_ab);
+    transcript.absorb_g1(&agg.z_c);
+
+    // The Groth16 equations of the proofs combined with the powers of r:
+    // prod e(r^i A_i, B_i)
+    //   = e(alpha, beta)^(sum r^i) e(sum r^i S_i, gamma) e(sum r^i C_i, delta)
+    // with S_i the combination of the public inputs of the i-th proof.
+    let mut input_scalars = vec![E::Fr::zero(); vk.ic.len()];
+    for (r_i, inputs) in powers(&r, m).iter().zip(public_inputs) {
+        input_scalars[0].add_assign(r_i);
+        for (scalar, input) in input_scalars[1..].iter_mut().zip(inputs) {
+            let mut term = *input;
+            term.mul_assign(r_i);
+            scalar.add_assign(&term);
+        }
+    }
+    let inputs = msm(&vk.ic, &input_scalars).into_affine();
+    let mut expected = E::pairing(vk.alpha_g1, vk.beta_g2).pow(input_scalars[0].into_repr());
+    expected.mul_assign(&pairing_product::<E>(&[
+        (inputs, vk.gamma_g2),
+        (agg.z_c, vk.delta_g2),
+    ]));
+    if expected != agg.z_ab {
+        return Ok(false);
+    }
+
+    let mut com_ab = agg.com_ab;
+    let mut com_c = agg.com_c;
+    let mut z_ab = agg.z_ab;
+    let mut z_c = agg.z_c.into_projective();
+    let mut s = E::Fr::one();
+    let mut xs = Vec::with_capacity(agg.rounds.len());
+    let mut x_invs = Vec::with_capacity(agg.rounds.len());
+    for round in &agg.rounds {
+        round.absorb(transcript);
+        let (x, x_inv) = challenge::<E, H>(transcript);
+
+        com_ab.0 = fold_gt::<E>(&com_ab.0, &round.com_ab_l.0, &round.com_ab_r.0, &x, &x_inv);
+        com_ab.1 = fold_gt::<E>(&com_ab.1, &round.com_ab_l.1, &round.com_ab_r.1, &x, &x_inv);
+        z_ab = fold_gt::<E>(&z_ab, &round.z_ab_l, &round.z_ab_r, &x, &x_inv);
+        com_c.0 = fold_gt::<E>(&com_c.0, &round.com_c_l.0, &round.com_c_r.0, &x, &x_inv);
+        com_c.1 = fold_gt::<E>(&com_c.1, &round.com_c_l.1, &round.com_c_r.1, &x, &x_inv);
+        z_c.add_assign(&round.z_c_l.mul(x.into_repr()));
+        z_c.add_assign(&round.z_c_r.mul(x_inv.into_repr()));
+
+        let mut one_plus_x_inv = x_inv;
+        one_plus_x_inv.add_assign(&E::Fr::one());
+        s.mul_assign(&one_plus_x_inv);
+        xs.push(x);
+        x_invs.push(x_inv);
+    }
+
+    let z = opening_challenge::<E, H>(
+        transcript,
+        &agg.final_a,
+        &agg.final_b,
+        &agg.final_c,
+        &agg.final_v,
+        &agg.final_w,
+    );
+
+    // The folded proofs must match the folded commitments and products.
+    let (a, b, c) = (agg.final_a, agg.final_b, agg.final_c);
+    let (v, w) = (agg.final_v, agg.final_w);
+    if pairing_product::<E>(&[(a, v.0), (w.0, b)]) != com_ab.0
+        || pairing_product::<E>(&[(a, v.1), (w.1, b)]) != com_ab.1
+        || pairing_product::<E>(&[(a, b)]) != z_ab
+        || pairing_product::<E>(&[(c, v.0)]) != com_c.0
+        || pairing_product::<E>(&[(c, v.1)]) != com_c.1
+        || c.mul(s.into_repr()) != z_c
+    {
+        return Ok(false);
+    }
+
+    // The folded keys must be the commitments to f_v and f_w, see `aggregate_proofs`.
+    let v_eval = fold_evaluation(&v_factors::<E>(&x_invs, &r_inv, m), &z);
+    let mut w_eval = z.pow(&[m as u64]);
+    w_eval.mul_assign(&fold_evaluation(&xs, &z));
+
+    let keys = [
+        (
+            srs.g_alpha[1],
+            srs.h_alpha[1],
+            v.0,
+            agg.opening_v.0,
+            w.0,
+            agg.opening_w.0,
+        ),
+        (
+            srs.g_beta[1],
+            srs.h_beta[1],
+            v.1,
+            agg.opening_v.1,
+            w.1,
+            agg.opening_w.1,
+        ),
+    ];
+    for &(g_s, h_s, v, opening_v, w, opening_w) in &keys {
+        // e(s G - z G, opening_v) = e(G, v - f_v(z) H)
+        let mut g_s_minus_z = g_s.into_projective();
+        g_s_minus_z.sub_assign(&E::G1Affine::one().mul(z.into_repr()));
+        let mut v_minus_eval = v.into_projective();
+        v_minus_eval.sub_assign(&E::G2Affine::one().mul(v_eval.into_repr()));
+        if pairing_product::<E>(&[(g_s_minus_z.into_affine(), opening_v)])
+            != pairing_product::<E>(&[(E::G1Affine::one(), v_minus_eval.into_affine())])
+        {
+            return Ok(false);
+        }
+
+        // e(opening_w, s H - z H) = e(w - f_w(z) G, H)
+        let mut h_s_minus_z = h_s.into_projective();
+        h_s_minus_z.sub_assign(&E::G2Affine::one().mul(z.into_repr()));
+        let mut w_minus_eval = w.into_projective();
+        w_minus_eval.sub_assign(&E::G1Affine::one().mul(w_eval.into_repr()));
+        if pairing_product::<E>(&[(opening_w, h_s_minus_z.into_affine())])
+            != pairing_product::<E>(&[(w_minus_eval.into_affine(), E::G2Affine::one())])
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// `prod e(a_i, b_i)`
+fn pairing_product<E: Engine>(pairs: &[(E::G1Affine, E::G2Affine)]) -> E::Fqk {
+    let prepared = pairs
+        .iter()
+        .map(|(a, b)| (a.prepare(), b.prepare()))
+        .collect::<Vec<_>>();
+    let refs = prepared.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+
+    E::final_exponentiation(&E::miller_loop(&refs)).expect("a Miller loop is never zero")
+}
@@ -0,0 +1,74 @@
+use ff::{PrimeField, PrimeFieldRepr};
+
+use super::{verify_proof, PreparedVerifyingKey, Proof};
+use crate::bls::Engine;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// Personalization of the hash of the transcript.
+const BINDING_PERSONALIZATION: &[u8; 8] = b"BPBIND00";
+
+/// A circuit bound to the transcript of the protocol its proof is sent in, e.g. a
+/// session id and the messages exchanged before it.
+///
+/// The hash of the transcript is an extra public input, after those of `circuit`. It
+/// takes part in no constraint of `circuit`, but like every public input it is
+/// committed to by the proof, so a proof for one transcript does not verify under
+/// another: replaying it needs a new proof, and so the witness.
+///
+/// The parameters are generated for the bound circuit, with any transcript, and only
+/// verify proofs of the bound circuit, with [`verify_bound_proof`].
+pub struct TranscriptBound<'a, C> {
+    pub circuit: C,
+    pub transcript: &'a [u8],
+}
+
+impl<'a, E: Engine, C: Circuit<E>> Circuit<E> for TranscriptBound<'a, C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.circuit.synthesize(&mut cs.namespace(|| "circuit"))?;
+
+        let transcript = self.transcript;
+        cs.alloc_input(|| "transcript", || Ok(transcript_scalar::<E>(transcript)))?;
+
+        Ok(())
+    }
+}
+
+/// Binds `circuit` to `transcript`.
+pub fn bind_transcript<C>(circuit: C, transcript: &[u8]) -> TranscriptBound<'_, C> {
+    TranscriptBound {
+        circuit,
+        transcript,
+    }
+}
+
+/// Verifies a proof of a circuit from [`bind_transcript`], rejecting it if it was bound
+/// to another transcript.
+pub fn verify_bound_proof<E: Engine>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    transcript: &[u8],
+    public_inputs: &[E::Fr],
+) -> Result<bool, SynthesisError> {
+    let mut inputs = Vec::with_capacity(public_inputs.len() + 1);
+    inputs.extend_from_slice(public_inputs);
+    inputs.push(transcript_scalar::<E>(transcript));
+
+    verify_proof(pvk, proof, &inputs)
+}
+
+/// Hashes the transcript into a scalar.
+fn transcript_scalar<E: Engine>(transcript: &[u8]) -> E::Fr {
+    let hash = blake2s_simd::Params::new()
+        .hash_length(32)
+        .personal(BINDING_PERSONALIZATION)
+        .hash(transcript);
+
+    // Keep `CAPACITY` bits of the hash, so the result is always below the modulus.
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.read_le(hash.as_bytes())
+        .expect("the hash is as long as the representation");
+    let bits = 64 * repr.as_ref().len() as u32;
+    repr.shr(bits - E::Fr::CAPACITY);
+
+    E::Fr::from_repr(repr).expect("the value is below the modulus")
+}
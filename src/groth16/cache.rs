@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use ff::{PrimeField, PrimeFieldRepr};
+
+use super::{verify_proof, CircuitTag, PreparedVerifyingKey, Proof};
+use crate::bls::Engine;
+use crate::SynthesisError;
+
+/// Number of results a [`CachingVerifier`] keeps by default.
+pub const DEFAULT_CACHE_SIZE: usize = 1024;
+
+const CACHE_KEY_PERSONALIZATION: &[u8; 8] = b"BPVCKEY0";
+
+type CacheKey = [u8; 32];
+
+/// Results of verifications, evicting the least recently used beyond `capacity`.
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    results: HashMap<CacheKey, (bool, u64)>,
+    recency: BTreeMap<u64, CacheKey>,
+}
+
+impl Lru {
+    fn get(&mut self, key: &CacheKey) -> Option<bool> {
+        let tick = self.tick;
+        let (valid, used) = self.results.get_mut(key)?;
+        self.recency.remove(&*used);
+        self.recency.insert(tick, *key);
+        *used = tick;
+        self.tick += 1;
+
+        Some(*valid)
+    }
+
+    fn insert(&mut self, key: CacheKey, valid: bool) {
+        if let Some((_, used)) = self.results.insert(key, (valid, self.tick)) {
+            self.recency.remove(&used);
+        }
+        self.recency.insert(self.tick, key);
+        self.tick += 1;
+
+        while self.results.len() > self.capacity {
+            let oldest = *self.recency.keys().next().expect("more results than ticks");
+            let key = self.recency.remove(&oldest).unwrap();
+            self.results.remove(&key);
+        }
+    }
+}
+
+/// Verifies single proofs with [`verify_proof`], remembering the results of the
+/// most recent verifications, e.g. for relays receiving the same proof from
+/// several peers.
+///
+/// Results are looked up by the [`Proof::fingerprint`], together with the
+/// public inputs and the fingerprint of the whole verifying key, see
+/// [`VerifyingKey::fingerprint`](super::VerifyingKey::fingerprint), so a proof
+/// submitted again for other inputs or another key is verified again. Errors are
+/// not cached.
+pub struct CachingVerifier<E: Engine> {
+    cache: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    _marker: PhantomData<E>,
+}
+
+impl<E: Engine> CachingVerifier<E> {
+    /// A verifier caching [`DEFAULT_CACHE_SIZE`] results.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CACHE_SIZE)
+    }
+
+    /// A verifier caching up to `capacity` results.
+    pub fn with_capacity(capacity: usize) -> Self {
+        CachingVerifier {
+            cache: Mutex::new(Lru {
+                capacity,
+                tick: 0,
+                results: HashMap::new(),
+                recency: BTreeMap::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Verifies `proof` for `public_inputs`, or returns the result of an earlier
+    /// verification of it.
+    pub fn verify<C: CircuitTag>(
+        &self,
+        pvk: &PreparedVerifyingKey<E, C>,
+        proof: &Proof<E, C>,
+        public_inputs: &[E::Fr],
+    ) -> Result<bool, SynthesisError> {
+        let key = cache_key(pvk, proof, public_inputs);
+        if let Some(valid) = self.cache.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(valid);
+        }
+
+        // Concurrent verifications of the same proof both run, and agree.
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let valid = verify_proof(pvk, proof, public_inputs)?;
+        self.cache.lock().unwrap().insert(key, valid);
+
+        Ok(valid)
+    }
+
+    /// Number of cached results.
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of verifications answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of verifications which ran [`verify_proof`].
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+impl<E: Engine> Default for CachingVerifier<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn cache_key<E: Engine, C: CircuitTag>(
+    pvk: &PreparedVerifyingKey<E, C>,
+    proof: &Proof<E, C>,
+    public_inputs: &[E::Fr],
+) -> CacheKey {
+    let mut state = blake2s_simd::Params::new()
+        .hash_length(32)
+        .personal(CACHE_KEY_PERSONALIZATION)
+        .to_state();
+
+    state.update(&proof.fingerprint());
+    state.update(&pvk.fingerprint);
+    let mut bytes = Vec::new();
+    for input in public_inputs {
+        input
+            .into_repr()
+            .write_le(&mut bytes)
+            .expect("writing to a vec never fails");
+    }
+    state.update(&bytes);
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(state.finalize().as_bytes());
+    key
+}
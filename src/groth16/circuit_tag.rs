@@ -0,0 +1,41 @@
+/// Marks the circuit a [`Proof`](super::Proof), its
+/// [`Parameters`](super::Parameters) and its verifying keys belong to, so that the type
+/// checker rejects verifying a proof of one circuit with the key of another.
+///
+/// Tags are types without values, usually one per circuit:
+///
+/// ```ignore
+/// enum Transfer {}
+/// impl CircuitTag for Transfer {}
+///
+/// let params = generate_random_parameters(circuit, rng)?.for_circuit::<Transfer>();
+/// let pvk = prepare_verifying_key(&params.vk);
+/// // A `Proof<Bls12, Transfer>`, which only verifies with `pvk`.
+/// let proof = create_random_proof(circuit, &params, rng)?;
+/// ```
+///
+/// A proof of one circuit does not verify with the key of another:
+///
+/// ```compile_fail
+/// use bellperson::bls::Bls12;
+/// use bellperson::groth16::{verify_proof, CircuitTag, PreparedVerifyingKey, Proof};
+///
+/// enum Transfer {}
+/// impl CircuitTag for Transfer {}
+/// enum Withdrawal {}
+/// impl CircuitTag for Withdrawal {}
+///
+/// fn check(pvk: &PreparedVerifyingKey<Bls12, Withdrawal>, proof: &Proof<Bls12, Transfer>) {
+///     verify_proof(pvk, proof, &[]).unwrap();
+/// }
+/// ```
+pub trait CircuitTag {}
+
+/// The tag of proofs and keys of any circuit, for values whose circuit is only known at
+/// runtime, e.g. keys looked up in a [`CircuitRegistry`](crate::registry::CircuitRegistry)
+/// or proofs read from the network. It is the default tag, and values of every tag
+/// convert to it with `into_any`, and back with `for_circuit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnyCircuit {}
+
+impl CircuitTag for AnyCircuit {}
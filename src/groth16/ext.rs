@@ -1,5 +1,5 @@
 use super::{create_proof_batch_priority, create_random_proof_batch_priority};
-use super::{ParameterSource, Proof};
+use super::{ConditionalSwap, ParameterSource, Proof};
 use crate::bls::Engine;
 use crate::{Circuit, SynthesisError};
 use rand_core::RngCore;
@@ -9,10 +9,12 @@ pub fn create_proof<E, C, P: ParameterSource<E>>(
     params: P,
     r: E::Fr,
     s: E::Fr,
-) -> Result<Proof<E>, SynthesisError>
+) -> Result<Proof<E, P::Tag>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     let proofs =
         create_proof_batch_priority::<E, C, P>(vec![circuit], params, vec![r], vec![s], false)?;
@@ -23,11 +25,13 @@ pub fn create_random_proof<E, C, R, P: ParameterSource<E>>(
     circuit: C,
     params: P,
     rng: &mut R,
-) -> Result<Proof<E>, SynthesisError>
+) -> Result<Proof<E, P::Tag>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
     R: RngCore,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     let proofs =
         create_random_proof_batch_priority::<E, C, R, P>(vec![circuit], params, rng, false)?;
@@ -39,10 +43,12 @@ pub fn create_proof_batch<E, C, P: ParameterSource<E>>(
     params: P,
     r: Vec<E::Fr>,
     s: Vec<E::Fr>,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     create_proof_batch_priority::<E, C, P>(circuits, params, r, s, false)
 }
@@ -51,11 +57,13 @@ pub fn create_random_proof_batch<E, C, R, P: ParameterSource<E>>(
     circuits: Vec<C>,
     params: P,
     rng: &mut R,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
     R: RngCore,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     create_random_proof_batch_priority::<E, C, R, P>(circuits, params, rng, false)
 }
@@ -65,10 +73,12 @@ pub fn create_proof_in_priority<E, C, P: ParameterSource<E>>(
     params: P,
     r: E::Fr,
     s: E::Fr,
-) -> Result<Proof<E>, SynthesisError>
+) -> Result<Proof<E, P::Tag>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     let proofs =
         create_proof_batch_priority::<E, C, P>(vec![circuit], params, vec![r], vec![s], true)?;
@@ -79,11 +89,13 @@ pub fn create_random_proof_in_priority<E, C, R, P: ParameterSource<E>>(
     circuit: C,
     params: P,
     rng: &mut R,
-) -> Result<Proof<E>, SynthesisError>
+) -> Result<Proof<E, P::Tag>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
     R: RngCore,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     let proofs =
         create_random_proof_batch_priority::<E, C, R, P>(vec![circuit], params, rng, true)?;
@@ -95,10 +107,12 @@ pub fn create_proof_batch_in_priority<E, C, P: ParameterSource<E>>(
     params: P,
     r: Vec<E::Fr>,
     s: Vec<E::Fr>,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     create_proof_batch_priority::<E, C, P>(circuits, params, r, s, true)
 }
@@ -107,11 +121,13 @@ pub fn create_random_proof_batch_in_priority<E, C, R, P: ParameterSource<E>>(
     circuits: Vec<C>,
     params: P,
     rng: &mut R,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
     R: RngCore,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     create_random_proof_batch_priority::<E, C, R, P>(circuits, params, rng, true)
 }
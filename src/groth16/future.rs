@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use ff::Field;
+use rand::rngs::StdRng;
+use rand_core::{RngCore, SeedableRng};
+
+use super::{
+    create_proof, verify_proofs_batch, ConditionalSwap, Parameters, PreparedVerifyingKey, Proof,
+};
+use crate::bls::Engine;
+use crate::multicore::{Waiter, Worker};
+use crate::{Circuit, SynthesisError};
+
+/// Like [`create_random_proof`](super::create_random_proof), but proves on the threads
+/// of the global [`Worker`] and returns right away. The returned [`Waiter`] is a future
+/// of the proof, so an async caller awaits it without blocking a thread.
+///
+/// Dropping the future does not cancel the proof: it is completed and discarded.
+pub fn create_random_proof_async<E, C, R>(
+    circuit: C,
+    params: Arc<Parameters<E>>,
+    rng: &mut R,
+) -> Waiter<Result<Proof<E>, SynthesisError>>
+where
+    E: Engine,
+    C: Circuit<E> + Send + 'static,
+    R: RngCore,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
+
+    Worker::new().compute(move || create_proof(circuit, &*params, r, s))
+}
+
+/// Like [`verify_proofs_batch`], but verifies on the threads of the global [`Worker`]
+/// and returns right away, with the randomness of the batch seeded from `rng`.
+///
+/// Dropping the future does not cancel the verification: it is completed and discarded.
+pub fn verify_proofs_batch_async<E, R>(
+    pvk: Arc<PreparedVerifyingKey<E>>,
+    rng: &mut R,
+    proofs: Vec<Proof<E>>,
+    public_inputs: Vec<Vec<E::Fr>>,
+) -> Waiter<Result<bool, SynthesisError>>
+where
+    E: Engine,
+    R: RngCore,
+    <<E as ff::ScalarEngine>::Fr as ff::PrimeField>::Repr: From<<E as ff::ScalarEngine>::Fr>,
+{
+    let mut seed = <StdRng as SeedableRng>::Seed::default();
+    rng.fill_bytes(&mut seed);
+
+    Worker::new().compute(move || {
+        let proofs = proofs.iter().collect::<Vec<_>>();
+        verify_proofs_batch(&*pvk, &mut StdRng::from_seed(seed), &proofs, &public_inputs)
+    })
+}
@@ -1,5 +1,6 @@
 use rand_core::RngCore;
 
+use std::marker::PhantomData;
 use std::sync::Arc;
 
 use crate::bls::Engine;
@@ -10,7 +11,7 @@ use super::{Parameters, VerifyingKey};
 
 use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 
-use crate::domain::{EvaluationDomain, Scalar};
+use crate::domain::{DomainPrecompute, EvaluationDomain, Scalar};
 
 use crate::multicore::Worker;
 
@@ -205,9 +206,12 @@ where
         assembly.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
     }
 
-    // Create bases for blind evaluation of polynomials at tau
+    // Create bases for blind evaluation of polynomials at tau, checking the size of
+    // the domain before allocating it
+    let precompute = DomainPrecompute::new(assembly.num_constraints)?;
     let powers_of_tau = vec![Scalar::<E>(E::Fr::zero()); assembly.num_constraints];
-    let mut powers_of_tau = EvaluationDomain::from_coeffs(powers_of_tau)?;
+    let mut powers_of_tau =
+        EvaluationDomain::from_coeffs_with_precompute(powers_of_tau, &precompute)?;
 
     // Compute G1 window table
     let mut g1_wnaf = Wnaf::new();
@@ -462,6 +466,7 @@ where
         delta_g1: g1.mul(delta).into_affine(),
         delta_g2: g2.mul(delta).into_affine(),
         ic: ic.into_iter().map(|e| e.into_affine()).collect(),
+        circuit: PhantomData,
     };
 
     Ok(Parameters {
@@ -0,0 +1,143 @@
+//! Proving keys with the H query in the Lagrange basis.
+//!
+//! The prover evaluates `H = (A * B - C) / t` on a coset of the evaluation domain,
+//! where `t` is constant, and the usual H query, `tau^i * t(tau) / delta`, needs the
+//! coefficients of `H`, which take another inverse FFT. A [`LagrangeProvingKey`]
+//! instead holds `L_i(tau) * t(tau) / delta` for the Lagrange polynomials `L_i` of the
+//! coset, so that the evaluations are the exponents of the H multiexp as they are,
+//! and a proof takes six FFTs instead of seven. The key has one point more than the
+//! [`Parameters`] it is made from, and the proofs are the same.
+
+use ff::{Field, PrimeField};
+use groupy::CurveProjective;
+use rand_core::RngCore;
+use std::sync::Arc;
+
+use super::prover::{create_proof_batch_in_basis, HBasis};
+use super::{
+    generate_random_parameters, AnyCircuit, CircuitTag, ConditionalSwap, Parameters, Proof,
+    VerifyingKey,
+};
+use crate::bls::Engine;
+use crate::domain::{EvaluationDomain, Point};
+use crate::multicore::Worker;
+use crate::{Circuit, SynthesisError};
+
+/// Groth16 parameters whose H query is in the Lagrange basis of the coset the prover
+/// evaluates H on. These only work with [`create_proof_lagrange`], not with the
+/// provers taking a [`ParameterSource`](super::ParameterSource).
+pub struct LagrangeProvingKey<E: Engine, C: CircuitTag = AnyCircuit> {
+    params: Parameters<E, C>,
+}
+
+impl<E: Engine, C: CircuitTag> Clone for LagrangeProvingKey<E, C> {
+    fn clone(&self) -> Self {
+        LagrangeProvingKey {
+            params: self.params.clone(),
+        }
+    }
+}
+
+impl<E: Engine, C: CircuitTag> LagrangeProvingKey<E, C> {
+    /// Converts the H query of `params` to the Lagrange basis, with an FFT over its
+    /// points.
+    pub fn from_parameters(params: Parameters<E, C>) -> Result<Self, SynthesisError> {
+        // The query is one point shorter than the domain, as H has a lower degree.
+        let mut h = params
+            .h
+            .iter()
+            .map(|p| Point(p.into_projective()))
+            .collect::<Vec<_>>();
+        h.push(Point(E::G1::zero()));
+        let size = h.len();
+        if !size.is_power_of_two() {
+            return Err(SynthesisError::InvalidDomainSize(size));
+        }
+
+        // sum_j h_j Q_j for the coefficients h_j = g^-j / n sum_i omega^-ij e_i of the
+        // evaluations e_i is sum_i e_i L_i for L_i = 1 / n sum_j omega^-ij g^-j Q_j.
+        let worker = Worker::new();
+        let mut domain = EvaluationDomain::from_coeffs(h)?;
+        let geninv = E::Fr::multiplicative_generator().inverse().unwrap();
+        domain.distribute_powers(&worker, geninv);
+        domain.ifft(&worker, &mut None)?;
+
+        let mut h = domain
+            .into_coeffs()
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        E::G1::batch_normalization(&mut h);
+
+        Ok(LagrangeProvingKey {
+            params: Parameters {
+                h: Arc::new(h.into_iter().map(|p| p.into_affine()).collect()),
+                ..params
+            },
+        })
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<E, C> {
+        &self.params.vk
+    }
+}
+
+/// Generates random parameters for `circuit`, like [`generate_random_parameters`],
+/// with the H query in the Lagrange basis.
+pub fn create_lagrange_params<E, C, R>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<LagrangeProvingKey<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore,
+{
+    LagrangeProvingKey::from_parameters(generate_random_parameters(circuit, rng)?)
+}
+
+/// Creates a proof of `circuit` with `pk`, like [`create_proof`](super::create_proof).
+pub fn create_proof_lagrange<E, C, T>(
+    circuit: C,
+    pk: &LagrangeProvingKey<E, T>,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E, T>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+    T: CircuitTag,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    let proofs = create_proof_batch_in_basis(
+        vec![circuit],
+        &pk.params,
+        vec![r],
+        vec![s],
+        false,
+        &Worker::new(),
+        HBasis::Lagrange,
+    )?;
+    Ok(proofs.into_iter().next().unwrap())
+}
+
+/// Creates a proof of `circuit` with `pk` and random `r` and `s`.
+pub fn create_random_proof_lagrange<E, C, R, T>(
+    circuit: C,
+    pk: &LagrangeProvingKey<E, T>,
+    rng: &mut R,
+) -> Result<Proof<E, T>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+    R: RngCore,
+    T: CircuitTag,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    let r = E::Fr::random(rng);
+    let s = E::Fr::random(rng);
+
+    create_proof_lagrange(circuit, pk, r, s)
+}
@@ -1,6 +1,7 @@
 use crate::bls::Engine;
 use groupy::{CurveAffine, EncodedPoint};
 
+use crate::multiexp::EncodedBases;
 use crate::SynthesisError;
 
 use memmap::Mmap;
@@ -11,15 +12,15 @@ use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use super::{ParameterSource, PreparedVerifyingKey, VerifyingKey};
+use super::{AnyCircuit, ParameterSource, PreparedVerifyingKey, VerifyingKey};
 
 pub struct MappedParameters<E: Engine> {
     /// The parameter file we're reading from.  
     pub param_file_path: PathBuf,
     /// The file descriptor we have mmaped.
     pub param_file: File,
-    /// The actual mmap.
-    pub params: Mmap,
+    /// The actual mmap, shared with the bases handed out to the prover.
+    pub params: Arc<Mmap>,
 
     /// This is always loaded (i.e. not lazily loaded).
     pub vk: VerifyingKey<E>,
@@ -48,34 +49,41 @@ pub struct MappedParameters<E: Engine> {
     pub checked: bool,
 }
 
+impl<E: Engine> MappedParameters<E> {
+    /// Bases stored at `ranges`, read from the mmap as they are needed. The ranges of
+    /// each query are laid out back to back in the parameter file.
+    fn bases<G: CurveAffine>(
+        &self,
+        ranges: &[Range<usize>],
+    ) -> Result<EncodedBases<G, Mmap>, SynthesisError> {
+        let region = match (ranges.first(), ranges.last()) {
+            (Some(first), Some(last)) => first.start..last.end,
+            _ => 0..0,
+        };
+
+        Ok(EncodedBases::new(
+            self.params.clone(),
+            region,
+            self.checked,
+        )?)
+    }
+}
+
 impl<'a, E: Engine> ParameterSource<E> for &'a MappedParameters<E> {
-    type G1Builder = (Arc<Vec<E::G1Affine>>, usize);
-    type G2Builder = (Arc<Vec<E::G2Affine>>, usize);
+    type G1Builder = EncodedBases<E::G1Affine, Mmap>;
+    type G2Builder = EncodedBases<E::G2Affine, Mmap>;
+    type Tag = AnyCircuit;
 
     fn get_vk(&self, _: usize) -> Result<&VerifyingKey<E>, SynthesisError> {
         Ok(&self.vk)
     }
 
     fn get_h(&self, _num_h: usize) -> Result<Self::G1Builder, SynthesisError> {
-        let builder = self
-            .h
-            .iter()
-            .cloned()
-            .map(|h| read_g1::<E>(&self.params, h, self.checked))
-            .collect::<Result<_, _>>()?;
-
-        Ok((Arc::new(builder), 0))
+        self.bases(&self.h)
     }
 
     fn get_l(&self, _num_l: usize) -> Result<Self::G1Builder, SynthesisError> {
-        let builder = self
-            .l
-            .iter()
-            .cloned()
-            .map(|l| read_g1::<E>(&self.params, l, self.checked))
-            .collect::<Result<_, _>>()?;
-
-        Ok((Arc::new(builder), 0))
+        self.bases(&self.l)
     }
 
     fn get_a(
@@ -83,16 +91,9 @@ impl<'a, E: Engine> ParameterSource<E> for &'a MappedParameters<E> {
         num_inputs: usize,
         _num_a: usize,
     ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
-        let builder = self
-            .a
-            .iter()
-            .cloned()
-            .map(|a| read_g1::<E>(&self.params, a, self.checked))
-            .collect::<Result<_, _>>()?;
+        let builder = self.bases(&self.a)?;
 
-        let builder: Arc<Vec<_>> = Arc::new(builder);
-
-        Ok(((builder.clone(), 0), (builder, num_inputs)))
+        Ok((builder.clone(), builder.with_offset(num_inputs)))
     }
 
     fn get_b_g1(
@@ -100,16 +101,9 @@ impl<'a, E: Engine> ParameterSource<E> for &'a MappedParameters<E> {
         num_inputs: usize,
         _num_b_g1: usize,
     ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
-        let builder = self
-            .b_g1
-            .iter()
-            .cloned()
-            .map(|b_g1| read_g1::<E>(&self.params, b_g1, self.checked))
-            .collect::<Result<_, _>>()?;
-
-        let builder: Arc<Vec<_>> = Arc::new(builder);
+        let builder = self.bases(&self.b_g1)?;
 
-        Ok(((builder.clone(), 0), (builder, num_inputs)))
+        Ok((builder.clone(), builder.with_offset(num_inputs)))
     }
 
     fn get_b_g2(
@@ -117,16 +111,9 @@ impl<'a, E: Engine> ParameterSource<E> for &'a MappedParameters<E> {
         num_inputs: usize,
         _num_b_g2: usize,
     ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
-        let builder = self
-            .b_g2
-            .iter()
-            .cloned()
-            .map(|b_g2| read_g2::<E>(&self.params, b_g2, self.checked))
-            .collect::<Result<_, _>>()?;
-
-        let builder: Arc<Vec<_>> = Arc::new(builder);
+        let builder = self.bases(&self.b_g2)?;
 
-        Ok(((builder.clone(), 0), (builder, num_inputs)))
+        Ok((builder.clone(), builder.with_offset(num_inputs)))
     }
 }
 
@@ -1,26 +1,83 @@
 //! The [Groth16] proving system.
 //!
+//! # Side channels
+//!
+//! A proof is blinded by the random scalars `r` and `s`. Anyone who learns them for a
+//! proof can strip the blinding and learn about the witness, so the prover multiplies
+//! curve points by them, or by shares of them in [`MPCProver`], with a Montgomery
+//! ladder, which does not branch or index memory depending on the scalar. This guards
+//! against an attacker sharing the machine with the prover who observes its timing or
+//! cache usage. The ladder does not protect against power analysis or fault attacks,
+//! and the multiexps over the witness itself are not constant time.
+//!
+//! The ladder swaps its points without branching through [`ConditionalSwap`], so an
+//! engine proves only if its projective points implement it, as those of BLS12-381 do.
+//!
+//! # Recursion
+//!
+//! Verifying a proof inside a circuit needs the pairing of the proof's curve as
+//! constraints over the circuit's scalar field. For BLS12-381, the only curve supported
+//! by this crate, the pairing is defined over the base field `Fq`, so a native
+//! verifier circuit needs a second engine whose scalar field is `Fq`, such as BW6-761
+//! for BLS12-377 or the MNT4/6 cycle. Neither is available to this crate, and emulating
+//! `Fq` arithmetic over `Fr` costs hundreds of constraints per multiplication, so proofs
+//! are not verified recursively; [`aggregate_proofs`] compresses batches of proofs
+//! instead.
+//!
 //! [Groth16]: https://eprint.iacr.org/2016/260
 
 #[cfg(test)]
 mod tests;
 
+mod aggregate;
+mod bound;
+mod cache;
+mod circuit_tag;
 mod ext;
+#[cfg(feature = "async")]
+mod future;
 mod generator;
+#[cfg(feature = "lagrange")]
+mod lagrange;
 mod mapped_params;
+mod mpc;
+mod nullifier;
 mod params;
 mod proof;
 mod prover;
+mod qap;
+mod same_witness;
+mod sigma;
+mod tagged;
+mod update;
 mod verifier;
 mod verifying_key;
 
 mod multiscalar;
 
+pub use self::aggregate::*;
+pub use self::bound::*;
+pub use self::cache::*;
+pub use self::circuit_tag::*;
 pub use self::ext::*;
+#[cfg(feature = "async")]
+pub use self::future::*;
 pub use self::generator::*;
+#[cfg(feature = "lagrange")]
+pub use self::lagrange::*;
 pub use self::mapped_params::*;
+pub use self::mpc::*;
+pub use self::nullifier::*;
 pub use self::params::*;
 pub use self::proof::*;
 pub use self::prover::*;
+pub(crate) use self::prover::{
+    assemble_proof, execute_fft, mul_ct, synthesize_circuit, ProofParts,
+};
+pub use self::qap::*;
+pub use self::same_witness::*;
+pub use self::sigma::*;
+pub use self::tagged::*;
+pub use self::update::*;
 pub use self::verifier::*;
 pub use self::verifying_key::*;
@@ -0,0 +1,509 @@
+//! Groth16 proving with a secret shared witness.
+//!
+//! The party knowing the witness computes the H polynomial and splits the witness into
+//! Shamir shares of degree one, so that any two of the `n` parties can complete a
+//! proof while a single party learns nothing about the witness. Every remaining step of
+//! the prover is linear in the witness, so each party runs the multiexps on its own
+//! share ([`MPCProver::prove_share`]) and the proof is the combination of two or more
+//! [`ProofShare`]s with their Lagrange coefficients ([`MPCProver::combine`]).
+//!
+//! The randomizers `r` and `s`, and their products with the witness, are shared along
+//! with it, so neither the parties nor the combiner see unblinded parts of the proof.
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::RngCore;
+
+use super::{execute_fft, mul_ct, synthesize_circuit, ConditionalSwap, ParameterSource, Proof};
+use crate::bls::Engine;
+use crate::domain::{DomainPrecompute, FftScratch};
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, DensityTracker, FullDensity};
+use crate::{Circuit, SynthesisError};
+
+type Exponents<E> = Arc<Vec<<<E as ff::ScalarEngine>::Fr as PrimeField>::Repr>>;
+
+/// A share of the witness of a circuit, as created by [`MPCProver::split`].
+#[derive(Clone)]
+pub struct WitnessShare<E: Engine> {
+    /// The point the share is evaluated at, the secret is at zero.
+    point: E::Fr,
+
+    input_assignment: Exponents<E>,
+    aux_assignment: Exponents<E>,
+    // The assignments multiplied by `s` and `r`, for the `s * A` and `r * B` terms of C.
+    s_input_assignment: Exponents<E>,
+    s_aux_assignment: Exponents<E>,
+    r_input_assignment: Exponents<E>,
+    r_aux_assignment: Exponents<E>,
+    h: Exponents<E>,
+
+    r: E::Fr,
+    s: E::Fr,
+    rs: E::Fr,
+
+    // The densities only depend on the circuit, they are the same for all shares.
+    a_aux_density: Arc<DensityTracker>,
+    b_input_density: Arc<DensityTracker>,
+    b_aux_density: Arc<DensityTracker>,
+}
+
+/// The part of a proof computed from a [`WitnessShare`].
+#[derive(Clone, Debug)]
+pub struct ProofShare<E: Engine> {
+    point: E::Fr,
+    a: E::G1,
+    b: E::G2,
+    c: E::G1,
+}
+
+/// Threshold prover, where any two of the parties holding shares of the witness can
+/// create a proof.
+pub struct MPCProver<E: Engine>(PhantomData<E>);
+
+impl<E: Engine> MPCProver<E> {
+    /// Synthesizes `circuit` and splits its witness into `num_parties` shares.
+    pub fn split<C, R>(
+        circuit: C,
+        num_parties: usize,
+        rng: &mut R,
+    ) -> Result<Vec<WitnessShare<E>>, SynthesisError>
+    where
+        C: Circuit<E>,
+        R: RngCore,
+    {
+        assert!(num_parties >= 2, "at least two parties are needed");
+
+        let worker = Worker::new();
+        let mut prover = synthesize_circuit::<E, C>(circuit)?;
+
+        let precompute = DomainPrecompute::new(prover.a.len())?;
+        let h = execute_fft(
+            &worker,
+            &mut prover,
+            &precompute,
+            &mut None,
+            &mut FftScratch::new(),
+        )?
+        .iter()
+        .map(|repr| E::Fr::from_repr(*repr).expect("the FFT output is reduced"))
+        .collect::<Vec<_>>();
+
+        let r = E::Fr::random(rng);
+        let s = E::Fr::random(rng);
+        let mut rs = r;
+        rs.mul_assign(&s);
+
+        let scaled = |values: &[E::Fr], by: &E::Fr| -> Vec<E::Fr> {
+            values
+                .iter()
+                .map(|v| {
+                    let mut v = *v;
+                    v.mul_assign(by);
+                    v
+                })
+                .collect()
+        };
+        let input = &prover.input_assignment;
+        let aux = &prover.aux_assignment;
+
+        let points = (1..=num_parties as u64)
+            .map(|i| E::Fr::from_repr(i.into()).expect("small values are valid"))
+            .collect::<Vec<_>>();
+
+        let mut input_assignment = share_vector::<E, _>(input, &points, rng).into_iter();
+        let mut aux_assignment = share_vector::<E, _>(aux, &points, rng).into_iter();
+        let mut s_input_assignment =
+            share_vector::<E, _>(&scaled(input, &s), &points, rng).into_iter();
+        let mut s_aux_assignment = share_vector::<E, _>(&scaled(aux, &s), &points, rng).into_iter();
+        let mut r_input_assignment =
+            share_vector::<E, _>(&scaled(input, &r), &points, rng).into_iter();
+        let mut r_aux_assignment = share_vector::<E, _>(&scaled(aux, &r), &points, rng).into_iter();
+        let mut h = share_vector::<E, _>(&h, &points, rng).into_iter();
+        let mut randomizers = share_vector::<E, _>(&[r, s, rs], &points, rng).into_iter();
+
+        let a_aux_density = Arc::new(prover.a_aux_density);
+        let b_input_density = Arc::new(prover.b_input_density);
+        let b_aux_density = Arc::new(prover.b_aux_density);
+
+        Ok(points
+            .into_iter()
+            .map(|point| {
+                let randomizers = randomizers.next().unwrap();
+                let from_repr = |repr| E::Fr::from_repr(repr).expect("shares are reduced");
+
+                WitnessShare {
+                    point,
+                    input_assignment: input_assignment.next().unwrap(),
+                    aux_assignment: aux_assignment.next().unwrap(),
+                    s_input_assignment: s_input_assignment.next().unwrap(),
+                    s_aux_assignment: s_aux_assignment.next().unwrap(),
+                    r_input_assignment: r_input_assignment.next().unwrap(),
+                    r_aux_assignment: r_aux_assignment.next().unwrap(),
+                    h: h.next().unwrap(),
+                    r: from_repr(randomizers[0]),
+                    s: from_repr(randomizers[1]),
+                    rs: from_repr(randomizers[2]),
+                    a_aux_density: a_aux_density.clone(),
+                    b_input_density: b_input_density.clone(),
+                    b_aux_density: b_aux_density.clone(),
+                }
+            })
+            .collect())
+    }
+
+    /// Computes the part of the proof belonging to `share`, without any interaction
+    /// with the other parties.
+    pub fn prove_share<P: ParameterSource<E>>(
+        share: WitnessShare<E>,
+        params: P,
+    ) -> Result<ProofShare<E>, SynthesisError>
+    where
+        E::G1: ConditionalSwap,
+        E::G2: ConditionalSwap,
+    {
+        let worker = Worker::new();
+        let vk = params.get_vk(share.input_assignment.len())?;
+
+        if vk.delta_g1.is_zero() || vk.delta_g2.is_zero() {
+            // If this element is zero, someone is trying to perform a
+            // subversion-CRS attack.
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        let h = multiexp(
+            &worker,
+            params.get_h(share.h.len())?,
+            FullDensity,
+            share.h.clone(),
+            &mut None,
+        );
+        let l = multiexp(
+            &worker,
+            params.get_l(share.aux_assignment.len())?,
+            FullDensity,
+            share.aux_assignment.clone(),
+            &mut None,
+        );
+
+        let (a_inputs_source, a_aux_source) = params.get_a(
+            share.input_assignment.len(),
+            share.a_aux_density.get_total_density(),
+        )?;
+        let a_inputs = multiexp(
+            &worker,
+            a_inputs_source.clone(),
+            FullDensity,
+            share.input_assignment.clone(),
+            &mut None,
+        );
+        let a_aux = multiexp(
+            &worker,
+            a_aux_source.clone(),
+            share.a_aux_density.clone(),
+            share.aux_assignment.clone(),
+            &mut None,
+        );
+        let s_a_inputs = multiexp(
+            &worker,
+            a_inputs_source,
+            FullDensity,
+            share.s_input_assignment.clone(),
+            &mut None,
+        );
+        let s_a_aux = multiexp(
+            &worker,
+            a_aux_source,
+            share.a_aux_density.clone(),
+            share.s_aux_assignment.clone(),
+            &mut None,
+        );
+
+        let b_input_density_total = share.b_input_density.get_total_density();
+        let b_aux_density_total = share.b_aux_density.get_total_density();
+
+        let (b_g1_inputs_source, b_g1_aux_source) =
+            params.get_b_g1(b_input_density_total, b_aux_density_total)?;
+        let r_b_g1_inputs = multiexp(
+            &worker,
+            b_g1_inputs_source,
+            share.b_input_density.clone(),
+            share.r_input_assignment.clone(),
+            &mut None,
+        );
+        let r_b_g1_aux = multiexp(
+            &worker,
+            b_g1_aux_source,
+            share.b_aux_density.clone(),
+            share.r_aux_assignment.clone(),
+            &mut None,
+        );
+
+        let (b_g2_inputs_source, b_g2_aux_source) =
+            params.get_b_g2(b_input_density_total, b_aux_density_total)?;
+        let b_g2_inputs = multiexp(
+            &worker,
+            b_g2_inputs_source,
+            share.b_input_density.clone(),
+            share.input_assignment.clone(),
+            &mut None,
+        );
+        let b_g2_aux = multiexp(
+            &worker,
+            b_g2_aux_source,
+            share.b_aux_density.clone(),
+            share.aux_assignment.clone(),
+            &mut None,
+        );
+
+        // The constants alpha and beta are added by every party, as the Lagrange
+        // coefficients sum up to one.
+        let mut a = mul_ct(vk.delta_g1.into_projective(), share.r);
+        a.add_assign_mixed(&vk.alpha_g1);
+        a.add_assign(&a_inputs.wait()?);
+        a.add_assign(&a_aux.wait()?);
+
+        let mut b = mul_ct(vk.delta_g2.into_projective(), share.s);
+        b.add_assign_mixed(&vk.beta_g2);
+        b.add_assign(&b_g2_inputs.wait()?);
+        b.add_assign(&b_g2_aux.wait()?);
+
+        // C = h + l + s * A + r * B - r * s * delta, expanded into terms linear in
+        // the shares.
+        let mut c = mul_ct(vk.delta_g1.into_projective(), share.rs);
+        c.add_assign(&mul_ct(vk.alpha_g1.into_projective(), share.s));
+        c.add_assign(&mul_ct(vk.beta_g1.into_projective(), share.r));
+        c.add_assign(&s_a_inputs.wait()?);
+        c.add_assign(&s_a_aux.wait()?);
+        c.add_assign(&r_b_g1_inputs.wait()?);
+        c.add_assign(&r_b_g1_aux.wait()?);
+        c.add_assign(&h.wait()?);
+        c.add_assign(&l.wait()?);
+
+        Ok(ProofShare {
+            point: share.point,
+            a,
+            b,
+            c,
+        })
+    }
+
+    /// Combines the proof shares of at least two different parties into a proof.
+    ///
+    /// Fails with `SynthesisError::AssignmentMissing` for fewer than two shares and
+    /// `SynthesisError::DivisionByZero` if a share is given twice.
+    pub fn combine(shares: &[ProofShare<E>]) -> Result<Proof<E>, SynthesisError> {
+        if shares.len() < 2 {
+            return Err(SynthesisError::AssignmentMissing);
+        }
+
+        let mut a = E::G1::zero();
+        let mut b = E::G2::zero();
+        let mut c = E::G1::zero();
+        for (j, share) in shares.iter().enumerate() {
+            // Lagrange coefficient of the share for evaluating at zero.
+            let mut numerator = E::Fr::one();
+            let mut denominator = E::Fr::one();
+            for (m, other) in shares.iter().enumerate() {
+                if m != j {
+                    numerator.mul_assign(&other.point);
+                    let mut diff = other.point;
+                    diff.sub_assign(&share.point);
+                    denominator.mul_assign(&diff);
+                }
+            }
+            let mut lambda = denominator
+                .inverse()
+                .ok_or(SynthesisError::DivisionByZero)?;
+            lambda.mul_assign(&numerator);
+
+            let mut tmp = share.a;
+            tmp.mul_assign(lambda);
+            a.add_assign(&tmp);
+            let mut tmp = share.b;
+            tmp.mul_assign(lambda);
+            b.add_assign(&tmp);
+            let mut tmp = share.c;
+            tmp.mul_assign(lambda);
+            c.add_assign(&tmp);
+        }
+
+        Ok(Proof::new(
+            a.into_affine(),
+            b.into_affine(),
+            c.into_affine(),
+        ))
+    }
+}
+
+/// Shares every element of `secrets` with a random polynomial of degree one, which is
+/// evaluated at each of `points`.
+fn share_vector<E: Engine, R: RngCore>(
+    secrets: &[E::Fr],
+    points: &[E::Fr],
+    rng: &mut R,
+) -> Vec<Exponents<E>> {
+    let mut shares = (0..points.len())
+        .map(|_| Vec::with_capacity(secrets.len()))
+        .collect::<Vec<_>>();
+    for secret in secrets {
+        let slope = E::Fr::random(rng);
+        for (share, point) in shares.iter_mut().zip(points) {
+            let mut value = slope;
+            value.mul_assign(point);
+            value.add_assign(secret);
+            share.push(value.into_repr());
+        }
+    }
+
+    shares.into_iter().map(Arc::new).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters};
+    use crate::groth16::{prepare_verifying_key, verify_proof};
+    use crate::ConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Proves knowledge of `x` with `x^(2^rounds) = y` for the public input `y`.
+    #[derive(Clone)]
+    struct Squaring {
+        x: Option<Fr>,
+        rounds: usize,
+    }
+
+    impl Circuit<Bls12> for Squaring {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut value = self.x;
+            let mut var = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+            for i in 0..self.rounds {
+                let next_value = value.map(|mut v| {
+                    v.square();
+                    v
+                });
+                let next = if i + 1 == self.rounds {
+                    cs.alloc_input(
+                        || "y",
+                        || next_value.ok_or(SynthesisError::AssignmentMissing),
+                    )?
+                } else {
+                    cs.alloc(
+                        || format!("x^2^{}", i + 1),
+                        || next_value.ok_or(SynthesisError::AssignmentMissing),
+                    )?
+                };
+                cs.enforce(
+                    || format!("squaring {}", i),
+                    |lc| lc + var,
+                    |lc| lc + var,
+                    |lc| lc + next,
+                );
+
+                value = next_value;
+                var = next;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn image(x: Fr, rounds: usize) -> Fr {
+        let mut y = x;
+        for _ in 0..rounds {
+            y.square();
+        }
+        y
+    }
+
+    #[test]
+    fn test_mpc_proof_2_of_2() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = generate_random_parameters::<Bls12, _, _>(
+            Squaring {
+                x: None,
+                rounds: 10,
+            },
+            rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let x = Fr::random(rng);
+        let y = image(x, 10);
+        let circuit = Squaring {
+            x: Some(x),
+            rounds: 10,
+        };
+
+        let shares = MPCProver::split(circuit.clone(), 2, rng).unwrap();
+        assert_eq!(shares.len(), 2);
+        let proof_shares = shares
+            .into_iter()
+            .map(|share| MPCProver::prove_share(share, &params).unwrap())
+            .collect::<Vec<_>>();
+
+        let proof = MPCProver::combine(&proof_shares).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[x]).unwrap());
+
+        // A single share is not enough, and the randomized proof differs from a regular one.
+        assert!(MPCProver::combine(&proof_shares[..1]).is_err());
+        assert!(MPCProver::combine(&[proof_shares[0].clone(), proof_shares[0].clone()]).is_err());
+        let regular = create_random_proof(circuit, &params, rng).unwrap();
+        assert!(verify_proof(&pvk, &regular, &[y]).unwrap());
+        assert!(regular != proof);
+    }
+
+    #[test]
+    fn test_mpc_proof_any_2_of_3() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params =
+            generate_random_parameters::<Bls12, _, _>(Squaring { x: None, rounds: 4 }, rng)
+                .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let x = Fr::random(rng);
+        let shares = MPCProver::split(
+            Squaring {
+                x: Some(x),
+                rounds: 4,
+            },
+            3,
+            rng,
+        )
+        .unwrap();
+        let proof_shares = shares
+            .into_iter()
+            .map(|share| MPCProver::prove_share(share, &params).unwrap())
+            .collect::<Vec<_>>();
+
+        let y = image(x, 4);
+        for &(i, j) in &[(0, 1), (0, 2), (1, 2)] {
+            let pair = [proof_shares[i].clone(), proof_shares[j].clone()];
+            let proof = MPCProver::combine(&pair).unwrap();
+            assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+        }
+        let proof = MPCProver::combine(&proof_shares).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+    }
+}
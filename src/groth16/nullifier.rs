@@ -0,0 +1,63 @@
+use ff::{PrimeField, PrimeFieldRepr};
+
+use super::Proof;
+use crate::bls::Engine;
+use crate::poseidon::{domain_tag, PoseidonConstants};
+
+/// Domain of the Poseidon hashes computing nullifiers.
+pub const NULLIFIER_DOMAIN: &[u8] = b"bellperson-groth16-nullifier";
+
+/// The number of field elements absorbed per permutation.
+const NULLIFIER_ARITY: usize = 4;
+
+/// A commitment to `proof` in `context`, for rejecting proofs that were already
+/// submitted.
+///
+/// The nullifier is the Poseidon hash of the compressed encoding of `proof` followed by
+/// `context`, packed into field elements, with a tag of [`NULLIFIER_DOMAIN`] and the
+/// length in the capacity. The encoding of a valid proof is canonical, so submitting
+/// the same proof twice in the same context gives the same nullifier, which a verifier
+/// keeping a set of the nullifiers it accepted rejects. `context` should name where the
+/// proof is accepted, e.g. the application and the verifier, so that the nullifiers of
+/// different verifiers are unrelated.
+///
+/// # Security model
+///
+/// The nullifier prevents replaying the same bytes, not proving the same statement
+/// again: Groth16 proofs are malleable, and anyone holding a proof can rerandomize it
+/// into a different valid proof of the same statement without knowing the witness,
+/// which has a different nullifier. Applications that must accept a statement at most
+/// once should derive the nullifier inside the circuit from a secret of the witness,
+/// or from the public inputs. A proof cannot have its own nullifier as a public input,
+/// as the nullifier depends on the proof; it can be an input of a later proof or
+/// message that refers to this one.
+pub fn nullifier_commitment<E: Engine>(proof: &Proof<E>, context: &[u8]) -> E::Fr {
+    let mut bytes = Vec::with_capacity(Proof::<E>::size() + context.len());
+    proof
+        .write(&mut bytes)
+        .expect("writing to a vec never fails");
+    bytes.extend_from_slice(context);
+
+    let preimage = pack_bytes::<E::Fr>(&bytes);
+    let tag = domain_tag::<E>(NULLIFIER_DOMAIN, bytes.len());
+
+    PoseidonConstants::<E>::new(NULLIFIER_ARITY).hash(tag, &preimage)
+}
+
+/// Packs `bytes` into field elements, as many little endian bytes per element as fit
+/// below the modulus. The last element is padded with zeros.
+fn pack_bytes<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+    let chunk_size = F::CAPACITY as usize / 8;
+
+    bytes
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let mut repr = F::Repr::default();
+            let mut padded = vec![0u8; repr.as_ref().len() * 8];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            repr.read_le(&padded[..])
+                .expect("the buffer has the size of the representation");
+            F::from_repr(repr).expect("the chunk is smaller than the modulus")
+        })
+        .collect()
+}
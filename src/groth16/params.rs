@@ -13,11 +13,10 @@ use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use super::{MappedParameters, VerifyingKey};
+use super::{AnyCircuit, CircuitTag, MappedParameters, VerifyingKey};
 
-#[derive(Clone)]
-pub struct Parameters<E: Engine> {
-    pub vk: VerifyingKey<E>,
+pub struct Parameters<E: Engine, C: CircuitTag = AnyCircuit> {
+    pub vk: VerifyingKey<E, C>,
 
     // Elements of the form ((tau^i * t(tau)) / delta) for i between 0 and
     // m-2 inclusive. Never contains points at infinity.
@@ -40,7 +39,20 @@ pub struct Parameters<E: Engine> {
     pub b_g2: Arc<Vec<E::G2Affine>>,
 }
 
-impl<E: Engine> PartialEq for Parameters<E> {
+impl<E: Engine, C: CircuitTag> Clone for Parameters<E, C> {
+    fn clone(&self) -> Self {
+        Parameters {
+            vk: self.vk.clone(),
+            h: self.h.clone(),
+            l: self.l.clone(),
+            a: self.a.clone(),
+            b_g1: self.b_g1.clone(),
+            b_g2: self.b_g2.clone(),
+        }
+    }
+}
+
+impl<E: Engine, C: CircuitTag> PartialEq for Parameters<E, C> {
     fn eq(&self, other: &Self) -> bool {
         self.vk == other.vk
             && self.h == other.h
@@ -51,7 +63,7 @@ impl<E: Engine> PartialEq for Parameters<E> {
     }
 }
 
-impl<E: Engine> Parameters<E> {
+impl<E: Engine, C: CircuitTag> Parameters<E, C> {
     pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         self.vk.write(&mut writer)?;
 
@@ -83,6 +95,33 @@ impl<E: Engine> Parameters<E> {
         Ok(())
     }
 
+    /// Forgets the circuit of these parameters.
+    pub fn into_any(self) -> Parameters<E> {
+        Parameters {
+            vk: self.vk.into_any(),
+            h: self.h,
+            l: self.l,
+            a: self.a,
+            b_g1: self.b_g1,
+            b_g2: self.b_g2,
+        }
+    }
+}
+
+impl<E: Engine> Parameters<E> {
+    /// Asserts that these parameters belong to the circuit `C`, e.g. after generating
+    /// or reading them.
+    pub fn for_circuit<C: CircuitTag>(self) -> Parameters<E, C> {
+        Parameters {
+            vk: self.vk.for_circuit(),
+            h: self.h,
+            l: self.l,
+            a: self.a,
+            b_g1: self.b_g1,
+            b_g2: self.b_g2,
+        }
+    }
+
     // Quickly iterates through the parameter file, recording all
     // parameter offsets and caches the verifying key (vk) for quick
     // access via reference.
@@ -144,7 +183,7 @@ impl<E: Engine> Parameters<E> {
         Ok(MappedParameters {
             param_file_path,
             param_file,
-            params,
+            params: Arc::new(params),
             vk,
             pvk,
             h,
@@ -382,8 +421,10 @@ impl<E: Engine> Parameters<E> {
 pub trait ParameterSource<E: Engine>: Send + Sync {
     type G1Builder: SourceBuilder<E::G1Affine>;
     type G2Builder: SourceBuilder<E::G2Affine>;
+    /// The circuit of the proofs created with these parameters.
+    type Tag: CircuitTag;
 
-    fn get_vk(&self, num_ic: usize) -> Result<&VerifyingKey<E>, SynthesisError>;
+    fn get_vk(&self, num_ic: usize) -> Result<&VerifyingKey<E, Self::Tag>, SynthesisError>;
     fn get_h(&self, num_h: usize) -> Result<Self::G1Builder, SynthesisError>;
     fn get_l(&self, num_l: usize) -> Result<Self::G1Builder, SynthesisError>;
     fn get_a(
@@ -403,11 +444,12 @@ pub trait ParameterSource<E: Engine>: Send + Sync {
     ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError>;
 }
 
-impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
+impl<'a, E: Engine, C: CircuitTag> ParameterSource<E> for &'a Parameters<E, C> {
     type G1Builder = (Arc<Vec<E::G1Affine>>, usize);
     type G2Builder = (Arc<Vec<E::G2Affine>>, usize);
+    type Tag = C;
 
-    fn get_vk(&self, _: usize) -> Result<&VerifyingKey<E>, SynthesisError> {
+    fn get_vk(&self, _: usize) -> Result<&VerifyingKey<E, C>, SynthesisError> {
         Ok(&self.vk)
     }
 
@@ -1,23 +1,59 @@
+use std::fmt;
 use std::io::{self, Read, Write};
+use std::marker::PhantomData;
 
 use groupy::{CurveAffine, EncodedPoint};
 
+use super::{AnyCircuit, CircuitTag};
 use crate::bls::Engine;
 
-#[derive(Clone, Debug)]
-pub struct Proof<E: Engine> {
+const FINGERPRINT_PERSONALIZATION: &[u8; 8] = b"BPPRFP00";
+
+pub struct Proof<E: Engine, C: CircuitTag = AnyCircuit> {
     pub a: E::G1Affine,
     pub b: E::G2Affine,
     pub c: E::G1Affine,
+    /// The circuit of the proof, see [`CircuitTag`].
+    pub circuit: PhantomData<fn() -> C>,
+}
+
+impl<E: Engine, C: CircuitTag> Clone for Proof<E, C> {
+    fn clone(&self) -> Self {
+        Proof {
+            a: self.a,
+            b: self.b,
+            c: self.c,
+            circuit: PhantomData,
+        }
+    }
 }
 
-impl<E: Engine> PartialEq for Proof<E> {
+impl<E: Engine, C: CircuitTag> fmt::Debug for Proof<E, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proof")
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("c", &self.c)
+            .finish()
+    }
+}
+
+impl<E: Engine, C: CircuitTag> PartialEq for Proof<E, C> {
     fn eq(&self, other: &Self) -> bool {
         self.a == other.a && self.b == other.b && self.c == other.c
     }
 }
 
-impl<E: Engine> Proof<E> {
+impl<E: Engine, C: CircuitTag> Proof<E, C> {
+    pub fn new(a: E::G1Affine, b: E::G2Affine, c: E::G1Affine) -> Self {
+        Proof {
+            a,
+            b,
+            c,
+            circuit: PhantomData,
+        }
+    }
+
     pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         writer.write_all(self.a.into_compressed().as_ref())?;
         writer.write_all(self.b.into_compressed().as_ref())?;
@@ -26,6 +62,35 @@ impl<E: Engine> Proof<E> {
         Ok(())
     }
 
+    /// The BLAKE2s hash of the serialization of this proof, identifying it e.g. in
+    /// caches of verification results.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut bytes = Vec::with_capacity(Proof::<E>::size());
+        self.write(&mut bytes)
+            .expect("writing to a vec never fails");
+
+        let hash = blake2s_simd::Params::new()
+            .hash_length(32)
+            .personal(FINGERPRINT_PERSONALIZATION)
+            .hash(&bytes);
+
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(hash.as_bytes());
+        fingerprint
+    }
+
+    /// Forgets the circuit of this proof.
+    pub fn into_any(self) -> Proof<E> {
+        Proof::new(self.a, self.b, self.c)
+    }
+}
+
+impl<E: Engine> Proof<E> {
+    /// Asserts that this proof belongs to the circuit `C`, e.g. after reading it.
+    pub fn for_circuit<C: CircuitTag>(self) -> Proof<E, C> {
+        Proof::new(self.a, self.b, self.c)
+    }
+
     pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
         let mut bytes = vec![0u8; Self::size()];
         reader.read_exact(&mut bytes)?;
@@ -41,12 +106,12 @@ impl<E: Engine> Proof<E> {
     }
 
     pub fn read_many(proof_bytes: &[u8], num_proofs: usize) -> io::Result<Vec<Self>> {
-        use crate::multicore::THREAD_POOL;
+        use crate::multicore::{install_in, THREAD_POOL};
         use rayon::prelude::*;
         debug_assert_eq!(proof_bytes.len(), num_proofs * Self::size());
 
         // Decompress and group check in parallel
-        THREAD_POOL.install(|| {
+        install_in(&THREAD_POOL, || {
             #[derive(Clone, Copy)]
             enum ProofPart<E: Engine> {
                 A(E::G1Affine),
@@ -136,11 +201,11 @@ impl<E: Engine> Proof<E> {
                 .collect::<io::Result<Vec<_>>>()?;
 
             let mut proofs = vec![
-                Proof::<E> {
-                    a: <E::G1Affine as CurveAffine>::zero(),
-                    b: <E::G2Affine as CurveAffine>::zero(),
-                    c: <E::G1Affine as CurveAffine>::zero(),
-                };
+                Proof::<E>::new(
+                    <E::G1Affine as CurveAffine>::zero(),
+                    <E::G2Affine as CurveAffine>::zero(),
+                    <E::G1Affine as CurveAffine>::zero(),
+                );
                 num_proofs
             ];
 
@@ -247,7 +312,7 @@ mod test_with_bls12_381 {
             assert!(params == de_params);
         }
 
-        let pvk = prepare_verifying_key::<Bls12>(&params.vk);
+        let pvk = prepare_verifying_key(&params.vk);
 
         for _ in 0..100 {
             let a = Fr::random(rng);
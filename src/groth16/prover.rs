@@ -7,11 +7,12 @@ use groupy::{CurveAffine, CurveProjective};
 use rand_core::RngCore;
 use rayon::prelude::*;
 
-use super::{ParameterSource, Proof};
-use crate::domain::{EvaluationDomain, Scalar};
-use crate::gpu::{LockedFFTKernel, LockedMultiexpKernel};
-use crate::multicore::{Worker, THREAD_POOL};
+use super::{CircuitTag, ParameterSource, Proof, VerifyingKey};
+use crate::domain::{DomainPrecompute, EvaluationDomain, FftScratch, Scalar};
+use crate::gpu::{GPUKernels, LockedFFTKernel, LockedMultiexpKernel};
+use crate::multicore::{Waiter, Worker};
 use crate::multiexp::{multiexp, DensityTracker, FullDensity};
+use crate::r1cs::R1CSMatrixCSR;
 use crate::{
     Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable, BELLMAN_VERSION,
 };
@@ -20,58 +21,29 @@ use log::info;
 #[cfg(feature = "gpu")]
 use crate::gpu::PriorityLock;
 
-fn eval<E: Engine>(
-    lc: &LinearCombination<E>,
-    mut input_density: Option<&mut DensityTracker>,
-    mut aux_density: Option<&mut DensityTracker>,
-    input_assignment: &[E::Fr],
-    aux_assignment: &[E::Fr],
-) -> E::Fr {
-    let mut acc = E::Fr::zero();
-
-    for (&index, &coeff) in lc.0.iter() {
-        let mut tmp;
-
-        match index {
-            Variable(Index::Input(i)) => {
-                tmp = input_assignment[i];
-                if let Some(ref mut v) = input_density {
-                    v.inc(i);
-                }
-            }
-            Variable(Index::Aux(i)) => {
-                tmp = aux_assignment[i];
-                if let Some(ref mut v) = aux_density {
-                    v.inc(i);
-                }
-            }
-        }
-
-        if coeff == E::Fr::one() {
-            acc.add_assign(&tmp);
-        } else {
-            tmp.mul_assign(&coeff);
-            acc.add_assign(&tmp);
-        }
-    }
-
-    acc
-}
+/// The number of constraints after which `ProvingAssignment::enforce` evaluates them,
+/// bounding the memory of the matrices holding them in the meantime.
+const FINALIZE_ROWS: usize = 1 << 14;
 
-struct ProvingAssignment<E: Engine> {
+pub(crate) struct ProvingAssignment<E: Engine> {
     // Density of queries
-    a_aux_density: DensityTracker,
-    b_input_density: DensityTracker,
-    b_aux_density: DensityTracker,
+    pub(crate) a_aux_density: DensityTracker,
+    pub(crate) b_input_density: DensityTracker,
+    pub(crate) b_aux_density: DensityTracker,
+
+    // Constraints enforced since the last `finalize_circuit`, less than `FINALIZE_ROWS`
+    a_matrix: R1CSMatrixCSR<E>,
+    b_matrix: R1CSMatrixCSR<E>,
+    c_matrix: R1CSMatrixCSR<E>,
 
     // Evaluations of A, B, C polynomials
-    a: Vec<Scalar<E>>,
-    b: Vec<Scalar<E>>,
-    c: Vec<Scalar<E>>,
+    pub(crate) a: Vec<Scalar<E>>,
+    pub(crate) b: Vec<Scalar<E>>,
+    pub(crate) c: Vec<Scalar<E>>,
 
     // Assignments of variables
-    input_assignment: Vec<E::Fr>,
-    aux_assignment: Vec<E::Fr>,
+    pub(crate) input_assignment: Vec<E::Fr>,
+    pub(crate) aux_assignment: Vec<E::Fr>,
 }
 use std::fmt;
 
@@ -81,6 +53,9 @@ impl<E: Engine> fmt::Debug for ProvingAssignment<E> {
             .field("a_aux_density", &self.a_aux_density)
             .field("b_input_density", &self.b_input_density)
             .field("b_aux_density", &self.b_aux_density)
+            .field("a_matrix", &self.a_matrix)
+            .field("b_matrix", &self.b_matrix)
+            .field("c_matrix", &self.c_matrix)
             .field(
                 "a",
                 &self
@@ -116,6 +91,9 @@ impl<E: Engine> PartialEq for ProvingAssignment<E> {
         self.a_aux_density == other.a_aux_density
             && self.b_input_density == other.b_input_density
             && self.b_aux_density == other.b_aux_density
+            && self.a_matrix == other.a_matrix
+            && self.b_matrix == other.b_matrix
+            && self.c_matrix == other.c_matrix
             && self.a == other.a
             && self.b == other.b
             && self.c == other.c
@@ -132,6 +110,9 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
             a_aux_density: DensityTracker::new(),
             b_input_density: DensityTracker::new(),
             b_aux_density: DensityTracker::new(),
+            a_matrix: R1CSMatrixCSR::new(),
+            b_matrix: R1CSMatrixCSR::new(),
+            c_matrix: R1CSMatrixCSR::new(),
             a: vec![],
             b: vec![],
             c: vec![],
@@ -173,38 +154,13 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
         LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
         LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
     {
-        let a = a(LinearCombination::zero());
-        let b = b(LinearCombination::zero());
-        let c = c(LinearCombination::zero());
-
-        self.a.push(Scalar(eval(
-            &a,
-            // Inputs have full density in the A query
-            // because there are constraints of the
-            // form x * 0 = 0 for each input.
-            None,
-            Some(&mut self.a_aux_density),
-            &self.input_assignment,
-            &self.aux_assignment,
-        )));
-        self.b.push(Scalar(eval(
-            &b,
-            Some(&mut self.b_input_density),
-            Some(&mut self.b_aux_density),
-            &self.input_assignment,
-            &self.aux_assignment,
-        )));
-        self.c.push(Scalar(eval(
-            &c,
-            // There is no C polynomial query,
-            // though there is an (beta)A + (alpha)B + C
-            // query for all aux variables.
-            // However, that query has full density.
-            None,
-            None,
-            &self.input_assignment,
-            &self.aux_assignment,
-        )));
+        self.a_matrix.push_row(&a(LinearCombination::zero()));
+        self.b_matrix.push_row(&b(LinearCombination::zero()));
+        self.c_matrix.push_row(&c(LinearCombination::zero()));
+
+        if self.a_matrix.num_rows() >= FINALIZE_ROWS {
+            self.finalize_circuit();
+        }
     }
 
     fn push_namespace<NR, N>(&mut self, _: N)
@@ -227,7 +183,10 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
         true
     }
 
-    fn extend(&mut self, other: Self) {
+    fn extend(&mut self, mut other: Self) {
+        self.finalize_circuit();
+        other.finalize_circuit();
+
         self.a_aux_density.extend(other.a_aux_density, false);
         self.b_input_density.extend(other.b_input_density, true);
         self.b_aux_density.extend(other.b_aux_density, false);
@@ -243,16 +202,163 @@ impl<E: Engine> ConstraintSystem<E> for ProvingAssignment<E> {
     }
 }
 
+impl<E: Engine> ProvingAssignment<E> {
+    /// Evaluates the constraints enforced since the last call, appending their values
+    /// to the evaluations of the A, B and C polynomials and marking the variables they
+    /// use in the densities.
+    pub(crate) fn finalize_circuit(&mut self) {
+        let (input_assignment, aux_assignment) = (&self.input_assignment, &self.aux_assignment);
+        let evaluate = |matrix: &R1CSMatrixCSR<E>| {
+            matrix
+                .evaluate(input_assignment, aux_assignment)
+                .into_iter()
+                .map(Scalar)
+        };
+        self.a.extend(evaluate(&self.a_matrix));
+        self.b.extend(evaluate(&self.b_matrix));
+        self.c.extend(evaluate(&self.c_matrix));
+
+        // Inputs have full density in the A query
+        // because there are constraints of the
+        // form x * 0 = 0 for each input.
+        self.a_matrix
+            .mark_density(None, Some(&mut self.a_aux_density));
+        self.b_matrix.mark_density(
+            Some(&mut self.b_input_density),
+            Some(&mut self.b_aux_density),
+        );
+        // There is no C polynomial query,
+        // though there is an (beta)A + (alpha)B + C
+        // query for all aux variables.
+        // However, that query has full density.
+
+        self.a_matrix.clear();
+        self.b_matrix.clear();
+        self.c_matrix.clear();
+    }
+}
+
+/// Synthesizes a circuit for proving, including the constraints binding the inputs.
+pub(crate) fn synthesize_circuit<E, C>(circuit: C) -> Result<ProvingAssignment<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut prover = ProvingAssignment::new();
+
+    prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
+
+    circuit.synthesize(&mut prover)?;
+
+    for i in 0..prover.input_assignment.len() {
+        prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
+    }
+    prover.finalize_circuit();
+
+    Ok(prover)
+}
+
+/// The basis of the H query of the parameters a proof is created with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HBasis {
+    /// `tau^i * t(tau) / delta`, for the coefficients of H.
+    Monomial,
+    /// `L_i(tau) * t(tau) / delta`, for the Lagrange polynomials `L_i` of the coset
+    /// the prover evaluates H on.
+    #[cfg(feature = "lagrange")]
+    Lagrange,
+}
+
+/// Computes `A * B - C` on the coset of the domain of a single circuit, consuming its
+/// A, B and C evaluations.
+fn coset_evaluations<E: Engine>(
+    worker: &Worker,
+    prover: &mut ProvingAssignment<E>,
+    precompute: &DomainPrecompute<E>,
+    fft_kern: &mut Option<LockedFFTKernel<E>>,
+    scratch: &mut FftScratch<Scalar<E>>,
+) -> Result<EvaluationDomain<E, Scalar<E>>, SynthesisError> {
+    let mut a = EvaluationDomain::from_coeffs_with_precompute(
+        std::mem::replace(&mut prover.a, Vec::new()),
+        precompute,
+    )?;
+    let mut b = EvaluationDomain::from_coeffs_with_precompute(
+        std::mem::replace(&mut prover.b, Vec::new()),
+        precompute,
+    )?;
+    let mut c = EvaluationDomain::from_coeffs_with_precompute(
+        std::mem::replace(&mut prover.c, Vec::new()),
+        precompute,
+    )?;
+
+    a.ifft_with_scratch(worker, fft_kern, scratch)?;
+    a.coset_fft_with_scratch(worker, fft_kern, scratch)?;
+    b.ifft_with_scratch(worker, fft_kern, scratch)?;
+    b.coset_fft_with_scratch(worker, fft_kern, scratch)?;
+    c.ifft_with_scratch(worker, fft_kern, scratch)?;
+    c.coset_fft_with_scratch(worker, fft_kern, scratch)?;
+
+    a.mul_assign(worker, &b);
+    drop(b);
+    a.sub_assign(worker, &c);
+    drop(c);
+
+    Ok(a)
+}
+
+/// Computes the coefficients of the H polynomial of a single circuit, consuming its
+/// A, B and C evaluations.
+pub(crate) fn execute_fft<E: Engine>(
+    worker: &Worker,
+    prover: &mut ProvingAssignment<E>,
+    precompute: &DomainPrecompute<E>,
+    fft_kern: &mut Option<LockedFFTKernel<E>>,
+    scratch: &mut FftScratch<Scalar<E>>,
+) -> Result<Arc<Vec<<E::Fr as PrimeField>::Repr>>, SynthesisError> {
+    let mut a = coset_evaluations(worker, prover, precompute, fft_kern, scratch)?;
+    a.icoset_fft_div_z_with_scratch(worker, fft_kern, scratch)?;
+    let mut a = a.into_coeffs();
+    let a_len = a.len() - 1;
+    a.truncate(a_len);
+
+    Ok(Arc::new(
+        a.into_iter().map(|s| s.0.into_repr()).collect::<Vec<_>>(),
+    ))
+}
+
+/// Like [`execute_fft`], but computes the evaluations of the H polynomial on the coset,
+/// for an H query in the Lagrange basis, which saves the inverse FFT.
+#[cfg(feature = "lagrange")]
+fn execute_coset_fft<E: Engine>(
+    worker: &Worker,
+    prover: &mut ProvingAssignment<E>,
+    precompute: &DomainPrecompute<E>,
+    fft_kern: &mut Option<LockedFFTKernel<E>>,
+    scratch: &mut FftScratch<Scalar<E>>,
+) -> Result<Arc<Vec<<E::Fr as PrimeField>::Repr>>, SynthesisError> {
+    let mut a = coset_evaluations(worker, prover, precompute, fft_kern, scratch)?;
+    a.divide_by_z_on_coset(worker);
+
+    Ok(Arc::new(
+        a.into_coeffs()
+            .into_iter()
+            .map(|s| s.0.into_repr())
+            .collect::<Vec<_>>(),
+    ))
+}
+
 pub fn create_random_proof_batch_priority<E, C, R, P: ParameterSource<E>>(
     circuits: Vec<C>,
     params: P,
     rng: &mut R,
     priority: bool,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
     R: RngCore,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     let r_s = (0..circuits.len()).map(|_| E::Fr::random(rng)).collect();
     let s_s = (0..circuits.len()).map(|_| E::Fr::random(rng)).collect();
@@ -266,14 +372,67 @@ pub fn create_proof_batch_priority<E, C, P: ParameterSource<E>>(
     r_s: Vec<E::Fr>,
     s_s: Vec<E::Fr>,
     priority: bool,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    create_proof_batch_priority_with_worker(circuits, params, r_s, s_s, priority, &Worker::new())
+}
+
+/// Like [`create_proof_batch_priority`], running on the threads of `worker`, e.g. one
+/// created with [`Worker::with_threads`] to share the cores with other provers.
+pub fn create_proof_batch_priority_with_worker<E, C, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    r_s: Vec<E::Fr>,
+    s_s: Vec<E::Fr>,
+    priority: bool,
+    worker: &Worker,
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    create_proof_batch_in_basis(
+        circuits,
+        params,
+        r_s,
+        s_s,
+        priority,
+        worker,
+        HBasis::Monomial,
+    )
+}
+
+/// Like [`create_proof_batch_priority_with_worker`], for parameters whose H query is in
+/// `h_basis`.
+pub(crate) fn create_proof_batch_in_basis<E, C, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    r_s: Vec<E::Fr>,
+    s_s: Vec<E::Fr>,
+    priority: bool,
+    worker: &Worker,
+    h_basis: HBasis,
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     info!("Bellperson {} is being used!", BELLMAN_VERSION);
 
-    THREAD_POOL.install(|| create_proof_batch_priority_inner(circuits, params, r_s, s_s, priority))
+    // Prioritized proofs also take precedence on the CPU, from the synthesis on.
+    let worker = &worker.clone().with_priority(priority || worker.priority());
+    worker.install(|| {
+        create_proof_batch_priority_inner(circuits, params, r_s, s_s, priority, worker, h_basis)
+    })
 }
 
 fn create_proof_batch_priority_inner<E, C, P: ParameterSource<E>>(
@@ -282,33 +441,24 @@ fn create_proof_batch_priority_inner<E, C, P: ParameterSource<E>>(
     r_s: Vec<E::Fr>,
     s_s: Vec<E::Fr>,
     priority: bool,
-) -> Result<Vec<Proof<E>>, SynthesisError>
+    worker: &Worker,
+    h_basis: HBasis,
+) -> Result<Vec<Proof<E, P::Tag>>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E> + Send,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
 {
     let mut provers = circuits
         .into_par_iter()
-        .map(|circuit| -> Result<_, SynthesisError> {
-            let mut prover = ProvingAssignment::new();
-
-            prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
-
-            circuit.synthesize(&mut prover)?;
-
-            for i in 0..prover.input_assignment.len() {
-                prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
-            }
-
-            Ok(prover)
-        })
+        .map(synthesize_circuit::<E, C>)
         .collect::<Result<Vec<_>, _>>()?;
 
     // Start fft/multiexp prover timer
     let start = Instant::now();
     info!("starting proof timer");
 
-    let worker = Worker::new();
     let input_len = provers[0].input_assignment.len();
     let vk = params.get_vk(input_len)?;
     let n = provers[0].a.len();
@@ -329,62 +479,53 @@ where
 
     #[cfg(feature = "gpu")]
     let prio_lock = if priority {
-        Some(PriorityLock::lock())
+        Some(PriorityLock::lock()?)
     } else {
         None
     };
 
-    let mut fft_kern = Some(LockedFFTKernel::<E>::new(log_d, priority));
-
-    let a_s = provers
-        .iter_mut()
-        .map(|prover| {
-            let mut a =
-                EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.a, Vec::new()))?;
-            let mut b =
-                EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.b, Vec::new()))?;
-            let mut c =
-                EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.c, Vec::new()))?;
-
-            a.ifft(&worker, &mut fft_kern)?;
-            a.coset_fft(&worker, &mut fft_kern)?;
-            b.ifft(&worker, &mut fft_kern)?;
-            b.coset_fft(&worker, &mut fft_kern)?;
-            c.ifft(&worker, &mut fft_kern)?;
-            c.coset_fft(&worker, &mut fft_kern)?;
-
-            a.mul_assign(&worker, &b);
-            drop(b);
-            a.sub_assign(&worker, &c);
-            drop(c);
-            a.divide_by_z_on_coset(&worker);
-            a.icoset_fft(&worker, &mut fft_kern)?;
-            let mut a = a.into_coeffs();
-            let a_len = a.len() - 1;
-            a.truncate(a_len);
-
-            Ok(Arc::new(
-                a.into_iter().map(|s| s.0.into_repr()).collect::<Vec<_>>(),
-            ))
-        })
-        .collect::<Result<Vec<_>, SynthesisError>>()?;
-
-    drop(fft_kern);
-    let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, priority));
-
-    let h_s = a_s
-        .into_iter()
-        .map(|a| {
-            let h = multiexp(
-                &worker,
-                params.get_h(a.len())?,
-                FullDensity,
-                a,
-                &mut multiexp_kern,
-            );
-            Ok(h)
-        })
-        .collect::<Result<Vec<_>, SynthesisError>>()?;
+    // Each circuit's FFT output is consumed by its H multiexp before the next circuit's
+    // FFTs start, so only one set of evaluation domains and H exponents is alive at a time.
+    // The FFT and multiexp kernels take turns on the GPU, sharing its lock, so they are
+    // created once for the whole batch.
+    let kernels = GPUKernels::new();
+    let mut fft_kern = Some(LockedFFTKernel::<E>::with_kernels(
+        log_d, priority, &kernels,
+    ));
+    let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::with_kernels(
+        log_d, priority, &kernels,
+    ));
+    let mut h_s = Vec::with_capacity(provers.len());
+    // All circuits have the same size, so their domains share the precomputed values.
+    let mut precompute = None;
+    let mut fft_scratch = FftScratch::new();
+    for prover in provers.iter_mut() {
+        let a = {
+            let precompute = match precompute {
+                Some(ref precompute) => precompute,
+                None => precompute.get_or_insert(DomainPrecompute::new(n)?),
+            };
+            match h_basis {
+                HBasis::Monomial => {
+                    execute_fft(worker, prover, precompute, &mut fft_kern, &mut fft_scratch)?
+                }
+                #[cfg(feature = "lagrange")]
+                HBasis::Lagrange => {
+                    execute_coset_fft(worker, prover, precompute, &mut fft_kern, &mut fft_scratch)?
+                }
+            }
+        };
+
+        let h = multiexp(
+            worker,
+            params.get_h(a.len())?,
+            FullDensity,
+            a,
+            &mut multiexp_kern,
+        );
+        // Wait here, so the exponents are freed before the next circuit is processed.
+        h_s.push(Waiter::done(h.wait()));
+    }
 
     let input_assignments = provers
         .par_iter_mut()
@@ -416,7 +557,7 @@ where
         .iter()
         .map(|aux_assignment| {
             let l = multiexp(
-                &worker,
+                worker,
                 params.get_l(aux_assignment.len())?,
                 FullDensity,
                 aux_assignment.clone(),
@@ -437,7 +578,7 @@ where
                 params.get_a(input_assignment.len(), a_aux_density_total)?;
 
             let a_inputs = multiexp(
-                &worker,
+                worker,
                 a_inputs_source,
                 FullDensity,
                 input_assignment.clone(),
@@ -445,7 +586,7 @@ where
             );
 
             let a_aux = multiexp(
-                &worker,
+                worker,
                 a_aux_source,
                 Arc::new(prover.a_aux_density),
                 aux_assignment.clone(),
@@ -461,7 +602,7 @@ where
                 params.get_b_g1(b_input_density_total, b_aux_density_total)?;
 
             let b_g1_inputs = multiexp(
-                &worker,
+                worker,
                 b_g1_inputs_source,
                 b_input_density.clone(),
                 input_assignment.clone(),
@@ -469,7 +610,7 @@ where
             );
 
             let b_g1_aux = multiexp(
-                &worker,
+                worker,
                 b_g1_aux_source,
                 b_aux_density.clone(),
                 aux_assignment.clone(),
@@ -480,14 +621,14 @@ where
                 params.get_b_g2(b_input_density_total, b_aux_density_total)?;
 
             let b_g2_inputs = multiexp(
-                &worker,
+                worker,
                 b_g2_inputs_source,
                 b_input_density,
                 input_assignment.clone(),
                 &mut multiexp_kern,
             );
             let b_g2_aux = multiexp(
-                &worker,
+                worker,
                 b_g2_aux_source,
                 b_aux_density,
                 aux_assignment.clone(),
@@ -505,7 +646,9 @@ where
         })
         .collect::<Result<Vec<_>, SynthesisError>>()?;
 
+    drop(fft_kern);
     drop(multiexp_kern);
+    drop(kernels);
 
     #[cfg(feature = "gpu")]
     drop(prio_lock);
@@ -521,47 +664,22 @@ where
                 (((h, l), (a_inputs, a_aux, b_g1_inputs, b_g1_aux, b_g2_inputs, b_g2_aux)), r),
                 s,
             )| {
-                if vk.delta_g1.is_zero() || vk.delta_g2.is_zero() {
-                    // If this element is zero, someone is trying to perform a
-                    // subversion-CRS attack.
-                    return Err(SynthesisError::UnexpectedIdentity);
-                }
-
-                let mut g_a = vk.delta_g1.mul(r);
-                g_a.add_assign_mixed(&vk.alpha_g1);
-                let mut g_b = vk.delta_g2.mul(s);
-                g_b.add_assign_mixed(&vk.beta_g2);
-                let mut g_c;
-                {
-                    let mut rs = r;
-                    rs.mul_assign(&s);
-
-                    g_c = vk.delta_g1.mul(rs);
-                    g_c.add_assign(&vk.alpha_g1.mul(s));
-                    g_c.add_assign(&vk.beta_g1.mul(r));
-                }
-                let mut a_answer = a_inputs.wait()?;
-                a_answer.add_assign(&a_aux.wait()?);
-                g_a.add_assign(&a_answer);
-                a_answer.mul_assign(s);
-                g_c.add_assign(&a_answer);
-
-                let mut b1_answer = b_g1_inputs.wait()?;
-                b1_answer.add_assign(&b_g1_aux.wait()?);
-                let mut b2_answer = b_g2_inputs.wait()?;
-                b2_answer.add_assign(&b_g2_aux.wait()?);
-
-                g_b.add_assign(&b2_answer);
-                b1_answer.mul_assign(r);
-                g_c.add_assign(&b1_answer);
-                g_c.add_assign(&h.wait()?);
-                g_c.add_assign(&l.wait()?);
-
-                Ok(Proof {
-                    a: g_a.into_affine(),
-                    b: g_b.into_affine(),
-                    c: g_c.into_affine(),
-                })
+                let mut a = a_inputs.wait()?;
+                a.add_assign(&a_aux.wait()?);
+                let mut b_g1 = b_g1_inputs.wait()?;
+                b_g1.add_assign(&b_g1_aux.wait()?);
+                let mut b_g2 = b_g2_inputs.wait()?;
+                b_g2.add_assign(&b_g2_aux.wait()?);
+
+                let parts = ProofParts {
+                    h: h.wait()?,
+                    l: l.wait()?,
+                    a,
+                    b_g1,
+                    b_g2,
+                };
+
+                assemble_proof(vk, &parts, r, s)
             },
         )
         .collect::<Result<Vec<_>, SynthesisError>>()?;
@@ -572,6 +690,114 @@ where
     Ok(proofs)
 }
 
+/// The multiexps of a proof over the queries of the parameters.
+pub(crate) struct ProofParts<E: Engine> {
+    /// `h(tau) * t(tau) / delta`, over the H query.
+    pub h: E::G1,
+    /// Over the L query, for the auxiliary assignment.
+    pub l: E::G1,
+    /// Over the A query, for the whole assignment.
+    pub a: E::G1,
+    /// Over the B query in G1, for the whole assignment.
+    pub b_g1: E::G1,
+    /// Over the B query in G2, for the whole assignment.
+    pub b_g2: E::G2,
+}
+
+/// Assembles a proof from its multiexps and the randomizers `r` and `s`, as the last
+/// step of proving.
+pub(crate) fn assemble_proof<E: Engine, T: CircuitTag>(
+    vk: &VerifyingKey<E, T>,
+    parts: &ProofParts<E>,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E, T>, SynthesisError>
+where
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    if vk.delta_g1.is_zero() || vk.delta_g2.is_zero() {
+        // If this element is zero, someone is trying to perform a
+        // subversion-CRS attack.
+        return Err(SynthesisError::UnexpectedIdentity);
+    }
+
+    // The randomizers are secret, see the module documentation on side channels.
+    let mut g_a = mul_ct(vk.delta_g1.into_projective(), r);
+    g_a.add_assign_mixed(&vk.alpha_g1);
+    let mut g_b = mul_ct(vk.delta_g2.into_projective(), s);
+    g_b.add_assign_mixed(&vk.beta_g2);
+    let mut g_c;
+    {
+        let mut rs = r;
+        rs.mul_assign(&s);
+
+        g_c = mul_ct(vk.delta_g1.into_projective(), rs);
+        g_c.add_assign(&mul_ct(vk.alpha_g1.into_projective(), s));
+        g_c.add_assign(&mul_ct(vk.beta_g1.into_projective(), r));
+    }
+    g_a.add_assign(&parts.a);
+    g_c.add_assign(&mul_ct(parts.a, s));
+
+    g_b.add_assign(&parts.b_g2);
+    g_c.add_assign(&mul_ct(parts.b_g1, r));
+    g_c.add_assign(&parts.h);
+    g_c.add_assign(&parts.l);
+
+    Ok(Proof::new(
+        g_a.into_affine(),
+        g_b.into_affine(),
+        g_c.into_affine(),
+    ))
+}
+
+/// Multiplies `point` by `scalar` with a Montgomery ladder, which runs the same group
+/// operations and memory accesses for every scalar.
+///
+/// Use this instead of [`CurveProjective::mul_assign`] for secret scalars. The ladder
+/// hides the scalar from timing and cache observations of its control flow, the group
+/// operations themselves are only as constant time as the curve implementation.
+pub(crate) fn mul_ct<G: CurveProjective + ConditionalSwap>(point: G, scalar: G::Scalar) -> G {
+    let repr = scalar.into_repr();
+    let limbs = repr.as_ref();
+
+    let mut r0 = G::zero();
+    let mut r1 = point;
+    let mut swapped = 0u8;
+    for i in (0..<G::Scalar as PrimeField>::NUM_BITS as usize).rev() {
+        let bit = ((limbs[i / 64] >> (i % 64)) & 1) as u8;
+        conditional_swap(&mut r0, &mut r1, bit ^ swapped);
+        swapped = bit;
+
+        r1.add_assign(&r0);
+        r0.double();
+    }
+    conditional_swap(&mut r0, &mut r1, swapped);
+
+    r0
+}
+
+/// Swaps `a` and `b` if `choice` is 1 and leaves them as they are if it is 0, without
+/// branching on `choice`.
+fn conditional_swap<G: ConditionalSwap>(a: &mut G, b: &mut G, choice: u8) {
+    // The volatile read keeps the compiler from turning the mask back into a branch.
+    let mask = unsafe { std::ptr::read_volatile(&0u64.wrapping_sub(choice as u64)) };
+    G::conditional_swap(a, b, mask);
+}
+
+/// Points whose values can be swapped by a mask of all ones or all zeros, touching the
+/// same memory either way.
+///
+/// Proving multiplies points by the secret randomizers with a constant-time ladder,
+/// which swaps its points with this, so the projective points of an engine must
+/// implement it to prove with the engine. The crate implements it for the points of BLS12-381.
+pub trait ConditionalSwap {
+    /// Swaps `a` and `b` if `mask` is all ones, leaves them if it is zero.
+    fn conditional_swap(a: &mut Self, b: &mut Self, mask: u64);
+}
+
+impl_conditional_swap_limbs!(crate::bls::G1Projective, crate::bls::G2Projective);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -581,6 +807,84 @@ mod tests {
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
 
+    #[test]
+    fn test_eval_sparse_and_dense() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let inputs = (0..8).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let aux = (0..8).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        // Cover the empty, single term and multi term sparse cases as well as dense ones.
+        for num_terms in 0..8 {
+            let mut prover = ProvingAssignment::<Bls12>::new();
+            for i in 0..8 {
+                prover.alloc_input(|| "", || Ok(inputs[i])).unwrap();
+                prover.alloc(|| "", || Ok(aux[i])).unwrap();
+            }
+
+            let mut lc = LinearCombination::<Bls12>::zero();
+            let mut expected = Fr::zero();
+            let mut expected_inputs = DensityTracker::new();
+            let mut expected_aux = DensityTracker::new();
+            for _ in 0..8 {
+                expected_inputs.add_element();
+                expected_aux.add_element();
+            }
+
+            for i in 0..num_terms {
+                let coeff = if rng.gen() {
+                    Fr::one()
+                } else {
+                    Fr::random(&mut rng)
+                };
+                let (var, mut value) = if i % 2 == 0 {
+                    expected_inputs.inc(i);
+                    (Variable(Index::Input(i)), inputs[i])
+                } else {
+                    expected_aux.inc(i);
+                    (Variable(Index::Aux(i)), aux[i])
+                };
+                value.mul_assign(&coeff);
+                expected.add_assign(&value);
+                lc = lc + (coeff, var);
+            }
+
+            prover.enforce(|| "", |_| lc.clone(), |_| lc.clone(), |_| lc.clone());
+            prover.finalize_circuit();
+
+            assert_eq!(prover.a, vec![Scalar(expected)], "{} terms", num_terms);
+            assert_eq!(prover.b, prover.a);
+            assert_eq!(prover.c, prover.a);
+            assert_eq!(expected_aux, prover.a_aux_density);
+            assert_eq!(expected_inputs, prover.b_input_density);
+            assert_eq!(expected_aux, prover.b_aux_density);
+        }
+    }
+
+    #[test]
+    fn test_bounded_matrices() {
+        let mut prover = ProvingAssignment::<Bls12>::new();
+        let one = prover.alloc_input(|| "one", || Ok(Fr::one())).unwrap();
+
+        // The constraints are evaluated every `FINALIZE_ROWS`, so the matrices never
+        // hold more of them.
+        for i in 0..FINALIZE_ROWS + 10 {
+            prover.enforce(|| "", |lc| lc + one, |lc| lc + one, |lc| lc + one);
+            assert!(prover.a_matrix.num_rows() < FINALIZE_ROWS);
+            assert_eq!(prover.a.len() + prover.a_matrix.num_rows(), i + 1);
+        }
+        assert_eq!(prover.a.len(), FINALIZE_ROWS);
+        assert_eq!(prover.a_matrix.num_rows(), 10);
+
+        prover.finalize_circuit();
+        assert_eq!(prover.a.len(), FINALIZE_ROWS + 10);
+        assert!(prover.a.iter().all(|v| v.0 == Fr::one()));
+        assert!(prover.a_matrix.is_empty());
+    }
+
     #[test]
     fn test_proving_assignment_extend() {
         let mut rng = XorShiftRng::from_seed([
@@ -641,4 +945,36 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_mul_ct() {
+        use crate::bls::{G1Projective, G2Projective};
+
+        fn check<G: CurveProjective + ConditionalSwap>(rng: &mut XorShiftRng) {
+            let point = G::random(rng);
+            let mut minus_one = G::Scalar::one();
+            minus_one.negate();
+
+            let scalars = vec![
+                G::Scalar::zero(),
+                G::Scalar::one(),
+                minus_one,
+                G::Scalar::random(rng),
+                G::Scalar::random(rng),
+            ];
+            for scalar in scalars {
+                let mut expected = point;
+                expected.mul_assign(scalar);
+                assert_eq!(mul_ct(point, scalar), expected);
+            }
+            assert_eq!(mul_ct(G::zero(), G::Scalar::random(rng)), G::zero());
+        }
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        check::<G1Projective>(&mut rng);
+        check::<G2Projective>(&mut rng);
+    }
 }
@@ -0,0 +1,168 @@
+use ff::Field;
+
+use super::{synthesize_circuit, CircuitTag, Parameters};
+use crate::bls::Engine;
+use crate::{Circuit, SynthesisError};
+
+/// Checks that the assignment of `circuit` satisfies the QAP it is proven with under
+/// `params`, without the FFTs and multiexps of a proof.
+///
+/// The circuit is synthesized as for proving, with the constraints binding the inputs,
+/// and must have as many inputs and auxiliary variables as `params` were generated
+/// for, and fit their domain, or [`SynthesisError::IncompatibleParameters`] is
+/// returned. The proving key holds the QAP polynomials only as curve points, so the
+/// constraints themselves come from the circuit.
+///
+/// The QAP is satisfied if `A * B - C` is divisible by `Z(x) = x^n - 1`, which
+/// vanishes on the domain of the `n`-th roots of unity `ω^i`. The remainder of the
+/// division is the polynomial of degree less than `n` that has the values of
+/// `A * B - C` on the domain, and these are `A(ω^i) * B(ω^i) - C(ω^i)` for the
+/// evaluations of the linear combinations of constraint `i`, so the remainder is zero
+/// if and only if each of them is. They are checked directly, and
+/// [`SynthesisError::Unsatisfiable`] returned if one is not zero.
+pub fn check_qap_satisfiability<E, C, T>(
+    params: &Parameters<E, T>,
+    circuit: C,
+) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    T: CircuitTag,
+{
+    let prover = synthesize_circuit(circuit)?;
+
+    if prover.input_assignment.len() != params.vk.ic.len() {
+        return Err(SynthesisError::IncompatibleParameters("number of inputs"));
+    }
+    if prover.aux_assignment.len() != params.l.len() {
+        return Err(SynthesisError::IncompatibleParameters(
+            "number of auxiliary variables",
+        ));
+    }
+    // The H query has a point per coefficient of H, whose degree is n - 2.
+    if prover.a.len().next_power_of_two() != params.h.len() + 1 {
+        return Err(SynthesisError::IncompatibleParameters("domain size"));
+    }
+
+    // The evaluations past the constraints, up to the size of the domain, are zero.
+    let satisfied = prover
+        .a
+        .iter()
+        .zip(prover.b.iter())
+        .zip(prover.c.iter())
+        .all(|((a, b), c)| {
+            let mut ab = a.0;
+            ab.mul_assign(&b.0);
+            ab == c.0
+        });
+    if !satisfied {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::generate_random_parameters;
+    use crate::ConstraintSystem;
+    use ff::PrimeField;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Proves that the public input is the product of the factors.
+    struct Product {
+        factors: Vec<Option<Fr>>,
+        product: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Product {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut factors = self.factors.into_iter().enumerate();
+            let (_, first) = factors.next().expect("there is a factor");
+            let mut acc_value = first;
+            let mut acc = cs.alloc(
+                || "factor 0",
+                || first.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            for (i, factor) in factors {
+                let x = cs.alloc(
+                    || format!("factor {}", i),
+                    || factor.ok_or(SynthesisError::AssignmentMissing),
+                )?;
+                acc_value = acc_value.and_then(|mut v| {
+                    v.mul_assign(&factor?);
+                    Some(v)
+                });
+                let next = cs.alloc(
+                    || format!("product {}", i),
+                    || acc_value.ok_or(SynthesisError::AssignmentMissing),
+                )?;
+                cs.enforce(
+                    || format!("multiply {}", i),
+                    |lc| lc + acc,
+                    |lc| lc + x,
+                    |lc| lc + next,
+                );
+                acc = next;
+            }
+
+            let product = cs.alloc_input(
+                || "product",
+                || self.product.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(
+                || "product",
+                |lc| lc + acc,
+                |lc| lc + CS::one(),
+                |lc| lc + product,
+            );
+
+            Ok(())
+        }
+    }
+
+    fn product(factors: &[u64], product: u64) -> Product {
+        let fr = |x: u64| Some(Fr::from_str(&x.to_string()).unwrap());
+
+        Product {
+            factors: factors.iter().map(|&x| fr(x)).collect(),
+            product: fr(product),
+        }
+    }
+
+    #[test]
+    fn test_check_qap_satisfiability() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let blank = Product {
+            factors: vec![None; 3],
+            product: None,
+        };
+        let params = generate_random_parameters::<Bls12, _, _>(blank, &mut rng).unwrap();
+
+        check_qap_satisfiability(&params, product(&[2, 3, 7], 42)).unwrap();
+        assert!(matches!(
+            check_qap_satisfiability(&params, product(&[2, 3, 7], 43)),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+
+        // Circuits of other shapes are not checked against the parameters.
+        assert!(matches!(
+            check_qap_satisfiability(&params, product(&[2, 3], 6)),
+            Err(SynthesisError::IncompatibleParameters(_))
+        ));
+        assert!(matches!(
+            check_qap_satisfiability(&params, product(&[2, 3, 7, 1, 1, 1, 1], 42)),
+            Err(SynthesisError::IncompatibleParameters(_))
+        ));
+    }
+}
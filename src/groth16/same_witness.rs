@@ -0,0 +1,243 @@
+//! Proofs of one witness under several parameter sets.
+//!
+//! The assignment of a circuit and the coefficients of its H polynomial only depend on
+//! the circuit and its witness, not on the parameters. Proving the same circuit under
+//! parameters from different setups, e.g. of different security levels, therefore
+//! only needs the five multiexps over the queries of each parameter set after one
+//! synthesis and one set of FFTs.
+
+use std::sync::Arc;
+
+use ff::{Field, PrimeField};
+use rand_core::RngCore;
+
+use super::{
+    assemble_proof, execute_fft, synthesize_circuit, CircuitTag, ConditionalSwap, ParameterSource,
+    Parameters, Proof, ProofParts,
+};
+use crate::bls::Engine;
+use crate::domain::{DomainPrecompute, FftScratch};
+use crate::gpu::{LockedFFTKernel, LockedMultiexpKernel};
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, FullDensity};
+use crate::{Circuit, SynthesisError};
+
+/// Proves `circuit` under each of `params`, which must all be parameters of the
+/// circuit, returning the proofs in the same order.
+///
+/// The circuit is synthesized and H computed once, and every proof has randomizers of
+/// its own, so the proofs are the same as if they were created one by one with
+/// [`create_random_proof`](super::create_random_proof).
+pub fn prove_batch_same_witness<E, C, R, T>(
+    circuit: C,
+    params: &[Parameters<E, T>],
+    rng: &mut R,
+) -> Result<Vec<Proof<E, T>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+    R: RngCore,
+    T: CircuitTag,
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    let worker = Worker::new();
+    let mut prover = synthesize_circuit::<E, C>(circuit)?;
+
+    let n = prover.a.len();
+    let mut log_d = 0;
+    while (1 << log_d) < n {
+        log_d += 1;
+    }
+
+    let h = {
+        let precompute = DomainPrecompute::new(n)?;
+        let mut fft_kern = Some(LockedFFTKernel::<E>::new(log_d, false));
+        execute_fft(
+            &worker,
+            &mut prover,
+            &precompute,
+            &mut fft_kern,
+            &mut FftScratch::new(),
+        )?
+    };
+
+    let into_reprs = |assignment: &[E::Fr]| {
+        Arc::new(assignment.iter().map(|s| s.into_repr()).collect::<Vec<_>>())
+    };
+    let input_assignment = into_reprs(&prover.input_assignment[..]);
+    let aux_assignment = into_reprs(&prover.aux_assignment[..]);
+
+    let a_aux_density_total = prover.a_aux_density.get_total_density();
+    let a_aux_density = Arc::new(prover.a_aux_density);
+    let b_input_density_total = prover.b_input_density.get_total_density();
+    let b_input_density = Arc::new(prover.b_input_density);
+    let b_aux_density_total = prover.b_aux_density.get_total_density();
+    let b_aux_density = Arc::new(prover.b_aux_density);
+
+    let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, false));
+    params
+        .iter()
+        .map(|params| {
+            let vk = params.get_vk(input_assignment.len())?;
+
+            let h = multiexp(
+                &worker,
+                params.get_h(h.len())?,
+                FullDensity,
+                h.clone(),
+                &mut multiexp_kern,
+            );
+            let l = multiexp(
+                &worker,
+                params.get_l(aux_assignment.len())?,
+                FullDensity,
+                aux_assignment.clone(),
+                &mut multiexp_kern,
+            );
+
+            let (a_inputs_source, a_aux_source) =
+                params.get_a(input_assignment.len(), a_aux_density_total)?;
+            let a_inputs = multiexp(
+                &worker,
+                a_inputs_source,
+                FullDensity,
+                input_assignment.clone(),
+                &mut multiexp_kern,
+            );
+            let a_aux = multiexp(
+                &worker,
+                a_aux_source,
+                a_aux_density.clone(),
+                aux_assignment.clone(),
+                &mut multiexp_kern,
+            );
+
+            let (b_g1_inputs_source, b_g1_aux_source) =
+                params.get_b_g1(b_input_density_total, b_aux_density_total)?;
+            let b_g1_inputs = multiexp(
+                &worker,
+                b_g1_inputs_source,
+                b_input_density.clone(),
+                input_assignment.clone(),
+                &mut multiexp_kern,
+            );
+            let b_g1_aux = multiexp(
+                &worker,
+                b_g1_aux_source,
+                b_aux_density.clone(),
+                aux_assignment.clone(),
+                &mut multiexp_kern,
+            );
+
+            let (b_g2_inputs_source, b_g2_aux_source) =
+                params.get_b_g2(b_input_density_total, b_aux_density_total)?;
+            let b_g2_inputs = multiexp(
+                &worker,
+                b_g2_inputs_source,
+                b_input_density.clone(),
+                input_assignment.clone(),
+                &mut multiexp_kern,
+            );
+            let b_g2_aux = multiexp(
+                &worker,
+                b_g2_aux_source,
+                b_aux_density.clone(),
+                aux_assignment.clone(),
+                &mut multiexp_kern,
+            );
+
+            let mut a = a_inputs.wait()?;
+            a.add_assign(&a_aux.wait()?);
+            let mut b_g1 = b_g1_inputs.wait()?;
+            b_g1.add_assign(&b_g1_aux.wait()?);
+            let mut b_g2 = b_g2_inputs.wait()?;
+            b_g2.add_assign(&b_g2_aux.wait()?);
+
+            let parts = ProofParts {
+                h: h.wait()?,
+                l: l.wait()?,
+                a,
+                b_g1,
+                b_g2,
+            };
+
+            assemble_proof(vk, &parts, E::Fr::random(rng), E::Fr::random(rng))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+    use crate::ConstraintSystem;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Proves knowledge of a cube root of the public input.
+    struct CubeRoot {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for CubeRoot {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let square = self.x.map(|mut x| {
+                x.square();
+                x
+            });
+            let cube = self.x.and_then(|x| {
+                let mut cube = square?;
+                cube.mul_assign(&x);
+                Some(cube)
+            });
+
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let x2 = cs.alloc(|| "x2", || square.ok_or(SynthesisError::AssignmentMissing))?;
+            let x3 = cs.alloc_input(|| "x3", || cube.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "square", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            cs.enforce(|| "cube", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_prove_batch_same_witness() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = (0..3)
+            .map(|_| {
+                generate_random_parameters::<Bls12, _, _>(CubeRoot { x: None }, &mut rng).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let pvks = params
+            .iter()
+            .map(|params| prepare_verifying_key(&params.vk))
+            .collect::<Vec<_>>();
+
+        let x = Fr::from_str("3").unwrap();
+        let cube = Fr::from_str("27").unwrap();
+        let proofs = prove_batch_same_witness(CubeRoot { x: Some(x) }, &params, &mut rng).unwrap();
+        assert_eq!(proofs.len(), params.len());
+
+        for (i, proof) in proofs.iter().enumerate() {
+            for (j, pvk) in pvks.iter().enumerate() {
+                // Each proof only verifies under its own parameters.
+                assert_eq!(verify_proof(pvk, proof, &[cube]).unwrap(), i == j);
+            }
+            assert!(!verify_proof(&pvks[i], proof, &[x]).unwrap());
+        }
+
+        let none = prove_batch_same_witness(CubeRoot { x: Some(x) }, &params[..0], &mut rng);
+        assert!(none.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use digest::Digest;
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::RngCore;
+
+use super::{mul_ct, verify_proof, ConditionalSwap, PreparedVerifyingKey, Proof};
+use crate::bls::Engine;
+use crate::transcript::FiatShamirRng;
+use crate::SynthesisError;
+
+/// Domain of the transcript deriving the shared challenge.
+const SIGMA_DOMAIN: &[u8] = b"bellperson-sigma-groth16";
+
+/// A Schnorr proof of knowledge of the discrete logarithm `w` of `public_key = w * G`,
+/// with `G` the generator of G1.
+///
+/// The prover commits to `commitment = k * G` for a random `k` and answers the
+/// challenge `c` with `response = k + c * w`.
+#[derive(Clone, Debug)]
+pub struct ComSigmaProof<E: Engine> {
+    pub commitment: E::G1Affine,
+    pub response: E::Fr,
+}
+
+impl<E: Engine> PartialEq for ComSigmaProof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.commitment == other.commitment && self.response == other.response
+    }
+}
+
+/// A Groth16 proof linked to a Schnorr proof by a common challenge.
+///
+/// The challenge of the Schnorr proof is derived with a [`FiatShamirRng`] from the
+/// Groth16 proof, its public inputs, the public key and the Schnorr commitment, so the
+/// Schnorr proof only verifies together with this Groth16 proof and statement.
+///
+/// The link is made by the transcript, not by the proof systems: that `w` is also the
+/// witness, or a part of it, of the circuit must be ensured by the application, e.g.
+/// by a circuit which checks `public_key` as a public input.
+#[derive(Clone, Debug)]
+pub struct SigmaStatement<E: Engine> {
+    pub proof: Proof<E>,
+    pub public_key: E::G1Affine,
+    pub sigma: ComSigmaProof<E>,
+}
+
+impl<E: Engine> PartialEq for SigmaStatement<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof
+            && self.public_key == other.public_key
+            && self.sigma == other.sigma
+    }
+}
+
+/// Proves knowledge of `witness` for `witness * G`, sharing the challenge with `proof`
+/// for `public_inputs`. The challenge is derived with the hash `H`.
+pub fn link_sigma_groth16<E, H, R>(
+    proof: Proof<E>,
+    public_inputs: &[E::Fr],
+    witness: E::Fr,
+    rng: &mut R,
+) -> SigmaStatement<E>
+where
+    E: Engine,
+    H: Digest + Clone,
+    R: RngCore,
+    E::G1: ConditionalSwap,
+{
+    // Both scalars are secret, so they are multiplied in constant time.
+    let public_key = mul_ct(E::G1::one(), witness).into_affine();
+    let k = E::Fr::random(rng);
+    let commitment = mul_ct(E::G1::one(), k).into_affine();
+
+    let c = challenge::<E, H>(&proof, public_inputs, &public_key, &commitment);
+    let mut response = c;
+    response.mul_assign(&witness);
+    response.add_assign(&k);
+
+    SigmaStatement {
+        proof,
+        public_key,
+        sigma: ComSigmaProof {
+            commitment,
+            response,
+        },
+    }
+}
+
+/// Verifies the Groth16 proof of `statement` for `public_inputs` and its Schnorr proof
+/// with the shared challenge, derived with the hash `H`.
+pub fn verify_sigma_groth16<E, H>(
+    pvk: &PreparedVerifyingKey<E>,
+    statement: &SigmaStatement<E>,
+    public_inputs: &[E::Fr],
+) -> Result<bool, SynthesisError>
+where
+    E: Engine,
+    H: Digest + Clone,
+{
+    let c = challenge::<E, H>(
+        &statement.proof,
+        public_inputs,
+        &statement.public_key,
+        &statement.sigma.commitment,
+    );
+
+    // response * G = commitment + c * public_key
+    let lhs = E::G1Affine::one().mul(statement.sigma.response.into_repr());
+    let mut rhs = statement.public_key.mul(c.into_repr());
+    rhs.add_assign_mixed(&statement.sigma.commitment);
+    if lhs != rhs {
+        return Ok(false);
+    }
+
+    verify_proof(pvk, &statement.proof, public_inputs)
+}
+
+fn challenge<E: Engine, H: Digest + Clone>(
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+    public_key: &E::G1Affine,
+    commitment: &E::G1Affine,
+) -> E::Fr {
+    let mut transcript = FiatShamirRng::<H>::new(SIGMA_DOMAIN);
+    transcript.absorb_g1(&proof.a);
+    transcript.absorb_g2(&proof.b);
+    transcript.absorb_g1(&proof.c);
+    for input in public_inputs {
+        transcript.absorb_fr(input);
+    }
+    transcript.absorb_g1(public_key);
+    transcript.absorb_g1(commitment);
+
+    E::Fr::random(&mut transcript)
+}
@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use super::{verify_proof, PreparedVerifyingKey, Proof};
+use crate::bls::Engine;
+use crate::SynthesisError;
+
+/// A proof with metadata, like the prover or the circuit version, which is kept next to
+/// the proof and not bound by it.
+///
+/// The serialized form is the compressed proof, the number of metadata entries as a
+/// big-endian `u32`, and every key and value sorted by key, each a `u32` length followed
+/// by the UTF-8 bytes.
+#[derive(Clone, Debug)]
+pub struct TaggedProof<E: Engine> {
+    pub proof: Proof<E>,
+    pub metadata: HashMap<String, String>,
+}
+
+impl<E: Engine> PartialEq for TaggedProof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof && self.metadata == other.metadata
+    }
+}
+
+impl<E: Engine> TaggedProof<E> {
+    pub fn new(proof: Proof<E>) -> Self {
+        TaggedProof {
+            proof,
+            metadata: HashMap::new(),
+        }
+    }
+
+    pub fn with_tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(|v| v.as_str())
+    }
+
+    /// Drops the metadata.
+    pub fn strip(self) -> Proof<E> {
+        self.proof
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.proof.write(&mut writer)?;
+        writer.write_u32::<BigEndian>(self.metadata.len() as u32)?;
+        let sorted = self.metadata.iter().collect::<BTreeMap<_, _>>();
+        for (key, value) in sorted {
+            write_string(&mut writer, key)?;
+            write_string(&mut writer, value)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let proof = Proof::read(&mut reader)?;
+        let num_entries = reader.read_u32::<BigEndian>()?;
+        let mut metadata = HashMap::new();
+        for _ in 0..num_entries {
+            let key = read_string(&mut reader)?;
+            let value = read_string(&mut reader)?;
+            if metadata.insert(key, value).is_some() {
+                return Err(invalid_data("duplicate metadata key"));
+            }
+        }
+
+        Ok(TaggedProof { proof, metadata })
+    }
+}
+
+impl<E: Engine> From<Proof<E>> for TaggedProof<E> {
+    fn from(proof: Proof<E>) -> Self {
+        TaggedProof::new(proof)
+    }
+}
+
+/// Verifies the proof of `tagged`, ignoring its metadata.
+pub fn verify_tagged_proof<E: Engine>(
+    pvk: &PreparedVerifyingKey<E>,
+    tagged: &TaggedProof<E>,
+    public_inputs: &[E::Fr],
+) -> Result<bool, SynthesisError> {
+    verify_proof(pvk, &tagged.proof, public_inputs)
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_string<W: Write>(mut writer: W, s: &str) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(s.len() as u32)?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_string<R: Read>(mut reader: R) -> io::Result<String> {
+    let len = reader.read_u32::<BigEndian>()?;
+    // Reads at most the bytes which are there, so a corrupt length cannot allocate more.
+    let mut bytes = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated metadata",
+        ));
+    }
+
+    String::from_utf8(bytes).map_err(|_| invalid_data("metadata is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key};
+    use crate::{Circuit, ConstraintSystem};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Proves knowledge of the square root of the public input.
+    struct Square {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Square {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let mut y = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                    y.square();
+                    Ok(y)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tagged_proof() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params = generate_random_parameters::<Bls12, _, _>(Square { x: None }, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let x = Fr::random(rng);
+        let mut y = x;
+        y.square();
+        let proof = create_random_proof(Square { x: Some(x) }, &params, rng).unwrap();
+
+        let tagged = TaggedProof::new(proof.clone())
+            .with_tag("prover", "node-7")
+            .with_tag("circuit", "square v1.2")
+            .with_tag("note", "quotes \" and \\ and \n and \u{1} and \u{1f600}");
+        assert_eq!(tagged.tag("prover"), Some("node-7"));
+        assert!(verify_tagged_proof(&pvk, &tagged, &[y]).unwrap());
+
+        let mut bytes = Vec::new();
+        tagged.write(&mut bytes).unwrap();
+        let parsed = TaggedProof::<Bls12>::read(&bytes[..]).unwrap();
+        assert!(parsed == tagged);
+        assert!(parsed.strip() == proof);
+
+        // The metadata is written sorted, so equal tags serialize the same.
+        let mut reordered = Vec::new();
+        TaggedProof::new(proof.clone())
+            .with_tag("note", "quotes \" and \\ and \n and \u{1} and \u{1f600}")
+            .with_tag("circuit", "square v1.2")
+            .with_tag("prover", "node-7")
+            .write(&mut reordered)
+            .unwrap();
+        assert_eq!(reordered, bytes);
+
+        // Without metadata, the tagged proof is the proof and a zero count.
+        let mut untagged = Vec::new();
+        TaggedProof::new(proof).write(&mut untagged).unwrap();
+        assert_eq!(untagged.len(), Proof::<Bls12>::size() + 4);
+
+        // Truncated bytes, a length past the end, a duplicate key and invalid UTF-8.
+        assert!(TaggedProof::<Bls12>::read(&bytes[..bytes.len() - 1]).is_err());
+        let mut long = untagged.clone();
+        long[Proof::<Bls12>::size() + 3] = 1;
+        long.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        assert!(TaggedProof::<Bls12>::read(&long[..]).is_err());
+        let mut duplicate = untagged.clone();
+        duplicate[Proof::<Bls12>::size() + 3] = 2;
+        for _ in 0..2 {
+            duplicate.extend_from_slice(&[0, 0, 0, 1, b'k', 0, 0, 0, 0]);
+        }
+        assert!(TaggedProof::<Bls12>::read(&duplicate[..]).is_err());
+        let mut invalid = untagged;
+        invalid[Proof::<Bls12>::size() + 3] = 1;
+        invalid.extend_from_slice(&[0, 0, 0, 1, 0xff, 0, 0, 0, 0]);
+        assert!(TaggedProof::<Bls12>::read(&invalid[..]).is_err());
+    }
+}
@@ -21,6 +21,14 @@ impl fmt::Display for Fr {
     }
 }
 
+impl crate::groth16::ConditionalSwap for Fr {
+    fn conditional_swap(a: &mut Self, b: &mut Self, mask: u64) {
+        let t = ((a.0).0 ^ (b.0).0) & mask as u32;
+        (a.0).0 ^= t;
+        (b.0).0 ^= t;
+    }
+}
+
 impl Field for Fr {
     fn random<R: RngCore>(rng: &mut R) -> Self {
         Fr(Wrapping(rng.next_u32()) % MODULUS_R)
@@ -4,7 +4,7 @@ use ff::{Field, PrimeField};
 use rand_core::SeedableRng;
 use rand_xorshift::XorShiftRng;
 
-mod dummy_engine;
+pub(super) mod dummy_engine;
 use self::dummy_engine::*;
 
 use std::marker::PhantomData;
@@ -515,16 +515,607 @@ fn test_verify_random_single() {
 
         // entirely random proofs
         {
-            let random_proof = Proof {
-                a: G1Projective::random(&mut rng).into_affine(),
-                b: G2Projective::random(&mut rng).into_affine(),
-                c: G1Projective::random(&mut rng).into_affine(),
-            };
+            let random_proof = Proof::new(
+                G1Projective::random(&mut rng).into_affine(),
+                G2Projective::random(&mut rng).into_affine(),
+                G1Projective::random(&mut rng).into_affine(),
+            );
             assert!(!verify_proof(&pvk, &random_proof, &[Fr::one()]).unwrap());
         }
     }
 }
 
+#[test]
+#[cfg(feature = "lagrange")]
+fn test_lagrange_proving_key() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_proof_lagrange, generate_random_parameters, LagrangeProvingKey};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+    let pk = LagrangeProvingKey::from_parameters(params.clone()).unwrap();
+    assert!(pk.vk() == &params.vk);
+
+    let pvk = prepare_verifying_key(pk.vk());
+
+    for _ in 0..10 {
+        let c = XORDemo {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData,
+        };
+        let r = Fr::random(&mut rng);
+        let s = Fr::random(&mut rng);
+
+        // The same proof as with the H query in the monomial basis.
+        let proof = create_proof_lagrange(c.clone(), &pk, r, s).unwrap();
+        assert_eq!(proof, create_proof(c, &params, r, s).unwrap());
+        assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+    }
+}
+
+#[test]
+fn test_verify_online_phase() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, prepare_inputs, verify_proof_final_phase,
+        verify_proof_online_phase,
+    };
+    use groupy::{CurveAffine, CurveProjective};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+
+    let pvk = prepare_verifying_key(&params.vk);
+    let inputs = prepare_inputs(&pvk, &[Fr::one()]).unwrap();
+    assert!(prepare_inputs(&pvk, &[Fr::one(), Fr::one()]).is_err());
+
+    for _ in 0..10 {
+        let c = XORDemo {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData,
+        };
+
+        let proof = create_random_proof(c, &params, &mut rng).unwrap();
+        let ml = verify_proof_online_phase(inputs, &proof, &pvk);
+        assert!(verify_proof_final_phase(&pvk, &ml));
+
+        // mess up the inputs
+        let wrong_inputs = prepare_inputs(&pvk, &[Fr::random(&mut rng)]).unwrap();
+        let ml = verify_proof_online_phase(wrong_inputs, &proof, &pvk);
+        assert!(!verify_proof_final_phase(&pvk, &ml));
+
+        // mess up the proof
+        let mut fake_proof = proof.clone();
+        fake_proof.c = fake_proof.c.mul(Fr::random(&mut rng)).into_affine();
+        let ml = verify_proof_online_phase(inputs, &fake_proof, &pvk);
+        assert!(!verify_proof_final_phase(&pvk, &ml));
+        assert!(!verify_proof(&pvk, &fake_proof, &[Fr::one()]).unwrap());
+    }
+}
+
+#[test]
+fn test_verifier_state() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters, Proof, VerifierState};
+    use groupy::{CurveAffine, CurveProjective};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let c = XORDemo {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+    let proof = create_random_proof(c, &params, &mut rng).unwrap();
+
+    let verify = |proof: &Proof<Bls12>, inputs: &[Fr]| {
+        let mut state = VerifierState::new(&pvk);
+        state.feed_ab(proof.a, proof.b)?;
+        state.feed_public_inputs(inputs)?;
+        state.feed_c(proof.c)?;
+        state.finalize()
+    };
+    assert!(verify(&proof, &[Fr::one()]).unwrap());
+    assert!(!verify(&proof, &[Fr::random(&mut rng)]).unwrap());
+    assert!(verify(&proof, &[Fr::one(), Fr::one()]).is_err());
+
+    let mut fake_proof = proof.clone();
+    fake_proof.c = fake_proof.c.mul(Fr::random(&mut rng)).into_affine();
+    assert!(!verify(&fake_proof, &[Fr::one()]).unwrap());
+
+    // Parts given out of order are rejected without changing the state.
+    let mut state = VerifierState::new(&pvk);
+    assert!(state.feed_c(proof.c).is_err());
+    assert!(state.feed_public_inputs(&[Fr::one()]).is_err());
+    assert!(state.finalize().is_err());
+    state.feed_ab(proof.a, proof.b).unwrap();
+    assert!(state.feed_ab(proof.a, proof.b).is_err());
+    assert!(state.feed_c(proof.c).is_err());
+    state.feed_public_inputs(&[Fr::one()]).unwrap();
+    assert!(state.finalize().is_err());
+    state.feed_c(proof.c).unwrap();
+    assert!(state.finalize().unwrap());
+    assert!(state.finalize().is_err());
+}
+
+#[test]
+fn test_bound_proof() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{
+        bind_transcript, create_random_proof, generate_random_parameters, verify_bound_proof,
+    };
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(bind_transcript(c, b""), &mut rng).unwrap()
+    };
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let c = XORDemo {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+    let proof = create_random_proof(bind_transcript(c, b"session 1"), &params, &mut rng).unwrap();
+
+    assert!(verify_bound_proof(&pvk, &proof, b"session 1", &[Fr::one()]).unwrap());
+
+    // Replaying the proof in another session fails, as does a wrong statement.
+    assert!(!verify_bound_proof(&pvk, &proof, b"session 2", &[Fr::one()]).unwrap());
+    assert!(!verify_bound_proof(&pvk, &proof, b"", &[Fr::one()]).unwrap());
+    assert!(!verify_bound_proof(&pvk, &proof, b"session 1", &[Fr::zero()]).unwrap());
+
+    // The bound circuit has its own keys: the proof does not verify as one of the
+    // circuit alone.
+    assert!(verify_proof(&pvk, &proof, &[Fr::one()]).is_err());
+}
+
+#[test]
+fn test_sigma_groth16() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, link_sigma_groth16, verify_sigma_groth16,
+    };
+    use groupy::CurveAffine;
+    use sha2::Sha256;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let prove = |rng: &mut XorShiftRng| {
+        let c = XORDemo {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData,
+        };
+        create_random_proof(c, &params, rng).unwrap()
+    };
+    let proof = prove(&mut rng);
+    let witness = Fr::random(&mut rng);
+
+    let statement =
+        link_sigma_groth16::<_, Sha256, _>(proof.clone(), &[Fr::one()], witness, &mut rng);
+    assert_eq!(
+        statement.public_key,
+        <Bls12 as Engine>::G1Affine::one()
+            .mul(witness.into_repr())
+            .into_affine()
+    );
+    assert!(verify_sigma_groth16::<_, Sha256>(&pvk, &statement, &[Fr::one()]).unwrap());
+    assert!(!verify_sigma_groth16::<_, Sha256>(&pvk, &statement, &[Fr::zero()]).unwrap());
+
+    // The Schnorr proof does not verify with another Groth16 proof of the same statement.
+    let mut moved = statement.clone();
+    moved.proof = prove(&mut rng);
+    assert!(!verify_sigma_groth16::<_, Sha256>(&pvk, &moved, &[Fr::one()]).unwrap());
+
+    // Nor with another public key or response.
+    let mut forged = statement.clone();
+    forged.public_key = <Bls12 as Engine>::G1Affine::one();
+    assert!(!verify_sigma_groth16::<_, Sha256>(&pvk, &forged, &[Fr::one()]).unwrap());
+    let mut forged = statement;
+    forged.sigma.response.add_assign(&Fr::one());
+    assert!(!verify_sigma_groth16::<_, Sha256>(&pvk, &forged, &[Fr::one()]).unwrap());
+}
+
+#[test]
+fn test_srs_update() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, update_srs, verify_srs_update,
+    };
+    use rand::rngs::StdRng;
+    use std::sync::Arc;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let mut contributor = StdRng::from_seed([7; 32]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+
+    // Two contributions in a row, each verified against its predecessor.
+    let (first, first_contrib) = update_srs(&params, &mut contributor);
+    assert!(verify_srs_update(&params, &first, &first_contrib));
+    let (second, second_contrib) = update_srs(&first, &mut contributor);
+    assert!(verify_srs_update(&first, &second, &second_contrib));
+    assert!(first.vk.delta_g1 != params.vk.delta_g1);
+
+    let c = XORDemo {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+    let proof = create_random_proof(c, &second, &mut rng).unwrap();
+    assert!(verify_proof(&prepare_verifying_key(&second.vk), &proof, &[Fr::one()]).unwrap());
+    assert!(!verify_proof(&prepare_verifying_key(&params.vk), &proof, &[Fr::one()]).unwrap());
+
+    // A contribution does not verify against another predecessor.
+    assert!(!verify_srs_update(&params, &second, &second_contrib));
+
+    // Nor with a forged response, or with `h` not divided by the same value.
+    let mut forged = second_contrib.clone();
+    forged.response.add_assign(&Fr::one());
+    assert!(!verify_srs_update(&first, &second, &forged));
+    let mut tampered = second.clone();
+    let mut h = (*tampered.h).clone();
+    h.swap(0, 1);
+    tampered.h = Arc::new(h);
+    assert!(!verify_srs_update(&first, &tampered, &second_contrib));
+}
+
+#[test]
+fn test_nullifier_commitment() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters, nullifier_commitment};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let mut seen = Vec::new();
+    for _ in 0..5 {
+        let c = XORDemo {
+            a: Some(true),
+            b: Some(false),
+            _marker: PhantomData,
+        };
+        let proof = create_random_proof(c, &params, &mut rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+
+        // Different proofs of the same statement have different nullifiers.
+        let nullifier = nullifier_commitment(&proof, b"verifier");
+        assert!(!seen.contains(&nullifier));
+        seen.push(nullifier);
+
+        // A replayed proof has the same nullifier, in its context only.
+        assert_eq!(nullifier, nullifier_commitment(&proof, b"verifier"));
+        assert_ne!(nullifier, nullifier_commitment(&proof, b"other verifier"));
+        assert_ne!(nullifier, nullifier_commitment(&proof, b""));
+    }
+}
+
+#[test]
+fn test_caching_verifier() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters, CachingVerifier};
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let proofs = (0..3)
+        .map(|_| {
+            let c = XORDemo {
+                a: Some(true),
+                b: Some(false),
+                _marker: PhantomData,
+            };
+            create_random_proof(c, &params, &mut rng).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let verifier = CachingVerifier::<Bls12>::with_capacity(2);
+
+    // The second verification of a proof is answered from the cache.
+    assert!(verifier.verify(&pvk, &proofs[0], &[Fr::one()]).unwrap());
+    assert_eq!((verifier.hits(), verifier.misses()), (0, 1));
+    assert!(verifier.verify(&pvk, &proofs[0], &[Fr::one()]).unwrap());
+    assert_eq!((verifier.hits(), verifier.misses()), (1, 1));
+
+    // Results for other inputs are cached separately, invalid ones too.
+    assert!(!verifier.verify(&pvk, &proofs[0], &[Fr::zero()]).unwrap());
+    assert!(!verifier.verify(&pvk, &proofs[0], &[Fr::zero()]).unwrap());
+    assert_eq!((verifier.hits(), verifier.misses()), (2, 2));
+    assert_eq!(verifier.len(), 2);
+
+    // Errors are not cached.
+    assert!(verifier.verify(&pvk, &proofs[1], &[]).is_err());
+    assert_eq!(verifier.len(), 2);
+
+    // Using the first result evicts the second, least recently used, one.
+    assert!(verifier.verify(&pvk, &proofs[0], &[Fr::one()]).unwrap());
+    assert!(verifier.verify(&pvk, &proofs[2], &[Fr::one()]).unwrap());
+    assert_eq!(verifier.len(), 2);
+    assert_eq!((verifier.hits(), verifier.misses()), (3, 4));
+    assert!(verifier.verify(&pvk, &proofs[0], &[Fr::one()]).unwrap());
+    assert!(!verifier.verify(&pvk, &proofs[0], &[Fr::zero()]).unwrap());
+    assert_eq!((verifier.hits(), verifier.misses()), (4, 5));
+
+    // A key which only differs in delta is another key.
+    let mut vk = params.vk.clone();
+    vk.delta_g2 = vk.gamma_g2;
+    let other_pvk = prepare_verifying_key(&vk);
+    assert!(!verifier
+        .verify(&other_pvk, &proofs[0], &[Fr::one()])
+        .unwrap());
+    assert_eq!((verifier.hits(), verifier.misses()), (4, 6));
+    assert!(verifier.verify(&pvk, &proofs[0], &[Fr::one()]).unwrap());
+
+    assert_eq!(CachingVerifier::<Bls12>::new().len(), 0);
+    assert_ne!(proofs[0].fingerprint(), proofs[1].fingerprint());
+    assert_ne!(params.vk.fingerprint(), vk.fingerprint());
+}
+
+#[test]
+fn test_circuit_tags() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, AnyCircuit, CircuitTag, Parameters, Proof,
+    };
+
+    enum Xor {}
+    impl CircuitTag for Xor {}
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng)
+            .unwrap()
+            .for_circuit::<Xor>()
+    };
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let c = XORDemo {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+    let proof: Proof<Bls12, Xor> = create_random_proof(c, &params, &mut rng).unwrap();
+    assert!(verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+
+    // Tags only exist in the types: serializations and proofs are unchanged.
+    let mut bytes = Vec::new();
+    proof.write(&mut bytes).unwrap();
+    let read = Proof::<Bls12>::read(&bytes[..])
+        .unwrap()
+        .for_circuit::<Xor>();
+    assert!(read == proof);
+    assert!(verify_proof(&pvk, &read, &[Fr::one()]).unwrap());
+
+    let mut param_bytes = Vec::new();
+    params.write(&mut param_bytes).unwrap();
+    let any_params: Parameters<Bls12, AnyCircuit> = params.into_any();
+    assert!(Parameters::read(&param_bytes[..], false).unwrap() == any_params);
+
+    // Erased keys and proofs verify like before.
+    let any_pvk = prepare_verifying_key(&any_params.vk);
+    assert!(verify_proof(&any_pvk, &proof.clone().into_any(), &[Fr::one()]).unwrap());
+    assert!(verify_proof(&pvk.into_any(), &proof.into_any(), &[Fr::one()]).unwrap());
+    assert!(verify_proof(&any_pvk.for_circuit::<Xor>(), &read, &[Fr::one()]).unwrap());
+}
+
+#[test]
+fn test_aggregate() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{
+        aggregate_proofs, create_random_proof, generate_random_parameters, verify_aggregated,
+        AggSRS,
+    };
+    use crate::transcript::FiatShamirRng;
+    use groupy::CurveProjective;
+    use sha2::Sha256;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = {
+        let c = XORDemo::<Bls12> {
+            a: None,
+            b: None,
+            _marker: PhantomData,
+        };
+
+        generate_random_parameters::<Bls12, _, _>(c, &mut rng).unwrap()
+    };
+    let srs = AggSRS::<Bls12>::setup(8, &mut rng);
+    assert_eq!(srs.size(), 8);
+
+    let transcript = |inputs: &[Vec<Fr>]| {
+        let mut transcript = FiatShamirRng::<Sha256>::new(b"test-aggregate");
+        for input in inputs.iter().flatten() {
+            transcript.absorb_fr(input);
+        }
+        transcript
+    };
+
+    for &n in &[2, 4, 8] {
+        let mut proofs = Vec::new();
+        let mut inputs = Vec::new();
+        for i in 0..n {
+            let (a, b) = (i % 2 == 0, i % 3 == 0);
+            let c = XORDemo {
+                a: Some(a),
+                b: Some(b),
+                _marker: PhantomData,
+            };
+            proofs.push(create_random_proof(c, &params, &mut rng).unwrap());
+            inputs.push(vec![if a ^ b { Fr::one() } else { Fr::zero() }]);
+        }
+
+        let agg = aggregate_proofs(&proofs, &params.vk, &srs, &mut transcript(&inputs)).unwrap();
+        assert_eq!(agg.rounds.len(), n.trailing_zeros() as usize);
+        assert!(
+            verify_aggregated(&agg, &params.vk, &srs, &mut transcript(&inputs), &inputs).unwrap()
+        );
+
+        // Wrong public inputs, a different transcript or a tampered proof are rejected.
+        let mut wrong_inputs = inputs.clone();
+        wrong_inputs[n - 1][0].add_assign(&Fr::one());
+        assert!(!verify_aggregated(
+            &agg,
+            &params.vk,
+            &srs,
+            &mut transcript(&inputs),
+            &wrong_inputs
+        )
+        .unwrap());
+        assert!(!verify_aggregated(
+            &agg,
+            &params.vk,
+            &srs,
+            &mut transcript(&wrong_inputs),
+            &inputs
+        )
+        .unwrap());
+        let mut tampered = agg.clone();
+        tampered.final_c = tampered.final_c.into_projective().double().into_affine();
+        assert!(!verify_aggregated(
+            &tampered,
+            &params.vk,
+            &srs,
+            &mut transcript(&inputs),
+            &inputs
+        )
+        .unwrap());
+    }
+
+    // The proof count must be a power of two within the SRS.
+    let c = XORDemo {
+        a: Some(true),
+        b: Some(true),
+        _marker: PhantomData,
+    };
+    let proof = create_random_proof(c, &params, &mut rng).unwrap();
+    for &n in &[1, 3, 16] {
+        let proofs = vec![proof.clone(); n];
+        match aggregate_proofs(&proofs, &params.vk, &srs, &mut transcript(&[])) {
+            Err(SynthesisError::InvalidProofCount(count)) => assert_eq!(count, n),
+            _ => panic!("aggregated {} proofs", n),
+        }
+    }
+}
+
 #[test]
 fn test_verify_random_batch() {
     use crate::bls::{Bls12, Fr, G1Projective, G2Projective};
@@ -634,21 +1225,21 @@ fn test_verify_random_batch() {
         // entirely random proofs
         {
             let random_proof = [
-                Proof {
-                    a: G1Projective::random(&mut rng).into_affine(),
-                    b: G2Projective::random(&mut rng).into_affine(),
-                    c: G1Projective::random(&mut rng).into_affine(),
-                },
-                Proof {
-                    a: G1Projective::random(&mut rng).into_affine(),
-                    b: G2Projective::random(&mut rng).into_affine(),
-                    c: G1Projective::random(&mut rng).into_affine(),
-                },
-                Proof {
-                    a: G1Projective::random(&mut rng).into_affine(),
-                    b: G2Projective::random(&mut rng).into_affine(),
-                    c: G1Projective::random(&mut rng).into_affine(),
-                },
+                Proof::new(
+                    G1Projective::random(&mut rng).into_affine(),
+                    G2Projective::random(&mut rng).into_affine(),
+                    G1Projective::random(&mut rng).into_affine(),
+                ),
+                Proof::new(
+                    G1Projective::random(&mut rng).into_affine(),
+                    G2Projective::random(&mut rng).into_affine(),
+                    G1Projective::random(&mut rng).into_affine(),
+                ),
+                Proof::new(
+                    G1Projective::random(&mut rng).into_affine(),
+                    G2Projective::random(&mut rng).into_affine(),
+                    G1Projective::random(&mut rng).into_affine(),
+                ),
             ];
             assert!(!verify_proofs_batch(
                 &pvk,
@@ -660,3 +1251,78 @@ fn test_verify_random_batch() {
         }
     }
 }
+
+#[test]
+fn test_merge_linear_constraints() {
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters};
+    use crate::util_cs::merge_cs::MergeLinearConstraints;
+
+    // `x + 1 = y` for an input `y`, enforced once more scaled, next to `1 = 1`.
+    struct Successor {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Successor {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let mut y = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                    y.add_assign(&Fr::one());
+                    Ok(y)
+                },
+            )?;
+
+            let three = Fr::from_str("3").unwrap();
+            cs.enforce(
+                || "successor",
+                |lc| lc + x + CS::one(),
+                |lc| lc + CS::one(),
+                |lc| lc + y,
+            );
+            cs.enforce(
+                || "three successors",
+                |lc| lc + (three, x) + (three, CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + (three, y),
+            );
+            cs.enforce(
+                || "one",
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+
+            Ok(())
+        }
+    }
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params = generate_random_parameters::<Bls12, _, _>(
+        MergeLinearConstraints(Successor { x: None }),
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let x = Fr::from_str("41").unwrap();
+    let proof = create_random_proof(
+        MergeLinearConstraints(Successor { x: Some(x) }),
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    // The constraint left still binds the input.
+    assert!(verify_proof(&pvk, &proof, &[Fr::from_str("42").unwrap()]).unwrap());
+    assert!(!verify_proof(&pvk, &proof, &[Fr::from_str("43").unwrap()]).unwrap());
+}
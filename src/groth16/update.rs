@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::{CryptoRng, RngCore};
+use rayon::prelude::*;
+use sha2::Sha256;
+
+use super::{mul_ct, ConditionalSwap, Parameters, VerifyingKey};
+use crate::bls::Engine;
+use crate::multicore::{install_in, Worker, THREAD_POOL};
+use crate::multiexp::{multiexp, FullDensity};
+use crate::transcript::FiatShamirRng;
+
+/// Domain of the transcript deriving the challenge of a contribution.
+const UPDATE_DOMAIN: &[u8] = b"bellperson-groth16-update";
+
+/// The circuit-specific parameters, updated by the contributions to the second phase of
+/// a ceremony. The first phase, the powers of tau, fixes `a`, `b_g1`, `b_g2` and all
+/// of the verifying key but `delta`, which each contributor rerandomizes.
+pub type SRS<E> = Parameters<E>;
+
+/// The public record of an update of an [`SRS`] by [`update_srs`].
+///
+/// The contributor multiplies `delta` by a secret `d` and proves knowledge of `d` with
+/// a Schnorr proof for `delta_after = d * delta_before`: it commits to
+/// `commitment = k * delta_before` for a random `k` and answers the challenge `c` with
+/// `response = k + c * d`.
+#[derive(Clone, Debug)]
+pub struct SRSContribution<E: Engine> {
+    pub delta_after: E::G1Affine,
+    pub commitment: E::G1Affine,
+    pub response: E::Fr,
+}
+
+impl<E: Engine> PartialEq for SRSContribution<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta_after == other.delta_after
+            && self.commitment == other.commitment
+            && self.response == other.response
+    }
+}
+
+/// Contributes the entropy of `rng` to `old_srs`, returning the updated parameters and
+/// the record of the contribution, to be checked with [`verify_srs_update`].
+///
+/// `delta` is multiplied by a random `d` and `h` and `l` by its inverse. Proofs for the
+/// new parameters are sound as long as one contributor, or the initial setup, discarded
+/// its secret.
+pub fn update_srs<E: Engine, R: RngCore + CryptoRng>(
+    old_srs: &SRS<E>,
+    rng: &mut R,
+) -> (SRS<E>, SRSContribution<E>)
+where
+    E::G1: ConditionalSwap,
+    E::G2: ConditionalSwap,
+{
+    let d = loop {
+        let d = E::Fr::random(&mut *rng);
+        if !d.is_zero() {
+            break d;
+        }
+    };
+    let d_inverse = d.inverse().expect("d is not zero");
+
+    // `d` is the secret of the contributor, so it is multiplied in constant time.
+    let delta_before = old_srs.vk.delta_g1;
+    let delta_g1 = mul_ct(delta_before.into_projective(), d).into_affine();
+    let delta_g2 = mul_ct(old_srs.vk.delta_g2.into_projective(), d).into_affine();
+    let h = scale::<E>(&old_srs.h, d_inverse);
+    let l = scale::<E>(&old_srs.l, d_inverse);
+
+    let k = E::Fr::random(&mut *rng);
+    let commitment = mul_ct(delta_before.into_projective(), k).into_affine();
+    let c = challenge::<E>(&delta_before, &delta_g1, &commitment);
+    let mut response = c;
+    response.mul_assign(&d);
+    response.add_assign(&k);
+
+    let srs = Parameters {
+        vk: VerifyingKey {
+            delta_g1,
+            delta_g2,
+            ..old_srs.vk.clone()
+        },
+        h: Arc::new(h),
+        l: Arc::new(l),
+        a: old_srs.a.clone(),
+        b_g1: old_srs.b_g1.clone(),
+        b_g2: old_srs.b_g2.clone(),
+    };
+    let contribution = SRSContribution {
+        delta_after: delta_g1,
+        commitment,
+        response,
+    };
+
+    (srs, contribution)
+}
+
+/// Checks that `new` is `old` updated by `contrib`: the parameters fixed by the first
+/// phase are unchanged, the contributor knows `d` with `new.delta = d * old.delta`, and
+/// `h` and `l` were divided by the same `d`.
+///
+/// `h` and `l` are compared with a random linear combination with the pairing equation
+/// `e(sum r_i h'_i, delta'_2) = e(sum r_i h_i, delta_2)`, so the check costs two
+/// multiexps on each side instead of a pairing per element.
+pub fn verify_srs_update<E: Engine>(
+    old: &SRS<E>,
+    new: &SRS<E>,
+    contrib: &SRSContribution<E>,
+) -> bool {
+    let (old_vk, new_vk) = (&old.vk, &new.vk);
+    if old_vk.alpha_g1 != new_vk.alpha_g1
+        || old_vk.beta_g1 != new_vk.beta_g1
+        || old_vk.beta_g2 != new_vk.beta_g2
+        || old_vk.gamma_g2 != new_vk.gamma_g2
+        || old_vk.ic != new_vk.ic
+        || old.a != new.a
+        || old.b_g1 != new.b_g1
+        || old.b_g2 != new.b_g2
+        || old.h.len() != new.h.len()
+        || old.l.len() != new.l.len()
+    {
+        return false;
+    }
+    if contrib.delta_after != new_vk.delta_g1 || new_vk.delta_g1.is_zero() {
+        return false;
+    }
+
+    // response * delta = commitment + c * delta'
+    let c = challenge::<E>(&old_vk.delta_g1, &new_vk.delta_g1, &contrib.commitment);
+    let lhs = old_vk.delta_g1.mul(contrib.response.into_repr());
+    let mut rhs = new_vk.delta_g1.mul(c.into_repr());
+    rhs.add_assign_mixed(&contrib.commitment);
+    if lhs != rhs {
+        return false;
+    }
+
+    // The same d in G2: e(delta_1, delta'_2) = e(delta'_1, delta_2)
+    if E::pairing(old_vk.delta_g1, new_vk.delta_g2) != E::pairing(new_vk.delta_g1, old_vk.delta_g2)
+    {
+        return false;
+    }
+
+    let rng = &mut rand::thread_rng();
+    let h_scalars = (0..old.h.len())
+        .map(|_| E::Fr::random(&mut *rng).into_repr())
+        .collect::<Vec<_>>();
+    let l_scalars = (0..old.l.len())
+        .map(|_| E::Fr::random(&mut *rng).into_repr())
+        .collect::<Vec<_>>();
+    let combine = |srs: &SRS<E>| -> Option<E::G1Affine> {
+        let mut sum = combination::<E>(&srs.h, &h_scalars)?;
+        sum.add_assign(&combination::<E>(&srs.l, &l_scalars)?);
+        Some(sum.into_affine())
+    };
+    match (combine(old), combine(new)) {
+        (Some(old_sum), Some(new_sum)) => {
+            E::pairing(new_sum, new_vk.delta_g2) == E::pairing(old_sum, old_vk.delta_g2)
+        }
+        _ => false,
+    }
+}
+
+/// Multiplies all of `points` by the secret `scalar`.
+fn scale<E: Engine>(points: &[E::G1Affine], scalar: E::Fr) -> Vec<E::G1Affine>
+where
+    E::G1: ConditionalSwap,
+{
+    let mut scaled = install_in(&THREAD_POOL, || {
+        points
+            .par_iter()
+            .map(|p| mul_ct(p.into_projective(), scalar))
+            .collect::<Vec<_>>()
+    });
+    E::G1::batch_normalization(&mut scaled);
+    scaled.into_iter().map(|p| p.into_affine()).collect()
+}
+
+/// `sum s_i p_i`, or `None` if the multiexp fails.
+fn combination<E: Engine>(
+    points: &Arc<Vec<E::G1Affine>>,
+    scalars: &[<E::Fr as PrimeField>::Repr],
+) -> Option<E::G1> {
+    if points.is_empty() {
+        return Some(E::G1::zero());
+    }
+
+    multiexp(
+        &Worker::new(),
+        (points.clone(), 0),
+        FullDensity,
+        Arc::new(scalars.to_vec()),
+        &mut None,
+    )
+    .wait()
+    .ok()
+}
+
+fn challenge<E: Engine>(
+    delta_before: &E::G1Affine,
+    delta_after: &E::G1Affine,
+    commitment: &E::G1Affine,
+) -> E::Fr {
+    let mut transcript = FiatShamirRng::<Sha256>::new(UPDATE_DOMAIN);
+    transcript.absorb_g1(delta_before);
+    transcript.absorb_g1(delta_after);
+    transcript.absorb_g1(commitment);
+
+    E::Fr::random(&mut transcript)
+}
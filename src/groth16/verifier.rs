@@ -2,13 +2,16 @@ use crate::bls::{Engine, PairingCurveAffine};
 use ff::{Field, PrimeField};
 use groupy::{CurveAffine, CurveProjective};
 use rayon::prelude::*;
+use std::marker::PhantomData;
 
-use super::{multiscalar, PreparedVerifyingKey, Proof, VerifyingKey};
-use crate::multicore::VERIFIER_POOL as POOL;
+use super::{multiscalar, AnyCircuit, CircuitTag, PreparedVerifyingKey, Proof, VerifyingKey};
+use crate::multicore::{install_in, VERIFIER_POOL as POOL};
 use crate::SynthesisError;
 
 /// Generate a prepared verifying key, required to verify a proofs.
-pub fn prepare_verifying_key<E: Engine>(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
+pub fn prepare_verifying_key<E: Engine, C: CircuitTag>(
+    vk: &VerifyingKey<E, C>,
+) -> PreparedVerifyingKey<E, C> {
     let mut neg_gamma = vk.gamma_g2;
     neg_gamma.negate();
     let mut neg_delta = vk.delta_g2;
@@ -24,17 +27,17 @@ pub fn prepare_verifying_key<E: Engine>(vk: &VerifyingKey<E>) -> PreparedVerifyi
         delta_g2: vk.delta_g2.prepare(),
         ic: vk.ic.clone(),
         multiscalar,
+        fingerprint: vk.fingerprint(),
+        circuit: PhantomData,
     }
 }
 
-/// Verify a single Proof.
-pub fn verify_proof<'a, E: Engine>(
-    pvk: &'a PreparedVerifyingKey<E>,
-    proof: &Proof<E>,
+/// Verify a single Proof, of the circuit of the key.
+pub fn verify_proof<'a, E: Engine, C: CircuitTag>(
+    pvk: &'a PreparedVerifyingKey<E, C>,
+    proof: &Proof<E, C>,
     public_inputs: &[E::Fr],
 ) -> Result<bool, SynthesisError> {
-    use multiscalar::MultiscalarPrecomp;
-
     if (public_inputs.len() + 1) != pvk.ic.len() {
         return Err(SynthesisError::MalformedVerifyingKey);
     }
@@ -54,9 +57,9 @@ pub fn verify_proof<'a, E: Engine>(
     // Miller Loop for inputs * (-gamma)
     let mut ml_acc = E::Fqk::zero();
 
-    POOL.install(|| {
+    install_in(&POOL, || {
         // Start the two independent miller loops
-        rayon::scope(|s| {
+        rayon::in_place_scope(|s| {
             // - Thread 1: Calculate ML alpha * beta
             let ml_a_b = &mut ml_a_b;
             s.spawn(move |_| {
@@ -68,20 +71,9 @@ pub fn verify_proof<'a, E: Engine>(
             s.spawn(move |_| *ml_all = E::miller_loop(&[(&proof.c.prepare(), &pvk.neg_delta_g2)]));
 
             // - Accumulate inputs (on the current thread)
-            let subset = pvk.multiscalar.at_point(1);
-            let public_inputs_repr: Vec<_> =
-                public_inputs.iter().map(PrimeField::into_repr).collect();
-
-            let mut acc = multiscalar::par_multiscalar::<&multiscalar::Getter<E>, E>(
-                &multiscalar::ScalarList::Slice(&public_inputs_repr),
-                &subset,
-                std::mem::size_of::<<E::Fr as PrimeField>::Repr>() * 8,
-            );
-
-            acc.add_assign_mixed(&pvk.ic[0]);
+            let acc_aff = accumulate_inputs(pvk, public_inputs);
 
             // Calculate ML inputs * (-gamma)
-            let acc_aff = acc.into_affine();
             ml_acc = E::miller_loop(&[(&acc_aff.prepare(), &pvk.neg_gamma_g2)]);
         });
     });
@@ -97,11 +89,165 @@ pub fn verify_proof<'a, E: Engine>(
     Ok(actual == pvk.alpha_g1_beta_g2)
 }
 
+/// `ic[0] + sum_i public_inputs[i] * ic[i + 1]`, the number of inputs must match the key.
+fn accumulate_inputs<E: Engine, C: CircuitTag>(
+    pvk: &PreparedVerifyingKey<E, C>,
+    public_inputs: &[E::Fr],
+) -> E::G1Affine {
+    use multiscalar::MultiscalarPrecomp;
+
+    let subset = pvk.multiscalar.at_point(1);
+    let public_inputs_repr: Vec<_> = public_inputs.iter().map(PrimeField::into_repr).collect();
+
+    let mut acc = multiscalar::par_multiscalar::<&multiscalar::Getter<E>, E>(
+        &multiscalar::ScalarList::Slice(&public_inputs_repr),
+        &subset,
+        std::mem::size_of::<<E::Fr as PrimeField>::Repr>() * 8,
+    );
+    acc.add_assign_mixed(&pvk.ic[0]);
+
+    acc.into_affine()
+}
+
+/// Combines the public inputs with the verifying key, for
+/// [`verify_proof_online_phase`].
+pub fn prepare_inputs<E: Engine, C: CircuitTag>(
+    pvk: &PreparedVerifyingKey<E, C>,
+    public_inputs: &[E::Fr],
+) -> Result<E::G1Affine, SynthesisError> {
+    if (public_inputs.len() + 1) != pvk.ic.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+
+    Ok(install_in(&POOL, || accumulate_inputs(pvk, public_inputs)))
+}
+
+/// The part of [`verify_proof`] which depends on the proof: a single Miller loop over
+/// `A * B`, `C * (-delta)` and `inputs * (-gamma)`, with the inputs from
+/// [`prepare_inputs`].
+///
+/// Everything which only depends on the key is precomputed by [`prepare_verifying_key`].
+/// The result still needs the final exponentiation, which is done by
+/// [`verify_proof_final_phase`], e.g. on another thread while the next proof is
+/// processed.
+pub fn verify_proof_online_phase<E: Engine, C: CircuitTag>(
+    prepared_inputs: E::G1Affine,
+    proof: &Proof<E, C>,
+    pvk: &PreparedVerifyingKey<E, C>,
+) -> E::Fqk {
+    E::miller_loop(&[
+        (&proof.a.prepare(), &proof.b.prepare()),
+        (&proof.c.prepare(), &pvk.neg_delta_g2),
+        (&prepared_inputs.prepare(), &pvk.neg_gamma_g2),
+    ])
+}
+
+/// Checks the result of [`verify_proof_online_phase`], returning whether the proof is
+/// valid.
+pub fn verify_proof_final_phase<E: Engine, C: CircuitTag>(
+    pvk: &PreparedVerifyingKey<E, C>,
+    ml: &E::Fqk,
+) -> bool {
+    match E::final_exponentiation(ml) {
+        Some(actual) => actual == pvk.alpha_g1_beta_g2,
+        None => false,
+    }
+}
+
+/// Verifies a single proof whose parts arrive one at a time, e.g. in different messages
+/// of an interactive protocol.
+///
+/// The parts must be given in order: [`feed_ab`](Self::feed_ab), then
+/// [`feed_public_inputs`](Self::feed_public_inputs), then [`feed_c`](Self::feed_c), and
+/// finally [`finalize`](Self::finalize). The Miller loop of each part is computed as soon
+/// as it arrives. A method called out of order returns [`SynthesisError::OutOfOrder`]
+/// and leaves the state unchanged.
+pub struct VerifierState<'a, E: Engine, C: CircuitTag = AnyCircuit> {
+    pvk: &'a PreparedVerifyingKey<E, C>,
+    stage: Stage<E>,
+}
+
+enum Stage<E: Engine> {
+    Start,
+    /// `ml_a_b` is the Miller loop of `A * B`.
+    AB {
+        ml_a_b: E::Fqk,
+    },
+    /// `ml` also includes `inputs * (-gamma)`.
+    Inputs {
+        ml: E::Fqk,
+    },
+    /// `ml_all` is the product of all three Miller loops.
+    C {
+        ml_all: E::Fqk,
+    },
+    Done,
+}
+
+impl<'a, E: Engine, C: CircuitTag> VerifierState<'a, E, C> {
+    pub fn new(pvk: &'a PreparedVerifyingKey<E, C>) -> Self {
+        VerifierState {
+            pvk,
+            stage: Stage::Start,
+        }
+    }
+
+    /// Takes the `A` and `B` elements of the proof.
+    pub fn feed_ab(&mut self, a: E::G1Affine, b: E::G2Affine) -> Result<(), SynthesisError> {
+        match self.stage {
+            Stage::Start => {
+                let ml_a_b = E::miller_loop(&[(&a.prepare(), &b.prepare())]);
+                self.stage = Stage::AB { ml_a_b };
+                Ok(())
+            }
+            _ => Err(SynthesisError::OutOfOrder("A and B")),
+        }
+    }
+
+    /// Takes the public inputs, the number of which must match the verifying key.
+    pub fn feed_public_inputs(&mut self, public_inputs: &[E::Fr]) -> Result<(), SynthesisError> {
+        match self.stage {
+            Stage::AB { ml_a_b } => {
+                let acc = prepare_inputs(self.pvk, public_inputs)?;
+                let mut ml = E::miller_loop(&[(&acc.prepare(), &self.pvk.neg_gamma_g2)]);
+                ml.mul_assign(&ml_a_b);
+                self.stage = Stage::Inputs { ml };
+                Ok(())
+            }
+            _ => Err(SynthesisError::OutOfOrder("public inputs")),
+        }
+    }
+
+    /// Takes the `C` element of the proof.
+    pub fn feed_c(&mut self, c: E::G1Affine) -> Result<(), SynthesisError> {
+        match self.stage {
+            Stage::Inputs { ml } => {
+                let mut ml_all = E::miller_loop(&[(&c.prepare(), &self.pvk.neg_delta_g2)]);
+                ml_all.mul_assign(&ml);
+                self.stage = Stage::C { ml_all };
+                Ok(())
+            }
+            _ => Err(SynthesisError::OutOfOrder("C")),
+        }
+    }
+
+    /// Does the final exponentiation, returning whether the proof is valid.
+    pub fn finalize(&mut self) -> Result<bool, SynthesisError> {
+        match self.stage {
+            Stage::C { ml_all } => {
+                self.stage = Stage::Done;
+                Ok(verify_proof_final_phase(self.pvk, &ml_all))
+            }
+            _ => Err(SynthesisError::OutOfOrder("finalization")),
+        }
+    }
+}
+
 /// Randomized batch verification - see Appendix B.2 in Zcash spec
-pub fn verify_proofs_batch<'a, E: Engine, R: rand::RngCore>(
-    pvk: &'a PreparedVerifyingKey<E>,
+pub fn verify_proofs_batch<'a, E: Engine, C: CircuitTag, R: rand::RngCore>(
+    pvk: &'a PreparedVerifyingKey<E, C>,
     rng: &mut R,
-    proofs: &[&Proof<E>],
+    proofs: &[&Proof<E, C>],
     public_inputs: &[Vec<E::Fr>],
 ) -> Result<bool, SynthesisError>
 where
@@ -159,11 +305,11 @@ where
     // Y^-Accum_Y
     let mut y = E::Fqk::zero();
 
-    POOL.install(|| {
+    install_in(&POOL, || {
         let accum_y = &accum_y;
         let rand_z_repr = &rand_z_repr;
 
-        rayon::scope(|s| {
+        rayon::in_place_scope(|s| {
             // - Thread 1: Calculate MillerLoop(\sum Accum_Gamma)
             let ml_g = &mut ml_g;
             s.spawn(move |_| {
@@ -221,27 +367,41 @@ where
             // - Thread 3: Calculate MillerLoop(Accum_AB)
             let acc_ab = &mut acc_ab;
             s.spawn(move |_| {
-                let accum_ab_mls: Vec<_> = proofs
+                // [z_j] pi_j,A
+                let mut mul_a: Vec<_> = proofs
                     .par_iter()
                     .zip(rand_z_repr.par_iter())
-                    .map(|(proof, rand)| {
-                        // [z_j] pi_j,A
-                        let mul_a = proof.a.mul(*rand);
-
-                        // -pi_j,B
-                        let mut cur_neg_b = proof.b.into_projective();
-                        cur_neg_b.negate();
-
-                        E::miller_loop(&[(
-                            &mul_a.into_affine().prepare(),
-                            &cur_neg_b.into_affine().prepare(),
-                        )])
+                    .map(|(proof, rand)| proof.a.mul(*rand))
+                    .collect();
+                // Normalizes all points with a single inversion, by Montgomery's trick,
+                // so that converting them to affine below needs none.
+                E::G1::batch_normalization(&mut mul_a);
+
+                // A Miller loop over several pairs squares its accumulator once for all
+                // of them, so each thread runs a single one over its chunk of proofs.
+                let threads = rayon::current_num_threads();
+                let chunk_size = (num_proofs + threads - 1) / threads;
+                let accum_ab_mls: Vec<_> = proofs
+                    .par_chunks(chunk_size)
+                    .zip(mul_a.par_chunks(chunk_size))
+                    .map(|(proofs, mul_a)| {
+                        let mut prepared = Vec::with_capacity(proofs.len());
+                        for (proof, mul_a) in proofs.iter().zip(mul_a) {
+                            // -pi_j,B, which is affine already
+                            let mut cur_neg_b = proof.b;
+                            cur_neg_b.negate();
+
+                            prepared.push((mul_a.into_affine().prepare(), cur_neg_b.prepare()));
+                        }
+                        let pairs: Vec<_> = prepared.iter().map(|(a, b)| (a, b)).collect();
+
+                        E::miller_loop(&pairs)
                     })
                     .collect();
 
                 // Accum_AB = mul_j(ml((zj*proof_aj), -proof_bj))
                 *acc_ab = accum_ab_mls[0];
-                for accum in accum_ab_mls.iter().skip(1).take(num_proofs) {
+                for accum in accum_ab_mls.iter().skip(1) {
                     acc_ab.mul_assign(accum);
                 }
             });
@@ -4,12 +4,14 @@ use groupy::{CurveAffine, EncodedPoint};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use memmap::Mmap;
 use std::io::{self, Read, Write};
+use std::marker::PhantomData;
 use std::mem;
 
-use super::multiscalar;
+use super::{multiscalar, AnyCircuit, CircuitTag};
 
-#[derive(Clone)]
-pub struct VerifyingKey<E: Engine> {
+const FINGERPRINT_PERSONALIZATION: &[u8; 8] = b"BPVKFP00";
+
+pub struct VerifyingKey<E: Engine, C: CircuitTag = AnyCircuit> {
     // alpha in g1 for verifying and for creating A/C elements of
     // proof. Never the point at infinity.
     pub alpha_g1: E::G1Affine,
@@ -34,9 +36,27 @@ pub struct VerifyingKey<E: Engine> {
     // this is the same size as the number of inputs, and never contains points
     // at infinity.
     pub ic: Vec<E::G1Affine>,
+
+    /// The circuit of the key, see [`CircuitTag`].
+    pub circuit: PhantomData<fn() -> C>,
+}
+
+impl<E: Engine, C: CircuitTag> Clone for VerifyingKey<E, C> {
+    fn clone(&self) -> Self {
+        VerifyingKey {
+            alpha_g1: self.alpha_g1,
+            beta_g1: self.beta_g1,
+            beta_g2: self.beta_g2,
+            gamma_g2: self.gamma_g2,
+            delta_g1: self.delta_g1,
+            delta_g2: self.delta_g2,
+            ic: self.ic.clone(),
+            circuit: PhantomData,
+        }
+    }
 }
 
-impl<E: Engine> PartialEq for VerifyingKey<E> {
+impl<E: Engine, C: CircuitTag> PartialEq for VerifyingKey<E, C> {
     fn eq(&self, other: &Self) -> bool {
         self.alpha_g1 == other.alpha_g1
             && self.beta_g1 == other.beta_g1
@@ -48,7 +68,28 @@ impl<E: Engine> PartialEq for VerifyingKey<E> {
     }
 }
 
-impl<E: Engine> VerifyingKey<E> {
+impl<E: Engine, C: CircuitTag> VerifyingKey<E, C> {
+    pub fn new(
+        alpha_g1: E::G1Affine,
+        beta_g1: E::G1Affine,
+        beta_g2: E::G2Affine,
+        gamma_g2: E::G2Affine,
+        delta_g1: E::G1Affine,
+        delta_g2: E::G2Affine,
+        ic: Vec<E::G1Affine>,
+    ) -> Self {
+        VerifyingKey {
+            alpha_g1,
+            beta_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g1,
+            delta_g2,
+            ic,
+            circuit: PhantomData,
+        }
+    }
+
     pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         writer.write_all(self.alpha_g1.into_uncompressed().as_ref())?;
         writer.write_all(self.beta_g1.into_uncompressed().as_ref())?;
@@ -64,6 +105,48 @@ impl<E: Engine> VerifyingKey<E> {
         Ok(())
     }
 
+    /// The BLAKE2s hash of the serialization of this key, identifying it e.g. in
+    /// caches of verification results.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)
+            .expect("writing to a vec never fails");
+
+        let hash = blake2s_simd::Params::new()
+            .hash_length(32)
+            .personal(FINGERPRINT_PERSONALIZATION)
+            .hash(&bytes);
+
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(hash.as_bytes());
+        fingerprint
+    }
+
+    /// Forgets the circuit of this key.
+    pub fn into_any(self) -> VerifyingKey<E> {
+        self.retag()
+    }
+
+    fn retag<T: CircuitTag>(self) -> VerifyingKey<E, T> {
+        VerifyingKey {
+            alpha_g1: self.alpha_g1,
+            beta_g1: self.beta_g1,
+            beta_g2: self.beta_g2,
+            gamma_g2: self.gamma_g2,
+            delta_g1: self.delta_g1,
+            delta_g2: self.delta_g2,
+            ic: self.ic,
+            circuit: PhantomData,
+        }
+    }
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    /// Asserts that this key belongs to the circuit `C`, e.g. after reading it.
+    pub fn for_circuit<C: CircuitTag>(self) -> VerifyingKey<E, C> {
+        self.retag()
+    }
+
     pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
         let mut g1_repr = <E::G1Affine as CurveAffine>::Uncompressed::empty();
         let mut g2_repr = <E::G2Affine as CurveAffine>::Uncompressed::empty();
@@ -129,6 +212,7 @@ impl<E: Engine> VerifyingKey<E> {
             delta_g1,
             delta_g2,
             ic,
+            circuit: PhantomData,
         })
     }
 
@@ -208,11 +292,12 @@ impl<E: Engine> VerifyingKey<E> {
             delta_g1,
             delta_g2,
             ic,
+            circuit: PhantomData,
         })
     }
 }
 
-pub struct PreparedVerifyingKey<E: Engine> {
+pub struct PreparedVerifyingKey<E: Engine, C: CircuitTag = AnyCircuit> {
     /// Pairing result of alpha*beta
     pub(crate) alpha_g1_beta_g2: E::Fqk,
     /// -gamma in G2 (used for single)
@@ -227,4 +312,37 @@ pub struct PreparedVerifyingKey<E: Engine> {
     pub(crate) ic: Vec<E::G1Affine>,
 
     pub(crate) multiscalar: multiscalar::MultiscalarPrecompOwned<E>,
+
+    /// [`VerifyingKey::fingerprint`] of the key this one was prepared from.
+    pub(crate) fingerprint: [u8; 32],
+
+    pub(crate) circuit: PhantomData<fn() -> C>,
+}
+
+impl<E: Engine, C: CircuitTag> PreparedVerifyingKey<E, C> {
+    /// Forgets the circuit of this key.
+    pub fn into_any(self) -> PreparedVerifyingKey<E> {
+        self.retag()
+    }
+
+    fn retag<T: CircuitTag>(self) -> PreparedVerifyingKey<E, T> {
+        PreparedVerifyingKey {
+            alpha_g1_beta_g2: self.alpha_g1_beta_g2,
+            neg_gamma_g2: self.neg_gamma_g2,
+            neg_delta_g2: self.neg_delta_g2,
+            gamma_g2: self.gamma_g2,
+            delta_g2: self.delta_g2,
+            ic: self.ic,
+            multiscalar: self.multiscalar,
+            fingerprint: self.fingerprint,
+            circuit: PhantomData,
+        }
+    }
+}
+
+impl<E: Engine> PreparedVerifyingKey<E> {
+    /// Asserts that this key belongs to the circuit `C`, e.g. after looking it up.
+    pub fn for_circuit<C: CircuitTag>(self) -> PreparedVerifyingKey<E, C> {
+        self.retag()
+    }
 }
@@ -0,0 +1,285 @@
+//! An inner product argument with a transparent setup.
+//!
+//! Following [Bulletproofs], the prover convinces the verifier that the vectors `a` and
+//! `b` committed to by `P = <a, G> + <b, H>` have the inner product `c`. In every round
+//! both vectors and the generators are halved, with the cross terms `L` and `R` sent to
+//! the verifier, so a proof for `n` elements has `2 log n` points. The challenges are
+//! derived with a [`FiatShamirRng`].
+//!
+//! The setup, an [`IPASRS`], is derived from a hash and needs no trusted party, at the
+//! price of a verifier linear in `n`. It is meant for small inner products; large
+//! circuits are better served by Groth16.
+//!
+//! [Bulletproofs]: https://eprint.iacr.org/2017/1066
+
+mod proof;
+mod srs;
+
+pub use self::proof::*;
+pub use self::srs::*;
+
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use sha2::Sha256;
+
+use crate::bls::Engine;
+use crate::transcript::FiatShamirRng;
+
+/// Domain of the transcript deriving the challenges.
+const IPA_DOMAIN: &[u8] = b"bellperson-ipa";
+
+/// The commitment `<a, G> + <b, H>` to the vectors, which a proof of their inner
+/// product is verified against.
+///
+/// Panics if the vectors do not have the same length or are longer than the SRS.
+pub fn ipa_commit<E: Engine>(srs: &IPASRS<E>, a: &[E::Fr], b: &[E::Fr]) -> E::G1Affine {
+    assert_eq!(a.len(), b.len(), "the vectors must have the same length");
+    assert!(a.len() <= srs.size(), "the vectors are longer than the SRS");
+
+    let mut commitment = inner_product_g::<E>(&srs.g[..a.len()], a);
+    commitment.add_assign(&inner_product_g::<E>(&srs.h[..b.len()], b));
+
+    commitment.into_affine()
+}
+
+/// Proves the inner product of `a` and `b` for their [`ipa_commit`]ment.
+///
+/// Panics if the vectors do not have the same length, a power of two, or are longer
+/// than the SRS.
+pub fn ipa_prove<E: Engine>(srs: &IPASRS<E>, a: &[E::Fr], b: &[E::Fr]) -> IPAProof<E> {
+    assert!(
+        a.len().is_power_of_two(),
+        "the length of the vectors must be a power of two"
+    );
+    let commitment = ipa_commit(srs, a, b);
+    let c = inner_product::<E>(a, b);
+
+    let mut transcript = transcript::<E>(&commitment, &c);
+    let u = scaled_u(srs, &mut transcript);
+
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    let mut g = srs.g[..a.len()].to_vec();
+    let mut h = srs.h[..b.len()].to_vec();
+    let mut ls = Vec::new();
+    let mut rs = Vec::new();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_lo, a_hi) = a.split_at(half);
+        let (b_lo, b_hi) = b.split_at(half);
+        let (g_lo, g_hi) = g.split_at(half);
+        let (h_lo, h_hi) = h.split_at(half);
+
+        // L = <a_lo, G_hi> + <b_hi, H_lo> + <a_lo, b_hi> U
+        let mut l = inner_product_g::<E>(g_hi, a_lo);
+        l.add_assign(&inner_product_g::<E>(h_lo, b_hi));
+        l.add_assign(&u.mul(inner_product::<E>(a_lo, b_hi).into_repr()));
+        // R = <a_hi, G_lo> + <b_lo, H_hi> + <a_hi, b_lo> U
+        let mut r = inner_product_g::<E>(g_lo, a_hi);
+        r.add_assign(&inner_product_g::<E>(h_hi, b_lo));
+        r.add_assign(&u.mul(inner_product::<E>(a_hi, b_lo).into_repr()));
+        let (l, r) = (l.into_affine(), r.into_affine());
+
+        let (x, xinv) = round_challenge::<E>(&mut transcript, &l, &r);
+        a = fold_scalars::<E>(a_lo, a_hi, &x, &xinv);
+        b = fold_scalars::<E>(b_lo, b_hi, &xinv, &x);
+        g = fold_points::<E>(g_lo, g_hi, &xinv, &x);
+        h = fold_points::<E>(h_lo, h_hi, &x, &xinv);
+
+        ls.push(l);
+        rs.push(r);
+    }
+
+    IPAProof {
+        l: ls,
+        r: rs,
+        a: a[0],
+        b: b[0],
+    }
+}
+
+/// Verifies that the vectors committed to by `commitment`, of length `2^rounds` with
+/// `rounds` the number of rounds of the proof, have the inner product `c`.
+pub fn ipa_verify<E: Engine>(
+    srs: &IPASRS<E>,
+    commitment: &E::G1Affine,
+    c: &E::Fr,
+    proof: &IPAProof<E>,
+) -> bool {
+    let rounds = proof.l.len();
+    if proof.r.len() != rounds
+        || rounds >= std::mem::size_of::<usize>() * 8
+        || (1 << rounds) > srs.size()
+    {
+        return false;
+    }
+
+    let mut transcript = transcript::<E>(commitment, c);
+    let u = scaled_u(srs, &mut transcript);
+
+    // P' = P + c U, then P' = x^2 L + P' + x^-2 R in every round.
+    let mut p = u.mul(c.into_repr());
+    p.add_assign_mixed(commitment);
+    let mut g = srs.g[..1 << rounds].to_vec();
+    let mut h = srs.h[..1 << rounds].to_vec();
+    for (l, r) in proof.l.iter().zip(&proof.r) {
+        let (x, xinv) = round_challenge::<E>(&mut transcript, l, r);
+        let mut x2 = x;
+        x2.square();
+        let mut xinv2 = xinv;
+        xinv2.square();
+
+        p.add_assign(&l.mul(x2.into_repr()));
+        p.add_assign(&r.mul(xinv2.into_repr()));
+
+        let half = g.len() / 2;
+        g = fold_points::<E>(&g[..half], &g[half..], &xinv, &x);
+        h = fold_points::<E>(&h[..half], &h[half..], &x, &xinv);
+    }
+
+    // P' = a G + b H + ab U
+    let mut ab = proof.a;
+    ab.mul_assign(&proof.b);
+    let mut expected = g[0].mul(proof.a.into_repr());
+    expected.add_assign(&h[0].mul(proof.b.into_repr()));
+    expected.add_assign(&u.mul(ab.into_repr()));
+
+    expected == p
+}
+
+fn transcript<E: Engine>(commitment: &E::G1Affine, c: &E::Fr) -> FiatShamirRng<Sha256> {
+    let mut transcript = FiatShamirRng::new(IPA_DOMAIN);
+    transcript.absorb_g1(commitment);
+    transcript.absorb_fr(c);
+    transcript
+}
+
+/// `U` scaled by a challenge, so that the prover cannot choose the inner product
+/// independently of the commitment.
+fn scaled_u<E: Engine>(srs: &IPASRS<E>, transcript: &mut FiatShamirRng<Sha256>) -> E::G1Affine {
+    srs.u
+        .mul(E::Fr::random(transcript).into_repr())
+        .into_affine()
+}
+
+/// The challenge of a round and its inverse.
+fn round_challenge<E: Engine>(
+    transcript: &mut FiatShamirRng<Sha256>,
+    l: &E::G1Affine,
+    r: &E::G1Affine,
+) -> (E::Fr, E::Fr) {
+    transcript.absorb_g1(l);
+    transcript.absorb_g1(r);
+    loop {
+        let x = E::Fr::random(&mut *transcript);
+        if let Some(xinv) = x.inverse() {
+            return (x, xinv);
+        }
+    }
+}
+
+fn inner_product<E: Engine>(a: &[E::Fr], b: &[E::Fr]) -> E::Fr {
+    let mut acc = E::Fr::zero();
+    for (a, b) in a.iter().zip(b) {
+        let mut product = *a;
+        product.mul_assign(b);
+        acc.add_assign(&product);
+    }
+    acc
+}
+
+fn inner_product_g<E: Engine>(points: &[E::G1Affine], scalars: &[E::Fr]) -> E::G1 {
+    let mut acc = E::G1::zero();
+    for (point, scalar) in points.iter().zip(scalars) {
+        acc.add_assign(&point.mul(scalar.into_repr()));
+    }
+    acc
+}
+
+/// `lo * x_lo + hi * x_hi`, elementwise.
+fn fold_scalars<E: Engine>(lo: &[E::Fr], hi: &[E::Fr], x_lo: &E::Fr, x_hi: &E::Fr) -> Vec<E::Fr> {
+    lo.iter()
+        .zip(hi)
+        .map(|(lo, hi)| {
+            let mut lo = *lo;
+            lo.mul_assign(x_lo);
+            let mut hi = *hi;
+            hi.mul_assign(x_hi);
+            lo.add_assign(&hi);
+            lo
+        })
+        .collect()
+}
+
+/// `lo * x_lo + hi * x_hi`, elementwise.
+fn fold_points<E: Engine>(
+    lo: &[E::G1Affine],
+    hi: &[E::G1Affine],
+    x_lo: &E::Fr,
+    x_hi: &E::Fr,
+) -> Vec<E::G1Affine> {
+    let mut folded = lo
+        .iter()
+        .zip(hi)
+        .map(|(lo, hi)| {
+            let mut point = lo.mul(x_lo.into_repr());
+            point.add_assign(&hi.mul(x_hi.into_repr()));
+            point
+        })
+        .collect::<Vec<_>>();
+    E::G1::batch_normalization(&mut folded);
+    folded.into_iter().map(|p| p.into_affine()).collect()
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_ipa() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let srs = IPASRS::<Bls12>::new(16);
+
+        for &n in &[1, 2, 8, 16] {
+            let a = (0..n).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+            let b = (0..n).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+            let commitment = ipa_commit(&srs, &a, &b);
+            let c = inner_product::<Bls12>(&a, &b);
+
+            let proof = ipa_prove(&srs, &a, &b);
+            assert_eq!(proof.l.len(), n.trailing_zeros() as usize);
+            assert!(ipa_verify(&srs, &commitment, &c, &proof));
+
+            // A wrong inner product, commitment or proof is rejected.
+            let mut wrong_c = c;
+            wrong_c.add_assign(&Fr::one());
+            assert!(!ipa_verify(&srs, &commitment, &wrong_c, &proof));
+            let other = ipa_commit(&srs, &b, &a);
+            assert!(!ipa_verify(&srs, &other, &c, &proof));
+            let mut wrong_proof = proof.clone();
+            wrong_proof.a.add_assign(&Fr::one());
+            assert!(!ipa_verify(&srs, &commitment, &c, &wrong_proof));
+
+            let mut bytes = Vec::new();
+            proof.write(&mut bytes).unwrap();
+            assert_eq!(IPAProof::<Bls12>::read(&bytes[..]).unwrap(), proof);
+        }
+
+        // The SRS must be large enough for the proof.
+        let a = (0..32).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let big_srs = IPASRS::<Bls12>::new(32);
+        assert_eq!(&big_srs.g[..16], &srs.g[..]);
+        let proof = ipa_prove(&big_srs, &a, &a);
+        let commitment = ipa_commit(&big_srs, &a, &a);
+        let c = inner_product::<Bls12>(&a, &a);
+        assert!(ipa_verify(&big_srs, &commitment, &c, &proof));
+        assert!(!ipa_verify(&srs, &commitment, &c, &proof));
+    }
+}
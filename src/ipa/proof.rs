@@ -0,0 +1,77 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{PrimeField, PrimeFieldRepr};
+use groupy::{CurveAffine, EncodedPoint};
+
+use crate::bls::Engine;
+
+/// An inner product argument, created by [`ipa_prove`](super::ipa_prove).
+///
+/// Holds the cross terms `L` and `R` of every halving round and the two scalars the
+/// vectors are folded into.
+#[derive(Clone, Debug)]
+pub struct IPAProof<E: Engine> {
+    pub l: Vec<E::G1Affine>,
+    pub r: Vec<E::G1Affine>,
+    pub a: E::Fr,
+    pub b: E::Fr,
+}
+
+impl<E: Engine> PartialEq for IPAProof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.l == other.l && self.r == other.r && self.a == other.a && self.b == other.b
+    }
+}
+
+impl<E: Engine> IPAProof<E> {
+    /// Writes the number of rounds, the compressed `L` and `R` of every round and the
+    /// little endian scalars.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.l.len() as u32)?;
+        for (l, r) in self.l.iter().zip(&self.r) {
+            writer.write_all(l.into_compressed().as_ref())?;
+            writer.write_all(r.into_compressed().as_ref())?;
+        }
+        self.a.into_repr().write_le(&mut writer)?;
+        self.b.into_repr().write_le(&mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        fn read_point<E: Engine, R: Read>(reader: &mut R) -> io::Result<E::G1Affine> {
+            let mut repr = <E::G1Affine as CurveAffine>::Compressed::empty();
+            reader.read_exact(repr.as_mut())?;
+            repr.into_affine()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+
+        fn read_scalar<E: Engine, R: Read>(reader: &mut R) -> io::Result<E::Fr> {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.read_le(reader)?;
+            E::Fr::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+
+        let rounds = reader.read_u32::<BigEndian>()? as usize;
+        // A proof for vectors of `2^rounds` elements, more than `usize::BITS` rounds
+        // cannot be valid.
+        if rounds >= std::mem::size_of::<usize>() * 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many rounds",
+            ));
+        }
+
+        let mut l = Vec::with_capacity(rounds);
+        let mut r = Vec::with_capacity(rounds);
+        for _ in 0..rounds {
+            l.push(read_point::<E, _>(&mut reader)?);
+            r.push(read_point::<E, _>(&mut reader)?);
+        }
+        let a = read_scalar::<E, _>(&mut reader)?;
+        let b = read_scalar::<E, _>(&mut reader)?;
+
+        Ok(IPAProof { l, r, a, b })
+    }
+}
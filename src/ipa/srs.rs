@@ -0,0 +1,47 @@
+use groupy::{CurveAffine, CurveProjective};
+use sha2::Sha256;
+
+use crate::bls::Engine;
+use crate::transcript::FiatShamirRng;
+
+/// Domain separator the generators of an [`IPASRS`] are derived from.
+const SRS_DOMAIN: &[u8] = b"bellperson-ipa-srs";
+
+/// The generators of inner product arguments over vectors of up to `size` elements.
+///
+/// All generators are hashed from a domain separator, so nobody knows discrete
+/// logarithms between them and there is no trapdoor to be destroyed after the setup.
+/// The generators of a smaller SRS are a prefix of those of a larger one.
+#[derive(Clone, Debug)]
+pub struct IPASRS<E: Engine> {
+    pub(super) g: Vec<E::G1Affine>,
+    pub(super) h: Vec<E::G1Affine>,
+    pub(super) u: E::G1Affine,
+}
+
+impl<E: Engine> PartialEq for IPASRS<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.g == other.g && self.h == other.h && self.u == other.u
+    }
+}
+
+impl<E: Engine> IPASRS<E> {
+    /// Derives the generators for vectors of up to `size` elements.
+    pub fn new(size: usize) -> Self {
+        let mut rng = FiatShamirRng::<Sha256>::new(SRS_DOMAIN);
+        let u = E::G1::random(&mut rng).into_affine();
+        let mut g = Vec::with_capacity(size);
+        let mut h = Vec::with_capacity(size);
+        for _ in 0..size {
+            g.push(E::G1::random(&mut rng).into_affine());
+            h.push(E::G1::random(&mut rng).into_affine());
+        }
+
+        IPASRS { g, h, u }
+    }
+
+    /// The largest number of elements of the vectors.
+    pub fn size(&self) -> usize {
+        self.g.len()
+    }
+}
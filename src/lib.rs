@@ -139,22 +139,46 @@
 #[macro_use]
 extern crate hex_literal;
 
+#[cfg(feature = "groth16")]
+pub mod archive;
 pub mod bls;
+#[cfg(feature = "groth16")]
+pub mod distributed;
 pub mod domain;
+pub mod folding;
 pub mod gadgets;
+pub mod glv;
 pub mod gpu;
 #[cfg(feature = "groth16")]
 pub mod groth16;
+pub mod ipa;
+pub mod marlin;
+pub mod mixed_radix;
 pub mod multicore;
 pub mod multiexp;
+#[cfg(feature = "groth16")]
+pub mod oracle;
+#[cfg(feature = "groth16")]
+pub mod pedersen;
+pub mod poseidon;
+#[cfg(feature = "groth16")]
+pub mod proving_server;
+pub mod r1cs;
+#[cfg(feature = "groth16")]
+pub mod registry;
+#[cfg(feature = "groth16")]
+pub mod service;
+pub mod transcript;
 
 pub mod util_cs;
+pub mod witness;
 use ff::{Field, ScalarEngine};
 
 use rustc_hash::FxHashMap as HashMap;
 use std::io;
 use std::marker::PhantomData;
 use std::ops::{Add, Sub};
+use util_cs::budget_cs::BudgetedCS;
 
 const BELLMAN_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -330,6 +354,18 @@ pub enum SynthesisError {
     /// During synthesis, our polynomials ended up being too high of degree
     #[error("polynomial degree is too large")]
     PolynomialDegreeTooLarge,
+    /// During synthesis, our polynomials did not fit into the largest evaluation domain
+    #[error("{size} coefficients exceed the maximal evaluation domain size of {max}")]
+    DomainTooLarge { size: usize, max: usize },
+    /// During synthesis, the requested size of an evaluation domain was not a power of two
+    #[error("evaluation domain size {0} is not a power of two")]
+    InvalidDomainSize(usize),
+    /// The number of values given to an FFT does not match its size
+    #[error("{len} values given to an FFT of size 2^{log_n}")]
+    FftSizeMismatch { len: usize, log_n: u32 },
+    /// The root of unity given to an FFT does not generate a domain of the right size
+    #[error("omega is not a primitive 2^{0}-th root of unity")]
+    InvalidRootOfUnity(u32),
     /// During proof generation, we encountered an identity in the CRS
     #[error("encountered an identity element in the CRS")]
     UnexpectedIdentity,
@@ -339,6 +375,21 @@ pub enum SynthesisError {
     /// During verification, our verifying key was malformed.
     #[error("malformed verifying key")]
     MalformedVerifyingKey,
+    /// During proof aggregation, the number of proofs was not supported by the SRS
+    #[error("cannot aggregate {0} proofs: the count must be a power of two, at least 2 and at most the SRS size")]
+    InvalidProofCount(usize),
+    /// During verification, a part of the proof was given out of order.
+    #[error("{0} given out of order")]
+    OutOfOrder(&'static str),
+    /// During synthesis, a hash was resumed from the state after a partial block
+    #[error("cannot resume a hash after {0} bits, which are not whole blocks")]
+    UnalignedHashState(u64),
+    /// During synthesis, a circuit enforced more constraints than its budget allows
+    #[error("{actual} constraints exceed the budget of {limit}")]
+    ConstraintBudgetExceeded { limit: usize, actual: usize },
+    /// During checking, the parameters were generated for a circuit of another shape
+    #[error("the parameters do not match the circuit: {0}")]
+    IncompatibleParameters(&'static str),
     /// During CRS generation, we observed an unconstrained auxiliary variable
     #[error("auxiliary variable was unconstrained")]
     UnconstrainedVariable,
@@ -419,6 +470,13 @@ pub trait ConstraintSystem<E: ScalarEngine>: Sized + Send {
         Namespace(self.get_root(), Default::default())
     }
 
+    /// Counts the constraints enforced through the returned constraint system, which
+    /// fails to allocate once there were more than `max_constraints`. See
+    /// [`util_cs::budget_cs`].
+    fn with_budget(&mut self, max_constraints: usize) -> BudgetedCS<'_, E, Self::Root> {
+        BudgetedCS::new(self.get_root(), max_constraints)
+    }
+
     /// Most implementations of ConstraintSystem are not 'extensible': they won't implement a specialized
     /// version of `extend` and should therefore also keep the default implementation of `is_extensible`
     /// so callers which optionally make use of `extend` can know to avoid relying on it when unimplemented.
@@ -461,6 +519,7 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for Name
         CS::one()
     }
 
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
     fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<E::Fr, SynthesisError>,
@@ -470,6 +529,7 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for Name
         self.0.alloc(annotation, f)
     }
 
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
     fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<E::Fr, SynthesisError>,
@@ -479,6 +539,7 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for Name
         self.0.alloc_input(annotation, f)
     }
 
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
     fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
     where
         A: FnOnce() -> AR,
@@ -526,6 +587,7 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs
         CS::one()
     }
 
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
     fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<E::Fr, SynthesisError>,
@@ -535,6 +597,7 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs
         (**self).alloc(annotation, f)
     }
 
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
     fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<E::Fr, SynthesisError>,
@@ -544,6 +607,7 @@ impl<'cs, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for &'cs
         (**self).alloc_input(annotation, f)
     }
 
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
     fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
     where
         A: FnOnce() -> AR,
@@ -0,0 +1,102 @@
+use digest::Digest;
+use ff::{Field, PrimeField};
+
+use crate::bls::Engine;
+use crate::folding::R1CSShape;
+use crate::transcript::FiatShamirRng;
+use crate::{Circuit, Index, SynthesisError};
+
+/// The constraint matrices of a circuit, over the domain of the `n`-th roots of unity
+/// the polynomials of a proof interpolate.
+///
+/// `n` is the smallest power of two of at least the number of constraints and the
+/// number of variables, including the one. Prover and verifier both derive the index
+/// from the circuit, so it is not part of the proof.
+#[derive(Clone, Debug)]
+pub struct MarlinIndex<E: Engine> {
+    pub(super) shape: R1CSShape<E>,
+    pub(super) log_n: u32,
+    /// The primitive `n`-th root of unity.
+    pub(super) omega: E::Fr,
+}
+
+impl<E: Engine> MarlinIndex<E> {
+    /// Synthesizes `circuit` to get its constraint matrices. Assignments are not needed.
+    pub fn new<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        Self::from_shape(R1CSShape::from_circuit(circuit)?)
+    }
+
+    pub(super) fn from_shape(shape: R1CSShape<E>) -> Result<Self, SynthesisError> {
+        let num_variables = shape.num_inputs() + 1 + shape.num_aux();
+        let n = std::cmp::max(std::cmp::max(shape.num_constraints(), num_variables), 2)
+            .next_power_of_two();
+        let log_n = n.trailing_zeros();
+        if log_n > E::Fr::S {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        let mut omega = E::Fr::root_of_unity();
+        for _ in log_n..E::Fr::S {
+            omega.square();
+        }
+
+        Ok(MarlinIndex {
+            shape,
+            log_n,
+            omega,
+        })
+    }
+
+    /// The size `n` of the domain.
+    pub fn domain_size(&self) -> usize {
+        1 << self.log_n
+    }
+
+    /// Number of public inputs, not counting the one.
+    pub fn num_inputs(&self) -> usize {
+        self.shape.num_inputs()
+    }
+
+    /// The smallest [`MarlinSRS::max_degree`] proving this circuit: `2n + l`, with `l`
+    /// the number of public inputs including the one.
+    ///
+    /// [`MarlinSRS::max_degree`]: super::MarlinSRS::max_degree
+    pub fn max_degree(&self) -> usize {
+        2 * self.domain_size() + self.num_inputs() + 1
+    }
+
+    /// The column of `index` in the matrices: the one and the public inputs come
+    /// first, then the auxiliary variables.
+    pub(super) fn column(&self, index: Index) -> usize {
+        match index {
+            Index::Input(i) => i,
+            Index::Aux(i) => self.num_inputs() + 1 + i,
+        }
+    }
+
+    /// Absorbs the sizes and the non-zero entries of the matrices.
+    pub(super) fn absorb<H: Digest + Clone>(&self, transcript: &mut FiatShamirRng<H>) {
+        let sizes = [
+            self.shape.num_constraints(),
+            self.num_inputs(),
+            self.shape.num_aux(),
+        ];
+        for size in &sizes {
+            transcript.absorb_bytes(&(*size as u64).to_le_bytes());
+        }
+
+        for matrix in &self.shape.matrices() {
+            for (i, row) in matrix.iter().enumerate() {
+                let mut bytes = Vec::with_capacity(8 * (1 + row.len()));
+                bytes.extend_from_slice(&(i as u64).to_le_bytes());
+                for (index, _) in row {
+                    bytes.extend_from_slice(&(self.column(*index) as u64).to_le_bytes());
+                }
+                transcript.absorb_bytes(&bytes);
+                for (_, coeff) in row {
+                    transcript.absorb_fr(coeff);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Proofs with a universal setup, following the polynomial IOP of [Marlin] compiled
+//! with KZG polynomial commitments.
+//!
+//! A [`MarlinSRS`] consists of the powers of a secret in G1 and is independent of the
+//! circuit: one setup proves any circuit whose [`MarlinIndex::max_degree`] it covers.
+//! [`MarlinProver::prove`] takes the same [`Circuit`]s as the Groth16 prover and has
+//! the signature of [`create_random_proof`], so a circuit can switch proof systems
+//! without changes. [`verify_marlin_proof`] checks a proof.
+//!
+//! The prover interpolates the assignment `z` and the products `Az`, `Bz` and `Cz`
+//! over the `n`-th roots of unity `H`, masked with random multiples of the vanishing
+//! polynomial `v_H` of `H`. The rowcheck `Az ∘ Bz = Cz` is shown with the quotient by
+//! `v_H`, the lincheck, that the products were computed from `z`, with a univariate
+//! sumcheck over `H` of a random combination of the rows. All polynomials are opened
+//! at a single random point with one batched KZG opening.
+//!
+//! Unlike Marlin, the index is not committed to in a preprocessing step: the verifier
+//! derives the matrices from the circuit and evaluates the lincheck polynomial itself,
+//! in time linear in the size of the circuit, while the proof stays constant sized.
+//!
+//! [Marlin]: https://eprint.iacr.org/2019/1047
+//! [`Circuit`]: crate::Circuit
+//! [`create_random_proof`]: crate::groth16::create_random_proof
+
+mod index;
+mod poly;
+mod prover;
+mod srs;
+mod verifier;
+
+pub use self::index::*;
+pub use self::prover::*;
+pub use self::srs::*;
+pub use self::verifier::*;
+
+use ff::Field;
+use sha2::Sha256;
+
+use crate::bls::Engine;
+use crate::multicore::Worker;
+use crate::transcript::FiatShamirRng;
+use crate::SynthesisError;
+
+/// Domain separator of the transcripts of proofs.
+const MARLIN_DOMAIN: &[u8] = b"bellperson-marlin";
+
+/// Number of committed polynomials: `w`, `z_A`, `z_B`, `z_C`, `h_0`, `s`, `h_1`, `g_1`
+/// and `g_1` shifted to the maximal degree.
+const NUM_POLYNOMIALS: usize = 9;
+
+/// Number of polynomials committed to before the lincheck challenges.
+const FIRST_ROUND: usize = 6;
+
+/// A proof created by [`MarlinProver::prove`].
+#[derive(Clone, Debug)]
+pub struct MarlinProof<E: Engine> {
+    /// Commitments to `w`, `z_A`, `z_B`, `z_C`, `h_0`, `s`, `h_1`, `g_1` and
+    /// `X^(D - n + 2) g_1`, with `D` the maximal degree of the SRS.
+    pub commitments: [E::G1Affine; NUM_POLYNOMIALS],
+    /// The values of the committed polynomials at the evaluation challenge.
+    pub evaluations: [E::Fr; NUM_POLYNOMIALS],
+    /// Batched KZG opening of all polynomials at the evaluation challenge.
+    pub opening: E::G1Affine,
+}
+
+/// The transcript of a proof, from which prover and verifier derive the challenges.
+struct Transcript(FiatShamirRng<Sha256>);
+
+impl Transcript {
+    /// Starts the transcript of a proof of the circuit `index` with `public_inputs`.
+    fn new<E: Engine>(index: &MarlinIndex<E>, srs: &MarlinSRS<E>, public_inputs: &[E::Fr]) -> Self {
+        let mut transcript = FiatShamirRng::new(MARLIN_DOMAIN);
+        transcript.absorb_bytes(&(srs.max_degree() as u64).to_le_bytes());
+        index.absorb(&mut transcript);
+        for input in public_inputs {
+            transcript.absorb_fr(input);
+        }
+
+        Transcript(transcript)
+    }
+
+    /// Absorbs the commitments of the first round and derives `alpha`, combining the
+    /// rows, the `eta`s, combining the matrices, and `c`, scaling the mask `s`.
+    fn lincheck_challenges<E: Engine>(
+        &mut self,
+        commitments: &[E::G1Affine],
+    ) -> (E::Fr, [E::Fr; 3], E::Fr) {
+        self.absorb_commitments::<E>(commitments);
+        let alpha = E::Fr::random(&mut self.0);
+        let etas = [
+            E::Fr::random(&mut self.0),
+            E::Fr::random(&mut self.0),
+            E::Fr::random(&mut self.0),
+        ];
+        let c = E::Fr::random(&mut self.0);
+
+        (alpha, etas, c)
+    }
+
+    /// Absorbs the commitments of the second round and derives the evaluation point.
+    fn evaluation_challenge<E: Engine>(&mut self, commitments: &[E::G1Affine]) -> E::Fr {
+        self.absorb_commitments::<E>(commitments);
+        E::Fr::random(&mut self.0)
+    }
+
+    /// Absorbs the evaluations and derives the challenge batching the openings.
+    fn batching_challenge<E: Engine>(&mut self, evaluations: &[E::Fr]) -> E::Fr {
+        for evaluation in evaluations {
+            self.0.absorb_fr(evaluation);
+        }
+        E::Fr::random(&mut self.0)
+    }
+
+    fn absorb_commitments<E: Engine>(&mut self, commitments: &[E::G1Affine]) {
+        for commitment in commitments {
+            self.0.absorb_g1(commitment);
+        }
+    }
+}
+
+/// The coefficients of `r_alpha(X) = (alpha^n - X^n) / (alpha - X)`, whose values at
+/// `H` weight the rows in the lincheck.
+fn row_polynomial<F: Field>(alpha: &F, n: usize) -> Vec<F> {
+    let mut coeffs = Vec::with_capacity(n);
+    let mut power = F::one();
+    for _ in 0..n {
+        coeffs.push(power);
+        power.mul_assign(alpha);
+    }
+    coeffs.reverse();
+    coeffs
+}
+
+/// `u_j = sum_M eta_M sum_i r_alpha(omega^i) M_ij` for the columns `j`, padded to `n`,
+/// so that the lincheck is `sum_i r_alpha(omega^i) sum_M eta_M (Mz)_i = sum_j u_j z_j`.
+fn lincheck_weights<E: Engine>(
+    worker: &Worker,
+    index: &MarlinIndex<E>,
+    row: &[E::Fr],
+    etas: &[E::Fr; 3],
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let row_weights = poly::evaluate_over_domain::<E>(worker, row)?;
+
+    let mut u = vec![E::Fr::zero(); index.domain_size()];
+    for (matrix, eta) in index.shape.matrices().iter().zip(etas) {
+        for (entries, weight) in matrix.iter().zip(&row_weights) {
+            let mut weight = *weight;
+            weight.mul_assign(eta);
+            for (column, coeff) in entries {
+                let mut term = *coeff;
+                term.mul_assign(&weight);
+                u[index.column(*column)].add_assign(&term);
+            }
+        }
+    }
+
+    Ok(u)
+}
@@ -0,0 +1,293 @@
+//! Univariate polynomials over `Fr`, as their coefficients in increasing degree.
+
+use ff::{Field, PrimeField};
+use rand_core::RngCore;
+
+use crate::bls::Engine;
+use crate::domain::{EvaluationDomain, Scalar};
+use crate::multicore::Worker;
+use crate::multiexp::batch_invert;
+use crate::SynthesisError;
+
+/// The polynomial of degree below `evals.len()`, a power of two, taking the values
+/// `evals` at the powers of the root of unity of that order.
+pub(super) fn interpolate<E: Engine>(
+    worker: &Worker,
+    evals: Vec<E::Fr>,
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let size = evals.len();
+    let mut domain = EvaluationDomain::<E, Scalar<E>>::from_coeffs_into_sized(
+        evals.into_iter().map(Scalar).collect(),
+        size,
+    )?;
+    domain.ifft(worker, &mut None)?;
+
+    Ok(domain.into_coeffs().into_iter().map(|s| s.0).collect())
+}
+
+/// The values of `coeffs`, of which there is a power of two, at the powers of the root
+/// of unity of that order.
+pub(super) fn evaluate_over_domain<E: Engine>(
+    worker: &Worker,
+    coeffs: &[E::Fr],
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    let mut domain = EvaluationDomain::<E, Scalar<E>>::from_coeffs_into_sized(
+        coeffs.iter().copied().map(Scalar).collect(),
+        coeffs.len(),
+    )?;
+    domain.fft(worker, &mut None)?;
+
+    Ok(domain.into_coeffs().into_iter().map(|s| s.0).collect())
+}
+
+/// `a * b`, with `a.len() + b.len() - 1` coefficients.
+pub(super) fn mul<E: Engine>(
+    worker: &Worker,
+    a: &[E::Fr],
+    b: &[E::Fr],
+) -> Result<Vec<E::Fr>, SynthesisError> {
+    if a.is_empty() || b.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let len = a.len() + b.len() - 1;
+    let a = EvaluationDomain::<E, Scalar<E>>::from_coeffs(a.iter().copied().map(Scalar).collect())?;
+    let b = EvaluationDomain::<E, Scalar<E>>::from_coeffs(b.iter().copied().map(Scalar).collect())?;
+    let mut product = a
+        .multiply(b, worker, &mut None)?
+        .into_coeffs()
+        .into_iter()
+        .map(|s| s.0)
+        .collect::<Vec<_>>();
+    product.resize(len, E::Fr::zero());
+
+    Ok(product)
+}
+
+/// A polynomial with `len` random coefficients.
+pub(super) fn random<F: Field, R: RngCore>(len: usize, rng: &mut R) -> Vec<F> {
+    (0..len).map(|_| F::random(rng)).collect()
+}
+
+/// `a += c * b`
+pub(super) fn add_scaled<F: Field>(a: &mut Vec<F>, b: &[F], c: &F) {
+    if a.len() < b.len() {
+        a.resize(b.len(), F::zero());
+    }
+    for (a, b) in a.iter_mut().zip(b) {
+        let mut term = *b;
+        term.mul_assign(c);
+        a.add_assign(&term);
+    }
+}
+
+/// `p += (X^n - 1) * t`
+pub(super) fn add_vanishing_multiple<F: Field>(p: &mut Vec<F>, t: &[F], n: usize) {
+    if p.len() < n + t.len() {
+        p.resize(n + t.len(), F::zero());
+    }
+    for (k, t) in t.iter().enumerate() {
+        p[k].sub_assign(t);
+        p[n + k].add_assign(t);
+    }
+}
+
+/// Divides `p` by `X^n - 1`, returning the quotient and the remainder of degree
+/// below `n`.
+pub(super) fn divide_by_vanishing<F: Field>(p: &[F], n: usize) -> (Vec<F>, Vec<F>) {
+    let mut remainder = p.to_vec();
+    if remainder.len() <= n {
+        remainder.resize(n, F::zero());
+        return (vec![], remainder);
+    }
+
+    let mut quotient = vec![F::zero(); remainder.len() - n];
+    for k in (n..remainder.len()).rev() {
+        let coeff = remainder[k];
+        quotient[k - n] = coeff;
+        remainder[k - n].add_assign(&coeff);
+    }
+    remainder.truncate(n);
+
+    (quotient, remainder)
+}
+
+/// Divides `p` by the monic polynomial `d`, returning `None` if there is a remainder.
+pub(super) fn divide_exact<F: Field>(p: &[F], d: &[F]) -> Option<Vec<F>> {
+    let deg = d.len() - 1;
+    debug_assert!(d[deg] == F::one(), "the divisor must be monic");
+    if p.len() <= deg {
+        return if p.iter().all(|c| c.is_zero()) {
+            Some(vec![])
+        } else {
+            None
+        };
+    }
+
+    let mut remainder = p.to_vec();
+    let mut quotient = vec![F::zero(); p.len() - deg];
+    for k in (0..quotient.len()).rev() {
+        let coeff = remainder[k + deg];
+        quotient[k] = coeff;
+        for (r, d) in remainder[k..k + deg].iter_mut().zip(d) {
+            let mut term = *d;
+            term.mul_assign(&coeff);
+            r.sub_assign(&term);
+        }
+    }
+
+    if remainder[..deg].iter().all(|c| c.is_zero()) {
+        Some(quotient)
+    } else {
+        None
+    }
+}
+
+/// `p(x)`
+pub(super) fn evaluate<F: Field>(p: &[F], x: &F) -> F {
+    p.iter().rev().fold(F::zero(), |mut acc, coeff| {
+        acc.mul_assign(x);
+        acc.add_assign(coeff);
+        acc
+    })
+}
+
+/// The quotient of `p - p(x)` by `X - x`.
+pub(super) fn divide_by_linear<F: Field>(p: &[F], x: &F) -> Vec<F> {
+    if p.len() < 2 {
+        return vec![];
+    }
+
+    // Synthetic division: the quotient coefficients are the partial Horner sums.
+    let mut quotient = vec![F::zero(); p.len() - 1];
+    let mut acc = F::zero();
+    for k in (1..p.len()).rev() {
+        acc.mul_assign(x);
+        acc.add_assign(&p[k]);
+        quotient[k - 1] = acc;
+    }
+
+    quotient
+}
+
+/// `prod_{i < count} (X - omega^i)`, the polynomial vanishing on the first `count`
+/// powers of `omega`.
+pub(super) fn vanishing_on_first<F: Field>(omega: &F, count: usize) -> Vec<F> {
+    let mut p = vec![F::one()];
+    let mut omega_i = F::one();
+    for _ in 0..count {
+        // p * (X - omega^i)
+        p.push(F::zero());
+        for k in (0..p.len()).rev() {
+            let mut term = if k > 0 { p[k - 1] } else { F::zero() };
+            let mut shifted = p[k];
+            shifted.mul_assign(&omega_i);
+            term.sub_assign(&shifted);
+            p[k] = term;
+        }
+        omega_i.mul_assign(omega);
+    }
+
+    p
+}
+
+/// The first `count` Lagrange polynomials of the `n`-th roots of unity, powers of
+/// `omega`, evaluated at `x`, which must not be a root of unity:
+/// `L_i(x) = omega^i (x^n - 1) / (n (x - omega^i))`.
+pub(super) fn lagrange_at<F: PrimeField>(omega: &F, n: usize, x: &F, count: usize) -> Vec<F> {
+    let mut omega_powers = Vec::with_capacity(count);
+    let mut denominators = Vec::with_capacity(count);
+    let mut omega_i = F::one();
+    for _ in 0..count {
+        let mut d = *x;
+        d.sub_assign(&omega_i);
+        omega_powers.push(omega_i);
+        denominators.push(d);
+        omega_i.mul_assign(omega);
+    }
+    batch_invert(&mut denominators, &mut Vec::with_capacity(count));
+
+    let mut scale = x.pow(&[n as u64]);
+    scale.sub_assign(&F::one());
+    scale.mul_assign(
+        &F::from_str(&n.to_string())
+            .and_then(|n| n.inverse())
+            .expect("n is a non-zero field element"),
+    );
+
+    omega_powers
+        .into_iter()
+        .zip(denominators)
+        .map(|(mut l, d)| {
+            l.mul_assign(&d);
+            l.mul_assign(&scale);
+            l
+        })
+        .collect()
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_polynomial_division() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let worker = Worker::new();
+        let n = 8;
+        let mut omega = Fr::root_of_unity();
+        for _ in 3..Fr::S {
+            omega.square();
+        }
+
+        let evals = (0..n).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let p = interpolate::<Bls12>(&worker, evals.clone()).unwrap();
+        let mut omega_i = Fr::one();
+        for eval in &evals {
+            assert_eq!(evaluate(&p, &omega_i), *eval);
+            omega_i.mul_assign(&omega);
+        }
+
+        // p * (X^n - 1) + t, divided by X^n - 1.
+        let t = (0..n).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let mut product = t.clone();
+        add_vanishing_multiple(&mut product, &p, n);
+        let (quotient, remainder) = divide_by_vanishing(&product, n);
+        assert_eq!(quotient, p);
+        assert_eq!(remainder, t);
+
+        // The product with the vanishing polynomial of the first 3 roots.
+        let d = vanishing_on_first(&omega, 3);
+        let product = mul::<Bls12>(&worker, &p, &d).unwrap();
+        assert_eq!(divide_exact(&product, &d).unwrap(), p);
+        assert!(divide_exact(&p, &d).is_none());
+        assert!(evaluate(&d, &omega.pow(&[2])).is_zero());
+
+        let x = Fr::random(rng);
+        let quotient = divide_by_linear(&p, &x);
+        let y = Fr::random(rng);
+        let mut value = evaluate(&p, &y);
+        value.sub_assign(&evaluate(&p, &x));
+        let mut y_minus_x = y;
+        y_minus_x.sub_assign(&x);
+        let mut expected = evaluate(&quotient, &y);
+        expected.mul_assign(&y_minus_x);
+        assert_eq!(expected, value);
+
+        let lagrange = lagrange_at(&omega, n, &x, n);
+        let mut interpolated = Fr::zero();
+        for (l, eval) in lagrange.iter().zip(&evals) {
+            let mut term = *l;
+            term.mul_assign(eval);
+            interpolated.add_assign(&term);
+        }
+        assert_eq!(interpolated, evaluate(&p, &x));
+    }
+}
@@ -0,0 +1,168 @@
+use std::marker::PhantomData;
+
+use ff::Field;
+use groupy::CurveAffine;
+use rand_core::RngCore;
+
+use super::poly::{
+    add_scaled, add_vanishing_multiple, divide_by_linear, divide_by_vanishing, divide_exact,
+    evaluate, interpolate, mul, random, vanishing_on_first,
+};
+use super::{
+    lincheck_weights, row_polynomial, MarlinIndex, MarlinProof, MarlinSRS, Transcript, FIRST_ROUND,
+    NUM_POLYNOMIALS,
+};
+use crate::bls::Engine;
+use crate::folding::R1CSShape;
+use crate::multicore::Worker;
+use crate::{Circuit, SynthesisError};
+
+/// Number of random coefficients of the multiples of `v_H` masking the polynomials
+/// of the witness, one more than the number of their evaluations in a proof.
+const MASK_LEN: usize = 2;
+
+/// Creates Marlin proofs, see the [module documentation](super).
+pub struct MarlinProver<E: Engine> {
+    _engine: PhantomData<E>,
+}
+
+impl<E: Engine> MarlinProver<E> {
+    /// Creates a proof for `circuit` with the universal `srs`, randomized with `rng`.
+    ///
+    /// This mirrors [`create_random_proof`], with the SRS taking the place of the
+    /// circuit specific parameters. Returns [`SynthesisError::PolynomialDegreeTooLarge`]
+    /// if the circuit is too large for the SRS and [`SynthesisError::Unsatisfiable`] if
+    /// the assignment does not satisfy the constraints.
+    ///
+    /// [`create_random_proof`]: crate::groth16::create_random_proof
+    pub fn prove<C, R>(
+        circuit: C,
+        srs: &MarlinSRS<E>,
+        rng: &mut R,
+    ) -> Result<MarlinProof<E>, SynthesisError>
+    where
+        C: Circuit<E> + Send,
+        R: RngCore,
+    {
+        let worker = Worker::new();
+        let (shape, x, w) = R1CSShape::with_assignment(circuit)?;
+        let index = MarlinIndex::from_shape(shape)?;
+        if srs.max_degree() < index.max_degree() {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+        let n = index.domain_size();
+        let num_inputs = x.len() + 1;
+
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+
+        // First round: the witness, the products of the matrices with z, the rowcheck
+        // quotient and the mask of the sumcheck.
+        let (az, bz, cz) = index.shape.multiply(&E::Fr::one(), &x, &w);
+        let mut z = Vec::with_capacity(n);
+        z.push(E::Fr::one());
+        z.extend_from_slice(&x);
+        z.extend_from_slice(&w);
+        z.resize(n, E::Fr::zero());
+
+        // z = x + v_l w on H, with x interpolating the inputs and v_l vanishing on their
+        // positions, so w takes the values of the auxiliary variables divided by v_l.
+        let mut w_evals = vec![E::Fr::zero(); num_inputs];
+        w_evals.extend_from_slice(&w);
+        w_evals.resize(n, E::Fr::zero());
+        let v_l = vanishing_on_first(&index.omega, num_inputs);
+        let mut w_poly = divide_exact(&interpolate::<E>(&worker, w_evals)?, &v_l)
+            .expect("the auxiliary values vanish at the inputs");
+        let w_mask = random::<E::Fr, _>(MASK_LEN, rng);
+        add_vanishing_multiple(&mut w_poly, &w_mask, n);
+        let mut z_poly = interpolate::<E>(&worker, z)?;
+        add_vanishing_multiple(&mut z_poly, &mul::<E>(&worker, &v_l, &w_mask)?, n);
+
+        let mut z_m = Vec::with_capacity(3);
+        for mut evals in vec![az, bz, cz] {
+            evals.resize(n, E::Fr::zero());
+            let mut poly = interpolate::<E>(&worker, evals)?;
+            add_vanishing_multiple(&mut poly, &random::<E::Fr, _>(MASK_LEN, rng), n);
+            z_m.push(poly);
+        }
+
+        let mut rowcheck = mul::<E>(&worker, &z_m[0], &z_m[1])?;
+        add_scaled(&mut rowcheck, &z_m[2], &minus_one);
+        let (h_0, remainder) = divide_by_vanishing(&rowcheck, n);
+        if remainder.iter().any(|c| !c.is_zero()) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        // The sum of a polynomial over H is n times the sum of its coefficients of
+        // degrees divisible by n.
+        let mut s = random::<E::Fr, _>(srs.max_degree() + 1, rng);
+        let mut sum = E::Fr::zero();
+        for coeff in s.iter().skip(n).step_by(n) {
+            sum.add_assign(coeff);
+        }
+        s[0] = sum;
+        s[0].negate();
+
+        let mut polynomials = vec![w_poly];
+        polynomials.extend(z_m.iter().cloned());
+        polynomials.push(h_0);
+        polynomials.push(s);
+
+        let mut transcript = Transcript::new(&index, srs, &x);
+        let mut commitments = polynomials
+            .iter()
+            .map(|p| srs.commit(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (alpha, etas, c) = transcript.lincheck_challenges::<E>(&commitments);
+
+        // Second round: the sumcheck of
+        // q = r_alpha sum_M eta_M z_M - u z + c s = h_1 v_H + X g_1,
+        // with the degree of g_1 bounded by its shift to the maximal degree.
+        let row = row_polynomial(&alpha, n);
+        let u = interpolate::<E>(&worker, lincheck_weights(&worker, &index, &row, &etas)?)?;
+        let mut combined = vec![];
+        for (poly, eta) in z_m.iter().zip(&etas) {
+            add_scaled(&mut combined, poly, eta);
+        }
+        let mut q = mul::<E>(&worker, &row, &combined)?;
+        add_scaled(&mut q, &mul::<E>(&worker, &u, &z_poly)?, &minus_one);
+        add_scaled(&mut q, &polynomials[FIRST_ROUND - 1], &c);
+        let (h_1, remainder) = divide_by_vanishing(&q, n);
+        debug_assert!(remainder[0].is_zero(), "the lincheck holds");
+        let g_1 = remainder[1..].to_vec();
+        let mut g_1_shifted = vec![E::Fr::zero(); srs.max_degree() - (n - 2)];
+        g_1_shifted.extend_from_slice(&g_1);
+
+        polynomials.push(h_1);
+        polynomials.push(g_1);
+        polynomials.push(g_1_shifted);
+        for p in &polynomials[FIRST_ROUND..] {
+            commitments.push(srs.commit(p)?);
+        }
+        let beta = transcript.evaluation_challenge::<E>(&commitments[FIRST_ROUND..]);
+
+        // Opening of all polynomials at beta, batched with the powers of gamma.
+        let evaluations = polynomials
+            .iter()
+            .map(|p| evaluate(p, &beta))
+            .collect::<Vec<_>>();
+        let gamma = transcript.batching_challenge::<E>(&evaluations);
+        let mut batched = vec![];
+        let mut gamma_i = E::Fr::one();
+        for p in &polynomials {
+            add_scaled(&mut batched, p, &gamma_i);
+            gamma_i.mul_assign(&gamma);
+        }
+        let opening = srs.commit(&divide_by_linear(&batched, &beta))?;
+
+        let mut proof = MarlinProof {
+            commitments: [E::G1Affine::zero(); NUM_POLYNOMIALS],
+            evaluations: [E::Fr::zero(); NUM_POLYNOMIALS],
+            opening,
+        };
+        proof.commitments.copy_from_slice(&commitments);
+        proof.evaluations.copy_from_slice(&evaluations);
+
+        Ok(proof)
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use rand_core::RngCore;
+use rayon::prelude::*;
+
+use crate::bls::Engine;
+use crate::multicore::{install_in, Worker, THREAD_POOL};
+use crate::multiexp::{multiexp, FullDensity};
+use crate::SynthesisError;
+
+/// The universal structured reference string: the powers of a secret `t` on the
+/// generator `G` of G1, and `H` and `t H` in G2, for KZG commitments to polynomials of
+/// degree up to [`max_degree`](MarlinSRS::max_degree).
+///
+/// One SRS proves any circuit small enough for it, see [`MarlinIndex::max_degree`].
+/// Whoever learns `t` can forge proofs, so it must be destroyed after the setup.
+///
+/// [`MarlinIndex::max_degree`]: super::MarlinIndex::max_degree
+#[derive(Clone, Debug)]
+pub struct MarlinSRS<E: Engine> {
+    /// `t^i G` for `i <= max_degree`.
+    pub(super) powers_of_g: Arc<Vec<E::G1Affine>>,
+    pub(super) h: E::G2Affine,
+    pub(super) t_h: E::G2Affine,
+}
+
+impl<E: Engine> PartialEq for MarlinSRS<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.powers_of_g == other.powers_of_g && self.h == other.h && self.t_h == other.t_h
+    }
+}
+
+impl<E: Engine> MarlinSRS<E> {
+    /// Creates the SRS for polynomials of degree up to `max_degree`, with the secret
+    /// sampled from `rng` and dropped when done.
+    pub fn setup<R: RngCore>(max_degree: usize, rng: &mut R) -> Self {
+        let t = E::Fr::random(rng);
+
+        let mut powers = Vec::with_capacity(max_degree + 1);
+        let mut t_i = E::Fr::one();
+        for _ in 0..=max_degree {
+            powers.push(t_i);
+            t_i.mul_assign(&t);
+        }
+        let mut powers_of_g = install_in(&THREAD_POOL, || {
+            powers
+                .par_iter()
+                .map(|t_i| E::G1Affine::one().mul(t_i.into_repr()))
+                .collect::<Vec<_>>()
+        });
+        E::G1::batch_normalization(&mut powers_of_g);
+
+        MarlinSRS {
+            powers_of_g: Arc::new(powers_of_g.into_iter().map(|p| p.into_affine()).collect()),
+            h: E::G2Affine::one(),
+            t_h: E::G2Affine::one().mul(t.into_repr()).into_affine(),
+        }
+    }
+
+    /// The maximal degree of the committed polynomials.
+    pub fn max_degree(&self) -> usize {
+        self.powers_of_g.len() - 1
+    }
+
+    /// KZG commitment to the polynomial with the coefficients `coeffs`.
+    ///
+    /// Returns [`SynthesisError::PolynomialDegreeTooLarge`] if the degree exceeds
+    /// [`max_degree`](MarlinSRS::max_degree).
+    pub(super) fn commit(&self, coeffs: &[E::Fr]) -> Result<E::G1Affine, SynthesisError> {
+        if coeffs.len() > self.powers_of_g.len() {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+        if coeffs.is_empty() {
+            return Ok(E::G1Affine::zero());
+        }
+
+        let exps = coeffs.iter().map(|c| c.into_repr()).collect::<Vec<_>>();
+        let commitment = multiexp(
+            &Worker::new(),
+            (self.powers_of_g.clone(), 0),
+            FullDensity,
+            Arc::new(exps),
+            &mut None,
+        )
+        .wait()?;
+
+        Ok(commitment.into_affine())
+    }
+}
@@ -0,0 +1,119 @@
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::poly::{evaluate, lagrange_at, vanishing_on_first};
+use super::{
+    lincheck_weights, row_polynomial, MarlinIndex, MarlinProof, MarlinSRS, Transcript, FIRST_ROUND,
+};
+use crate::bls::Engine;
+use crate::multicore::Worker;
+use crate::SynthesisError;
+
+/// Verifies `proof` for the circuit `index` with `public_inputs`.
+///
+/// Returns [`SynthesisError::MalformedVerifyingKey`] if the number of public inputs
+/// does not match the circuit and [`SynthesisError::PolynomialDegreeTooLarge`] if the
+/// circuit is too large for the SRS.
+pub fn verify_marlin_proof<E: Engine>(
+    srs: &MarlinSRS<E>,
+    index: &MarlinIndex<E>,
+    proof: &MarlinProof<E>,
+    public_inputs: &[E::Fr],
+) -> Result<bool, SynthesisError> {
+    if public_inputs.len() != index.num_inputs() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    if srs.max_degree() < index.max_degree() {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    let mut transcript = Transcript::new(index, srs, public_inputs);
+    let (alpha, etas, c) = transcript.lincheck_challenges::<E>(&proof.commitments[..FIRST_ROUND]);
+    let beta = transcript.evaluation_challenge::<E>(&proof.commitments[FIRST_ROUND..]);
+    let gamma = transcript.batching_challenge::<E>(&proof.evaluations);
+
+    let n = index.domain_size();
+    let mut v_h = beta.pow(&[n as u64]);
+    v_h.sub_assign(&E::Fr::one());
+    if v_h.is_zero() {
+        // The Lagrange polynomials cannot be evaluated at roots of unity, and an
+        // honest prover hits one with negligible probability.
+        return Ok(false);
+    }
+
+    let [w, z_a, z_b, z_c, h_0, s, h_1, g_1, g_1_shifted] = proof.evaluations;
+
+    // The degree bound of g_1.
+    let mut shifted = beta.pow(&[(srs.max_degree() - (n - 2)) as u64]);
+    shifted.mul_assign(&g_1);
+    if shifted != g_1_shifted {
+        return Ok(false);
+    }
+
+    // The rowcheck: z_A z_B - z_C = h_0 v_H.
+    let mut lhs = z_a;
+    lhs.mul_assign(&z_b);
+    lhs.sub_assign(&z_c);
+    let mut rhs = h_0;
+    rhs.mul_assign(&v_h);
+    if lhs != rhs {
+        return Ok(false);
+    }
+
+    // The lincheck: r_alpha sum_M eta_M z_M - u z + c s = h_1 v_H + X g_1, with
+    // z = x + v_l w.
+    let mut inputs = vec![E::Fr::one()];
+    inputs.extend_from_slice(public_inputs);
+    let mut z = inner_product(&lagrange_at(&index.omega, n, &beta, inputs.len()), &inputs);
+    let mut v_l_w = evaluate(&vanishing_on_first(&index.omega, inputs.len()), &beta);
+    v_l_w.mul_assign(&w);
+    z.add_assign(&v_l_w);
+
+    let worker = Worker::new();
+    let row = row_polynomial(&alpha, n);
+    let u = lincheck_weights(&worker, index, &row, &etas)?;
+    let mut u_z = inner_product(&lagrange_at(&index.omega, n, &beta, n), &u);
+    u_z.mul_assign(&z);
+
+    let mut lhs = inner_product(&etas, &[z_a, z_b, z_c]);
+    lhs.mul_assign(&evaluate(&row, &beta));
+    lhs.sub_assign(&u_z);
+    let mut c_s = c;
+    c_s.mul_assign(&s);
+    lhs.add_assign(&c_s);
+    let mut rhs = h_1;
+    rhs.mul_assign(&v_h);
+    let mut beta_g_1 = beta;
+    beta_g_1.mul_assign(&g_1);
+    rhs.add_assign(&beta_g_1);
+    if lhs != rhs {
+        return Ok(false);
+    }
+
+    // The batched opening: e(C - v G + beta pi, H) = e(pi, t H) for the combinations
+    // C of the commitments and v of the evaluations with the powers of gamma.
+    let mut commitment = E::G1::zero();
+    let mut value = E::Fr::zero();
+    let mut gamma_i = E::Fr::one();
+    for (comm, eval) in proof.commitments.iter().zip(&proof.evaluations) {
+        commitment.add_assign(&comm.mul(gamma_i.into_repr()));
+        let mut term = *eval;
+        term.mul_assign(&gamma_i);
+        value.add_assign(&term);
+        gamma_i.mul_assign(&gamma);
+    }
+    commitment.sub_assign(&E::G1Affine::one().mul(value.into_repr()));
+    commitment.add_assign(&proof.opening.mul(beta.into_repr()));
+
+    Ok(E::pairing(commitment.into_affine(), srs.h) == E::pairing(proof.opening, srs.t_h))
+}
+
+/// `sum a_i b_i`
+fn inner_product<F: Field>(a: &[F], b: &[F]) -> F {
+    a.iter().zip(b).fold(F::zero(), |mut acc, (a, b)| {
+        let mut term = *a;
+        term.mul_assign(b);
+        acc.add_assign(&term);
+        acc
+    })
+}
@@ -0,0 +1,306 @@
+//! FFTs over domains of size `2^a * 3^b * 5^c`.
+//!
+//! An [`EvaluationDomain`](crate::domain::EvaluationDomain) has a power of two
+//! size, so a polynomial just above a power of two is padded to twice its size. A
+//! [`MixedDomain`] may also have factors of three and five, as far as `r - 1`, the
+//! order of the multiplicative group of the field, has them. The FFT splits the
+//! domain into its coprime prime power factors with the Good-Thomas algorithm, and
+//! transforms each with a radix-`p` Cooley-Tukey FFT.
+//!
+//! The scalar field of BLS12-381 has `r - 1 = 2^32 * 3 * ...`, so its mixed domains
+//! are `3 * 2^a`, which are 25% smaller than the next power of two for sizes between
+//! `2^(a + 1)` and `3 * 2^a`.
+
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+use rayon::prelude::*;
+
+use crate::SynthesisError;
+
+/// The primes the size of a [`MixedDomain`] may have as factors.
+const PRIMES: [usize; 3] = [2, 3, 5];
+
+/// A multiplicative subgroup of size `2^a * 3^b * 5^c` of the field.
+#[derive(Clone, Copy, Debug)]
+pub struct MixedDomain<F: PrimeField> {
+    size: usize,
+    omega: F,
+    omegainv: F,
+    minv: F,
+}
+
+impl<F: PrimeField> MixedDomain<F> {
+    /// The domain of `size` elements, if the field has one.
+    pub fn new(size: usize) -> Option<Self> {
+        factor(size)?;
+        let omega = root_of_unity::<F>(size)?;
+
+        Some(MixedDomain {
+            size,
+            omega,
+            omegainv: omega.inverse().unwrap(),
+            minv: F::from_str(&format!("{}", size))
+                .unwrap()
+                .inverse()
+                .unwrap(),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The generator of the domain, a primitive `size`-th root of unity.
+    pub fn omega(&self) -> F {
+        self.omega
+    }
+
+    /// Evaluates the polynomial with the coefficients `coeffs` at the powers of
+    /// `omega`, in place.
+    pub fn fft(&self, coeffs: &mut [F]) {
+        assert_eq!(coeffs.len(), self.size);
+
+        mixed_radix_fft(coeffs, self.omega);
+    }
+
+    /// Interpolates the evaluations `evals` at the powers of `omega`, in place.
+    pub fn ifft(&self, evals: &mut [F]) {
+        assert_eq!(evals.len(), self.size);
+
+        mixed_radix_fft(evals, self.omegainv);
+        evals.par_iter_mut().for_each(|e| e.mul_assign(&self.minv));
+    }
+}
+
+/// Selects the smallest domain of the scalar field of `E` with at least `min_size`
+/// elements.
+pub fn select_fft_domain_mixed<E: ScalarEngine>(
+    min_size: usize,
+) -> Result<MixedDomain<E::Fr>, SynthesisError> {
+    let min_size = std::cmp::max(min_size, 1);
+
+    // The smallest size of each odd part 3^b * 5^c, with the power of two making
+    // up the rest. Odd parts beyond the next power of two give larger sizes than
+    // the power of two alone.
+    let limit = min_size
+        .checked_next_power_of_two()
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+    let mut sizes = vec![];
+    let mut threes = 1;
+    while threes <= limit {
+        let mut odd = threes;
+        while odd <= limit {
+            let mut size = odd;
+            while size < min_size {
+                size *= 2;
+            }
+            sizes.push(size);
+            odd *= 5;
+        }
+        threes *= 3;
+    }
+    sizes.sort_unstable();
+
+    sizes
+        .into_iter()
+        .find_map(MixedDomain::new)
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)
+}
+
+/// FFT of `coeffs` over the powers of `omega`, a primitive root of unity of the
+/// order of the length of `coeffs`, which must be `2^a * 3^b * 5^c`.
+pub fn mixed_radix_fft<F: PrimeField>(coeffs: &mut [F], omega: F) {
+    let factors = factor(coeffs.len()).expect("the size is of the form 2^a * 3^b * 5^c");
+
+    good_thomas_fft(coeffs, omega, &factors);
+}
+
+/// The prime power factors `(p, p^e)` of `n`, if it has no other prime factors.
+fn factor(n: usize) -> Option<Vec<(usize, usize)>> {
+    if n == 0 {
+        return None;
+    }
+
+    let mut rest = n;
+    let mut factors = vec![];
+    for &p in PRIMES.iter() {
+        let mut power = 1;
+        while rest % p == 0 {
+            rest /= p;
+            power *= p;
+        }
+        if power > 1 {
+            factors.push((p, power));
+        }
+    }
+
+    if rest == 1 {
+        Some(factors)
+    } else {
+        None
+    }
+}
+
+/// A primitive `n`-th root of unity, `g^((r - 1) / n)` for the multiplicative
+/// generator `g`, if `n` divides `r - 1`.
+fn root_of_unity<F: PrimeField>(n: usize) -> Option<F> {
+    let mut exp = F::char();
+    exp.sub_noborrow(&1.into());
+
+    // Long division by n, from the most significant limb.
+    let mut rem = 0u128;
+    for limb in exp.as_mut().iter_mut().rev() {
+        let cur = (rem << 64) | u128::from(*limb);
+        *limb = (cur / n as u128) as u64;
+        rem = cur % n as u128;
+    }
+    if rem != 0 {
+        return None;
+    }
+
+    Some(F::multiplicative_generator().pow(exp.as_ref()))
+}
+
+/// FFT of size `n = n_1 * n_2` for coprime `n_1`, the first of `factors`, and `n_2`,
+/// the product of the others. The input at `(i_1 * n_2 + i_2 * n_1) % n` is at
+/// `(i_1, i_2)` of an `n_1` by `n_2` grid, and as `omega^n_2` and `omega^n_1` are
+/// roots of unity of orders `n_1` and `n_2`, the output at `k` is the two
+/// dimensional FFT of the grid at `(k % n_1, k % n_2)`, without twiddle factors.
+fn good_thomas_fft<F: PrimeField>(a: &mut [F], omega: F, factors: &[(usize, usize)]) {
+    match factors {
+        [] => {}
+        [(p, _)] => radix_fft(a, omega, *p),
+        [(p, n1), rest @ ..] => {
+            let n = a.len();
+            let (p, n1) = (*p, *n1);
+            let n2 = n / n1;
+
+            let mut grid = (0..n)
+                .map(|i| a[((i / n2) * n2 + (i % n2) * n1) % n])
+                .collect::<Vec<_>>();
+            let omega_n2 = omega.pow(&[n1 as u64]);
+            grid.par_chunks_mut(n2)
+                .for_each(|row| good_thomas_fft(row, omega_n2, rest));
+
+            // Transposed, so that the columns are contiguous.
+            let mut columns = (0..n)
+                .map(|i| grid[(i % n1) * n2 + i / n1])
+                .collect::<Vec<_>>();
+            let omega_n1 = omega.pow(&[n2 as u64]);
+            columns
+                .par_chunks_mut(n1)
+                .for_each(|column| radix_fft(column, omega_n1, p));
+
+            for (k, a) in a.iter_mut().enumerate() {
+                *a = columns[(k % n2) * n1 + k % n1];
+            }
+        }
+    }
+}
+
+/// Radix-`p` FFT of a power of `p` elements: the FFTs of the `p` subsequences of
+/// every `p`-th element combine into `X_k = sum_r omega^(r * k) Y_r[k % m]`.
+fn radix_fft<F: PrimeField>(a: &mut [F], omega: F, p: usize) {
+    let n = a.len();
+    if n == 1 {
+        return;
+    }
+    let m = n / p;
+
+    let mut subs = (0..n).map(|i| a[(i % m) * p + i / m]).collect::<Vec<_>>();
+    let omega_p = omega.pow(&[p as u64]);
+    for sub in subs.chunks_mut(m) {
+        radix_fft(sub, omega_p, p);
+    }
+
+    let mut omega_k = F::one();
+    for (k, a) in a.iter_mut().enumerate() {
+        let mut sum = F::zero();
+        let mut twiddle = F::one();
+        for r in 0..p {
+            let mut t = subs[r * m + k % m];
+            t.mul_assign(&twiddle);
+            sum.add_assign(&t);
+            twiddle.mul_assign(&omega_k);
+        }
+        *a = sum;
+        omega_k.mul_assign(&omega);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn naive_dft(coeffs: &[Fr], omega: Fr) -> Vec<Fr> {
+        (0..coeffs.len())
+            .map(|k| {
+                let x = omega.pow(&[k as u64]);
+                coeffs.iter().rev().fold(Fr::zero(), |mut acc, c| {
+                    acc.mul_assign(&x);
+                    acc.add_assign(c);
+                    acc
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mixed_radix_fft() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for &size in &[1, 2, 3, 4, 6, 8, 12, 24, 48, 96, 128, 384] {
+            let domain = MixedDomain::<Fr>::new(size).unwrap();
+            let coeffs = (0..size).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+            let mut evals = coeffs.clone();
+            domain.fft(&mut evals);
+            assert_eq!(evals, naive_dft(&coeffs, domain.omega()), "size {}", size);
+
+            domain.ifft(&mut evals);
+            assert_eq!(evals, coeffs, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_mixed_domain_omega() {
+        for &size in &[3, 6, 3 << 10, 1 << 20] {
+            let omega = MixedDomain::<Fr>::new(size).unwrap().omega();
+            assert_eq!(omega.pow(&[size as u64]), Fr::one());
+            for &(p, _) in factor(size).unwrap().iter() {
+                assert_ne!(omega.pow(&[(size / p) as u64]), Fr::one());
+            }
+        }
+
+        // r - 1 has a single factor of three and none of five.
+        assert!(MixedDomain::<Fr>::new(9).is_none());
+        assert!(MixedDomain::<Fr>::new(5).is_none());
+        assert!(MixedDomain::<Fr>::new(7).is_none());
+    }
+
+    #[test]
+    fn test_select_fft_domain_mixed() {
+        for &(min_size, size) in &[
+            (0, 1),
+            (1, 1),
+            (3, 3),
+            (5, 6),
+            (9, 12),
+            (17, 24),
+            (1024, 1024),
+            (1025, 1536),
+            (1537, 2048),
+        ] {
+            let domain = select_fft_domain_mixed::<Bls12>(min_size).unwrap();
+            assert_eq!(domain.size(), size, "min_size {}", min_size);
+        }
+
+        assert!(select_fft_domain_mixed::<Bls12>((3 << 32) + 1).is_err());
+    }
+}
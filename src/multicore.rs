@@ -5,20 +5,33 @@
 //!
 //! [`CpuPool`]: futures_cpupool::CpuPool
 
-use crossbeam_channel::{bounded, Receiver};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TryRecvError};
 use lazy_static::lazy_static;
+use log::error;
+use std::cell::Cell;
+use std::collections::VecDeque;
 use std::env;
+use std::fmt;
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::Waker;
+use std::time::Duration;
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+mod affinity;
+
+pub use self::affinity::numa_nodes;
 
 lazy_static! {
-    static ref NUM_CPUS: usize = if let Ok(num) = env::var("BELLMAN_NUM_CPUS") {
-        if let Ok(num) = num.parse() {
-            num
-        } else {
-            num_cpus::get()
-        }
-    } else {
-        num_cpus::get()
-    };
+    /// The thread count and where it was set, once it is.
+    static ref THREADS: Mutex<Option<(usize, InitSite)>> = Mutex::new(None);
+    static ref NUM_CPUS: usize = current_threads();
     pub static ref THREAD_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
         .num_threads(*NUM_CPUS)
         .build()
@@ -27,57 +40,750 @@ lazy_static! {
         .num_threads(NUM_CPUS.max(6))
         .build()
         .unwrap();
+    /// Runs the high-priority scopes of the workers on [`THREAD_POOL`], see
+    /// [`Worker::scope_with_priority`].
+    pub static ref PRIORITY_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .num_threads(*NUM_CPUS)
+        .start_handler(|_| mark_priority_thread())
+        .build()
+        .unwrap();
+    /// The computations queued on [`THREAD_POOL`].
+    static ref THREAD_POOL_QUEUE: Arc<ComputeQueue> = Arc::new(ComputeQueue::default());
+}
+
+/// Where the thread count of the pools was set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InitSite {
+    /// A call of [`initialize`] at this location.
+    Initialize(&'static Location<'static>),
+    /// The first use of the pools, taking the count from `BELLMAN_NUM_CPUS` or the
+    /// number of logical CPUs.
+    Default,
+}
+
+impl fmt::Display for InitSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitSite::Initialize(location) => write!(f, "multicore::initialize at {}", location),
+            InitSite::Default => write!(f, "the default on first use of the thread pools"),
+        }
+    }
+}
+
+/// The thread count of the pools was set already, by the call or use at `site`.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the thread count was already set to {threads} by {site}")]
+pub struct AlreadyInitialized {
+    pub threads: usize,
+    pub site: InitSite,
+}
+
+/// Sets the number of threads of the global pools, [`THREAD_POOL`] and
+/// [`PRIORITY_POOL`], and thereby of [`Worker::new`].
+///
+/// This must be called before the pools are first used, or [`current_threads`] is
+/// called, and only once; otherwise the count stays as it was and the error tells where
+/// it was set. Without a call, the count is taken from `BELLMAN_NUM_CPUS` or the number
+/// of logical CPUs on first use.
+///
+/// Panics if `threads` is zero.
+#[track_caller]
+pub fn initialize(threads: usize) -> Result<(), AlreadyInitialized> {
+    assert!(threads > 0, "the pools need at least one thread");
+    let mut current = THREADS.lock().unwrap();
+    if let Some((threads, site)) = *current {
+        return Err(AlreadyInitialized { threads, site });
+    }
+    *current = Some((threads, InitSite::Initialize(Location::caller())));
+
+    Ok(())
+}
+
+/// The number of threads of the global pools, fixing it to the default if
+/// [`initialize`] was not called yet.
+pub fn current_threads() -> usize {
+    let mut current = THREADS.lock().unwrap();
+    current
+        .get_or_insert_with(|| (default_threads(), InitSite::Default))
+        .0
+}
+
+fn default_threads() -> usize {
+    match env::var("BELLMAN_NUM_CPUS").map(|num| num.parse()) {
+        Ok(Ok(num)) => num,
+        _ => num_cpus::get(),
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The computations queued on one pool, see [`Worker::compute`]. Every queued
+/// computation has a task spawned on the pool, which runs the next computation of the
+/// queue, the high-priority ones first, rather than its own. A high-priority
+/// computation thereby starts before the low-priority ones queued before it, without
+/// any thread waiting for another.
+#[derive(Default)]
+struct ComputeQueue {
+    jobs: Mutex<ComputeJobs>,
+}
+
+#[derive(Default)]
+struct ComputeJobs {
+    high: VecDeque<Job>,
+    low: VecDeque<Job>,
+}
+
+impl ComputeQueue {
+    fn push(&self, priority: bool, job: Job) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if priority {
+            jobs.high.push_back(job);
+        } else {
+            jobs.low.push_back(job);
+        }
+    }
+
+    fn pop(&self) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.high.pop_front().or_else(|| jobs.low.pop_front())
+    }
+}
+
+thread_local! {
+    /// Whether the current thread belongs to a pool running high-priority work.
+    static PRIORITY_THREAD: Cell<bool> = Cell::new(false);
+}
+
+/// Marks the current thread as one running high-priority work, from the start handler
+/// of its pool.
+fn mark_priority_thread() {
+    PRIORITY_THREAD.with(|priority| priority.set(true));
+}
+
+/// Whether the current thread runs high-priority work. Work started from there is high
+/// priority as well, as it would otherwise wait for the work that waits for it.
+fn on_priority_thread() -> bool {
+    PRIORITY_THREAD.with(|priority| priority.get())
+}
+
+/// How FFTs on the CPU are split into parallel sub-FFTs.
+///
+/// An FFT of size `2^n` is either computed serially or as `2^t` sub-FFTs of size
+/// `2^(n - t)` in parallel. Small FFTs are dominated by the synchronization of the
+/// parallel version, so they should run serially; large FFTs may profit from more
+/// sub-FFTs than there are threads, as the threads then finish more evenly. The `fft`
+/// benchmark runs sizes and sub-FFT counts against each other to find good values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FftConfig {
+    /// FFTs of size below `2^min_parallel_log_size` are computed serially. Defaults to
+    /// one more than the logarithm of the number of sub-FFTs, so that every sub-FFT has
+    /// at least two elements.
+    pub min_parallel_log_size: Option<u32>,
+    /// Binary logarithm of the number of sub-FFTs. Defaults to the binary logarithm of
+    /// the number of threads.
+    pub max_log_threads_override: Option<u32>,
+    /// FFTs of size `2^min_four_step_log_size` and above are computed with the four-step
+    /// FFT, which works in place and without buffers of the size of the FFT. Off by
+    /// default.
+    pub min_four_step_log_size: Option<u32>,
+}
+
+impl FftConfig {
+    /// Reads the settings from `BELLMAN_FFT_MIN_PARALLEL_LOG_SIZE`,
+    /// `BELLMAN_FFT_LOG_THREADS` and `BELLMAN_FFT_MIN_FOUR_STEP_LOG_SIZE`, leaving unset
+    /// or invalid ones at their defaults.
+    pub fn from_env() -> Self {
+        fn read(name: &str) -> Option<u32> {
+            let value = env::var(name).ok()?;
+            match value.parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    error!("Invalid {}! Using the default...", name);
+                    None
+                }
+            }
+        }
+
+        FftConfig {
+            min_parallel_log_size: read("BELLMAN_FFT_MIN_PARALLEL_LOG_SIZE"),
+            max_log_threads_override: read("BELLMAN_FFT_LOG_THREADS"),
+            min_four_step_log_size: read("BELLMAN_FFT_MIN_FOUR_STEP_LOG_SIZE"),
+        }
+    }
+
+    /// Binary logarithm of the number of sub-FFTs for an FFT of size `2^log_n`, `None` if
+    /// it should be computed serially.
+    pub fn parallel_log_threads(&self, log_n: u32, log_cpus: u32) -> Option<u32> {
+        let log_threads = self.max_log_threads_override.unwrap_or(log_cpus);
+        let min_parallel_log_size = self.min_parallel_log_size.unwrap_or(log_threads + 1);
+
+        if log_n < min_parallel_log_size {
+            None
+        } else {
+            Some(log_threads.min(log_n))
+        }
+    }
+
+    /// Whether an FFT of size `2^log_n` should be computed with the four-step FFT.
+    pub fn four_step(&self, log_n: u32) -> bool {
+        match self.min_four_step_log_size {
+            Some(min_log_size) => log_n >= min_log_size,
+            None => false,
+        }
+    }
+}
+
+/// Where the crate runs its parallel work, set with [`set_thread_strategy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadStrategy {
+    /// In the pools of the crate: [`THREAD_POOL`], [`VERIFIER_POOL`] and those of
+    /// workers created with [`Worker::with_threads`]. The default.
+    OwnPools,
+    /// In the rayon pool of the caller, the global one if it is not running in a pool,
+    /// for applications which size the pool for everything they run themselves.
+    ///
+    /// The crate then never blocks a thread of the pool on work queued to it: scopes
+    /// run with [`rayon::in_place_scope`], so the calling thread takes part in the
+    /// work, and [`Worker::compute`] runs its closure right away. Proving and verifying
+    /// can therefore be called from within rayon tasks without deadlocking.
+    CallerPool,
+}
+
+static THREAD_STRATEGY: AtomicU8 = AtomicU8::new(0);
+
+/// Sets where the crate runs its parallel work from now on. Workers which exist already
+/// keep their pools.
+pub fn set_thread_strategy(strategy: ThreadStrategy) {
+    let value = match strategy {
+        ThreadStrategy::OwnPools => 0,
+        ThreadStrategy::CallerPool => 1,
+    };
+    THREAD_STRATEGY.store(value, Ordering::SeqCst);
+}
+
+pub fn thread_strategy() -> ThreadStrategy {
+    match THREAD_STRATEGY.load(Ordering::SeqCst) {
+        0 => ThreadStrategy::OwnPools,
+        _ => ThreadStrategy::CallerPool,
+    }
+}
+
+/// Runs `f` in `pool`, or on the current thread with [`ThreadStrategy::CallerPool`] or
+/// when running high-priority work, which stays in its priority pool.
+pub(crate) fn install_in<F, R>(pool: &rayon::ThreadPool, f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    match thread_strategy() {
+        ThreadStrategy::OwnPools if !on_priority_thread() => pool.install(f),
+        _ => f(),
+    }
+}
+
+/// Runs the parallel parts of FFTs and multiexps.
+///
+/// [`Worker::new`] runs them on the global [`THREAD_POOL`], with [`current_threads`]
+/// threads, or in the pool of the caller with [`ThreadStrategy::CallerPool`].
+/// [`Worker::with_threads`] creates a worker with its own pool, so that e.g. two
+/// provers in one process each get their share of the cores instead of both splitting
+/// their work for all of them.
+///
+/// Work is either of low or high priority, see [`Worker::scope_with_priority`]. A
+/// worker created with [`Worker::with_priority`] runs all its work at high priority,
+/// e.g. for a proof which must be done before a deadline while other proofs are
+/// computed in the background.
+#[derive(Clone)]
+pub struct Worker {
+    fft_config: FftConfig,
+    pool: WorkerPool,
+    priority: bool,
 }
 
 #[derive(Clone)]
-pub struct Worker {}
+enum WorkerPool {
+    /// [`THREAD_POOL`], with [`PRIORITY_POOL`] for high-priority work.
+    Global,
+    /// The pool of a worker, its priority pool and the computations queued on the pool.
+    Own(
+        Arc<rayon::ThreadPool>,
+        Arc<rayon::ThreadPool>,
+        Arc<ComputeQueue>,
+    ),
+    /// The pool of the caller, see [`ThreadStrategy::CallerPool`].
+    Caller,
+}
+
+impl WorkerPool {
+    fn from_strategy() -> Self {
+        match thread_strategy() {
+            ThreadStrategy::OwnPools => WorkerPool::Global,
+            ThreadStrategy::CallerPool => WorkerPool::Caller,
+        }
+    }
+}
 
 impl Worker {
+    /// Creates a worker with the FFT settings from [`FftConfig::from_env`].
     pub fn new() -> Worker {
-        Worker {}
+        Worker {
+            fft_config: FftConfig::from_env(),
+            pool: WorkerPool::from_strategy(),
+            priority: false,
+        }
+    }
+
+    pub fn with_fft_config(fft_config: FftConfig) -> Worker {
+        Worker {
+            fft_config,
+            pool: WorkerPool::from_strategy(),
+            priority: false,
+        }
+    }
+
+    /// Creates a worker with its own pool of `num_threads` threads and the FFT settings
+    /// from [`FftConfig::from_env`].
+    ///
+    /// The worker has a priority pool of as many threads of its own as well, so that its
+    /// high-priority work also runs on `num_threads` threads.
+    pub fn with_threads(num_threads: usize) -> Worker {
+        assert!(num_threads > 0, "a worker needs at least one thread");
+        Self::with_pool(num_threads, |_| {})
+    }
+
+    /// Creates a worker with its own pool of one thread per CPU in `cpus`, each pinned
+    /// to its CPU, and the FFT settings from [`FftConfig::from_env`].
+    ///
+    /// Pinning needs the `affinity` feature and Linux; elsewhere, or if the CPUs do not
+    /// exist, the threads run unpinned.
+    pub fn with_cpus(cpus: &[usize]) -> Worker {
+        assert!(!cpus.is_empty(), "a worker needs at least one CPU");
+        let cpus = cpus.to_vec();
+        Self::with_pool(cpus.len(), move |index| {
+            affinity::pin_current_thread(&cpus[index..=index]);
+        })
+    }
+
+    /// Creates a worker with a pool and a priority pool of `num_threads` threads each,
+    /// calling `start_handler` with its index on every thread of both.
+    fn with_pool<S>(num_threads: usize, start_handler: S) -> Worker
+    where
+        S: Fn(usize) + Clone + Send + Sync + 'static,
+    {
+        let build = |priority: bool| {
+            let start_handler = start_handler.clone();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .start_handler(move |index| {
+                    if priority {
+                        mark_priority_thread();
+                    }
+                    start_handler(index);
+                })
+                .build()
+                .expect("failed to create the thread pool of a worker");
+            Arc::new(pool)
+        };
+
+        Worker {
+            fft_config: FftConfig::from_env(),
+            pool: WorkerPool::Own(build(false), build(true), Arc::default()),
+            priority: false,
+        }
+    }
+
+    /// Makes the worker run in the pool of its caller, as with
+    /// [`ThreadStrategy::CallerPool`].
+    pub fn in_caller_pool(mut self) -> Worker {
+        self.pool = WorkerPool::Caller;
+        self
+    }
+
+    /// Makes the worker run all its work at high priority, or at low priority again.
+    pub fn with_priority(mut self, priority: bool) -> Worker {
+        self.priority = priority;
+        self
+    }
+
+    pub fn priority(&self) -> bool {
+        self.priority
+    }
+
+    pub fn fft_config(&self) -> &FftConfig {
+        &self.fft_config
+    }
+
+    /// Whether work started from the current thread runs at high priority.
+    fn effective_priority(&self, priority: bool) -> bool {
+        priority || on_priority_thread()
+    }
+
+    /// The pool running the work of the worker of the given priority, `None` if it
+    /// runs in the pool of the caller.
+    fn pool(&self, priority: bool) -> Option<&rayon::ThreadPool> {
+        match self.pool {
+            WorkerPool::Caller => None,
+            WorkerPool::Global if priority => Some(&PRIORITY_POOL),
+            WorkerPool::Global => Some(&THREAD_POOL),
+            WorkerPool::Own(_, ref priority_pool, _) if priority => Some(priority_pool),
+            WorkerPool::Own(ref pool, _, _) => Some(pool),
+        }
+    }
+
+    /// The number of threads of the pool of the worker.
+    pub fn num_threads(&self) -> usize {
+        self.num_threads_with_priority(self.effective_priority(self.priority))
+    }
+
+    fn num_threads_with_priority(&self, priority: bool) -> usize {
+        match self.pool(priority) {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        }
     }
 
     pub fn log_num_cpus(&self) -> u32 {
-        log2_floor(*NUM_CPUS)
+        log2_floor(self.num_threads())
+    }
+
+    /// Runs `f` in the pool of the worker, so that the parallel iterators it uses run
+    /// on the threads of the worker.
+    pub fn install<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        let priority = self.effective_priority(self.priority);
+        match self.pool(priority) {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
     }
 
+    /// Runs `f` in the background, or right away for a worker in the pool of the
+    /// caller, which must not wait for work it queued itself.
+    ///
+    /// Computations run on the pool of the worker, also those of high priority, which
+    /// start before the low-priority computations still queued on the same pool. If a
+    /// computation panics, its waiter reports [`WaitError::Disconnected`].
     pub fn compute<F, R>(&self, f: F) -> Waiter<R>
     where
         F: FnOnce() -> R + Send + 'static,
         R: Send + 'static,
     {
-        let (sender, receiver) = bounded(1);
-        THREAD_POOL.spawn(move || {
-            let res = f();
-            sender.send(res).unwrap();
+        let (pool, queue) = match self.pool {
+            WorkerPool::Caller => return Waiter::done(f()),
+            WorkerPool::Global => (&*THREAD_POOL, &*THREAD_POOL_QUEUE),
+            WorkerPool::Own(ref pool, _, ref queue) => (&**pool, queue),
+        };
+
+        let (completion, waiter) = Waiter::channel();
+        queue.push(
+            self.effective_priority(self.priority),
+            Box::new(move || {
+                // A panic would abort the process on a pool thread. Dropping the
+                // completion without a result instead disconnects the waiter.
+                match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+                    Ok(result) => completion.send(result),
+                    Err(_) => error!("a computation on a worker panicked"),
+                }
+            }),
+        );
+        let queue = queue.clone();
+        pool.spawn(move || {
+            if let Some(job) = queue.pop() {
+                job();
+            }
         });
 
-        Waiter { receiver }
+        waiter
+    }
+
+    /// A queue for computations on this worker with backpressure, letting
+    /// [`DEFAULT_QUEUE_JOBS_PER_THREAD`] computations per thread wait or run.
+    pub fn queue(&self) -> WorkerQueue {
+        self.queue_with_limit(DEFAULT_QUEUE_JOBS_PER_THREAD * self.num_threads())
+    }
+
+    /// A queue for computations on this worker, letting `limit` of them wait or run.
+    pub fn queue_with_limit(&self, limit: usize) -> WorkerQueue {
+        assert!(limit > 0, "a queue must let at least one computation run");
+        WorkerQueue {
+            worker: self.clone(),
+            limit,
+            outstanding: Arc::new(Outstanding::default()),
+        }
     }
 
+    /// Runs `f` with a scope to spawn the chunks of work on, at the priority of the
+    /// worker, see [`Worker::scope_with_priority`].
     pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
     where
         F: FnOnce(&rayon::Scope<'a>, usize) -> R + Send,
         R: Send,
     {
-        let chunk_size = if elements < *NUM_CPUS {
+        self.scope_with_priority(elements, self.priority, f)
+    }
+
+    /// Runs `f` with a scope to spawn the chunks of work on and the size of the chunks
+    /// to split `elements` into, one per thread.
+    ///
+    /// High-priority scopes run on the priority pool of the worker, [`PRIORITY_POOL`]
+    /// for workers on the global pool, so their chunks do not queue behind low-priority
+    /// ones on the pool of the worker. Low-priority work is not held
+    /// back in the meantime, it shares the cores. Work started from within
+    /// high-priority work is of high priority as well. Workers in the pool of the
+    /// caller ignore the priority.
+    pub fn scope_with_priority<'a, F, R>(&self, elements: usize, priority: bool, f: F) -> R
+    where
+        F: FnOnce(&rayon::Scope<'a>, usize) -> R + Send,
+        R: Send,
+    {
+        let priority = self.effective_priority(priority);
+        let num_threads = self.num_threads_with_priority(priority);
+        let chunk_size = if elements < num_threads {
             1
         } else {
-            elements / *NUM_CPUS
+            elements / num_threads
         };
 
-        THREAD_POOL.scope(|scope| f(scope, chunk_size))
+        match self.pool(priority) {
+            Some(pool) => pool.scope(|scope| f(scope, chunk_size)),
+            None => rayon::in_place_scope(|scope| f(scope, chunk_size)),
+        }
+    }
+
+    /// Splits `data` into one chunk per thread and calls `f` with the offset and the
+    /// elements of every chunk, in parallel, like a [`Worker::scope`] spawning a task per
+    /// chunk.
+    pub fn scope_chunks_mut<T, F>(&self, data: &mut [T], f: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        let f = &f;
+        self.scope(data.len(), |scope, chunk| {
+            for (i, data) in data.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| f(i * chunk, data));
+            }
+        });
     }
 }
 
+/// The number of computations a [`WorkerQueue`] from [`Worker::queue`] lets wait or run
+/// per thread of the worker.
+pub const DEFAULT_QUEUE_JOBS_PER_THREAD: usize = 2;
+
+/// A handle to queue computations on a [`Worker`] with backpressure.
+///
+/// At most `limit` computations queued through the handle, and its clones, wait or run
+/// at a time; [`WorkerQueue::compute`] blocks until one of them finishes when the limit
+/// is reached. This bounds the memory held by queued closures, e.g. the parameters they
+/// capture, when queueing more work than the threads can take on. The handle must not
+/// be used from within its own computations, which would wait for themselves.
+#[derive(Clone)]
+pub struct WorkerQueue {
+    worker: Worker,
+    limit: usize,
+    outstanding: Arc<Outstanding>,
+}
+
+#[derive(Default)]
+struct Outstanding {
+    count: Mutex<usize>,
+    finished: Condvar,
+}
+
+/// A slot of a [`WorkerQueue`], released when its computation finishes or panics.
+struct Permit(Arc<Outstanding>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap() -= 1;
+        self.0.finished.notify_one();
+    }
+}
+
+impl WorkerQueue {
+    /// Runs `f` on the worker like [`Worker::compute`], first waiting for a slot if
+    /// `limit` computations of this queue are waiting or running.
+    pub fn compute<F, R>(&self, f: F) -> Waiter<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut count = self.outstanding.count.lock().unwrap();
+        while *count >= self.limit {
+            count = self.outstanding.finished.wait(count).unwrap();
+        }
+        *count += 1;
+        drop(count);
+
+        self.spawn(f)
+    }
+
+    /// Runs `f` on the worker if a slot is free, and gives it back otherwise.
+    pub fn try_compute<F, R>(&self, f: F) -> Result<Waiter<R>, F>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut count = self.outstanding.count.lock().unwrap();
+        if *count >= self.limit {
+            return Err(f);
+        }
+        *count += 1;
+        drop(count);
+
+        Ok(self.spawn(f))
+    }
+
+    fn spawn<F, R>(&self, f: F) -> Waiter<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let permit = Permit(self.outstanding.clone());
+        self.worker.compute(move || {
+            let res = f();
+            drop(permit);
+            res
+        })
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The number of computations of this queue which wait or run.
+    pub fn outstanding(&self) -> usize {
+        *self.outstanding.count.lock().unwrap()
+    }
+}
+
+/// The result of a computation running on a [`Worker`].
+///
+/// With the `async` feature, a waiter is also a [`Future`] of the result, woken by the
+/// worker thread finishing the computation, so that awaiting it does not block a
+/// thread. Dropping the future does not cancel the computation: it runs to completion
+/// and its result is discarded.
 pub struct Waiter<T> {
     receiver: Receiver<T>,
+    /// Whether the result has been taken.
+    taken: AtomicBool,
+    /// The task to wake once the result is sent, if one is waiting.
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+/// The sending side of a [`Waiter`], which wakes its task once the result is sent or
+/// the computation ended without one.
+struct Completion<T> {
+    sender: Option<Sender<T>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl<T> Completion<T> {
+    fn send(mut self, value: T) {
+        if let Some(sender) = self.sender.take() {
+            // The waiter may have been dropped, in which case the result is discarded.
+            let _ = sender.send(value);
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        // Disconnects the channel before waking the task, so that a computation which
+        // panicked is seen as such.
+        self.sender.take();
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Why a [`Waiter`] has no result to give.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitError {
+    /// The result was already taken by an earlier call.
+    #[error("the result was already taken")]
+    Taken,
+    /// The computation ended without a result, e.g. because it panicked.
+    #[error("the computation ended without a result")]
+    Disconnected,
 }
 
 impl<T> Waiter<T> {
+    fn new(receiver: Receiver<T>) -> Self {
+        Waiter {
+            receiver,
+            taken: AtomicBool::new(false),
+            waker: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// A waiter for the result sent with the returned completion.
+    fn channel() -> (Completion<T>, Self) {
+        let (sender, receiver) = bounded(1);
+        let waiter = Waiter::new(receiver);
+        let completion = Completion {
+            sender: Some(sender),
+            waker: waiter.waker.clone(),
+        };
+
+        (completion, waiter)
+    }
+
     /// Wait for the result.
+    ///
+    /// Panics if the computation ended without a result or the result was already
+    /// taken.
     pub fn wait(&self) -> T {
-        self.receiver.recv().unwrap()
+        let received = self
+            .receiver
+            .recv()
+            .map_err(|_| Some(WaitError::Disconnected));
+        match self.take(received) {
+            Ok(Some(value)) => value,
+            Ok(None) => unreachable!("blocking receives do not time out"),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Waits at most `timeout` for the result, returning `None` if it is not ready by
+    /// then. The waiter can be polled again after a timeout.
+    ///
+    /// Once the result was returned, every later call returns [`WaitError::Taken`]. If
+    /// the computation ended without a result, e.g. because it panicked, every call
+    /// returns [`WaitError::Disconnected`].
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Option<T>, WaitError> {
+        self.take(self.receiver.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => None,
+            RecvTimeoutError::Disconnected => Some(WaitError::Disconnected),
+        }))
+    }
+
+    /// Returns the result if it is ready, `None` otherwise, without blocking. Repeated
+    /// calls behave like those of [`Waiter::wait_timeout`].
+    pub fn try_wait(&self) -> Result<Option<T>, WaitError> {
+        self.take(self.receiver.try_recv().map_err(|e| match e {
+            TryRecvError::Empty => None,
+            TryRecvError::Disconnected => Some(WaitError::Disconnected),
+        }))
+    }
+
+    /// Records that the result was taken. `received` is the result or an error, if
+    /// any.
+    fn take(&self, received: Result<T, Option<WaitError>>) -> Result<Option<T>, WaitError> {
+        match received {
+            Ok(value) => {
+                self.taken.store(true, Ordering::SeqCst);
+                Ok(Some(value))
+            }
+            Err(_) if self.taken.load(Ordering::SeqCst) => Err(WaitError::Taken),
+            Err(None) => Ok(None),
+            Err(Some(e)) => Err(e),
+        }
     }
 
     /// One off sending.
@@ -85,7 +791,28 @@ impl<T> Waiter<T> {
         let (sender, receiver) = bounded(1);
         sender.send(val).unwrap();
 
-        Waiter { receiver }
+        Waiter::new(receiver)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Future for Waiter<T> {
+    type Output = T;
+
+    /// Panics like [`Waiter::wait`] if the computation ended without a result or the
+    /// result was already taken.
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        // The lock is held from checking for the result until the waker is stored, so
+        // that a result sent in between still wakes the task.
+        let mut waker = self.waker.lock().unwrap();
+        match self.try_wait() {
+            Ok(Some(value)) => Poll::Ready(value),
+            Ok(None) => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            Err(e) => panic!("{}", e),
+        }
     }
 }
 
@@ -114,4 +841,341 @@ mod tests {
         assert_eq!(log2_floor(7), 2);
         assert_eq!(log2_floor(8), 3);
     }
+
+    #[test]
+    fn test_initialize_after_use() {
+        // Reading the count fixes it to the default.
+        let threads = current_threads();
+        assert_eq!(threads, *NUM_CPUS);
+        assert_eq!(
+            initialize(threads + 1),
+            Err(AlreadyInitialized {
+                threads,
+                site: InitSite::Default,
+            })
+        );
+        assert_eq!(current_threads(), threads);
+    }
+
+    #[test]
+    fn test_worker_threads() {
+        use std::sync::atomic::AtomicUsize;
+
+        let global = Worker::new();
+        assert_eq!(global.num_threads(), *NUM_CPUS);
+
+        // Workers with their own pools run side by side, each splitting its work by its
+        // own number of threads and running it on its own threads.
+        let handles = [3, 5]
+            .iter()
+            .map(|&num_threads| {
+                std::thread::spawn(move || {
+                    let worker = Worker::with_threads(num_threads);
+                    assert_eq!(worker.num_threads(), num_threads);
+                    assert_eq!(worker.log_num_cpus(), log2_floor(num_threads));
+
+                    let chunks = AtomicUsize::new(0);
+                    let foreign = AtomicUsize::new(0);
+                    let elements = vec![0u8; 1000];
+                    let chunk_size = worker.scope(elements.len(), |scope, chunk| {
+                        for _ in elements.chunks(chunk) {
+                            let (chunks, foreign) = (&chunks, &foreign);
+                            scope.spawn(move |_| {
+                                chunks.fetch_add(1, Ordering::SeqCst);
+                                if rayon::current_num_threads() != num_threads {
+                                    foreign.fetch_add(1, Ordering::SeqCst);
+                                }
+                            });
+                        }
+                        chunk
+                    });
+
+                    let in_pool = worker.compute(move || rayon::current_num_threads()).wait();
+                    (
+                        chunk_size,
+                        chunks.into_inner(),
+                        foreign.into_inner(),
+                        in_pool,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let results = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![(333, 4, 0, 3), (200, 5, 0, 5)]);
+    }
+
+    #[test]
+    fn test_scope_chunks_mut() {
+        use std::sync::atomic::AtomicUsize;
+
+        let worker = Worker::with_threads(3);
+        for &len in &[0, 2, 1000] {
+            let mut data = vec![usize::MAX; len];
+            let calls = AtomicUsize::new(0);
+            worker.scope_chunks_mut(&mut data, |offset, chunk| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                for (i, v) in chunk.iter_mut().enumerate() {
+                    assert_eq!(*v, usize::MAX, "every element is visited once");
+                    *v = offset + i;
+                }
+            });
+            assert_eq!(data, (0..len).collect::<Vec<_>>());
+            assert!(calls.into_inner() <= 4);
+        }
+    }
+
+    #[test]
+    fn test_priority_scope_order() {
+        let low = Worker::with_threads(2);
+        let high = low.clone().with_priority(true);
+        assert!(high.priority() && !low.priority());
+        // The priority pool of a worker has as many threads as its pool.
+        assert_eq!(high.num_threads(), 2);
+
+        // Block all threads of the pool of the worker.
+        let (started, blocking) = bounded::<()>(0);
+        let (release, blocked) = bounded::<()>(0);
+        let blockers = (0..low.num_threads())
+            .map(|_| {
+                let started = started.clone();
+                let blocked = blocked.clone();
+                low.compute(move || {
+                    started.send(()).unwrap();
+                    blocked.recv().unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..blockers.len() {
+            blocking.recv().unwrap();
+        }
+
+        // A low-priority scope queues behind the blocked threads, a high-priority one
+        // on the same worker runs in the meantime.
+        let order = Arc::new(Mutex::new(vec![]));
+        let low_scope = {
+            let low = low.clone();
+            let order = order.clone();
+            std::thread::spawn(move || {
+                low.scope(2, |scope, _| {
+                    scope.spawn(|_| order.lock().unwrap().push("low"))
+                })
+            })
+        };
+        high.scope(2, |scope, _| {
+            scope.spawn(|_| order.lock().unwrap().push("high"));
+        });
+
+        for _ in 0..blockers.len() {
+            release.send(()).unwrap();
+        }
+        for blocker in blockers {
+            blocker.wait();
+        }
+        low_scope.join().unwrap();
+        assert_eq!(*order.lock().unwrap(), vec!["high", "low"]);
+    }
+
+    #[test]
+    fn test_compute_priority() {
+        let low = Worker::with_threads(1);
+        let high = low.clone().with_priority(true);
+
+        // Block the only thread, then queue low-priority computations before a
+        // high-priority one, which starts first.
+        let (sender, receiver) = bounded::<()>(0);
+        let blocked = low.compute(move || receiver.recv().unwrap());
+        let order = Arc::new(Mutex::new(vec![]));
+        let waiters = (0..3)
+            .map(|i| (i, &low))
+            .chain(std::iter::once((3, &high)))
+            .map(|(i, worker)| {
+                let order = order.clone();
+                worker.compute(move || order.lock().unwrap().push(i))
+            })
+            .collect::<Vec<_>>();
+        sender.send(()).unwrap();
+        blocked.wait();
+        for waiter in waiters {
+            waiter.wait();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![3, 0, 1, 2]);
+
+        // High-priority work does not hold back the low-priority work of other pools.
+        let (sender, receiver) = bounded::<()>(0);
+        let running = Worker::with_threads(1)
+            .with_priority(true)
+            .compute(move || receiver.recv().unwrap());
+        let other = Worker::with_threads(1).compute(|| 1);
+        assert_eq!(other.wait_timeout(Duration::from_secs(60)), Ok(Some(1)));
+        sender.send(()).unwrap();
+        running.wait();
+    }
+
+    #[test]
+    fn test_worker_queue_backpressure() {
+        use std::sync::atomic::AtomicUsize;
+
+        /// Counts the closures which exist, recording the maximum.
+        struct Alive(Arc<(AtomicUsize, AtomicUsize)>);
+
+        impl Alive {
+            fn new(counts: &Arc<(AtomicUsize, AtomicUsize)>) -> Self {
+                let alive = counts.0.fetch_add(1, Ordering::SeqCst) + 1;
+                counts.1.fetch_max(alive, Ordering::SeqCst);
+                Alive(counts.clone())
+            }
+        }
+
+        impl Drop for Alive {
+            fn drop(&mut self) {
+                (self.0).0.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        let worker = Worker::with_threads(2);
+        let queue = worker.queue();
+        assert_eq!(queue.limit(), DEFAULT_QUEUE_JOBS_PER_THREAD * 2);
+
+        let counts = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0)));
+        let waiters = (0..100)
+            .map(|i| {
+                let alive = Alive::new(&counts);
+                queue.compute(move || {
+                    let _alive = alive;
+                    std::thread::sleep(Duration::from_millis(1));
+                    i
+                })
+            })
+            .collect::<Vec<_>>();
+        assert!(queue.outstanding() <= queue.limit());
+
+        let results = waiters.iter().map(|w| w.wait()).collect::<Vec<_>>();
+        assert_eq!(results, (0..100).collect::<Vec<_>>());
+        assert!(counts.1.load(Ordering::SeqCst) <= queue.limit() + worker.num_threads());
+
+        // A full queue gives the closure back instead of blocking.
+        let queue = worker.queue_with_limit(1);
+        let (sender, receiver) = bounded::<()>(0);
+        let blocked = queue.compute(move || receiver.recv().unwrap());
+        assert_eq!(queue.outstanding(), 1);
+        let rejected = match queue.try_compute(|| 1) {
+            Err(f) => f,
+            Ok(_) => panic!("the queue is full"),
+        };
+        sender.send(()).unwrap();
+        blocked.wait();
+        assert_eq!(queue.compute(rejected).wait(), 1);
+        assert_eq!(queue.outstanding(), 0);
+    }
+
+    #[test]
+    fn test_waiter_polling() {
+        let (sender, receiver) = bounded(1);
+        let waiter = Waiter::new(receiver);
+
+        // Timeouts and polls without a result can be repeated.
+        assert_eq!(waiter.try_wait(), Ok(None));
+        assert_eq!(waiter.wait_timeout(Duration::from_millis(10)), Ok(None));
+        assert_eq!(waiter.try_wait(), Ok(None));
+
+        let producer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            sender.send(42).unwrap();
+        });
+        assert_eq!(waiter.wait_timeout(Duration::from_secs(60)), Ok(Some(42)));
+        producer.join().unwrap();
+
+        // The result is only given once.
+        assert_eq!(waiter.try_wait(), Err(WaitError::Taken));
+        assert_eq!(
+            waiter.wait_timeout(Duration::from_millis(10)),
+            Err(WaitError::Taken)
+        );
+
+        let waiter = Waiter::done(7);
+        assert_eq!(waiter.try_wait(), Ok(Some(7)));
+        assert_eq!(waiter.try_wait(), Err(WaitError::Taken));
+
+        let waiter = Worker::new().compute(|| 3);
+        assert_eq!(waiter.wait_timeout(Duration::from_secs(60)), Ok(Some(3)));
+        assert_eq!(
+            waiter.wait_timeout(Duration::from_secs(60)),
+            Err(WaitError::Taken)
+        );
+    }
+
+    #[test]
+    fn test_waiter_panicked_producer() {
+        let (sender, receiver) = bounded::<u32>(1);
+        let waiter = Waiter::new(receiver);
+
+        let producer = std::thread::spawn(move || {
+            let _sender = sender;
+            panic!("the producer failed");
+        });
+        assert!(producer.join().is_err());
+
+        assert_eq!(
+            waiter.wait_timeout(Duration::from_millis(10)),
+            Err(WaitError::Disconnected)
+        );
+        assert_eq!(waiter.try_wait(), Err(WaitError::Disconnected));
+    }
+
+    #[test]
+    fn test_waiter_panicked_computation() {
+        let worker = Worker::with_threads(1);
+        let queue = worker.queue_with_limit(1);
+
+        let waiter = queue.compute(|| -> u32 { panic!("the computation failed") });
+        assert_eq!(
+            waiter.wait_timeout(Duration::from_secs(60)),
+            Err(WaitError::Disconnected)
+        );
+        assert_eq!(waiter.try_wait(), Err(WaitError::Disconnected));
+
+        // The thread of the worker and the slot of the queue are free again.
+        let waiter = queue.try_compute(|| 5).ok().unwrap();
+        assert_eq!(waiter.wait_timeout(Duration::from_secs(60)), Ok(Some(5)));
+    }
+
+    #[test]
+    fn test_fft_config() {
+        // The default splits into one sub-FFT per thread once every one has two elements.
+        let config = FftConfig::default();
+        assert_eq!(config.parallel_log_threads(3, 3), None);
+        assert_eq!(config.parallel_log_threads(4, 3), Some(3));
+        assert_eq!(config.parallel_log_threads(20, 3), Some(3));
+        assert_eq!(config.parallel_log_threads(0, 0), None);
+        assert_eq!(config.parallel_log_threads(1, 0), Some(0));
+
+        let config = FftConfig {
+            min_parallel_log_size: Some(10),
+            max_log_threads_override: None,
+            min_four_step_log_size: None,
+        };
+        assert_eq!(config.parallel_log_threads(9, 3), None);
+        assert_eq!(config.parallel_log_threads(10, 3), Some(3));
+
+        let config = FftConfig {
+            min_parallel_log_size: Some(2),
+            max_log_threads_override: Some(5),
+            min_four_step_log_size: None,
+        };
+        assert_eq!(config.parallel_log_threads(1, 3), None);
+        assert_eq!(config.parallel_log_threads(3, 3), Some(3));
+        assert_eq!(config.parallel_log_threads(8, 3), Some(5));
+        assert!(!config.four_step(30));
+
+        let config = FftConfig {
+            min_four_step_log_size: Some(20),
+            ..FftConfig::default()
+        };
+        assert!(!config.four_step(19));
+        assert!(config.four_step(20));
+    }
 }
@@ -0,0 +1,90 @@
+//! Pinning threads to CPUs and reading the NUMA topology, on Linux with the `affinity`
+//! feature. On other platforms, or without the feature, threads are not pinned and no
+//! topology is known, so the hints are ignored.
+
+/// Restricts the current thread to run on `cpus`, returning whether that succeeded.
+/// CPUs beyond the largest set the kernel interface supports are left out.
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+pub(super) fn pin_current_thread(cpus: &[usize]) -> bool {
+    let max_cpus = 8 * std::mem::size_of::<libc::cpu_set_t>();
+    // Safe as `cpu_set_t` is a plain bit set, only written within its bounds, and the
+    // call only reads it.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &cpu in cpus.iter().filter(|&&cpu| cpu < max_cpus) {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(all(feature = "affinity", target_os = "linux")))]
+pub(super) fn pin_current_thread(_cpus: &[usize]) -> bool {
+    false
+}
+
+/// The CPUs of every NUMA node of the machine, in the order of the nodes, empty if the
+/// topology is unknown.
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+pub fn numa_nodes() -> Vec<Vec<usize>> {
+    let mut nodes = Vec::new();
+    // Node ids are dense on all but exotic configurations; stop at the first gap.
+    for node in 0.. {
+        let path = format!("/sys/devices/system/node/node{}/cpulist", node);
+        let cpus = match std::fs::read_to_string(path) {
+            Ok(list) => parse_cpu_list(&list),
+            Err(_) => break,
+        };
+        match cpus {
+            // Memory-only nodes have no CPUs to run on.
+            Some(cpus) if !cpus.is_empty() => nodes.push(cpus),
+            Some(_) => {}
+            None => return Vec::new(),
+        }
+    }
+
+    nodes
+}
+
+#[cfg(not(all(feature = "affinity", target_os = "linux")))]
+pub fn numa_nodes() -> Vec<Vec<usize>> {
+    Vec::new()
+}
+
+/// Parses a list of CPUs in the format of the kernel, such as `0-3,8,10-11`.
+#[cfg_attr(not(all(feature = "affinity", target_os = "linux")), allow(dead_code))]
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        let mut bounds = range.splitn(2, '-');
+        let first = bounds.next()?.parse().ok()?;
+        let last = match bounds.next() {
+            Some(last) => last.parse().ok()?,
+            None => first,
+        };
+        if last < first {
+            return None;
+        }
+        cpus.extend(first..=last);
+    }
+
+    Some(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpu_list("5"), Some(vec![5]));
+        assert_eq!(parse_cpu_list("\n"), Some(vec![]));
+        assert_eq!(parse_cpu_list("3-1"), None);
+        assert_eq!(parse_cpu_list("a-b"), None);
+    }
+}
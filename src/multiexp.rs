@@ -1,22 +1,30 @@
 use bit_vec::{self, BitVec};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
-use groupy::{CurveAffine, CurveProjective};
-use log::{info, warn};
+use groupy::{CurveAffine, CurveProjective, EncodedPoint};
+use log::{error, info, warn};
 use rayon::prelude::*;
-use std::io;
+use std::io::{self, Read, Write};
 use std::iter;
-use std::sync::Arc;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 
-use super::multicore::{Waiter, Worker};
+use super::multicore::{install_in, Waiter, Worker, THREAD_POOL};
 use super::SynthesisError;
 use crate::gpu;
 
+mod batch_affine;
+
+pub(crate) use self::batch_affine::batch_invert;
+
 /// An object that builds a source of bases.
 pub trait SourceBuilder<G: CurveAffine>: Send + Sync + 'static + Clone {
     type Source: Source<G>;
 
     fn new(self) -> Self::Source;
-    fn get(self) -> (Arc<Vec<G>>, usize);
+    /// All bases with the number of skipped ones. Fails if the bases cannot be read.
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError>;
 }
 
 /// A source of bases, like an iterator.
@@ -27,6 +35,9 @@ pub trait Source<G: CurveAffine> {
         to: &mut <G as CurveAffine>::Projective,
     ) -> Result<(), SynthesisError>;
 
+    /// Parses the element from the source and returns it. Fails if the point is at infinity.
+    fn next_base(&mut self) -> Result<G, SynthesisError>;
+
     /// Skips `amt` elements from the source, avoiding deserialization.
     fn skip(&mut self, amt: usize) -> Result<(), SynthesisError>;
 }
@@ -38,8 +49,8 @@ impl<G: CurveAffine> SourceBuilder<G> for (Arc<Vec<G>>, usize) {
         (self.0.clone(), self.1)
     }
 
-    fn get(self) -> (Arc<Vec<G>>, usize) {
-        (self.0.clone(), self.1)
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        Ok((self.0.clone(), self.1))
     }
 }
 
@@ -67,6 +78,25 @@ impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
         Ok(())
     }
 
+    fn next_base(&mut self) -> Result<G, SynthesisError> {
+        if self.0.len() <= self.1 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+            .into());
+        }
+
+        let base = self.0[self.1];
+        if base.is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        self.1 += 1;
+
+        Ok(base)
+    }
+
     fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
         if self.0.len() <= self.1 {
             return Err(io::Error::new(
@@ -82,6 +112,168 @@ impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
     }
 }
 
+/// Bases stored as consecutive uncompressed points in a byte region, typically a memory
+/// mapped parameter file. Points are deserialized on demand by the multiexp worker threads,
+/// instead of being collected into a `Vec` up front.
+pub struct EncodedBases<G: CurveAffine, B> {
+    bytes: Arc<B>,
+    start: usize,
+    len: usize,
+    offset: usize,
+    checked: bool,
+    _g: PhantomData<G>,
+}
+
+impl<G: CurveAffine, B> Clone for EncodedBases<G, B> {
+    fn clone(&self) -> Self {
+        EncodedBases {
+            bytes: self.bytes.clone(),
+            start: self.start,
+            len: self.len,
+            offset: self.offset,
+            checked: self.checked,
+            _g: PhantomData,
+        }
+    }
+}
+
+impl<G: CurveAffine, B: AsRef<[u8]>> EncodedBases<G, B> {
+    /// Uses the points stored in `range` of `bytes`. Fails if the range lies outside of
+    /// `bytes` or does not hold a whole number of points.
+    pub fn new(bytes: Arc<B>, range: Range<usize>, checked: bool) -> io::Result<Self> {
+        let point_len = G::Uncompressed::size();
+
+        if range.start > range.end || range.end > (*bytes).as_ref().len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bases region is truncated",
+            ));
+        }
+        if (range.end - range.start) % point_len != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "bases region is not aligned to the point size",
+            ));
+        }
+
+        Ok(EncodedBases {
+            bytes,
+            start: range.start,
+            len: (range.end - range.start) / point_len,
+            offset: 0,
+            checked,
+            _g: PhantomData,
+        })
+    }
+
+    /// Skips the first `offset` bases, like the `usize` of `(Arc<Vec<G>>, usize)`.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Number of points in the region, including the skipped ones.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn read(&self, idx: usize) -> Result<G, SynthesisError> {
+        if self.len <= idx {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+            .into());
+        }
+
+        let point_len = G::Uncompressed::size();
+        let start = self.start + idx * point_len;
+        let bytes: &[u8] = (*self.bytes).as_ref();
+
+        let mut repr = G::Uncompressed::empty();
+        repr.as_mut()
+            .copy_from_slice(&bytes[start..start + point_len]);
+
+        let base = if self.checked {
+            repr.into_affine()
+        } else {
+            repr.into_affine_unchecked()
+        }
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(base)
+    }
+}
+
+impl<G: CurveAffine, B: AsRef<[u8]> + Send + Sync + 'static> SourceBuilder<G>
+    for EncodedBases<G, B>
+{
+    type Source = EncodedBases<G, B>;
+
+    fn new(self) -> EncodedBases<G, B> {
+        self
+    }
+
+    /// Materializes all bases, which is only needed by the GPU kernels. Fails if the
+    /// region contains invalid points, like the CPU path.
+    fn get(self) -> Result<(Arc<Vec<G>>, usize), SynthesisError> {
+        let bases = (0..self.len)
+            .map(|i| self.read(i))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((Arc::new(bases), self.offset))
+    }
+}
+
+impl<G: CurveAffine, B: AsRef<[u8]>> Source<G> for EncodedBases<G, B> {
+    fn add_assign_mixed(
+        &mut self,
+        to: &mut <G as CurveAffine>::Projective,
+    ) -> Result<(), SynthesisError> {
+        let base = self.read(self.offset)?;
+
+        if base.is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        to.add_assign_mixed(&base);
+
+        self.offset += 1;
+
+        Ok(())
+    }
+
+    fn next_base(&mut self) -> Result<G, SynthesisError> {
+        let base = self.read(self.offset)?;
+
+        if base.is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        self.offset += 1;
+
+        Ok(base)
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        if self.len <= self.offset {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+            .into());
+        }
+
+        self.offset += amt;
+
+        Ok(())
+    }
+}
+
 pub trait QueryDensity {
     /// Returns whether the base exists.
     type Iter: Iterator<Item = bool>;
@@ -193,6 +385,7 @@ impl DensityTracker {
 }
 
 fn multiexp_inner<Q, D, G, S>(
+    pool: &Worker,
     bases: S,
     density_map: D,
     exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
@@ -204,6 +397,18 @@ where
     G: CurveAffine,
     S: SourceBuilder<G>,
 {
+    if exponents.len() >= batch_affine::MIN_EXPONENTS {
+        if let Some(result) = batch_affine::multiexp(
+            pool,
+            bases.clone(),
+            density_map.clone(),
+            exponents.clone(),
+            c,
+        ) {
+            return result;
+        }
+    }
+
     // Perform this region of the multiexp
     let this = move |bases: S,
                      density_map: D,
@@ -282,8 +487,290 @@ where
         })
 }
 
+/// The non-zero exponents of a multiexp, each with the position of its base among the
+/// bases the density map selects.
+///
+/// Zero exponents add nothing, but the windowed multiexp still reads and skips their
+/// bases once per window. Witnesses of circuits with many constant or unused wires make
+/// the A and B queries mostly zero, and then going over the non-zero terms only saves
+/// most of the work.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparsePolynomial<F: PrimeField> {
+    len: usize,
+    terms: Vec<(usize, F::Repr)>,
+}
+
+impl<F: PrimeField> SparsePolynomial<F> {
+    /// The non-zero exponents of `exponents` whose bases exist in `density_map`.
+    pub fn from_exponents<Q: QueryDensity>(exponents: &[F::Repr], density_map: Q) -> Self {
+        let zero = F::zero().into_repr();
+
+        let mut len = 0;
+        let mut terms = vec![];
+        for (&exp, density) in exponents.iter().zip(density_map.iter()) {
+            if density {
+                if exp != zero {
+                    terms.push((len, exp));
+                }
+                len += 1;
+            }
+        }
+
+        SparsePolynomial { len, terms }
+    }
+
+    /// Like [`from_exponents`](Self::from_exponents), if more than 90% of the exponents
+    /// are zero. The exponents are only counted otherwise.
+    pub fn from_sparse_exponents<Q>(exponents: &[F::Repr], density_map: Q) -> Option<Self>
+    where
+        Q: QueryDensity + Copy,
+    {
+        let zero = F::zero().into_repr();
+
+        let mut len = 0;
+        let mut num_terms = 0;
+        for (exp, density) in exponents.iter().zip(density_map.iter()) {
+            if density {
+                len += 1;
+                if *exp != zero {
+                    num_terms += 1;
+                }
+            }
+        }
+
+        if is_sparse(num_terms, len) {
+            Some(Self::from_exponents(exponents, density_map))
+        } else {
+            None
+        }
+    }
+
+    /// The number of bases, including those with zero exponents.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of non-zero exponents.
+    pub fn num_terms(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// The positions of the bases with non-zero exponents, and the exponents, in order.
+    pub fn terms(&self) -> &[(usize, F::Repr)] {
+        &self.terms
+    }
+
+    /// Whether more than 90% of the exponents are zero.
+    pub fn is_sparse(&self) -> bool {
+        is_sparse(self.terms.len(), self.len)
+    }
+}
+
+fn is_sparse(num_terms: usize, len: usize) -> bool {
+    num_terms * 10 < len
+}
+
+/// Like [`multiexp_inner`], reading only the bases of the terms of `exponents`.
+fn multiexp_sparse_inner<G, S>(
+    bases: S,
+    exponents: Arc<SparsePolynomial<<G::Engine as ScalarEngine>::Fr>>,
+    c: u32,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    G: CurveAffine,
+    S: SourceBuilder<G>,
+{
+    let this = move |bases: S,
+                     exponents: Arc<SparsePolynomial<<G::Engine as ScalarEngine>::Fr>>,
+                     skip: u32|
+          -> Result<_, SynthesisError> {
+        let mut acc = G::Projective::zero();
+        let mut bases = bases.new();
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+
+        let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+        let handle_trivial = skip == 0;
+
+        // The position of the next base of the source.
+        let mut position = 0;
+        for &(index, exp) in exponents.terms() {
+            if index > position {
+                bases.skip(index - position)?;
+            }
+            position = index + 1;
+
+            if exp == one {
+                if handle_trivial {
+                    bases.add_assign_mixed(&mut acc)?;
+                } else {
+                    bases.skip(1)?;
+                }
+            } else {
+                let mut exp = exp;
+                exp.shr(skip);
+                let exp = exp.as_ref()[0] % (1 << c);
+
+                if exp != 0 {
+                    bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
+                } else {
+                    bases.skip(1)?;
+                }
+            }
+        }
+
+        let mut running_sum = G::Projective::zero();
+        for exp in buckets.into_iter().rev() {
+            running_sum.add_assign(&exp);
+            acc.add_assign(&running_sum);
+        }
+
+        Ok(acc)
+    };
+
+    let parts = (0..<G::Engine as ScalarEngine>::Fr::NUM_BITS)
+        .into_par_iter()
+        .step_by(c as usize)
+        .map(|skip| this(bases.clone(), exponents.clone(), skip))
+        .collect::<Vec<Result<_, _>>>();
+
+    parts
+        .into_iter()
+        .rev()
+        .try_fold(<G as CurveAffine>::Projective::zero(), |mut acc, part| {
+            for _ in 0..c {
+                acc.double();
+            }
+
+            acc.add_assign(&part?);
+            Ok(acc)
+        })
+}
+
+/// Multiexp over slices on the calling thread, e.g. to check results of the GPU.
+#[cfg(any(feature = "gpu", test))]
+pub(crate) fn multiexp_slice<G>(
+    bases: &[G],
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    G: CurveAffine,
+{
+    multiexp_inner(
+        &Worker::new().in_caller_pool(),
+        (Arc::new(bases.to_vec()), 0),
+        FullDensity,
+        Arc::new(exponents.to_vec()),
+        window_size::<G>(exponents.len()),
+    )
+}
+
+/// Size of the base field elements the window heuristic was tuned for (BLS12-381 G1).
+const REFERENCE_BASE_BYTES: usize = 48;
+
+/// Window size for a multiexp with `num_exponents` exponents over `G`.
+///
+/// Each window touches its buckets in random order and adds them up with full
+/// projective additions, both of which get relatively more expensive than the mixed
+/// additions into the buckets as the coordinates grow. The window therefore shrinks by
+/// one bit for every doubling of the base field size, e.g. for G2 over `Fq2`.
+fn window_size<G: CurveAffine>(num_exponents: usize) -> u32 {
+    if num_exponents < 32 {
+        return 3;
+    }
+
+    let c = (f64::from(num_exponents as u32)).ln().ceil() as u32;
+
+    let ratio = std::mem::size_of::<G::Base>() / REFERENCE_BASE_BYTES;
+    let shrink = if ratio > 1 {
+        (ratio as f64).log2().floor() as u32
+    } else {
+        0
+    };
+
+    std::cmp::max(c.saturating_sub(shrink), 3)
+}
+
+lazy_static::lazy_static! {
+    /// The settings of [`multiexp`], read from the environment on first use.
+    static ref MULTIEXP_CONFIG: MultiexpConfig = MultiexpConfig::from_env();
+}
+
+/// Settings for multiexps dispatched to the GPU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MultiexpConfig {
+    /// Fraction of the chunks computed on the GPU which are recomputed on the CPU and
+    /// compared, between 0.0 (nothing is checked) and 1.0 (everything is checked).
+    ///
+    /// On a mismatch the device is not used again and the multiexp is computed on the
+    /// CPU instead.
+    pub verify_gpu_sample_rate: f32,
+    /// Fraction of the exponents computed on the GPUs, between 0.0 and 1.0, see
+    /// [`MultiexpConfig::gpu_split`]. The rest is computed on the CPU at the same time.
+    pub gpu_fraction: f32,
+}
+
+impl Default for MultiexpConfig {
+    fn default() -> Self {
+        MultiexpConfig {
+            verify_gpu_sample_rate: 0f32,
+            gpu_fraction: 1f32,
+        }
+    }
+}
+
+impl MultiexpConfig {
+    /// Reads the sample rate from `BELLMAN_VERIFY_GPU_SAMPLE_RATE`, defaulting to 0,
+    /// and the fraction of the exponents left to the CPU from
+    /// `BELLMAN_CPU_UTILIZATION`, also defaulting to 0.
+    pub fn from_env() -> Self {
+        let verify_gpu_sample_rate = std::env::var("BELLMAN_VERIFY_GPU_SAMPLE_RATE")
+            .map(|v| match v.parse() {
+                Ok(val) => val,
+                Err(_) => {
+                    error!("Invalid BELLMAN_VERIFY_GPU_SAMPLE_RATE! Defaulting to 0...");
+                    0f32
+                }
+            })
+            .unwrap_or(0f32)
+            .max(0f32)
+            .min(1f32);
+
+        let cpu_utilization = std::env::var("BELLMAN_CPU_UTILIZATION")
+            .map(|v| match v.parse() {
+                Ok(val) => val,
+                Err(_) => {
+                    error!("Invalid BELLMAN_CPU_UTILIZATION! Defaulting to 0...");
+                    0f32
+                }
+            })
+            .unwrap_or(0f32)
+            .max(0f32)
+            .min(1f32);
+
+        MultiexpConfig {
+            verify_gpu_sample_rate,
+            gpu_fraction: 1f32 - cpu_utilization,
+        }
+    }
+
+    /// Splits `n` exponents into the first `gpu_fraction` of them, computed on the GPUs,
+    /// and the rest, computed on the CPU.
+    pub fn gpu_split(&self, n: usize) -> (Range<usize>, Range<usize>) {
+        let gpu_n = ((n as f64) * f64::from(self.gpu_fraction)).round() as usize;
+        let gpu_n = gpu_n.min(n);
+        (0..gpu_n, gpu_n..n)
+    }
+}
+
 /// Perform multi-exponentiation. The caller is responsible for ensuring the
 /// query size is the same as the number of exponents.
+///
+/// GPU results are checked as configured by [`MultiexpConfig::from_env`], which is read
+/// once, on the first multiexp. Use [`multiexp_with_config`] for other settings.
 pub fn multiexp<Q, D, G, S>(
     pool: &Worker,
     bases: S,
@@ -291,6 +778,25 @@ pub fn multiexp<Q, D, G, S>(
     exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
     kern: &mut Option<gpu::LockedMultiexpKernel<G::Engine>>,
 ) -> Waiter<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+    S: SourceBuilder<G>,
+{
+    multiexp_with_config(pool, bases, density_map, exponents, kern, &MULTIEXP_CONFIG)
+}
+
+/// Like [`multiexp`], checking GPU results as set by `config`.
+pub fn multiexp_with_config<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    kern: &mut Option<gpu::LockedMultiexpKernel<G::Engine>>,
+    config: &MultiexpConfig,
+) -> Waiter<Result<<G as CurveAffine>::Projective, SynthesisError>>
 where
     for<'a> &'a Q: QueryDensity,
     D: Send + Sync + 'static + Clone + AsRef<Q>,
@@ -299,7 +805,17 @@ where
     S: SourceBuilder<G>,
 {
     if let Some(ref mut kern) = kern {
-        if let Ok(p) = kern.with(|k: &mut gpu::MultiexpKernel<G::Engine>| {
+        // The bases are only materialized for the GPU, the CPU streams them from their
+        // source.
+        let mut invalid = None;
+        let result = kern.with(|k: &mut gpu::MultiexpKernel<G::Engine>| {
+            let (bss, skip) = match bases.clone().get() {
+                Ok(bases) => bases,
+                Err(e) => {
+                    invalid = Some(e);
+                    return Err(gpu::GPUError::Simple("invalid bases"));
+                }
+            };
             let mut exps = vec![exponents[0]; exponents.len()];
             let mut n = 0;
             for (&e, d) in exponents.iter().zip(density_map.as_ref().iter()) {
@@ -309,18 +825,20 @@ where
                 }
             }
 
-            let (bss, skip) = bases.clone().get();
-            k.multiexp(pool, bss, Arc::new(exps.clone()), skip, n)
-        }) {
-            return Waiter::done(Ok(p));
+            k.multiexp(pool, bss, Arc::new(exps), skip, n, config)
+        });
+        if let Some(e) = invalid {
+            return Waiter::done(Err(e));
         }
-    }
 
-    let c = if exponents.len() < 32 {
-        3u32
-    } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
-    };
+        match result {
+            Ok(p) => return Waiter::done(Ok(p)),
+            Err(e @ gpu::GPUError::ResultMismatch { .. }) => {
+                error!("{} Computing the multiexp on the CPU.", e);
+            }
+            Err(_) => {}
+        }
+    }
 
     if let Some(query_size) = density_map.as_ref().get_query_size() {
         // If the density map has a known query size, it should not be
@@ -328,7 +846,24 @@ where
         assert!(query_size == exponents.len());
     }
 
-    let result = pool.compute(move || multiexp_inner(bases, density_map, exponents, c));
+    // Mostly zero exponents, e.g. of the A and B queries of circuits with many constant
+    // wires, are cheaper to go over as a list of the non-zero ones.
+    let sparse = SparsePolynomial::<<G::Engine as ScalarEngine>::Fr>::from_sparse_exponents(
+        &exponents,
+        density_map.as_ref(),
+    );
+    let result = match sparse {
+        Some(sparse) => {
+            let c = window_size::<G>(sparse.num_terms());
+            let sparse = Arc::new(sparse);
+            pool.compute(move || multiexp_sparse_inner(bases, sparse, c))
+        }
+        None => {
+            let c = window_size::<G>(exponents.len());
+            let worker = pool.clone();
+            pool.compute(move || multiexp_inner(&worker, bases, density_map, exponents, c))
+        }
+    };
 
     #[cfg(feature = "gpu")]
     {
@@ -342,6 +877,249 @@ where
     result
 }
 
+/// A multiexp over all of `bases` and `exponents`, of which the first `gpu_fraction`
+/// is computed on the GPUs of `kern` and the rest on `pool` at the same time. The best
+/// fraction depends on how fast the GPUs are against the CPU, the `multiexp` benchmark
+/// with the `gpu` feature compares some. If the GPUs fail, everything is computed on
+/// the CPU.
+pub fn heterogeneous_multiexp<G>(
+    pool: &Worker,
+    bases: Arc<Vec<G>>,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    gpu_fraction: f32,
+    kern: &mut gpu::LockedMultiexpKernel<G::Engine>,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+{
+    let config = MultiexpConfig {
+        gpu_fraction: gpu_fraction.max(0f32).min(1f32),
+        ..*MULTIEXP_CONFIG
+    };
+
+    let n = exponents.len();
+    let result = kern.with(|k: &mut gpu::MultiexpKernel<G::Engine>| {
+        k.multiexp(pool, bases.clone(), exponents.clone(), 0, n, &config)
+    });
+    match result {
+        Ok(p) => return Ok(p),
+        Err(e @ gpu::GPUError::ResultMismatch { .. }) => {
+            error!("{} Computing the multiexp on the CPU.", e);
+        }
+        Err(_) => {}
+    }
+
+    multiexp(pool, (bases, 0), FullDensity, exponents, &mut None).wait()
+}
+
+/// Largest window supported by [`PrecomputedBases`]; every worker thread allocates
+/// `2^window_bits - 1` buckets.
+pub const MAX_PRECOMPUTED_WINDOW_BITS: u32 = 16;
+
+/// Fixed-base tables for running many multiexps over the same bases.
+///
+/// For every base `g` and every window `k` the table holds `2^(window_bits * k) * g`. A
+/// multiexp over these bases is then a single bucket pass over the `window_bits`-bit digits
+/// of the exponents: there is no doubling chain and no per-window bucket summation.
+///
+/// Memory usage is `num_bases * ceil(NUM_BITS / window_bits)` affine points. For BLS12-381
+/// G1 (96 bytes per point) with an 8 bit window that is 32 points per base, i.e. about 3GiB
+/// for 2^20 bases. Wider windows shrink the tables at the cost of more buckets per thread.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrecomputedBases<G: CurveAffine> {
+    window_bits: u32,
+    num_windows: usize,
+    num_bases: usize,
+    // `num_windows` consecutive entries per base.
+    tables: Vec<G>,
+}
+
+/// The most table entries [`PrecomputedBases::read`] allocates for before reading them.
+const MAX_PREALLOCATED_TABLES: usize = 1 << 16;
+
+impl<G: CurveAffine> PrecomputedBases<G> {
+    /// Builds the tables for `bases` in parallel.
+    pub fn new(bases: &[G], window_bits: u32) -> Self {
+        assert!(
+            window_bits > 0 && window_bits <= MAX_PRECOMPUTED_WINDOW_BITS,
+            "unsupported window size: {}",
+            window_bits
+        );
+        let num_windows = Self::windows_for(window_bits);
+
+        let tables = install_in(&THREAD_POOL, || {
+            bases
+                .par_iter()
+                .flat_map(|base| {
+                    let mut shifted = Vec::with_capacity(num_windows);
+                    let mut current = base.into_projective();
+                    shifted.push(*base);
+                    for _ in 1..num_windows {
+                        for _ in 0..window_bits {
+                            current.double();
+                        }
+                        shifted.push(current.into_affine());
+                    }
+                    shifted
+                })
+                .collect()
+        });
+
+        PrecomputedBases {
+            window_bits,
+            num_windows,
+            num_bases: bases.len(),
+            tables,
+        }
+    }
+
+    fn windows_for(window_bits: u32) -> usize {
+        let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS;
+        ((num_bits + window_bits - 1) / window_bits) as usize
+    }
+
+    pub fn window_bits(&self) -> u32 {
+        self.window_bits
+    }
+
+    /// Number of bases the tables were built from.
+    pub fn len(&self) -> usize {
+        self.num_bases
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_bases == 0
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.window_bits)?;
+        writer.write_u32::<BigEndian>(self.num_bases as u32)?;
+        for g in &self.tables {
+            writer.write_all(g.into_uncompressed().as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
+        let window_bits = reader.read_u32::<BigEndian>()?;
+        if window_bits == 0 || window_bits > MAX_PRECOMPUTED_WINDOW_BITS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid precomputation window size",
+            ));
+        }
+        let num_windows = Self::windows_for(window_bits);
+        let num_bases = reader.read_u32::<BigEndian>()? as usize;
+        let len = num_bases.checked_mul(num_windows).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "too many precomputed bases")
+        })?;
+
+        // The header is not trusted to allocate for, the tables grow as they are read.
+        let mut tables = Vec::with_capacity(len.min(MAX_PREALLOCATED_TABLES));
+        let mut repr = G::Uncompressed::empty();
+        for _ in 0..len {
+            reader.read_exact(repr.as_mut())?;
+            let g = if checked {
+                repr.into_affine()
+            } else {
+                repr.into_affine_unchecked()
+            }
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            tables.push(g);
+        }
+
+        Ok(PrecomputedBases {
+            window_bits,
+            num_windows,
+            num_bases,
+            tables,
+        })
+    }
+}
+
+fn window_digit<R: PrimeFieldRepr>(exp: &R, skip: u32, c: u32) -> usize {
+    let limbs = exp.as_ref();
+    let limb = (skip / 64) as usize;
+    let shift = skip % 64;
+
+    let mut digit = limbs[limb] >> shift;
+    if shift + c > 64 && limb + 1 < limbs.len() {
+        digit |= limbs[limb + 1] << (64 - shift);
+    }
+
+    (digit % (1 << c)) as usize
+}
+
+fn multiexp_precomputed_inner<G: CurveAffine>(
+    tables: &[G],
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    window_bits: u32,
+    num_windows: usize,
+) -> <G as CurveAffine>::Projective {
+    let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << window_bits) - 1];
+
+    for (exp, shifted) in exponents.iter().zip(tables.chunks(num_windows)) {
+        for (k, base) in shifted.iter().enumerate() {
+            let digit = window_digit(exp, k as u32 * window_bits, window_bits);
+            if digit != 0 {
+                buckets[digit - 1].add_assign_mixed(base);
+            }
+        }
+    }
+
+    // Summation by parts
+    let mut acc = G::Projective::zero();
+    let mut running_sum = G::Projective::zero();
+    for exp in buckets.into_iter().rev() {
+        running_sum.add_assign(&exp);
+        acc.add_assign(&running_sum);
+    }
+
+    acc
+}
+
+/// Perform multi-exponentiation over precomputed bases, the first `exponents.len()` of
+/// which are used. The tables are only used on the CPU, so `kern` is currently ignored.
+pub fn multiexp_precomputed<G>(
+    pool: &Worker,
+    bases: &PrecomputedBases<G>,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    _kern: &mut Option<gpu::LockedMultiexpKernel<G::Engine>>,
+) -> Waiter<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    G: CurveAffine,
+    G::Engine: crate::bls::Engine,
+{
+    if exponents.len() > bases.len() {
+        return Waiter::done(Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "expected more bases from source",
+        )
+        .into()));
+    }
+
+    let window_bits = bases.window_bits;
+    let num_windows = bases.num_windows;
+    let result = Mutex::new(<G as CurveAffine>::Projective::zero());
+
+    pool.scope(exponents.len(), |scope, chunk| {
+        for (exps, tables) in exponents
+            .chunks(chunk)
+            .zip(bases.tables.chunks(chunk * num_windows))
+        {
+            let result = &result;
+            scope.spawn(move |_| {
+                let part = multiexp_precomputed_inner(tables, exps, window_bits, num_windows);
+                result.lock().unwrap().add_assign(&part);
+            });
+        }
+    });
+
+    Waiter::done(Ok(result.into_inner().unwrap()))
+}
+
 #[cfg(any(feature = "pairing", feature = "blst"))]
 #[test]
 fn test_with_bls12() {
@@ -393,18 +1171,22 @@ fn test_with_bls12() {
     assert_eq!(naive, fast);
 }
 
-pub fn create_multiexp_kernel<E>(_log_d: usize, priority: bool) -> Option<gpu::MultiexpKernel<E>>
+pub fn create_multiexp_kernel<E>(
+    _log_d: usize,
+    priority: bool,
+    lock: &gpu::GPULock,
+) -> gpu::GPUResult<gpu::MultiexpKernel<E>>
 where
     E: crate::bls::Engine,
 {
-    match gpu::MultiexpKernel::<E>::create(priority) {
+    match gpu::MultiexpKernel::<E>::create_with_lock(priority, lock) {
         Ok(k) => {
             info!("GPU Multiexp kernel instantiated!");
-            Some(k)
+            Ok(k)
         }
         Err(e) => {
             warn!("Cannot instantiate GPU Multiexp kernel! Error: {}", e);
-            None
+            Err(e)
         }
     }
 }
@@ -476,6 +1258,477 @@ mod tests {
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
 
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_window_size() {
+        use crate::bls::{Bls12, Engine};
+
+        type G1 = <Bls12 as Engine>::G1Affine;
+        type G2 = <Bls12 as Engine>::G2Affine;
+
+        assert_eq!(window_size::<G1>(16), 3);
+        assert_eq!(window_size::<G2>(16), 3);
+        assert_eq!(window_size::<G1>(1 << 20), 14);
+        assert_eq!(window_size::<G2>(1 << 20), 13);
+        assert!(window_size::<G2>(40) >= 3);
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_multiexp_g2() {
+        use crate::bls::{Bls12, Engine};
+
+        type G2 = <Bls12 as Engine>::G2Affine;
+        type Fr = <Bls12 as ScalarEngine>::Fr;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pool = Worker::new();
+
+        let mut max_scalar = Fr::one();
+        max_scalar.negate();
+
+        // Both sides of the batch-affine threshold.
+        for &samples in &[10, 100, 1500] {
+            let bases = Arc::new(
+                (0..samples)
+                    .map(|_| <Bls12 as Engine>::G2::random(&mut rng).into_affine())
+                    .collect::<Vec<G2>>(),
+            );
+            let exponents = (0..samples)
+                .map(|i| match i % 4 {
+                    0 => max_scalar,
+                    1 => Fr::zero(),
+                    _ => Fr::random(&mut rng),
+                })
+                .map(|e| e.into_repr())
+                .collect::<Vec<_>>();
+
+            let mut expected = <Bls12 as Engine>::G2::zero();
+            for (base, exp) in bases.iter().zip(exponents.iter()) {
+                expected.add_assign(&base.mul(*exp));
+            }
+
+            let actual = multiexp(
+                &pool,
+                (bases.clone(), 0),
+                FullDensity,
+                Arc::new(exponents),
+                &mut None,
+            )
+            .wait()
+            .unwrap();
+            assert_eq!(expected, actual, "{} samples", samples);
+        }
+
+        // Only maximal scalars: the result is minus the sum of the bases.
+        let bases = Arc::new(
+            (0..64)
+                .map(|_| <Bls12 as Engine>::G2::random(&mut rng).into_affine())
+                .collect::<Vec<G2>>(),
+        );
+        let mut expected = <Bls12 as Engine>::G2::zero();
+        for base in bases.iter() {
+            expected.add_assign_mixed(base);
+        }
+        expected.negate();
+        let actual = multiexp(
+            &pool,
+            (bases, 0),
+            FullDensity,
+            Arc::new(vec![max_scalar.into_repr(); 64]),
+            &mut None,
+        )
+        .wait()
+        .unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_multiexp_sparse() {
+        use crate::bls::{Bls12, Engine};
+
+        type Fr = <Bls12 as ScalarEngine>::Fr;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pool = Worker::new();
+
+        let samples = 2000;
+        let bases = Arc::new(
+            (0..samples)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        // Every 25th exponent is non-zero, among them ones, which the first window adds
+        // directly.
+        let exponents = (0..samples)
+            .map(|i| match i % 25 {
+                0 if i % 100 == 0 => Fr::one(),
+                0 => Fr::random(&mut rng),
+                _ => Fr::zero(),
+            })
+            .map(|e| e.into_repr())
+            .collect::<Vec<_>>();
+
+        let mut density = DensityTracker::new();
+        for i in 0..samples {
+            density.add_element();
+            if i % 3 != 1 {
+                density.inc(i);
+            }
+        }
+
+        let sparse = SparsePolynomial::<Fr>::from_sparse_exponents(&exponents, &density).unwrap();
+        assert_eq!(sparse.len(), density.get_total_density());
+        assert!(sparse.is_sparse());
+        assert!(
+            SparsePolynomial::<Fr>::from_sparse_exponents(&exponents[..30], &density).is_none()
+        );
+
+        let mut expected = <Bls12 as Engine>::G1::zero();
+        let mut selected = bases.iter();
+        for (i, exp) in exponents.iter().enumerate() {
+            if i % 3 != 1 {
+                expected.add_assign(&selected.next().unwrap().mul(*exp));
+            }
+        }
+
+        let actual = multiexp(
+            &pool,
+            (bases.clone(), 0),
+            Arc::new(density.clone()),
+            Arc::new(exponents.clone()),
+            &mut None,
+        )
+        .wait()
+        .unwrap();
+        assert_eq!(expected, actual);
+
+        let dense = multiexp_inner(
+            &pool,
+            (bases, 0),
+            Arc::new(density),
+            Arc::new(exponents),
+            window_size::<<Bls12 as Engine>::G1Affine>(samples),
+        )
+        .unwrap();
+        assert_eq!(dense, actual);
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_multiexp_g2_identity() {
+        use crate::bls::{Bls12, Engine};
+
+        type G2 = <Bls12 as Engine>::G2Affine;
+        type Fr = <Bls12 as ScalarEngine>::Fr;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pool = Worker::new();
+
+        for &samples in &[50, 1500] {
+            let mut bases = (0..samples)
+                .map(|_| <Bls12 as Engine>::G2::random(&mut rng).into_affine())
+                .collect::<Vec<G2>>();
+            bases[7] = G2::zero();
+            let bases = Arc::new(bases);
+
+            // An identity base with a zero exponent is never read.
+            let mut exponents = (0..samples)
+                .map(|_| Fr::random(&mut rng).into_repr())
+                .collect::<Vec<_>>();
+            exponents[7] = Fr::zero().into_repr();
+            assert!(multiexp(
+                &pool,
+                (bases.clone(), 0),
+                FullDensity,
+                Arc::new(exponents.clone()),
+                &mut None
+            )
+            .wait()
+            .is_ok());
+
+            // Otherwise it is rejected.
+            exponents[7] = Fr::one().into_repr();
+            match multiexp(
+                &pool,
+                (bases, 0),
+                FullDensity,
+                Arc::new(exponents),
+                &mut None,
+            )
+            .wait()
+            {
+                Err(SynthesisError::UnexpectedIdentity) => {}
+                other => panic!("expected UnexpectedIdentity, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_heterogeneous_multiexp() {
+        use crate::bls::{Bls12, Engine};
+
+        type G1 = <Bls12 as Engine>::G1Affine;
+        type Fr = <Bls12 as ScalarEngine>::Fr;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pool = Worker::new();
+
+        let samples = 1000;
+        let bases = Arc::new(
+            (0..samples)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut rng).into_affine())
+                .collect::<Vec<G1>>(),
+        );
+        let exponents = Arc::new(
+            (0..samples)
+                .map(|_| Fr::random(&mut rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+        let expected = multiexp_slice(&bases, &exponents).unwrap();
+
+        // Without a GPU, everything is computed on the CPU.
+        let mut kern = gpu::LockedMultiexpKernel::<Bls12>::new(10, false);
+        for &gpu_fraction in &[0f32, 0.3, 1.0, 2.0] {
+            let actual = heterogeneous_multiexp(
+                &pool,
+                bases.clone(),
+                exponents.clone(),
+                gpu_fraction,
+                &mut kern,
+            )
+            .unwrap();
+            assert_eq!(actual, expected, "GPU fraction {}", gpu_fraction);
+        }
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_multiexp_encoded_bases() {
+        use crate::bls::{Bls12, Engine};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pool = Worker::new();
+
+        let bases = Arc::new(
+            (0..200)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exps = Arc::new(
+            (0..150)
+                .map(|_| <Bls12 as ScalarEngine>::Fr::random(&mut rng).into_repr())
+                .collect::<Vec<_>>(),
+        );
+
+        // Put some unrelated bytes in front, as in a parameter file.
+        let mut bytes = vec![7u8; 13];
+        for base in bases.iter() {
+            bytes.extend_from_slice(base.into_uncompressed().as_ref());
+        }
+        let bytes = Arc::new(bytes);
+        let region = 13..bytes.len();
+
+        for &checked in &[true, false] {
+            let encoded = EncodedBases::<<Bls12 as Engine>::G1Affine, _>::new(
+                bytes.clone(),
+                region.clone(),
+                checked,
+            )
+            .unwrap();
+            assert_eq!(encoded.len(), bases.len());
+
+            let expected = multiexp(
+                &pool,
+                (bases.clone(), 50),
+                FullDensity,
+                exps.clone(),
+                &mut None,
+            )
+            .wait()
+            .unwrap();
+            let actual = multiexp(
+                &pool,
+                encoded.with_offset(50),
+                FullDensity,
+                exps.clone(),
+                &mut None,
+            )
+            .wait()
+            .unwrap();
+            assert_eq!(expected, actual);
+        }
+
+        // Asking for more bases than the region holds fails.
+        let encoded = EncodedBases::<<Bls12 as Engine>::G1Affine, _>::new(
+            bytes.clone(),
+            region.clone(),
+            true,
+        )
+        .unwrap()
+        .with_offset(100);
+        assert!(
+            multiexp(&pool, encoded, FullDensity, exps.clone(), &mut None)
+                .wait()
+                .is_err()
+        );
+
+        // Invalid points are reported when materializing the bases for the GPU.
+        let mut invalid = (*bytes).clone();
+        invalid[13..13 + 96].copy_from_slice(&[0xff; 96]);
+        let encoded = EncodedBases::<<Bls12 as Engine>::G1Affine, _>::new(
+            Arc::new(invalid),
+            region.clone(),
+            true,
+        )
+        .unwrap();
+        assert!(encoded.clone().get().is_err());
+        assert!(multiexp(&pool, encoded, FullDensity, exps, &mut None)
+            .wait()
+            .is_err());
+
+        // Misaligned and truncated regions are rejected up front.
+        assert!(EncodedBases::<<Bls12 as Engine>::G1Affine, _>::new(
+            bytes.clone(),
+            14..bytes.len(),
+            true
+        )
+        .is_err());
+        assert!(EncodedBases::<<Bls12 as Engine>::G1Affine, _>::new(
+            bytes.clone(),
+            13..bytes.len() + 96,
+            true
+        )
+        .is_err());
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_multiexp_precomputed() {
+        use crate::bls::{Bls12, Engine};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let pool = Worker::new();
+
+        let bases = Arc::new(
+            (0..300)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut rng).into_affine())
+                .collect::<Vec<_>>(),
+        );
+        let mut exps = (0..bases.len())
+            .map(|_| <Bls12 as ScalarEngine>::Fr::random(&mut rng).into_repr())
+            .collect::<Vec<_>>();
+        // Trivial exponents take a different route in the standard multiexp.
+        exps[0] = <Bls12 as ScalarEngine>::Fr::zero().into_repr();
+        exps[1] = <Bls12 as ScalarEngine>::Fr::one().into_repr();
+        let mut max = <Bls12 as ScalarEngine>::Fr::one();
+        max.negate();
+        exps[2] = max.into_repr();
+
+        for &window_bits in &[1, 4, 7, 8, 13] {
+            let precomputed = PrecomputedBases::new(&bases, window_bits);
+
+            // Use fewer exponents than there are bases, as the prover does.
+            for &n in &[0, 1, 37, bases.len()] {
+                let exps = Arc::new(exps[..n].to_vec());
+
+                let expected = multiexp(
+                    &pool,
+                    (bases.clone(), 0),
+                    FullDensity,
+                    exps.clone(),
+                    &mut None,
+                )
+                .wait()
+                .unwrap();
+                let actual = multiexp_precomputed(&pool, &precomputed, exps, &mut None)
+                    .wait()
+                    .unwrap();
+
+                assert_eq!(expected, actual, "window {} size {}", window_bits, n);
+            }
+        }
+
+        let precomputed = PrecomputedBases::new(&bases[..10], 8);
+        let too_many = Arc::new(exps[..11].to_vec());
+        assert!(
+            multiexp_precomputed(&pool, &precomputed, too_many, &mut None)
+                .wait()
+                .is_err()
+        );
+    }
+
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    #[test]
+    fn test_precomputed_bases_serialization() {
+        use crate::bls::{Bls12, Engine};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let bases = (0..20)
+            .map(|_| <Bls12 as Engine>::G2::random(&mut rng).into_affine())
+            .collect::<Vec<_>>();
+        let precomputed = PrecomputedBases::new(&bases, 6);
+
+        let mut v = vec![];
+        precomputed.write(&mut v).unwrap();
+
+        let de = PrecomputedBases::read(&v[..], true).unwrap();
+        assert_eq!(precomputed, de);
+        let de = PrecomputedBases::read(&v[..], false).unwrap();
+        assert_eq!(precomputed, de);
+
+        // Truncated input.
+        assert!(
+            PrecomputedBases::<<Bls12 as Engine>::G2Affine>::read(&v[..v.len() - 1], true).is_err()
+        );
+
+        // A header claiming more bases than follow fails without allocating for them.
+        let mut header = v[..4].to_vec();
+        header.extend_from_slice(&u32::MAX.to_be_bytes());
+        let err =
+            PrecomputedBases::<<Bls12 as Engine>::G2Affine>::read(&header[..], true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_gpu_split() {
+        let config = |gpu_fraction| MultiexpConfig {
+            gpu_fraction,
+            ..MultiexpConfig::default()
+        };
+
+        // The GPUs take the first exponents, the CPU the rest.
+        assert_eq!(config(0.25).gpu_split(8), (0..2, 2..8));
+        assert_eq!(config(0.7).gpu_split(10), (0..7, 7..10));
+        assert_eq!(config(1.0).gpu_split(5), (0..5, 5..5));
+        assert_eq!(config(0.0).gpu_split(5), (0..0, 0..5));
+        assert_eq!(config(0.5).gpu_split(0), (0..0, 0..0));
+    }
+
     #[test]
     fn test_extend_density_regular() {
         let mut rng = XorShiftRng::from_seed([
@@ -0,0 +1,635 @@
+//! Bucket accumulation with batched affine additions.
+//!
+//! Adding two affine points needs a field inversion, but the inversions of many
+//! independent additions can share a single one (Montgomery's trick). Each window of the
+//! multiexp therefore keeps its buckets in affine form and schedules additions into
+//! batches in which every bucket appears at most once. Additions that hit a bucket which
+//! is already part of the current batch are deferred to the next one.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+use groupy::{CurveAffine, CurveProjective};
+
+use super::{QueryDensity, Source, SourceBuilder};
+use crate::multicore::Worker;
+use crate::SynthesisError;
+
+/// Below this many exponents the batching bookkeeping costs more than it saves.
+pub(super) const MIN_EXPONENTS: usize = 1 << 10;
+
+/// Largest number of additions sharing one inversion.
+const MAX_BATCH_SIZE: usize = 256;
+
+/// The number of bases read from the source and added to the buckets of all windows
+/// at a time. Small in tests, so that they cover several chunks.
+#[cfg(not(test))]
+const CHUNK_SIZE: usize = 1 << 16;
+#[cfg(test)]
+const CHUNK_SIZE: usize = 100;
+
+/// Affine points of a short Weierstrass curve `y^2 = x^3 + b`, with access to their
+/// coordinates.
+pub(crate) trait AffineCoordinates: CurveAffine {
+    /// The coordinates of a point that is not the identity.
+    fn xy(&self) -> (Self::Base, Self::Base);
+
+    /// The point with the given coordinates, which must be on the curve.
+    fn from_xy(x: Self::Base, y: Self::Base) -> Self;
+}
+
+/// Runs the multiexp with batched affine additions if `G` supports it, returns `None`
+/// otherwise.
+///
+/// As every window visits all bases, they are read from the source and converted to
+/// coordinates once, [`CHUNK_SIZE`] at a time, and every chunk is added to the buckets
+/// of all windows in parallel on `pool`.
+pub(super) fn multiexp<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    c: u32,
+) -> Option<Result<<G as CurveAffine>::Projective, SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    S: SourceBuilder<G>,
+{
+    #[cfg(any(feature = "pairing", feature = "blst"))]
+    {
+        use crate::bls::{G1Affine, G2Affine};
+        use std::any::TypeId;
+
+        if TypeId::of::<G>() == TypeId::of::<G1Affine>() {
+            return Some(multiexp_with::<G1Affine, _, _, _, _>(
+                pool,
+                bases,
+                density_map,
+                exponents,
+                c,
+            ));
+        }
+        if TypeId::of::<G>() == TypeId::of::<G2Affine>() {
+            return Some(multiexp_with::<G2Affine, _, _, _, _>(
+                pool,
+                bases,
+                density_map,
+                exponents,
+                c,
+            ));
+        }
+    }
+
+    None
+}
+
+/// The multiexp over the curve `C`, which must be the same type as `G`.
+fn multiexp_with<C, Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    c: u32,
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+where
+    C: AffineCoordinates,
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: CurveAffine,
+    S: SourceBuilder<G>,
+{
+    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+
+    let num_buckets = (1 << c) - 1;
+    let mut windows = (0..<G::Engine as ScalarEngine>::Fr::NUM_BITS)
+        .step_by(c as usize)
+        .map(|skip| (skip, BucketAccumulator::<C>::new(num_buckets)))
+        .collect::<Vec<_>>();
+
+    let mut source = bases.new();
+    let mut terms = exponents.iter().zip(density_map.as_ref().iter()).peekable();
+    let mut points = Vec::with_capacity(std::cmp::min(CHUNK_SIZE, exponents.len()));
+    while terms.peek().is_some() {
+        points.clear();
+        for (&exp, density) in terms.by_ref() {
+            if density {
+                if exp == zero {
+                    source.skip(1)?;
+                } else {
+                    let base = source.next_base()?;
+                    let base = (&base as &dyn Any)
+                        .downcast_ref::<C>()
+                        .expect("the caller checks that the curves match");
+                    let (x, y) = base.xy();
+                    points.push((exp, x, y));
+                    if points.len() == CHUNK_SIZE {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let points = &points;
+        pool.scope(windows.len(), |scope, _| {
+            for (skip, buckets) in windows.iter_mut() {
+                let skip = *skip;
+                scope.spawn(move |_| {
+                    for (exp, x, y) in points.iter() {
+                        let mut exp = *exp;
+                        exp.shr(skip);
+                        let digit = exp.as_ref()[0] % (1 << c);
+
+                        if digit != 0 {
+                            buckets.add(digit as usize - 1, *x, *y);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    let mut parts = vec![C::Projective::zero(); windows.len()];
+    pool.scope(windows.len(), |scope, _| {
+        for ((_, buckets), part) in windows.into_iter().zip(parts.iter_mut()) {
+            scope.spawn(move |_| {
+                // Summation by parts, as in the projective multiexp.
+                let mut running_sum = C::Projective::zero();
+                for bucket in buckets.finish().into_iter().rev() {
+                    running_sum.add_assign(&bucket);
+                    part.add_assign(&running_sum);
+                }
+            });
+        }
+    });
+
+    let mut acc = C::Projective::zero();
+    for part in parts.into_iter().rev() {
+        for _ in 0..c {
+            acc.double();
+        }
+
+        acc.add_assign(&part);
+    }
+
+    let acc: Box<dyn Any> = Box::new(acc);
+    Ok(*acc
+        .downcast::<<G as CurveAffine>::Projective>()
+        .expect("the caller checks that the curves match"))
+}
+
+/// A scheduled addition of `(x, y)` into `bucket`, waiting for the inverse of its
+/// denominator.
+struct Addition<F> {
+    bucket: usize,
+    x: F,
+    numerator: F,
+}
+
+/// Affine buckets of one window.
+struct BucketAccumulator<C: AffineCoordinates> {
+    /// `None` is the identity.
+    buckets: Vec<Option<(C::Base, C::Base)>>,
+    /// Whether a bucket is part of the current batch.
+    busy: Vec<bool>,
+    batch: Vec<Addition<C::Base>>,
+    denominators: Vec<C::Base>,
+    scratch: Vec<C::Base>,
+    deferred: Vec<(usize, C::Base, C::Base)>,
+    /// Additions that conflicted while `deferred` was full, e.g. because all bases end up
+    /// in the same bucket. These are done projectively.
+    overflow: Vec<C::Projective>,
+    max_batch: usize,
+}
+
+impl<C: AffineCoordinates> BucketAccumulator<C> {
+    fn new(num_buckets: usize) -> Self {
+        let max_batch = std::cmp::min(MAX_BATCH_SIZE, num_buckets);
+
+        BucketAccumulator {
+            buckets: vec![None; num_buckets],
+            busy: vec![false; num_buckets],
+            batch: Vec::with_capacity(max_batch),
+            denominators: Vec::with_capacity(max_batch),
+            scratch: Vec::with_capacity(max_batch),
+            deferred: Vec::with_capacity(max_batch),
+            overflow: Vec::new(),
+            max_batch,
+        }
+    }
+
+    /// Adds the point `(x, y)`, which is not the identity, to `bucket`.
+    fn add(&mut self, bucket: usize, x: C::Base, y: C::Base) {
+        self.schedule(bucket, x, y);
+
+        if self.batch.len() >= self.max_batch {
+            self.apply_batch();
+            self.retry_deferred();
+        }
+    }
+
+    /// Returns the sums of all buckets.
+    fn finish(mut self) -> Vec<C::Projective> {
+        while !self.batch.is_empty() || !self.deferred.is_empty() {
+            self.apply_batch();
+            self.retry_deferred();
+        }
+
+        let mut sums = std::mem::replace(&mut self.overflow, Vec::new());
+        sums.resize(self.buckets.len(), C::Projective::zero());
+
+        for (sum, bucket) in sums.iter_mut().zip(self.buckets.into_iter()) {
+            if let Some((x, y)) = bucket {
+                sum.add_assign_mixed(&C::from_xy(x, y));
+            }
+        }
+
+        sums
+    }
+
+    fn schedule(&mut self, bucket: usize, x: C::Base, y: C::Base) {
+        if self.busy[bucket] {
+            if self.deferred.len() < self.max_batch {
+                self.deferred.push((bucket, x, y));
+            } else {
+                if self.overflow.is_empty() {
+                    self.overflow = vec![C::Projective::zero(); self.buckets.len()];
+                }
+                self.overflow[bucket].add_assign_mixed(&C::from_xy(x, y));
+            }
+            return;
+        }
+
+        let (x1, y1) = match self.buckets[bucket] {
+            Some(point) => point,
+            None => {
+                self.buckets[bucket] = Some((x, y));
+                return;
+            }
+        };
+
+        let (numerator, denominator) = if x1 != x {
+            // lambda = (y - y1) / (x - x1)
+            let mut numerator = y;
+            numerator.sub_assign(&y1);
+            let mut denominator = x;
+            denominator.sub_assign(&x1);
+
+            (numerator, denominator)
+        } else if y1 == y && !y.is_zero() {
+            // Doubling: lambda = 3 * x^2 / (2 * y)
+            let mut x_squared = x;
+            x_squared.square();
+            let mut numerator = x_squared;
+            numerator.double();
+            numerator.add_assign(&x_squared);
+            let mut denominator = y;
+            denominator.double();
+
+            (numerator, denominator)
+        } else {
+            // Adding the negation of the bucket, or doubling a point of order two.
+            self.buckets[bucket] = None;
+            return;
+        };
+
+        self.busy[bucket] = true;
+        self.batch.push(Addition {
+            bucket,
+            x,
+            numerator,
+        });
+        self.denominators.push(denominator);
+    }
+
+    fn apply_batch(&mut self) {
+        batch_invert(&mut self.denominators, &mut self.scratch);
+
+        for (addition, inverse) in self.batch.drain(..).zip(self.denominators.drain(..)) {
+            let (x1, y1) = self.buckets[addition.bucket].expect("scheduled buckets are not empty");
+
+            let mut lambda = addition.numerator;
+            lambda.mul_assign(&inverse);
+
+            // x3 = lambda^2 - x1 - x2
+            let mut x3 = lambda;
+            x3.square();
+            x3.sub_assign(&x1);
+            x3.sub_assign(&addition.x);
+
+            // y3 = lambda * (x1 - x3) - y1
+            let mut y3 = x1;
+            y3.sub_assign(&x3);
+            y3.mul_assign(&lambda);
+            y3.sub_assign(&y1);
+
+            self.buckets[addition.bucket] = Some((x3, y3));
+            self.busy[addition.bucket] = false;
+        }
+    }
+
+    fn retry_deferred(&mut self) {
+        let mut deferred = std::mem::replace(&mut self.deferred, Vec::new());
+        for (bucket, x, y) in deferred.drain(..) {
+            self.schedule(bucket, x, y);
+        }
+
+        // Keep the allocation around, unless scheduling filled up a new one.
+        if self.deferred.is_empty() {
+            self.deferred = deferred;
+        }
+    }
+}
+
+/// Replaces every element of `v` by its inverse, using a single field inversion. All
+/// elements must be non-zero.
+pub(crate) fn batch_invert<F: Field>(v: &mut [F], scratch: &mut Vec<F>) {
+    scratch.clear();
+
+    let mut acc = F::one();
+    for x in v.iter() {
+        scratch.push(acc);
+        acc.mul_assign(x);
+    }
+
+    let mut inverse = acc.inverse().expect("denominators are never zero");
+    for (x, prefix) in v.iter_mut().rev().zip(scratch.iter().rev()) {
+        let mut tmp = inverse;
+        tmp.mul_assign(prefix);
+        inverse.mul_assign(x);
+        *x = tmp;
+    }
+}
+
+/// The coordinates are taken from the uncompressed encoding, which stores them big endian
+/// and, for points other than the identity, without any flag bits set.
+#[cfg(any(feature = "pairing", feature = "blst"))]
+mod bls12 {
+    use ff::{PrimeField, PrimeFieldRepr};
+    use groupy::{CurveAffine, EncodedPoint};
+
+    use super::AffineCoordinates;
+    use crate::bls::{Fq, Fq2, FqRepr, G1Affine, G1Uncompressed, G2Affine, G2Uncompressed};
+
+    const FQ_BYTES: usize = 48;
+
+    fn read_fq(bytes: &[u8]) -> Fq {
+        let mut repr = FqRepr::default();
+        repr.read_be(bytes)
+            .expect("encoded coordinates are complete");
+        Fq::from_repr(repr).expect("encoded coordinates are reduced")
+    }
+
+    fn write_fq(fq: &Fq, bytes: &mut [u8]) {
+        fq.into_repr()
+            .write_be(bytes)
+            .expect("encoded coordinates fit");
+    }
+
+    #[cfg(feature = "pairing")]
+    fn fq2(c0: Fq, c1: Fq) -> Fq2 {
+        Fq2 { c0, c1 }
+    }
+
+    #[cfg(feature = "pairing")]
+    fn fq2_parts(fq2: &Fq2) -> (Fq, Fq) {
+        (fq2.c0, fq2.c1)
+    }
+
+    #[cfg(feature = "blst")]
+    fn fq2(c0: Fq, c1: Fq) -> Fq2 {
+        Fq2::new(c0, c1)
+    }
+
+    #[cfg(feature = "blst")]
+    fn fq2_parts(fq2: &Fq2) -> (Fq, Fq) {
+        (fq2.c0(), fq2.c1())
+    }
+
+    impl AffineCoordinates for G1Affine {
+        fn xy(&self) -> (Fq, Fq) {
+            let encoded = self.into_uncompressed();
+            let bytes = encoded.as_ref();
+
+            (
+                read_fq(&bytes[..FQ_BYTES]),
+                read_fq(&bytes[FQ_BYTES..2 * FQ_BYTES]),
+            )
+        }
+
+        fn from_xy(x: Fq, y: Fq) -> Self {
+            let mut encoded = G1Uncompressed::empty();
+            {
+                let bytes = encoded.as_mut();
+                write_fq(&x, &mut bytes[..FQ_BYTES]);
+                write_fq(&y, &mut bytes[FQ_BYTES..2 * FQ_BYTES]);
+            }
+
+            encoded
+                .into_affine_unchecked()
+                .expect("sums of points are on the curve")
+        }
+    }
+
+    /// Elements of `Fq2` are encoded as `c1 || c0`.
+    impl AffineCoordinates for G2Affine {
+        fn xy(&self) -> (Fq2, Fq2) {
+            let encoded = self.into_uncompressed();
+            let bytes = encoded.as_ref();
+            let coordinate = |i: usize| read_fq(&bytes[i * FQ_BYTES..(i + 1) * FQ_BYTES]);
+
+            (
+                fq2(coordinate(1), coordinate(0)),
+                fq2(coordinate(3), coordinate(2)),
+            )
+        }
+
+        fn from_xy(x: Fq2, y: Fq2) -> Self {
+            let mut encoded = G2Uncompressed::empty();
+            {
+                let bytes = encoded.as_mut();
+                let (x_c0, x_c1) = fq2_parts(&x);
+                let (y_c0, y_c1) = fq2_parts(&y);
+                for (i, fq) in [x_c1, x_c0, y_c1, y_c0].iter().enumerate() {
+                    write_fq(fq, &mut bytes[i * FQ_BYTES..(i + 1) * FQ_BYTES]);
+                }
+            }
+
+            encoded
+                .into_affine_unchecked()
+                .expect("sums of points are on the curve")
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Engine, Fq};
+    use crate::multiexp::FullDensity;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn naive_multiexp<G: CurveAffine>(
+        bases: &[G],
+        exponents: &[<G::Scalar as PrimeField>::Repr],
+    ) -> G::Projective {
+        let mut acc = G::Projective::zero();
+        for (base, exp) in bases.iter().zip(exponents.iter()) {
+            acc.add_assign(&base.mul(*exp));
+        }
+        acc
+    }
+
+    /// Few distinct bases, each repeated and negated, with many zero, equal and tiny
+    /// exponents, so buckets see doublings and cancellations.
+    fn adversarial_inputs<G: CurveAffine>(
+        rng: &mut XorShiftRng,
+        n: usize,
+    ) -> (Vec<G>, Vec<<G::Scalar as PrimeField>::Repr>) {
+        let distinct = (0..4)
+            .map(|_| G::Projective::random(rng).into_affine())
+            .collect::<Vec<_>>();
+        let mut minus_one = G::Scalar::one();
+        minus_one.negate();
+        let special = [
+            G::Scalar::zero(),
+            G::Scalar::one(),
+            minus_one,
+            G::Scalar::from_str("7").unwrap(),
+        ];
+        let shared = G::Scalar::random(rng);
+
+        let mut bases = Vec::with_capacity(n);
+        let mut exponents = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut base = distinct[i % distinct.len()];
+            if i % 3 == 0 {
+                base.negate();
+            }
+            bases.push(base);
+
+            let exp = match i % 5 {
+                0 | 1 => special[(i / 5) % special.len()],
+                2 => shared,
+                _ => G::Scalar::random(rng),
+            };
+            exponents.push(exp.into_repr());
+        }
+
+        (bases, exponents)
+    }
+
+    fn check_against_naive<G: CurveAffine>(
+        bases: Vec<G>,
+        exponents: Vec<<G::Scalar as PrimeField>::Repr>,
+    ) {
+        let expected = naive_multiexp(&bases, &exponents);
+
+        let bases = Arc::new(bases);
+        let exponents = Arc::new(exponents);
+        for &c in &[1, 3, 8] {
+            let actual = multiexp(
+                &Worker::new(),
+                (bases.clone(), 0),
+                FullDensity,
+                exponents.clone(),
+                c,
+            )
+            .expect("bls12 curves support batched additions")
+            .unwrap();
+            assert_eq!(expected, actual, "window size {}", c);
+        }
+    }
+
+    #[test]
+    fn test_batch_affine_g1() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let (bases, exponents) = adversarial_inputs::<<Bls12 as Engine>::G1Affine>(&mut rng, 700);
+        check_against_naive(bases, exponents);
+
+        let bases = (0..300)
+            .map(|_| <Bls12 as Engine>::G1::random(&mut rng).into_affine())
+            .collect::<Vec<_>>();
+        let exponents = (0..300)
+            .map(|_| <Bls12 as ScalarEngine>::Fr::random(&mut rng).into_repr())
+            .collect::<Vec<_>>();
+        check_against_naive(bases, exponents);
+    }
+
+    #[test]
+    fn test_batch_affine_g2() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let (bases, exponents) = adversarial_inputs::<<Bls12 as Engine>::G2Affine>(&mut rng, 300);
+        check_against_naive(bases, exponents);
+    }
+
+    #[test]
+    fn test_bucket_doubling_and_identity() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let p = <Bls12 as Engine>::G1::random(&mut rng).into_affine();
+        let mut minus_p = p;
+        minus_p.negate();
+        let (x, y) = p.xy();
+        let (minus_x, minus_y) = minus_p.xy();
+
+        // Bucket 0: p + p + p - p, bucket 1: p - p, bucket 2: everything conflicts
+        // with itself and ends up in the overflow.
+        let mut buckets = BucketAccumulator::<<Bls12 as Engine>::G1Affine>::new(3);
+        for &(bucket, x, y) in &[
+            (0, x, y),
+            (0, x, y),
+            (1, x, y),
+            (1, minus_x, minus_y),
+            (0, x, y),
+            (0, minus_x, minus_y),
+        ] {
+            buckets.add(bucket, x, y);
+        }
+        for _ in 0..1000 {
+            buckets.add(2, x, y);
+        }
+        let sums = buckets.finish();
+
+        let mut two_p = p.into_projective();
+        two_p.double();
+        assert_eq!(sums[0], two_p);
+        assert!(sums[1].is_zero());
+        assert_eq!(
+            sums[2],
+            p.mul(<Bls12 as ScalarEngine>::Fr::from_str("1000").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_batch_invert() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let values = (0..50).map(|_| Fq::random(&mut rng)).collect::<Vec<_>>();
+        let mut inverted = values.clone();
+        batch_invert(&mut inverted, &mut Vec::new());
+
+        for (value, inverse) in values.iter().zip(inverted.iter()) {
+            assert_eq!(value.inverse().unwrap(), *inverse);
+        }
+        batch_invert::<Fq>(&mut [], &mut Vec::new());
+    }
+}
@@ -0,0 +1,253 @@
+//! Proof generation behind a common interface.
+//!
+//! A [`ProofOracle`] turns public inputs into a proof of a fixed circuit, so that
+//! whoever needs proofs does not decide where they are made:
+//!
+//! - [`LocalProofOracle`] proves in process with `create_random_proof`.
+//! - [`RemoteProofOracle`], with the `remote-oracle` feature, asks a service over
+//!   HTTP, e.g. a prover running in a TEE, and checks its proofs before returning
+//!   them.
+
+use std::io;
+use std::sync::Arc;
+
+use ff::ScalarEngine;
+
+use crate::bls::Engine;
+use crate::groth16::{create_random_proof, Parameters, Proof};
+use crate::{Circuit, SynthesisError};
+
+#[cfg(feature = "remote-oracle")]
+pub use self::remote::RemoteProofOracle;
+
+#[derive(thiserror::Error, Debug)]
+pub enum OracleError {
+    /// The circuit could not be built or proved for the inputs.
+    #[error("proving failed: {0}")]
+    Synthesis(#[from] SynthesisError),
+    /// The proof could not be requested from or read back from the oracle.
+    #[error("transport error: {0}")]
+    Transport(#[from] io::Error),
+    /// The oracle answered the request with an error, or not at all.
+    #[cfg(feature = "remote-oracle")]
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The oracle returned a proof that does not verify for the inputs.
+    #[error("the oracle returned an invalid proof")]
+    InvalidProof,
+}
+
+/// A source of proofs of a circuit.
+pub trait ProofOracle<E: Engine>: Send + Sync {
+    /// A proof of the circuit for the public `inputs`.
+    fn prove(&self, inputs: &[E::Fr]) -> Result<Proof<E>, OracleError>;
+}
+
+/// Builds and proves the circuit for some public inputs.
+type Prove<E> =
+    dyn Fn(&[<E as ScalarEngine>::Fr]) -> Result<Proof<E>, SynthesisError> + Send + Sync;
+
+/// A [`ProofOracle`] proving on this machine.
+///
+/// The circuit for some inputs comes from a function, which has to find the rest of
+/// the assignment on its own, e.g. by looking it up by the inputs.
+pub struct LocalProofOracle<E: Engine> {
+    prove: Box<Prove<E>>,
+}
+
+impl<E: Engine> LocalProofOracle<E> {
+    /// An oracle proving the circuits `build` makes from the inputs with `params`.
+    pub fn new<C, F>(params: Arc<Parameters<E>>, build: F) -> Self
+    where
+        C: Circuit<E> + Send,
+        F: Fn(&[E::Fr]) -> Result<C, SynthesisError> + Send + Sync + 'static,
+    {
+        let prove = move |inputs: &[E::Fr]| {
+            let circuit = build(inputs)?;
+            create_random_proof(circuit, &*params, &mut rand::thread_rng())
+        };
+
+        LocalProofOracle {
+            prove: Box::new(prove),
+        }
+    }
+}
+
+impl<E: Engine> ProofOracle<E> for LocalProofOracle<E> {
+    fn prove(&self, inputs: &[E::Fr]) -> Result<Proof<E>, OracleError> {
+        Ok((self.prove)(inputs)?)
+    }
+}
+
+impl<E: Engine, O: ProofOracle<E> + ?Sized> ProofOracle<E> for Box<O> {
+    fn prove(&self, inputs: &[E::Fr]) -> Result<Proof<E>, OracleError> {
+        (**self).prove(inputs)
+    }
+}
+
+impl<E: Engine, O: ProofOracle<E> + ?Sized> ProofOracle<E> for Arc<O> {
+    fn prove(&self, inputs: &[E::Fr]) -> Result<Proof<E>, OracleError> {
+        (**self).prove(inputs)
+    }
+}
+
+#[cfg(feature = "remote-oracle")]
+mod remote {
+    use std::sync::Arc;
+
+    use ff::{PrimeField, PrimeFieldRepr};
+
+    use super::{OracleError, ProofOracle};
+    use crate::bls::Engine;
+    use crate::groth16::{verify_proof, PreparedVerifyingKey, Proof};
+
+    /// A [`ProofOracle`] asking a service over HTTP.
+    ///
+    /// The inputs are posted to the URL of the service as the concatenation of their
+    /// big endian encodings, and it answers with the compressed serialization of the
+    /// proof. Such proofs are not trusted: every one is verified against the verifying
+    /// key of the circuit, and [`OracleError::InvalidProof`] returned for those that
+    /// fail. Requests block, so the oracle must not be used on an async runtime's
+    /// threads.
+    pub struct RemoteProofOracle<E: Engine> {
+        url: String,
+        client: reqwest::blocking::Client,
+        pvk: Arc<PreparedVerifyingKey<E>>,
+    }
+
+    impl<E: Engine> RemoteProofOracle<E> {
+        /// An oracle asking the service at `url` for proofs verified with `pvk`.
+        pub fn new(url: impl Into<String>, pvk: Arc<PreparedVerifyingKey<E>>) -> Self {
+            Self::with_client(url, pvk, reqwest::blocking::Client::new())
+        }
+
+        /// Like [`RemoteProofOracle::new`], sending requests with `client`, e.g. one
+        /// with a timeout or client certificates.
+        pub fn with_client(
+            url: impl Into<String>,
+            pvk: Arc<PreparedVerifyingKey<E>>,
+            client: reqwest::blocking::Client,
+        ) -> Self {
+            RemoteProofOracle {
+                url: url.into(),
+                client,
+                pvk,
+            }
+        }
+    }
+
+    impl<E: Engine> ProofOracle<E> for RemoteProofOracle<E> {
+        fn prove(&self, inputs: &[E::Fr]) -> Result<Proof<E>, OracleError> {
+            let mut body = Vec::new();
+            for input in inputs {
+                input.into_repr().write_be(&mut body)?;
+            }
+
+            let response = self
+                .client
+                .post(&self.url)
+                .header("content-type", "application/octet-stream")
+                .body(body)
+                .send()?
+                .error_for_status()?;
+            let proof = Proof::read(&response.bytes()?[..])?;
+
+            if verify_proof(&self.pvk, &proof, inputs)? {
+                Ok(proof)
+            } else {
+                Err(OracleError::InvalidProof)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+    use crate::ConstraintSystem;
+    use ff::{Field, PrimeField};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Proves knowledge of a square root of the public input.
+    struct SquareRoot {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for SquareRoot {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let mut y = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                    y.square();
+                    Ok(y)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    /// Fails for every input.
+    struct Unavailable;
+
+    impl ProofOracle<Bls12> for Unavailable {
+        fn prove(&self, _: &[Fr]) -> Result<Proof<Bls12>, OracleError> {
+            Err(io::Error::new(io::ErrorKind::ConnectionRefused, "no oracle").into())
+        }
+    }
+
+    #[test]
+    fn test_local_proof_oracle() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = Arc::new(
+            generate_random_parameters::<Bls12, _, _>(SquareRoot { x: None }, &mut rng).unwrap(),
+        );
+        let pvk = prepare_verifying_key(&params.vk);
+
+        // The witness of 9 is 3, and no other input has one.
+        let oracle: Box<dyn ProofOracle<Bls12>> =
+            Box::new(LocalProofOracle::new(params, |inputs: &[Fr]| {
+                let nine = Fr::from_str("9").unwrap();
+                match inputs {
+                    [y] if *y == nine => Ok(SquareRoot {
+                        x: Some(Fr::from_str("3").unwrap()),
+                    }),
+                    _ => Err(SynthesisError::AssignmentMissing),
+                }
+            }));
+
+        let nine = Fr::from_str("9").unwrap();
+        let proof = oracle.prove(&[nine]).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[nine]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[Fr::one()]).unwrap());
+
+        assert!(matches!(
+            oracle.prove(&[Fr::one()]),
+            Err(OracleError::Synthesis(SynthesisError::AssignmentMissing))
+        ));
+    }
+
+    #[test]
+    fn test_proof_oracle_error() {
+        let oracle: Box<dyn ProofOracle<Bls12>> = Box::new(Unavailable);
+        match oracle.prove(&[Fr::one()]) {
+            Err(OracleError::Transport(e)) => {
+                assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused)
+            }
+            _ => panic!("expected a transport error"),
+        }
+    }
+}
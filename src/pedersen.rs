@@ -0,0 +1,129 @@
+//! Pedersen commitments to single scalars in G1.
+//!
+//! A commitment to `value` with the random `blinding` is `value * G + blinding * H`.
+//! It hides `value` as long as `blinding` is kept secret and binds the committer to
+//! `value` as long as nobody knows the discrete logarithm of `H` to the base `G`. Both
+//! generators are therefore derived from a hash of [`PEDERSEN_DOMAIN`], so that
+//! nobody, including the authors of this crate, chose them.
+//!
+//! The coordinates of G1 points are elements of the base field, which is larger than
+//! the scalar field circuits are defined over, so commitments in G1 cannot be opened
+//! inside a circuit without emulating the base field; openings are checked natively
+//! with [`PedersenGenerators::verify_opening`]. Commitments to be opened inside a
+//! circuit are made on an embedded curve instead, see
+//! [`gadgets::pedersen`](crate::gadgets::pedersen).
+
+use ff::{Field, PrimeField};
+use groupy::{CurveAffine, CurveProjective};
+use sha2::Sha256;
+
+use crate::bls::Engine;
+use crate::groth16::{mul_ct, ConditionalSwap};
+use crate::transcript::FiatShamirRng;
+
+/// Domain separator the generators are derived from.
+pub const PEDERSEN_DOMAIN: &[u8] = b"bellperson-pedersen-generators";
+
+/// The generators `G` and `H` of the commitments, derived once by their owner and
+/// passed to every commitment and opening.
+pub struct PedersenGenerators<E: Engine> {
+    pub g: E::G1Affine,
+    pub h: E::G1Affine,
+}
+
+impl<E: Engine> Clone for PedersenGenerators<E> {
+    fn clone(&self) -> Self {
+        PedersenGenerators {
+            g: self.g,
+            h: self.h,
+        }
+    }
+}
+
+impl<E: Engine> PedersenGenerators<E> {
+    /// Hashes [`PEDERSEN_DOMAIN`] to two points of unknown discrete logarithms.
+    pub fn new() -> Self {
+        let mut rng = FiatShamirRng::<Sha256>::new(PEDERSEN_DOMAIN);
+        let g = E::G1::random(&mut rng).into_affine();
+        let h = E::G1::random(&mut rng).into_affine();
+
+        PedersenGenerators { g, h }
+    }
+
+    /// Commits to `value`, hidden by `blinding`, which must be chosen uniformly at
+    /// random.
+    pub fn commit(&self, value: E::Fr, blinding: E::Fr) -> E::G1Affine
+    where
+        E::G1: ConditionalSwap,
+    {
+        // Both scalars are secret, so they are multiplied in constant time.
+        let mut commitment = mul_ct(self.g.into_projective(), value);
+        commitment.add_assign(&mul_ct(self.h.into_projective(), blinding));
+
+        commitment.into_affine()
+    }
+
+    /// Checks that `commitment` opens to `value` with `blinding`.
+    pub fn verify_opening(&self, commitment: &E::G1Affine, value: E::Fr, blinding: E::Fr) -> bool {
+        let mut expected = self.g.mul(value.into_repr());
+        expected.add_assign(&self.h.mul(blinding.into_repr()));
+
+        expected.into_affine() == *commitment
+    }
+}
+
+impl<E: Engine> Default for PedersenGenerators<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr, G1Affine};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_generators() {
+        let generators = PedersenGenerators::<Bls12>::new();
+        let (g, h) = (generators.g, generators.h);
+        let again = PedersenGenerators::<Bls12>::default();
+        assert_eq!((g, h), (again.g, again.h));
+
+        assert!(!g.is_zero() && !h.is_zero());
+        assert_ne!(g, h);
+        assert_ne!(g, G1Affine::one());
+        assert_ne!(h, G1Affine::one());
+    }
+
+    #[test]
+    fn test_commitment() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let generators = PedersenGenerators::<Bls12>::new();
+
+        for _ in 0..10 {
+            let (v1, b1) = (Fr::random(rng), Fr::random(rng));
+            let (v2, b2) = (Fr::random(rng), Fr::random(rng));
+            let c1 = generators.commit(v1, b1);
+            let c2 = generators.commit(v2, b2);
+
+            assert!(generators.verify_opening(&c1, v1, b1));
+            assert!(!generators.verify_opening(&c1, v2, b1));
+            assert!(!generators.verify_opening(&c1, v1, b2));
+
+            // The commitments are additively homomorphic.
+            let mut sum = c1.into_projective();
+            sum.add_assign_mixed(&c2);
+            let (mut v, mut b) = (v1, b1);
+            v.add_assign(&v2);
+            b.add_assign(&b2);
+            assert_eq!(sum.into_affine(), generators.commit(v, b));
+        }
+    }
+}
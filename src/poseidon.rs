@@ -0,0 +1,426 @@
+//! The [Poseidon] hash over the scalar field, with the S-box `x^5`.
+//!
+//! The permutation acts on a state of `arity + 1` elements: the first, the capacity,
+//! holds a domain tag, and the others absorb the preimage, `arity` elements at a time.
+//! The round constants are derived from a hash of [`POSEIDON_DOMAIN`] and the
+//! parameters, and the MDS matrix is the Cauchy matrix `1 / (i + (t + j))` for the
+//! width `t`, so that nobody chose them. The numbers of rounds are those recommended
+//! for 128 bits of security with 255 bit fields.
+//!
+//! [`PoseidonConstants::standard`] are instead the constants of the [reference
+//! implementation], which other implementations of Poseidon over BLS12-381 share.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458
+//! [reference implementation]: https://extgit.iaik.tugraz.at/krypto/hadeshash
+
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use sha2::Sha256;
+
+use crate::bls::Engine;
+use crate::transcript::FiatShamirRng;
+
+/// Domain separator the round constants and domain tags are derived from.
+pub const POSEIDON_DOMAIN: &[u8] = b"bellperson-poseidon";
+
+/// The number of full rounds, half of them before and half after the partial rounds.
+const FULL_ROUNDS: usize = 8;
+
+/// The parameters of a Poseidon permutation.
+#[derive(Clone, Debug)]
+pub struct PoseidonConstants<E: Engine> {
+    pub arity: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    /// The `(arity + 1) x (arity + 1)` MDS matrix, by rows.
+    pub mds: Vec<Vec<E::Fr>>,
+    /// The `arity + 1` constants added to the state in each round, round by round.
+    pub round_constants: Vec<E::Fr>,
+}
+
+impl<E: Engine> PoseidonConstants<E> {
+    /// The constants for hashing `arity` elements at a time. `arity` must be between 1
+    /// and 16.
+    pub fn new(arity: usize) -> Self {
+        assert!(
+            (1..=16).contains(&arity),
+            "the arity must be between 1 and 16"
+        );
+        let partial_rounds = match arity + 1 {
+            2..=3 => 55,
+            4..=7 => 56,
+            8..=15 => 57,
+            _ => 59,
+        };
+
+        Self::with_rounds(arity, FULL_ROUNDS, partial_rounds)
+    }
+
+    /// The constants of the reference implementation for the width `arity + 1`, with
+    /// 8 full rounds and the partial rounds of its parameters `x5_255_3` and
+    /// `x5_255_5`, derived from its Grain LFSR. `arity` must be 2 or 4.
+    pub fn standard(arity: usize) -> Self {
+        let partial_rounds = match arity {
+            2 => 57,
+            4 => 60,
+            _ => panic!("the standard arities are 2 and 4"),
+        };
+        let width = arity + 1;
+
+        let mut grain = Grain::new(E::Fr::NUM_BITS, width, FULL_ROUNDS, partial_rounds);
+        let round_constants = (0..width * (FULL_ROUNDS + partial_rounds))
+            .map(|_| loop {
+                if let Ok(c) = E::Fr::from_repr(grain.next_repr::<E::Fr>()) {
+                    break c;
+                }
+            })
+            .collect();
+
+        // The Cauchy matrix `1 / (x_i + y_j)` of `2 * width` distinct elements, reduced
+        // rather than rejected. The reference checks the matrix against invariant
+        // subspaces as well, which those of both widths pass.
+        let mds = loop {
+            let elements = (0..2 * width)
+                .map(|_| grain.next_reduced::<E::Fr>())
+                .collect::<Vec<_>>();
+            let distinct = elements
+                .iter()
+                .enumerate()
+                .all(|(i, x)| !elements[..i].contains(x));
+            let (xs, ys) = elements.split_at(width);
+            let inverses = xs
+                .iter()
+                .map(|x| {
+                    ys.iter()
+                        .map(|y| {
+                            let mut sum = *x;
+                            sum.add_assign(y);
+                            sum.inverse()
+                        })
+                        .collect::<Option<Vec<_>>>()
+                })
+                .collect::<Option<Vec<_>>>();
+            if let (true, Some(mds)) = (distinct, inverses) {
+                break mds;
+            }
+        };
+
+        PoseidonConstants {
+            arity,
+            full_rounds: FULL_ROUNDS,
+            partial_rounds,
+            mds,
+            round_constants,
+        }
+    }
+
+    /// The constants with custom numbers of rounds. `full_rounds` must be even.
+    pub fn with_rounds(arity: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(arity > 0, "the arity must be positive");
+        assert!(
+            full_rounds % 2 == 0,
+            "the number of full rounds must be even"
+        );
+        let width = arity + 1;
+
+        let mut rng = FiatShamirRng::<Sha256>::new(POSEIDON_DOMAIN);
+        rng.absorb_bytes(b"round constants");
+        rng.absorb_bytes(&(width as u64).to_le_bytes());
+        rng.absorb_bytes(&(full_rounds as u64).to_le_bytes());
+        rng.absorb_bytes(&(partial_rounds as u64).to_le_bytes());
+        let round_constants = (0..width * (full_rounds + partial_rounds))
+            .map(|_| E::Fr::random(&mut rng))
+            .collect();
+
+        let mds = (0..width)
+            .map(|i| {
+                (0..width)
+                    .map(|j| {
+                        let x = E::Fr::from_str(&i.to_string()).unwrap();
+                        let mut sum = E::Fr::from_str(&(width + j).to_string()).unwrap();
+                        sum.add_assign(&x);
+                        sum.inverse().expect("i + t + j is not zero")
+                    })
+                    .collect()
+            })
+            .collect();
+
+        PoseidonConstants {
+            arity,
+            full_rounds,
+            partial_rounds,
+            mds,
+            round_constants,
+        }
+    }
+
+    /// The size of the state, `arity + 1`.
+    pub fn width(&self) -> usize {
+        self.arity + 1
+    }
+
+    /// Applies the permutation to `state`, which has [`width`](Self::width) elements.
+    pub fn permute(&self, state: &mut [E::Fr]) {
+        assert_eq!(state.len(), self.width(), "the state has the wrong width");
+
+        let half = self.full_rounds / 2;
+        let rounds = self.full_rounds + self.partial_rounds;
+        for (round, constants) in self.round_constants.chunks(self.width()).enumerate() {
+            for (s, c) in state.iter_mut().zip(constants) {
+                s.add_assign(c);
+            }
+            if round < half || round >= rounds - half {
+                state.iter_mut().for_each(quintic_s_box);
+            } else {
+                quintic_s_box(&mut state[0]);
+            }
+            self.apply_mds(state);
+        }
+    }
+
+    /// Hashes `preimage` with `domain_tag` in the capacity, absorbing [`arity`] elements
+    /// per permutation and padding the last chunk with zeros. As the padding is not
+    /// injective, `domain_tag` should bind the length of `preimage`, like those of
+    /// [`domain_tag`].
+    ///
+    /// [`arity`]: PoseidonConstants::arity
+    pub fn hash(&self, domain_tag: E::Fr, preimage: &[E::Fr]) -> E::Fr {
+        let mut state = vec![E::Fr::zero(); self.width()];
+        state[0] = domain_tag;
+
+        if preimage.is_empty() {
+            self.permute(&mut state);
+        }
+        for chunk in preimage.chunks(self.arity) {
+            for (s, x) in state[1..].iter_mut().zip(chunk) {
+                s.add_assign(x);
+            }
+            self.permute(&mut state);
+        }
+
+        state[1]
+    }
+
+    fn apply_mds(&self, state: &mut [E::Fr]) {
+        let result = self
+            .mds
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(state.iter())
+                    .fold(E::Fr::zero(), |mut acc, (m, s)| {
+                        let mut term = *m;
+                        term.mul_assign(s);
+                        acc.add_assign(&term);
+                        acc
+                    })
+            })
+            .collect::<Vec<_>>();
+        state.copy_from_slice(&result);
+    }
+}
+
+/// The self-shrinking Grain LFSR of the reference implementation, seeded with the
+/// parameters of the permutation.
+struct Grain {
+    bits: [bool; 80],
+    pos: usize,
+    field_bits: u32,
+}
+
+impl Grain {
+    fn new(field_bits: u32, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        // A prime field, the S-box `x^alpha`, the sizes and ones.
+        let params = [
+            (1, 2),
+            (0, 4),
+            (field_bits as u64, 12),
+            (width as u64, 12),
+            (full_rounds as u64, 10),
+            (partial_rounds as u64, 10),
+        ];
+        let mut bits = [true; 80];
+        let mut i = 0;
+        for &(value, len) in &params {
+            for j in (0..len).rev() {
+                bits[i] = (value >> j) & 1 == 1;
+                i += 1;
+            }
+        }
+
+        let mut grain = Grain {
+            bits,
+            pos: 0,
+            field_bits,
+        };
+        for _ in 0..160 {
+            grain.step();
+        }
+
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let bit = |i: usize| self.bits[(self.pos + i) % 80];
+        let new = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+        self.bits[self.pos] = new;
+        self.pos = (self.pos + 1) % 80;
+
+        new
+    }
+
+    /// Of each pair of bits, the second if the first is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let (first, second) = (self.step(), self.step());
+            if first {
+                return second;
+            }
+        }
+    }
+
+    /// The next `field_bits` bits, the most significant first.
+    fn next_repr<F: PrimeField>(&mut self) -> F::Repr {
+        let mut repr = F::Repr::default();
+        for _ in 0..self.field_bits {
+            repr.shl(1);
+            if self.next_bit() {
+                repr.as_mut()[0] |= 1;
+            }
+        }
+
+        repr
+    }
+
+    /// The next `field_bits` bits, reduced modulo the characteristic.
+    fn next_reduced<F: PrimeField>(&mut self) -> F {
+        let mut repr = self.next_repr::<F>();
+        while repr >= F::char() {
+            repr.sub_noborrow(&F::char());
+        }
+
+        F::from_repr(repr).expect("the repr is reduced")
+    }
+}
+
+/// The tag of the hashes of `len` elements by the application named `domain`.
+pub fn domain_tag<E: Engine>(domain: &[u8], len: usize) -> E::Fr {
+    let mut rng = FiatShamirRng::<Sha256>::new(POSEIDON_DOMAIN);
+    rng.absorb_bytes(b"domain tag");
+    rng.absorb_bytes(domain);
+    rng.absorb_bytes(&(len as u64).to_le_bytes());
+
+    E::Fr::random(&mut rng)
+}
+
+/// `x^5`
+pub(crate) fn quintic_s_box<F: Field>(x: &mut F) {
+    let mut x4 = *x;
+    x4.square();
+    x4.square();
+    x.mul_assign(&x4);
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_poseidon_constants() {
+        for &arity in &[1, 2, 4, 8, 11, 16] {
+            let constants = PoseidonConstants::<Bls12>::new(arity);
+            let width = constants.width();
+            assert_eq!(constants.mds.len(), width);
+            assert_eq!(
+                constants.round_constants.len(),
+                width * (constants.full_rounds + constants.partial_rounds)
+            );
+
+            // Deterministic, and different between arities.
+            let again = PoseidonConstants::<Bls12>::new(arity);
+            assert_eq!(constants.round_constants, again.round_constants);
+            assert_eq!(constants.mds, again.mds);
+        }
+        assert_ne!(
+            PoseidonConstants::<Bls12>::new(2).round_constants[..3],
+            PoseidonConstants::<Bls12>::new(4).round_constants[..3]
+        );
+    }
+
+    #[test]
+    fn test_poseidon_standard() {
+        // The test vectors of the reference implementation, the permutations of
+        // `0, 1, ..., t - 1`.
+        let vectors: [(usize, &[&str]); 2] = [
+            (
+                2,
+                &[
+                    "18456658763349757341014058622209659766100673761449600566550821987295786346378",
+                    "37068251774887509885063625701815026138353041152735229476479055620962268601796",
+                    "26763157702141528937904191329664859174584798817251788852101947537759678822298",
+                ],
+            ),
+            (
+                4,
+                &[
+                    "19254296030192702347705476604674913000958357963414387321395030558732596555288",
+                    "46100521179547351030890356806707858465640857221128207179269773056079077808119",
+                    "20243588025220817172052439566138805764005249007732516020412708149385313486286",
+                    "35175955397230834946990620339380655400826139806258387820473756013382386627942",
+                    "1808162148717216882509606295568402045499399102423428336298026884332797092785",
+                ],
+            ),
+        ];
+
+        for (arity, expected) in vectors.iter() {
+            let constants = PoseidonConstants::<Bls12>::standard(*arity);
+            let mut state = (0..constants.width())
+                .map(|i| Fr::from_str(&i.to_string()).unwrap())
+                .collect::<Vec<_>>();
+            constants.permute(&mut state);
+
+            let expected = expected
+                .iter()
+                .map(|x| Fr::from_str(x).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[test]
+    fn test_poseidon_hash() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let constants = PoseidonConstants::<Bls12>::new(2);
+
+        let preimage = (0..5).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let tag = domain_tag::<Bls12>(b"test", preimage.len());
+        let hash = constants.hash(tag, &preimage);
+        assert_eq!(hash, constants.hash(tag, &preimage));
+
+        // Another tag, another element or trailing zeros change the hash.
+        assert_ne!(
+            hash,
+            constants.hash(domain_tag::<Bls12>(b"other", preimage.len()), &preimage)
+        );
+        let mut changed = preimage.clone();
+        changed[4].add_assign(&Fr::one());
+        assert_ne!(hash, constants.hash(tag, &changed));
+        let mut padded = preimage.clone();
+        padded.push(Fr::zero());
+        assert_ne!(
+            hash,
+            constants.hash(domain_tag::<Bls12>(b"test", padded.len()), &padded)
+        );
+
+        // The permutation is not the identity on the zero state.
+        let mut state = vec![Fr::zero(); constants.width()];
+        constants.permute(&mut state);
+        assert!(state.iter().all(|s| !s.is_zero()));
+    }
+}
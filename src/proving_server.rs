@@ -0,0 +1,265 @@
+//! Proving as a service: jobs name a circuit by its [`CircuitId`] and carry a
+//! serialized witness, and their proofs are fetched later by [`JobId`].
+//!
+//! A [`JobQueue`] implements [`ProvingServer`] in process, running every job on a
+//! thread of its own which proves on a [`Worker`]. With the `rpc` feature, [`rpc`]
+//! exposes any server over JSON-RPC 2.0, e.g. to run provers as separate services.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use ff::Field;
+
+use crate::bls::Engine;
+use crate::groth16::{create_proof_batch_priority_with_worker, ConditionalSwap, Parameters, Proof};
+use crate::multicore::{Worker, DEFAULT_QUEUE_JOBS_PER_THREAD};
+use crate::registry::CircuitId;
+use crate::{Circuit, SynthesisError};
+
+#[cfg(feature = "rpc")]
+pub mod rpc;
+
+/// Identifies a job submitted to a [`ProvingServer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JobId(pub u64);
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The state of a job.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The job is being proven.
+    Pending,
+    /// The proof is ready.
+    Done,
+    /// No proof could be created, e.g. because the circuit is unknown or the witness
+    /// does not satisfy it.
+    Failed(String),
+}
+
+/// Why a [`ProvingServer`] did not take a job.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitError {
+    /// As many jobs as the server takes wait or run, the job may be submitted again
+    /// once some of them are done.
+    #[error("the queue of jobs is full")]
+    QueueFull,
+}
+
+/// A service creating proofs for the circuits it knows.
+pub trait ProvingServer<E: Engine>: Send + Sync {
+    /// Queues a proof of the circuit `circuit_id` with `witness`, whose encoding is
+    /// defined by the circuit. Does not wait for room in the queue.
+    fn submit_job(&self, circuit_id: CircuitId, witness: Vec<u8>) -> Result<JobId, SubmitError>;
+
+    /// The proof of the job `id`, once it is done.
+    fn get_proof(&self, id: JobId) -> Option<Proof<E>>;
+
+    /// The state of the job `id`, `None` for jobs this server does not know.
+    fn status(&self, id: JobId) -> Option<JobStatus>;
+}
+
+/// Proves a job of a registered circuit from its witness on the worker.
+type Prove<E> = dyn Fn(&[u8], &Worker) -> Result<Proof<E>, SynthesisError> + Send + Sync;
+
+/// How long a [`JobQueue`] keeps finished jobs by default.
+pub const DEFAULT_JOB_TTL: Duration = Duration::from_secs(600);
+
+enum Job<E: Engine> {
+    Pending,
+    Done(Proof<E>),
+    Failed(String),
+}
+
+/// The jobs of a [`JobQueue`], with the finished ones in the order they finished.
+struct Jobs<E: Engine> {
+    jobs: HashMap<JobId, Job<E>>,
+    finished: VecDeque<(Instant, JobId)>,
+}
+
+impl<E: Engine> Jobs<E> {
+    /// Records the result of the job `id`. Removed jobs stay removed.
+    fn finish(&mut self, id: JobId, job: Job<E>) {
+        if let Some(slot) = self.jobs.get_mut(&id) {
+            *slot = job;
+            self.finished.push_back((Instant::now(), id));
+        }
+    }
+
+    /// Forgets the jobs which finished more than `ttl` ago.
+    fn evict(&mut self, ttl: Duration) {
+        while let Some(&(finished, id)) = self.finished.front() {
+            if finished.elapsed() < ttl {
+                break;
+            }
+            self.finished.pop_front();
+            self.jobs.remove(&id);
+        }
+    }
+}
+
+/// A [`ProvingServer`] proving jobs on a [`Worker`].
+///
+/// Circuits are registered with their parameters and a function decoding a witness
+/// into a circuit with its assignment. Every job runs on a thread of its own, which
+/// waits for the computations of the proof on the worker, so jobs never hold the
+/// threads of the worker. At most the limit of jobs run at once; further submissions
+/// fail with [`SubmitError::QueueFull`] until one of them is done. Finished jobs are kept until
+/// they are removed with [`JobQueue::remove`], or for [`DEFAULT_JOB_TTL`], see
+/// [`JobQueue::set_ttl`].
+pub struct JobQueue<E: Engine> {
+    circuits: HashMap<CircuitId, Arc<Prove<E>>>,
+    worker: Worker,
+    limit: usize,
+    running: Arc<AtomicUsize>,
+    jobs: Arc<Mutex<Jobs<E>>>,
+    ttl: Duration,
+    next_id: AtomicU64,
+}
+
+impl<E: Engine> JobQueue<E> {
+    /// A queue proving on the global worker.
+    pub fn new() -> Self {
+        Self::with_worker(&Worker::new())
+    }
+
+    /// A queue proving on `worker`, running [`DEFAULT_QUEUE_JOBS_PER_THREAD`] jobs per
+    /// thread of the worker at once.
+    pub fn with_worker(worker: &Worker) -> Self {
+        Self::with_limit(worker, DEFAULT_QUEUE_JOBS_PER_THREAD * worker.num_threads())
+    }
+
+    /// A queue proving on `worker`, running at most `limit` jobs at once.
+    pub fn with_limit(worker: &Worker, limit: usize) -> Self {
+        assert!(limit > 0, "a queue must let at least one job run");
+        JobQueue {
+            circuits: HashMap::new(),
+            worker: worker.clone(),
+            limit,
+            running: Arc::new(AtomicUsize::new(0)),
+            jobs: Arc::new(Mutex::new(Jobs {
+                jobs: HashMap::new(),
+                finished: VecDeque::new(),
+            })),
+            ttl: DEFAULT_JOB_TTL,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Keeps finished jobs for `ttl`, after which they are unknown.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    }
+
+    /// Registers the circuit `id`, proved with `params` for the circuits `build`
+    /// decodes from the witnesses of jobs. Replaces a circuit registered before.
+    pub fn register<C, F>(&mut self, id: CircuitId, params: Arc<Parameters<E>>, build: F)
+    where
+        C: Circuit<E> + Send,
+        F: Fn(&[u8]) -> Result<C, SynthesisError> + Send + Sync + 'static,
+        E::G1: ConditionalSwap,
+        E::G2: ConditionalSwap,
+    {
+        let prove = move |witness: &[u8], worker: &Worker| {
+            let circuit = build(witness)?;
+            let rng = &mut rand::thread_rng();
+            let r = E::Fr::random(rng);
+            let s = E::Fr::random(rng);
+            let mut proofs = create_proof_batch_priority_with_worker(
+                vec![circuit],
+                &*params,
+                vec![r],
+                vec![s],
+                false,
+                worker,
+            )?;
+            Ok(proofs.pop().expect("one proof per circuit"))
+        };
+        self.circuits.insert(id, Arc::new(prove));
+    }
+
+    /// Forgets the job `id`, returning its proof if it was done. A pending job still
+    /// runs, but its result is discarded.
+    pub fn remove(&self, id: JobId) -> Option<Proof<E>> {
+        match self.jobs().jobs.remove(&id) {
+            Some(Job::Done(proof)) => Some(proof),
+            _ => None,
+        }
+    }
+
+    /// The jobs, without the expired ones.
+    fn jobs(&self) -> MutexGuard<'_, Jobs<E>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.evict(self.ttl);
+        jobs
+    }
+}
+
+impl<E: Engine> Default for JobQueue<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> ProvingServer<E> for JobQueue<E> {
+    fn submit_job(&self, circuit_id: CircuitId, witness: Vec<u8>) -> Result<JobId, SubmitError> {
+        let id = JobId(self.next_id.fetch_add(1, Ordering::SeqCst));
+        let mut jobs = self.jobs();
+        jobs.jobs.insert(id, Job::Pending);
+        let prove = match self.circuits.get(&circuit_id) {
+            Some(prove) => prove.clone(),
+            None => {
+                jobs.finish(id, Job::Failed("unknown circuit".into()));
+                return Ok(id);
+            }
+        };
+        drop(jobs);
+
+        if self.running.fetch_add(1, Ordering::SeqCst) >= self.limit {
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.jobs.lock().unwrap().jobs.remove(&id);
+            return Err(SubmitError::QueueFull);
+        }
+
+        let jobs = self.jobs.clone();
+        let running = self.running.clone();
+        let worker = self.worker.clone();
+        std::thread::Builder::new()
+            .name(format!("proving job {}", id))
+            .spawn(move || {
+                let job = match catch_unwind(AssertUnwindSafe(|| prove(&witness, &worker))) {
+                    Ok(Ok(proof)) => Job::Done(proof),
+                    Ok(Err(e)) => Job::Failed(e.to_string()),
+                    Err(_) => Job::Failed("the prover panicked".into()),
+                };
+                jobs.lock().unwrap().finish(id, job);
+                running.fetch_sub(1, Ordering::SeqCst);
+            })
+            .expect("failed to spawn the thread of a proving job");
+
+        Ok(id)
+    }
+
+    fn get_proof(&self, id: JobId) -> Option<Proof<E>> {
+        match self.jobs().jobs.get(&id) {
+            Some(Job::Done(proof)) => Some(proof.clone()),
+            _ => None,
+        }
+    }
+
+    fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.jobs().jobs.get(&id).map(|job| match job {
+            Job::Pending => JobStatus::Pending,
+            Job::Done(_) => JobStatus::Done,
+            Job::Failed(e) => JobStatus::Failed(e.clone()),
+        })
+    }
+}
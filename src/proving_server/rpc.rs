@@ -0,0 +1,123 @@
+//! A JSON-RPC 2.0 binding of a [`ProvingServer`].
+//!
+//! Byte strings are hex encoded, and proofs in their compressed serialization:
+//!
+//! - `submit_job(circuit_id, witness)` queues a job and returns its id, a number, or
+//!   fails with the code [`QUEUE_FULL`] while the queue is full.
+//! - `get_proof(job_id)` returns the proof of the job, or `null` until it is done.
+//! - `job_status(job_id)` returns `{"status": "pending" | "done" | "failed"}`, with the
+//!   reason of a failure under `"error"`, or `null` for unknown jobs.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use jsonrpc_core::serde_json::{json, Value};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Params};
+use jsonrpc_http_server::{Server, ServerBuilder};
+
+use super::{JobId, JobStatus, ProvingServer};
+use crate::bls::Engine;
+use crate::registry::CircuitId;
+
+/// The error code of a job submitted while the queue of the server is full.
+pub const QUEUE_FULL: i64 = -32000;
+
+/// The methods of `server` as a JSON-RPC handler, for any transport.
+pub fn rpc_handler<E, S>(server: Arc<S>) -> IoHandler
+where
+    E: Engine,
+    S: ProvingServer<E> + 'static,
+{
+    let mut io = IoHandler::new();
+
+    let submitter = server.clone();
+    io.add_sync_method("submit_job", move |params: Params| {
+        let (circuit_id, witness) = params.parse::<(String, String)>()?;
+        let circuit_id = decode_hex(&circuit_id)
+            .filter(|id| id.len() == 32)
+            .ok_or_else(|| Error::invalid_params("the circuit id is not 32 hex encoded bytes"))?;
+        let witness = decode_hex(&witness)
+            .ok_or_else(|| Error::invalid_params("the witness is not hex encoded"))?;
+
+        let mut id = CircuitId::default();
+        id.copy_from_slice(&circuit_id);
+        match submitter.submit_job(id, witness) {
+            Ok(job) => Ok(Value::from(job.0)),
+            Err(e) => Err(Error {
+                code: ErrorCode::ServerError(QUEUE_FULL),
+                message: e.to_string(),
+                data: None,
+            }),
+        }
+    });
+
+    let getter = server.clone();
+    io.add_sync_method("get_proof", move |params: Params| {
+        let (id,) = params.parse::<(u64,)>()?;
+        Ok(match getter.get_proof(JobId(id)) {
+            Some(proof) => {
+                let mut bytes = Vec::new();
+                proof
+                    .write(&mut bytes)
+                    .expect("writing to a vec never fails");
+                Value::from(encode_hex(&bytes))
+            }
+            None => Value::Null,
+        })
+    });
+
+    io.add_sync_method("job_status", move |params: Params| {
+        let (id,) = params.parse::<(u64,)>()?;
+        Ok(match server.status(JobId(id)) {
+            Some(JobStatus::Pending) => json!({ "status": "pending" }),
+            Some(JobStatus::Done) => json!({ "status": "done" }),
+            Some(JobStatus::Failed(e)) => json!({ "status": "failed", "error": e }),
+            None => Value::Null,
+        })
+    });
+
+    io
+}
+
+/// Serves the methods of `server` over HTTP on `addr`. A port of 0 picks a free port,
+/// which [`Server::address`] returns.
+pub fn start_http<E, S>(server: Arc<S>, addr: &SocketAddr) -> io::Result<Server>
+where
+    E: Engine,
+    S: ProvingServer<E> + 'static,
+{
+    ServerBuilder::new(rpc_handler(server)).start_http(addr)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(encode_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(decode_hex("00ab7f"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(decode_hex("0x00AB7F"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("+f"), None);
+    }
+}
@@ -0,0 +1,273 @@
+//! Constraint matrices in compressed sparse row (CSR) format.
+//!
+//! The linear combinations of a constraint system are hash maps, which are cheap to
+//! build but scatter the terms of a circuit over the heap. Copying them into a
+//! [`R1CSMatrixCSR`], one row per constraint, stores all terms in two flat arrays, so
+//! that evaluating the rows against a witness reads memory in order.
+//!
+//! The prover evaluates all constraints from these matrices. This replaces its former
+//! sparse path, which copied linear combinations of up to three terms into inline
+//! vectors and evaluated them separately from the longer ones: every row is stored the
+//! same way here, however short. The `r1cs` benchmark compares evaluating the matrices,
+//! including the copy into them, against evaluating the linear combinations directly.
+
+use ff::{Field, ScalarEngine};
+use rayon::prelude::*;
+
+use crate::multiexp::DensityTracker;
+use crate::{Index, LinearCombination, Variable};
+
+/// One of the `A`, `B` or `C` matrices of a rank-1 constraint system, with a row per
+/// constraint and a column per variable.
+pub struct R1CSMatrixCSR<E: ScalarEngine> {
+    /// The terms of row `i` are at `row_ptr[i]..row_ptr[i + 1]`.
+    row_ptr: Vec<usize>,
+    columns: Vec<Variable>,
+    values: Vec<E::Fr>,
+}
+
+impl<E: ScalarEngine> Default for R1CSMatrixCSR<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: ScalarEngine> Clone for R1CSMatrixCSR<E> {
+    fn clone(&self) -> Self {
+        R1CSMatrixCSR {
+            row_ptr: self.row_ptr.clone(),
+            columns: self.columns.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<E: ScalarEngine> PartialEq for R1CSMatrixCSR<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.row_ptr == other.row_ptr
+            && self.columns == other.columns
+            && self.values == other.values
+    }
+}
+
+impl<E: ScalarEngine> std::fmt::Debug for R1CSMatrixCSR<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("R1CSMatrixCSR")
+            .field("rows", &self.num_rows())
+            .field("nnz", &self.nnz())
+            .finish()
+    }
+}
+
+impl<E: ScalarEngine> R1CSMatrixCSR<E> {
+    /// A matrix without rows.
+    pub fn new() -> Self {
+        R1CSMatrixCSR {
+            row_ptr: vec![0],
+            columns: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// A matrix without rows, with room for `rows` rows of `nnz` terms in total.
+    pub fn with_capacity(rows: usize, nnz: usize) -> Self {
+        let mut row_ptr = Vec::with_capacity(rows + 1);
+        row_ptr.push(0);
+
+        R1CSMatrixCSR {
+            row_ptr,
+            columns: Vec::with_capacity(nnz),
+            values: Vec::with_capacity(nnz),
+        }
+    }
+
+    /// The matrix with a row per linear combination of `lcs`.
+    pub fn from_lcs<'a, I>(lcs: I) -> Self
+    where
+        I: IntoIterator<Item = &'a LinearCombination<E>>,
+    {
+        let mut matrix = Self::new();
+        for lc in lcs {
+            matrix.push_row(lc);
+        }
+        matrix
+    }
+
+    /// Appends the terms of `lc` as a row.
+    pub fn push_row(&mut self, lc: &LinearCombination<E>) {
+        for (&var, &coeff) in lc.iter() {
+            self.columns.push(var);
+            self.values.push(coeff);
+        }
+        self.row_ptr.push(self.columns.len());
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.row_ptr.len() - 1
+    }
+
+    /// The number of stored terms.
+    pub fn nnz(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_rows() == 0
+    }
+
+    /// The variables and coefficients of the terms of row `i`.
+    pub fn row(&self, i: usize) -> (&[Variable], &[E::Fr]) {
+        let range = self.row_ptr[i]..self.row_ptr[i + 1];
+        (&self.columns[range.clone()], &self.values[range])
+    }
+
+    /// Removes all rows.
+    pub fn clear(&mut self) {
+        self.row_ptr.truncate(1);
+        self.columns.clear();
+        self.values.clear();
+    }
+
+    /// The value of every row for the assignment of the variables, in parallel.
+    pub fn evaluate(&self, input_assignment: &[E::Fr], aux_assignment: &[E::Fr]) -> Vec<E::Fr> {
+        (0..self.num_rows())
+            .into_par_iter()
+            .map(|i| self.evaluate_row(i, input_assignment, aux_assignment))
+            .collect()
+    }
+
+    /// The value of row `i` for the assignment of the variables.
+    pub fn evaluate_row(
+        &self,
+        i: usize,
+        input_assignment: &[E::Fr],
+        aux_assignment: &[E::Fr],
+    ) -> E::Fr {
+        let (columns, values) = self.row(i);
+        match (columns, values) {
+            ([], []) => E::Fr::zero(),
+            // Single terms, like the constraints of constants, need no accumulator.
+            ([var], [coeff]) => term::<E>(*var, coeff, input_assignment, aux_assignment),
+            _ => columns
+                .iter()
+                .zip(values)
+                .fold(E::Fr::zero(), |mut acc, (var, coeff)| {
+                    acc.add_assign(&term::<E>(*var, coeff, input_assignment, aux_assignment));
+                    acc
+                }),
+        }
+    }
+
+    /// Marks the variables with a term in any row in `input_density` and `aux_density`.
+    pub fn mark_density(
+        &self,
+        mut input_density: Option<&mut DensityTracker>,
+        mut aux_density: Option<&mut DensityTracker>,
+    ) {
+        for var in &self.columns {
+            match var.get_unchecked() {
+                Index::Input(i) => {
+                    if let Some(density) = input_density.as_mut() {
+                        density.inc(i);
+                    }
+                }
+                Index::Aux(i) => {
+                    if let Some(density) = aux_density.as_mut() {
+                        density.inc(i);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn term<E: ScalarEngine>(
+    var: Variable,
+    coeff: &E::Fr,
+    input_assignment: &[E::Fr],
+    aux_assignment: &[E::Fr],
+) -> E::Fr {
+    let mut value = match var.get_unchecked() {
+        Index::Input(i) => input_assignment[i],
+        Index::Aux(i) => aux_assignment[i],
+    };
+    if *coeff != E::Fr::one() {
+        value.mul_assign(coeff);
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use rand::Rng;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_csr_evaluate() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let inputs = (0..8).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let aux = (0..8).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        let mut matrix = R1CSMatrixCSR::<Bls12>::new();
+        let mut expected = Vec::new();
+        let mut expected_inputs = DensityTracker::new();
+        let mut expected_aux = DensityTracker::new();
+        for _ in 0..8 {
+            expected_inputs.add_element();
+            expected_aux.add_element();
+        }
+
+        // Empty rows, rows of a single term and rows of several terms.
+        for num_terms in 0..8 {
+            let mut lc = LinearCombination::<Bls12>::zero();
+            let mut value = Fr::zero();
+            for i in 0..num_terms {
+                let coeff = if rng.gen() {
+                    Fr::one()
+                } else {
+                    Fr::random(&mut rng)
+                };
+                let (var, mut term) = if i % 2 == 0 {
+                    expected_inputs.inc(i);
+                    (Variable(Index::Input(i)), inputs[i])
+                } else {
+                    expected_aux.inc(i);
+                    (Variable(Index::Aux(i)), aux[i])
+                };
+                term.mul_assign(&coeff);
+                value.add_assign(&term);
+                lc = lc + (coeff, var);
+            }
+            matrix.push_row(&lc);
+            expected.push(value);
+        }
+
+        assert_eq!(matrix.num_rows(), 8);
+        assert_eq!(matrix.nnz(), (0..8).sum::<usize>());
+        assert_eq!(matrix.evaluate(&inputs, &aux), expected);
+
+        let mut input_density = DensityTracker::new();
+        let mut aux_density = DensityTracker::new();
+        for _ in 0..8 {
+            input_density.add_element();
+            aux_density.add_element();
+        }
+        matrix.mark_density(Some(&mut input_density), Some(&mut aux_density));
+        assert_eq!(expected_inputs, input_density);
+        assert_eq!(expected_aux, aux_density);
+
+        matrix.clear();
+        assert!(matrix.is_empty());
+        assert!(matrix.evaluate(&inputs, &aux).is_empty());
+    }
+}
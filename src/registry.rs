@@ -0,0 +1,263 @@
+//! Verifying keys of several circuits, looked up by their fingerprint.
+//!
+//! The registry is filled once and then only read, so it can be shared as a global:
+//!
+//! ```ignore
+//! lazy_static! {
+//!     static ref REGISTRY: CircuitRegistry<Bls12> = {
+//!         let mut registry = CircuitRegistry::new();
+//!         registry.load_from_directory("/var/tmp/params".as_ref()).unwrap();
+//!         registry
+//!     };
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::bls::Engine;
+use crate::groth16::{prepare_verifying_key, CircuitTag, PreparedVerifyingKey, VerifyingKey};
+
+/// Identifies a circuit by the fingerprint of its verifying key.
+pub type CircuitId = [u8; 32];
+
+const FINGERPRINT_PERSONALIZATION: &[u8; 8] = b"BPVKFP00";
+
+/// Extension of parameter files picked up by [`CircuitRegistry::load_from_directory`].
+pub const PARAMS_EXTENSION: &str = "params";
+
+/// Computes the fingerprint of `vk`, the BLAKE2s hash of its serialization.
+pub fn circuit_id<E: Engine, C: CircuitTag>(vk: &VerifyingKey<E, C>) -> CircuitId {
+    let mut bytes = Vec::new();
+    vk.write(&mut bytes).expect("writing to a vec never fails");
+
+    let hash = blake2s_simd::Params::new()
+        .hash_length(32)
+        .personal(FINGERPRINT_PERSONALIZATION)
+        .hash(&bytes);
+
+    let mut id = [0u8; 32];
+    id.copy_from_slice(hash.as_bytes());
+    id
+}
+
+struct RegisteredCircuit<E: Engine> {
+    pvk: PreparedVerifyingKey<E>,
+    params_path: Option<PathBuf>,
+}
+
+/// Maps circuit ids to the prepared verifying keys and parameter files of the circuits.
+pub struct CircuitRegistry<E: Engine> {
+    circuits: HashMap<CircuitId, RegisteredCircuit<E>>,
+}
+
+impl<E: Engine> Default for CircuitRegistry<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> CircuitRegistry<E> {
+    pub fn new() -> Self {
+        CircuitRegistry {
+            circuits: HashMap::new(),
+        }
+    }
+
+    /// Registers `vk` as the verifying key of the circuit `id`, replacing a previously
+    /// registered key.
+    pub fn register(&mut self, id: CircuitId, vk: &VerifyingKey<E>) {
+        self.insert(id, vk, None);
+    }
+
+    fn insert(&mut self, id: CircuitId, vk: &VerifyingKey<E>, params_path: Option<PathBuf>) {
+        self.circuits.insert(
+            id,
+            RegisteredCircuit {
+                pvk: prepare_verifying_key(vk),
+                params_path,
+            },
+        );
+    }
+
+    pub fn lookup(&self, id: &CircuitId) -> Option<&PreparedVerifyingKey<E>> {
+        self.circuits.get(id).map(|circuit| &circuit.pvk)
+    }
+
+    /// The parameter file of the circuit, if it was loaded from one.
+    pub fn params_path(&self, id: &CircuitId) -> Option<&Path> {
+        self.circuits
+            .get(id)
+            .and_then(|circuit| circuit.params_path.as_ref())
+            .map(|path| path.as_path())
+    }
+
+    pub fn len(&self) -> usize {
+        self.circuits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.circuits.is_empty()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &CircuitId> {
+        self.circuits.keys()
+    }
+
+    /// Registers the circuits of all parameter files (ending in `.params`) in `dir`,
+    /// under the fingerprints of their verifying keys. Only the verifying keys at the
+    /// start of the files are read.
+    ///
+    /// Returns the ids of the registered circuits, in the order of the file names.
+    pub fn load_from_directory(&mut self, dir: &Path) -> io::Result<Vec<CircuitId>> {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.retain(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .map_or(false, |ext| ext == PARAMS_EXTENSION)
+        });
+        paths.sort();
+
+        let mut ids = Vec::with_capacity(paths.len());
+        for path in paths {
+            let vk = VerifyingKey::<E>::read(BufReader::new(File::open(&path)?))?;
+            let id = circuit_id(&vk);
+            self.insert(id, &vk, Some(path));
+            ids.push(id);
+        }
+
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::BufWriter;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters, verify_proof};
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Proves knowledge of `x` with `x^(2^squarings)` as the public input, different
+    /// numbers of squarings make different circuits.
+    struct Squarings {
+        x: Option<Fr>,
+        squarings: usize,
+    }
+
+    impl Circuit<Bls12> for Squarings {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut value = self.x;
+            let mut var = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+            for i in 0..self.squarings {
+                value = value.map(|mut v| {
+                    v.square();
+                    v
+                });
+                let next = if i + 1 == self.squarings {
+                    cs.alloc_input(|| "y", || value.ok_or(SynthesisError::AssignmentMissing))?
+                } else {
+                    cs.alloc(
+                        || format!("x^2^{}", i + 1),
+                        || value.ok_or(SynthesisError::AssignmentMissing),
+                    )?
+                };
+                cs.enforce(
+                    || format!("squaring {}", i),
+                    |lc| lc + var,
+                    |lc| lc + var,
+                    |lc| lc + next,
+                );
+                var = next;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registry_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CircuitRegistry<Bls12>>();
+    }
+
+    #[test]
+    fn test_load_from_directory() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let dir = std::env::temp_dir().join(format!("bellperson-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let params = [("claim", 2), ("transfer", 3)]
+            .iter()
+            .map(|&(name, squarings)| {
+                let params = generate_random_parameters::<Bls12, _, _>(
+                    Squarings { x: None, squarings },
+                    rng,
+                )
+                .unwrap();
+                let path = dir.join(format!("{}.{}", name, PARAMS_EXTENSION));
+                params
+                    .write(BufWriter::new(File::create(&path).unwrap()))
+                    .unwrap();
+
+                (params, path, squarings)
+            })
+            .collect::<Vec<_>>();
+        fs::write(dir.join("README"), b"not a parameter file").unwrap();
+
+        let mut registry = CircuitRegistry::<Bls12>::new();
+        let ids = registry.load_from_directory(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(registry.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+
+        for ((params, path, squarings), id) in params.iter().zip(&ids) {
+            assert_eq!(*id, circuit_id(&params.vk));
+            assert_eq!(registry.params_path(id), Some(path.as_path()));
+
+            let x = Fr::random(rng);
+            let mut y = x;
+            for _ in 0..*squarings {
+                y.square();
+            }
+            let proof = create_random_proof(
+                Squarings {
+                    x: Some(x),
+                    squarings: *squarings,
+                },
+                params,
+                rng,
+            )
+            .unwrap();
+            assert!(verify_proof(registry.lookup(id).unwrap(), &proof, &[y]).unwrap());
+        }
+
+        // Keys registered directly have no parameter file.
+        let vk = &params[0].0.vk;
+        let mut other = [0u8; 32];
+        other[0] = 1;
+        registry.register(other, vk);
+        assert!(registry.lookup(&other).is_some());
+        assert_eq!(registry.params_path(&other), None);
+        assert!(registry.lookup(&[0u8; 32]).is_none());
+    }
+}
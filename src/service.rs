@@ -0,0 +1,652 @@
+//! Verification of Groth16 proofs behind a common interface.
+//!
+//! A [`VerificationService`] verifies [`VerificationRequest`]s, which erase the engine of
+//! the proof, so that services for different backends can be used as trait objects:
+//!
+//! - [`LocalVerifier`] verifies every proof on its own with `verify_proof`.
+//! - [`BatchingVerifier`] collects proofs and verifies them together with
+//!   `verify_proofs_batch`.
+//! - [`EthereumPrecompileVerifier`] encodes proofs as calldata for a verifier contract
+//!   and hands them to a caller supplied submission function.
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use ff::{PrimeField, PrimeFieldRepr};
+use groupy::CurveAffine;
+
+use crate::bls::Engine;
+use crate::groth16::{verify_proof, verify_proofs_batch, PreparedVerifyingKey, Proof};
+use crate::SynthesisError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    /// Verification itself failed, e.g. because of a malformed verifying key.
+    #[error("verification failed: {0}")]
+    Synthesis(#[from] SynthesisError),
+    /// Requests of different engines were mixed up.
+    #[error("request is for a different engine")]
+    EngineMismatch,
+    /// The backend reported an error.
+    #[error("backend error: {0}")]
+    Backend(String),
+}
+
+/// Future returned by [`VerificationService::verify`].
+pub type VerifyFuture<'a> = Pin<Box<dyn Future<Output = Result<bool, ServiceError>> + Send + 'a>>;
+
+/// A backend verifying proofs.
+pub trait VerificationService: Send + Sync {
+    /// Verifies the proof of `request`, resolving to whether it is valid.
+    fn verify<'a>(&'a self, request: &'a VerificationRequest) -> VerifyFuture<'a>;
+}
+
+/// A proof with its verifying key and public inputs, of any engine.
+#[derive(Clone)]
+pub struct VerificationRequest {
+    inner: Arc<dyn ErasedRequest>,
+}
+
+impl VerificationRequest {
+    pub fn new<E>(
+        proof: &Proof<E>,
+        pvk: &Arc<PreparedVerifyingKey<E>>,
+        public_inputs: &[E::Fr],
+    ) -> Self
+    where
+        E: Engine,
+        <E::Fr as PrimeField>::Repr: From<E::Fr>,
+        PreparedVerifyingKey<E>: Send + Sync,
+    {
+        VerificationRequest {
+            inner: Arc::new(Request {
+                proof: proof.clone(),
+                pvk: pvk.clone(),
+                public_inputs: public_inputs.to_vec(),
+            }),
+        }
+    }
+}
+
+/// The engine specific operations on a request.
+trait ErasedRequest: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    /// Requests with the same key can be verified in one batch.
+    fn batch_key(&self) -> (TypeId, usize);
+
+    fn verify(&self) -> Result<bool, ServiceError>;
+
+    /// Verifies `batch`, whose members all have the batch key of `self`.
+    fn verify_batch(&self, batch: &[&VerificationRequest]) -> Result<bool, ServiceError>;
+
+    fn calldata(&self) -> Vec<u8>;
+}
+
+struct Request<E: Engine> {
+    proof: Proof<E>,
+    pvk: Arc<PreparedVerifyingKey<E>>,
+    public_inputs: Vec<E::Fr>,
+}
+
+impl<E> ErasedRequest for Request<E>
+where
+    E: Engine,
+    <E::Fr as PrimeField>::Repr: From<E::Fr>,
+    PreparedVerifyingKey<E>: Send + Sync,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn batch_key(&self) -> (TypeId, usize) {
+        (
+            TypeId::of::<E>(),
+            &*self.pvk as *const PreparedVerifyingKey<E> as usize,
+        )
+    }
+
+    fn verify(&self) -> Result<bool, ServiceError> {
+        Ok(verify_proof(&self.pvk, &self.proof, &self.public_inputs)?)
+    }
+
+    fn verify_batch(&self, batch: &[&VerificationRequest]) -> Result<bool, ServiceError> {
+        let requests = batch
+            .iter()
+            .map(|r| {
+                r.inner
+                    .as_any()
+                    .downcast_ref::<Request<E>>()
+                    .ok_or(ServiceError::EngineMismatch)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let proofs = requests.iter().map(|r| &r.proof).collect::<Vec<_>>();
+        let public_inputs = requests
+            .iter()
+            .map(|r| r.public_inputs.clone())
+            .collect::<Vec<_>>();
+
+        Ok(verify_proofs_batch(
+            &self.pvk,
+            &mut rand::thread_rng(),
+            &proofs,
+            &public_inputs,
+        )?)
+    }
+
+    fn calldata(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        encode_g1(&mut data, &self.proof.a);
+        encode_g2(&mut data, &self.proof.b);
+        encode_g1(&mut data, &self.proof.c);
+        for input in &self.public_inputs {
+            input
+                .into_repr()
+                .write_be(&mut data)
+                .expect("writing to a vec never fails");
+        }
+
+        data
+    }
+}
+
+/// Size of a base field element in the EIP-2537 encoding.
+const PADDED_FP_SIZE: usize = 64;
+
+/// Appends the base field elements of an uncompressed point, padded to
+/// [`PADDED_FP_SIZE`] and with the flag bits cleared, so the identity encodes as
+/// zeros.
+fn encode_coordinates(data: &mut Vec<u8>, uncompressed: &[u8], order: &[usize]) {
+    let mut bytes = uncompressed.to_vec();
+    bytes[0] &= 0x1f;
+
+    let fp_size = bytes.len() / order.len();
+    for &i in order {
+        data.extend(std::iter::repeat(0).take(PADDED_FP_SIZE - fp_size));
+        data.extend_from_slice(&bytes[i * fp_size..(i + 1) * fp_size]);
+    }
+}
+
+fn encode_g1<G: CurveAffine>(data: &mut Vec<u8>, point: &G) {
+    encode_coordinates(data, point.into_uncompressed().as_ref(), &[0, 1]);
+}
+
+fn encode_g2<G: CurveAffine>(data: &mut Vec<u8>, point: &G) {
+    // Points are serialized as x.c1, x.c0, y.c1, y.c0, while EIP-2537 starts with c0.
+    encode_coordinates(data, point.into_uncompressed().as_ref(), &[1, 0, 3, 2]);
+}
+
+/// Verifies every request right away.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalVerifier;
+
+impl VerificationService for LocalVerifier {
+    fn verify<'a>(&'a self, request: &'a VerificationRequest) -> VerifyFuture<'a> {
+        Box::pin(async move { request.inner.verify() })
+    }
+}
+
+struct Slot {
+    result: Option<Result<bool, ServiceError>>,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    fn complete(slot: &Mutex<Slot>, result: Result<bool, ServiceError>) {
+        let mut slot = slot.lock().unwrap();
+        slot.result = Some(result);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct PendingResult {
+    slot: Arc<Mutex<Slot>>,
+}
+
+impl Future for PendingResult {
+    type Output = Result<bool, ServiceError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// How long [`BatchingVerifier::new`] lets a request wait for a full batch.
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_millis(50);
+
+type Pending = Vec<(VerificationRequest, Arc<Mutex<Slot>>)>;
+
+/// Collects requests and verifies them in batches.
+///
+/// A request is queued as soon as `verify` is called. The queue is flushed when it
+/// holds `batch_size` requests, when its oldest request has waited for the maximum
+/// delay, on [`BatchingVerifier::flush`] and on drop, so every request resolves even if
+/// no batch fills up. Batches are verified on a thread of the verifier, so `verify`
+/// returns right away also for the request filling a batch. Requests for the same
+/// verifying key are verified in one batch; if the batch does not verify, its requests
+/// are verified one by one to find the invalid ones.
+pub struct BatchingVerifier {
+    batch_size: usize,
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    flusher: Option<JoinHandle<()>>,
+}
+
+struct Queue {
+    /// Full batches waiting for the flushing thread.
+    full: VecDeque<Pending>,
+    pending: Pending,
+    /// When the oldest pending request was queued.
+    oldest: Option<Instant>,
+    closed: bool,
+}
+
+impl Queue {
+    fn take(&mut self) -> Pending {
+        self.oldest = None;
+        std::mem::replace(&mut self.pending, Vec::new())
+    }
+}
+
+impl BatchingVerifier {
+    /// Verifies batches of `batch_size` requests, waiting at most
+    /// [`DEFAULT_MAX_DELAY`] for a batch to fill up.
+    pub fn new(batch_size: usize) -> Self {
+        Self::with_max_delay(batch_size, DEFAULT_MAX_DELAY)
+    }
+
+    /// Verifies batches of `batch_size` requests, verifying smaller batches once their
+    /// oldest request has waited for `max_delay`.
+    pub fn with_max_delay(batch_size: usize, max_delay: Duration) -> Self {
+        assert!(batch_size > 0, "batch size must be positive");
+
+        let queue = Arc::new((
+            Mutex::new(Queue {
+                full: VecDeque::new(),
+                pending: Vec::new(),
+                oldest: None,
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+        let flusher = {
+            let queue = queue.clone();
+            std::thread::spawn(move || Self::run_flusher(&queue, max_delay))
+        };
+
+        BatchingVerifier {
+            batch_size,
+            queue,
+            flusher: Some(flusher),
+        }
+    }
+
+    /// Number of queued requests which do not fill a batch yet.
+    pub fn pending(&self) -> usize {
+        self.queue.0.lock().unwrap().pending.len()
+    }
+
+    /// Verifies all queued requests.
+    pub fn flush(&self) {
+        let batches = {
+            let mut queue = self.queue.0.lock().unwrap();
+            let mut batches = queue.full.drain(..).collect::<Vec<_>>();
+            batches.push(queue.take());
+            batches
+        };
+        for batch in batches {
+            Self::verify_all(batch);
+        }
+    }
+
+    /// Verifies the full batches as they are queued and flushes the queue whenever its
+    /// oldest request has waited for `max_delay`, until the verifier is dropped.
+    fn run_flusher(queue: &(Mutex<Queue>, Condvar), max_delay: Duration) {
+        let (lock, queued) = queue;
+        let mut queue = lock.lock().unwrap();
+        loop {
+            if let Some(batch) = queue.full.pop_front() {
+                drop(queue);
+                Self::verify_all(batch);
+                queue = lock.lock().unwrap();
+                continue;
+            }
+            if queue.closed {
+                break;
+            }
+
+            let deadline = match queue.oldest {
+                Some(oldest) => oldest + max_delay,
+                None => {
+                    queue = queued.wait(queue).unwrap();
+                    continue;
+                }
+            };
+
+            let now = Instant::now();
+            if now >= deadline {
+                let pending = queue.take();
+                drop(queue);
+                Self::verify_all(pending);
+                queue = lock.lock().unwrap();
+            } else {
+                queue = queued.wait_timeout(queue, deadline - now).unwrap().0;
+            }
+        }
+    }
+
+    fn verify_all(pending: Pending) {
+        let mut batches: HashMap<_, Vec<_>> = HashMap::new();
+        for (request, slot) in pending {
+            batches
+                .entry(request.inner.batch_key())
+                .or_default()
+                .push((request, slot));
+        }
+
+        for batch in batches.values() {
+            let requests = batch.iter().map(|(r, _)| r).collect::<Vec<_>>();
+            if let Ok(true) = requests[0].inner.verify_batch(&requests) {
+                for (_, slot) in batch {
+                    Slot::complete(slot, Ok(true));
+                }
+            } else {
+                for (request, slot) in batch {
+                    Slot::complete(slot, request.inner.verify());
+                }
+            }
+        }
+    }
+}
+
+impl VerificationService for BatchingVerifier {
+    fn verify<'a>(&'a self, request: &'a VerificationRequest) -> VerifyFuture<'a> {
+        let slot = Arc::new(Mutex::new(Slot {
+            result: None,
+            waker: None,
+        }));
+
+        {
+            let (lock, queued) = &*self.queue;
+            let mut queue = lock.lock().unwrap();
+            queue.pending.push((request.clone(), slot.clone()));
+            if queue.pending.len() >= self.batch_size {
+                let full = queue.take();
+                queue.full.push_back(full);
+                queued.notify_one();
+            } else if queue.oldest.is_none() {
+                queue.oldest = Some(Instant::now());
+                queued.notify_one();
+            }
+        }
+
+        Box::pin(PendingResult { slot })
+    }
+}
+
+impl Drop for BatchingVerifier {
+    fn drop(&mut self) {
+        self.flush();
+
+        self.queue.0.lock().unwrap().closed = true;
+        self.queue.1.notify_one();
+        if let Some(flusher) = self.flusher.take() {
+            let _ = flusher.join();
+        }
+    }
+}
+
+/// Function submitting calldata to the contract at the given address.
+pub type SubmitFn = dyn Fn(&[u8; 20], Vec<u8>) -> Result<bool, ServiceError> + Send + Sync;
+
+/// Verifies proofs with a contract using the BLS12-381 precompiles of EIP-2537.
+///
+/// This only encodes the calldata, talking to a node is left to the submission
+/// function. The calldata holds A, B and C of the proof in the EIP-2537 point encoding,
+/// followed by the public inputs as 32 byte big-endian integers.
+pub struct EthereumPrecompileVerifier {
+    contract: [u8; 20],
+    submit: Box<SubmitFn>,
+}
+
+impl EthereumPrecompileVerifier {
+    pub fn new<F>(contract: [u8; 20], submit: F) -> Self
+    where
+        F: Fn(&[u8; 20], Vec<u8>) -> Result<bool, ServiceError> + Send + Sync + 'static,
+    {
+        EthereumPrecompileVerifier {
+            contract,
+            submit: Box::new(submit),
+        }
+    }
+
+    pub fn contract(&self) -> &[u8; 20] {
+        &self.contract
+    }
+
+    pub fn calldata(&self, request: &VerificationRequest) -> Vec<u8> {
+        request.inner.calldata()
+    }
+}
+
+impl VerificationService for EthereumPrecompileVerifier {
+    fn verify<'a>(&'a self, request: &'a VerificationRequest) -> VerifyFuture<'a> {
+        Box::pin(async move { (self.submit)(&self.contract, self.calldata(request)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::task::{RawWaker, RawWakerVTable};
+
+    use crate::bls::{Bls12, Fr};
+    use crate::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key};
+    use crate::{Circuit, ConstraintSystem};
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        unsafe { Waker::from_raw(clone(std::ptr::null())) }
+    }
+
+    fn poll<F: Future + ?Sized>(future: &mut Pin<Box<F>>) -> Poll<F::Output> {
+        let waker = noop_waker();
+        future.as_mut().poll(&mut Context::from_waker(&waker))
+    }
+
+    fn ready<T>(poll: Poll<T>) -> T {
+        match poll {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    /// Polls `future` until it is ready, which it must be within a minute.
+    fn wait<F: Future + ?Sized>(future: &mut Pin<Box<F>>) -> F::Output {
+        let start = Instant::now();
+        loop {
+            if let Poll::Ready(output) = poll(future) {
+                return output;
+            }
+            assert!(start.elapsed() < Duration::from_secs(60));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Proves knowledge of `a` and `b` with `a * b = c`.
+    struct Mul {
+        a: Option<Fr>,
+        b: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Mul {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc(|| "b", || self.b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = cs.alloc_input(
+                || "c",
+                || {
+                    let mut c = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+                    c.mul_assign(&self.b.ok_or(SynthesisError::AssignmentMissing)?);
+                    Ok(c)
+                },
+            )?;
+            cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+
+            Ok(())
+        }
+    }
+
+    /// Returns valid requests, followed by one whose public input is wrong.
+    fn requests(num: usize) -> Vec<VerificationRequest> {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let params =
+            generate_random_parameters::<Bls12, _, _>(Mul { a: None, b: None }, rng).unwrap();
+        let pvk = Arc::new(prepare_verifying_key(&params.vk));
+
+        let mut requests = (0..=num)
+            .map(|_| {
+                let a = Fr::random(rng);
+                let b = Fr::random(rng);
+                let proof = create_random_proof(
+                    Mul {
+                        a: Some(a),
+                        b: Some(b),
+                    },
+                    &params,
+                    rng,
+                )
+                .unwrap();
+                let mut c = a;
+                c.mul_assign(&b);
+
+                (proof, c)
+            })
+            .collect::<Vec<_>>();
+        requests[num].1.add_assign(&Fr::one());
+
+        requests
+            .iter()
+            .map(|(proof, c)| VerificationRequest::new(proof, &pvk, &[*c]))
+            .collect()
+    }
+
+    #[test]
+    fn test_local_verifier() {
+        let requests = requests(2);
+        let service: Box<dyn VerificationService> = Box::new(LocalVerifier);
+
+        let results = requests
+            .iter()
+            .map(|r| ready(poll(&mut service.verify(r))).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_batching_verifier() {
+        let requests = requests(4);
+        let service = BatchingVerifier::with_max_delay(3, Duration::from_secs(3600));
+
+        // The third request fills the first batch, which is verified in the background.
+        let mut first = requests[..3]
+            .iter()
+            .map(|r| service.verify(r))
+            .collect::<Vec<_>>();
+        assert_eq!(service.pending(), 0);
+        let first = first
+            .iter_mut()
+            .map(|f| wait(f).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(first, vec![true, true, true]);
+
+        let mut second = requests[3..]
+            .iter()
+            .map(|r| service.verify(r))
+            .collect::<Vec<_>>();
+        assert_eq!(service.pending(), 2);
+        for future in &mut second {
+            assert!(poll(future).is_pending());
+        }
+        service.flush();
+
+        let second = second
+            .iter_mut()
+            .map(|f| ready(poll(f)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(second, vec![true, false]);
+    }
+
+    #[test]
+    fn test_batching_verifier_lone_request() {
+        let requests = requests(1);
+        let service = BatchingVerifier::with_max_delay(8, Duration::from_millis(10));
+
+        // Neither request fills a batch, the flushing thread flushes them.
+        let mut futures = requests
+            .iter()
+            .map(|r| service.verify(r))
+            .collect::<Vec<_>>();
+        let results = futures
+            .iter_mut()
+            .map(|f| wait(f).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![true, false]);
+        assert_eq!(service.pending(), 0);
+    }
+
+    #[test]
+    fn test_ethereum_precompile_verifier() {
+        let requests = requests(1);
+        let contract = [0x42; 20];
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let service = EthereumPrecompileVerifier::new(contract, move |address, calldata| {
+            tx.lock().unwrap().send((*address, calldata)).unwrap();
+            Ok(true)
+        });
+
+        assert!(ready(poll(&mut service.verify(&requests[0]))).unwrap());
+        let (address, calldata) = rx.recv().unwrap();
+        assert_eq!(address, contract);
+        assert_eq!(calldata, service.calldata(&requests[0]));
+
+        // Two G1 points, a G2 point and one public input.
+        assert_eq!(calldata.len(), 2 * 128 + 256 + 32);
+        for fp in calldata[..512].chunks(PADDED_FP_SIZE) {
+            assert!(fp[..16].iter().all(|b| *b == 0));
+        }
+    }
+}
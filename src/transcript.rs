@@ -0,0 +1,201 @@
+//! Fiat-Shamir transcripts, for turning public-coin protocols built on top of the proof
+//! systems into non-interactive ones.
+//!
+//! A [`FiatShamirRng`] absorbs the messages of the prover, group and field elements or
+//! raw bytes, and its random output is a hash of everything absorbed so far. Prover and
+//! verifier absorbing the same messages in the same order derive the same challenges.
+
+use digest::{Digest, Output};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use groupy::CurveAffine;
+use rand_core::{impls, Error, RngCore};
+
+/// Tags of the kinds of absorbed values, so that e.g. the encoding of a point is not
+/// mistaken for the same bytes absorbed with [`FiatShamirRng::absorb_bytes`].
+const TAG_BYTES: u8 = b'B';
+const TAG_FR: u8 = b'F';
+const TAG_G1: u8 = b'1';
+const TAG_G2: u8 = b'2';
+const TAG_GT: u8 = b'T';
+
+/// A random number generator whose output is determined by the absorbed transcript.
+///
+/// Every value is absorbed as its tag, its length as a little endian `u64` and its
+/// bytes: field elements in the little endian encoding of their canonical
+/// representation, curve points in their compressed encoding and target group elements
+/// as text. The output is the concatenation of the blocks `H(seed || i)` for
+/// `i = 0, 1, ...` as little endian `u64`s, where `seed` is the hash of the domain and
+/// all values absorbed. Absorbing another value starts a new output stream from the
+/// updated seed.
+#[derive(Clone)]
+pub struct FiatShamirRng<H: Digest + Clone> {
+    hasher: H,
+    /// The hash of the transcript, computed when output is first requested.
+    seed: Option<Output<H>>,
+    counter: u64,
+    block: Output<H>,
+    position: usize,
+}
+
+impl<H: Digest + Clone> FiatShamirRng<H> {
+    /// Starts a transcript for the protocol named `domain`.
+    pub fn new(domain: &[u8]) -> Self {
+        let mut hasher = H::new();
+        hasher.update(&(domain.len() as u64).to_le_bytes());
+        hasher.update(domain);
+
+        FiatShamirRng {
+            hasher,
+            seed: None,
+            counter: 0,
+            block: Output::<H>::default(),
+            position: 0,
+        }
+    }
+
+    pub fn absorb_bytes(&mut self, bytes: &[u8]) {
+        self.absorb(TAG_BYTES, bytes);
+    }
+
+    pub fn absorb_fr<F: PrimeField>(&mut self, value: &F) {
+        let mut bytes = Vec::new();
+        value
+            .into_repr()
+            .write_le(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        self.absorb(TAG_FR, &bytes);
+    }
+
+    pub fn absorb_g1<G: CurveAffine>(&mut self, point: &G) {
+        self.absorb(TAG_G1, point.into_compressed().as_ref());
+    }
+
+    pub fn absorb_g2<G: CurveAffine>(&mut self, point: &G) {
+        self.absorb(TAG_G2, point.into_compressed().as_ref());
+    }
+
+    /// Absorbs an element of the target group of a pairing, or of any other field,
+    /// in its textual form: the field traits have no byte encoding, while the textual
+    /// form lists the canonical representations of all coefficients.
+    pub fn absorb_gt<F: Field>(&mut self, value: &F) {
+        self.absorb(TAG_GT, value.to_string().as_bytes());
+    }
+
+    fn absorb(&mut self, tag: u8, bytes: &[u8]) {
+        self.hasher.update(&[tag]);
+        self.hasher.update(&(bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+        self.seed = None;
+    }
+
+    /// The next output block, hashing the transcript first if needed.
+    fn next_block(&mut self) {
+        if self.seed.is_none() {
+            self.seed = Some(self.hasher.clone().finalize());
+            self.counter = 0;
+        }
+        let seed = self.seed.as_ref().expect("the seed was just set");
+
+        let mut hasher = H::new();
+        hasher.update(seed);
+        hasher.update(&self.counter.to_le_bytes());
+        self.block = hasher.finalize();
+        self.counter += 1;
+        self.position = 0;
+    }
+}
+
+impl<H: Digest + Clone> RngCore for FiatShamirRng<H> {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut filled = 0;
+        while filled < dest.len() {
+            if self.seed.is_none() || self.position == self.block.len() {
+                self.next_block();
+            }
+            let len = std::cmp::min(dest.len() - filled, self.block.len() - self.position);
+            dest[filled..filled + len]
+                .copy_from_slice(&self.block[self.position..self.position + len]);
+            filled += len;
+            self.position += len;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, any(feature = "pairing", feature = "blst")))]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Fr, G1Affine, G2Affine};
+    use sha3::Sha3_256;
+
+    fn transcript() -> FiatShamirRng<Sha3_256> {
+        let mut rng = FiatShamirRng::<Sha3_256>::new(b"bellperson-test");
+        rng.absorb_bytes(b"hello");
+        rng.absorb_fr(&Fr::one());
+        rng.absorb_g1(&G1Affine::one());
+        rng
+    }
+
+    #[test]
+    fn test_deterministic_output() {
+        // Computed independently from the encoding documented on `FiatShamirRng`, so
+        // the output stays the same across runs and versions.
+        let mut rng = transcript();
+        assert_eq!(rng.next_u64(), 0x869d_2916_700a_3779);
+        assert_eq!(rng.next_u64(), 0xb5b4_3c82_d021_135b);
+        rng.next_u64();
+        rng.next_u64();
+        // The first value of the second block.
+        assert_eq!(rng.next_u64(), 0x76d0_5c6f_9c69_13a0);
+
+        let mut a = transcript();
+        let mut b = transcript();
+        let mut bytes = [0u8; 100];
+        a.fill_bytes(&mut bytes);
+        for chunk in bytes.chunks(4) {
+            let mut expected = [0u8; 4];
+            b.fill_bytes(&mut expected[..chunk.len()]);
+            assert_eq!(chunk, &expected[..chunk.len()]);
+        }
+    }
+
+    #[test]
+    fn test_transcript_separation() {
+        let mut a = transcript();
+        let mut b = transcript();
+        a.absorb_g2(&G2Affine::one());
+        b.absorb_g2(&G2Affine::one());
+        assert_eq!(a.next_u64(), b.next_u64());
+        assert_eq!(Fr::random(&mut a), Fr::random(&mut b));
+
+        // Absorbing more changes the output, as do the tag and the domain.
+        let before = a.clone().next_u64();
+        a.absorb_bytes(b"");
+        assert_ne!(a.next_u64(), before);
+
+        let mut bytes = FiatShamirRng::<Sha3_256>::new(b"");
+        let mut fr = FiatShamirRng::<Sha3_256>::new(b"");
+        let mut other_domain = FiatShamirRng::<Sha3_256>::new(b"other");
+        let mut repr = Vec::new();
+        Fr::one().into_repr().write_le(&mut repr).unwrap();
+        bytes.absorb_bytes(&repr);
+        fr.absorb_fr(&Fr::one());
+        other_domain.absorb_fr(&Fr::one());
+        let out = fr.next_u64();
+        assert_ne!(bytes.next_u64(), out);
+        assert_ne!(other_domain.next_u64(), out);
+    }
+}
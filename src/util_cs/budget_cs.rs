@@ -0,0 +1,221 @@
+//! Limiting the number of constraints of a circuit.
+//!
+//! A circuit that enforces constraints in a loop by mistake may only fail when its
+//! parameters or proofs are made, because its domain does not fit. [`BudgetedCS`]
+//! fails as soon as it is over budget instead: `enforce` cannot return an error, so
+//! the constraint over the limit is recorded, and the next allocation, or
+//! [`BudgetedCS::check`] at the end, returns
+//! [`SynthesisError::ConstraintBudgetExceeded`].
+
+use ff::ScalarEngine;
+
+use crate::{Circuit, ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+/// `circuit`, failing to synthesize with more than `max_constraints` constraints.
+pub struct Budgeted<C> {
+    pub circuit: C,
+    pub max_constraints: usize,
+}
+
+impl<E: ScalarEngine, C: Circuit<E>> Circuit<E> for Budgeted<C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        synthesize_with_budget(self.circuit, cs, self.max_constraints).map(|_| ())
+    }
+}
+
+/// Synthesizes `circuit` into `cs`, failing once it enforces more than
+/// `max_constraints` constraints, and returns how many it enforced.
+pub fn synthesize_with_budget<E, C, CS>(
+    circuit: C,
+    cs: &mut CS,
+    max_constraints: usize,
+) -> Result<usize, SynthesisError>
+where
+    E: ScalarEngine,
+    C: Circuit<E>,
+    CS: ConstraintSystem<E>,
+{
+    let mut budgeted = cs.with_budget(max_constraints);
+    circuit.synthesize(&mut budgeted)?;
+    budgeted.check()?;
+
+    Ok(budgeted.num_constraints())
+}
+
+/// Passes everything on to `inner`, counting the constraints, as created by
+/// [`ConstraintSystem::with_budget`].
+pub struct BudgetedCS<'a, E: ScalarEngine, CS: ConstraintSystem<E>> {
+    inner: &'a mut CS,
+    limit: usize,
+    num_constraints: usize,
+    _marker: std::marker::PhantomData<fn() -> E>,
+}
+
+impl<'a, E: ScalarEngine, CS: ConstraintSystem<E>> BudgetedCS<'a, E, CS> {
+    pub(crate) fn new(inner: &'a mut CS, limit: usize) -> Self {
+        BudgetedCS {
+            inner,
+            limit,
+            num_constraints: 0,
+            _marker: Default::default(),
+        }
+    }
+
+    /// The number of constraints enforced so far.
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// Fails if more constraints were enforced than the budget allows.
+    pub fn check(&self) -> Result<(), SynthesisError> {
+        if self.num_constraints > self.limit {
+            return Err(SynthesisError::ConstraintBudgetExceeded {
+                limit: self.limit,
+                actual: self.num_constraints,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for BudgetedCS<'a, E, CS> {
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.check()?;
+        self.inner.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.check()?;
+        self.inner.alloc_input(annotation, f)
+    }
+
+    #[cfg_attr(feature = "debug-constraints", track_caller)]
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.num_constraints += 1;
+        self.inner.enforce(annotation, a, b, c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.get_root().push_namespace(name_fn);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.inner.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::num::AllocatedNum;
+    use crate::util_cs::test_cs::TestConstraintSystem;
+    use ff::PrimeField;
+
+    /// Squares a number `rounds` times.
+    struct Squares {
+        rounds: usize,
+    }
+
+    impl Circuit<Bls12> for Squares {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut x =
+                AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::from_str("2").unwrap()))?;
+            for i in 0..self.rounds {
+                x = x.square(cs.namespace(|| format!("square {}", i)))?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_constraint_budget() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let used = synthesize_with_budget(Squares { rounds: 10 }, &mut cs, 10).unwrap();
+        assert_eq!(used, 10);
+        assert_eq!(cs.num_constraints(), 10);
+        assert!(cs.is_satisfied());
+
+        // The square over the budget is caught when the next one allocates its result.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        match synthesize_with_budget(Squares { rounds: 100 }, &mut cs, 10) {
+            Err(SynthesisError::ConstraintBudgetExceeded { limit, actual }) => {
+                assert_eq!(limit, 10);
+                assert_eq!(actual, 11);
+            }
+            other => panic!("expected an exceeded budget, got {:?}", other),
+        }
+        assert_eq!(cs.num_constraints(), 11);
+
+        // With the last constraint over the budget, at the end.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let circuit = Budgeted {
+            circuit: Squares { rounds: 11 },
+            max_constraints: 10,
+        };
+        assert!(matches!(
+            circuit.synthesize(&mut cs),
+            Err(SynthesisError::ConstraintBudgetExceeded {
+                limit: 10,
+                actual: 11
+            })
+        ));
+    }
+
+    #[test]
+    fn test_constraint_budget_namespace() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        Squares { rounds: 3 }.synthesize(&mut cs).unwrap();
+
+        // A budget for a part of the circuit counts only the constraints of that part,
+        // which keep their names.
+        {
+            let mut ns = cs.namespace(|| "part");
+            let mut budgeted = ns.with_budget(2);
+            Squares { rounds: 2 }.synthesize(&mut budgeted).unwrap();
+            assert!(budgeted.check().is_ok());
+        }
+        assert_eq!(cs.num_constraints(), 5);
+        assert_eq!(
+            cs.get("part/square 1/squared num"),
+            Fr::from_str("16").unwrap()
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! A constraint system that remembers where every constraint was enforced.
+//!
+//! [`ConstraintDebugger`] wraps another constraint system, passing everything on to
+//! it, and keeps the constraints and the assignment to itself. The location of each
+//! `enforce` is that of the gadget calling it, as the [`Namespace`](crate::Namespace)
+//! and `&mut` forwarding implementations are `#[track_caller]` with the
+//! `debug-constraints` feature, which this module needs. Without the feature nothing
+//! changes for other constraint systems.
+
+use std::fmt;
+use std::panic::Location;
+
+use crate::bls::Engine;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use ff::Field;
+use log::warn;
+
+struct DebugConstraint<E: Engine> {
+    a: LinearCombination<E>,
+    b: LinearCombination<E>,
+    c: LinearCombination<E>,
+    path: String,
+    location: &'static Location<'static>,
+}
+
+/// A term of a linear combination, with the value of its variable.
+pub struct DebugTerm<E: Engine> {
+    pub variable: Variable,
+    pub coeff: E::Fr,
+    pub value: E::Fr,
+}
+
+/// An unsatisfied constraint `A * B = C`, with where it was enforced and the values
+/// of its terms.
+pub struct UnsatisfiedConstraint<E: Engine> {
+    pub index: usize,
+    pub path: String,
+    pub location: &'static Location<'static>,
+    pub a: Vec<DebugTerm<E>>,
+    pub b: Vec<DebugTerm<E>>,
+    pub c: Vec<DebugTerm<E>>,
+    /// The values of `A`, `B` and `C`.
+    pub values: (E::Fr, E::Fr, E::Fr),
+}
+
+impl<E: Engine> fmt::Display for UnsatisfiedConstraint<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "constraint {} `{}`, enforced at {}, is not satisfied",
+            self.index, self.path, self.location
+        )?;
+
+        let (a, b, c) = &self.values;
+        for (name, terms, value) in &[("A", &self.a, a), ("B", &self.b, b), ("C", &self.c, c)] {
+            writeln!(f, "  {} = {}", name, value)?;
+            for term in terms.iter() {
+                let variable = match term.variable.get_unchecked() {
+                    Index::Input(i) => format!("input {}", i),
+                    Index::Aux(i) => format!("aux {}", i),
+                };
+                writeln!(f, "    {} * {} = {}", term.coeff, variable, term.value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Records the constraints and the assignment of a circuit, with the source locations
+/// of the constraints, while synthesizing it into `CS`.
+pub struct ConstraintDebugger<E: Engine, CS: ConstraintSystem<E>> {
+    inner: CS,
+    current_namespace: Vec<String>,
+    constraints: Vec<DebugConstraint<E>>,
+    inputs: Vec<Option<E::Fr>>,
+    aux: Vec<Option<E::Fr>>,
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> ConstraintDebugger<E, CS> {
+    /// Wraps `inner`, which has allocated the input one and nothing else.
+    pub fn new(inner: CS) -> Self {
+        ConstraintDebugger {
+            inner,
+            current_namespace: vec![],
+            constraints: vec![],
+            inputs: vec![Some(E::Fr::one())],
+            aux: vec![],
+        }
+    }
+
+    pub fn inner(&self) -> &CS {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.inner
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// The first constraint that does not hold. Constraints over variables without a
+    /// value are skipped.
+    pub fn which_is_unsatisfied(&self) -> Option<UnsatisfiedConstraint<E>> {
+        for (index, constraint) in self.constraints.iter().enumerate() {
+            let a = match self.terms(&constraint.a) {
+                Some(terms) => terms,
+                None => continue,
+            };
+            let b = match self.terms(&constraint.b) {
+                Some(terms) => terms,
+                None => continue,
+            };
+            let c = match self.terms(&constraint.c) {
+                Some(terms) => terms,
+                None => continue,
+            };
+
+            let sum = |terms: &[DebugTerm<E>]| {
+                terms.iter().fold(E::Fr::zero(), |mut acc, term| {
+                    let mut value = term.value;
+                    value.mul_assign(&term.coeff);
+                    acc.add_assign(&value);
+                    acc
+                })
+            };
+            let values = (sum(&a), sum(&b), sum(&c));
+
+            let mut product = values.0;
+            product.mul_assign(&values.1);
+            if product != values.2 {
+                return Some(UnsatisfiedConstraint {
+                    index,
+                    path: constraint.path.clone(),
+                    location: constraint.location,
+                    a,
+                    b,
+                    c,
+                    values,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Whether all constraints hold, logging the first one that does not otherwise.
+    pub fn is_satisfied(&self) -> bool {
+        match self.which_is_unsatisfied() {
+            Some(unsatisfied) => {
+                warn!("{}", unsatisfied);
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn terms(&self, lc: &LinearCombination<E>) -> Option<Vec<DebugTerm<E>>> {
+        lc.iter()
+            .map(|(&variable, &coeff)| {
+                let value = match variable.get_unchecked() {
+                    Index::Input(i) => self.inputs.get(i).copied().flatten(),
+                    Index::Aux(i) => self.aux.get(i).copied().flatten(),
+                }?;
+
+                Some(DebugTerm {
+                    variable,
+                    coeff,
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    fn record(&mut self, variable: Variable, value: Option<E::Fr>) {
+        let (values, i) = match variable.get_unchecked() {
+            Index::Input(i) => (&mut self.inputs, i),
+            Index::Aux(i) => (&mut self.aux, i),
+        };
+        if values.len() <= i {
+            values.resize(i + 1, None);
+        }
+        values[i] = value;
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> ConstraintSystem<E> for ConstraintDebugger<E, CS> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = f();
+        let recorded = value.as_ref().ok().copied();
+        let variable = self.inner.alloc(annotation, || value)?;
+        self.record(variable, recorded);
+
+        Ok(variable)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = f();
+        let recorded = value.as_ref().ok().copied();
+        let variable = self.inner.alloc_input(annotation, || value)?;
+        self.record(variable, recorded);
+
+        Ok(variable)
+    }
+
+    #[track_caller]
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let location = Location::caller();
+        let name = annotation().into();
+        let path = self
+            .current_namespace
+            .iter()
+            .chain(Some(&name))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+        self.inner
+            .enforce(|| name, |_| a.clone(), |_| b.clone(), |_| c.clone());
+
+        self.constraints.push(DebugConstraint {
+            a,
+            b,
+            c,
+            path,
+            location,
+        });
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name = name_fn().into();
+        self.current_namespace.push(name.clone());
+        self.inner.get_root().push_namespace(|| name);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.current_namespace.pop();
+        self.inner.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::num::AllocatedNum;
+    use crate::util_cs::test_cs::TestConstraintSystem;
+    use ff::PrimeField;
+
+    #[test]
+    fn test_constraint_debugger() {
+        let mut cs = ConstraintDebugger::new(TestConstraintSystem::<Bls12>::new());
+
+        let a =
+            AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("2").unwrap())).unwrap();
+        let b =
+            AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("3").unwrap())).unwrap();
+        a.mul(cs.namespace(|| "product"), &b).unwrap();
+        assert!(cs.is_satisfied());
+
+        let line = line!() + 3;
+        {
+            let mut cs = cs.namespace(|| "outer");
+            cs.enforce(
+                || "wrong product",
+                |lc| lc + a.get_variable(),
+                |lc| lc + b.get_variable(),
+                |lc| {
+                    lc + (
+                        Fr::from_str("7").unwrap(),
+                        TestConstraintSystem::<Bls12>::one(),
+                    )
+                },
+            );
+        }
+
+        let unsatisfied = cs.which_is_unsatisfied().unwrap();
+        assert_eq!(unsatisfied.index, 1);
+        assert_eq!(unsatisfied.path, "outer/wrong product");
+        assert_eq!(unsatisfied.location.file(), file!());
+        assert_eq!(unsatisfied.location.line(), line);
+        assert_eq!(unsatisfied.a[0].value, Fr::from_str("2").unwrap());
+        assert_eq!(unsatisfied.b[0].value, Fr::from_str("3").unwrap());
+        assert_eq!(unsatisfied.values.2, Fr::from_str("7").unwrap());
+        assert!(unsatisfied.to_string().contains("outer/wrong product"));
+
+        // The wrapped system has the same constraints.
+        let inner = cs.into_inner();
+        assert_eq!(inner.num_constraints(), 2);
+        assert_eq!(inner.which_is_unsatisfied(), Some("outer/wrong product"));
+    }
+
+    #[test]
+    fn test_constraint_debugger_gadget_location() {
+        let mut cs = ConstraintDebugger::new(TestConstraintSystem::<Bls12>::new());
+
+        let bit = AllocatedBit::alloc(cs.namespace(|| "bit"), Some(false)).unwrap();
+        Boolean::enforce_equal(
+            cs.namespace(|| "equal"),
+            &Boolean::from(bit),
+            &Boolean::constant(true),
+        )
+        .unwrap();
+
+        // The constraint of the gadget, not of a namespace.
+        let unsatisfied = cs.which_is_unsatisfied().unwrap();
+        assert_eq!(unsatisfied.path, "equal/enforce equal to one");
+        assert!(unsatisfied.location.file().ends_with("boolean.rs"));
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use ff::{Field, ScalarEngine};
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+/// The numbers of constraints of each structural type, as counted by a
+/// [`ConstraintSystemInspector`]. Every constraint `A * B = C` has exactly one type,
+/// the first of these which applies:
+///
+/// - `boolean`: involves a single variable, and holds exactly if it is 0 or 1, like
+///   `x * (1 - x) = 0` or `x * x = x`.
+/// - `constant`: involves at most one variable, and is linear, so it fixes that variable
+///   to a constant, like `x * 1 = 5`.
+/// - `linear`: `A` or `B` is a constant, so the constraint is a linear equation.
+/// - `quadratic`: `A` and `B` both involve variables.
+///
+/// The constant `one` is not a variable here.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstraintStats {
+    pub linear: usize,
+    pub quadratic: usize,
+    pub constant: usize,
+    pub boolean: usize,
+}
+
+impl ConstraintStats {
+    /// The number of constraints of all types.
+    pub fn total(&self) -> usize {
+        self.linear + self.quadratic + self.constant + self.boolean
+    }
+}
+
+/// Wraps a constraint system, counting the constraints enforced through it by
+/// structural type, e.g. to find the linear constraints of a circuit which could be
+/// folded into others.
+pub struct ConstraintSystemInspector<E: ScalarEngine, CS: ConstraintSystem<E>> {
+    cs: CS,
+    stats: ConstraintStats,
+    _e: PhantomData<E>,
+}
+
+impl<E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystemInspector<E, CS> {
+    pub fn new(cs: CS) -> Self {
+        ConstraintSystemInspector {
+            cs,
+            stats: ConstraintStats::default(),
+            _e: PhantomData,
+        }
+    }
+
+    /// The numbers of constraints enforced so far.
+    pub fn stats(&self) -> ConstraintStats {
+        self.stats
+    }
+
+    /// The wrapped constraint system.
+    pub fn into_inner(self) -> CS {
+        self.cs
+    }
+
+    fn record(
+        &mut self,
+        a: &LinearCombination<E>,
+        b: &LinearCombination<E>,
+        c: &LinearCombination<E>,
+    ) {
+        let one = CS::one();
+        let (a0, a) = split(a, one);
+        let (b0, b) = split(b, one);
+        let (c0, c) = split(c, one);
+
+        let linear = a.is_empty() || b.is_empty();
+        let variables = a
+            .iter()
+            .chain(&b)
+            .chain(&c)
+            .map(|(var, _)| *var)
+            .collect::<HashSet<_>>();
+
+        let counter = match variables.len() {
+            0 => &mut self.stats.constant,
+            1 if linear => &mut self.stats.constant,
+            1 if is_boolean::<E>((a0, &a[..]), (b0, &b[..]), (c0, &c[..])) => {
+                &mut self.stats.boolean
+            }
+            _ if linear => &mut self.stats.linear,
+            _ => &mut self.stats.quadratic,
+        };
+        *counter += 1;
+    }
+}
+
+/// The coefficient of `one` in `lc`, and its other terms with nonzero coefficients.
+fn split<E: ScalarEngine>(
+    lc: &LinearCombination<E>,
+    one: Variable,
+) -> (E::Fr, Vec<(Variable, E::Fr)>) {
+    let mut constant = E::Fr::zero();
+    let mut terms = Vec::new();
+    for (&var, coeff) in lc.iter() {
+        if var == one {
+            constant.add_assign(coeff);
+        } else if !coeff.is_zero() {
+            terms.push((var, *coeff));
+        }
+    }
+
+    (constant, terms)
+}
+
+/// Whether `(a0 + a1 x) * (b0 + b1 x) = c0 + c1 x` of a single variable `x` is a
+/// nonzero multiple of `x^2 - x = 0`.
+fn is_boolean<E: ScalarEngine>(
+    a: (E::Fr, &[(Variable, E::Fr)]),
+    b: (E::Fr, &[(Variable, E::Fr)]),
+    c: (E::Fr, &[(Variable, E::Fr)]),
+) -> bool {
+    let coeff = |terms: &[(Variable, E::Fr)]| {
+        terms
+            .first()
+            .map(|(_, coeff)| *coeff)
+            .unwrap_or_else(E::Fr::zero)
+    };
+    let (a0, a1) = (a.0, coeff(a.1));
+    let (b0, b1) = (b.0, coeff(b.1));
+    let (c0, c1) = (c.0, coeff(c.1));
+
+    // x^2: a1 b1
+    let mut square = a1;
+    square.mul_assign(&b1);
+
+    // x: a1 b0 + a0 b1 - c1, which must be -a1 b1
+    let mut linear = a1;
+    linear.mul_assign(&b0);
+    let mut tmp = a0;
+    tmp.mul_assign(&b1);
+    linear.add_assign(&tmp);
+    linear.sub_assign(&c1);
+    linear.add_assign(&square);
+
+    // 1: a0 b0 - c0, which must be 0
+    let mut constant = a0;
+    constant.mul_assign(&b0);
+    constant.sub_assign(&c0);
+
+    !square.is_zero() && linear.is_zero() && constant.is_zero()
+}
+
+impl<E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E>
+    for ConstraintSystemInspector<E, CS>
+{
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+        self.record(&a, &b, &c);
+
+        self.cs.enforce(annotation, |_| a, |_| b, |_| c)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.cs.get_root().pop_namespace()
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::util_cs::test_cs::TestConstraintSystem;
+    use ff::PrimeField;
+
+    #[test]
+    fn test_inspector_stats() {
+        let mut cs = ConstraintSystemInspector::<Bls12, _>::new(TestConstraintSystem::new());
+        let one = TestConstraintSystem::<Bls12>::one();
+        let three = Fr::from_str("3").unwrap();
+
+        // x * (1 - x) = 0
+        let bit = AllocatedBit::alloc(cs.namespace(|| "bit"), Some(true)).unwrap();
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(three)).unwrap();
+        let y =
+            AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(Fr::from_str("9").unwrap())).unwrap();
+        // x * x = x^2
+        x.square(cs.namespace(|| "x^2")).unwrap();
+        cs.enforce(
+            || "x * 1 = 3",
+            |lc| lc + x.get_variable(),
+            |lc| lc + one,
+            |lc| lc + (three, one),
+        );
+        cs.enforce(
+            || "3 * x = y",
+            |lc| lc + (three, one),
+            |lc| lc + x.get_variable(),
+            |lc| lc + y.get_variable(),
+        );
+        cs.enforce(
+            || "b * b = b",
+            |lc| lc + bit.get_variable(),
+            |lc| lc + bit.get_variable(),
+            |lc| lc + bit.get_variable(),
+        );
+
+        assert_eq!(
+            cs.stats(),
+            ConstraintStats {
+                linear: 1,
+                quadratic: 1,
+                constant: 1,
+                boolean: 2,
+            }
+        );
+        assert_eq!(cs.stats().total(), 5);
+
+        // The constraints reach the wrapped system.
+        let cs = cs.into_inner();
+        assert_eq!(cs.num_constraints(), 5);
+        assert!(cs.is_satisfied());
+    }
+}
@@ -0,0 +1,287 @@
+//! Removing linear constraints that the other constraints imply.
+//!
+//! A constraint whose `A` or `B` is a constant is linear, `L = 0` for a linear
+//! combination `L`. Adding two independent linear constraints into one would let a
+//! prover satisfy their sum without satisfying either, so they cannot be merged
+//! soundly. What can go are the linear constraints whose `L` is zero, like those of
+//! gadgets over constants, and those whose `L` is a multiple of the `L` of an earlier
+//! one, like the same check enforced by two gadgets, as the constraints left imply
+//! them. The proof system is unchanged, the circuit just has fewer constraints, so
+//! parameters and proofs have to be made from the same [`MergeLinearConstraints`].
+
+use std::collections::HashSet;
+
+use ff::{Field, PrimeField, ScalarEngine};
+
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// `circuit` with the linear constraints [`merge_linear_constraints`] removes.
+pub struct MergeLinearConstraints<C>(pub C);
+
+impl<E: ScalarEngine, C: Circuit<E>> Circuit<E> for MergeLinearConstraints<C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        merge_linear_constraints(self.0, cs).map(|_| ())
+    }
+}
+
+/// Synthesizes `circuit` into `cs` without the linear constraints that are zero or
+/// multiples of earlier ones, and returns how many were removed. Everything else is
+/// passed on to `cs` as the circuit synthesizes it.
+pub fn merge_linear_constraints<E, C, CS>(circuit: C, cs: &mut CS) -> Result<usize, SynthesisError>
+where
+    E: ScalarEngine,
+    C: Circuit<E>,
+    CS: ConstraintSystem<E>,
+{
+    let mut merging = LinearMergeCS {
+        inner: cs,
+        seen: HashSet::new(),
+        removed: 0,
+    };
+    circuit.synthesize(&mut merging)?;
+
+    Ok(merging.removed)
+}
+
+/// The constant value of `lc`, if it has no terms but those of the input one.
+fn constant<E: ScalarEngine, CS: ConstraintSystem<E>>(lc: &LinearCombination<E>) -> Option<E::Fr> {
+    let mut value = E::Fr::zero();
+    for (var, coeff) in lc.iter() {
+        if *var == CS::one() {
+            value.add_assign(coeff);
+        } else if !coeff.is_zero() {
+            return None;
+        }
+    }
+
+    Some(value)
+}
+
+/// `L` for a linear constraint `A * B = C`, as `A * B - C` with a constant `A` or `B`.
+fn linear_form<E: ScalarEngine, CS: ConstraintSystem<E>>(
+    a: &LinearCombination<E>,
+    b: &LinearCombination<E>,
+    c: &LinearCombination<E>,
+) -> Option<LinearCombination<E>> {
+    let (scalar, lc) = match (constant::<E, CS>(a), constant::<E, CS>(b)) {
+        (Some(scalar), _) => (scalar, b),
+        (_, Some(scalar)) => (scalar, a),
+        (None, None) => return None,
+    };
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    Some(LinearCombination::zero() + (scalar, lc) + (minus_one, c))
+}
+
+/// The terms of `lc` with non-zero coefficients, in order of their variables, scaled
+/// so that the first coefficient is one, or `None` if there are none.
+#[allow(clippy::type_complexity)]
+fn normalized<E: ScalarEngine>(
+    lc: &LinearCombination<E>,
+) -> Option<Vec<((bool, usize), Vec<u64>)>> {
+    // Terms of the same variable are already summed, so no coefficient is split.
+    let mut terms = lc
+        .iter()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .map(|(var, coeff)| (index(var), *coeff))
+        .collect::<Vec<_>>();
+    terms.sort_by_key(|(index, _)| *index);
+
+    let inv = terms.first()?.1.inverse().unwrap();
+    Some(
+        terms
+            .into_iter()
+            .map(|(index, mut coeff)| {
+                coeff.mul_assign(&inv);
+                (index, coeff.into_repr().as_ref().to_vec())
+            })
+            .collect(),
+    )
+}
+
+fn index(var: &Variable) -> (bool, usize) {
+    match var.get_unchecked() {
+        Index::Input(i) => (false, i),
+        Index::Aux(i) => (true, i),
+    }
+}
+
+/// Passes everything but the implied linear constraints on to `inner`.
+struct LinearMergeCS<'a, E: ScalarEngine, CS: ConstraintSystem<E>> {
+    inner: &'a mut CS,
+    /// The normalized `L` of every linear constraint passed on.
+    #[allow(clippy::type_complexity)]
+    seen: HashSet<Vec<((bool, usize), Vec<u64>)>>,
+    removed: usize,
+}
+
+impl<'a, E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E>
+    for LinearMergeCS<'a, E, CS>
+{
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        if let Some(lc) = linear_form::<E, CS>(&a, &b, &c) {
+            let implied = match normalized(&lc) {
+                None => true,
+                Some(key) => !self.seen.insert(key),
+            };
+            if implied {
+                self.removed += 1;
+                return;
+            }
+        }
+
+        self.inner.enforce(annotation, |_| a, |_| b, |_| c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.get_root().push_namespace(name_fn);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.inner.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::num::AllocatedNum;
+    use crate::util_cs::test_cs::TestConstraintSystem;
+
+    /// `x^2 = y` and `x + y = z`, the latter enforced three times, once scaled, and
+    /// some constraints over constants.
+    struct Redundant {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Redundant {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let y = x.square(cs.namespace(|| "y"))?;
+            let z = x.add(cs.namespace(|| "z"), &y)?;
+            z.inputize(cs.namespace(|| "z input"))?;
+
+            let two = Fr::from_str("2").unwrap();
+            cs.enforce(
+                || "z again",
+                |lc| lc + x.get_variable() + y.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + z.get_variable(),
+            );
+            cs.enforce(
+                || "twice z",
+                |lc| lc + (two, CS::one()),
+                |lc| lc + x.get_variable() + y.get_variable(),
+                |lc| lc + (two, z.get_variable()),
+            );
+
+            // 1 = 1 and true = true.
+            cs.enforce(
+                || "one",
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+            Boolean::enforce_equal(
+                cs.namespace(|| "constants"),
+                &Boolean::constant(true),
+                &Boolean::constant(true),
+            )?;
+
+            // A bit is not linear, and z = 2x is not implied.
+            AllocatedBit::alloc(cs.namespace(|| "bit"), self.x.map(|_| true))?;
+            cs.enforce(
+                || "z is 2x",
+                |lc| lc + (two, x.get_variable()),
+                |lc| lc + CS::one(),
+                |lc| lc + z.get_variable(),
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_merge_linear_constraints() {
+        for &(x, satisfied) in &[("1", true), ("2", false)] {
+            let x = Some(Fr::from_str(x).unwrap());
+
+            let mut full = TestConstraintSystem::<Bls12>::new();
+            Redundant { x }.synthesize(&mut full).unwrap();
+            assert_eq!(full.is_satisfied(), satisfied);
+
+            let mut merged = TestConstraintSystem::<Bls12>::new();
+            let removed = merge_linear_constraints(Redundant { x }, &mut merged).unwrap();
+            assert_eq!(merged.is_satisfied(), satisfied);
+
+            assert_eq!(removed, 3);
+            assert_eq!(merged.num_constraints(), full.num_constraints() - 3);
+            assert_eq!(merged.num_inputs(), full.num_inputs());
+        }
+
+        // The constraints keep their names.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        merge_linear_constraints(
+            Redundant {
+                x: Some(Fr::from_str("2").unwrap()),
+            },
+            &mut cs,
+        )
+        .unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("z is 2x"));
+        assert_eq!(cs.get("y/squared num"), Fr::from_str("4").unwrap());
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use crate::bls::Engine;
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// A constraint, reduced to the variables occurring in it.
+#[derive(Debug)]
+struct MockConstraint {
+    a: Vec<Index>,
+    b: Vec<Index>,
+    c: Vec<Index>,
+    name: String,
+}
+
+/// Constraint system recording the structure of a circuit, without evaluating any
+/// assignments.
+///
+/// Witness closures are never called, so synthesis is about as fast as building the
+/// linear combinations. This makes it suitable for checking the shape of a circuit over
+/// many random parameters, while `TestConstraintSystem` checks the witness.
+pub struct MockConstraintSystem<E: Engine> {
+    current_namespace: Vec<String>,
+    constraints: Vec<MockConstraint>,
+    inputs: Vec<String>,
+    aux: Vec<String>,
+    _e: PhantomData<E>,
+}
+
+impl<E: Engine> Default for MockConstraintSystem<E> {
+    fn default() -> Self {
+        MockConstraintSystem {
+            current_namespace: vec![],
+            constraints: vec![],
+            inputs: vec![String::from("ONE")],
+            aux: vec![],
+            _e: PhantomData,
+        }
+    }
+}
+
+impl<E: Engine> MockConstraintSystem<E> {
+    pub fn new() -> Self {
+        MockConstraintSystem::default()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux.len()
+    }
+
+    /// Checks the constraint graph: every allocated variable is used by some constraint,
+    /// and no variable is defined in terms of itself. See `unused_variables` and
+    /// `find_cycle`.
+    pub fn is_satisfied_symbolically(&self) -> bool {
+        self.unused_variables().is_empty() && self.find_cycle().is_none()
+    }
+
+    /// Paths of the variables, other than `ONE`, that do not occur in any constraint.
+    pub fn unused_variables(&self) -> Vec<&str> {
+        let mut used = HashSet::new();
+        for constraint in &self.constraints {
+            used.extend(
+                constraint
+                    .a
+                    .iter()
+                    .chain(&constraint.b)
+                    .chain(&constraint.c),
+            );
+        }
+
+        let inputs = self
+            .inputs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(i, _)| !used.contains(&Index::Input(*i)));
+        let aux = self
+            .aux
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !used.contains(&Index::Aux(*i)));
+
+        inputs.chain(aux).map(|(_, path)| path.as_str()).collect()
+    }
+
+    /// Looks for a cycle among the variables defined by constraints, returning the paths
+    /// of the variables along it.
+    ///
+    /// A constraint `A * B = C` defines the most recently allocated auxiliary variable
+    /// of `C` which does not occur in `A` or `B`, in terms of all other variables of the
+    /// constraint. This matches gadgets allocating a result and then constraining it.
+    /// Constraints defining nothing, like boolean constraints, only check values.
+    pub fn find_cycle(&self) -> Option<Vec<&str>> {
+        let mut dependencies = vec![Vec::new(); self.aux.len()];
+        for constraint in &self.constraints {
+            if let Some(defined) = defined_variable(constraint) {
+                dependencies[defined].extend(
+                    constraint
+                        .a
+                        .iter()
+                        .chain(&constraint.b)
+                        .chain(&constraint.c)
+                        .filter_map(|index| match *index {
+                            Index::Aux(i) if i != defined => Some(i),
+                            _ => None,
+                        }),
+                );
+            }
+        }
+
+        // Iterative depth first search, 0 = unvisited, 1 = on the stack, 2 = done.
+        let mut state = vec![0u8; self.aux.len()];
+        for root in 0..self.aux.len() {
+            if state[root] != 0 {
+                continue;
+            }
+
+            let mut stack = vec![(root, 0)];
+            state[root] = 1;
+            while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+                if let Some(&dependency) = dependencies[node].get(*next) {
+                    *next += 1;
+                    match state[dependency] {
+                        0 => {
+                            state[dependency] = 1;
+                            stack.push((dependency, 0));
+                        }
+                        1 => {
+                            let start = stack
+                                .iter()
+                                .position(|&(n, _)| n == dependency)
+                                .expect("visited nodes are on the stack");
+                            return Some(
+                                stack[start..]
+                                    .iter()
+                                    .map(|&(n, _)| self.aux[n].as_str())
+                                    .collect(),
+                            );
+                        }
+                        _ => {}
+                    }
+                } else {
+                    state[node] = 2;
+                    stack.pop();
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Names of the constraints, in order.
+    pub fn constraint_names(&self) -> impl Iterator<Item = &str> {
+        self.constraints.iter().map(|c| c.name.as_str())
+    }
+}
+
+fn defined_variable(constraint: &MockConstraint) -> Option<usize> {
+    constraint
+        .c
+        .iter()
+        .filter_map(|index| match *index {
+            Index::Aux(i) => Some(i),
+            Index::Input(_) => None,
+        })
+        .filter(|&i| {
+            !constraint.a.contains(&Index::Aux(i)) && !constraint.b.contains(&Index::Aux(i))
+        })
+        .max()
+}
+
+fn variables<E: Engine>(lc: &LinearCombination<E>) -> Vec<Index> {
+    let mut vars = lc
+        .iter()
+        .map(|(var, _)| var.get_unchecked())
+        .collect::<Vec<_>>();
+    vars.sort_unstable_by_key(|index| match *index {
+        Index::Input(i) => (0, i),
+        Index::Aux(i) => (1, i),
+    });
+    vars.dedup();
+    vars
+}
+
+fn compute_path(ns: &[String], this: &str) -> String {
+    assert!(
+        !this.chars().any(|a| a == '/'),
+        "'/' is not allowed in names"
+    );
+
+    if ns.is_empty() {
+        return this.to_string();
+    }
+
+    let name = ns.join("/");
+    format!("{}/{}", name, this)
+}
+
+impl<E: Engine> ConstraintSystem<E> for MockConstraintSystem<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // don't invoke f, only the structure is recorded
+        let path = compute_path(&self.current_namespace, &annotation().into());
+        self.aux.push(path);
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // don't invoke f, only the structure is recorded
+        let path = compute_path(&self.current_namespace, &annotation().into());
+        self.inputs.push(path);
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let name = compute_path(&self.current_namespace, &annotation().into());
+
+        self.constraints.push(MockConstraint {
+            a: variables(&a(LinearCombination::zero())),
+            b: variables(&b(LinearCombination::zero())),
+            c: variables(&c(LinearCombination::zero())),
+            name,
+        });
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current_namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::num::AllocatedNum;
+    use ff::Field;
+
+    #[test]
+    fn test_mock_cs_does_not_evaluate() {
+        let mut cs = MockConstraintSystem::<Bls12>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || -> Result<Fr, _> {
+            panic!("witness evaluated")
+        })
+        .unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::one())).unwrap();
+        let c = a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+        c.inputize(cs.namespace(|| "c")).unwrap();
+
+        assert!(c.get_value().is_none());
+        assert_eq!(cs.num_constraints(), 2);
+        assert_eq!(cs.num_inputs(), 2);
+        assert_eq!(cs.num_aux(), 3);
+        assert!(cs.is_satisfied_symbolically());
+        assert_eq!(
+            cs.constraint_names().collect::<Vec<_>>(),
+            vec![
+                "a * b/multiplication constraint",
+                "c/enforce input is correct"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_cs_unused_variables() {
+        let mut cs = MockConstraintSystem::<Bls12>::new();
+
+        let a = cs.alloc(|| "a", || Ok(Fr::one())).unwrap();
+        cs.alloc(|| "unused", || Ok(Fr::one())).unwrap();
+        cs.alloc_input(|| "unused input", || Ok(Fr::one())).unwrap();
+        cs.enforce(
+            || "a is boolean",
+            |lc| lc + a,
+            |lc| lc + CS::one() - a,
+            |lc| lc,
+        );
+
+        assert_eq!(cs.unused_variables(), vec!["unused input", "unused"]);
+        assert!(cs.find_cycle().is_none());
+        assert!(!cs.is_satisfied_symbolically());
+    }
+
+    type CS = MockConstraintSystem<Bls12>;
+
+    #[test]
+    fn test_mock_cs_cycles() {
+        let mut cs = MockConstraintSystem::<Bls12>::new();
+
+        let x = cs.alloc(|| "x", || Ok(Fr::one())).unwrap();
+        let y = cs.alloc(|| "y", || Ok(Fr::one())).unwrap();
+        let z = cs.alloc(|| "z", || Ok(Fr::one())).unwrap();
+
+        // z is defined by x and y, which is fine.
+        cs.enforce(|| "z = x * y", |lc| lc + x, |lc| lc + y, |lc| lc + z);
+        assert!(cs.is_satisfied_symbolically());
+
+        // Defining x by z closes the loop x -> z -> x.
+        cs.enforce(|| "x = z", |lc| lc + z, |lc| lc + CS::one(), |lc| lc + x);
+
+        let cycle = cs.find_cycle().unwrap();
+        assert_eq!(cycle.len(), 2);
+        for path in &["x", "z"] {
+            assert!(cycle.contains(path));
+        }
+        assert!(!cs.is_satisfied_symbolically());
+    }
+}
@@ -1,3 +1,11 @@
 pub mod bench_cs;
+pub mod budget_cs;
+#[cfg(feature = "debug-constraints")]
+pub mod debug_cs;
+pub mod inspector_cs;
+pub mod merge_cs;
 pub mod metric_cs;
+pub mod mock_cs;
+#[cfg(feature = "profile")]
+pub mod profile_cs;
 pub mod test_cs;
@@ -0,0 +1,212 @@
+//! Measuring where the synthesis of a circuit spends its time.
+//!
+//! [`ProfilingCS`] wraps another constraint system and times each `alloc`, including
+//! the computation of the value, and each `enforce`, including building the linear
+//! combinations, by the source location of the gadget calling it. The locations come
+//! from `#[track_caller]` on the [`Namespace`](crate::Namespace) and `&mut` forwarding
+//! implementations, so the `profile` feature, which this module needs, enables
+//! `debug-constraints`.
+
+use std::collections::HashMap;
+use std::panic::Location;
+use std::time::{Duration, Instant};
+
+use ff::ScalarEngine;
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+/// The time spent in the allocations and constraints of one source location.
+#[derive(Clone, Debug)]
+pub struct ConstraintTimeEntry {
+    pub location: &'static Location<'static>,
+    pub allocations: usize,
+    pub constraints: usize,
+    pub total: Duration,
+}
+
+/// Times the allocations and constraints of a circuit while synthesizing it into
+/// `CS`.
+pub struct ProfilingCS<E: ScalarEngine, CS: ConstraintSystem<E>> {
+    inner: CS,
+    entries: HashMap<&'static Location<'static>, ConstraintTimeEntry>,
+    _marker: std::marker::PhantomData<fn() -> E>,
+}
+
+impl<E: ScalarEngine, CS: ConstraintSystem<E>> ProfilingCS<E, CS> {
+    pub fn new(inner: CS) -> Self {
+        ProfilingCS {
+            inner,
+            entries: HashMap::new(),
+            _marker: Default::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &CS {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.inner
+    }
+
+    /// The time spent per source location, the slowest first.
+    pub fn report(&self) -> Vec<ConstraintTimeEntry> {
+        let mut report = self.entries.values().cloned().collect::<Vec<_>>();
+        report.sort_by(|a, b| b.total.cmp(&a.total));
+
+        report
+    }
+
+    fn entry(&mut self, location: &'static Location<'static>) -> &mut ConstraintTimeEntry {
+        self.entries
+            .entry(location)
+            .or_insert_with(|| ConstraintTimeEntry {
+                location,
+                allocations: 0,
+                constraints: 0,
+                total: Duration::default(),
+            })
+    }
+}
+
+impl<E: ScalarEngine, CS: ConstraintSystem<E>> ConstraintSystem<E> for ProfilingCS<E, CS> {
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    #[track_caller]
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let location = Location::caller();
+        let start = Instant::now();
+        let variable = self.inner.alloc(annotation, f);
+
+        let entry = self.entry(location);
+        entry.allocations += 1;
+        entry.total += start.elapsed();
+
+        variable
+    }
+
+    #[track_caller]
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let location = Location::caller();
+        let start = Instant::now();
+        let variable = self.inner.alloc_input(annotation, f);
+
+        let entry = self.entry(location);
+        entry.allocations += 1;
+        entry.total += start.elapsed();
+
+        variable
+    }
+
+    #[track_caller]
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let location = Location::caller();
+        let start = Instant::now();
+        self.inner.enforce(annotation, a, b, c);
+
+        let entry = self.entry(location);
+        entry.constraints += 1;
+        entry.total += start.elapsed();
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.get_root().push_namespace(name_fn);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.inner.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::num::AllocatedNum;
+    use crate::util_cs::test_cs::TestConstraintSystem;
+    use ff::PrimeField;
+
+    #[test]
+    fn test_profiling_cs() {
+        let mut cs = ProfilingCS::new(TestConstraintSystem::<Bls12>::new());
+
+        let slow = || {
+            std::thread::sleep(Duration::from_millis(10));
+            Ok(Fr::from_str("3").unwrap())
+        };
+        let one = TestConstraintSystem::<Bls12>::one();
+        let line = line!() + 3;
+        for i in 0..3 {
+            let mut cs = cs.namespace(|| format!("round {}", i));
+            let x = cs.alloc(|| "x", slow).unwrap();
+            cs.enforce(|| "x is 3", |lc| lc + x, |lc| lc + one, |lc| lc + x);
+        }
+        let a =
+            AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("2").unwrap())).unwrap();
+        a.square(cs.namespace(|| "square")).unwrap();
+
+        let report = cs.report();
+        assert!(report.windows(2).all(|w| w[0].total >= w[1].total));
+
+        // The slow allocations of the loop come first, and those of the gadget are
+        // attributed to the gadget, not to the test calling it.
+        assert_eq!(report[0].location.file(), file!());
+        assert_eq!(report[0].location.line(), line);
+        assert_eq!(report[0].allocations, 3);
+        assert!(report[0].total >= Duration::from_millis(30));
+
+        let enforce = report
+            .iter()
+            .find(|entry| entry.location.line() == line + 1)
+            .unwrap();
+        assert_eq!(enforce.constraints, 3);
+        assert_eq!(enforce.allocations, 0);
+
+        let num_rs = report
+            .iter()
+            .filter(|entry| entry.location.file().ends_with("num.rs"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            num_rs.iter().map(|entry| entry.allocations).sum::<usize>(),
+            2
+        );
+        assert_eq!(
+            num_rs.iter().map(|entry| entry.constraints).sum::<usize>(),
+            1
+        );
+
+        let inner = cs.into_inner();
+        assert_eq!(inner.num_constraints(), 4);
+        assert!(inner.is_satisfied());
+    }
+}
@@ -0,0 +1,399 @@
+//! Witness extension: filling in the auxiliary variables a circuit's constraints
+//! determine from the others.
+//!
+//! Many auxiliary variables are defined by a single constraint, like the product
+//! `a * b = c` or the sum `(a + b) * 1 = c`, whose `C` is the first use of the
+//! variable. A [`WitnessPlan`] finds these definitions once per circuit, and orders
+//! them into layers, each of which only depends on the variables the caller assigns,
+//! the inputs and the earlier layers. Extending a witness then computes each layer in
+//! parallel. The variables no constraint defines this way, like the bits of a
+//! decomposition, are [free](WitnessPlan::free_aux) and are assigned by the caller.
+//!
+//! The plan only depends on the shape of the circuit, so it is built once, e.g. next
+//! to the circuit's parameters, and used for every witness.
+
+use ff::{Field, ScalarEngine};
+use rayon::prelude::*;
+
+use crate::r1cs::R1CSMatrixCSR;
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// Layers with fewer definitions than this are computed on the calling thread, as a
+/// deep chain of single definitions gains nothing from rayon.
+const MIN_PARALLEL_LAYER: usize = 64;
+
+/// An auxiliary variable defined by the constraint `A * B = C` at `constraint`, and
+/// the inverse of its coefficient in `C`.
+#[derive(Clone, Debug)]
+struct Definition<E: ScalarEngine> {
+    aux: usize,
+    constraint: usize,
+    coeff_inv: E::Fr,
+}
+
+/// The layers of the auxiliary variables the constraints of a circuit define.
+pub struct WitnessPlan<E: ScalarEngine> {
+    a: R1CSMatrixCSR<E>,
+    b: R1CSMatrixCSR<E>,
+    c: R1CSMatrixCSR<E>,
+    num_inputs: usize,
+    num_aux: usize,
+    layers: Vec<Vec<Definition<E>>>,
+    free: Vec<usize>,
+}
+
+impl<E: ScalarEngine> WitnessPlan<E> {
+    /// The plan of `circuit`, which is synthesized without calling any of its
+    /// assignment closures, like by the parameter generator.
+    pub fn from_circuit<C: Circuit<E>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut shape = ShapeAssembly {
+            num_inputs: 0,
+            num_aux: 0,
+            a: R1CSMatrixCSR::new(),
+            b: R1CSMatrixCSR::new(),
+            c: R1CSMatrixCSR::new(),
+        };
+        shape.alloc_input(|| "", || Ok(E::Fr::one()))?;
+        circuit.synthesize(&mut shape)?;
+
+        Ok(Self::from_matrices(
+            shape.a,
+            shape.b,
+            shape.c,
+            shape.num_inputs,
+            shape.num_aux,
+        ))
+    }
+
+    /// The plan of the constraints with the rows of `a`, `b` and `c`.
+    ///
+    /// Constraint `i` defines the auxiliary variable of `C` that no earlier
+    /// constraint uses, if there is only one such variable and it is not also in `A`
+    /// or `B`. The constraints are taken in order, so a variable used before the
+    /// constraint that could define it is free.
+    pub fn from_matrices(
+        a: R1CSMatrixCSR<E>,
+        b: R1CSMatrixCSR<E>,
+        c: R1CSMatrixCSR<E>,
+        num_inputs: usize,
+        num_aux: usize,
+    ) -> Self {
+        assert_eq!(a.num_rows(), b.num_rows());
+        assert_eq!(a.num_rows(), c.num_rows());
+
+        // The layer of every defined variable, counting from one, and zero for free
+        // ones.
+        let mut level = vec![0; num_aux];
+        let mut seen = vec![false; num_aux];
+        let mut layers: Vec<Vec<Definition<E>>> = vec![];
+
+        let aux_terms = |matrix: &R1CSMatrixCSR<E>, i: usize| {
+            let (columns, values) = matrix.row(i);
+            columns
+                .iter()
+                .zip(values.iter())
+                .filter_map(|(var, coeff)| match var.get_unchecked() {
+                    Index::Aux(j) => Some((j, *coeff)),
+                    Index::Input(_) => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for i in 0..c.num_rows() {
+            let a_terms = aux_terms(&a, i);
+            let b_terms = aux_terms(&b, i);
+            let c_terms = aux_terms(&c, i);
+
+            let in_a_or_b = |j: usize| a_terms.iter().chain(&b_terms).any(|&(k, _)| k == j);
+            let mut candidates = c_terms
+                .iter()
+                .filter(|&&(j, coeff)| !seen[j] && !coeff.is_zero() && !in_a_or_b(j));
+            let definition = match (candidates.next(), candidates.next()) {
+                (Some(&(j, coeff)), None) => Some((j, coeff)),
+                _ => None,
+            };
+
+            if let Some((j, coeff)) = definition {
+                let depth = a_terms
+                    .iter()
+                    .chain(&b_terms)
+                    .chain(&c_terms)
+                    .filter(|&&(k, _)| k != j)
+                    .map(|&(k, _)| level[k])
+                    .max()
+                    .unwrap_or(0);
+
+                level[j] = depth + 1;
+                if layers.len() <= depth {
+                    layers.push(vec![]);
+                }
+                layers[depth].push(Definition {
+                    aux: j,
+                    constraint: i,
+                    coeff_inv: coeff.inverse().unwrap(),
+                });
+            }
+
+            for &(j, _) in a_terms.iter().chain(&b_terms).chain(&c_terms) {
+                seen[j] = true;
+            }
+        }
+
+        let free = (0..num_aux).filter(|&j| level[j] == 0).collect();
+
+        WitnessPlan {
+            a,
+            b,
+            c,
+            num_inputs,
+            num_aux,
+            layers,
+            free,
+        }
+    }
+
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The number of auxiliary variables the plan computes.
+    pub fn num_defined(&self) -> usize {
+        self.num_aux - self.free.len()
+    }
+
+    /// The auxiliary variables the caller assigns, in order.
+    pub fn free_aux(&self) -> &[usize] {
+        &self.free
+    }
+
+    /// Computes the defined auxiliary variables of `aux_assignment` from the free ones
+    /// and `input_assignment`, whose first element is one. Whatever the defined
+    /// variables held before is overwritten.
+    pub fn extend(&self, input_assignment: &[E::Fr], aux_assignment: &mut [E::Fr]) {
+        assert_eq!(input_assignment.len(), self.num_inputs);
+        assert_eq!(aux_assignment.len(), self.num_aux);
+
+        for layer in &self.layers {
+            let aux = &*aux_assignment;
+            let compute = |d: &Definition<E>| {
+                // A * B - C is what the term of the variable in C lacks.
+                let mut value = self.a.evaluate_row(d.constraint, input_assignment, aux);
+                value.mul_assign(&self.b.evaluate_row(d.constraint, input_assignment, aux));
+                value.sub_assign(&self.c.evaluate_row(d.constraint, input_assignment, aux));
+                value.mul_assign(&d.coeff_inv);
+                value.add_assign(&aux[d.aux]);
+                value
+            };
+
+            let values = if layer.len() < MIN_PARALLEL_LAYER {
+                layer.iter().map(compute).collect::<Vec<_>>()
+            } else {
+                layer.par_iter().map(compute).collect::<Vec<_>>()
+            };
+            for (d, value) in layer.iter().zip(values) {
+                aux_assignment[d.aux] = value;
+            }
+        }
+    }
+}
+
+/// Records the constraints of a circuit, without its assignment.
+struct ShapeAssembly<E: ScalarEngine> {
+    num_inputs: usize,
+    num_aux: usize,
+    a: R1CSMatrixCSR<E>,
+    b: R1CSMatrixCSR<E>,
+    c: R1CSMatrixCSR<E>,
+}
+
+impl<E: ScalarEngine> ConstraintSystem<E> for ShapeAssembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.num_aux += 1;
+
+        Ok(Variable(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.num_inputs += 1;
+
+        Ok(Variable(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.a.push_row(&a(LinearCombination::zero()));
+        self.b.push_row(&b(LinearCombination::zero()));
+        self.c.push_row(&c(LinearCombination::zero()));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use crate::gadgets::boolean::AllocatedBit;
+    use crate::gadgets::num::AllocatedNum;
+    use ff::PrimeField;
+
+    /// `width` chains of `depth` steps `y = y^2 + x_j`, starting at `y = x_j`, for
+    /// free `x_j = x + j`, and a bit that is free as it is only constrained to be
+    /// one.
+    struct Chains {
+        x: Option<Fr>,
+        width: usize,
+        depth: usize,
+    }
+
+    impl Circuit<Bls12> for Chains {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            for j in 0..self.width {
+                let mut cs = cs.namespace(|| format!("chain {}", j));
+                let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                    let mut x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                    x.add_assign(&Fr::from_str(&j.to_string()).unwrap());
+                    Ok(x)
+                })?;
+
+                let mut y = x.clone();
+                for i in 0..self.depth {
+                    let mut cs = cs.namespace(|| format!("step {}", i));
+                    y = y
+                        .square(cs.namespace(|| "square"))?
+                        .add(cs.namespace(|| "add"), &x)?;
+                }
+                y.inputize(cs.namespace(|| "output"))?;
+            }
+
+            AllocatedBit::alloc(cs.namespace(|| "bit"), self.x.map(|_| true))?;
+
+            Ok(())
+        }
+    }
+
+    fn expected(x: Fr, width: usize, depth: usize) -> (Vec<Fr>, Vec<Fr>) {
+        let mut inputs = vec![Fr::one()];
+        let mut aux = vec![];
+        for j in 0..width {
+            let mut x = x;
+            x.add_assign(&Fr::from_str(&j.to_string()).unwrap());
+            aux.push(x);
+
+            let mut y = x;
+            for _ in 0..depth {
+                y.square();
+                aux.push(y);
+                y.add_assign(&x);
+                aux.push(y);
+            }
+            inputs.push(y);
+        }
+        aux.push(Fr::one());
+
+        (inputs, aux)
+    }
+
+    #[test]
+    fn test_witness_plan_layers() {
+        let plan = WitnessPlan::<Bls12>::from_circuit(Chains {
+            x: None,
+            width: 3,
+            depth: 5,
+        })
+        .unwrap();
+
+        // Each step is a square and a sum, one layer each, for all chains at once.
+        assert_eq!(plan.num_layers(), 10);
+        assert!(plan.layers.iter().all(|layer| layer.len() == 3));
+        assert_eq!(plan.num_defined(), 30);
+        // The x of each chain and the bit.
+        assert_eq!(plan.free_aux(), &[0, 11, 22, 33]);
+    }
+
+    #[test]
+    fn test_witness_extension() {
+        let x = Fr::from_str("7").unwrap();
+
+        for &(width, depth) in &[(1, 1), (1, 50), (100, 2), (5, 5)] {
+            let plan = WitnessPlan::<Bls12>::from_circuit(Chains {
+                x: None,
+                width,
+                depth,
+            })
+            .unwrap();
+            let (inputs, expected) = expected(x, width, depth);
+
+            // Only the free variables, and garbage elsewhere.
+            let mut aux = vec![Fr::from_str("12345").unwrap(); expected.len()];
+            for &j in plan.free_aux() {
+                aux[j] = expected[j];
+            }
+            plan.extend(&inputs, &mut aux);
+
+            assert_eq!(aux, expected, "{} chains of {} steps", width, depth);
+        }
+    }
+
+    #[test]
+    fn test_witness_plan_scaled_definition() {
+        // v0 * v0 = 3 * v1 defines v1 = v0^2 / 3, and v0 * v1 = v1 + v2 defines
+        // v2 = v0 * v1 - v1, as v1 is not new.
+        let v = |i| Variable(Index::Aux(i));
+        let three = Fr::from_str("3").unwrap();
+        let lc = |terms: &[(Fr, usize)]| {
+            terms
+                .iter()
+                .fold(LinearCombination::<Bls12>::zero(), |lc, &(coeff, i)| {
+                    lc + (coeff, v(i))
+                })
+        };
+        let one = Fr::one();
+
+        let a = R1CSMatrixCSR::from_lcs(&[lc(&[(one, 0)]), lc(&[(one, 0)])]);
+        let b = R1CSMatrixCSR::from_lcs(&[lc(&[(one, 0)]), lc(&[(one, 1)])]);
+        let c = R1CSMatrixCSR::from_lcs(&[lc(&[(three, 1)]), lc(&[(one, 1), (one, 2)])]);
+        let plan = WitnessPlan::from_matrices(a, b, c, 1, 3);
+        assert_eq!(plan.free_aux(), &[0]);
+        assert_eq!(plan.num_layers(), 2);
+
+        let v0 = Fr::from_str("6").unwrap();
+        let mut aux = vec![v0, Fr::zero(), Fr::zero()];
+        plan.extend(&[one], &mut aux);
+        assert_eq!(aux[1], Fr::from_str("12").unwrap());
+        assert_eq!(aux[2], Fr::from_str("60").unwrap());
+    }
+}
@@ -0,0 +1,85 @@
+//! Proving and verifying from within rayon tasks, with the work running in the pool of
+//! the caller.
+
+use bellperson::bls::{Bls12, Fr};
+use bellperson::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    Parameters, PreparedVerifyingKey,
+};
+use bellperson::multicore::{set_thread_strategy, thread_strategy, ThreadStrategy, Worker};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::Field;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Proves knowledge of a square root of the public input.
+struct SquareRoot {
+    x: Option<Fr>,
+}
+
+impl Circuit<Bls12> for SquareRoot {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+        let y = cs.alloc_input(
+            || "y",
+            || {
+                let mut y = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                y.square();
+                Ok(y)
+            },
+        )?;
+        cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+        Ok(())
+    }
+}
+
+fn prove_and_verify(params: &Parameters<Bls12>, pvk: &PreparedVerifyingKey<Bls12>, seed: u8) {
+    let rng = &mut XorShiftRng::from_seed([
+        seed, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let x = Fr::random(rng);
+    let mut y = x;
+    y.square();
+
+    let proof = create_random_proof(SquareRoot { x: Some(x) }, params, rng).unwrap();
+    assert!(verify_proof(pvk, &proof, &[y]).unwrap());
+}
+
+#[test]
+fn test_prove_and_verify_in_rayon_tasks() {
+    // A small pool, so that blocking any of its threads on work queued to it would
+    // deadlock.
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build_global()
+        .unwrap();
+    set_thread_strategy(ThreadStrategy::CallerPool);
+    assert_eq!(thread_strategy(), ThreadStrategy::CallerPool);
+
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let params = generate_random_parameters(SquareRoot { x: None }, rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let (params, pvk) = (&params, &pvk);
+    rayon::join(
+        || {
+            // Workers run on the threads of the pool they are used from.
+            assert_eq!(Worker::new().num_threads(), 2);
+            rayon::join(
+                || prove_and_verify(params, pvk, 1),
+                || prove_and_verify(params, pvk, 2),
+            )
+        },
+        || {
+            rayon::join(
+                || prove_and_verify(params, pvk, 3),
+                || prove_and_verify(params, pvk, 4),
+            )
+        },
+    );
+}
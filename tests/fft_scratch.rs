@@ -0,0 +1,120 @@
+//! Checks that FFTs with a warm [`FftScratch`] do not allocate memory depending on the
+//! size of the domain.
+#![cfg(not(feature = "gpu"))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use bellperson::bls::{Bls12, Fr};
+use bellperson::domain::{EvaluationDomain, FftScratch, Scalar};
+use bellperson::multicore::{FftConfig, Worker};
+use ff::Field;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Counts the allocations and records the largest one made while tracking is enabled.
+struct CountingAllocator;
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+static LARGEST: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if TRACKING.load(Ordering::SeqCst) {
+            COUNT.fetch_add(1, Ordering::SeqCst);
+            LARGEST.fetch_max(layout.size(), Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if TRACKING.load(Ordering::SeqCst) {
+            COUNT.fetch_add(1, Ordering::SeqCst);
+            LARGEST.fetch_max(new_size, Ordering::SeqCst);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const LOG_SUB_FFTS: u32 = 2;
+
+/// The transforms of the H polynomial of one proof, returning the number of
+/// allocations and the size of the largest one.
+fn transforms(
+    worker: &Worker,
+    domain: &mut EvaluationDomain<Bls12, Scalar<Bls12>>,
+    scratch: Option<&mut FftScratch<Scalar<Bls12>>>,
+) -> (usize, usize) {
+    COUNT.store(0, Ordering::SeqCst);
+    LARGEST.store(0, Ordering::SeqCst);
+    TRACKING.store(true, Ordering::SeqCst);
+
+    match scratch {
+        Some(scratch) => {
+            domain
+                .ifft_with_scratch(worker, &mut None, scratch)
+                .unwrap();
+            domain
+                .coset_fft_with_scratch(worker, &mut None, scratch)
+                .unwrap();
+            domain
+                .icoset_fft_div_z_with_scratch(worker, &mut None, scratch)
+                .unwrap();
+        }
+        None => {
+            domain.ifft(worker, &mut None).unwrap();
+            domain.coset_fft(worker, &mut None).unwrap();
+            domain.icoset_fft_div_z(worker, &mut None).unwrap();
+        }
+    }
+
+    TRACKING.store(false, Ordering::SeqCst);
+    (COUNT.load(Ordering::SeqCst), LARGEST.load(Ordering::SeqCst))
+}
+
+#[test]
+fn test_fft_scratch_allocations() {
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    // Always split the FFTs into the same number of sub-FFTs, independent of the machine.
+    let worker = Worker::with_fft_config(FftConfig {
+        min_parallel_log_size: Some(LOG_SUB_FFTS),
+        max_log_threads_override: Some(LOG_SUB_FFTS),
+        min_four_step_log_size: None,
+    });
+
+    let mut counts = Vec::new();
+    for &log_d in &[10, 14] {
+        let coeffs = (0..1 << log_d)
+            .map(|_| Scalar::<Bls12>(Fr::random(rng)))
+            .collect::<Vec<_>>();
+        let mut domain = EvaluationDomain::from_coeffs(coeffs).unwrap();
+        let sub_fft_bytes = std::mem::size_of::<Fr>() << (log_d - LOG_SUB_FFTS);
+
+        // Without a scratch the sub-FFT buffers are allocated on every FFT.
+        let (_, largest) = transforms(&worker, &mut domain, None);
+        assert!(largest >= sub_fft_bytes);
+
+        // The first transforms fill the scratch, later ones only allocate for the
+        // bookkeeping of the thread pool.
+        let mut scratch = FftScratch::new();
+        transforms(&worker, &mut domain, Some(&mut scratch));
+        let (count, largest) = transforms(&worker, &mut domain, Some(&mut scratch));
+        assert!(largest < sub_fft_bytes);
+        counts.push(count);
+    }
+
+    // A domain 16 times as large does not need more allocations.
+    assert!(counts[1] <= counts[0] + 16);
+}
@@ -0,0 +1,147 @@
+use bellperson::bls::{Bls12, Fr};
+use bellperson::folding::{FoldingScheme, RelaxedR1CSCircuit};
+use bellperson::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::{Field, PrimeField};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Proves knowledge of `x` with `x^3 + x + 5 = y` for the public input `y`.
+#[derive(Clone)]
+struct CubeCircuit {
+    x: Option<Fr>,
+}
+
+impl Circuit<Bls12> for CubeCircuit {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x_value = self.x;
+        let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        let x_sq_value = x_value.map(|mut x| {
+            x.square();
+            x
+        });
+        let x_sq = cs.alloc(
+            || "x^2",
+            || x_sq_value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(|| "x^2 = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x_sq);
+
+        let x_cu_value = x_sq_value.map(|mut x_sq| {
+            x_sq.mul_assign(&x_value.unwrap());
+            x_sq
+        });
+        let x_cu = cs.alloc(
+            || "x^3",
+            || x_cu_value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(
+            || "x^3 = x^2 * x",
+            |lc| lc + x_sq,
+            |lc| lc + x,
+            |lc| lc + x_cu,
+        );
+
+        let five = Fr::from_str("5").unwrap();
+        let y = cs.alloc_input(
+            || "y",
+            || {
+                let mut y = x_cu_value.ok_or(SynthesisError::AssignmentMissing)?;
+                y.add_assign(&x_value.unwrap());
+                y.add_assign(&five);
+                Ok(y)
+            },
+        )?;
+        cs.enforce(
+            || "y = x^3 + x + 5",
+            |lc| lc + x_cu + x + (five, CS::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + y,
+        );
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_folding_and_final_proof() {
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let scheme = FoldingScheme::<Bls12>::setup(CubeCircuit { x: None }, rng).unwrap();
+
+    let mut acc = scheme
+        .relax(CubeCircuit {
+            x: Some(Fr::random(rng)),
+        })
+        .unwrap();
+    assert!(scheme.is_satisfied(&acc).unwrap());
+    let mut acc_instance = acc.instance.clone();
+
+    for _ in 0..4 {
+        let next = scheme
+            .relax(CubeCircuit {
+                x: Some(Fr::random(rng)),
+            })
+            .unwrap();
+        assert!(scheme.is_satisfied(&next).unwrap());
+
+        let (folded, comm_t) = scheme.fold(&acc, &next).unwrap();
+        assert!(scheme.is_satisfied(&folded).unwrap());
+
+        // The verifier arrives at the same instance without the witnesses.
+        acc_instance = scheme.fold_instances(&acc_instance, &next.instance, &comm_t);
+        assert!(acc_instance == folded.instance);
+
+        acc = folded;
+    }
+    assert!(acc.instance.u != Fr::one());
+
+    // Folding in an instance which is not satisfied yields an unsatisfied accumulator.
+    let mut bad = scheme
+        .relax(CubeCircuit {
+            x: Some(Fr::random(rng)),
+        })
+        .unwrap();
+    bad.instance.x[0].add_assign(&Fr::one());
+    let (bad_acc, _) = scheme.fold(&acc, &bad).unwrap();
+    assert!(!scheme.is_satisfied(&bad_acc).unwrap());
+
+    // Prove the accumulated instance with Groth16.
+    let params = generate_random_parameters::<Bls12, _, _>(
+        RelaxedR1CSCircuit::new(scheme.shape(), None),
+        rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let proof = create_random_proof(
+        RelaxedR1CSCircuit::new(scheme.shape(), Some(&acc)),
+        &params,
+        rng,
+    )
+    .unwrap();
+
+    let inputs = scheme.public_inputs(&acc_instance, &acc.w, &acc.e).unwrap();
+    assert!(verify_proof(&pvk, &proof, &inputs).unwrap());
+
+    let mut wrong_inputs = inputs;
+    wrong_inputs[0].add_assign(&Fr::one());
+    assert!(!verify_proof(&pvk, &proof, &wrong_inputs).unwrap());
+
+    // A witness and error vector satisfying the constraints, but not opening the
+    // commitments of the accumulated instance, are rejected before the proof is checked.
+    assert!(matches!(
+        scheme.public_inputs(&acc_instance, &bad_acc.w, &bad_acc.e),
+        Err(SynthesisError::Unsatisfiable)
+    ));
+    let mut other_e = acc.e.clone();
+    other_e[0].add_assign(&Fr::one());
+    assert!(scheme
+        .public_inputs(&acc_instance, &acc.w, &other_e)
+        .is_err());
+}
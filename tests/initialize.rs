@@ -0,0 +1,27 @@
+//! Setting the thread count of the global pools, which needs a process of its own as
+//! the count can only be set before the pools are used.
+
+use bellperson::multicore::{current_threads, initialize, AlreadyInitialized, InitSite, Worker};
+
+#[test]
+fn test_initialize() {
+    let line = line!() + 1;
+    initialize(3).unwrap();
+    assert_eq!(current_threads(), 3);
+    assert_eq!(Worker::new().num_threads(), 3);
+
+    // The first call wins and the error points at it.
+    let err = initialize(5).unwrap_err();
+    match err {
+        AlreadyInitialized {
+            threads: 3,
+            site: InitSite::Initialize(location),
+        } => {
+            assert_eq!(location.file(), file!());
+            assert_eq!(location.line(), line);
+        }
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert!(err.to_string().contains(file!()));
+    assert_eq!(current_threads(), 3);
+}
@@ -0,0 +1,144 @@
+//! Proves from memory mapped parameters and checks that the bases are streamed out of the
+//! mmap, instead of being collected into a `Vec` first.
+#![cfg(not(feature = "gpu"))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::File;
+use std::io::BufWriter;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use bellperson::bls::{Bls12, Engine, Fr};
+use bellperson::groth16::{
+    create_random_proof, generate_random_parameters, verify_proof, Parameters,
+};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::Field;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Records the largest allocation made while tracking is enabled.
+struct CountingAllocator;
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static LARGEST: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if TRACKING.load(Ordering::SeqCst) {
+            LARGEST.fetch_max(layout.size(), Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if TRACKING.load(Ordering::SeqCst) {
+            LARGEST.fetch_max(new_size, Ordering::SeqCst);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const NUM_SQUARINGS: usize = 1000;
+
+/// Proves knowledge of `x` such that `x^(2^NUM_SQUARINGS)` is the public input.
+#[derive(Clone)]
+struct RepeatedSquaring {
+    x: Option<Fr>,
+}
+
+impl Circuit<Bls12> for RepeatedSquaring {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut value = self.x;
+        let mut var = cs.alloc(|| "x", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        for i in 0..NUM_SQUARINGS {
+            let squared = value.map(|mut v| {
+                v.square();
+                v
+            });
+            let squared_var = if i == NUM_SQUARINGS - 1 {
+                cs.alloc_input(
+                    || "output",
+                    || squared.ok_or(SynthesisError::AssignmentMissing),
+                )?
+            } else {
+                cs.alloc(
+                    || format!("square {}", i),
+                    || squared.ok_or(SynthesisError::AssignmentMissing),
+                )?
+            };
+
+            cs.enforce(
+                || format!("square {} constraint", i),
+                |lc| lc + var,
+                |lc| lc + var,
+                |lc| lc + squared_var,
+            );
+
+            value = squared;
+            var = squared_var;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_prove_from_mapped_params_streams_bases() {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let params =
+        generate_random_parameters::<Bls12, _, _>(RepeatedSquaring { x: None }, &mut rng).unwrap();
+
+    let path = std::env::temp_dir().join(format!(
+        "bellperson-mapped-params-{}.params",
+        std::process::id()
+    ));
+    params
+        .write(BufWriter::new(File::create(&path).unwrap()))
+        .unwrap();
+    let mapped = Parameters::<Bls12>::build_mapped_parameters(path.clone(), false).unwrap();
+
+    // A full `Vec` of even the smallest query would be at least this large.
+    let smallest_query = [mapped.h.len(), mapped.l.len(), mapped.a.len()]
+        .iter()
+        .cloned()
+        .min()
+        .unwrap();
+    let full_vec = smallest_query * mem::size_of::<<Bls12 as Engine>::G1Affine>();
+
+    let x = Fr::random(&mut rng);
+    let mut output = x;
+    for _ in 0..NUM_SQUARINGS {
+        output.square();
+    }
+
+    LARGEST.store(0, Ordering::SeqCst);
+    TRACKING.store(true, Ordering::SeqCst);
+    let proof = create_random_proof(RepeatedSquaring { x: Some(x) }, &mapped, &mut rng);
+    TRACKING.store(false, Ordering::SeqCst);
+    let proof = proof.unwrap();
+
+    let largest = LARGEST.load(Ordering::SeqCst);
+    assert!(
+        largest < full_vec,
+        "largest allocation while proving was {} bytes, a full vec of bases is {} bytes",
+        largest,
+        full_vec
+    );
+
+    assert!(verify_proof(&mapped.pvk, &proof, &[output]).unwrap());
+
+    std::fs::remove_file(path).unwrap();
+}
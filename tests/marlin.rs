@@ -0,0 +1,114 @@
+use bellperson::bls::{Bls12, Fr};
+use bellperson::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+};
+use bellperson::marlin::{verify_marlin_proof, MarlinIndex, MarlinProver, MarlinSRS};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::{Field, PrimeField};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Proves knowledge of `a` and `b` with `a * b = c` for the public input `c`.
+#[derive(Clone)]
+struct ProductCircuit {
+    a: Option<Fr>,
+    b: Option<Fr>,
+}
+
+impl Circuit<Bls12> for ProductCircuit {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+        let b = cs.alloc(|| "b", || self.b.ok_or(SynthesisError::AssignmentMissing))?;
+        let c = cs.alloc_input(
+            || "c",
+            || {
+                let mut c = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+                c.mul_assign(&self.b.ok_or(SynthesisError::AssignmentMissing)?);
+                Ok(c)
+            },
+        )?;
+        cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_marlin_roundtrip() {
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let index = MarlinIndex::<Bls12>::new(ProductCircuit { a: None, b: None }).unwrap();
+    let srs = MarlinSRS::<Bls12>::setup(index.max_degree(), rng);
+
+    let circuit = ProductCircuit {
+        a: Some(Fr::from_str("3").unwrap()),
+        b: Some(Fr::from_str("5").unwrap()),
+    };
+    let c = Fr::from_str("15").unwrap();
+    let proof = MarlinProver::prove(circuit.clone(), &srs, rng).unwrap();
+    assert!(verify_marlin_proof(&srs, &index, &proof, &[c]).unwrap());
+    assert!(!verify_marlin_proof(&srs, &index, &proof, &[Fr::one()]).unwrap());
+
+    let mut tampered = proof.clone();
+    tampered.evaluations[0].add_assign(&Fr::one());
+    assert!(!verify_marlin_proof(&srs, &index, &tampered, &[c]).unwrap());
+
+    // The same circuit proves with Groth16 without changes.
+    let params =
+        generate_random_parameters::<Bls12, _, _>(ProductCircuit { a: None, b: None }, rng)
+            .unwrap();
+    let proof = create_random_proof(circuit, &params, rng).unwrap();
+    assert!(verify_proof(&prepare_verifying_key(&params.vk), &proof, &[c]).unwrap());
+
+    // Too small an SRS, and unsatisfied constraints.
+    let small = MarlinSRS::<Bls12>::setup(index.max_degree() - 1, rng);
+    assert!(matches!(
+        MarlinProver::prove(
+            ProductCircuit {
+                a: Some(Fr::one()),
+                b: Some(c),
+            },
+            &small,
+            rng,
+        ),
+        Err(SynthesisError::PolynomialDegreeTooLarge)
+    ));
+}
+
+#[test]
+fn test_marlin_unsatisfied() {
+    /// Claims `a * b = 1` with the values of a `ProductCircuit`.
+    struct Unsatisfied;
+
+    impl Circuit<Bls12> for Unsatisfied {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let a = cs.alloc(|| "a", || Ok(Fr::from_str("3").unwrap()))?;
+            let b = cs.alloc(|| "b", || Ok(Fr::from_str("5").unwrap()))?;
+            cs.enforce(
+                || "a * b = 1",
+                |lc| lc + a,
+                |lc| lc + b,
+                |lc| lc + CS::one(),
+            );
+
+            Ok(())
+        }
+    }
+
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let index = MarlinIndex::<Bls12>::new(Unsatisfied).unwrap();
+    let srs = MarlinSRS::<Bls12>::setup(index.max_degree(), rng);
+    assert!(matches!(
+        MarlinProver::prove(Unsatisfied, &srs, rng),
+        Err(SynthesisError::Unsatisfiable)
+    ));
+}
@@ -0,0 +1,223 @@
+//! Submitting a job to a proving server over JSON-RPC on HTTP and fetching its proof.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{mpsc, Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bellperson::bls::{Bls12, Fr, FrRepr};
+use bellperson::groth16::{generate_random_parameters, prepare_verifying_key, verify_proof, Proof};
+use bellperson::multicore::Worker;
+use bellperson::proving_server::{rpc, JobQueue, JobStatus, ProvingServer, SubmitError};
+use bellperson::registry::circuit_id;
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde_json::{json, Value};
+
+/// Proves knowledge of a square root of the public input.
+struct SquareRoot {
+    x: Option<Fr>,
+}
+
+impl Circuit<Bls12> for SquareRoot {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+        let y = cs.alloc_input(
+            || "y",
+            || {
+                let mut y = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                y.square();
+                Ok(y)
+            },
+        )?;
+        cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+        Ok(())
+    }
+}
+
+/// The witness of a `SquareRoot`, `x` in little endian.
+fn decode_witness(witness: &[u8]) -> Result<SquareRoot, SynthesisError> {
+    let mut repr = FrRepr::default();
+    repr.read_le(witness)?;
+    let x = Fr::from_repr(repr).map_err(|_| SynthesisError::AssignmentMissing)?;
+
+    Ok(SquareRoot { x: Some(x) })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Calls `method` with `params` in an HTTP request, returning the result.
+fn call(addr: &SocketAddr, method: &str, params: Value) -> Value {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        addr,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+    let mut response: Value = serde_json::from_str(body).unwrap();
+    assert_eq!(response["error"], Value::Null, "{}", response);
+
+    response["result"].take()
+}
+
+#[test]
+fn test_prove_over_http() {
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let params = generate_random_parameters::<Bls12, _, _>(SquareRoot { x: None }, rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let id = circuit_id(&params.vk);
+
+    let mut queue = JobQueue::<Bls12>::new();
+    queue.register(id, Arc::new(params), decode_witness);
+    let server =
+        rpc::start_http::<Bls12, _>(Arc::new(queue), &"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = *server.address();
+
+    let x = Fr::random(rng);
+    let mut y = x;
+    y.square();
+    let mut witness = Vec::new();
+    x.into_repr().write_le(&mut witness).unwrap();
+
+    let job = call(&addr, "submit_job", json!([hex(&id), hex(&witness)]));
+    let job = job.as_u64().unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let proof = loop {
+        match call(&addr, "get_proof", json!([job])) {
+            Value::String(proof) => break proof,
+            Value::Null => {
+                assert!(Instant::now() < deadline, "the proof is not ready in time");
+                thread::sleep(Duration::from_millis(10));
+            }
+            other => panic!("unexpected result {}", other),
+        }
+    };
+    assert_eq!(
+        call(&addr, "job_status", json!([job])),
+        json!({ "status": "done" })
+    );
+
+    let bytes = (0..proof.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&proof[i..i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+    let proof = Proof::<Bls12>::read(&bytes[..]).unwrap();
+    assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+
+    // Jobs for unknown circuits fail, and unknown jobs have no state.
+    let failed = call(&addr, "submit_job", json!([hex(&[0; 32]), hex(&witness)]));
+    let status = call(&addr, "job_status", json!([failed]));
+    assert_eq!(status["status"], "failed");
+    assert_eq!(call(&addr, "get_proof", json!([failed])), Value::Null);
+    assert_eq!(call(&addr, "job_status", json!([1000])), Value::Null);
+
+    server.close();
+}
+
+#[test]
+fn test_full_queue_and_expiry() {
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let params = generate_random_parameters::<Bls12, _, _>(SquareRoot { x: None }, rng).unwrap();
+    let id = circuit_id(&params.vk);
+
+    // The job blocks its single slot until it is released.
+    let (release, gate) = mpsc::channel();
+    let gate = Mutex::new(gate);
+    let mut queue = JobQueue::<Bls12>::with_limit(&Worker::new(), 1);
+    queue.set_ttl(Duration::from_secs(1));
+    queue.register(id, Arc::new(params), move |witness| {
+        gate.lock().unwrap().recv().unwrap();
+        decode_witness(witness)
+    });
+
+    let mut witness = Vec::new();
+    Fr::random(rng).into_repr().write_le(&mut witness).unwrap();
+
+    // Submitting to the full queue fails at once.
+    let job = queue.submit_job(id, witness.clone()).unwrap();
+    assert_eq!(queue.submit_job(id, witness), Err(SubmitError::QueueFull));
+    assert_eq!(queue.status(job), Some(JobStatus::Pending));
+
+    release.send(()).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while queue.status(job) != Some(JobStatus::Done) {
+        assert!(Instant::now() < deadline, "the proof is not ready in time");
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // Finished jobs are forgotten after the time to live.
+    thread::sleep(Duration::from_millis(1100));
+    assert_eq!(queue.status(job), None);
+    assert!(queue.get_proof(job).is_none());
+}
+
+#[test]
+fn test_more_jobs_than_threads() {
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let params = generate_random_parameters::<Bls12, _, _>(SquareRoot { x: None }, rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let id = circuit_id(&params.vk);
+
+    // Every job waits for all others to start, which cannot happen if jobs take the
+    // threads of the worker they prove on.
+    let worker = Worker::with_threads(2);
+    let jobs = 2 * worker.num_threads();
+    let started = Barrier::new(jobs);
+    let mut queue = JobQueue::<Bls12>::with_limit(&worker, jobs);
+    queue.register(id, Arc::new(params), move |witness| {
+        started.wait();
+        decode_witness(witness)
+    });
+
+    let submitted = (0..jobs)
+        .map(|_| {
+            let x = Fr::random(rng);
+            let mut y = x;
+            y.square();
+            let mut witness = Vec::new();
+            x.into_repr().write_le(&mut witness).unwrap();
+            (queue.submit_job(id, witness).unwrap(), y)
+        })
+        .collect::<Vec<_>>();
+
+    let deadline = Instant::now() + Duration::from_secs(120);
+    for (job, y) in submitted {
+        let proof = loop {
+            if let Some(proof) = queue.get_proof(job) {
+                break proof;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "the proofs are not ready in time"
+            );
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+    }
+}
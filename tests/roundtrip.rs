@@ -0,0 +1,220 @@
+//! Round trips of proofs, verifying keys and parameters through every serialization
+//! format, over randomly sampled values.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+use bellperson::bls::{Bls12, Fr, G1Projective, G2Projective};
+use bellperson::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    Parameters, Proof, TaggedProof, VerifyingKey,
+};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::Field;
+use groupy::CurveProjective;
+use memmap::MmapOptions;
+use proptest::prelude::*;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Proves knowledge of the `num_factors` factors of a public product.
+#[derive(Clone)]
+struct Product {
+    factors: Vec<Option<Fr>>,
+}
+
+impl Circuit<Bls12> for Product {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut product_value = Some(Fr::one());
+        let mut product = CS::one();
+        for (i, factor_value) in self.factors.into_iter().enumerate() {
+            let factor = cs.alloc(
+                || format!("factor {}", i),
+                || factor_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+
+            let next_value = product_value.and_then(|mut p| {
+                p.mul_assign(&factor_value?);
+                Some(p)
+            });
+            let next = cs.alloc(
+                || format!("product {}", i),
+                || next_value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            cs.enforce(
+                || format!("product {}", i),
+                |lc| lc + product,
+                |lc| lc + factor,
+                |lc| lc + next,
+            );
+
+            product = next;
+            product_value = next_value;
+        }
+
+        let output = cs.alloc_input(
+            || "output",
+            || product_value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(
+            || "output",
+            |lc| lc + product,
+            |lc| lc + CS::one(),
+            |lc| lc + output,
+        );
+
+        Ok(())
+    }
+}
+
+fn rng_from_seed(seed: [u8; 16]) -> XorShiftRng {
+    // The xorshift generator must not be seeded with zeroes.
+    let mut seed = seed;
+    seed[0] |= 1;
+    XorShiftRng::from_seed(seed)
+}
+
+/// A proof of random curve points, each a generator times a random scalar.
+fn random_proof(seed: [u8; 16]) -> Proof<Bls12> {
+    let rng = &mut rng_from_seed(seed);
+
+    let mut a = G1Projective::one();
+    a.mul_assign(Fr::random(rng));
+    let mut b = G2Projective::one();
+    b.mul_assign(Fr::random(rng));
+    let mut c = G1Projective::one();
+    c.mul_assign(Fr::random(rng));
+
+    Proof::new(a.into_affine(), b.into_affine(), c.into_affine())
+}
+
+fn random_params(seed: [u8; 16], num_factors: usize) -> Parameters<Bls12> {
+    let rng = &mut rng_from_seed(seed);
+    generate_random_parameters(
+        Product {
+            factors: vec![None; num_factors],
+        },
+        rng,
+    )
+    .unwrap()
+}
+
+fn temp_path(name: &str, seed: [u8; 16]) -> std::path::PathBuf {
+    let suffix = seed
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    std::env::temp_dir().join(format!(
+        "bellperson-roundtrip-{}-{}-{}.bin",
+        name,
+        std::process::id(),
+        suffix
+    ))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn proof_binary_roundtrip(seed in any::<[u8; 16]>()) {
+        let proof = random_proof(seed);
+
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).unwrap();
+        prop_assert_eq!(bytes.len(), Proof::<Bls12>::size());
+        prop_assert_eq!(Proof::<Bls12>::read(&bytes[..]).unwrap(), proof.clone());
+
+        // Writing again gives the same bytes, so the encoding is canonical.
+        let mut again = Vec::new();
+        Proof::<Bls12>::read(&bytes[..]).unwrap().write(&mut again).unwrap();
+        prop_assert_eq!(again, bytes);
+    }
+
+    #[test]
+    fn proof_batch_roundtrip(seeds in prop::collection::vec(any::<[u8; 16]>(), 1..8)) {
+        let proofs = seeds.iter().map(|seed| random_proof(*seed)).collect::<Vec<_>>();
+
+        let mut bytes = Vec::new();
+        for proof in &proofs {
+            proof.write(&mut bytes).unwrap();
+        }
+        prop_assert_eq!(Proof::<Bls12>::read_many(&bytes, proofs.len()).unwrap(), proofs);
+    }
+
+    #[test]
+    fn tagged_proof_roundtrip(
+        seed in any::<[u8; 16]>(),
+        metadata in prop::collection::hash_map(".*", ".*", 0..6),
+    ) {
+        let tagged = TaggedProof {
+            proof: random_proof(seed),
+            metadata: metadata.into_iter().collect::<HashMap<_, _>>(),
+        };
+
+        let mut bytes = Vec::new();
+        tagged.write(&mut bytes).unwrap();
+        prop_assert_eq!(TaggedProof::<Bls12>::read(&bytes[..]).unwrap(), tagged);
+    }
+}
+
+proptest! {
+    // Generating parameters is slow, so there are fewer cases.
+    #![proptest_config(ProptestConfig::with_cases(4))]
+
+    #[test]
+    fn vk_and_params_roundtrip(seed in any::<[u8; 16]>(), num_factors in 1usize..6) {
+        let params = random_params(seed, num_factors);
+
+        let mut vk_bytes = Vec::new();
+        params.vk.write(&mut vk_bytes).unwrap();
+        prop_assert!(VerifyingKey::<Bls12>::read(&vk_bytes[..]).unwrap() == params.vk);
+
+        let mut bytes = Vec::new();
+        params.write(&mut bytes).unwrap();
+        prop_assert!(bytes.starts_with(&vk_bytes));
+        prop_assert!(Parameters::<Bls12>::read(&bytes[..], true).unwrap() == params);
+        prop_assert!(Parameters::<Bls12>::read(&bytes[..], false).unwrap() == params);
+
+        // The memory mapped readers parse the same layout.
+        let path = temp_path("params", seed);
+        File::create(&path).unwrap().write_all(&bytes).unwrap();
+        let file = File::open(&path).unwrap();
+        let mmap = unsafe { MmapOptions::new().map(&file).unwrap() };
+        let mut offset = 0;
+        let vk = VerifyingKey::<Bls12>::read_mmap(&mmap, &mut offset).unwrap();
+        let mapped = Parameters::<Bls12>::read_mmap(&mmap, true).unwrap();
+        drop(mmap);
+        std::fs::remove_file(&path).unwrap();
+        prop_assert_eq!(offset, vk_bytes.len());
+        prop_assert!(vk == params.vk);
+        prop_assert!(mapped == params);
+    }
+
+    #[test]
+    fn created_proof_roundtrip(seed in any::<[u8; 16]>(), num_factors in 1usize..6) {
+        let params = random_params(seed, num_factors);
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let rng = &mut rng_from_seed(seed);
+        let factors = (0..num_factors).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+        let mut output = Fr::one();
+        for factor in &factors {
+            output.mul_assign(factor);
+        }
+        let proof = create_random_proof(
+            Product {
+                factors: factors.into_iter().map(Some).collect(),
+            },
+            &params,
+            rng,
+        )
+        .unwrap();
+
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).unwrap();
+        let read = Proof::<Bls12>::read(&bytes[..]).unwrap();
+        prop_assert_eq!(&read, &proof);
+        prop_assert!(verify_proof(&pvk, &read, &[output]).unwrap());
+    }
+}
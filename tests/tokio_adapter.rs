@@ -0,0 +1,127 @@
+//! Awaiting proofs and verifications from a tokio runtime, with the work running on the
+//! threads of the crate's workers.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bellperson::bls::{Bls12, Fr};
+use bellperson::groth16::{
+    create_random_proof_async, generate_random_parameters, prepare_verifying_key,
+    verify_proofs_batch_async,
+};
+use bellperson::multicore::Worker;
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use ff::Field;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Proves knowledge of a square root of the public input.
+struct SquareRoot {
+    x: Option<Fr>,
+}
+
+impl Circuit<Bls12> for SquareRoot {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+        let y = cs.alloc_input(
+            || "y",
+            || {
+                let mut y = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+                y.square();
+                Ok(y)
+            },
+        )?;
+        cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_concurrent_proofs_without_blocking_pool() {
+    // A current thread runtime only starts threads for its blocking pool.
+    let started = Arc::new(AtomicUsize::new(0));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .on_thread_start({
+            let started = started.clone();
+            move || {
+                started.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .build()
+        .unwrap();
+
+    let rng = &mut XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let params =
+        Arc::new(generate_random_parameters::<Bls12, _, _>(SquareRoot { x: None }, rng).unwrap());
+    let pvk = Arc::new(prepare_verifying_key(&params.vk));
+
+    runtime.block_on(async {
+        // All proofs are in flight at once, each task awaiting its own.
+        let mut tasks = Vec::new();
+        let mut inputs = Vec::new();
+        for _ in 0..8 {
+            let x = Fr::random(rng);
+            let mut y = x;
+            y.square();
+            inputs.push(vec![y]);
+
+            let proof = create_random_proof_async(SquareRoot { x: Some(x) }, params.clone(), rng);
+            tasks.push(tokio::spawn(proof));
+        }
+
+        let mut proofs = Vec::new();
+        for task in tasks {
+            proofs.push(task.await.unwrap().unwrap());
+        }
+
+        let valid = verify_proofs_batch_async(pvk.clone(), rng, proofs.clone(), inputs.clone());
+        assert!(valid.await.unwrap());
+
+        inputs[0][0].add_assign(&Fr::one());
+        let invalid = verify_proofs_batch_async(pvk.clone(), rng, proofs, inputs);
+        assert!(!invalid.await.unwrap());
+    });
+
+    assert_eq!(started.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_await_waiters() {
+    let worker = Worker::new();
+
+    // Awaiting a result which is ready, and results computed in parallel.
+    assert_eq!(worker.compute(|| 1).await, 1);
+    let waiters = (0..4)
+        .map(|i| {
+            worker.compute(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                i * i
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut squares = Vec::new();
+    for waiter in waiters {
+        squares.push(waiter.await);
+    }
+    assert_eq!(squares, vec![0, 1, 4, 9]);
+
+    // A dropped waiter lets its computation finish, and the worker keeps working.
+    let finished = Arc::new(AtomicUsize::new(0));
+    let dropped = worker.compute({
+        let finished = finished.clone();
+        move || {
+            std::thread::sleep(Duration::from_millis(20));
+            finished.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    drop(dropped);
+    assert_eq!(worker.compute(|| 2).await, 2);
+    while finished.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+}